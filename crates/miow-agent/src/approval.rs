@@ -0,0 +1,296 @@
+use crate::autonomous::AgentEvent;
+use crate::error::AgentError;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+/// An agent action that is waiting for a person to allow it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    pub id: String,
+    pub tool: String,
+    /// What the action will do, e.g. "run `cargo test` in /src/app"
+    pub action: String,
+}
+
+/// A clarifying question the agent wants a person to answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Question {
+    pub id: String,
+    pub question: String,
+}
+
+static NEXT_APPROVAL: AtomicU64 = AtomicU64::new(1);
+
+/// Ids are process-unique, with a random part so they can't be guessed
+fn next_id() -> String {
+    let seq = NEXT_APPROVAL.fetch_add(1, Ordering::Relaxed);
+    let nonce = std::hash::BuildHasher::hash_one(&std::collections::hash_map::RandomState::new(), seq);
+    format!("{}-{:016x}", seq, nonce)
+}
+
+impl ApprovalRequest {
+    pub fn new(tool: &str, action: String) -> Self {
+        Self {
+            id: next_id(),
+            tool: tool.to_string(),
+            action,
+        }
+    }
+}
+
+impl Question {
+    pub fn new(question: String) -> Self {
+        Self { id: next_id(), question }
+    }
+}
+
+tokio::task_local! {
+    /// Event stream of the agent run a tool executes in, for approvals asked from inside the tool
+    static RUN_EVENTS: Option<Sender<AgentEvent>>;
+}
+
+/// Run `future`, usually a tool's `execute`, with `events` as the run's event stream
+pub(crate) async fn with_run_events<F: std::future::Future>(events: Option<Sender<AgentEvent>>, future: F) -> F::Output {
+    RUN_EVENTS.scope(events, future).await
+}
+
+/// Ask `approver` about `request` through the current run's event stream and report
+/// the outcome on it; errors and a missing approver count as a refusal
+pub(crate) async fn request_approval(approver: Option<&dyn Approver>, request: &ApprovalRequest) -> bool {
+    let events = RUN_EVENTS.try_with(Clone::clone).ok().flatten();
+    info!("   ✋ Waiting for approval to {}", request.action);
+    let approved = match approver {
+        Some(approver) => approver.approve(request, events.as_ref()).await.unwrap_or_else(|e| {
+            warn!("   ⚠️  Approval failed: {}", e);
+            false
+        }),
+        None => {
+            warn!("   ⚠️  Nobody can approve '{}'; refusing", request.action);
+            false
+        }
+    };
+    if !approved {
+        warn!("   🚫 Not approved: {}", request.action);
+    }
+    if let Some(tx) = &events {
+        let _ = tx.send(AgentEvent::ApprovalResolved { id: request.id.clone(), approved }).await;
+    }
+    approved
+}
+
+/// Decides whether the agent may go ahead with an action that needs approval
+#[async_trait]
+pub trait Approver: Send + Sync {
+    /// `events` is the run's event stream, for approvers that ask through it
+    async fn approve(&self, request: &ApprovalRequest, events: Option<&Sender<AgentEvent>>) -> Result<bool>;
+}
+
+/// Answers the agent's clarifying questions
+#[async_trait]
+pub trait Answerer: Send + Sync {
+    /// The answer, or `None` when nobody gave one
+    async fn answer(&self, question: &Question, events: Option<&Sender<AgentEvent>>) -> Result<Option<String>>;
+}
+
+/// Approves everything (`--yes`)
+pub struct AutoApprover;
+
+#[async_trait]
+impl Approver for AutoApprover {
+    async fn approve(&self, _request: &ApprovalRequest, _events: Option<&Sender<AgentEvent>>) -> Result<bool> {
+        Ok(true)
+    }
+}
+
+/// Asks on the terminal; fails when there is none
+pub struct TerminalApprover;
+
+#[async_trait]
+impl Approver for TerminalApprover {
+    async fn approve(&self, request: &ApprovalRequest, _events: Option<&Sender<AgentEvent>>) -> Result<bool> {
+        if !std::io::stdin().is_terminal() {
+            return Err(AgentError::NobodyToAsk(format!(
+                "'{}' needs approval but there is no terminal; rerun with --yes or set approval = \"auto\" in miow.toml",
+                request.action
+            ))
+            .into());
+        }
+        let question = format!("Allow the agent to {}? [y/N] ", request.action);
+        let answer = tokio::task::spawn_blocking(move || -> std::io::Result<String> {
+            eprint!("{}", question);
+            std::io::stderr().flush()?;
+            let mut answer = String::new();
+            std::io::stdin().read_line(&mut answer)?;
+            Ok(answer)
+        })
+        .await??;
+        Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+    }
+}
+
+/// Requests waiting for an answer, keyed by id
+type PendingApprovals = Arc<Mutex<HashMap<String, (Option<PathBuf>, oneshot::Sender<bool>)>>>;
+
+/// Emits an `ApprovalRequired` event and waits for `resolve` to be called with
+/// its id, e.g. from a web endpoint. Unanswered requests are denied after `timeout`.
+/// Questions work the same way through `QuestionAsked` and `reply`.
+#[derive(Clone)]
+pub struct ChannelApprover {
+    /// Responder and codebase of each request waiting for an answer
+    pending: PendingApprovals,
+    questions: Arc<Mutex<HashMap<String, oneshot::Sender<String>>>>,
+    timeout: Duration,
+    /// Codebase the requests made through this handle are about
+    codebase: Option<PathBuf>,
+}
+
+impl ChannelApprover {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            questions: Arc::new(Mutex::new(HashMap::new())),
+            timeout,
+            codebase: None,
+        }
+    }
+
+    /// A handle sharing this one's pending requests, whose own requests are
+    /// about `codebase`; see `codebase_of`
+    pub fn for_codebase(&self, codebase: impl Into<PathBuf>) -> Self {
+        Self { codebase: Some(codebase.into()), ..self.clone() }
+    }
+
+    /// The codebase a pending request is about, when the handle that made it named one
+    pub fn codebase_of(&self, id: &str) -> Option<PathBuf> {
+        self.pending.lock().unwrap().get(id).and_then(|(codebase, _)| codebase.clone())
+    }
+
+    /// Answer a pending question; `false` when there is no such question
+    pub fn reply(&self, id: &str, answer: String) -> bool {
+        match self.questions.lock().unwrap().remove(id) {
+            Some(responder) => responder.send(answer).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Answer a pending request; `false` when there is no such request (already answered or timed out)
+    pub fn resolve(&self, id: &str, approved: bool) -> bool {
+        match self.pending.lock().unwrap().remove(id) {
+            Some((_, responder)) => responder.send(approved).is_ok(),
+            None => false,
+        }
+    }
+}
+
+#[async_trait]
+impl Approver for ChannelApprover {
+    async fn approve(&self, request: &ApprovalRequest, events: Option<&Sender<AgentEvent>>) -> Result<bool> {
+        let events = events.ok_or_else(|| {
+            AgentError::NobodyToAsk(format!("'{}' needs approval but there is no client to ask", request.action))
+        })?;
+
+        // Register before announcing so an immediate answer can't arrive first
+        let (responder, answer) = oneshot::channel();
+        self.pending.lock().unwrap().insert(request.id.clone(), (self.codebase.clone(), responder));
+
+        if events.send(AgentEvent::ApprovalRequired { request: request.clone() }).await.is_err() {
+            self.pending.lock().unwrap().remove(&request.id);
+            return Ok(false);
+        }
+
+        let approved = matches!(tokio::time::timeout(self.timeout, answer).await, Ok(Ok(true)));
+        self.pending.lock().unwrap().remove(&request.id);
+        Ok(approved)
+    }
+}
+
+#[async_trait]
+impl Answerer for ChannelApprover {
+    async fn answer(&self, question: &Question, events: Option<&Sender<AgentEvent>>) -> Result<Option<String>> {
+        let events = events.ok_or_else(|| {
+            AgentError::NobodyToAsk(format!("'{}' needs an answer but there is no client to ask", question.question))
+        })?;
+
+        let (responder, answer) = oneshot::channel();
+        self.questions.lock().unwrap().insert(question.id.clone(), responder);
+
+        if events.send(AgentEvent::QuestionAsked { question: question.clone() }).await.is_err() {
+            self.questions.lock().unwrap().remove(&question.id);
+            return Ok(None);
+        }
+
+        let answer = tokio::time::timeout(self.timeout, answer).await.ok().and_then(Result::ok);
+        self.questions.lock().unwrap().remove(&question.id);
+        Ok(answer.filter(|a| !a.trim().is_empty()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_channel_approver_round_trip() {
+        let approver = ChannelApprover::new(Duration::from_secs(5));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let request = ApprovalRequest::new("run_command", "run `ls`".into());
+
+        let answering = approver.clone();
+        tokio::spawn(async move {
+            if let Some(AgentEvent::ApprovalRequired { request }) = rx.recv().await {
+                assert!(answering.resolve(&request.id, true));
+                assert!(!answering.resolve(&request.id, true));
+            }
+        });
+
+        assert!(approver.approve(&request, Some(&tx)).await.unwrap());
+        assert!(approver.approve(&request, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_codebase_of_pending_requests() {
+        let approver = ChannelApprover::new(Duration::from_secs(5));
+        let scoped = approver.for_codebase("/src/app");
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let request = ApprovalRequest::new("run_command", "run `ls`".into());
+
+        let answering = approver.clone();
+        tokio::spawn(async move {
+            if let Some(AgentEvent::ApprovalRequired { request }) = rx.recv().await {
+                assert_eq!(answering.codebase_of(&request.id), Some(PathBuf::from("/src/app")));
+                assert!(answering.resolve(&request.id, false));
+                assert_eq!(answering.codebase_of(&request.id), None);
+            }
+        });
+
+        assert!(!scoped.approve(&request, Some(&tx)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_channel_approver_answers_questions() {
+        let approver = ChannelApprover::new(Duration::from_secs(5));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let question = Question::new("Which auth provider?".into());
+
+        let answering = approver.clone();
+        tokio::spawn(async move {
+            if let Some(AgentEvent::QuestionAsked { question }) = rx.recv().await {
+                assert!(answering.reply(&question.id, "OAuth".into()));
+                assert!(!answering.reply(&question.id, "again".into()));
+            }
+        });
+
+        assert_eq!(approver.answer(&question, Some(&tx)).await.unwrap().as_deref(), Some("OAuth"));
+        assert!(approver.answer(&question, None).await.is_err());
+    }
+}
@@ -1,18 +1,41 @@
 use anyhow::{Context, Result, anyhow};
-use miow_llm::LLMProvider;
+use miow_llm::{CancellationToken, LLMProvider};
+use miow_common::SecretRedactor;
 use miow_graph::KnowledgeGraph;
 use miow_vector::VectorStore;
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tracing::{info, debug, error};
+use tracing::{info, info_span, debug, error, warn, Instrument};
 use async_trait::async_trait;
-use crate::tools::{Tool, ToolRegistry, ViewFileTool, ListDirTool, RunCommandTool, WriteFileTool};
+use crate::approval::{request_approval, with_run_events, Answerer, ApprovalRequest, Approver, Question};
+use crate::budget::{AgentBudget, BudgetLimit, BudgetTracker, BudgetUsage};
+use crate::command_policy::CommandPolicy;
+use crate::error::AgentError;
+use crate::lsp_tool::{LspConfig, LspTool};
+use crate::search_cache::SearchCache;
+use crate::self_monitor::{HealthIssue, SelfMonitor};
+use crate::tools::{GitBlameTool, GitLogTool, GrepTool, Tool, ToolRegistry, ToolSafety, ViewFileTool, ListDirTool, RunCommandTool, WriteFileTool};
+
+/// Searches per self-review round, so one vague task can't fan out into dozens
+const MAX_FOLLOW_UP_QUERIES: usize = 6;
+
+/// Called with the agent's context before each step, so a run can be resumed
+type CheckpointFn = Arc<dyn Fn(&AgentContext) + Send + Sync>;
 
 /// An autonomous agent that iteratively gathers context and solves tasks using tools
 pub struct AutonomousAgent {
     llm: Arc<dyn LLMProvider>,
     tools: ToolRegistry,
+    cancel: CancellationToken,
+    project_root: std::path::PathBuf,
+    command_policy: CommandPolicy,
+    budget: AgentBudget,
+    approver: Option<Arc<dyn Approver>>,
+    answerer: Option<Arc<dyn Answerer>>,
+    graph: Arc<KnowledgeGraph>,
+    vector_store: Option<Arc<VectorStore>>,
+    checkpoint: Option<CheckpointFn>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +43,32 @@ pub struct AgentContext {
     pub task: String,
     pub gathered_info: Vec<VerifiedInfo>,
     pub history: Vec<String>,
+    /// Set when the run stopped on a budget limit rather than by choosing "done";
+    /// the gathered info is then a best effort
+    #[serde(default)]
+    pub budget_exhausted: Option<BudgetLimit>,
+    /// Gaps the self-review found and follow-up searches could not fill
+    #[serde(default)]
+    pub open_gaps: Vec<String>,
+    /// Spent so far, so a resumed run stays within the same budget
+    #[serde(default)]
+    pub usage: BudgetUsage,
+    /// Tasks earlier in the session, oldest first; their context is already gathered
+    #[serde(default)]
+    pub earlier_tasks: Vec<String>,
+}
+
+impl AgentContext {
+    /// The context to start a follow-up task from: everything gathered so far
+    /// and the actions that found it, with a fresh budget
+    pub fn follow_up(mut self, task: &str) -> Self {
+        self.earlier_tasks.push(std::mem::replace(&mut self.task, task.to_string()));
+        self.history.push(format!("--- Follow-up task: {} ---", task));
+        self.budget_exhausted = None;
+        self.open_gaps.clear();
+        self.usage = BudgetUsage::default();
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +76,18 @@ pub struct VerifiedInfo {
     pub content: String,
     pub source: String,
     pub relevance: String,
+    /// Came from outside the codebase (web search), so it documents a dependency
+    /// rather than showing project code
+    #[serde(default)]
+    pub external: bool,
+}
+
+impl VerifiedInfo {
+    /// The tool and arguments that returned this info; `None` for a user's answer
+    pub fn tool_call(&self) -> Option<(&str, serde_json::Value)> {
+        let (tool, args) = self.source.strip_prefix("Tool: ")?.split_once(" Args: ")?;
+        Some((tool, serde_json::from_str(args).unwrap_or_default()))
+    }
 }
 
 use tokio::sync::mpsc::Sender;
@@ -39,7 +100,20 @@ pub enum AgentEvent {
     ToolCall { tool: String, args: serde_json::Value },
     ToolOutput { output: String },
     Error { error: String },
+    BudgetExhausted { limit: BudgetLimit },
+    /// Self-review found these gaps and is searching to fill them
+    Reflection { gaps: Vec<String> },
+    /// The agent is paused until the request is approved or denied
+    ApprovalRequired { request: ApprovalRequest },
+    ApprovalResolved { id: String, approved: bool },
+    /// The agent is paused until the question is answered
+    QuestionAsked { question: Question },
+    QuestionAnswered { id: String, answered: bool },
     Done,
+    /// The run moved on to another phase, e.g. writing the implementation plan
+    Phase { phase: String },
+    /// Text an LLM streamed while writing the run's output
+    Token { content: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +125,9 @@ pub enum AgentAction {
         args: serde_json::Value,
         reason: String,
     },
+    /// Ask the user to settle something the code can't
+    #[serde(rename = "ask_user")]
+    AskUser { question: String },
     #[serde(rename = "done")]
     Done,
 }
@@ -64,36 +141,179 @@ impl AutonomousAgent {
         let mut registry = ToolRegistry::new();
         registry.register(Arc::new(ViewFileTool));
         registry.register(Arc::new(ListDirTool));
-        registry.register(Arc::new(RunCommandTool));
+        registry.register(Arc::new(RunCommandTool::default()));
         registry.register(Arc::new(WriteFileTool));
-        registry.register(Arc::new(SearchTool::new(graph, vector_store)));
+        registry.register(Arc::new(SearchTool::new(graph.clone(), vector_store.clone())));
+
+        Self {
+            llm,
+            tools: registry,
+            cancel: CancellationToken::new(),
+            project_root: std::path::PathBuf::from("."),
+            command_policy: CommandPolicy::default(),
+            budget: AgentBudget::default(),
+            approver: None,
+            answerer: None,
+            graph,
+            vector_store,
+            checkpoint: None,
+        }
+    }
+
+    /// Give the agent the codebase root so it can grep file contents and read git history
+    pub fn with_project_root(mut self, root: std::path::PathBuf) -> Self {
+        self.tools.register(Arc::new(GrepTool::new(root.clone())));
+        self.tools.register(Arc::new(GitLogTool::new(root.clone())));
+        self.tools.register(Arc::new(GitBlameTool::new(root.clone())));
+        self.tools.register(Arc::new(LspTool::new(root.clone(), self.graph.clone(), None)));
+        self.project_root = root;
+        self.register_run_command();
+        self
+    }
+
+    /// Limit what `run_command` may run, where, and for how long
+    pub fn with_command_policy(mut self, policy: CommandPolicy) -> Self {
+        self.command_policy = policy;
+        self.register_run_command();
+        self
+    }
+
+    /// Cap iterations, tool calls, tokens and time for each run
+    pub fn with_budget(mut self, budget: AgentBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Add a project-specific tool, replacing any built-in tool with the same name
+    pub fn with_tool(mut self, tool: Arc<dyn Tool>) -> Self {
+        self.tools.register(tool);
+        self
+    }
+
+    /// Who decides on actions that need approval; without one they are refused
+    pub fn with_approver(mut self, approver: Arc<dyn Approver>) -> Self {
+        self.approver = Some(approver);
+        self.register_run_command();
+        self
+    }
+
+    /// Who answers clarifying questions; without one the agent isn't offered `ask_user`
+    pub fn with_answerer(mut self, answerer: Arc<dyn Answerer>) -> Self {
+        self.answerer = Some(answerer);
+        self
+    }
+
+    /// Answer `lsp` with this language server instead of graph name matches;
+    /// call after `with_project_root`
+    pub fn with_lsp(mut self, config: LspConfig) -> Self {
+        let tool = LspTool::new(self.project_root.clone(), self.graph.clone(), Some(config));
+        self.tools.register(Arc::new(tool));
+        self
+    }
 
-        Self { llm, tools: registry }
+    /// Serve `search` from a cache shared with the rest of the run
+    pub fn with_search_cache(mut self, cache: SearchCache) -> Self {
+        let tool = SearchTool::new(self.graph.clone(), self.vector_store.clone()).with_cache(cache);
+        self.tools.register(Arc::new(tool));
+        self
+    }
+
+    fn register_run_command(&mut self) {
+        let mut tool = RunCommandTool::new(self.project_root.clone(), self.command_policy.clone());
+        if let Some(approver) = &self.approver {
+            tool = tool.with_approver(approver.clone());
+        }
+        self.tools.register(Arc::new(tool));
+    }
+
+    /// Stop the loop (and in-flight LLM calls) when `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.llm = Arc::new(miow_llm::CancellableLLMProvider::new(self.llm, token.clone()));
+        self.cancel = token;
+        self
+    }
+
+    /// Called with the context before every step, e.g. to save it so an
+    /// interrupted run can be resumed
+    pub fn with_checkpoint(mut self, checkpoint: impl Fn(&AgentContext) + Send + Sync + 'static) -> Self {
+        self.checkpoint = Some(Arc::new(checkpoint));
+        self
     }
 
     /// Run the autonomous loop to gather context and solve the task
     pub async fn run(&self, task: &str, event_tx: Option<Sender<AgentEvent>>) -> Result<AgentContext> {
-        let mut context = AgentContext {
+        let context = AgentContext {
             task: task.to_string(),
             gathered_info: Vec::new(),
             history: Vec::new(),
+            budget_exhausted: None,
+            open_gaps: Vec::new(),
+            usage: BudgetUsage::default(),
+            earlier_tasks: Vec::new(),
         };
+        self.resume(context, event_tx).await
+    }
+
+    /// Continue the loop from a checkpointed context, keeping what it gathered
+    /// and what it has spent
+    #[tracing::instrument(name = "agent.run", skip_all, fields(resumed_at = context.usage.iterations))]
+    pub async fn resume(&self, mut context: AgentContext, event_tx: Option<Sender<AgentEvent>>) -> Result<AgentContext> {
+        if context.usage.iterations == 0 {
+            info!("🚀 Starting Autonomous Agent Loop for task: {}", context.task);
+        } else {
+            info!("⏯️  Resuming Autonomous Agent Loop after step {} for task: {}", context.usage.iterations, context.task);
+        }
+
+        let mut budget = BudgetTracker::resume(self.budget.clone(), context.usage);
+        let mut monitor = SelfMonitor::new();
+        let max_steps = budget.max_iterations();
+        loop {
+            context.usage = budget.usage();
+            if let Some(checkpoint) = &self.checkpoint {
+                checkpoint(&context);
+            }
+
+            if self.cancel.is_cancelled() {
+                error!("🛑 Agent run cancelled at step {}", budget.iterations + 1);
+                if let Some(tx) = &event_tx {
+                    let _ = tx.send(AgentEvent::Error { error: "Run cancelled".to_string() }).await;
+                }
+                metrics::counter!("miow_agent_runs_total", "outcome" => "cancelled").increment(1);
+                return Err(AgentError::Cancelled.into());
+            }
 
-        info!("🚀 Starting Autonomous Agent Loop for task: {}", task);
+            if let Some(limit) = budget.exhausted() {
+                self.stop_on_budget(&mut context, limit, &event_tx).await;
+                break;
+            }
 
-        let max_steps = 15;
-        for step in 0..max_steps {
-            info!("🔄 Step {}/{}", step + 1, max_steps);
+            budget.iterations += 1;
+            let step = budget.iterations;
+            info!("🔄 Step {}/{}", step, max_steps);
             if let Some(tx) = &event_tx {
-                let _ = tx.send(AgentEvent::Step { step: step + 1, max_steps }).await;
+                let _ = tx.send(AgentEvent::Step { step, max_steps }).await;
             }
             
-            // 1. Decide next action
-            let action = self.decide_next_step(&context).await?;
+            // 1. Decide next action (bounded by whatever time is left)
+            let decision = match budget.remaining_time() {
+                Some(left) => match tokio::time::timeout(left, self.decide_next_step(&context)).await {
+                    Ok(decision) => decision,
+                    Err(_) => {
+                        self.stop_on_budget(&mut context, BudgetLimit::WallClock, &event_tx).await;
+                        break;
+                    }
+                },
+                None => self.decide_next_step(&context).await,
+            };
+            let (action, tokens) = decision?;
+            budget.tokens += tokens;
             info!("🤖 Decision: {:?}", action);
 
             match action {
                 AgentAction::UseTool { tool, args, reason } => {
+                    budget.tool_calls += 1;
+                    metrics::counter!("miow_agent_tool_calls_total", "tool" => tool.clone()).increment(1);
+
                     context.history.push(format!("Action: UseTool {} (Reason: {})", tool, reason));
                     if let Some(tx) = &event_tx {
                         let _ = tx.send(AgentEvent::Thought { content: format!("Decided to use tool '{}' because: {}", tool, reason) }).await;
@@ -101,21 +321,48 @@ impl AutonomousAgent {
                     }
                     
                     if let Some(tool_impl) = self.tools.get(&tool) {
+                        if let Some(action) = tool_impl.approval_reason(&args)
+                            && !self.approve(&tool, action, &event_tx).await
+                        {
+                            context.history.push(format!("Denied: the user did not approve this {} call; try another way", tool));
+                            continue;
+                        }
+
                         info!("   🔨 Executing tool: {}", tool);
-                        match tool_impl.execute(args.clone()).await {
+                        let step_id = format!("{} {}", tool, args);
+                        monitor.record_step_start(step_id.clone());
+                        // Tools that ask for approval themselves (`run_command`) do it through the run's events
+                        let execute = with_run_events(event_tx.clone(), tool_impl.execute(args.clone()));
+                        let execution = async {
+                            match budget.remaining_time() {
+                                Some(left) => tokio::time::timeout(left, execute)
+                                    .await
+                                    .unwrap_or_else(|_| Err(anyhow!("Tool '{}' ran past the agent's time budget", tool))),
+                                None => execute.await,
+                            }
+                        }
+                        .instrument(info_span!("agent.tool", tool = %tool, step));
+                        // Dropping the tool's future kills a command it started
+                        let Some(result) = self.unless_cancelled(execution).await else {
+                            continue;
+                        };
+                        monitor.record_step_complete(&step_id, result.is_ok(), result.as_ref().err().map(|e| e.to_string()));
+                        match result {
                             Ok(output) => {
                                 info!("   ✅ Tool success");
+                                let output = redact_for_llm(&tool, output);
                                 if let Some(tx) = &event_tx {
                                     let _ = tx.send(AgentEvent::ToolOutput { output: output.chars().take(1000).collect() }).await;
                                 }
                                 context.history.push(format!("Output: {}", output.chars().take(500).collect::<String>()));
                                 
                                 // If it was a search or read, add to gathered info
-                                if tool == "search" || tool == "view_file" {
+                                if tool == "search" || tool == "view_file" || tool == "web_search" {
                                     context.gathered_info.push(VerifiedInfo {
                                         content: output,
                                         source: format!("Tool: {} Args: {}", tool, args),
                                         relevance: reason,
+                                        external: tool == "web_search",
                                     });
                                 }
                             },
@@ -131,6 +378,32 @@ impl AutonomousAgent {
                         error!("   ❌ Tool not found: {}", tool);
                         context.history.push(format!("Error: Tool '{}' not found", tool));
                     }
+
+                    for issue in monitor.check_health() {
+                        if let HealthIssue::InfiniteLoop { pattern } = issue {
+                            warn!("   🔁 {}", pattern);
+                            context.history.push(format!("Warning: you are repeating yourself ({}). Try a different tool or query, or choose done.", pattern));
+                        }
+                    }
+                },
+                AgentAction::AskUser { question } => {
+                    // Counted as a tool call so the agent can't ask forever
+                    budget.tool_calls += 1;
+                    context.history.push(format!("Action: AskUser {}", question));
+                    match self.ask(&question, &event_tx).await {
+                        Some(answer) => {
+                            context.history.push(format!("User answered: {}", answer));
+                            context.gathered_info.push(VerifiedInfo {
+                                content: format!("Q: {}\nA: {}", question, answer),
+                                source: "User answer".to_string(),
+                                relevance: question,
+                                external: false,
+                            });
+                        }
+                        None => context
+                            .history
+                            .push("The user did not answer; decide without it and don't ask again".to_string()),
+                    }
                 },
                 AgentAction::Done => {
                     info!("✅ Agent decided it is done.");
@@ -142,17 +415,168 @@ impl AutonomousAgent {
             }
         }
 
+        if !self.cancel.is_cancelled() {
+            self.reflect(&mut context, &monitor, &mut budget, &event_tx).await;
+        }
+
+        info!(
+            "📊 Agent spent {} steps, {} tool calls, ~{} tokens",
+            budget.iterations, budget.tool_calls, budget.tokens
+        );
+        let outcome = if context.budget_exhausted.is_some() { "budget_exhausted" } else { "done" };
+        metrics::counter!("miow_agent_runs_total", "outcome" => outcome).increment(1);
+        metrics::histogram!("miow_agent_iterations").record(budget.iterations as f64);
+        context.usage = budget.usage();
         Ok(context)
     }
 
-    async fn decide_next_step(&self, context: &AgentContext) -> Result<AgentAction> {
+    /// Grade the gathered context against the task, drop duplicates, and run
+    /// targeted searches for what's missing before the context is accepted
+    #[tracing::instrument(name = "agent.reflect", skip_all)]
+    async fn reflect(
+        &self,
+        context: &mut AgentContext,
+        monitor: &SelfMonitor,
+        budget: &mut BudgetTracker,
+        event_tx: &Option<Sender<AgentEvent>>,
+    ) {
+        let rounds = budget.reflection_rounds();
+        for round in 0..=rounds {
+            let review = monitor.review_context(&context.task, &context.gathered_info);
+            for &i in review.duplicates.iter().rev() {
+                context.gathered_info.remove(i);
+            }
+            context.open_gaps = review.open_gaps().map(|gap| gap.to_string()).collect();
+            if context.open_gaps.is_empty() || round == rounds || context.budget_exhausted.is_some() {
+                break;
+            }
+
+            info!("🪞 Self-review found gaps: {}", context.open_gaps.join("; "));
+            if let Some(tx) = event_tx {
+                let _ = tx.send(AgentEvent::Reflection { gaps: context.open_gaps.clone() }).await;
+            }
+
+            let mut found = false;
+            for query in review.follow_up_queries(&context.task).into_iter().take(MAX_FOLLOW_UP_QUERIES) {
+                if let Some(limit) = budget.tools_exhausted() {
+                    info!("   ⏱️  Skipping further follow-ups: {} reached", limit);
+                    break;
+                }
+                if let Some(info) = self.follow_up(&query, budget).await {
+                    context.history.push(format!("Follow-up: {}", info.source));
+                    context.gathered_info.push(info);
+                    found = true;
+                }
+            }
+            if !found {
+                break;
+            }
+        }
+
+        if !context.open_gaps.is_empty() {
+            warn!("⚠️  Context still has gaps: {}", context.open_gaps.join("; "));
+        }
+    }
+
+    /// Search for `query`, falling back to an exact grep for identifiers the index doesn't know
+    async fn follow_up(&self, query: &str, budget: &mut BudgetTracker) -> Option<VerifiedInfo> {
+        let mut attempts = vec![("search", json!({ "query": query }))];
+        if !query.contains(char::is_whitespace) {
+            attempts.push(("grep", json!({ "pattern": regex::escape(query), "max_matches": 20 })));
+        }
+
+        for (tool, args) in attempts {
+            let Some(tool_impl) = self.tools.get(tool) else { continue };
+            if budget.tools_exhausted().is_some() {
+                return None;
+            }
+            budget.tool_calls += 1;
+            match tool_impl.execute(args.clone()).await {
+                Ok(output) if !output.trim().is_empty() && !output.starts_with("No ") => {
+                    return Some(VerifiedInfo {
+                        content: redact_for_llm(tool, output),
+                        source: format!("Tool: {} Args: {}", tool, args),
+                        relevance: format!("Self-review follow-up for '{}'", query),
+                        external: false,
+                    });
+                }
+                Ok(_) => {}
+                Err(e) => debug!("Follow-up {} for '{}' failed: {}", tool, query, e),
+            }
+        }
+        None
+    }
+
+    /// Ask the approver about an action; errors and a missing approver count as a refusal
+    async fn approve(&self, tool: &str, action: String, event_tx: &Option<Sender<AgentEvent>>) -> bool {
+        let request = ApprovalRequest::new(tool, action);
+        let approval = with_run_events(event_tx.clone(), request_approval(self.approver.as_deref(), &request));
+        self.unless_cancelled(approval).await.unwrap_or(false)
+    }
+
+    /// Put a question to the answerer; `None` when there is none or nobody answered
+    async fn ask(&self, question: &str, event_tx: &Option<Sender<AgentEvent>>) -> Option<String> {
+        let question = Question::new(question.to_string());
+        info!("   ❓ Waiting for an answer to: {}", question.question);
+        let answer = match &self.answerer {
+            Some(answerer) => self
+                .unless_cancelled(answerer.answer(&question, event_tx.as_ref()))
+                .await
+                .unwrap_or(Ok(None))
+                .unwrap_or_else(|e| {
+                    warn!("   ⚠️  Question failed: {}", e);
+                    None
+                }),
+            None => None,
+        };
+        if let Some(tx) = event_tx {
+            let _ = tx.send(AgentEvent::QuestionAnswered { id: question.id, answered: answer.is_some() }).await;
+        }
+        answer
+    }
+
+    /// `fut`'s output, or `None` if the run is cancelled first; the loop then
+    /// stops at its next cancellation check
+    async fn unless_cancelled<T>(&self, fut: impl std::future::Future<Output = T>) -> Option<T> {
+        tokio::select! {
+            _ = self.cancel.cancelled() => None,
+            output = fut => Some(output),
+        }
+    }
+
+    /// End the run on a budget limit, keeping whatever has been gathered so far
+    async fn stop_on_budget(&self, context: &mut AgentContext, limit: BudgetLimit, event_tx: &Option<Sender<AgentEvent>>) {
+        warn!("⏱️  Agent stopped at its {}; continuing with partial context ({} items)", limit, context.gathered_info.len());
+        context.history.push(format!("Stopped: {} reached", limit));
+        context.budget_exhausted = Some(limit);
+        if let Some(tx) = event_tx {
+            let _ = tx.send(AgentEvent::BudgetExhausted { limit }).await;
+        }
+    }
+
+    /// Ask the LLM for the next action; also returns the tokens the call used
+    #[tracing::instrument(name = "agent.decide", skip_all, fields(step = context.usage.iterations + 1))]
+    async fn decide_next_step(&self, context: &AgentContext) -> Result<(AgentAction, usize)> {
         let tools_schema = serde_json::to_string_pretty(&self.tools.list_tools())?;
+        let ask_user = if self.answerer.is_some() {
+            "\n- Respond {\"action\": \"ask_user\", \"question\": \"...\"} only when the task is ambiguous in a way the code can't settle."
+        } else {
+            ""
+        };
+        let earlier = if context.earlier_tasks.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\nThis follows up on earlier tasks in the same session, whose context is already gathered below; search only for what this task adds:\n- {}\n",
+                context.earlier_tasks.join("\n- ")
+            )
+        };
         
         let prompt = format!(
             r#"You are an Autonomous Context Engine. Your goal is to build a perfect context for the user's task.
 
 Task: "{}"
-
+{}
 Available Tools:
 {}
 
@@ -166,8 +590,10 @@ Decide the next step.
 - Use "search" to find relevant symbols.
 - Use "list_dir" to explore the file structure.
 - Use "view_file" to read file contents.
-- Use "run_command" only if necessary (e.g. grep).
-- Choose "done" when you have gathered sufficient information.
+- Use "grep" (when available) to confirm exact strings, identifiers or config keys in file contents.
+- Use "git_log" / "git_blame" (when available) to see what changed recently in the files you will touch.
+- Use "run_command" only if necessary.
+- Choose "done" when you have gathered sufficient information.{}
 
 Respond with JSON ONLY:
 {{
@@ -182,15 +608,23 @@ OR
 }}
 "#,
             context.task,
+            earlier,
             tools_schema,
             self.format_gathered_info(&context.gathered_info),
-            context.history.join("\n")
+            context.history.join("\n"),
+            ask_user
         );
 
         let response = self.llm.generate(&prompt).await?;
+        let tokens = match &response.usage {
+            Some(usage) => usage.total_tokens,
+            // Rough estimate for providers that don't report usage
+            None => (prompt.len() + response.content.len()) / 4,
+        };
         let clean = self.clean_json(&response.content);
         
-        serde_json::from_str(&clean).context("Failed to parse agent decision")
+        let action = serde_json::from_str(&clean).context("Failed to parse agent decision")?;
+        Ok((action, tokens))
     }
 
     fn format_gathered_info(&self, info: &[VerifiedInfo]) -> String {
@@ -213,15 +647,33 @@ OR
     }
 }
 
+/// `text` with any secrets replaced by placeholders, for tool output and file
+/// contents about to be sent to the LLM
+pub(crate) fn redact_for_llm(source: &str, text: String) -> String {
+    let (clean, kinds) = SecretRedactor::global().redact(&text);
+    if kinds.is_empty() {
+        return text;
+    }
+    warn!("🔒 Redacted {} secrets from {} before sending it to the LLM", kinds.len(), source);
+    clean
+}
+
 /// Tool to search the knowledge graph and vector store
 pub struct SearchTool {
     graph: Arc<KnowledgeGraph>,
     vector_store: Option<Arc<VectorStore>>,
+    cache: SearchCache,
 }
 
 impl SearchTool {
     pub fn new(graph: Arc<KnowledgeGraph>, vector_store: Option<Arc<VectorStore>>) -> Self {
-        Self { graph, vector_store }
+        Self { graph, vector_store, cache: SearchCache::new() }
+    }
+
+    /// Share search results with other searchers in the same run
+    pub fn with_cache(mut self, cache: SearchCache) -> Self {
+        self.cache = cache;
+        self
     }
 }
 
@@ -229,6 +681,7 @@ impl SearchTool {
 impl Tool for SearchTool {
     fn name(&self) -> &str { "search" }
     fn description(&self) -> &str { "Search for symbols in the codebase using graph and vector search" }
+    fn safety(&self) -> ToolSafety { ToolSafety::ReadOnly }
     fn schema(&self) -> serde_json::Value {
         json!({
             "type": "object",
@@ -244,18 +697,18 @@ impl Tool for SearchTool {
         let mut results = Vec::new();
 
         // Vector search
-        if let Some(vs) = &self.vector_store {
-            if let Ok(vec_results) = vs.search_similar(query, 5).await {
-                for vr in vec_results {
-                    if let Ok(symbols) = self.graph.find_symbols_by_name(&vr.symbol.name) {
-                        results.extend(symbols);
-                    }
+        if let Some(vs) = &self.vector_store
+            && let Ok(vec_results) = self.cache.search_similar(vs, query, 5).await
+        {
+            for vr in vec_results {
+                if let Ok(symbols) = self.graph.find_symbols_by_name(&vr.symbol.name) {
+                    results.extend(symbols);
                 }
             }
         }
 
         // Graph search
-        if let Ok(graph_results) = self.graph.search_symbols(query) {
+        if let Ok(graph_results) = self.cache.search_symbols(&self.graph, query) {
             results.extend(graph_results);
         }
 
@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Limits on a single autonomous agent run. Read from the `[agent]` table of
+/// `miow.toml`, overridden by CLI flags or the web request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgentBudget {
+    /// Decide/act rounds before the agent has to stop
+    pub max_iterations: usize,
+    /// Tool executions across the whole run
+    pub max_tool_calls: Option<usize>,
+    /// Prompt plus completion tokens spent on agent decisions
+    pub max_tokens: Option<usize>,
+    /// Time the loop may take, in seconds
+    #[serde(rename = "wall_clock_secs", with = "optional_secs")]
+    pub wall_clock: Option<Duration>,
+    /// Self-review rounds after the loop, each followed by searches for what's missing
+    pub reflection_rounds: usize,
+}
+
+impl Default for AgentBudget {
+    fn default() -> Self {
+        Self {
+            max_iterations: 15,
+            max_tool_calls: None,
+            max_tokens: None,
+            wall_clock: None,
+            reflection_rounds: 1,
+        }
+    }
+}
+
+impl AgentBudget {
+    /// This budget with no limit looser than `limit`'s; limits `limit` leaves unset stay as they are
+    pub fn capped_by(self, limit: &AgentBudget) -> Self {
+        fn min_set<T: Ord + Copy>(requested: Option<T>, limit: Option<T>) -> Option<T> {
+            match (requested, limit) {
+                (Some(requested), Some(limit)) => Some(requested.min(limit)),
+                (requested, None) => requested,
+                (None, limit) => limit,
+            }
+        }
+        Self {
+            max_iterations: self.max_iterations.min(limit.max_iterations),
+            max_tool_calls: min_set(self.max_tool_calls, limit.max_tool_calls),
+            max_tokens: min_set(self.max_tokens, limit.max_tokens),
+            wall_clock: min_set(self.wall_clock, limit.wall_clock),
+            reflection_rounds: self.reflection_rounds.min(limit.reflection_rounds),
+        }
+    }
+}
+
+/// Which limit ended a run early
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetLimit {
+    Iterations,
+    ToolCalls,
+    Tokens,
+    WallClock,
+}
+
+impl std::fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetLimit::Iterations => write!(f, "iteration limit"),
+            BudgetLimit::ToolCalls => write!(f, "tool call limit"),
+            BudgetLimit::Tokens => write!(f, "token limit"),
+            BudgetLimit::WallClock => write!(f, "time limit"),
+        }
+    }
+}
+
+/// What a run has spent so far; saved with the run so a resumed run keeps counting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BudgetUsage {
+    pub iterations: usize,
+    pub tool_calls: usize,
+    pub tokens: usize,
+}
+
+/// Spending against an `AgentBudget` during one run
+#[derive(Debug)]
+pub(crate) struct BudgetTracker {
+    budget: AgentBudget,
+    started: Instant,
+    pub iterations: usize,
+    pub tool_calls: usize,
+    pub tokens: usize,
+}
+
+impl BudgetTracker {
+    /// Continue from an interrupted run's spending; the wall clock starts afresh
+    pub fn resume(budget: AgentBudget, usage: BudgetUsage) -> Self {
+        Self {
+            budget,
+            started: Instant::now(),
+            iterations: usage.iterations,
+            tool_calls: usage.tool_calls,
+            tokens: usage.tokens,
+        }
+    }
+
+    pub fn usage(&self) -> BudgetUsage {
+        BudgetUsage {
+            iterations: self.iterations,
+            tool_calls: self.tool_calls,
+            tokens: self.tokens,
+        }
+    }
+
+    /// The first limit that has been reached, if any
+    pub fn exhausted(&self) -> Option<BudgetLimit> {
+        if self.iterations >= self.budget.max_iterations {
+            Some(BudgetLimit::Iterations)
+        } else {
+            self.tools_exhausted()
+        }
+    }
+
+    /// Like `exhausted`, for tool calls made outside the decide/act loop
+    pub fn tools_exhausted(&self) -> Option<BudgetLimit> {
+        if self.budget.max_tool_calls.is_some_and(|max| self.tool_calls >= max) {
+            Some(BudgetLimit::ToolCalls)
+        } else if self.budget.max_tokens.is_some_and(|max| self.tokens >= max) {
+            Some(BudgetLimit::Tokens)
+        } else if self.remaining_time().is_some_and(|left| left.is_zero()) {
+            Some(BudgetLimit::WallClock)
+        } else {
+            None
+        }
+    }
+
+    /// Time left before the wall-clock limit, when there is one
+    pub fn remaining_time(&self) -> Option<Duration> {
+        self.budget.wall_clock.map(|limit| limit.saturating_sub(self.started.elapsed()))
+    }
+
+    pub fn max_iterations(&self) -> usize {
+        self.budget.max_iterations
+    }
+
+    pub fn reflection_rounds(&self) -> usize {
+        self.budget.reflection_rounds
+    }
+}
+
+mod optional_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(value: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(duration) => serializer.serialize_some(&duration.as_secs()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_secs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exhausted_reports_first_limit_hit() {
+        let budget = AgentBudget {
+            max_iterations: 3,
+            max_tool_calls: Some(2),
+            max_tokens: Some(1000),
+            wall_clock: None,
+            reflection_rounds: 0,
+        };
+        let mut tracker = BudgetTracker::resume(budget.clone(), BudgetUsage::default());
+        assert_eq!(tracker.exhausted(), None);
+
+        tracker.tool_calls = 2;
+        assert_eq!(tracker.exhausted(), Some(BudgetLimit::ToolCalls));
+        tracker.iterations = 3;
+        assert_eq!(tracker.exhausted(), Some(BudgetLimit::Iterations));
+
+        // A resumed run counts what it spent before the interruption
+        let resumed = BudgetTracker::resume(budget, BudgetUsage { tokens: 1000, ..BudgetUsage::default() });
+        assert_eq!(resumed.exhausted(), Some(BudgetLimit::Tokens));
+
+        let expired = BudgetTracker::resume(
+            AgentBudget { wall_clock: Some(Duration::ZERO), ..AgentBudget::default() },
+            BudgetUsage::default(),
+        );
+        assert_eq!(expired.exhausted(), Some(BudgetLimit::WallClock));
+    }
+
+    #[test]
+    fn test_budget_from_json() {
+        let budget: AgentBudget = serde_json::from_str(r#"{"max_tokens": 5000, "wall_clock_secs": 90}"#).unwrap();
+        assert_eq!(budget.max_iterations, 15);
+        assert_eq!(budget.max_tokens, Some(5000));
+        assert_eq!(budget.wall_clock, Some(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn test_capped_by_keeps_the_tighter_limits() {
+        let project = AgentBudget { max_iterations: 10, max_tokens: Some(20_000), ..AgentBudget::default() };
+        let requested = AgentBudget {
+            max_iterations: 1000,
+            max_tool_calls: Some(5),
+            max_tokens: None,
+            wall_clock: Some(Duration::from_secs(60)),
+            reflection_rounds: 9,
+        };
+        let budget = requested.capped_by(&project);
+        assert_eq!(budget.max_iterations, 10);
+        assert_eq!(budget.max_tool_calls, Some(5));
+        assert_eq!(budget.max_tokens, Some(20_000));
+        assert_eq!(budget.wall_clock, Some(Duration::from_secs(60)));
+        assert_eq!(budget.reflection_rounds, 1);
+    }
+}
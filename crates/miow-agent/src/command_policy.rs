@@ -0,0 +1,289 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// What `run_command` may run and how. Read from the `[commands]` table of the user's
+/// `miow.toml`; a project's own `[commands]` can only tighten it (`restricted_by`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandPolicy {
+    /// Programs the agent may run; empty allows anything not denied
+    pub allow: Vec<String>,
+    /// Programs, or command fragments containing spaces, that are always refused.
+    /// This is best effort, not a security boundary: only the first word of each
+    /// command is compared, so `sh -c 'curl …'` or `env curl` get past it. Use an
+    /// allowlist or approvals to keep a command from running.
+    pub deny: Vec<String>,
+    /// Refuse working directories outside the codebase root
+    pub confine_to_root: bool,
+    pub timeout_secs: u64,
+    /// Stdout and stderr are each cut to this many bytes
+    pub max_output_bytes: usize,
+    pub approval: ApprovalMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalMode {
+    /// Run permitted commands without asking (`--yes`)
+    Auto,
+    /// Ask the agent's approver (the terminal, or the web client) before each command
+    Prompt,
+    /// Never run commands
+    Deny,
+}
+
+/// A project's `[commands]` table. Only the settings it states apply, and each can
+/// only make the policy it's applied to stricter.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandRestrictions {
+    pub allow: Option<Vec<String>>,
+    pub deny: Vec<String>,
+    pub confine_to_root: Option<bool>,
+    pub timeout_secs: Option<u64>,
+    pub max_output_bytes: Option<usize>,
+    pub approval: Option<ApprovalMode>,
+}
+
+impl ApprovalMode {
+    /// Auto < Prompt < Deny
+    fn strictness(self) -> u8 {
+        match self {
+            ApprovalMode::Auto => 0,
+            ApprovalMode::Prompt => 1,
+            ApprovalMode::Deny => 2,
+        }
+    }
+}
+
+impl Default for CommandPolicy {
+    fn default() -> Self {
+        Self {
+            allow: vec![],
+            deny: ["sudo", "su", "mkfs", "dd", "shutdown", "reboot", "curl", "wget", "ssh", "scp", "rm -rf /"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            confine_to_root: true,
+            timeout_secs: 30,
+            max_output_bytes: 64 * 1024,
+            approval: ApprovalMode::Prompt,
+        }
+    }
+}
+
+impl CommandPolicy {
+    /// For servers and other runs with nobody to answer a prompt: asking becomes refusing
+    pub fn non_interactive(mut self) -> Self {
+        if self.approval == ApprovalMode::Prompt {
+            self.approval = ApprovalMode::Deny;
+        }
+        self
+    }
+
+    /// This policy tightened by `restrictions`: allowlists intersect, denylists add up,
+    /// and approval, confinement, the timeout and the output cap keep the stricter setting
+    pub fn restricted_by(mut self, restrictions: &CommandRestrictions) -> Self {
+        if let Some(allow) = &restrictions.allow {
+            if self.allow.is_empty() {
+                self.allow = allow.clone();
+            } else if !allow.is_empty() {
+                self.allow.retain(|program| allow.contains(program));
+                // Nothing left in both lists; an empty allowlist would allow anything
+                if self.allow.is_empty() {
+                    self.approval = ApprovalMode::Deny;
+                }
+            }
+        }
+        for pattern in &restrictions.deny {
+            if !self.deny.contains(pattern) {
+                self.deny.push(pattern.clone());
+            }
+        }
+        self.confine_to_root |= restrictions.confine_to_root.unwrap_or(false);
+        if let Some(timeout) = restrictions.timeout_secs {
+            self.timeout_secs = self.timeout_secs.min(timeout);
+        }
+        if let Some(max_bytes) = restrictions.max_output_bytes {
+            self.max_output_bytes = self.max_output_bytes.min(max_bytes);
+        }
+        if let Some(approval) = restrictions.approval.filter(|a| a.strictness() > self.approval.strictness()) {
+            self.approval = approval;
+        }
+        self
+    }
+
+    /// Check a command against the allow and deny lists
+    pub fn check(&self, command: &str) -> Result<()> {
+        if self.approval == ApprovalMode::Deny {
+            return Err(anyhow!("Running commands is disabled by the command policy"));
+        }
+
+        for pattern in self.deny.iter().filter(|p| p.contains(' ')) {
+            if command.contains(pattern.as_str()) {
+                return Err(anyhow!("Command blocked by policy (matches '{}')", pattern));
+            }
+        }
+
+        let programs = programs_in(command);
+        if let Some(program) = programs.iter().find(|p| self.deny.iter().any(|d| d == *p)) {
+            return Err(anyhow!("Command blocked by policy ('{}' is denied)", program));
+        }
+        if !self.allow.is_empty() {
+            // Substitutions could run anything, whatever the first word says
+            if command.contains("$(") || command.contains('`') {
+                return Err(anyhow!("Command substitution is not allowed when an allowlist is set"));
+            }
+            if let Some(program) = programs.iter().find(|p| !self.allow.iter().any(|a| a == *p)) {
+                return Err(anyhow!("Command blocked by policy ('{}' is not in the allowlist)", program));
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve the requested working directory, keeping it inside `root` when confined
+    pub fn working_dir(&self, root: &Path, requested: Option<&str>) -> Result<PathBuf> {
+        let dir = match requested {
+            Some(dir) => root.join(dir),
+            None => root.to_path_buf(),
+        };
+        let dir = dir.canonicalize().map_err(|e| anyhow!("Working directory {}: {}", dir.display(), e))?;
+        if self.confine_to_root && !dir.starts_with(root.canonicalize()?) {
+            return Err(anyhow!("Working directory {} is outside the codebase", dir.display()));
+        }
+        Ok(dir)
+    }
+
+    /// Whether each command must be approved before it runs
+    pub fn needs_approval(&self) -> bool {
+        self.approval == ApprovalMode::Prompt
+    }
+}
+
+/// First word of each command in a pipeline, `&&` / `||` / `;` chain or `&` background
+/// list, ignoring leading `VAR=value` assignments, redirections (`2>&1`, `> out.log`)
+/// and directory prefixes
+fn programs_in(command: &str) -> Vec<String> {
+    command_segments(command)
+        .into_iter()
+        .filter_map(|segment| {
+            let mut words = segment.split_whitespace();
+            while let Some(word) = words.next() {
+                if word.contains('=') {
+                    continue;
+                }
+                if let Some(operator) = redirection(word) {
+                    // A bare operator (`>`, `2>>`) takes the next word as its target
+                    if operator.len() == word.len() {
+                        words.next();
+                    }
+                    continue;
+                }
+                return Some(word.rsplit('/').next().unwrap_or(word).to_string());
+            }
+            None
+        })
+        .collect()
+}
+
+/// `command` split at `|`, `;`, newlines and `&`, except the `&` of a redirection
+/// such as `2>&1` or `&> out.log`
+fn command_segments(command: &str) -> Vec<&str> {
+    let bytes = command.as_bytes();
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        let separator = match byte {
+            b'|' | b';' | b'\n' => true,
+            b'&' => {
+                let after_redirect = i > 0 && matches!(bytes[i - 1], b'>' | b'<');
+                let before_redirect = bytes.get(i + 1) == Some(&b'>');
+                !after_redirect && !before_redirect
+            }
+            _ => false,
+        };
+        if separator {
+            segments.push(&command[start..i]);
+            start = i + 1;
+        }
+    }
+    segments.push(&command[start..]);
+    segments
+}
+
+/// The redirection operator `word` starts with (`2>`, `>>`, `&>`, `<`), if any
+fn redirection(word: &str) -> Option<&str> {
+    let digits = word.len() - word.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &word[digits..];
+    let rest = rest.strip_prefix('&').filter(|r| r.starts_with('>')).unwrap_or(rest);
+    let operator_len = rest.len() - rest.trim_start_matches(['>', '<']).len();
+    if operator_len == 0 {
+        return None;
+    }
+    let end = word.len() - rest.len() + operator_len;
+    // `>&1`, `<&0`: the target is part of the word
+    Some(&word[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_programs_in() {
+        assert_eq!(programs_in("cargo test && /usr/bin/grep -r foo | head"), vec!["cargo", "grep", "head"]);
+        assert_eq!(programs_in("RUST_LOG=debug cargo run"), vec!["cargo"]);
+        assert_eq!(programs_in("cargo test 2>&1 | tail -n 20"), vec!["cargo", "tail"]);
+        assert_eq!(programs_in("cargo build &> build.log; 2> err.log ls >&2"), vec!["cargo", "ls"]);
+        assert_eq!(programs_in("sleep 5 & python evil.py"), vec!["sleep", "python"]);
+    }
+
+    #[test]
+    fn test_check() {
+        let policy = CommandPolicy::default();
+        assert!(policy.check("cargo test").is_ok());
+        assert!(policy.check("ls && sudo rm x").is_err());
+        assert!(policy.check("rm -rf / --no-preserve-root").is_err());
+
+        let allowlisted = CommandPolicy { allow: vec!["cargo".into(), "ls".into()], ..CommandPolicy::default() };
+        assert!(allowlisted.check("cargo check; ls src").is_ok());
+        assert!(allowlisted.check("cargo check | python -c 'x'").is_err());
+        assert!(allowlisted.check("ls $(python evil.py)").is_err());
+        assert!(allowlisted.check("cargo test 2>&1").is_ok());
+        assert!(allowlisted.check("ls & python evil.py").is_err());
+
+        assert!(CommandPolicy::default().non_interactive().check("ls").is_err());
+    }
+
+    #[test]
+    fn test_restrictions_only_tighten() {
+        let user = CommandPolicy {
+            allow: vec!["cargo".into(), "ls".into()],
+            approval: ApprovalMode::Prompt,
+            ..CommandPolicy::default()
+        };
+        let relaxing: CommandRestrictions = serde_json::from_value(serde_json::json!({
+            "allow": ["cargo", "python"], "deny": [], "confine_to_root": false, "approval": "auto", "timeout_secs": 600
+        }))
+        .unwrap();
+        let policy = user.clone().restricted_by(&relaxing);
+        assert_eq!(policy.allow, vec!["cargo"]);
+        assert_eq!(policy.deny, CommandPolicy::default().deny);
+        assert!(policy.confine_to_root);
+        assert_eq!(policy.approval, ApprovalMode::Prompt);
+        assert_eq!(policy.timeout_secs, 30);
+
+        let tightening: CommandRestrictions =
+            serde_json::from_value(serde_json::json!({ "deny": ["git push"], "approval": "deny", "timeout_secs": 5 })).unwrap();
+        let policy = user.clone().restricted_by(&tightening);
+        assert_eq!(policy.allow, vec!["cargo", "ls"]);
+        assert!(policy.deny.contains(&"git push".to_string()));
+        assert_eq!(policy.approval, ApprovalMode::Deny);
+        assert_eq!(policy.timeout_secs, 5);
+
+        // No program left in both allowlists refuses every command
+        let disjoint = CommandRestrictions { allow: Some(vec!["python".into()]), ..CommandRestrictions::default() };
+        assert!(user.restricted_by(&disjoint).check("ls").is_err());
+    }
+}
@@ -151,7 +151,7 @@ struct ItemSummary {
 fn truncate_preview(content: &str, max_chars: usize) -> String {
     let mut s: String = content.chars().take(max_chars).collect();
     if content.chars().count() > max_chars {
-        s.push_str("…");
+        s.push('…');
     }
     s
 }
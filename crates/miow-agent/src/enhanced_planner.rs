@@ -21,6 +21,7 @@ pub struct ExecutionPlan {
     pub goal: String,
     pub steps: Vec<PlanStep>,
     pub estimated_duration: u64, // seconds
+    #[serde(default)]
     pub created_at: u64,
 }
 
@@ -173,10 +174,10 @@ Respond ONLY with valid JSON."#,
         visited.insert(step.id.clone(), true);
         
         for dep_id in &step.dependencies {
-            if let Some(dep_step) = all_steps.iter().find(|s| &s.id == dep_id) {
-                if self.has_circular_dependency(dep_step, all_steps, visited)? {
-                    return Ok(true);
-                }
+            if let Some(dep_step) = all_steps.iter().find(|s| &s.id == dep_id)
+                && self.has_circular_dependency(dep_step, all_steps, visited)?
+            {
+                return Ok(true);
             }
         }
         
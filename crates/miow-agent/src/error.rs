@@ -0,0 +1,15 @@
+/// Agent failures callers may want to tell apart. They travel inside
+/// `anyhow::Error`.
+#[derive(Debug, thiserror::Error)]
+pub enum AgentError {
+    /// The run's cancellation token fired: the client left or the server is stopping
+    #[error("Agent run cancelled")]
+    Cancelled,
+    /// A tool needed approval or an answer and there was nobody to ask
+    #[error("{0}")]
+    NobodyToAsk(String),
+    #[error("Invalid run id '{0}'")]
+    InvalidRunId(String),
+    #[error("No saved run '{0}'")]
+    RunNotFound(String),
+}
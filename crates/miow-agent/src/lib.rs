@@ -3,6 +3,16 @@ pub mod router;
 pub mod workers;
 pub mod context_auditor;
 pub mod tools;
+pub mod script_tool;
+pub mod command_policy;
+pub mod budget;
+pub mod approval;
+pub mod error;
+pub mod search_cache;
+pub mod lsp_tool;
+pub mod run_state;
+#[cfg(feature = "web-search")]
+pub mod web_search;
 pub mod prompt_registry;
 pub mod enhanced_planner;
 pub mod self_monitor;
@@ -11,7 +21,17 @@ pub use autonomous::AutonomousAgent;
 pub use router::{GeminiRouterAgent, RouterAgent, SearchPlan, SearchQuery, WorkerPlan};
 pub use workers::{WorkerAgent, GeminiWorkerAgent, WorkerResult};
 pub use context_auditor::GeminiContextAuditor;
-pub use tools::{Tool, ToolRegistry, ViewFileTool, ListDirTool, RunCommandTool, WriteFileTool};
+pub use tools::{Tool, ToolRegistry, ToolSafety, BUILTIN_TOOL_NAMES, ViewFileTool, ListDirTool, RunCommandTool, WriteFileTool, GrepTool, GitLogTool, GitBlameTool};
+pub use approval::{Answerer, ApprovalRequest, Approver, AutoApprover, ChannelApprover, Question, TerminalApprover};
+pub use budget::{AgentBudget, BudgetLimit, BudgetUsage};
+pub use error::AgentError;
+pub use script_tool::{ScriptTool, ScriptToolArg, ScriptToolSpec};
+pub use command_policy::{ApprovalMode, CommandPolicy, CommandRestrictions};
+pub use search_cache::SearchCache;
+pub use lsp_tool::{LspConfig, LspTool};
+pub use run_state::{unix_now, PromptItem, PromptRecord, RunRecorder, RunState, RunStore};
+#[cfg(feature = "web-search")]
+pub use web_search::{WebSearchBackend, WebSearchTool};
 pub use prompt_registry::{PromptRegistry, SpecializedPrompt, PromptCategory, Priority};
 pub use enhanced_planner::{EnhancedPlanner, ExecutionPlan, PlanStep};
 pub use self_monitor::{SelfMonitor, HealthMetrics, HealthIssue};
@@ -0,0 +1,498 @@
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use lsp_types::notification::Notification;
+use lsp_types::request::Request;
+use lsp_types::{Location, Position, Url};
+use miow_graph::KnowledgeGraph;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{Mutex, mpsc};
+use tracing::{info, warn};
+
+use crate::tools::{Tool, ToolSafety};
+
+/// Locations listed per answer; the rest are counted
+const MAX_LOCATIONS: usize = 50;
+
+/// The language server used by the `lsp` tool. Read from the `[lsp]` table of the user's `miow.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LspConfig {
+    /// Program speaking LSP over stdio, e.g. `rust-analyzer` or `typescript-language-server`
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Per request, including the server's start-up and indexing on the first one
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+impl LspConfig {
+    /// A well-known server for the project's language, if it is on `PATH`
+    pub fn detect(root: &Path) -> Option<Self> {
+        let candidates: [(&str, &str, &[&str]); 3] = [
+            ("Cargo.toml", "rust-analyzer", &[]),
+            ("tsconfig.json", "typescript-language-server", &["--stdio"]),
+            ("pyproject.toml", "pyright-langserver", &["--stdio"]),
+        ];
+        candidates
+            .into_iter()
+            .find(|(marker, command, _)| root.join(marker).exists() && on_path(command))
+            .map(|(_, command, args)| Self {
+                command: command.to_string(),
+                args: args.iter().map(|a| a.to_string()).collect(),
+                timeout_secs: default_timeout_secs(),
+            })
+    }
+}
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// Quiet spell after which a server with no work in progress counts as ready
+const SETTLE_QUIET: Duration = Duration::from_secs(2);
+
+/// A language server process spoken to with JSON-RPC over stdio. A background
+/// task reads its output, so giving up on an answer never splits a message.
+struct LspClient {
+    _child: Child,
+    stdin: ChildStdin,
+    incoming: mpsc::UnboundedReceiver<Value>,
+    next_id: i64,
+    opened: HashSet<Url>,
+    timeout: Duration,
+}
+
+impl LspClient {
+    async fn start(config: &LspConfig, root: &Path) -> Result<Self> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start language server '{}'", config.command))?;
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("Language server has no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("Language server has no stdout"))?;
+
+        let (tx, incoming) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut stdout = BufReader::new(stdout);
+            while let Ok(message) = read_message(&mut stdout).await {
+                if tx.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut client = Self {
+            _child: child,
+            stdin,
+            incoming,
+            next_id: 0,
+            opened: HashSet::new(),
+            timeout: Duration::from_secs(config.timeout_secs.max(1)),
+        };
+
+        let root_uri = Url::from_directory_path(root).map_err(|_| anyhow!("Invalid codebase root: {}", root.display()))?;
+        #[allow(deprecated)]
+        let params = lsp_types::InitializeParams {
+            process_id: Some(std::process::id()),
+            root_uri: Some(root_uri.clone()),
+            workspace_folders: Some(vec![lsp_types::WorkspaceFolder { uri: root_uri, name: "root".to_string() }]),
+            capabilities: lsp_types::ClientCapabilities {
+                // Lets us see when start-up indexing is done
+                window: Some(lsp_types::WindowClientCapabilities {
+                    work_done_progress: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        client.request::<lsp_types::request::Initialize>(params).await?;
+        client.notify::<lsp_types::notification::Initialized>(lsp_types::InitializedParams {}).await?;
+        client.settle().await?;
+        info!("🔌 Language server '{}' started", config.command);
+        Ok(client)
+    }
+
+    /// Wait out start-up work: until no progress is running and the server has
+    /// been quiet for a moment. Servers answer with nothing before they have
+    /// loaded the project. Gives up waiting (but not on the server) at the timeout.
+    async fn settle(&mut self) -> Result<()> {
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let mut active = HashSet::new();
+        loop {
+            let wait_until = if active.is_empty() {
+                deadline.min(tokio::time::Instant::now() + SETTLE_QUIET)
+            } else {
+                deadline
+            };
+            let Ok(message) = tokio::time::timeout_at(wait_until, self.incoming.recv()).await else {
+                return Ok(());
+            };
+            let message = message.ok_or_else(|| anyhow!("Language server exited"))?;
+            if message["method"] == "$/progress" {
+                let token = message["params"]["token"].to_string();
+                match message["params"]["value"]["kind"].as_str() {
+                    Some("begin") => {
+                        active.insert(token);
+                    }
+                    Some("end") => {
+                        active.remove(&token);
+                    }
+                    _ => {}
+                }
+            } else {
+                self.answer_server_request(&message).await?;
+            }
+        }
+    }
+
+    /// Tell the server about `path` the first time it is asked about
+    async fn open(&mut self, uri: &Url, path: &Path) -> Result<()> {
+        if self.opened.contains(uri) {
+            return Ok(());
+        }
+        let text = tokio::fs::read_to_string(path).await.with_context(|| format!("Failed to read {}", path.display()))?;
+        let params = lsp_types::DidOpenTextDocumentParams {
+            text_document: lsp_types::TextDocumentItem {
+                uri: uri.clone(),
+                language_id: language_id(path).to_string(),
+                version: 1,
+                text,
+            },
+        };
+        self.notify::<lsp_types::notification::DidOpenTextDocument>(params).await?;
+        self.opened.insert(uri.clone());
+        Ok(())
+    }
+
+    async fn request<R: Request>(&mut self, params: R::Params) -> Result<R::Result> {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": R::METHOD, "params": params })).await?;
+
+        let timeout = self.timeout;
+        let response = tokio::time::timeout(timeout, self.wait_for(id))
+            .await
+            .map_err(|_| anyhow!("Language server did not answer {} within {:?}", R::METHOD, timeout))??;
+        if let Some(error) = response.get("error") {
+            bail!("{} failed: {}", R::METHOD, error["message"].as_str().unwrap_or("unknown error"));
+        }
+        serde_json::from_value(response.get("result").cloned().unwrap_or(Value::Null))
+            .with_context(|| format!("Unexpected {} response", R::METHOD))
+    }
+
+    async fn notify<N: Notification>(&mut self, params: N::Params) -> Result<()> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": N::METHOD, "params": params })).await
+    }
+
+    /// Read until the response to `id`, answering the server's own requests on the way
+    async fn wait_for(&mut self, id: i64) -> Result<Value> {
+        loop {
+            let message = self.incoming.recv().await.ok_or_else(|| anyhow!("Language server exited"))?;
+            if message.get("method").is_none() && message["id"].as_i64() == Some(id) {
+                return Ok(message);
+            }
+            self.answer_server_request(&message).await?;
+        }
+    }
+
+    /// Reply to requests the server makes of us (e.g. workspace/configuration or
+    /// window/workDoneProgress/create); notifications need no reply
+    async fn answer_server_request(&mut self, message: &Value) -> Result<()> {
+        let (Some(id), Some(method)) = (message.get("id"), message["method"].as_str()) else {
+            return Ok(());
+        };
+        let result = match method {
+            "workspace/configuration" => {
+                let items = message["params"]["items"].as_array().map_or(0, Vec::len);
+                Value::Array(vec![Value::Null; items])
+            }
+            _ => Value::Null,
+        };
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "result": result })).await
+    }
+
+    async fn send(&mut self, message: &Value) -> Result<()> {
+        let body = serde_json::to_vec(message)?;
+        self.stdin.write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes()).await?;
+        self.stdin.write_all(&body).await?;
+        self.stdin.flush().await.context("Language server closed its input")
+    }
+}
+
+/// Read one `Content-Length`-framed JSON-RPC message
+async fn read_message(stdout: &mut BufReader<ChildStdout>) -> Result<Value> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if stdout.read_line(&mut header).await? == 0 {
+            bail!("Language server exited");
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>().context("Bad Content-Length header")?);
+        }
+    }
+    let mut body = vec![0; length.ok_or_else(|| anyhow!("Language server sent no Content-Length"))?];
+    stdout.read_exact(&mut body).await?;
+    serde_json::from_slice(&body).context("Language server sent invalid JSON")
+}
+
+enum Server {
+    NotStarted,
+    Running(Box<LspClient>),
+    /// Failed to start or to answer; the graph answers instead for the rest of the run
+    Unavailable,
+}
+
+/// Tool to find where a symbol is defined or used. Asks the language server
+/// when one is configured, which resolves the symbol at an exact position;
+/// otherwise, or when the server finds nothing, matches names in the graph.
+pub struct LspTool {
+    root: PathBuf,
+    graph: Arc<KnowledgeGraph>,
+    config: Option<LspConfig>,
+    server: Mutex<Server>,
+}
+
+impl LspTool {
+    pub fn new(root: PathBuf, graph: Arc<KnowledgeGraph>, config: Option<LspConfig>) -> Self {
+        Self { root, graph, config, server: Mutex::new(Server::NotStarted) }
+    }
+
+    /// Locations from the language server, or `None` when there is no usable server
+    async fn ask_server(&self, action: &str, path: &Path, position: Position) -> Option<Vec<Location>> {
+        let config = self.config.as_ref()?;
+        let mut server = self.server.lock().await;
+        if let Server::NotStarted = *server {
+            *server = match LspClient::start(config, &self.root).await {
+                Ok(client) => Server::Running(Box::new(client)),
+                Err(e) => {
+                    warn!("Language server unavailable, using the graph: {:#}", e);
+                    Server::Unavailable
+                }
+            };
+        }
+        let Server::Running(client) = &mut *server else { return None };
+
+        let result = async {
+            let uri = Url::from_file_path(path).map_err(|_| anyhow!("Invalid path: {}", path.display()))?;
+            client.open(&uri, path).await?;
+            let at = lsp_types::TextDocumentPositionParams {
+                text_document: lsp_types::TextDocumentIdentifier { uri },
+                position,
+            };
+            if action == "definition" {
+                let params = lsp_types::GotoDefinitionParams {
+                    text_document_position_params: at,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                };
+                let response = client.request::<lsp_types::request::GotoDefinition>(params).await?;
+                Ok::<_, anyhow::Error>(definition_locations(response))
+            } else {
+                let params = lsp_types::ReferenceParams {
+                    text_document_position: at,
+                    context: lsp_types::ReferenceContext { include_declaration: false },
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                };
+                Ok(client.request::<lsp_types::request::References>(params).await?.unwrap_or_default())
+            }
+        }
+        .await;
+
+        match result {
+            Ok(locations) => Some(locations),
+            Err(e) => {
+                warn!("Language server {} failed, using the graph from now on: {:#}", action, e);
+                *server = Server::Unavailable;
+                None
+            }
+        }
+    }
+
+    fn ask_graph(&self, action: &str, symbol: &str) -> Result<String> {
+        let results = if action == "definition" {
+            self.graph.find_symbols_by_name(symbol)?
+        } else {
+            self.graph.find_references_to(symbol)?
+        };
+        if results.is_empty() {
+            return Ok(format!("No {} found for `{}`.", plural(action), symbol));
+        }
+        let mut out = format!("{} of `{}` (name match in the graph, may include unrelated symbols of the same name):\n", title(action), symbol);
+        for result in results.iter().take(MAX_LOCATIONS) {
+            out.push_str(&format!("  {}:{} {} {}\n", result.file_path, result.start_line, result.kind, result.name));
+        }
+        if results.len() > MAX_LOCATIONS {
+            out.push_str(&format!("  ... and {} more\n", results.len() - MAX_LOCATIONS));
+        }
+        Ok(out)
+    }
+
+    fn format_locations(&self, action: &str, symbol: &str, locations: &[Location]) -> String {
+        let mut out = format!("{} of `{}` (language server):\n", title(action), symbol);
+        for location in locations.iter().take(MAX_LOCATIONS) {
+            let Ok(path) = location.uri.to_file_path() else { continue };
+            let line = location.range.start.line as usize;
+            let text = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| content.lines().nth(line).map(|l| l.trim().to_string()))
+                .unwrap_or_default();
+            let shown = path.strip_prefix(&self.root).unwrap_or(&path);
+            out.push_str(&format!("  {}:{}: {}\n", shown.display(), line + 1, text));
+        }
+        if locations.len() > MAX_LOCATIONS {
+            out.push_str(&format!("  ... and {} more\n", locations.len() - MAX_LOCATIONS));
+        }
+        out
+    }
+}
+
+#[async_trait]
+impl Tool for LspTool {
+    fn name(&self) -> &str { "lsp" }
+    fn description(&self) -> &str {
+        "Find where a symbol used at a given line is defined, or every place that references it. \
+         Exact when a language server is available, otherwise by name"
+    }
+    fn safety(&self) -> ToolSafety { ToolSafety::ReadOnly }
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": { "type": "string", "enum": ["definition", "references"], "description": "What to find" },
+                "symbol": { "type": "string", "description": "Name of the symbol" },
+                "path": { "type": "string", "description": "File where the symbol appears, relative to the codebase root" },
+                "line": { "type": "integer", "description": "Line (1-based) in that file where the symbol appears" }
+            },
+            "required": ["action", "symbol"]
+        })
+    }
+    async fn execute(&self, args: Value) -> Result<String> {
+        let action = args["action"].as_str().ok_or_else(|| anyhow!("Missing 'action' argument"))?;
+        if action != "definition" && action != "references" {
+            bail!("'action' must be \"definition\" or \"references\"");
+        }
+        let symbol = args["symbol"].as_str().ok_or_else(|| anyhow!("Missing 'symbol' argument"))?;
+
+        // The server needs an exact position, so only a path and line let it help
+        if let (Some(rel), Some(line)) = (args["path"].as_str(), args["line"].as_u64()) {
+            let path = self.root.join(rel).canonicalize().with_context(|| format!("Path not found: {}", rel))?;
+            if !path.starts_with(self.root.canonicalize()?) {
+                bail!("Path is outside the codebase: {}", rel);
+            }
+            let content = tokio::fs::read_to_string(&path).await.with_context(|| format!("Failed to read {}", rel))?;
+            let position = symbol_position(&content, line.saturating_sub(1) as usize, symbol)
+                .ok_or_else(|| anyhow!("`{}` does not appear on line {} of {}", symbol, line, rel))?;
+
+            if let Some(locations) = self.ask_server(action, &path, position).await
+                && !locations.is_empty()
+            {
+                return Ok(self.format_locations(action, symbol, &locations));
+            }
+        }
+        self.ask_graph(action, symbol)
+    }
+}
+
+/// Position of the first whole-word `symbol` on `line` (0-based), in the
+/// UTF-16 columns LSP counts in
+fn symbol_position(content: &str, line: usize, symbol: &str) -> Option<Position> {
+    let text = content.lines().nth(line)?;
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let start = text.match_indices(symbol).map(|(i, _)| i).find(|&i| {
+        let before = text[..i].chars().next_back().is_none_or(|c| !is_ident(c));
+        let after = text[i + symbol.len()..].chars().next().is_none_or(|c| !is_ident(c));
+        before && after
+    })?;
+    Some(Position::new(line as u32, text[..start].encode_utf16().count() as u32))
+}
+
+fn definition_locations(response: Option<lsp_types::GotoDefinitionResponse>) -> Vec<Location> {
+    match response {
+        None => Vec::new(),
+        Some(lsp_types::GotoDefinitionResponse::Scalar(location)) => vec![location],
+        Some(lsp_types::GotoDefinitionResponse::Array(locations)) => locations,
+        Some(lsp_types::GotoDefinitionResponse::Link(links)) => links
+            .into_iter()
+            .map(|link| Location::new(link.target_uri, link.target_selection_range))
+            .collect(),
+    }
+}
+
+fn language_id(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).unwrap_or_default() {
+        "rs" => "rust",
+        "ts" => "typescript",
+        "tsx" => "typescriptreact",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "javascriptreact",
+        "py" => "python",
+        "go" => "go",
+        "java" => "java",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "hpp" => "cpp",
+        _ => "plaintext",
+    }
+}
+
+fn title(action: &str) -> &'static str {
+    if action == "definition" { "Definitions" } else { "References" }
+}
+
+fn plural(action: &str) -> &'static str {
+    if action == "definition" { "definitions" } else { "references" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_position_matches_whole_words_in_utf16() {
+        let content = "fn main() {\n    let é = user_id(userId);\n}\n";
+        assert_eq!(symbol_position(content, 1, "userId"), Some(Position::new(1, 20)));
+        assert_eq!(symbol_position(content, 1, "user"), None);
+        assert_eq!(symbol_position(content, 5, "main"), None);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_graph_without_server() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("main.rs"), "fn main() { run(); }\n").unwrap();
+        let graph = Arc::new(KnowledgeGraph::new(dir.join("graph.db")).unwrap());
+
+        let tool = LspTool::new(dir.to_path_buf(), graph, None);
+        let output = tool
+            .execute(json!({ "action": "definition", "symbol": "run", "path": "main.rs", "line": 1 }))
+            .await
+            .unwrap();
+        assert_eq!(output, "No definitions found for `run`.");
+        assert!(tool.execute(json!({ "action": "rename", "symbol": "run" })).await.is_err());
+    }
+}
@@ -1,10 +1,11 @@
+use crate::autonomous::redact_for_llm;
 use crate::PromptRegistry;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use miow_analyzer::Intent;
 use miow_core::ProjectSignature;
 use miow_llm::{LLMProvider, Message, Role};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
 use std::sync::Arc;
 
 /// A single semantic search query the router wants to execute.
@@ -35,9 +36,9 @@ pub struct WorkerPlan {
 /// Top‑level router output describing how to search the codebase.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchPlan {
-    /// High‑level intent label, e.g. "create_login_page", "add_auth", "fix_bug".
-    #[serde(default)]
-    pub global_intent: String,
+    /// High‑level intent; labels outside the taxonomy (e.g. "fix_bug") are resolved to it.
+    #[serde(default, deserialize_with = "miow_analyzer::intent::deserialize_lenient")]
+    pub global_intent: Intent,
     /// General queries that all workers / retrievers can use.
     #[serde(default)]
     pub search_queries: Vec<SearchQuery>,
@@ -50,6 +51,63 @@ pub struct SearchPlan {
 }
 
 impl SearchPlan {
+    /// Drop queries that repeat an earlier one (same text and kind, ignoring case),
+    /// whether in the global list or another worker's. The kept query inherits
+    /// the dropped one's target paths. Returns how many were dropped.
+    pub fn dedup_queries(&mut self) -> usize {
+        let key = |q: &SearchQuery| {
+            (
+                q.query.trim().to_lowercase(),
+                q.kind.as_deref().unwrap_or("").trim().to_lowercase(),
+            )
+        };
+
+        // Where a query lives: None for the global list, Some(i) for worker i, plus its index
+        type Slot = (Option<usize>, usize);
+        let mut first: std::collections::HashMap<(String, String), Slot> =
+            std::collections::HashMap::new();
+        let mut extra_paths: Vec<(Slot, Vec<String>)> = Vec::new();
+        let mut removed = 0;
+
+        let mut dedup = |owner: Option<usize>, queries: &mut Vec<SearchQuery>| {
+            let mut index = 0;
+            queries.retain(|q| {
+                let keep = match first.get(&key(q)) {
+                    Some(&kept) => {
+                        extra_paths.push((kept, q.target_paths.clone()));
+                        removed += 1;
+                        false
+                    }
+                    None => {
+                        first.insert(key(q), (owner, index));
+                        true
+                    }
+                };
+                if keep {
+                    index += 1;
+                }
+                keep
+            });
+        };
+        dedup(None, &mut self.search_queries);
+        for (i, worker) in self.workers.iter_mut().enumerate() {
+            dedup(Some(i), &mut worker.queries);
+        }
+
+        for ((owner, index), paths) in extra_paths {
+            let kept = match owner {
+                None => &mut self.search_queries[index],
+                Some(w) => &mut self.workers[w].queries[index],
+            };
+            for path in paths {
+                if !kept.target_paths.contains(&path) {
+                    kept.target_paths.push(path);
+                }
+            }
+        }
+        removed
+    }
+
     /// Convenience helper to flatten all queries into plain strings.
     pub fn all_query_strings(&self) -> Vec<String> {
         let mut out = Vec::new();
@@ -69,7 +127,7 @@ impl SearchPlan {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.global_intent.trim().is_empty()
+        self.global_intent == Intent::Unknown
             && self.search_queries.is_empty()
             && self.workers.is_empty()
     }
@@ -111,6 +169,9 @@ impl RouterAgent for GeminiRouterAgent {
         user_prompt: &str,
         project_signature: &ProjectSignature,
     ) -> Result<SearchPlan> {
+        // The task may carry pasted file contents or command output
+        let user_prompt = &redact_for_llm("the router's task", user_prompt.to_string());
+
         // First, classify the task to get recommended workers
         let task_classification = self.classify_task(user_prompt, project_signature).await?;
         let recommended_workers = self.registry.get_recommended_prompts(&task_classification.task_type);
@@ -119,6 +180,7 @@ impl RouterAgent for GeminiRouterAgent {
         let available_workers = self.get_available_workers_description();
 
         let project_description = project_signature.to_description();
+        let intents: Vec<&str> = Intent::ALL.iter().map(Intent::as_str).collect();
 
         let system_prompt = format!(r#"You are a Senior Architect Router Agent for an autonomous code-understanding system.
 Your job is to:
@@ -131,7 +193,7 @@ Available specialized workers:
 
 You MUST respond with a single JSON object ONLY, no extra commentary, matching this schema:
 {{
-  "global_intent": "short_snake_case_label",
+  "global_intent": "{}",
   "search_queries": [
     {{ "query": "string", "kind": "component|type|schema|api|style|helper|any", "target_paths": ["optional/path", "..."] }}
   ],
@@ -153,7 +215,7 @@ Guidelines:
 - Use target_paths hints when obvious (e.g. React: src/components, Next.js: app, pages).
 - Select 2-4 workers from the available list based on task needs.
 - If unsure, leave target_paths empty.
-"#, available_workers);
+"#, available_workers, intents.join("|"));
 
         let user_message = format!(
             "User task:\n{}\n\nDetected project description:\n{}\n\nRecommended workers based on task type: {}\n",
@@ -327,7 +389,7 @@ impl GeminiRouterAgent {
         }
 
         Ok(SearchPlan {
-            global_intent: "fallback_plan".to_string(),
+            global_intent: Intent::Unknown,
             search_queries: vec![SearchQuery {
                 query: user_prompt.to_string(),
                 kind: Some("any".to_string()),
@@ -346,3 +408,53 @@ struct TaskClassification {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(text: &str, paths: &[&str]) -> SearchQuery {
+        SearchQuery {
+            query: text.to_string(),
+            kind: Some("component".to_string()),
+            target_paths: paths.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_dedup_queries_across_workers() {
+        let mut plan = SearchPlan {
+            global_intent: Intent::CreatePage,
+            search_queries: vec![query("LoginForm", &["src/auth"])],
+            workers: vec![
+                WorkerPlan {
+                    worker_id: "ui".to_string(),
+                    description: String::new(),
+                    queries: vec![query("Button", &[]), query(" loginform ", &["src/pages"])],
+                },
+                WorkerPlan {
+                    worker_id: "auth".to_string(),
+                    description: String::new(),
+                    queries: vec![query("button", &["src/ui"]), query("useSession", &[])],
+                },
+            ],
+            execution_plan: Vec::new(),
+        };
+
+        assert_eq!(plan.dedup_queries(), 2);
+        assert_eq!(plan.search_queries[0].target_paths, vec!["src/auth", "src/pages"]);
+        assert_eq!(plan.workers[0].queries.len(), 1);
+        assert_eq!(plan.workers[0].queries[0].target_paths, vec!["src/ui"]);
+        assert_eq!(plan.workers[1].queries[0].query, "useSession");
+    }
+
+    #[test]
+    fn test_plan_intent_resolves_to_taxonomy() {
+        let plan: SearchPlan = serde_json::from_str(r#"{"global_intent": "fix_bug", "search_queries": []}"#).unwrap();
+        assert_eq!(plan.global_intent, Intent::Fix);
+        let plan: SearchPlan = serde_json::from_str(r#"{"global_intent": "CreateComponent"}"#).unwrap();
+        assert_eq!(plan.global_intent, Intent::CreateComponent);
+        let plan: SearchPlan = serde_json::from_str(r#"{"search_queries": []}"#).unwrap();
+        assert!(plan.is_empty());
+    }
+}
@@ -0,0 +1,488 @@
+use anyhow::{Context, Result, bail};
+use miow_core::ProjectSignature;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::autonomous::AgentContext;
+use crate::error::AgentError;
+use crate::router::SearchPlan;
+
+/// What a prompt-generation run has finished so far. Saved after every step so
+/// an interrupted run (crash, Ctrl-C, network outage) resumes without redoing
+/// the LLM calls behind the finished ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunState {
+    pub run_id: String,
+    pub task: String,
+    pub project_root: PathBuf,
+    /// Unix seconds
+    pub started_at: u64,
+    pub updated_at: u64,
+    #[serde(default)]
+    pub signature: Option<ProjectSignature>,
+    #[serde(default)]
+    pub search_plan: Option<SearchPlan>,
+    /// The agent's context as of its last completed step
+    #[serde(default)]
+    pub agent: Option<AgentContext>,
+    /// The agent loop (and its self-review) finished; `agent` is final
+    #[serde(default)]
+    pub agent_done: bool,
+    #[serde(default)]
+    pub implementation_plan: Option<String>,
+    /// The generated prompt, once the run is complete
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// What the prompt was built from, saved with it
+    #[serde(default)]
+    pub record: Option<PromptRecord>,
+    /// The run this one regenerates the prompt of
+    #[serde(default)]
+    pub rerun_of: Option<String>,
+    /// Files the user picked to build the prompt from
+    #[serde(default)]
+    pub selected_files: Vec<String>,
+    /// Request settings (budget, format) needed to start the run again
+    #[serde(default)]
+    pub options: Option<serde_json::Value>,
+    /// LLM tokens and cost the run spent
+    #[serde(default)]
+    pub usage: Option<miow_llm::UsageSummary>,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    /// Why the run failed, if it did
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The session the run belongs to, named after its first run; `None` until
+    /// the run is followed up on, when it is its own session
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// The run in the session this one follows up on
+    #[serde(default)]
+    pub follow_up_of: Option<String>,
+    /// That run's implementation plan, for this one to build on
+    #[serde(default)]
+    pub previous_plan: Option<String>,
+}
+
+impl RunState {
+    /// Id of the session the run belongs to
+    pub fn session(&self) -> &str {
+        self.session_id.as_deref().unwrap_or(&self.run_id)
+    }
+}
+
+/// Inputs and size of a generated prompt, for comparing runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptRecord {
+    /// Citation anchors (or sources) of the context items in the prompt
+    pub context_ids: Vec<String>,
+    /// Prompt generator settings
+    pub config: serde_json::Value,
+    pub model: Option<String>,
+    pub context_tokens: usize,
+    pub prompt_tokens: usize,
+    /// The symbols and files in the prompt, for feedback on them
+    #[serde(default)]
+    pub items: Vec<PromptItem>,
+}
+
+impl PromptRecord {
+    /// The prompt's items in any of `files`, or under a directory among them; all
+    /// of them when no files are given. Runs saved before items were recorded
+    /// fall back to the files their citations name.
+    pub fn items_in(&self, files: &[String]) -> Vec<PromptItem> {
+        let items = if self.items.is_empty() {
+            let mut items: Vec<PromptItem> = Vec::new();
+            for path in self.context_ids.iter().filter_map(|id| cited_path(id)) {
+                if !items.iter().any(|item| item.file_path == path) {
+                    items.push(PromptItem { file_path: path.to_string(), name: None });
+                }
+            }
+            items
+        } else {
+            self.items.clone()
+        };
+        if files.is_empty() {
+            return items;
+        }
+        items.into_iter().filter(|item| files.iter().any(|file| item.is_in(file))).collect()
+    }
+}
+
+/// A symbol, or a snippet of a file, in a prompt's context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptItem {
+    pub file_path: String,
+    /// None for a snippet rather than a symbol
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl PromptItem {
+    /// Whether the item is in `file`: the same path relative to the project, the
+    /// path given absolutely, or a directory above it
+    pub fn is_in(&self, file: &str) -> bool {
+        let file = file.trim_start_matches("./").trim_end_matches('/');
+        let path = self.file_path.as_str();
+        !file.is_empty()
+            && (path == file
+                || path.starts_with(&format!("{}/", file))
+                || file.ends_with(&format!("/{}", path)))
+    }
+}
+
+/// The file a citation anchor like `src/a.ts` or `src/a.ts:3-9` names; other
+/// context ids (`type:Name`, tool output, URLs) name none
+fn cited_path(id: &str) -> Option<&str> {
+    if ["type:", "schema:", "test:", "Tool:"].iter().any(|prefix| id.starts_with(prefix)) || id.contains("://") {
+        return None;
+    }
+    match id.rsplit_once(':') {
+        Some((path, lines)) if lines.split('-').all(|n| n.parse::<usize>().is_ok()) => Some(path),
+        _ => Some(id),
+    }
+}
+
+/// Run states as JSON files, one per run id, in a directory
+#[derive(Debug, Clone)]
+pub struct RunStore {
+    dir: PathBuf,
+}
+
+impl RunStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// A fresh id, unique per run on this machine
+    pub fn new_run_id() -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        format!("{:x}-{:x}", now.as_secs(), now.subsec_nanos() ^ std::process::id())
+    }
+
+    fn path(&self, run_id: &str) -> Result<PathBuf> {
+        if run_id.is_empty() || !run_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(AgentError::InvalidRunId(run_id.to_string()).into());
+        }
+        Ok(self.dir.join(format!("{}.json", run_id)))
+    }
+
+    pub fn exists(&self, run_id: &str) -> bool {
+        self.path(run_id).map(|p| p.exists()).unwrap_or(false)
+    }
+
+    pub fn load(&self, run_id: &str) -> Result<RunState> {
+        let path = self.path(run_id)?;
+        let content = match std::fs::read_to_string(&path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(AgentError::RunNotFound(run_id.to_string()).into())
+            }
+            result => result.with_context(|| format!("Failed to read run '{}'", run_id))?,
+        };
+        serde_json::from_str(&content).with_context(|| format!("Invalid run file {}", path.display()))
+    }
+
+    /// Every saved run, newest first. Files that can't be read are skipped.
+    pub fn list(&self) -> Result<Vec<RunState>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.dir.display())),
+        };
+        let mut runs = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let state = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(serde_json::from_str::<RunState>(&content)?));
+            match state {
+                Ok(state) => runs.push(state),
+                Err(e) => warn!("Skipping run file {}: {:#}", path.display(), e),
+            }
+        }
+        runs.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| b.run_id.cmp(&a.run_id)));
+        Ok(runs)
+    }
+
+    /// Start run `run_id` from what run `from` gathered, without its prompt, so
+    /// the prompt is generated again (e.g. with another format or templates)
+    pub fn fork(&self, from: &str, run_id: &str) -> Result<RunState> {
+        let mut state = self.load(from)?;
+        if !state.agent_done && state.search_plan.is_none() {
+            bail!("Run '{}' never finished gathering context; resume it with --resume", from);
+        }
+        let now = unix_now();
+        state.run_id = run_id.to_string();
+        state.started_at = now;
+        state.updated_at = now;
+        state.prompt = None;
+        state.record = None;
+        state.rerun_of = Some(from.to_string());
+        state.usage = None;
+        state.duration_ms = None;
+        state.error = None;
+        self.save(&state)?;
+        Ok(state)
+    }
+
+    /// The last run of session `session_id`: the one no other run follows up on,
+    /// newest first if the session branched
+    pub fn latest_in_session(&self, session_id: &str) -> Result<RunState> {
+        self.path(session_id)?;
+        let runs: Vec<RunState> = self.list()?.into_iter().filter(|run| run.session() == session_id).collect();
+        let followed: HashSet<&str> = runs.iter().filter_map(|run| run.follow_up_of.as_deref()).collect();
+        let latest = runs.iter().find(|run| !followed.contains(run.run_id.as_str())).or(runs.first());
+        latest.cloned().ok_or_else(|| AgentError::RunNotFound(session_id.to_string()).into())
+    }
+
+    /// Start run `run_id` for `task` as a follow-up to run `from`, in its session.
+    /// It begins with everything `from` gathered and its plan, so the agent only
+    /// searches for what the new task adds.
+    pub fn follow_up(&self, from: &str, run_id: &str, task: &str) -> Result<RunState> {
+        let previous = self.load(from)?;
+        let Some(agent) = previous.agent.clone().filter(|_| previous.agent_done) else {
+            bail!("Run '{}' never finished gathering context; resume it with --resume", from);
+        };
+        let now = unix_now();
+        let state = RunState {
+            run_id: run_id.to_string(),
+            task: task.to_string(),
+            project_root: previous.project_root.clone(),
+            started_at: now,
+            updated_at: now,
+            signature: previous.signature.clone(),
+            search_plan: None,
+            agent: Some(agent.follow_up(task)),
+            agent_done: false,
+            implementation_plan: None,
+            prompt: None,
+            record: None,
+            rerun_of: None,
+            selected_files: Vec::new(),
+            options: previous.options.clone(),
+            usage: None,
+            duration_ms: None,
+            error: None,
+            session_id: Some(previous.session().to_string()),
+            follow_up_of: Some(from.to_string()),
+            previous_plan: previous.implementation_plan.clone(),
+        };
+        self.save(&state)?;
+        Ok(state)
+    }
+
+    /// Write atomically, so a crash mid-save leaves the previous checkpoint intact
+    pub fn save(&self, state: &RunState) -> Result<()> {
+        let path = self.path(&state.run_id)?;
+        std::fs::create_dir_all(&self.dir)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(state)?)?;
+        std::fs::rename(&tmp, &path).with_context(|| format!("Failed to save {}", path.display()))
+    }
+}
+
+/// A run being recorded: each `update` is saved straight away. Clones share the state.
+#[derive(Debug, Clone)]
+pub struct RunRecorder {
+    store: RunStore,
+    state: Arc<Mutex<RunState>>,
+}
+
+impl RunRecorder {
+    /// Pick up `run_id` from `store`, or start recording it as a new run of `task`
+    pub fn open(store: RunStore, run_id: &str, task: &str, project_root: &Path) -> Result<Self> {
+        let state = if store.exists(run_id) {
+            store.load(run_id)?
+        } else {
+            let now = unix_now();
+            let state = RunState {
+                run_id: run_id.to_string(),
+                task: task.to_string(),
+                project_root: project_root.to_path_buf(),
+                started_at: now,
+                updated_at: now,
+                signature: None,
+                search_plan: None,
+                agent: None,
+                agent_done: false,
+                implementation_plan: None,
+                prompt: None,
+                record: None,
+                rerun_of: None,
+                selected_files: Vec::new(),
+                options: None,
+                usage: None,
+                duration_ms: None,
+                error: None,
+                session_id: None,
+                follow_up_of: None,
+                previous_plan: None,
+            };
+            store.save(&state)?;
+            state
+        };
+        Ok(Self { store, state: Arc::new(Mutex::new(state)) })
+    }
+
+    pub fn run_id(&self) -> String {
+        self.state.lock().unwrap().run_id.clone()
+    }
+
+    /// A copy of what has been recorded so far
+    pub fn state(&self) -> RunState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Change the state and save it; a failed save is logged, not fatal
+    pub fn update(&self, change: impl FnOnce(&mut RunState)) {
+        let mut state = self.state.lock().unwrap();
+        change(&mut state);
+        state.updated_at = unix_now();
+        if let Err(e) = self.store.save(&state) {
+            warn!("Failed to checkpoint run {}: {:#}", state.run_id, e);
+        }
+    }
+}
+
+/// Seconds since the Unix epoch, as run and job timestamps are stored
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorded_steps_survive_reopening() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let store = RunStore::new(dir);
+        let run_id = RunStore::new_run_id();
+
+        let run = RunRecorder::open(store.clone(), &run_id, "Add a login page", Path::new("/repo")).unwrap();
+        run.update(|state| state.implementation_plan = Some("1. Add the route".to_string()));
+        drop(run);
+
+        // Reopening ignores the task passed in and keeps what was recorded
+        let resumed = RunRecorder::open(store.clone(), &run_id, "something else", Path::new("/other")).unwrap();
+        let state = resumed.state();
+        assert_eq!(state.task, "Add a login page");
+        assert_eq!(state.project_root, PathBuf::from("/repo"));
+        assert_eq!(state.implementation_plan.as_deref(), Some("1. Add the route"));
+        assert!(!state.agent_done);
+
+        // A rerun starts from the gathered context but not the prompt
+        resumed.update(|state| {
+            state.agent_done = true;
+            state.prompt = Some("# TASK".to_string());
+            state.selected_files = vec!["src/login.tsx".to_string()];
+            state.duration_ms = Some(1200);
+        });
+        let rerun_id = format!("{}-rerun", run_id);
+        let rerun = store.fork(&run_id, &rerun_id).unwrap();
+        assert_eq!(rerun.rerun_of.as_deref(), Some(run_id.as_str()));
+        assert!(rerun.prompt.is_none());
+        assert!(rerun.duration_ms.is_none());
+        assert_eq!(rerun.selected_files, vec!["src/login.tsx".to_string()]);
+        assert_eq!(rerun.implementation_plan.as_deref(), Some("1. Add the route"));
+        let listed: Vec<String> = store.list().unwrap().into_iter().map(|s| s.run_id).collect();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.contains(&rerun_id));
+
+        let error = store.load("../escape").unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(AgentError::InvalidRunId(_))));
+        let error = store.latest_in_session("../escape").unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(AgentError::InvalidRunId(_))));
+        let error = store.load("missing").unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(AgentError::RunNotFound(_))));
+    }
+
+    #[test]
+    fn test_follow_ups_continue_the_session() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let store = RunStore::new(dir);
+        let first = RunRecorder::open(store.clone(), "first", "Add a login form", Path::new("/repo")).unwrap();
+        first.update(|state| {
+            state.agent = Some(AgentContext {
+                task: "Add a login form".to_string(),
+                gathered_info: Vec::new(),
+                history: vec!["search Form".to_string()],
+                budget_exhausted: None,
+                open_gaps: vec!["password rules".to_string()],
+                usage: crate::budget::BudgetUsage { iterations: 4, ..Default::default() },
+                earlier_tasks: Vec::new(),
+            });
+            state.implementation_plan = Some("1. Add LoginForm".to_string());
+        });
+
+        // Only a run that finished gathering can be followed up on
+        assert!(store.follow_up("first", "second", "Now add validation").is_err());
+        first.update(|state| state.agent_done = true);
+
+        let second = store.follow_up("first", "second", "Now add validation to that form").unwrap();
+        assert_eq!(second.session(), "first");
+        assert_eq!(second.follow_up_of.as_deref(), Some("first"));
+        assert_eq!(second.previous_plan.as_deref(), Some("1. Add LoginForm"));
+        assert!(!second.agent_done && second.implementation_plan.is_none());
+        let agent = second.agent.unwrap();
+        assert_eq!(agent.task, "Now add validation to that form");
+        assert_eq!(agent.earlier_tasks, vec!["Add a login form".to_string()]);
+        assert_eq!(agent.history[0], "search Form");
+        assert_eq!(agent.usage.iterations, 0);
+        assert!(agent.open_gaps.is_empty());
+
+        // The session is named after its first run, and continues from its last
+        assert_eq!(store.latest_in_session("first").unwrap().run_id, "second");
+        let mut second = RunRecorder::open(store.clone(), "second", "", Path::new("")).unwrap().state();
+        second.agent_done = true;
+        store.save(&second).unwrap();
+        let third = store.follow_up("second", "third", "And a reset link").unwrap();
+        assert_eq!(third.session(), "first");
+        assert_eq!(third.agent.unwrap().earlier_tasks.len(), 2);
+        assert_eq!(store.latest_in_session("first").unwrap().run_id, "third");
+        let error = store.latest_in_session("second").unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(AgentError::RunNotFound(_))));
+    }
+
+    #[test]
+    fn test_feedback_picks_prompt_items_by_file() {
+        let item = |path: &str, name: Option<&str>| PromptItem { file_path: path.to_string(), name: name.map(str::to_string) };
+        let record = PromptRecord {
+            items: vec![
+                item("src/LoginForm.tsx", Some("LoginForm")),
+                item("src/forms/Input.tsx", Some("Input")),
+                item("src/forms/Input.tsx", Some("InputProps")),
+                item("src/api.ts", None),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(record.items_in(&[]).len(), 4);
+        let picked = record.items_in(&["./src/forms/".to_string(), "/repo/src/LoginForm.tsx".to_string()]);
+        let names: Vec<_> = picked.iter().map(|i| i.name.as_deref().unwrap_or("")).collect();
+        assert_eq!(names, vec!["LoginForm", "Input", "InputProps"]);
+        assert!(record.items_in(&["src/Login".to_string()]).is_empty());
+
+        // Runs recorded before items fall back to the files their citations name
+        let old = PromptRecord {
+            context_ids: vec![
+                "src/a.ts:3-9".to_string(),
+                "src/a.ts".to_string(),
+                "type:User".to_string(),
+                "Tool: read_file Args: {}".to_string(),
+                "https://docs.rs/axum".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(old.items_in(&[]), vec![item("src/a.ts", None)]);
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::{Result, anyhow, bail};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::path::PathBuf;
+use tokio::process::Command;
+use tracing::info;
+
+use crate::command_policy::CommandPolicy;
+use crate::tools::{BUILTIN_TOOL_NAMES, Tool, run_with_limits};
+
+/// A project-specific tool declared in a `[[tools]]` table of `miow.toml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptToolSpec {
+    pub name: String,
+    pub description: String,
+    /// Run with `sh -c` in the codebase root. Arguments arrive as environment
+    /// variables of the same name, so quote them: `cargo test -- "$filter"`.
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<ScriptToolArg>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptToolArg {
+    /// Lowercase identifier, so it can't shadow `PATH`, `HOME` and the like
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Runs a `ScriptToolSpec` under the command policy: the command must pass its
+/// allow and deny lists, and runs with its timeout and output cap. Script tools
+/// always count as `Executes`, whatever the project claims they do.
+pub struct ScriptTool {
+    spec: ScriptToolSpec,
+    root: PathBuf,
+    policy: CommandPolicy,
+}
+
+impl ScriptTool {
+    pub fn new(spec: ScriptToolSpec, root: PathBuf, policy: CommandPolicy) -> Result<Self> {
+        let valid_name = |name: &str| {
+            name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+                && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        };
+        if !valid_name(&spec.name) {
+            bail!("Tool name '{}' must be a lowercase identifier", spec.name);
+        }
+        if BUILTIN_TOOL_NAMES.contains(&spec.name.as_str()) {
+            bail!("Tool name '{}' is taken by a built-in tool", spec.name);
+        }
+        if let Some(arg) = spec.args.iter().find(|arg| !valid_name(&arg.name)) {
+            bail!("Argument '{}' of tool '{}' must be a lowercase identifier", arg.name, spec.name);
+        }
+        Ok(Self { spec, root, policy })
+    }
+}
+
+#[async_trait]
+impl Tool for ScriptTool {
+    fn name(&self) -> &str { &self.spec.name }
+    fn description(&self) -> &str { &self.spec.description }
+    fn schema(&self) -> Value {
+        let properties: serde_json::Map<String, Value> = self
+            .spec
+            .args
+            .iter()
+            .map(|arg| (arg.name.clone(), json!({ "type": "string", "description": arg.description })))
+            .collect();
+        let required: Vec<&str> = self.spec.args.iter().filter(|a| a.required).map(|a| a.name.as_str()).collect();
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required
+        })
+    }
+    async fn execute(&self, args: Value) -> Result<String> {
+        self.policy.check(&self.spec.command)?;
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&self.spec.command).current_dir(&self.root);
+        for arg in &self.spec.args {
+            let value = match &args[arg.name.as_str()] {
+                Value::Null if arg.required => return Err(anyhow!("Missing '{}' argument", arg.name)),
+                Value::Null => String::new(),
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            command.env(&arg.name, value);
+        }
+
+        info!("Running project tool '{}'", self.spec.name);
+        run_with_limits(command, &self.policy).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolSafety;
+
+    fn spec(args: Vec<ScriptToolArg>) -> ScriptToolSpec {
+        ScriptToolSpec {
+            name: "echo_arg".into(),
+            description: "Echo an argument".into(),
+            command: "printf '%s' \"$word\"".into(),
+            args,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_arguments_are_passed_as_env_not_shell_text() {
+        let arg = ScriptToolArg { name: "word".into(), description: String::new(), required: true };
+        let tool = ScriptTool::new(spec(vec![arg]), std::env::temp_dir(), CommandPolicy::default()).unwrap();
+
+        let output = tool.execute(json!({ "word": "hi; echo injected" })).await.unwrap();
+        assert_eq!(output, "hi; echo injected");
+        assert!(tool.execute(json!({})).await.is_err());
+
+        let bad = ScriptToolArg { name: "PATH".into(), description: String::new(), required: false };
+        assert!(ScriptTool::new(spec(vec![bad]), std::env::temp_dir(), CommandPolicy::default()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_script_tools_execute_under_the_policy() {
+        let tool = ScriptTool::new(spec(vec![]), std::env::temp_dir(), CommandPolicy::default()).unwrap();
+        assert_eq!(tool.safety(), ToolSafety::Executes);
+
+        let allowlisted = CommandPolicy { allow: vec!["ls".into()], ..CommandPolicy::default() };
+        let tool = ScriptTool::new(spec(vec![]), std::env::temp_dir(), allowlisted).unwrap();
+        assert!(tool.execute(json!({})).await.is_err());
+
+        let builtin = ScriptToolSpec { name: "view_file".into(), ..spec(vec![]) };
+        assert!(ScriptTool::new(builtin, std::env::temp_dir(), CommandPolicy::default()).is_err());
+    }
+}
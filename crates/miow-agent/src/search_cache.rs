@@ -0,0 +1,145 @@
+use anyhow::Result;
+use miow_graph::{KnowledgeGraph, SearchScope, SymbolSearchResult};
+use miow_vector::VectorStore;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+type VectorResults = Vec<miow_vector::SymbolSearchResult>;
+type Slots<K, V> = Arc<Mutex<HashMap<K, Arc<OnceCell<V>>>>>;
+
+/// Graph and vector search results for one run, so workers, the context
+/// gatherer and the agent never execute the same query twice. Concurrent
+/// vector searches for the same query wait for a single embedding and
+/// search. Failed queries are not cached. Every search is limited to the
+/// cache's scope, if it has one.
+#[derive(Clone, Default)]
+pub struct SearchCache {
+    scope: Arc<SearchScope>,
+    graph: Slots<String, Vec<SymbolSearchResult>>,
+    vector: Slots<(String, usize), VectorResults>,
+    hits: Arc<AtomicUsize>,
+    misses: Arc<AtomicUsize>,
+}
+
+impl SearchCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit every graph and vector search to `scope`
+    pub fn with_scope(mut self, scope: SearchScope) -> Self {
+        self.scope = Arc::new(scope);
+        self.clear();
+        self
+    }
+
+    pub fn scope(&self) -> &SearchScope {
+        &self.scope
+    }
+
+    /// `KnowledgeGraph::search_symbols`, cached by the trimmed query; case is
+    /// ignored as the graph's `LIKE` match ignores it
+    pub fn search_symbols(&self, graph: &KnowledgeGraph, query: &str) -> Result<Vec<SymbolSearchResult>> {
+        let query = query.trim();
+        let cell = self.graph.lock().unwrap().entry(query.to_lowercase()).or_default().clone();
+        if let Some(results) = cell.get() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(results.clone());
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let results = graph.search_symbols_in(query, &self.scope)?;
+        // A racing caller may have filled the cell first; its results are equivalent
+        let _ = cell.set(results.clone());
+        Ok(results)
+    }
+
+    /// `VectorStore::search_similar`, cached by the trimmed query and limit
+    pub async fn search_similar(&self, store: &VectorStore, query: &str, limit: usize) -> Result<VectorResults> {
+        let query = query.trim();
+        let cell = self.vector.lock().unwrap().entry((query.to_string(), limit)).or_default().clone();
+        if let Some(results) = cell.get() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(results.clone());
+        }
+        let results = cell
+            .get_or_try_init(|| async {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                if self.scope.is_empty() {
+                    return store.search_similar(query, limit).await;
+                }
+                let mut results = store.search_similar_within(query, limit, &self.scope.paths, &self.scope.kinds).await?;
+                results.retain(|r| self.scope.matches(&r.symbol.file_path, &r.symbol.kind));
+                Ok(results)
+            })
+            .await?;
+        Ok(results.clone())
+    }
+
+    /// Forget everything, e.g. at the start of a new run
+    pub fn clear(&self) {
+        self.graph.lock().unwrap().clear();
+        self.vector.lock().unwrap().clear();
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    /// (served from cache, executed)
+    pub fn stats(&self) -> (usize, usize) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_graph_queries_hit_the_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let graph = KnowledgeGraph::new(dir.join("graph.db").to_str().unwrap()).unwrap();
+
+        let cache = SearchCache::new();
+        cache.search_symbols(&graph, "Button").unwrap();
+        cache.search_symbols(&graph, " button ").unwrap();
+        cache.search_symbols(&graph, "Input").unwrap();
+        assert_eq!(cache.stats(), (1, 2));
+
+        cache.clear();
+        assert_eq!(cache.stats(), (0, 0));
+    }
+
+    #[test]
+    fn test_scope_limits_graph_search() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let mut graph = KnowledgeGraph::new(dir.join("graph.db").to_str().unwrap()).unwrap();
+        let file = |symbols: serde_json::Value| {
+            serde_json::from_value(serde_json::json!({
+                "symbols": symbols, "imports": [], "design_tokens": [], "type_definitions": [], "constants": [], "schemas": [], "language": "typescript",
+            }))
+            .unwrap()
+        };
+        let symbol = |name: &str, kind: &str| serde_json::json!({
+            "name": name, "kind": kind, "start_line": 1, "end_line": 2, "start_byte": 0, "end_byte": 0,
+            "content": "", "metadata": "{}", "style_tags": null, "children": [], "references": [],
+        });
+        graph.insert_file("apps/web/login.ts", &file(serde_json::json!([symbol("loginUser", "Function"), symbol("LoginForm", "Component")]))).unwrap();
+        graph.insert_file("apps/web-admin/login.ts", &file(serde_json::json!([symbol("loginAdmin", "Function")]))).unwrap();
+
+        let names = |cache: &SearchCache| {
+            let mut names: Vec<String> = cache.search_symbols(&graph, "login").unwrap().into_iter().map(|s| s.name).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(&SearchCache::new()).len(), 3);
+        let scoped = SearchCache::new().with_scope(SearchScope::new(&["./apps/web/".to_string()], &[]));
+        assert_eq!(names(&scoped), vec!["LoginForm", "loginUser"]);
+        let scoped = SearchCache::new().with_scope(SearchScope::new(&["apps/web".to_string()], &["function".to_string()]));
+        assert_eq!(names(&scoped), vec!["loginUser"]);
+        assert!(scoped.scope().matches("apps/web/login.ts", "Function"));
+        assert!(!scoped.scope().contains_path("apps/web-admin/login.ts"));
+    }
+}
@@ -1,8 +1,9 @@
-use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::time::{Duration, Instant};
 
+use crate::autonomous::VerifiedInfo;
+
 /// Self-monitoring system for agent health
 pub struct SelfMonitor {
     execution_history: Vec<ExecutionRecord>,
@@ -53,6 +54,72 @@ pub enum HealthIssue {
     },
 }
 
+/// Something the gathered context lacks, or has too much of
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "gap", rename_all = "snake_case")]
+pub enum ContextGap {
+    NoContext,
+    /// The task is about data shapes but no type definitions were gathered
+    MissingTypes,
+    /// The task is about endpoints or pages but no routes were gathered
+    MissingRoutes,
+    /// Identifiers named in the task that appear in none of the gathered context
+    UncoveredTerms { terms: Vec<String> },
+    DuplicateSnippets { count: usize },
+}
+
+impl std::fmt::Display for ContextGap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContextGap::NoContext => write!(f, "no context gathered"),
+            ContextGap::MissingTypes => write!(f, "no type definitions"),
+            ContextGap::MissingRoutes => write!(f, "no routes or handlers"),
+            ContextGap::UncoveredTerms { terms } => write!(f, "nothing found for {}", terms.join(", ")),
+            ContextGap::DuplicateSnippets { count } => write!(f, "{} duplicate snippets", count),
+        }
+    }
+}
+
+/// Result of grading gathered context against the task
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContextReview {
+    pub gaps: Vec<ContextGap>,
+    /// Indices of gathered items that repeat an earlier item
+    pub duplicates: Vec<usize>,
+}
+
+impl ContextReview {
+    /// Gaps a follow-up search could fill (duplicates are fixed by dropping them)
+    pub fn open_gaps(&self) -> impl Iterator<Item = &ContextGap> {
+        self.gaps.iter().filter(|gap| !matches!(gap, ContextGap::DuplicateSnippets { .. }))
+    }
+
+    /// Searches likely to fill the open gaps, most specific first
+    pub fn follow_up_queries(&self, task: &str) -> Vec<String> {
+        let mut queries = Vec::new();
+        for gap in self.open_gaps() {
+            match gap {
+                ContextGap::NoContext => queries.push(task.to_string()),
+                ContextGap::UncoveredTerms { terms } => queries.extend(terms.iter().cloned()),
+                ContextGap::MissingTypes => {
+                    queries.extend(task_terms(task).into_iter().map(|term| format!("{} type", term)));
+                    queries.push("types".to_string());
+                }
+                ContextGap::MissingRoutes => queries.extend(["routes", "router", "handler"].map(String::from)),
+                ContextGap::DuplicateSnippets { .. } => {}
+            }
+        }
+        let mut seen = HashSet::new();
+        queries.retain(|query| seen.insert(query.to_lowercase()));
+        queries
+    }
+}
+
+const TYPE_TASK_WORDS: &[&str] = &["type", "interface", "struct", "model", "schema", "field", "prop", "dto"];
+const TYPE_MARKERS: &[&str] = &["struct ", "interface ", "type ", "class ", "enum ", "(struct)", "(interface)", "(class)"];
+const ROUTE_TASK_WORDS: &[&str] = &["endpoint", "route", "api", "page", "handler", "url"];
+const ROUTE_MARKERS: &[&str] = &["route", "router", "app.get", "app.post", "#[get", "#[post", "@app.", "@get", "handler"];
+
 struct LoopDetector {
     recent_steps: Vec<String>,
     max_history: usize,
@@ -152,15 +219,15 @@ impl SelfMonitor {
     }
     
     fn check_stuck_state(&self) -> Option<HealthIssue> {
-        if let Some(last) = self.execution_history.last() {
-            if last.completed_at.is_none() {
-                let duration = last.started_at.elapsed();
-                if duration > Duration::from_secs(120) {
-                    return Some(HealthIssue::StuckState {
-                        step_id: last.step_id.clone(),
-                        duration: duration.as_secs(),
-                    });
-                }
+        if let Some(last) = self.execution_history.last()
+            && last.completed_at.is_none()
+        {
+            let duration = last.started_at.elapsed();
+            if duration > Duration::from_secs(120) {
+                return Some(HealthIssue::StuckState {
+                    step_id: last.step_id.clone(),
+                    duration: duration.as_secs(),
+                });
             }
         }
         None
@@ -273,6 +340,59 @@ impl SelfMonitor {
         suggestions
     }
     
+    /// Grade gathered context against the task: duplicates, missing types or
+    /// routes the task calls for, and identifiers from the task found nowhere
+    pub fn review_context(&self, task: &str, gathered: &[VerifiedInfo]) -> ContextReview {
+        let mut review = ContextReview::default();
+        if gathered.is_empty() {
+            review.gaps.push(ContextGap::NoContext);
+            return review;
+        }
+
+        let mut seen = HashSet::new();
+        for (i, info) in gathered.iter().enumerate() {
+            let normalized: String = info.content.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !seen.insert(normalized) {
+                review.duplicates.push(i);
+            }
+        }
+        if !review.duplicates.is_empty() {
+            review.gaps.push(ContextGap::DuplicateSnippets { count: review.duplicates.len() });
+        }
+
+        let corpus = gathered
+            .iter()
+            .map(|info| format!("{}\n{}", info.source, info.content))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .to_lowercase();
+        let task_lower = task.to_lowercase();
+        // Whole words or their plurals, so "typescript" isn't a type and "pagination" isn't a page
+        let mentions = |words: &[&str]| {
+            task_lower.split(|c: char| !c.is_alphanumeric()).any(|token| {
+                words.iter().any(|word| {
+                    token.strip_prefix(word).is_some_and(|rest| matches!(rest, "" | "s" | "es"))
+                })
+            })
+        };
+
+        if mentions(TYPE_TASK_WORDS) && !TYPE_MARKERS.iter().any(|m| corpus.contains(m)) {
+            review.gaps.push(ContextGap::MissingTypes);
+        }
+        if mentions(ROUTE_TASK_WORDS) && !ROUTE_MARKERS.iter().any(|m| corpus.contains(m)) {
+            review.gaps.push(ContextGap::MissingRoutes);
+        }
+
+        let uncovered: Vec<String> = task_terms(task)
+            .into_iter()
+            .filter(|term| !corpus.contains(&term.to_lowercase()))
+            .collect();
+        if !uncovered.is_empty() {
+            review.gaps.push(ContextGap::UncoveredTerms { terms: uncovered });
+        }
+        review
+    }
+
     /// Get health metrics
     pub fn get_metrics(&self) -> &HealthMetrics {
         &self.health_metrics
@@ -286,6 +406,30 @@ impl SelfMonitor {
     }
 }
 
+/// Words in the task that look like code identifiers: `quoted`, CamelCase,
+/// snake_case or paths like `auth::login`
+fn task_terms(task: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for (i, part) in task.split('`').enumerate() {
+        if i % 2 == 1 {
+            terms.push(part.trim().to_string());
+            continue;
+        }
+        for word in part.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':' || c == '.')) {
+            let word = word.trim_matches(|c: char| c == '.' || c == ':');
+            let identifier_like = word.chars().skip(1).any(|c| c.is_uppercase())
+                || word.contains('_')
+                || word.contains("::");
+            if identifier_like && word.len() >= 3 {
+                terms.push(word.to_string());
+            }
+        }
+    }
+    let mut seen = HashSet::new();
+    terms.retain(|term| !term.is_empty() && seen.insert(term.clone()));
+    terms
+}
+
 impl LoopDetector {
     fn add_step(&mut self, step_id: String) {
         self.recent_steps.push(step_id);
@@ -365,6 +509,46 @@ mod tests {
         assert!(detector.detect_loop().is_some());
     }
     
+    #[test]
+    fn test_review_context() {
+        let info = |content: &str| VerifiedInfo {
+            content: content.to_string(),
+            source: "Tool: search".to_string(),
+            relevance: String::new(),
+            external: false,
+        };
+        let monitor = SelfMonitor::new();
+
+        let review = monitor.review_context("Add an endpoint for `UserProfile`", &[]);
+        assert_eq!(review.gaps, vec![ContextGap::NoContext]);
+
+        let gathered = vec![info("fn load_user() {}"), info("fn  load_user() {}\n"), info("struct Session {}")];
+        let review = monitor.review_context("Add an endpoint returning UserProfile from load_user", &gathered);
+        assert_eq!(review.duplicates, vec![1]);
+        assert!(review.gaps.contains(&ContextGap::MissingRoutes));
+        assert!(review.gaps.contains(&ContextGap::UncoveredTerms { terms: vec!["UserProfile".to_string()] }));
+        assert_eq!(review.follow_up_queries("x")[..3], ["routes", "router", "handler"]);
+    }
+
+    #[test]
+    fn test_review_matches_whole_words() {
+        let monitor = SelfMonitor::new();
+        let gathered = vec![VerifiedInfo {
+            content: "fn load_user() {}".to_string(),
+            source: "Tool: search".to_string(),
+            relevance: String::new(),
+            external: false,
+        }];
+
+        let review = monitor.review_context("Migrate load_user to typescript with pagination", &gathered);
+        assert!(!review.gaps.contains(&ContextGap::MissingTypes));
+        assert!(!review.gaps.contains(&ContextGap::MissingRoutes));
+
+        let review = monitor.review_context("Add types and pages for load_user", &gathered);
+        assert!(review.gaps.contains(&ContextGap::MissingTypes));
+        assert!(review.gaps.contains(&ContextGap::MissingRoutes));
+    }
+
     #[test]
     fn test_health_metrics() {
         let mut monitor = SelfMonitor::new();
@@ -2,46 +2,101 @@ use async_trait::async_trait;
 use anyhow::{Result, anyhow, Context};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::process::Command;
-use tracing::{info, warn};
+use tracing::info;
 
-/// Trait defining a tool that the agent can use
+use crate::approval::{request_approval, ApprovalRequest, Approver};
+use crate::command_policy::CommandPolicy;
+
+/// What a tool can do to the machine it runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolSafety {
+    /// Only reads the codebase or the index; runs without approval
+    ReadOnly,
+    /// Creates or modifies files
+    Writes,
+    /// Runs programs
+    Executes,
+}
+
+/// A tool the agent can call. Implement it to give the agent project-specific
+/// abilities and add it with `ToolRegistry::register` or `AutonomousAgent::with_tool`.
+///
+/// - `name` is what the model writes in `"tool"`; it must be unique, and a tool
+///   registered under an existing name replaces the old one.
+/// - `schema` is a JSON Schema object describing `args`; the model sees it
+///   verbatim, so give every property a `description`.
+/// - `execute` receives the model's `args` unchecked, so validate them and return
+///   an error for anything missing or malformed. Errors are shown to the model,
+///   which can retry. Keep output short; it goes back into the next prompt.
+/// - `safety` decides whether calls need a person's approval: anything other than
+///   `ReadOnly` is asked about unless `approval_reason` is overridden. It defaults
+///   to `Executes`, so a tool that doesn't say is treated as running programs.
 #[async_trait]
 pub trait Tool: Send + Sync {
     fn name(&self) -> &str;
     fn description(&self) -> &str;
     fn schema(&self) -> serde_json::Value;
     async fn execute(&self, args: serde_json::Value) -> Result<String>;
+
+    fn safety(&self) -> ToolSafety {
+        ToolSafety::Executes
+    }
+
+    /// Describe the action when running it with `args` needs a person's approval first
+    fn approval_reason(&self, args: &serde_json::Value) -> Option<String> {
+        match self.safety() {
+            ToolSafety::ReadOnly => None,
+            _ => Some(format!("use `{}` with {}", self.name(), args)),
+        }
+    }
 }
 
 /// Registry to hold available tools
+#[derive(Default)]
 pub struct ToolRegistry {
     tools: std::collections::HashMap<String, Arc<dyn Tool>>,
 }
 
 impl ToolRegistry {
     pub fn new() -> Self {
-        Self {
-            tools: std::collections::HashMap::new(),
-        }
+        Self::default()
     }
 
+    /// Add a tool, replacing any tool with the same name
     pub fn register(&mut self, tool: Arc<dyn Tool>) {
         self.tools.insert(tool.name().to_string(), tool);
     }
 
+    /// Builder form of `register`
+    pub fn with_tool(mut self, tool: Arc<dyn Tool>) -> Self {
+        self.register(tool);
+        self
+    }
+
     pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
         self.tools.get(name).cloned()
     }
 
+    /// Names of the registered tools, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.tools.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
     pub fn list_tools(&self) -> Vec<serde_json::Value> {
-        self.tools.values().map(|t| {
+        let mut tools: Vec<&Arc<dyn Tool>> = self.tools.values().collect();
+        tools.sort_by(|a, b| a.name().cmp(b.name()));
+        tools.into_iter().map(|t| {
             json!({
                 "name": t.name(),
                 "description": t.description(),
-                "parameters": t.schema()
+                "parameters": t.schema(),
+                "safety": t.safety()
             })
         }).collect()
     }
@@ -49,13 +104,31 @@ impl ToolRegistry {
 
 // --- Tool Implementations ---
 
+/// Names of the tools `AutonomousAgent` provides itself, which project tools can't take
+pub const BUILTIN_TOOL_NAMES: &[&str] = &[
+    "view_file",
+    "list_dir",
+    "run_command",
+    "write_file",
+    "search",
+    "grep",
+    "git_log",
+    "git_blame",
+    "lsp",
+    "web_search",
+];
+
 /// Tool to view file contents
 pub struct ViewFileTool;
 
+/// Files bigger than this are only read once approved
+const LARGE_FILE_BYTES: u64 = 1024 * 1024;
+
 #[async_trait]
 impl Tool for ViewFileTool {
     fn name(&self) -> &str { "view_file" }
     fn description(&self) -> &str { "Read the contents of a file" }
+    fn safety(&self) -> ToolSafety { ToolSafety::ReadOnly }
     fn schema(&self) -> serde_json::Value {
         json!({
             "type": "object",
@@ -78,6 +151,12 @@ impl Tool for ViewFileTool {
             
         Ok(content)
     }
+
+    fn approval_reason(&self, args: &serde_json::Value) -> Option<String> {
+        let path = args["path"].as_str()?;
+        let size = std::fs::metadata(path).ok()?.len();
+        (size > LARGE_FILE_BYTES).then(|| format!("read {} ({} KB)", path, size / 1024))
+    }
 }
 
 /// Tool to list directory contents
@@ -87,6 +166,7 @@ pub struct ListDirTool;
 impl Tool for ListDirTool {
     fn name(&self) -> &str { "list_dir" }
     fn description(&self) -> &str { "List contents of a directory" }
+    fn safety(&self) -> ToolSafety { ToolSafety::ReadOnly }
     fn schema(&self) -> serde_json::Value {
         json!({
             "type": "object",
@@ -117,50 +197,98 @@ impl Tool for ListDirTool {
     }
 }
 
-/// Tool to run shell commands
-pub struct RunCommandTool;
+/// Tool to run shell commands, within the limits of a `CommandPolicy`. When the
+/// policy asks for approval, every command waits for the approver in `execute`,
+/// and is refused without one.
+pub struct RunCommandTool {
+    root: PathBuf,
+    policy: CommandPolicy,
+    approver: Option<Arc<dyn Approver>>,
+}
+
+impl RunCommandTool {
+    /// Run commands from `root` (the codebase) under `policy`
+    pub fn new(root: PathBuf, policy: CommandPolicy) -> Self {
+        Self { root, policy, approver: None }
+    }
+
+    /// Who approves commands when the policy asks for approval
+    pub fn with_approver(mut self, approver: Arc<dyn Approver>) -> Self {
+        self.approver = Some(approver);
+        self
+    }
+}
+
+impl Default for RunCommandTool {
+    fn default() -> Self {
+        Self::new(PathBuf::from("."), CommandPolicy::default())
+    }
+}
 
 #[async_trait]
 impl Tool for RunCommandTool {
     fn name(&self) -> &str { "run_command" }
-    fn description(&self) -> &str { "Execute a shell command" }
+    fn description(&self) -> &str { "Execute a shell command in the codebase (subject to the command policy)" }
+    fn safety(&self) -> ToolSafety { ToolSafety::Executes }
     fn schema(&self) -> serde_json::Value {
         json!({
             "type": "object",
             "properties": {
                 "command": { "type": "string", "description": "Command to execute" },
-                "cwd": { "type": "string", "description": "Working directory" }
+                "cwd": { "type": "string", "description": "Working directory, relative to the codebase root" }
             },
             "required": ["command"]
         })
     }
     async fn execute(&self, args: serde_json::Value) -> Result<String> {
         let command_str = args["command"].as_str().ok_or_else(|| anyhow!("Missing 'command' argument"))?;
-        let cwd = args["cwd"].as_str().unwrap_or(".");
-        
-        info!("Executing command: '{}' in '{}'", command_str, cwd);
+        self.policy.check(command_str)?;
+        let cwd = self.policy.working_dir(&self.root, args["cwd"].as_str())?;
 
-        // Security check (basic)
-        if command_str.contains("rm -rf /") || command_str.contains("mkfs") {
-            return Err(anyhow!("Command blocked for security reasons"));
+        // Commands the policy refuses have failed above without bothering anyone
+        if self.policy.needs_approval() {
+            let request = ApprovalRequest::new(self.name(), format!("run `{}` in {}", command_str, cwd.display()));
+            if !request_approval(self.approver.as_deref(), &request).await {
+                return Err(anyhow!("Command was not approved: {}", command_str));
+            }
         }
+        info!("Executing command: '{}' in '{}'", command_str, cwd.display());
 
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(command_str)
-            .current_dir(cwd)
-            .output()
-            .await
-            .context("Failed to execute command")?;
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(command_str).current_dir(&cwd);
+        run_with_limits(command, &self.policy).await
+    }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        
-        if output.status.success() {
-            Ok(stdout.to_string())
-        } else {
-            Ok(format!("Command failed with code {:?}\nSTDOUT:\n{}\nSTDERR:\n{}", output.status.code(), stdout, stderr))
-        }
+    /// Approval happens in `execute`, so callers other than the agent loop can't skip it
+    fn approval_reason(&self, _args: &serde_json::Value) -> Option<String> {
+        None
+    }
+}
+
+/// Run `command`, killing it after the policy's timeout and capping what it printed
+pub(crate) async fn run_with_limits(mut command: Command, policy: &CommandPolicy) -> Result<String> {
+    let child = command.kill_on_drop(true).output();
+    let output = tokio::time::timeout(std::time::Duration::from_secs(policy.timeout_secs), child)
+        .await
+        .map_err(|_| anyhow!("Command timed out after {}s", policy.timeout_secs))?
+        .context("Failed to execute command")?;
+
+    let stdout = capped_output(&output.stdout, policy.max_output_bytes);
+    let stderr = capped_output(&output.stderr, policy.max_output_bytes);
+
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        Ok(format!("Command failed with code {:?}\nSTDOUT:\n{}\nSTDERR:\n{}", output.status.code(), stdout, stderr))
+    }
+}
+
+fn capped_output(bytes: &[u8], max_bytes: usize) -> String {
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(max_bytes)]).into_owned();
+    if bytes.len() > max_bytes {
+        format!("{}\n... (output truncated, {} bytes total)", text, bytes.len())
+    } else {
+        text
     }
 }
 
@@ -171,6 +299,7 @@ pub struct WriteFileTool;
 impl Tool for WriteFileTool {
     fn name(&self) -> &str { "write_file" }
     fn description(&self) -> &str { "Write content to a file (overwrites)" }
+    fn safety(&self) -> ToolSafety { ToolSafety::Writes }
     fn schema(&self) -> serde_json::Value {
         json!({
             "type": "object",
@@ -196,3 +325,407 @@ impl Tool for WriteFileTool {
         Ok(format!("Successfully wrote to {}", path_str))
     }
 }
+
+/// Matches reported before the search stops, unless the caller asks for fewer
+const GREP_DEFAULT_MAX_MATCHES: usize = 50;
+const GREP_MAX_MATCHES_LIMIT: usize = 200;
+const GREP_MAX_CONTEXT_LINES: usize = 5;
+/// Files larger than this are not searched
+const GREP_MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// Long lines (minified code) are cut so one match can't flood the context
+const GREP_MAX_LINE_CHARS: usize = 240;
+
+/// Tool to search file contents with a regex, like ripgrep. Lets the agent
+/// confirm exact strings that graph and vector search only approximate.
+pub struct GrepTool {
+    root: PathBuf,
+}
+
+impl GrepTool {
+    /// Search within `root`, the indexed codebase
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+}
+
+#[async_trait]
+impl Tool for GrepTool {
+    fn name(&self) -> &str { "grep" }
+    fn description(&self) -> &str {
+        "Search file contents in the codebase with a regular expression (respects .gitignore). \
+         Returns path:line:text for each match"
+    }
+    fn safety(&self) -> ToolSafety { ToolSafety::ReadOnly }
+    fn schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "pattern": { "type": "string", "description": "Regular expression to search for" },
+                "path": { "type": "string", "description": "Subdirectory or file to search, relative to the codebase root" },
+                "glob": { "type": "string", "description": "Only search files matching this glob, e.g. *.tsx" },
+                "case_insensitive": { "type": "boolean", "description": "Ignore case (default false)" },
+                "context": { "type": "integer", "description": "Lines of context around each match (0-5, default 0)" },
+                "max_matches": { "type": "integer", "description": "Stop after this many matches (default 50, max 200)" }
+            },
+            "required": ["pattern"]
+        })
+    }
+    async fn execute(&self, args: serde_json::Value) -> Result<String> {
+        let pattern = args["pattern"].as_str().ok_or_else(|| anyhow!("Missing 'pattern' argument"))?;
+        let regex = regex::RegexBuilder::new(pattern)
+            .case_insensitive(args["case_insensitive"].as_bool().unwrap_or(false))
+            .build()
+            .context("Invalid regex pattern")?;
+
+        let search_root = match args["path"].as_str() {
+            Some(sub) => {
+                let candidate = self.root.join(sub).canonicalize()
+                    .with_context(|| format!("Path not found: {}", sub))?;
+                if !candidate.starts_with(self.root.canonicalize()?) {
+                    return Err(anyhow!("Path is outside the codebase: {}", sub));
+                }
+                candidate
+            }
+            None => self.root.clone(),
+        };
+        let options = GrepOptions {
+            glob: args["glob"].as_str().map(String::from),
+            context: (args["context"].as_u64().unwrap_or(0) as usize).min(GREP_MAX_CONTEXT_LINES),
+            max_matches: (args["max_matches"].as_u64().unwrap_or(GREP_DEFAULT_MAX_MATCHES as u64) as usize)
+                .clamp(1, GREP_MAX_MATCHES_LIMIT),
+        };
+
+        let root = self.root.clone();
+        tokio::task::spawn_blocking(move || grep(&root, &search_root, &regex, &options))
+            .await
+            .context("Search task failed")?
+    }
+}
+
+struct GrepOptions {
+    glob: Option<String>,
+    context: usize,
+    max_matches: usize,
+}
+
+/// Walk `search_root` and print matches with paths relative to `root`
+fn grep(root: &Path, search_root: &Path, regex: &regex::Regex, options: &GrepOptions) -> Result<String> {
+    let mut walker = ignore::WalkBuilder::new(search_root);
+    walker.sort_by_file_name(|a, b| a.cmp(b));
+    if let Some(glob) = &options.glob {
+        let overrides = ignore::overrides::OverrideBuilder::new(search_root)
+            .add(glob)
+            .context("Invalid glob")?
+            .build()?;
+        walker.overrides(overrides);
+    }
+
+    let mut output = Vec::new();
+    let mut matches = 0;
+    for entry in walker.build().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file())
+            || entry.metadata().map_or(true, |m| m.len() > GREP_MAX_FILE_SIZE)
+        {
+            continue;
+        }
+        // Binary or non-UTF-8 files have nothing useful to show
+        let Ok(content) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        if content.contains('\0') {
+            continue;
+        }
+
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_string_lossy().to_string();
+        let lines: Vec<&str> = content.lines().collect();
+        let mut last_printed: Option<usize> = None;
+        for (index, line) in lines.iter().enumerate() {
+            if !regex.is_match(line) {
+                continue;
+            }
+            let start = index.saturating_sub(options.context);
+            let end = (index + options.context).min(lines.len() - 1);
+            if last_printed.is_some_and(|last| start > last + 1) {
+                output.push("--".to_string());
+            }
+            for (n, text) in lines.iter().enumerate().take(end + 1).skip(start) {
+                if last_printed.is_some_and(|last| n <= last) {
+                    continue;
+                }
+                let separator = if n == index || regex.is_match(text) { ':' } else { '-' };
+                let text: String = text.chars().take(GREP_MAX_LINE_CHARS).collect();
+                output.push(format!("{}{}{}{}{}", relative, separator, n + 1, separator, text));
+                last_printed = Some(n);
+            }
+
+            matches += 1;
+            if matches >= options.max_matches {
+                output.push(format!("(stopped after {} matches; narrow the pattern, path or glob)", matches));
+                return Ok(output.join("\n"));
+            }
+        }
+        if last_printed.is_some() && options.context > 0 {
+            output.push("--".to_string());
+        }
+    }
+
+    if output.last().is_some_and(|line| line == "--") {
+        output.pop();
+    }
+    if output.is_empty() {
+        return Ok("No matches found.".to_string());
+    }
+    Ok(output.join("\n"))
+}
+
+/// Commits listed by `git_log` unless the caller asks for fewer
+const GIT_LOG_DEFAULT_COUNT: u64 = 10;
+const GIT_LOG_MAX_COUNT: u64 = 50;
+/// Patches are cut here so one large commit can't flood the context
+const GIT_PATCH_MAX_CHARS: usize = 6000;
+const GIT_BLAME_MAX_LINES: u64 = 200;
+
+/// Tool to list recent commits touching a path, so the agent can see what
+/// changed recently and avoid reintroducing patterns that were just removed
+pub struct GitLogTool {
+    root: PathBuf,
+}
+
+impl GitLogTool {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+}
+
+#[async_trait]
+impl Tool for GitLogTool {
+    fn name(&self) -> &str { "git_log" }
+    fn description(&self) -> &str { "List recent git commits, optionally only those touching a path, with changed files or patches" }
+    fn safety(&self) -> ToolSafety { ToolSafety::ReadOnly }
+    fn schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "File or directory relative to the codebase root" },
+                "max_count": { "type": "integer", "description": "Number of commits (default 10, max 50)" },
+                "patch": { "type": "boolean", "description": "Include the diff of each commit (default false: changed files only)" }
+            }
+        })
+    }
+    async fn execute(&self, args: serde_json::Value) -> Result<String> {
+        let count = args["max_count"].as_u64().unwrap_or(GIT_LOG_DEFAULT_COUNT).clamp(1, GIT_LOG_MAX_COUNT);
+        let count_arg = format!("--max-count={}", count);
+        let detail = if args["patch"].as_bool().unwrap_or(false) { "--patch" } else { "--stat" };
+
+        let mut git_args = vec!["log", count_arg.as_str(), "--date=short", "--format=commit %h %ad %an%n    %s", detail];
+        if let Some(path) = args["path"].as_str() {
+            git_args.extend(["--", path]);
+        }
+
+        let mut output = run_git(&self.root, &git_args).await?;
+        if output.trim().is_empty() {
+            return Ok("No commits found.".to_string());
+        }
+        if output.len() > GIT_PATCH_MAX_CHARS {
+            let cut = (0..=GIT_PATCH_MAX_CHARS).rev().find(|i| output.is_char_boundary(*i)).unwrap_or(0);
+            output.truncate(cut);
+            output.push_str("\n... (truncated; ask for fewer commits or a narrower path)");
+        }
+        Ok(output)
+    }
+}
+
+/// Tool to show who last changed each line in a range and in which commit
+pub struct GitBlameTool {
+    root: PathBuf,
+}
+
+impl GitBlameTool {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+}
+
+#[async_trait]
+impl Tool for GitBlameTool {
+    fn name(&self) -> &str { "git_blame" }
+    fn description(&self) -> &str { "Show the commit, author and date that last changed each line in a range of a file" }
+    fn safety(&self) -> ToolSafety { ToolSafety::ReadOnly }
+    fn schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "File relative to the codebase root" },
+                "start_line": { "type": "integer", "description": "First line (1-based)" },
+                "end_line": { "type": "integer", "description": "Last line (at most 200 lines after start_line)" }
+            },
+            "required": ["path", "start_line", "end_line"]
+        })
+    }
+    async fn execute(&self, args: serde_json::Value) -> Result<String> {
+        let path = args["path"].as_str().ok_or_else(|| anyhow!("Missing 'path' argument"))?;
+        let start = args["start_line"].as_u64().ok_or_else(|| anyhow!("Missing 'start_line' argument"))?.max(1);
+        let end = args["end_line"].as_u64().ok_or_else(|| anyhow!("Missing 'end_line' argument"))?;
+        if end < start {
+            return Err(anyhow!("end_line must not be before start_line"));
+        }
+        let end = end.min(start + GIT_BLAME_MAX_LINES - 1);
+
+        let range = format!("-L{},{}", start, end);
+        run_git(&self.root, &["blame", range.as_str(), "--date=short", "--abbrev=8", "--", path]).await
+    }
+}
+
+/// Run git in `root`; a non-zero exit becomes an error carrying git's message
+async fn run_git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .await
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codebase() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/login.ts"), "import { api } from './api';\n\nexport function login() {\n  return api.post('/login');\n}\n").unwrap();
+        std::fs::write(dir.join("src/api.ts"), "export const api = { post: (url: string) => fetch(url) };\n").unwrap();
+        std::fs::write(dir.join("README.md"), "Call login() to sign in\n").unwrap();
+        temp_dir
+    }
+
+    #[tokio::test]
+    async fn test_grep_reports_path_line_and_text() {
+        let temp_dir = codebase();
+        let tool = GrepTool::new(temp_dir.path().to_path_buf());
+
+        let output = tool.execute(json!({ "pattern": r"login\(" })).await.unwrap();
+        assert_eq!(output, "README.md:1:Call login() to sign in\nsrc/login.ts:3:export function login() {");
+
+        let output = tool.execute(json!({ "pattern": "LOGIN", "glob": "*.ts", "case_insensitive": true })).await.unwrap();
+        assert_eq!(output, "src/login.ts:3:export function login() {\nsrc/login.ts:4:  return api.post('/login');");
+
+        let output = tool.execute(json!({ "pattern": "nothing matches this" })).await.unwrap();
+        assert_eq!(output, "No matches found.");
+
+        // Ignore files are respected
+        std::fs::write(temp_dir.path().join(".ignore"), "README.md\n").unwrap();
+        let output = tool.execute(json!({ "pattern": r"login\(" })).await.unwrap();
+        assert_eq!(output, "src/login.ts:3:export function login() {");
+    }
+
+    #[tokio::test]
+    async fn test_grep_context_and_match_limit() {
+        let temp_dir = codebase();
+        let tool = GrepTool::new(temp_dir.path().to_path_buf());
+
+        let output = tool.execute(json!({ "pattern": "export function", "path": "src", "context": 1 })).await.unwrap();
+        assert_eq!(output, "src/login.ts-2-\nsrc/login.ts:3:export function login() {\nsrc/login.ts-4-  return api.post('/login');");
+
+        let output = tool.execute(json!({ "pattern": "api", "max_matches": 2 })).await.unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[2].starts_with("(stopped after 2 matches"));
+    }
+
+    #[tokio::test]
+    async fn test_grep_rejects_bad_arguments() {
+        let temp_dir = codebase();
+        let tool = GrepTool::new(temp_dir.path().join("src"));
+
+        assert!(tool.execute(json!({})).await.is_err());
+        assert!(tool.execute(json!({ "pattern": "(unclosed" })).await.is_err());
+        assert!(tool.execute(json!({ "pattern": "login", "path": "../" })).await.is_err());
+        assert!(tool.execute(json!({ "pattern": "login", "path": "missing" })).await.is_err());
+    }
+
+    struct Refuse;
+
+    #[async_trait]
+    impl Approver for Refuse {
+        async fn approve(
+            &self,
+            _request: &ApprovalRequest,
+            _events: Option<&tokio::sync::mpsc::Sender<crate::autonomous::AgentEvent>>,
+        ) -> Result<bool> {
+            Ok(false)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_command_waits_for_approval() {
+        let temp_dir = codebase();
+        let root = temp_dir.path().to_path_buf();
+        let args = json!({ "command": "echo approved" });
+
+        let prompt = CommandPolicy::default();
+        assert!(RunCommandTool::new(root.clone(), prompt.clone()).execute(args.clone()).await.is_err());
+        let refused = RunCommandTool::new(root.clone(), prompt.clone()).with_approver(Arc::new(Refuse));
+        assert!(refused.execute(args.clone()).await.is_err());
+        let approved = RunCommandTool::new(root.clone(), prompt).with_approver(Arc::new(crate::AutoApprover));
+        assert_eq!(approved.execute(args.clone()).await.unwrap(), "approved\n");
+
+        let auto = CommandPolicy { approval: crate::ApprovalMode::Auto, ..CommandPolicy::default() };
+        assert_eq!(RunCommandTool::new(root, auto).execute(args).await.unwrap(), "approved\n");
+    }
+
+    /// `codebase()` as a git repository with two commits, the second editing login.ts
+    async fn repository() -> tempfile::TempDir {
+        let temp_dir = codebase();
+        let dir = temp_dir.path();
+        let identity = ["-c", "user.name=Ada", "-c", "user.email=ada@example.com"];
+        run_git(dir, &["init", "--quiet"]).await.unwrap();
+        run_git(dir, &["add", "."]).await.unwrap();
+        run_git(dir, &[&identity[..], &["commit", "--quiet", "-m", "Add login"]].concat()).await.unwrap();
+        std::fs::write(dir.join("src/login.ts"), "export function login() {\n  return api.post('/session');\n}\n").unwrap();
+        run_git(dir, &[&identity[..], &["commit", "--quiet", "-am", "Post to /session"]].concat()).await.unwrap();
+        temp_dir
+    }
+
+    #[tokio::test]
+    async fn test_git_log_lists_commits_touching_a_path() {
+        let temp_dir = repository().await;
+        let tool = GitLogTool::new(temp_dir.path().to_path_buf());
+
+        let output = tool.execute(json!({ "path": "src/login.ts" })).await.unwrap();
+        assert!(output.contains("Post to /session") && output.contains("Add login"));
+        assert!(output.contains("Ada"));
+        assert!(output.contains("src/login.ts |"));
+
+        let output = tool.execute(json!({ "path": "README.md", "max_count": 1, "patch": true })).await.unwrap();
+        assert!(output.contains("Add login") && !output.contains("Post to /session"));
+        assert!(output.contains("+Call login() to sign in"));
+
+        let output = tool.execute(json!({ "path": "missing.ts" })).await.unwrap();
+        assert_eq!(output, "No commits found.");
+    }
+
+    #[tokio::test]
+    async fn test_git_blame_shows_the_commit_for_each_line() {
+        let temp_dir = repository().await;
+        let tool = GitBlameTool::new(temp_dir.path().to_path_buf());
+
+        let output = tool.execute(json!({ "path": "src/login.ts", "start_line": 1, "end_line": 2 })).await.unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.contains("Ada")));
+        assert!(lines[1].contains("/session"));
+        // The second line changed in a later commit than the first
+        assert_ne!(lines[0].split(' ').next(), lines[1].split(' ').next());
+
+        assert!(tool.execute(json!({ "path": "src/login.ts", "start_line": 3, "end_line": 1 })).await.is_err());
+        assert!(tool.execute(json!({ "path": "missing.ts", "start_line": 1, "end_line": 1 })).await.is_err());
+        assert!(tool.execute(json!({ "path": "src/login.ts" })).await.is_err());
+    }
+}
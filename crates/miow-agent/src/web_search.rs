@@ -0,0 +1,202 @@
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use serde_json::json;
+use std::time::Duration;
+
+use crate::tools::{Tool, ToolSafety};
+
+/// Marks web results so they are never mistaken for code from the project
+pub const EXTERNAL_LABEL: &str = "[EXTERNAL: web search, not from this codebase]";
+
+const DEFAULT_MAX_RESULTS: u64 = 5;
+const MAX_RESULTS: u64 = 10;
+const SNIPPET_MAX_CHARS: usize = 400;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Search API the tool queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebSearchBackend {
+    SerpApi,
+    Brave,
+    Tavily,
+}
+
+impl WebSearchBackend {
+    /// Parse `serpapi`, `brave` or `tavily`, ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "serpapi" => Some(Self::SerpApi),
+            "brave" => Some(Self::Brave),
+            "tavily" => Some(Self::Tavily),
+            _ => None,
+        }
+    }
+
+    /// Environment variable holding the backend's API key
+    pub fn key_var(self) -> &'static str {
+        match self {
+            Self::SerpApi => "SERPAPI_API_KEY",
+            Self::Brave => "BRAVE_API_KEY",
+            Self::Tavily => "TAVILY_API_KEY",
+        }
+    }
+}
+
+/// One hit from a search API
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// Tool to search the web for library documentation and API notes the
+/// codebase can't answer. The query is sent to a third-party search API.
+pub struct WebSearchTool {
+    backend: WebSearchBackend,
+    api_key: String,
+    client: reqwest::Client,
+}
+
+impl WebSearchTool {
+    pub fn new(backend: WebSearchBackend, api_key: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self { backend, api_key: api_key.into(), client }
+    }
+
+    /// Configure from `MIOW_WEB_SEARCH` (`serpapi`, `brave` or `tavily`) and that
+    /// backend's key variable; `None` when either is missing
+    pub fn from_env() -> Option<Self> {
+        let backend = WebSearchBackend::parse(&std::env::var("MIOW_WEB_SEARCH").ok()?)?;
+        let api_key = std::env::var(backend.key_var()).ok().filter(|k| !k.trim().is_empty())?;
+        Some(Self::new(backend, api_key))
+    }
+
+    async fn fetch(&self, query: &str, count: u64) -> Result<serde_json::Value> {
+        let request = match self.backend {
+            WebSearchBackend::SerpApi => self
+                .client
+                .get("https://serpapi.com/search.json")
+                .query(&[("engine", "google"), ("q", query), ("num", &count.to_string()), ("api_key", &self.api_key)]),
+            WebSearchBackend::Brave => self
+                .client
+                .get("https://api.search.brave.com/res/v1/web/search")
+                .header("X-Subscription-Token", &self.api_key)
+                .header("Accept", "application/json")
+                .query(&[("q", query), ("count", &count.to_string())]),
+            WebSearchBackend::Tavily => self
+                .client
+                .post("https://api.tavily.com/search")
+                .json(&json!({ "api_key": self.api_key, "query": query, "max_results": count })),
+        };
+
+        let response = request.send().await.context("Web search request failed")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Web search returned {}: {}", status, body.chars().take(200).collect::<String>()));
+        }
+        response.json().await.context("Web search returned invalid JSON")
+    }
+}
+
+#[async_trait]
+impl Tool for WebSearchTool {
+    fn name(&self) -> &str { "web_search" }
+    fn description(&self) -> &str {
+        "Search the web for documentation of external libraries and APIs; results are not from this codebase"
+    }
+    fn safety(&self) -> ToolSafety { ToolSafety::ReadOnly }
+    fn schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Search query, e.g. a library name, version and API" },
+                "max_results": { "type": "integer", "description": "Number of results (default 5, max 10)" }
+            },
+            "required": ["query"]
+        })
+    }
+    async fn execute(&self, args: serde_json::Value) -> Result<String> {
+        let query = args["query"].as_str().ok_or_else(|| anyhow!("Missing 'query' argument"))?;
+        let count = args["max_results"].as_u64().unwrap_or(DEFAULT_MAX_RESULTS).clamp(1, MAX_RESULTS);
+
+        let body = self.fetch(query, count).await?;
+        let mut results = parse_results(self.backend, &body);
+        results.truncate(count as usize);
+        if results.is_empty() {
+            return Ok("No web results found.".to_string());
+        }
+        Ok(format_results(query, &results))
+    }
+}
+
+/// Pull title, URL and snippet out of a backend's response
+pub fn parse_results(backend: WebSearchBackend, body: &serde_json::Value) -> Vec<WebResult> {
+    let (items, url_key, snippet_key) = match backend {
+        WebSearchBackend::SerpApi => (&body["organic_results"], "link", "snippet"),
+        WebSearchBackend::Brave => (&body["web"]["results"], "url", "description"),
+        WebSearchBackend::Tavily => (&body["results"], "url", "content"),
+    };
+    items
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    Some(WebResult {
+                        title: item["title"].as_str().unwrap_or_default().to_string(),
+                        url: item[url_key].as_str()?.to_string(),
+                        snippet: item[snippet_key].as_str().unwrap_or_default().to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn format_results(query: &str, results: &[WebResult]) -> String {
+    let mut out = format!("{} results for '{}'\n", EXTERNAL_LABEL, query);
+    for (i, result) in results.iter().enumerate() {
+        let snippet: String = result.snippet.chars().take(SNIPPET_MAX_CHARS).collect();
+        out.push_str(&format!("{}. {}\n   {}\n   {}\n", i + 1, result.title, result.url, snippet.trim()));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_results_per_backend() {
+        let serpapi = json!({ "organic_results": [{ "title": "zod", "link": "https://zod.dev", "snippet": "Schema validation" }] });
+        let brave = json!({ "web": { "results": [{ "title": "zod", "url": "https://zod.dev", "description": "Schema validation" }] } });
+        let tavily = json!({ "results": [{ "title": "zod", "url": "https://zod.dev", "content": "Schema validation" }, { "title": "no url" }] });
+
+        let expected = vec![WebResult {
+            title: "zod".to_string(),
+            url: "https://zod.dev".to_string(),
+            snippet: "Schema validation".to_string(),
+        }];
+        assert_eq!(parse_results(WebSearchBackend::SerpApi, &serpapi), expected);
+        assert_eq!(parse_results(WebSearchBackend::Brave, &brave), expected);
+        assert_eq!(parse_results(WebSearchBackend::Tavily, &tavily), expected);
+        assert!(parse_results(WebSearchBackend::Brave, &serpapi).is_empty());
+    }
+
+    #[test]
+    fn test_results_are_labeled_external() {
+        let results = vec![WebResult {
+            title: "zod".to_string(),
+            url: "https://zod.dev".to_string(),
+            snippet: "Schema validation".to_string(),
+        }];
+        assert!(format_results("zod refine", &results).starts_with(EXTERNAL_LABEL));
+        assert_eq!(WebSearchBackend::parse(" Tavily "), Some(WebSearchBackend::Tavily));
+        assert_eq!(WebSearchBackend::parse("bing"), None);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::{SearchQuery, SpecializedPrompt, PromptRegistry};
+use crate::autonomous::redact_for_llm;
+use crate::{SearchQuery, PromptRegistry};
 use async_trait::async_trait;
 use miow_common::{CodeChunk, Result as MiowResult};
 use miow_core::ProjectSignature;
@@ -57,7 +58,7 @@ impl WorkerAgent for GeminiWorkerAgent {
         prompt_key: &str,
         user_prompt: &str,
         project_signature: &ProjectSignature,
-        search_queries: &[SearchQuery],
+        _search_queries: &[SearchQuery],
     ) -> MiowResult<WorkerResult> {
         let prompt = self.registry.get_prompt(prompt_key)
             .ok_or_else(|| miow_common::MiowError::Generic(
@@ -67,13 +68,9 @@ impl WorkerAgent for GeminiWorkerAgent {
         // Build the full prompt by substituting variables
         let template = &prompt.template;
         let project_info = project_signature.to_description();
-        let query_list = search_queries.iter()
-            .map(|q| format!("- {} ({})", q.query, q.kind.as_deref().unwrap_or("any")))
-            .collect::<Vec<_>>()
-            .join("\n");
 
         let full_prompt = template
-            .replace("{user_prompt}", user_prompt)
+            .replace("{user_prompt}", &redact_for_llm(prompt_key, user_prompt.to_string()))
             .replace("{project_info}", &project_info)
             .replace("{project_stack}", &project_info)
             .replace("{file_path}", "") // Could be enhanced to pass specific files
@@ -119,44 +116,44 @@ impl GeminiWorkerAgent {
         // custom JSON schema parsing based on what it returns
 
         // Try to parse as JSON first
-        if let Ok(json) = serde_json::from_str::<serde_json::Value>(response) {
-            if let Some(array) = json.as_array() {
-                let mut chunks = Vec::new();
-                for item in array {
-                    if let Some(obj) = item.as_object() {
-                        let chunk = CodeChunk {
-                            id: format!("{}-{}", prompt_key, chunks.len()),
-                            content: obj.get("content")
-                                .or_else(|| obj.get("definition"))
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("")
-                                .to_string(),
-                            file_path: obj.get("file_path")
-                                .or_else(|| obj.get("path"))
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("")
-                                .to_string(),
-                            language: obj.get("language")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("unknown")
-                                .to_string(),
-                            start_line: 0,
-                            end_line: 0,
-                            kind: obj.get("kind")
-                                .or_else(|| obj.get("type"))
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("unknown")
-                                .to_string(),
-                            metadata: json!({
-                                "worker": prompt_key,
-                                "description": obj.get("description").and_then(|v| v.as_str()).unwrap_or("")
-                            }),
-                        };
-                        chunks.push(chunk);
-                    }
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(response)
+            && let Some(array) = json.as_array()
+        {
+            let mut chunks = Vec::new();
+            for item in array {
+                if let Some(obj) = item.as_object() {
+                    let chunk = CodeChunk {
+                        id: format!("{}-{}", prompt_key, chunks.len()),
+                        content: obj.get("content")
+                            .or_else(|| obj.get("definition"))
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("")
+                            .to_string(),
+                        file_path: obj.get("file_path")
+                            .or_else(|| obj.get("path"))
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("")
+                            .to_string(),
+                        language: obj.get("language")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("unknown")
+                            .to_string(),
+                        start_line: 0,
+                        end_line: 0,
+                        kind: obj.get("kind")
+                            .or_else(|| obj.get("type"))
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("unknown")
+                            .to_string(),
+                        metadata: json!({
+                            "worker": prompt_key,
+                            "description": obj.get("description").and_then(|v| v.as_str()).unwrap_or("")
+                        }),
+                    };
+                    chunks.push(chunk);
                 }
-                return Ok(chunks);
             }
+            return Ok(chunks);
         }
 
         // Fallback: create a single chunk with the raw response
@@ -0,0 +1,299 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// What a task asks for. Every stage labels tasks with one of these, so the
+/// label an LLM or the router returns is resolved to it before use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Intent {
+    Create,
+    CreateComponent,
+    CreateFunction,
+    CreatePage,
+    Modify,
+    Fix,
+    Refactor,
+    Test,
+    Document,
+    Explain,
+    #[default]
+    Unknown,
+}
+
+impl Intent {
+    pub const ALL: [Intent; 11] = [
+        Intent::Create,
+        Intent::CreateComponent,
+        Intent::CreateFunction,
+        Intent::CreatePage,
+        Intent::Modify,
+        Intent::Fix,
+        Intent::Refactor,
+        Intent::Test,
+        Intent::Document,
+        Intent::Explain,
+        Intent::Unknown,
+    ];
+
+    /// The canonical label, e.g. `create_component`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Intent::Create => "create",
+            Intent::CreateComponent => "create_component",
+            Intent::CreateFunction => "create_function",
+            Intent::CreatePage => "create_page",
+            Intent::Modify => "modify",
+            Intent::Fix => "fix",
+            Intent::Refactor => "refactor",
+            Intent::Test => "test",
+            Intent::Document => "document",
+            Intent::Explain => "explain",
+            Intent::Unknown => "unknown",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Intent::Create => "add a feature or other new code",
+            Intent::CreateComponent => "add a UI component",
+            Intent::CreateFunction => "add a function, helper or utility",
+            Intent::CreatePage => "add a page, screen or route",
+            Intent::Modify => "change how existing code behaves",
+            Intent::Fix => "fix a bug or error",
+            Intent::Refactor => "restructure or rename code without changing behavior",
+            Intent::Test => "add or update tests",
+            Intent::Document => "write documentation or comments",
+            Intent::Explain => "explain how code works, without changing it",
+            Intent::Unknown => "none of the above",
+        }
+    }
+
+    /// The intent a more specific one narrows: `create` for `create_page`
+    pub fn family(&self) -> Intent {
+        match self {
+            Intent::CreateComponent | Intent::CreateFunction | Intent::CreatePage => Intent::Create,
+            other => *other,
+        }
+    }
+}
+
+impl fmt::Display for Intent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Accepts the label in any casing or separator style: `create_component`,
+/// `CreateComponent`, `create-component`
+impl FromStr for Intent {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let wanted = squash(s);
+        Intent::ALL
+            .into_iter()
+            .find(|intent| squash(intent.as_str()) == wanted)
+            .ok_or_else(|| anyhow::anyhow!("Unknown intent '{}'", s))
+    }
+}
+
+/// Deserialize a label an LLM wrote, resolving aliases like `fix_bug` with
+/// the built-in taxonomy; anything unrecognized becomes `Unknown`
+pub fn deserialize_lenient<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Intent, D::Error> {
+    let label = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+    Ok(IntentClassifier::default().resolve(&label).unwrap_or_default())
+}
+
+/// One entry of the taxonomy: a task is `intent` when it uses one of
+/// `keywords` and, if any are given, one of `requires`. Words match
+/// regardless of inflection ("fixes", "fixing" and "fixed" all match "fix").
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntentRule {
+    pub intent: Intent,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// Other labels for the intent, e.g. `fix_bug` for `fix`
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+impl IntentRule {
+    fn new(intent: Intent, keywords: &[&str], requires: &[&str], aliases: &[&str]) -> Self {
+        let owned = |words: &[&str]| words.iter().map(|w| w.to_string()).collect();
+        Self { intent, keywords: owned(keywords), requires: owned(requires), aliases: owned(aliases) }
+    }
+
+    fn matches(&self, stems: &[String]) -> bool {
+        let any = |words: &[String]| words.iter().any(|w| stems.contains(&stem(&w.to_lowercase())));
+        any(&self.keywords) && (self.requires.is_empty() || any(&self.requires))
+    }
+}
+
+/// The rules' verdict on a task
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classification {
+    pub intent: Intent,
+    /// Intents of other families whose rules also matched. Plain `create`
+    /// never counts: its verbs go with any intent ("add tests").
+    pub alternatives: Vec<Intent>,
+}
+
+impl Classification {
+    /// Whether the rules settled on one intent; otherwise an LLM should decide
+    pub fn is_confident(&self) -> bool {
+        self.intent != Intent::Unknown && self.alternatives.is_empty()
+    }
+}
+
+/// Classifies tasks with an ordered list of rules: the project's own rules
+/// first, then the built-in ones. The first matching rule decides.
+#[derive(Debug, Clone)]
+pub struct IntentClassifier {
+    rules: Vec<IntentRule>,
+}
+
+impl Default for IntentClassifier {
+    fn default() -> Self {
+        const CREATE: &[&str] = &["create", "make", "add", "new", "build", "implement"];
+        Self {
+            rules: vec![
+                IntentRule::new(Intent::CreateComponent, CREATE, &["component", "widget"], &[]),
+                IntentRule::new(Intent::CreateFunction, CREATE, &["function", "helper", "util", "utility"], &[]),
+                IntentRule::new(Intent::CreatePage, CREATE, &["page", "screen", "view", "route"], &[]),
+                IntentRule::new(Intent::Test, &["test", "spec", "coverage"], &[], &["add_tests", "write_tests", "testing"]),
+                IntentRule::new(Intent::Document, &["document", "docs", "docstring", "readme", "jsdoc"], &[], &["documentation", "docs"]),
+                IntentRule::new(Intent::Explain, &["explain", "understand", "describe", "walkthrough"], &[], &["explanation", "question"]),
+                IntentRule::new(Intent::Create, CREATE, &[], &["add_feature", "feature", "new_feature", "implement"]),
+                IntentRule::new(Intent::Modify, &["modify", "update", "change", "edit", "extend"], &[], &["enhancement", "update", "change"]),
+                IntentRule::new(Intent::Fix, &["fix", "debug", "solve", "bug", "broken", "error"], &[], &["fix_bug", "bugfix", "bug", "debug"]),
+                IntentRule::new(Intent::Refactor, &["refactor", "rename", "improve", "optimize", "cleanup", "simplify"], &[], &["rename", "cleanup", "optimize"]),
+            ],
+        }
+    }
+}
+
+impl IntentClassifier {
+    /// The built-in taxonomy with `rules` (from miow.toml) checked first
+    pub fn with_rules(rules: Vec<IntentRule>) -> Self {
+        let mut classifier = Self::default();
+        classifier.rules.splice(0..0, rules);
+        classifier
+    }
+
+    pub fn classify(&self, prompt: &str) -> Classification {
+        let stems = stems(prompt);
+        let mut matched = self.rules.iter().filter(|rule| rule.matches(&stems)).map(|rule| rule.intent);
+        let Some(intent) = matched.next() else {
+            return Classification { intent: Intent::Unknown, alternatives: Vec::new() };
+        };
+        let mut alternatives: Vec<Intent> = Vec::new();
+        for other in matched {
+            if other != Intent::Create && other.family() != intent.family() && !alternatives.contains(&other) {
+                alternatives.push(other);
+            }
+        }
+        Classification { intent, alternatives }
+    }
+
+    /// The intent a label names: a canonical label, an alias, or failing
+    /// those, what the rules make of its words (`create_login_page`)
+    pub fn resolve(&self, label: &str) -> Option<Intent> {
+        let label = label.trim().trim_matches(|c: char| c == '"' || c == '`' || c == '.');
+        if label.is_empty() {
+            return None;
+        }
+        if let Ok(intent) = label.parse() {
+            return Some(intent);
+        }
+        let wanted = squash(label);
+        if let Some(rule) = self.rules.iter().find(|rule| rule.aliases.iter().any(|a| squash(a) == wanted)) {
+            return Some(rule.intent);
+        }
+        let classification = self.classify(&label.replace(['_', '-'], " "));
+        (classification.intent != Intent::Unknown).then_some(classification.intent)
+    }
+
+    /// System prompt asking an LLM to pick one label of the taxonomy
+    pub fn llm_instructions(&self) -> String {
+        let mut instructions = String::from(
+            "Classify the developer's task into exactly one of these intents. Respond with the label only.\n\n",
+        );
+        for intent in Intent::ALL {
+            instructions.push_str(&format!("- {}: {}\n", intent, intent.description()));
+        }
+        instructions
+    }
+}
+
+/// Lowercase alphanumerics only, so `Create-Component` and `create_component` compare equal
+fn squash(label: &str) -> String {
+    label.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+fn stems(text: &str) -> Vec<String> {
+    text.to_lowercase().split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).map(stem).collect()
+}
+
+/// Strip common English suffixes so inflections of a word compare equal
+fn stem(word: &str) -> String {
+    let mut word = word;
+    for suffix in ["ing", "ed", "es", "s"] {
+        if let Some(stripped) = word.strip_suffix(suffix).filter(|w| w.len() >= 3) {
+            word = stripped;
+            break;
+        }
+    }
+    word.strip_suffix('e').filter(|w| w.len() >= 3).unwrap_or(word).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_resolve_to_one_intent() {
+        let classifier = IntentClassifier::default();
+        for label in ["create_component", "CreateComponent", "create-component", "\"create_component\"\n"] {
+            assert_eq!(classifier.resolve(label), Some(Intent::CreateComponent), "{}", label);
+        }
+        assert_eq!(classifier.resolve("fix_bug"), Some(Intent::Fix));
+        assert_eq!(classifier.resolve("add_feature"), Some(Intent::Create));
+        assert_eq!(classifier.resolve("create_login_page"), Some(Intent::CreatePage));
+        assert_eq!(classifier.resolve("banana"), None);
+        assert_eq!(serde_json::to_string(&Intent::CreatePage).unwrap(), "\"create_page\"");
+    }
+
+    #[test]
+    fn test_classify_flags_ambiguous_tasks() {
+        let classifier = IntentClassifier::default();
+        let classification = classifier.classify("Fixes the crash when saving drafts");
+        assert_eq!(classification, Classification { intent: Intent::Fix, alternatives: vec![] });
+        assert!(classification.is_confident());
+
+        // Narrower intents of the same family don't count as alternatives
+        assert!(classifier.classify("Create a new Avatar component").is_confident());
+        assert_eq!(classifier.classify("Add tests for the cart reducer").intent, Intent::Test);
+        assert!(classifier.classify("Add tests for the cart reducer").is_confident());
+        // "address" is not "add"
+        assert_eq!(classifier.classify("Validate the address field").intent, Intent::Unknown);
+
+        let ambiguous = classifier.classify("Add a settings page and fix the broken logout");
+        assert_eq!(ambiguous.intent, Intent::CreatePage);
+        assert_eq!(ambiguous.alternatives, vec![Intent::Fix]);
+        assert!(!ambiguous.is_confident());
+    }
+
+    #[test]
+    fn test_project_rules_come_first() {
+        let rule: IntentRule = serde_json::from_str(
+            r#"{"intent": "create_function", "keywords": ["add"], "requires": ["endpoint"], "aliases": ["new_endpoint"]}"#,
+        )
+        .unwrap();
+        let classifier = IntentClassifier::with_rules(vec![rule]);
+        assert_eq!(classifier.classify("Add an endpoint for invoices page").intent, Intent::CreateFunction);
+        assert_eq!(classifier.resolve("new-endpoint"), Some(Intent::CreateFunction));
+    }
+}
@@ -1,13 +1,28 @@
-use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
+pub mod intent;
+
+pub use intent::{Classification, Intent, IntentClassifier, IntentRule};
+
 /// Context analyzer - analyzes user prompts and finds relevant context
-pub struct ContextAnalyzer;
+pub struct ContextAnalyzer {
+    classifier: IntentClassifier,
+}
 
 impl ContextAnalyzer {
     pub fn new() -> Self {
-        Self
+        Self { classifier: IntentClassifier::default() }
+    }
+
+    /// Classify intents with the project's taxonomy instead of the built-in one
+    pub fn with_classifier(mut self, classifier: IntentClassifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    pub fn classifier(&self) -> &IntentClassifier {
+        &self.classifier
     }
 
     /// Analyze a prompt and extract keywords/entities
@@ -111,40 +126,9 @@ impl ContextAnalyzer {
             .collect()
     }
 
-    /// Infer the intent from the prompt
-    fn infer_intent(&self, prompt: &str) -> PromptIntent {
-        let lower = prompt.to_lowercase();
-
-        if lower.contains("create")
-            || lower.contains("make")
-            || lower.contains("add")
-            || lower.contains("new")
-        {
-            if lower.contains("component") {
-                PromptIntent::CreateComponent
-            } else if lower.contains("function") || lower.contains("helper") {
-                PromptIntent::CreateFunction
-            } else if lower.contains("page") || lower.contains("screen") {
-                PromptIntent::CreatePage
-            } else {
-                PromptIntent::Create
-            }
-        } else if lower.contains("modify")
-            || lower.contains("update")
-            || lower.contains("change")
-            || lower.contains("edit")
-        {
-            PromptIntent::Modify
-        } else if lower.contains("fix") || lower.contains("debug") || lower.contains("solve") {
-            PromptIntent::Fix
-        } else if lower.contains("refactor")
-            || lower.contains("improve")
-            || lower.contains("optimize")
-        {
-            PromptIntent::Refactor
-        } else {
-            PromptIntent::Unknown
-        }
+    /// Infer the intent from the prompt with the taxonomy rules alone
+    fn infer_intent(&self, prompt: &str) -> Intent {
+        self.classifier.classify(prompt).intent
     }
 
     /// Extract potential entity names (capitalized words, camelCase, etc.)
@@ -183,22 +167,10 @@ impl Default for ContextAnalyzer {
 pub struct AnalyzedPrompt {
     pub original: String,
     pub keywords: Vec<String>,
-    pub intent: PromptIntent,
+    pub intent: Intent,
     pub entities: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum PromptIntent {
-    Create,
-    CreateComponent,
-    CreateFunction,
-    CreatePage,
-    Modify,
-    Fix,
-    Refactor,
-    Unknown,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,10 +194,10 @@ mod tests {
         let prompt1 = "Create a new Button component";
         assert_eq!(
             analyzer.analyze_prompt(prompt1).intent,
-            PromptIntent::CreateComponent
+            Intent::CreateComponent
         );
 
         let prompt2 = "Fix the authentication bug";
-        assert_eq!(analyzer.analyze_prompt(prompt2).intent, PromptIntent::Fix);
+        assert_eq!(analyzer.analyze_prompt(prompt2).intent, Intent::Fix);
     }
 }
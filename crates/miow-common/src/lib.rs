@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+pub mod redaction;
+
+pub use redaction::SecretRedactor;
+
 /// Represents a chunk of code with metadata for vector storage and retrieval
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeChunk {
@@ -28,6 +32,12 @@ pub struct FileEntry {
     pub is_binary: bool,
 }
 
+impl Default for FileMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FileMap {
     pub fn new() -> Self {
         Self { files: Vec::new() }
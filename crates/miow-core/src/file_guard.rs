@@ -0,0 +1,82 @@
+use crate::types::SkipReason;
+
+/// Bytes sniffed for NULs when deciding whether a file is binary, as git does
+const BINARY_SNIFF_LEN: usize = 8000;
+
+const BINARY_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "ico", "bmp", "pdf", "zip", "gz", "tgz", "bz2", "xz", "7z", "tar", "jar",
+    "war", "class", "so", "dylib", "dll", "exe", "o", "a", "lib", "wasm", "woff", "woff2", "ttf", "otf", "eot",
+    "mp3", "mp4", "mov", "avi", "webm", "sqlite", "db", "bin", "lockb",
+];
+
+/// Lockfiles are large, generated and say nothing about how the code is written
+const LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "Cargo.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "composer.lock",
+    "Gemfile.lock",
+    "go.sum",
+];
+
+const GENERATED_SUFFIXES: &[&str] = &[".min.js", ".min.css", ".min.mjs", ".bundle.js", ".chunk.js", ".map"];
+
+/// Decide from the name alone whether a file should be passed over
+pub fn skip_by_name(file_name: &str) -> Option<SkipReason> {
+    let extension = file_name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    if extension.is_some_and(|ext| BINARY_EXTENSIONS.contains(&ext.as_str())) {
+        return Some(SkipReason::Binary);
+    }
+    if LOCKFILES.contains(&file_name) || GENERATED_SUFFIXES.iter().any(|suffix| file_name.ends_with(suffix)) {
+        return Some(SkipReason::Generated);
+    }
+    None
+}
+
+/// Decode a file's bytes, or say why it shouldn't be indexed: NUL bytes or
+/// invalid UTF-8 mean binary, and a line longer than `max_line_length`
+/// (when non-zero) means a minified bundle
+pub fn check_content(bytes: Vec<u8>, max_line_length: usize) -> Result<String, SkipReason> {
+    let sniff = &bytes[..bytes.len().min(BINARY_SNIFF_LEN)];
+    if sniff.contains(&0) {
+        return Err(SkipReason::Binary);
+    }
+    let content = String::from_utf8(bytes).map_err(|_| SkipReason::Binary)?;
+
+    if max_line_length > 0 {
+        if let Some(longest) = content.lines().map(str::len).max().filter(|len| *len > max_line_length) {
+            return Err(SkipReason::Minified { longest_line: longest });
+        }
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_by_name() {
+        assert!(matches!(skip_by_name("logo.PNG"), Some(SkipReason::Binary)));
+        assert!(matches!(skip_by_name("package-lock.json"), Some(SkipReason::Generated)));
+        assert!(matches!(skip_by_name("vendor.min.js"), Some(SkipReason::Generated)));
+        assert!(skip_by_name("package.json").is_none());
+        assert!(skip_by_name("Makefile").is_none());
+    }
+
+    #[test]
+    fn test_check_content() {
+        assert_eq!(check_content(b"fn main() {}\n".to_vec(), 100).unwrap(), "fn main() {}\n");
+        assert!(matches!(check_content(vec![0x47, 0x00, 0x11], 100), Err(SkipReason::Binary)));
+        assert!(matches!(check_content(vec![0xff, 0xfe, 0x41], 100), Err(SkipReason::Binary)));
+
+        let minified = format!("var a={};\n", "1".repeat(200));
+        assert!(matches!(check_content(minified.clone().into_bytes(), 100), Err(SkipReason::Minified { .. })));
+        assert!(check_content(minified.into_bytes(), 0).is_ok());
+    }
+}
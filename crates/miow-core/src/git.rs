@@ -0,0 +1,197 @@
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Graph metadata key holding the commit the index was built from
+pub const INDEXED_COMMIT_KEY: &str = "indexed_commit";
+/// Graph metadata keys recording the remote an index was cloned from and where the clone lives
+pub const ORIGIN_URL_KEY: &str = "origin_url";
+pub const CHECKOUT_PATH_KEY: &str = "checkout_path";
+
+/// Files that differ between a revision and the working tree, relative to the
+/// indexed root. Untracked files count as modified.
+#[derive(Debug, Default)]
+pub struct GitChanges {
+    pub modified: HashSet<String>,
+    pub deleted: Vec<String>,
+}
+
+/// Commit checked out in `root`, or `None` outside a git repository
+pub fn head_commit(root: &Path) -> Option<String> {
+    git(root, &["rev-parse", "HEAD"]).ok().map(|out| out.trim().to_string())
+}
+
+/// The configured `user.email` and `user.name` of the repository at `root`, for
+/// matching against CODEOWNERS entries
+pub fn user_identity(root: &Path) -> Vec<String> {
+    ["user.email", "user.name"]
+        .iter()
+        .filter_map(|key| git(root, &["config", key]).ok())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// How many commits HEAD is ahead of `commit`
+pub fn commits_since(root: &Path, commit: &str) -> Option<usize> {
+    git(root, &["rev-list", "--count", &format!("{}..HEAD", commit)])
+        .ok()
+        .and_then(|out| out.trim().parse().ok())
+}
+
+/// Files changed since `rev`, including uncommitted and untracked ones
+pub fn changed_since(root: &Path, rev: &str) -> Result<GitChanges> {
+    // A typo'd revision must fail loudly rather than look like "nothing changed"
+    git(root, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev)])
+        .with_context(|| format!("Unknown git revision: {}", rev))?;
+
+    let diff = git(root, &["diff", "--name-status", "--no-renames", "--relative", "-z", rev, "--"])?;
+    let mut changes = parse_name_status(&diff);
+
+    let untracked = git(root, &["ls-files", "--others", "--exclude-standard", "-z"])?;
+    changes
+        .modified
+        .extend(untracked.split('\0').filter(|p| !p.is_empty()).map(String::from));
+    Ok(changes)
+}
+
+/// The email of the author of the most commits to each file among the last
+/// `max_commits`, like `git shortlog -e` per file; ties go to the most recent
+/// author. Keyed by path relative to `root`.
+pub fn top_authors(root: &Path, max_commits: usize) -> Result<HashMap<String, String>> {
+    let log = git(
+        root,
+        &["log", "--no-merges", "--relative", "--name-only", "--format=%x00%aE", "-n", &max_commits.to_string()],
+    )?;
+    Ok(parse_author_log(&log))
+}
+
+/// `git log --name-only --format=%x00<author>` output: NUL, author line, changed paths
+fn parse_author_log(output: &str) -> HashMap<String, String> {
+    // Per file: commits per author, and the order authors were first seen (newest first)
+    let mut counts: HashMap<&str, Vec<(&str, usize)>> = HashMap::new();
+    for commit in output.split('\0').filter(|c| !c.trim().is_empty()) {
+        let mut lines = commit.lines();
+        let Some(author) = lines.next() else { continue };
+        for path in lines.filter(|l| !l.is_empty()) {
+            let authors = counts.entry(path).or_default();
+            match authors.iter_mut().find(|(a, _)| *a == author) {
+                Some((_, count)) => *count += 1,
+                None => authors.push((author, 1)),
+            }
+        }
+    }
+    counts
+        .into_iter()
+        .filter_map(|(path, authors)| {
+            // max_by_key keeps the last maximum, so search from the oldest
+            let (author, _) = authors.into_iter().rev().max_by_key(|(_, count)| *count)?;
+            Some((path.to_string(), author.to_string()))
+        })
+        .collect()
+}
+
+/// Shallow-clone `url` into `cache_root`, or fast-forward the existing clone
+/// to the remote's latest commit. Returns the checkout directory.
+pub fn clone_or_update(url: &str, cache_root: &Path) -> Result<PathBuf> {
+    let is_remote = ["https://", "http://", "ssh://", "git://", "file://", "git@"]
+        .iter()
+        .any(|prefix| url.starts_with(prefix));
+    if !is_remote {
+        anyhow::bail!("Not a git URL: {}", url);
+    }
+
+    let dir_name = repo_dir_name(url);
+    let checkout = cache_root.join(&dir_name);
+    if checkout.join(".git").exists() {
+        git(&checkout, &["fetch", "--depth", "1", "origin"])?;
+        git(&checkout, &["reset", "--hard", "FETCH_HEAD"])?;
+    } else {
+        std::fs::create_dir_all(cache_root)
+            .with_context(|| format!("Failed to create {}", cache_root.display()))?;
+        git(cache_root, &["clone", "--depth", "1", "--", url, &dir_name])?;
+    }
+    Ok(checkout)
+}
+
+/// `https://github.com/org/repo.git` -> `github.com-org-repo`
+fn repo_dir_name(url: &str) -> String {
+    let trimmed = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .trim_start_matches("git@")
+        .trim_end_matches('/')
+        .trim_end_matches(".git");
+    let name: String = trimmed
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '_' { c } else { '-' })
+        .collect();
+    match name.trim_matches(|c| c == '-' || c == '.') {
+        "" => "repo".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// `git diff --name-status -z` output: status and path, NUL-separated
+fn parse_name_status(output: &str) -> GitChanges {
+    let mut changes = GitChanges::default();
+    let mut fields = output.split('\0').filter(|f| !f.is_empty());
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        if status.starts_with('D') {
+            changes.deleted.push(path.to_string());
+        } else {
+            changes.modified.insert(path.to_string());
+        }
+    }
+    changes.deleted.sort();
+    changes
+}
+
+fn git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_name_status() {
+        let changes = parse_name_status("M\0src/a.rs\0D\0src/old.rs\0A\0src/new file.rs\0T\0link\0");
+        assert_eq!(changes.deleted, vec!["src/old.rs"]);
+        assert_eq!(changes.modified.len(), 3);
+        assert!(changes.modified.contains("src/new file.rs"));
+    }
+
+    #[test]
+    fn test_parse_author_log() {
+        let log = "\0bo@x.io\n\nsrc/a.rs\nsrc/b.rs\n\0al@x.io\n\nsrc/a.rs\n\0al@x.io\n\nsrc/a.rs\nsrc/c.rs\n\0cy@x.io\n\nsrc/b.rs\n";
+        let authors = parse_author_log(log);
+        assert_eq!(authors["src/a.rs"], "al@x.io");
+        // One commit each: the newer author wins
+        assert_eq!(authors["src/b.rs"], "bo@x.io");
+        assert_eq!(authors.len(), 3);
+    }
+
+    #[test]
+    fn test_repo_dir_name() {
+        assert_eq!(repo_dir_name("https://github.com/org/repo.git"), "github.com-org-repo");
+        assert_eq!(repo_dir_name("git@github.com:org/repo"), "github.com-org-repo");
+        assert_eq!(repo_dir_name("file:///srv/mirrors/lib/"), "srv-mirrors-lib");
+        assert_eq!(repo_dir_name("file:///.."), "repo");
+    }
+}
@@ -0,0 +1,174 @@
+use miow_graph::{
+    ConstantData, DesignTokenData, EnvVarData, ExportData, ImportData, IndexErrorData, ParsedFileData, RouteData, SchemaData,
+    SchemaFieldData, SymbolData, TestData, TypeDefinitionData,
+};
+use miow_parsers::{
+    extract_env_vars, extract_routes, extract_tests, EnvVarUsage, ParseDiagnostic, ParsedFile, Route, Symbol, TestCase,
+};
+
+/// Convert parser output into the graph's storage types. Routes, env var
+/// reads and tests come from separate passes because they depend on the file's path.
+pub fn to_graph_data(parsed: ParsedFile, relative_path: &str, content: &str) -> ParsedFileData {
+    ParsedFileData {
+        symbols: parsed.symbols.into_iter().map(convert_symbol).collect(),
+        imports: parsed
+            .imports
+            .into_iter()
+            .map(|imp| ImportData {
+                source: imp.source,
+                names: imp.names.into_iter().map(|n| n.name).collect(),
+                start_line: imp.range.start_line,
+                end_line: imp.range.end_line,
+            })
+            .collect(),
+        exports: parsed
+            .exports
+            .into_iter()
+            .map(|e| ExportData {
+                name: e.name,
+                alias: e.alias,
+                source: e.source,
+                is_default: e.is_default,
+                is_type: e.is_type,
+                start_line: e.range.start_line,
+            })
+            .collect(),
+        design_tokens: parsed
+            .design_tokens
+            .into_iter()
+            .map(|token| DesignTokenData {
+                token_type: format!("{:?}", token.token_type),
+                name: token.name,
+                value: token.value,
+                context: token.context,
+                start_line: token.range.start_line,
+                end_line: token.range.end_line,
+            })
+            .collect(),
+        type_definitions: parsed
+            .type_definitions
+            .into_iter()
+            .map(|td| TypeDefinitionData {
+                name: td.name,
+                kind: format!("{:?}", td.kind),
+                definition: td.definition,
+                start_line: td.range.start_line,
+                end_line: td.range.end_line,
+            })
+            .collect(),
+        constants: parsed
+            .constants
+            .into_iter()
+            .map(|c| ConstantData {
+                name: c.name,
+                value: c.value,
+                category: format!("{:?}", c.category),
+                start_line: c.range.start_line,
+                end_line: c.range.end_line,
+            })
+            .collect(),
+        schemas: parsed
+            .schemas
+            .into_iter()
+            .map(|s| SchemaData {
+                name: s.name,
+                schema_type: format!("{:?}", s.schema_type),
+                definition: s.definition,
+                start_line: s.range.start_line,
+                end_line: s.range.end_line,
+                fields: s
+                    .fields
+                    .into_iter()
+                    .map(|f| SchemaFieldData {
+                        name: f.name,
+                        field_type: f.type_annotation,
+                        is_required: f.is_required,
+                        default_value: f.default_value,
+                        references: f.references,
+                    })
+                    .collect(),
+            })
+            .collect(),
+        routes: convert_routes(extract_routes(relative_path, content)),
+        env_vars: convert_env_vars(extract_env_vars(relative_path, content)),
+        tests: convert_tests(extract_tests(relative_path, content)),
+        index_errors: Vec::new(),
+        language: parsed.language,
+        content_hash: Some(content_hash(content)),
+        modified_at: None,
+        line_count: Some(content.lines().count() as i64),
+    }
+}
+
+fn convert_symbol(symbol: Symbol) -> SymbolData {
+    SymbolData {
+        name: symbol.name,
+        kind: format!("{:?}", symbol.kind),
+        start_line: symbol.range.start_line,
+        end_line: symbol.range.end_line,
+        start_byte: symbol.range.start_byte,
+        end_byte: symbol.range.end_byte,
+        content: symbol.content,
+        metadata: serde_json::to_string(&symbol.metadata).unwrap_or_default(),
+        style_tags: None, // Will be populated during style analysis
+        children: symbol.children.into_iter().map(convert_symbol).collect(),
+        references: symbol.references,
+    }
+}
+
+fn convert_routes(routes: Vec<Route>) -> Vec<RouteData> {
+    routes
+        .into_iter()
+        .map(|r| RouteData {
+            method: r.method,
+            path: r.path,
+            handler: r.handler,
+            framework: r.framework,
+            start_line: r.range.start_line,
+        })
+        .collect()
+}
+
+fn convert_env_vars(env_vars: Vec<EnvVarUsage>) -> Vec<EnvVarData> {
+    env_vars
+        .into_iter()
+        .map(|e| EnvVarData {
+            name: e.name,
+            accessor: e.accessor,
+            start_line: e.range.start_line,
+        })
+        .collect()
+}
+
+fn convert_tests(tests: Vec<TestCase>) -> Vec<TestData> {
+    tests
+        .into_iter()
+        .map(|t| TestData {
+            name: t.name,
+            framework: t.framework,
+            content: t.content,
+            references: t.references,
+            start_line: t.range.start_line,
+            end_line: t.range.end_line,
+        })
+        .collect()
+}
+
+pub fn convert_diagnostics(diagnostics: &[ParseDiagnostic]) -> Vec<IndexErrorData> {
+    diagnostics
+        .iter()
+        .map(|d| IndexErrorData {
+            line: d.line,
+            column: d.column,
+            message: d.message.clone(),
+        })
+        .collect()
+}
+
+/// FNV-1a over the file's bytes; stable across builds, unlike `DefaultHasher`
+pub fn content_hash(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
@@ -1,26 +1,64 @@
 use crate::types::*;
 use anyhow::Result;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use ignore::WalkBuilder;
-use miow_parsers::{parse_python, parse_rust, parse_typescript, ParsedFile};
+use miow_parsers::{
+    is_container_config, is_manifest, is_tailwind_config, IncrementalParser, ParseDiagnostic, ParsedFile,
+};
+use miow_graph::{KnowledgeGraph, ParsedFileData};
 use miow_vector::{SymbolVector, VectorStore};
-use std::collections::HashMap;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
 use std::time::Instant;
-use tracing::{debug, info, warn};
+use tracing::{debug, info, info_span, warn};
 
+use crate::file_guard;
+use crate::git::{self, GitChanges, INDEXED_COMMIT_KEY};
+use crate::graph_data::{content_hash, convert_diagnostics, to_graph_data};
+use crate::language_registry::LanguageRegistry;
+use crate::ownership;
 // Add project signature import
 use crate::project_signature::ProjectSignature;
 
+/// Files written to the knowledge graph per transaction
+const GRAPH_BATCH_SIZE: usize = 200;
+
+/// Receives `IndexProgress` events while an index runs
+pub type ProgressSender = tokio::sync::mpsc::UnboundedSender<IndexProgress>;
+
 /// Indexes a codebase by traversing files and extracting metadata
 pub struct CodebaseIndexer {
     root_path: PathBuf,
     config: IndexConfig,
     vector_store: Option<Arc<VectorStore>>,
+    graph: Option<Arc<KnowledgeGraph>>,
     project_signature: Option<ProjectSignature>,
+    /// Parser for each file extension
+    languages: Arc<LanguageRegistry>,
+    /// Syntax trees kept between `reparse_file` calls
+    incremental: IncrementalParser,
+    progress: Option<ProgressSender>,
+}
+
+/// What the parse workers and the graph writer produced
+#[derive(Default)]
+struct ParseOutcome {
+    symbols: usize,
+    design_tokens: usize,
+    routes: usize,
+    parse_errors: Vec<FileParseErrors>,
 }
 
+/// A worker's result for one file: `None` when parsing failed outright
+type ParseMessage = (usize, Option<ParsedFile>, Vec<ParseDiagnostic>);
+
+/// Parsed files handed from the graph writer to the embedder, by relative path
+type EmbedSender = tokio::sync::mpsc::Sender<(String, ParsedFile)>;
+
 impl CodebaseIndexer {
     pub fn new(root_path: PathBuf) -> Result<Self> {
         if !root_path.exists() {
@@ -32,10 +70,14 @@ impl CodebaseIndexer {
         }
 
         Ok(Self {
+            languages: Arc::new(LanguageRegistry::for_project(&root_path)?),
             root_path,
             config: IndexConfig::default(),
             vector_store: None,
+            graph: None,
             project_signature: None,
+            incremental: IncrementalParser::new(),
+            progress: None,
         })
     }
 
@@ -49,6 +91,24 @@ impl CodebaseIndexer {
         self
     }
 
+    /// Write parsed files into the knowledge graph as they are parsed
+    pub fn with_graph(mut self, graph: Arc<KnowledgeGraph>) -> Self {
+        self.graph = Some(graph);
+        self
+    }
+
+    /// Parse files with this registry's parsers, including any registered at runtime
+    pub fn with_language_registry(mut self, languages: Arc<LanguageRegistry>) -> Self {
+        self.languages = languages;
+        self
+    }
+
+    /// Report discovered, parsed, embedded and failed files as the index runs
+    pub fn with_progress(mut self, progress: ProgressSender) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
     // New method to detect and set project signature
     pub fn detect_project_signature(&mut self) -> Result<&ProjectSignature> {
         if self.project_signature.is_none() {
@@ -58,6 +118,7 @@ impl CodebaseIndexer {
         Ok(self.project_signature.as_ref().unwrap())
     }
 
+    #[tracing::instrument(name = "index", skip_all, fields(root = %self.root_path.display()))]
     pub async fn index(&mut self) -> Result<IndexReport> {
         let start = Instant::now();
         info!("Starting codebase indexing at {:?}", self.root_path);
@@ -76,6 +137,17 @@ impl CodebaseIndexer {
         let mut files = Vec::new();
         let mut files_by_language: HashMap<String, usize> = HashMap::new();
         let mut total_size = 0u64;
+        let mut skipped = Vec::new();
+
+        // `--since` runs only read the files git reports as changed
+        let since_changes: Option<GitChanges> = match (&config.since, &self.graph) {
+            (Some(rev), Some(_)) => Some(git::changed_since(root_path, rev)?),
+            (Some(_), None) => {
+                warn!("Indexing changes since a revision needs a knowledge graph; indexing every file");
+                None
+            }
+            (None, _) => None,
+        };
 
         // Build walker with gitignore support
         let mut builder = WalkBuilder::new(&self.root_path);
@@ -121,15 +193,39 @@ impl CodebaseIndexer {
 
             // Get file extension
             let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            let relative_path = path
+                .strip_prefix(root_path)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string();
 
-            // Check if extension is in include list
+            if let Some(git_changes) = &since_changes {
+                if !git_changes.modified.contains(&relative_path.replace('\\', "/")) {
+                    continue;
+                }
+            }
+
+            // Check if extension is in include list; manifests, Dockerfiles/Compose files and
+            // registered plugin languages are always indexed
             if !config
                 .include_extensions
                 .contains(&extension.to_string())
+                && !is_manifest(file_name)
+                && !is_container_config(file_name)
+                && !self.languages.is_plugin_extension(extension)
             {
                 continue;
             }
 
+            if config.skip_generated {
+                if let Some(reason) = file_guard::skip_by_name(file_name) {
+                    debug!("Skipping {}: {}", relative_path, reason);
+                    skipped.push(SkippedFile { relative_path, reason });
+                    continue;
+                }
+            }
+
             // Get file metadata
             let metadata = match fs::metadata(path) {
                 Ok(m) => m,
@@ -144,90 +240,32 @@ impl CodebaseIndexer {
             // Skip files that are too large
             if size > config.max_file_size {
                 debug!("Skipping large file: {:?} ({} bytes)", path, size);
+                skipped.push(SkippedFile {
+                    relative_path,
+                    reason: SkipReason::TooLarge { size, limit: config.max_file_size },
+                });
                 continue;
             }
 
             // Read file content
-            let content = match fs::read_to_string(path) {
-                Ok(c) => c,
+            let bytes = match fs::read(path) {
+                Ok(b) => b,
                 Err(err) => {
                     warn!("Error reading file {:?}: {}", path, err);
                     continue;
                 }
             };
+            let max_line_length = if config.skip_generated { config.max_line_length } else { 0 };
+            let content = match file_guard::check_content(bytes, max_line_length) {
+                Ok(content) => content,
+                Err(reason) => {
+                    debug!("Skipping {}: {}", relative_path, reason);
+                    skipped.push(SkippedFile { relative_path, reason });
+                    continue;
+                }
+            };
 
             let language = Language::from_extension(extension);
-            let relative_path = path
-                .strip_prefix(&root_path)
-                .unwrap_or(path)
-                .to_string_lossy()
-                .to_string();
-
-            // Enhanced parsing with project signature context
-            if let Ok(parsed) = self.parse_file_enhanced(&content, extension, &signature, &config) {
-                // Index symbols with enhanced metadata
-                if let Some(store) = &vector_store {
-                    for symbol in parsed.symbols {
-                        let mut enhanced_metadata = symbol.metadata.clone();
-                        
-                        // Tag with UI library if applicable
-                        if let Some(ui_lib) = &signature.ui_library {
-                            enhanced_metadata.tags.push(format!("ui:{}", ui_lib.to_lowercase()));
-                        }
-
-                        // Tag with validation library
-                        if let Some(val_lib) = &signature.validation_library {
-                            enhanced_metadata.tags.push(format!("validation:{}", val_lib.to_lowercase()));
-                        }
-
-                        // Prioritize common UI components
-                        if Self::is_common_ui_component(&symbol.name) {
-                            enhanced_metadata.tags.push("common-ui".to_string());
-                            enhanced_metadata.priority = Some(1.0); // High priority
-                        }
-
-                        // Tag Zod schemas and form-related symbols
-                        if symbol.name.to_lowercase().contains("schema") || 
-                           symbol.content.contains("z.object") ||
-                           symbol.name.to_lowercase().contains("form") ||
-                           symbol.name.to_lowercase().contains("input") ||
-                           symbol.name.to_lowercase().contains("button") {
-                            enhanced_metadata.tags.push("form-validation".to_string());
-                        }
-
-                        let symbol_vector = SymbolVector {
-                            id: format!("{}:{}", relative_path, symbol.name),
-                            name: symbol.name,
-                            kind: format!("{:?}", symbol.kind),
-                            content: symbol.content,
-                            file_path: relative_path.clone(),
-                            metadata: serde_json::to_string(&enhanced_metadata).unwrap_or_default(),
-                        };
-
-                        if let Err(e) = store.insert_symbol(&symbol_vector).await {
-                            warn!(
-                                "Failed to insert symbol {} into vector store: {}",
-                                symbol_vector.name, e
-                            );
-                        }
-                    }
-
-                    // Index validation schemas separately for better search
-                    for schema in &parsed.schemas {
-                        let schema_vector = SymbolVector {
-                            id: format!("schema:{}", schema.name),
-                            name: format!("Validation Schema: {}", schema.name),
-                            kind: "validation-schema".to_string(),
-                            content: schema.definition.clone(),
-                            file_path: relative_path.clone(),
-                            metadata: serde_json::to_string(schema).unwrap_or_default(),
-                        };
-                        if let Err(e) = store.insert_symbol(&schema_vector).await {
-                            warn!("Failed to insert schema {}: {}", schema.name, e);
-                        }
-                    }
-                }
-            }
 
             files.push(CodeFile {
                 path: path.to_path_buf(),
@@ -235,6 +273,7 @@ impl CodebaseIndexer {
                 language,
                 size,
                 content,
+                modified_at: modified_at(&metadata),
             });
 
             total_size += size;
@@ -244,12 +283,150 @@ impl CodebaseIndexer {
             *files_by_language.entry(lang_name).or_insert(0) += 1;
         }
 
+        // Incremental runs only parse what changed since the graph was written
+        let mut changes = None;
+        let mut unchanged_files = Vec::new();
+        if config.incremental {
+            match &self.graph {
+                Some(graph) => {
+                    let (to_parse, unchanged, detected) = Self::detect_changes(files, graph)?;
+                    info!(
+                        "Incremental index: {} added, {} changed, {} removed, {} unchanged",
+                        detected.added.len(),
+                        detected.changed.len(),
+                        detected.removed.len(),
+                        detected.unchanged
+                    );
+                    Self::remove_stale(graph, vector_store.as_deref(), &detected).await?;
+                    files = to_parse;
+                    unchanged_files = unchanged;
+                    changes = Some(detected);
+                }
+                None => warn!("Incremental indexing needs a knowledge graph; indexing every file"),
+            }
+        } else if let (Some(git_changes), Some(graph)) = (&since_changes, &self.graph) {
+            let stored = graph.file_fingerprints()?;
+            let mut detected = IndexChanges::default();
+            for file in &files {
+                match stored.contains_key(&file.relative_path) {
+                    true => detected.changed.push(file.relative_path.clone()),
+                    false => detected.added.push(file.relative_path.clone()),
+                }
+            }
+            detected.removed = git_changes
+                .deleted
+                .iter()
+                .filter(|path| stored.contains_key(*path))
+                .cloned()
+                .collect();
+            detected.unchanged = stored.len().saturating_sub(detected.changed.len() + detected.removed.len());
+            info!(
+                "Changes since {}: {} added, {} changed, {} removed",
+                config.since.as_deref().unwrap_or_default(),
+                detected.added.len(),
+                detected.changed.len(),
+                detected.removed.len()
+            );
+            Self::remove_stale(graph, vector_store.as_deref(), &detected).await?;
+            changes = Some(detected);
+        }
+
+        let progress = self.progress.clone();
+        if let Some(progress) = &progress {
+            let _ = progress.send(IndexProgress::Discovered { files: files.len() });
+        }
+
+        // Parse on a worker pool; a single writer thread batches inserts into the graph
+        let workers = config.workers.max(1);
+        info!("Parsing {} files on {} workers", files.len(), workers);
+        let graph = self.graph.clone();
+        let parse_signature = signature.clone();
+        let languages = self.languages.clone();
+        let parse_progress = progress.clone();
+        // Parsed files are embedded as they arrive rather than held until parsing ends
+        let (embed_tx, mut embed_rx) = match vector_store {
+            Some(_) => {
+                let (tx, rx) = tokio::sync::mpsc::channel(workers * 4);
+                (Some(tx), Some(rx))
+            }
+            None => (None, None),
+        };
+        // The pool's threads report their spans under the index run's
+        let span = tracing::Span::current();
+        let parsing = tokio::task::spawn_blocking(move || {
+            let _entered = span.enter();
+            let outcome = Self::parse_and_store(
+                &files,
+                &languages,
+                &parse_signature,
+                workers,
+                graph,
+                embed_tx,
+                parse_progress.as_ref(),
+            );
+            (files, outcome)
+        });
+
+        if let (Some(store), Some(embed_rx)) = (vector_store, embed_rx.as_mut()) {
+            let changed: HashSet<&str> = changes.iter().flat_map(|c| c.changed.iter().map(String::as_str)).collect();
+            let mut done = 0;
+            while let Some((relative_path, parsed)) = embed_rx.recv().await {
+                // The graph rows were just replaced; renamed symbols would otherwise keep their old vectors
+                if changed.contains(relative_path.as_str()) {
+                    if let Err(e) = store.delete_file(&relative_path).await {
+                        warn!("Failed to remove {} from vector store: {}", relative_path, e);
+                    }
+                }
+                Self::index_vectors(store, &signature, &relative_path, parsed).await;
+                done += 1;
+                if let Some(progress) = &progress {
+                    // Files without a parser never arrive, so the total only grows
+                    let total = done + embed_rx.len();
+                    let _ = progress.send(IndexProgress::Embedded { done, total });
+                }
+            }
+        }
+        let (files, outcome) = parsing.await?;
+        let outcome = outcome?;
+
+        // Files without a parser never get a graph row, so they'd show up as added on every run
+        if let (Some(changes), Some(graph)) = (changes.as_mut(), &self.graph) {
+            let stored = graph.file_fingerprints()?;
+            changes.added.retain(|path| stored.contains_key(path));
+        }
+        let mut files = files;
+        files.extend(unchanged_files);
+
+        // Lets `ask` tell when the index has fallen behind HEAD
+        if let (Some(graph), Some(head)) = (&self.graph, git::head_commit(&self.root_path)) {
+            graph.set_metadata(INDEXED_COMMIT_KEY, &head)?;
+        }
+
+        // Ownership is recomputed for every file: a CODEOWNERS edit reassigns files that didn't change
+        if let Some(graph) = &self.graph {
+            let indexed = graph.list_files()?;
+            graph.replace_file_owners(&ownership::file_owners(&self.root_path, &indexed))?;
+        }
+
+        // A component's props interface may live in a file this run didn't reparse
+        if let Some(graph) = &self.graph {
+            let components = graph.rebuild_component_catalog()?;
+            debug!("Cataloged the props of {} components", components);
+        }
+
         let duration = start.elapsed();
         info!(
             "Indexed {} files in {:.2}s",
             files.len(),
             duration.as_secs_f64()
         );
+        if let Some(progress) = &progress {
+            let _ = progress.send(IndexProgress::Finished {
+                files: files.len(),
+                symbols: outcome.symbols,
+                duration_ms: duration.as_millis(),
+            });
+        }
 
         Ok(IndexReport {
             total_files: files.len(),
@@ -257,26 +434,386 @@ impl CodebaseIndexer {
             total_size,
             duration_ms: duration.as_millis(),
             files,
+            symbols_indexed: outcome.symbols,
+            design_tokens_indexed: outcome.design_tokens,
+            routes_indexed: outcome.routes,
+            parse_errors: outcome.parse_errors,
+            changes,
+            skipped,
+        })
+    }
+
+    /// Drop removed files from the graph and then the vector store. Changed files
+    /// keep their rows and vectors until their reparse replaces both.
+    async fn remove_stale(graph: &KnowledgeGraph, vector_store: Option<&VectorStore>, changes: &IndexChanges) -> Result<()> {
+        for removed in &changes.removed {
+            graph.remove_file(removed)?;
+            if let Some(store) = vector_store {
+                if let Err(e) = store.delete_file(removed).await {
+                    warn!("Failed to remove {} from vector store: {}", removed, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Split walked files into those that need parsing and those whose stored
+    /// fingerprint still matches; whatever is left in the graph was deleted
+    fn detect_changes(
+        files: Vec<CodeFile>,
+        graph: &KnowledgeGraph,
+    ) -> Result<(Vec<CodeFile>, Vec<CodeFile>, IndexChanges)> {
+        let mut stored = graph.file_fingerprints()?;
+        let mut changes = IndexChanges::default();
+        let mut to_parse = Vec::new();
+        let mut unchanged = Vec::new();
+
+        for file in files {
+            match stored.remove(&file.relative_path) {
+                None => {
+                    changes.added.push(file.relative_path.clone());
+                    to_parse.push(file);
+                }
+                Some(fingerprint) => {
+                    // Content is already in memory, so compare hashes rather than trusting
+                    // mtimes, which have one-second resolution on some filesystems
+                    if fingerprint.content_hash == Some(content_hash(&file.content)) {
+                        unchanged.push(file);
+                    } else {
+                        changes.changed.push(file.relative_path.clone());
+                        to_parse.push(file);
+                    }
+                }
+            }
+        }
+
+        changes.removed = stored.into_keys().collect();
+        changes.removed.sort();
+        changes.unchanged = unchanged.len();
+        Ok((to_parse, unchanged, changes))
+    }
+
+    /// Re-parse one changed file for the watch pipeline, reusing its previous
+    /// syntax tree, and replace its rows in the knowledge graph
+    pub fn reparse_file(&mut self, path: &Path) -> Result<Option<ParsedFile>> {
+        let content = fs::read_to_string(path)?;
+        let signature = self.detect_project_signature()?.clone();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+        // Configs and manifests share source extensions but have dedicated parsers,
+        // and a registered plugin parser overrides the built-in grammar
+        let reparsed = if is_tailwind_config(file_name)
+            || is_manifest(file_name)
+            || is_container_config(file_name)
+            || self.languages.is_plugin_extension(extension)
+        {
+            None
+        } else {
+            self.incremental.parse(path, &content)?
+        };
+        let parsed = match reparsed {
+            Some(mut parsed) => {
+                Self::tag_with_signature(&mut parsed, &signature);
+                Some(parsed)
+            }
+            None => Self::parse_file_enhanced(&self.languages, &content, path, &signature)?,
+        };
+        let Some(parsed) = parsed else {
+            return Ok(None);
+        };
+
+        if let Some(graph) = &self.graph {
+            let relative_path = path
+                .strip_prefix(&self.root_path)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string();
+            let mut data = to_graph_data(parsed.clone(), &relative_path, &content);
+            data.index_errors = convert_diagnostics(&parsed.syntax_errors);
+            data.modified_at = fs::metadata(path).ok().and_then(|m| modified_at(&m));
+            graph.insert_files(&[(relative_path, data)])?;
+        }
+        Ok(Some(parsed))
+    }
+
+    /// Drop the cached syntax tree of a deleted file
+    pub fn forget_file(&mut self, path: &Path) {
+        self.incremental.forget(path);
+    }
+
+    /// Whether a full index would read `path`: not ignored by the root
+    /// `.gitignore`, the ignore patterns or the generated-file checks, not hidden
+    /// or under `.miow`, and of an indexed extension
+    pub fn is_indexable(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root_path) else {
+            return false;
+        };
+        let hidden = relative.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+        if hidden || Self::should_ignore_static(path, &self.config.ignore_patterns) {
+            return false;
+        }
+        if root_gitignore(&self.root_path).matched_path_or_any_parents(relative, false).is_ignore() {
+            return false;
+        }
+
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if !self.config.include_extensions.iter().any(|e| e == extension)
+            && !is_manifest(file_name)
+            && !is_container_config(file_name)
+            && !self.languages.is_plugin_extension(extension)
+        {
+            return false;
+        }
+        if self.config.skip_generated && file_guard::skip_by_name(file_name).is_some() {
+            return false;
+        }
+        fs::metadata(path).map(|m| m.len() <= self.config.max_file_size).unwrap_or(true)
+    }
+
+    /// Bring one file's graph rows and vectors in line with the disk for the
+    /// watch daemon: re-parse it when it exists, drop it when it was deleted
+    pub async fn sync_file(&mut self, path: &Path) -> Result<FileSync> {
+        let relative_path = path
+            .strip_prefix(&self.root_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+
+        if !path.exists() {
+            self.forget_file(path);
+            if let Some(graph) = &self.graph {
+                graph.remove_file(&relative_path)?;
+            }
+            if let Some(store) = &self.vector_store {
+                store.delete_file(&relative_path).await?;
+            }
+            return Ok(FileSync::Removed);
+        }
+        if !path.is_file() || !self.is_indexable(path) {
+            return Ok(FileSync::Skipped);
+        }
+
+        let Some(parsed) = self.reparse_file(path)? else {
+            return Ok(FileSync::Skipped);
+        };
+        let symbols = parsed.symbols.len();
+        let signature = self.detect_project_signature()?.clone();
+        if let Some(store) = &self.vector_store {
+            // Renamed symbols would otherwise keep their old vectors
+            store.delete_file(&relative_path).await?;
+            Self::index_vectors(store, &signature, &relative_path, parsed).await;
+        }
+        Ok(FileSync::Indexed { symbols })
+    }
+
+    fn parse_and_store(
+        files: &[CodeFile],
+        languages: &LanguageRegistry,
+        signature: &ProjectSignature,
+        workers: usize,
+        graph: Option<Arc<KnowledgeGraph>>,
+        embed: Option<EmbedSender>,
+        progress: Option<&ProgressSender>,
+    ) -> Result<ParseOutcome> {
+        let processed = AtomicUsize::new(0);
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(workers).build()?;
+        let (tx, rx) = mpsc::sync_channel::<ParseMessage>(workers * 4);
+        let parent = tracing::Span::current();
+
+        std::thread::scope(|scope| {
+            let writer_parent = parent.clone();
+            let writer = scope.spawn(move || -> Result<ParseOutcome> {
+                let _entered = writer_parent.enter();
+                let mut outcome = ParseOutcome::default();
+                let mut batch: Vec<(String, ParsedFileData)> = Vec::with_capacity(GRAPH_BATCH_SIZE);
+
+                for (index, parsed, diagnostics) in rx {
+                    let file = &files[index];
+                    if !diagnostics.is_empty() {
+                        outcome.parse_errors.push(FileParseErrors {
+                            relative_path: file.relative_path.clone(),
+                            failed: parsed.is_none(),
+                            diagnostics: diagnostics.clone(),
+                        });
+                    }
+
+                    let Some(parsed) = parsed else {
+                        if let Some(graph) = &graph {
+                            graph.record_index_errors(&file.relative_path, &convert_diagnostics(&diagnostics))?;
+                        }
+                        continue;
+                    };
+
+                    let (data, kept) = match (&graph, &embed) {
+                        (Some(_), Some(_)) => (
+                            Some(to_graph_data(parsed.clone(), &file.relative_path, &file.content)),
+                            Some(parsed),
+                        ),
+                        (Some(_), None) => (Some(to_graph_data(parsed, &file.relative_path, &file.content)), None),
+                        (None, _) => (None, Some(parsed)),
+                    };
+
+                    if let (Some(parsed), Some(embed)) = (kept, &embed) {
+                        // The embedder only goes away when the index run does
+                        let _ = embed.blocking_send((file.relative_path.clone(), parsed));
+                    }
+                    let (Some(graph), Some(mut data)) = (&graph, data) else {
+                        continue;
+                    };
+
+                    data.index_errors = convert_diagnostics(&diagnostics);
+                    data.modified_at = file.modified_at;
+                    outcome.symbols += data.symbols.len();
+                    outcome.design_tokens += data.design_tokens.len();
+                    outcome.routes += data.routes.len();
+                    batch.push((file.relative_path.clone(), data));
+                    if batch.len() >= GRAPH_BATCH_SIZE {
+                        graph.insert_files(&batch)?;
+                        batch.clear();
+                    }
+                }
+
+                if let (Some(graph), false) = (&graph, batch.is_empty()) {
+                    graph.insert_files(&batch)?;
+                }
+                outcome.parse_errors.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+                Ok(outcome)
+            });
+
+            pool.install(|| {
+                files.par_iter().enumerate().for_each_with(tx, |tx, (index, file)| {
+                    let _span = info_span!(parent: &parent, "parse_file", path = %file.relative_path, bytes = file.content.len())
+                        .entered();
+                    let result = Self::parse_file_enhanced(languages, &file.content, &file.path, signature);
+                    if let Some(progress) = progress {
+                        if let Err(e) = &result {
+                            let _ = progress.send(IndexProgress::Failed {
+                                path: file.relative_path.clone(),
+                                message: format!("{:#}", e),
+                            });
+                        }
+                        let done = processed.fetch_add(1, Ordering::Relaxed) + 1;
+                        let _ = progress.send(IndexProgress::Parsed {
+                            done,
+                            total: files.len(),
+                            path: file.relative_path.clone(),
+                        });
+                    }
+                    let message = match result {
+                        // Tree-sitter recovers from syntax errors, so report them alongside what did parse
+                        Ok(Some(mut parsed)) => {
+                            let diagnostics = std::mem::take(&mut parsed.syntax_errors);
+                            (index, Some(parsed), diagnostics)
+                        }
+                        Ok(None) => return,
+                        Err(e) => {
+                            debug!("Failed to parse {}: {}", file.relative_path, e);
+                            let failure = ParseDiagnostic {
+                                line: 1,
+                                column: 1,
+                                message: format!("{:#}", e),
+                            };
+                            (index, None, vec![failure])
+                        }
+                    };
+                    // The writer only stops early on a graph error, which it reports itself
+                    let _ = tx.send(message);
+                });
+            });
+
+            writer.join().expect("graph writer thread panicked")
         })
     }
 
-    fn parse_file_enhanced(&self, content: &str, extension: &str, signature: &ProjectSignature, _config: &IndexConfig) -> Result<ParsedFile> {
-        let mut parsed = match extension {
-            "ts" => parse_typescript(content, false),
-            "tsx" => parse_typescript(content, true),
-            "rs" => parse_rust(content),
-            "py" => parse_python(content),
-            _ => anyhow::bail!("Unsupported extension: {}", extension),
-        }?;
-
-        // Enhance parsed data with signature context
-        // For example, tag symbols based on detected libraries
+    /// Insert a file's symbols and validation schemas into the vector store
+    async fn index_vectors(store: &VectorStore, signature: &ProjectSignature, relative_path: &str, parsed: ParsedFile) {
+        for symbol in parsed.symbols {
+            let mut enhanced_metadata = symbol.metadata.clone();
+
+            // Tag with UI library if applicable
+            if let Some(ui_lib) = &signature.ui_library {
+                enhanced_metadata.tags.push(format!("ui:{}", ui_lib.to_lowercase()));
+            }
+
+            // Tag with validation library
+            if let Some(val_lib) = &signature.validation_library {
+                enhanced_metadata.tags.push(format!("validation:{}", val_lib.to_lowercase()));
+            }
+
+            // Prioritize common UI components
+            if Self::is_common_ui_component(&symbol.name) {
+                enhanced_metadata.tags.push("common-ui".to_string());
+                enhanced_metadata.priority = Some(1.0); // High priority
+            }
+
+            // Tag Zod schemas and form-related symbols
+            if symbol.name.to_lowercase().contains("schema") ||
+               symbol.content.contains("z.object") ||
+               symbol.name.to_lowercase().contains("form") ||
+               symbol.name.to_lowercase().contains("input") ||
+               symbol.name.to_lowercase().contains("button") {
+                enhanced_metadata.tags.push("form-validation".to_string());
+            }
+
+            let symbol_vector = SymbolVector {
+                id: format!("{}:{}", relative_path, symbol.name),
+                name: symbol.name,
+                kind: format!("{:?}", symbol.kind),
+                content: symbol.content,
+                file_path: relative_path.to_string(),
+                metadata: serde_json::to_string(&enhanced_metadata).unwrap_or_default(),
+            };
+
+            if let Err(e) = store.insert_symbol(&symbol_vector).await {
+                warn!(
+                    "Failed to insert symbol {} into vector store: {}",
+                    symbol_vector.name, e
+                );
+            }
+        }
+
+        // Index validation schemas separately for better search
+        for schema in &parsed.schemas {
+            let schema_vector = SymbolVector {
+                id: format!("schema:{}", schema.name),
+                name: format!("Validation Schema: {}", schema.name),
+                kind: "validation-schema".to_string(),
+                content: schema.definition.clone(),
+                file_path: relative_path.to_string(),
+                metadata: serde_json::to_string(schema).unwrap_or_default(),
+            };
+            if let Err(e) = store.insert_symbol(&schema_vector).await {
+                warn!("Failed to insert schema {}: {}", schema.name, e);
+            }
+        }
+    }
+
+    /// Parse a file with the parser for its extension; `None` for files no parser handles
+    fn parse_file_enhanced(
+        languages: &LanguageRegistry,
+        content: &str,
+        path: &Path,
+        signature: &ProjectSignature,
+    ) -> Result<Option<ParsedFile>> {
+        let Some(mut parsed) = languages.parse_file(path, content)? else {
+            return Ok(None);
+        };
+
+        Self::tag_with_signature(&mut parsed, signature);
+        Ok(Some(parsed))
+    }
+
+    /// Enhance parsed data with signature context,
+    /// for example tag symbols based on detected libraries
+    fn tag_with_signature(parsed: &mut ParsedFile, signature: &ProjectSignature) {
         for symbol in &mut parsed.symbols {
             // If Zod detected, tag schema-related symbols
-            if signature.validation_library.as_ref().map_or(false, |v| v == "Zod") {
-                if symbol.content.contains("z.") || symbol.name.to_lowercase().contains("schema") {
-                    symbol.metadata.tags.push("zod-schema".to_string());
-                }
+            if signature.validation_library.as_deref() == Some("Zod")
+                && (symbol.content.contains("z.") || symbol.name.to_lowercase().contains("schema"))
+            {
+                symbol.metadata.tags.push("zod-schema".to_string());
             }
 
             // Tag common UI components regardless of library
@@ -294,8 +831,6 @@ impl CodebaseIndexer {
                 }
             }
         }
-
-        Ok(parsed)
     }
 
     fn is_common_ui_component(name: &str) -> bool {
@@ -303,10 +838,6 @@ impl CodebaseIndexer {
         common_ui.iter().any(|c| name.contains(c))
     }
 
-    fn should_ignore(&self, path: &std::path::Path) -> bool {
-        Self::should_ignore_static(path, &self.config.ignore_patterns)
-    }
-
     fn should_ignore_static(path: &std::path::Path, ignore_patterns: &[String]) -> bool {
         let path_str = path.to_string_lossy();
 
@@ -320,6 +851,25 @@ impl CodebaseIndexer {
     }
 }
 
+/// Matcher for the `.gitignore` and `.git/info/exclude` at the project root
+fn root_gitignore(root: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for file in [root.join(".gitignore"), root.join(".git").join("info").join("exclude")] {
+        if file.exists() {
+            if let Some(err) = builder.add(&file) {
+                debug!("Could not read {}: {}", file.display(), err);
+            }
+        }
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+fn modified_at(metadata: &fs::Metadata) -> Option<i64> {
+    let modified = metadata.modified().ok()?;
+    let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(since_epoch.as_secs() as i64)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -342,4 +892,140 @@ mod tests {
         let signature = indexer.detect_project_signature().unwrap();
         assert!(!signature.language.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_parallel_index_writes_graph() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "pub fn add(a: i32, b: i32) -> i32 { a + b }\n").unwrap();
+        fs::write(dir.join("src/routes.ts"), "router.get('/health', health);\nexport function health() {}\n").unwrap();
+
+        let graph = Arc::new(KnowledgeGraph::in_memory().unwrap());
+        let config = IndexConfig { workers: 2, ..IndexConfig::default() };
+        let mut indexer = CodebaseIndexer::new(dir.to_path_buf())
+            .unwrap()
+            .with_config(config)
+            .with_graph(graph.clone());
+        let report = indexer.index().await.unwrap();
+
+        assert_eq!(report.total_files, 2);
+        assert_eq!(report.routes_indexed, 1);
+        assert_eq!(graph.count_files().unwrap(), 2);
+        assert!(!graph.search_symbols("add").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_index_stats_report_file_lengths() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        // The symbol ends long before the file does
+        fs::write(dir.join("lib.rs"), format!("pub fn first() {{}}\n{}", "// note\n".repeat(40))).unwrap();
+
+        let graph = Arc::new(KnowledgeGraph::in_memory().unwrap());
+        let mut indexer = CodebaseIndexer::new(dir.to_path_buf()).unwrap().with_graph(graph.clone());
+        indexer.index().await.unwrap();
+
+        let stats = graph.index_stats(5).unwrap();
+        assert_eq!(stats.largest_files.len(), 1);
+        assert_eq!(stats.largest_files[0].lines, 41);
+        assert_eq!(stats.largest_files[0].symbols, 1);
+    }
+
+    #[tokio::test]
+    async fn test_broken_file_keeps_symbols_and_records_errors() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("lib.rs"), "pub fn good() -> u8 { 1 }\n\nfn broken( {\n    let x = ;\n").unwrap();
+
+        let graph = Arc::new(KnowledgeGraph::in_memory().unwrap());
+        let mut indexer = CodebaseIndexer::new(dir.to_path_buf()).unwrap().with_graph(graph.clone());
+        let report = indexer.index().await.unwrap();
+
+        assert_eq!(report.parse_errors.len(), 1);
+        assert!(!report.parse_errors[0].failed);
+        assert!(!graph.search_symbols("good").unwrap().is_empty());
+        assert!(!graph.list_index_errors().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_file_follows_edits_and_deletes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join(".gitignore"), "generated/\n").unwrap();
+        fs::write(dir.join("src/login.ts"), "export function login() {}\n").unwrap();
+
+        let graph = Arc::new(KnowledgeGraph::in_memory().unwrap());
+        let mut indexer = CodebaseIndexer::new(dir.to_path_buf()).unwrap().with_graph(graph.clone());
+        indexer.index().await.unwrap();
+
+        fs::write(dir.join("src/login.ts"), "export function logout() {}\n").unwrap();
+        let edited = indexer.sync_file(&dir.join("src/login.ts")).await.unwrap();
+        assert_eq!(edited, FileSync::Indexed { symbols: 1 });
+        assert!(graph.search_symbols("login").unwrap().is_empty());
+        assert!(!graph.search_symbols("logout").unwrap().is_empty());
+
+        fs::create_dir_all(dir.join("generated")).unwrap();
+        fs::write(dir.join("generated/api.ts"), "export function api() {}\n").unwrap();
+        assert_eq!(indexer.sync_file(&dir.join("generated/api.ts")).await.unwrap(), FileSync::Skipped);
+        fs::write(dir.join("README.md"), "# Login\n").unwrap();
+        assert_eq!(indexer.sync_file(&dir.join("README.md")).await.unwrap(), FileSync::Skipped);
+
+        fs::remove_file(dir.join("src/login.ts")).unwrap();
+        assert_eq!(indexer.sync_file(&dir.join("src/login.ts")).await.unwrap(), FileSync::Removed);
+        assert_eq!(graph.count_files().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_reindex_detects_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("a.rs"), "pub fn alpha() {}\n").unwrap();
+        fs::write(dir.join("b.rs"), "pub fn beta() {}\n").unwrap();
+        fs::write(dir.join("c.rs"), "pub fn gamma() {}\n").unwrap();
+
+        let graph = Arc::new(KnowledgeGraph::in_memory().unwrap());
+        CodebaseIndexer::new(dir.to_path_buf()).unwrap().with_graph(graph.clone()).index().await.unwrap();
+
+        fs::write(dir.join("a.rs"), "pub fn alpha_renamed() {}\n").unwrap();
+        fs::remove_file(dir.join("b.rs")).unwrap();
+        fs::write(dir.join("d.rs"), "pub fn delta() {}\n").unwrap();
+
+        let config = IndexConfig { incremental: true, ..IndexConfig::default() };
+        let mut indexer = CodebaseIndexer::new(dir.to_path_buf()).unwrap().with_config(config).with_graph(graph.clone());
+        let report = indexer.index().await.unwrap();
+
+        let changes = report.changes.unwrap();
+        assert_eq!(changes.added, vec!["d.rs"]);
+        assert_eq!(changes.changed, vec!["a.rs"]);
+        assert_eq!(changes.removed, vec!["b.rs"]);
+        assert_eq!(changes.unchanged, 1);
+        assert_eq!(graph.count_files().unwrap(), 3);
+        assert!(graph.find_symbols_by_name("alpha").unwrap().is_empty());
+        assert!(graph.find_symbols_by_name("beta").unwrap().is_empty());
+        assert_eq!(graph.find_symbols_by_name("alpha_renamed").unwrap().len(), 1);
+        assert_eq!(graph.find_symbols_by_name("gamma").unwrap().len(), 1);
+        assert_eq!(graph.find_symbols_by_name("delta").unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_progress_events() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("a.rs"), "pub fn alpha() {}\n").unwrap();
+        fs::write(dir.join("b.py"), "def beta():\n    pass\n").unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let report = CodebaseIndexer::new(dir.to_path_buf()).unwrap().with_progress(tx).index().await.unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        assert!(matches!(events.first(), Some(IndexProgress::Discovered { files: 2 })));
+        assert!(matches!(events.last(), Some(IndexProgress::Finished { files: 2, .. })));
+        let parsed = events.iter().filter(|e| matches!(e, IndexProgress::Parsed { .. })).count();
+        assert_eq!(parsed, report.total_files);
+    }
 }
@@ -164,7 +164,7 @@ impl IntelligentSignatureDetector {
         
         // Sort by count
         let mut sorted: Vec<_> = counts.into_iter().collect();
-        sorted.sort_by(|a, b| b.1.cmp(&a.1));
+        sorted.sort_by_key(|entry| std::cmp::Reverse(entry.1));
         
         Ok(sorted.into_iter().collect())
     }
@@ -222,7 +222,16 @@ Respond ONLY with valid JSON."#,
         project_root: &Path,
     ) -> Result<ProjectSignature> {
         // Parse dependencies from actual files for accuracy
-        let dependencies = self.parse_dependencies(project_root, &analysis.package_manager)?;
+        let manifest = ProjectSignature::read_manifest(project_root, &analysis.package_manager);
+        let (dependencies, dev_dependencies, scripts, workspace_members) = match manifest {
+            Some(m) => (
+                m.dependencies.into_iter().collect(),
+                m.dev_dependencies.into_iter().collect(),
+                m.scripts.into_iter().collect(),
+                m.workspace_members,
+            ),
+            None => Default::default(),
+        };
         
         Ok(ProjectSignature {
             language: analysis.language,
@@ -232,64 +241,15 @@ Respond ONLY with valid JSON."#,
             validation_library: analysis.validation_library,
             auth_library: analysis.auth_library,
             styling: analysis.styling,
-            dependencies: dependencies.0,
-            dev_dependencies: dependencies.1,
+            dependencies,
+            dev_dependencies,
             features: analysis.features,
+            scripts,
+            workspace_members,
+            containers: ProjectSignature::detect_containers(project_root),
         })
     }
     
-    fn parse_dependencies(
-        &self,
-        project_root: &Path,
-        package_manager: &str,
-    ) -> Result<(HashMap<String, String>, HashMap<String, String>)> {
-        let mut deps = HashMap::new();
-        let mut dev_deps = HashMap::new();
-        
-        match package_manager {
-            "npm" | "yarn" | "pnpm" => {
-                if let Ok(content) = fs::read_to_string(project_root.join("package.json")) {
-                    if let Ok(package_json) = serde_json::from_str::<serde_json::Value>(&content) {
-                        if let Some(dependencies) = package_json["dependencies"].as_object() {
-                            for (name, version) in dependencies {
-                                deps.insert(
-                                    name.clone(),
-                                    version.as_str().unwrap_or("").to_string(),
-                                );
-                            }
-                        }
-                        if let Some(dev_dependencies) = package_json["devDependencies"].as_object() {
-                            for (name, version) in dev_dependencies {
-                                dev_deps.insert(
-                                    name.clone(),
-                                    version.as_str().unwrap_or("").to_string(),
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-            "cargo" => {
-                if let Ok(content) = fs::read_to_string(project_root.join("Cargo.toml")) {
-                    // Simple parsing - could use toml crate
-                    for line in content.lines() {
-                        if line.contains(" = ") && !line.trim().starts_with('#') {
-                            let parts: Vec<&str> = line.split(" = ").collect();
-                            if parts.len() == 2 {
-                                let name = parts[0].trim().trim_matches('"');
-                                let version = parts[1].trim().trim_matches('"');
-                                deps.insert(name.to_string(), version.to_string());
-                            }
-                        }
-                    }
-                }
-            }
-            _ => {}
-        }
-        
-        Ok((deps, dev_deps))
-    }
-    
     /// Clear cache
     pub fn clear_cache(&mut self) {
         self.cache.clear();
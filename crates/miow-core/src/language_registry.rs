@@ -1,8 +1,35 @@
 use anyhow::Result;
+use miow_parsers::{
+    is_container_config, is_manifest, is_tailwind_config, parse_c, parse_container_config, parse_cpp, parse_java,
+    parse_manifest, parse_php, parse_prisma, parse_python, parse_ruby, parse_rust, parse_sql, parse_stylesheet,
+    parse_tailwind_config, parse_terraform, parse_typescript, ExternalGrammar, ParsedFile,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Extra languages a project declares, relative to its root
+pub const PROJECT_LANGUAGES_FILE: &str = ".miow/languages.json";
+
+/// Extra languages the user declares for every project, relative to `user_config_dir()`
+pub const USER_LANGUAGES_FILE: &str = "languages.json";
+
+/// What a languages file may load
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageTrust {
+    /// Native grammar libraries as well as Wasm plugins: the user's own
+    /// config, or a project the user opted into trusting
+    Native,
+    /// Only sandboxed Wasm plugins: a project's file arrives with whatever was
+    /// checked out, and a native library runs with the user's privileges
+    WasmOnly,
+}
+
+/// Turns the contents of a file into symbols; registered per file extension
+pub type ParseFn = Arc<dyn Fn(&str) -> Result<ParsedFile> + Send + Sync>;
 
 /// Dynamic language configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +47,8 @@ pub enum ParserType {
     TreeSitter { grammar: String },
     Regex { patterns: Vec<String> },
     Custom { handler: String },
+    /// A parser plugin compiled to WebAssembly; needs the `wasm` feature
+    Wasm { module: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +69,10 @@ pub struct PackageManager {
 /// Dynamic language registry that can be extended via configuration
 pub struct LanguageRegistry {
     languages: HashMap<String, LanguageConfig>,
+    /// Parser for each file extension (without the dot)
+    parsers: HashMap<String, ParseFn>,
+    /// Extensions registered on top of the built-in parsers
+    plugin_extensions: HashSet<String>,
 }
 
 impl LanguageRegistry {
@@ -47,14 +80,50 @@ impl LanguageRegistry {
     pub fn new() -> Self {
         let mut registry = Self {
             languages: HashMap::new(),
+            parsers: HashMap::new(),
+            plugin_extensions: HashSet::new(),
         };
         
         // Register default languages
         registry.register_defaults();
+        registry.register_default_parsers();
         
         registry
     }
     
+    /// Default languages plus those declared in the user's `languages.json` and
+    /// the project's `.miow/languages.json`; the project may only add Wasm plugins
+    pub fn for_project(project_root: &Path) -> Result<Self> {
+        Self::load_for_project(project_root, LanguageTrust::WasmOnly)
+    }
+
+    /// Like `for_project`, but also loads native grammar libraries the project declares
+    pub fn for_trusted_project(project_root: &Path) -> Result<Self> {
+        Self::load_for_project(project_root, LanguageTrust::Native)
+    }
+
+    /// Default languages plus those declared in the user's `languages.json`, for
+    /// projects whose own languages file must not be read
+    pub fn for_user() -> Result<Self> {
+        let mut registry = Self::new();
+        if let Some(dir) = crate::user_config_dir() {
+            let user_file = dir.join(USER_LANGUAGES_FILE);
+            if user_file.exists() {
+                registry.load_from_file(&user_file, LanguageTrust::Native)?;
+            }
+        }
+        Ok(registry)
+    }
+
+    fn load_for_project(project_root: &Path, project_trust: LanguageTrust) -> Result<Self> {
+        let mut registry = Self::for_user()?;
+        let languages_file = project_root.join(PROJECT_LANGUAGES_FILE);
+        if languages_file.exists() {
+            registry.load_from_file(&languages_file, project_trust)?;
+        }
+        Ok(registry)
+    }
+
     /// Register default languages
     fn register_defaults(&mut self) {
         // TypeScript/JavaScript
@@ -195,8 +264,236 @@ impl LanguageRegistry {
                 "Prefer list comprehensions over loops".to_string(),
             ],
         });
+
+        // Java
+        self.register(LanguageConfig {
+            name: "java".to_string(),
+            extensions: vec!["java".to_string()],
+            parser_type: ParserType::TreeSitter {
+                grammar: "tree-sitter-java".to_string(),
+            },
+            framework_indicators: vec![
+                FrameworkIndicator {
+                    name: "Spring Boot".to_string(),
+                    files: vec![],
+                    dependencies: vec!["spring-boot".to_string()],
+                    directory_patterns: vec!["src/main/java".to_string()],
+                },
+                FrameworkIndicator {
+                    name: "Quarkus".to_string(),
+                    files: vec![],
+                    dependencies: vec!["quarkus".to_string()],
+                    directory_patterns: vec![],
+                },
+            ],
+            package_managers: vec![
+                PackageManager {
+                    name: "maven".to_string(),
+                    manifest_file: "pom.xml".to_string(),
+                    lock_file: None,
+                },
+                PackageManager {
+                    name: "gradle".to_string(),
+                    manifest_file: "build.gradle".to_string(),
+                    lock_file: Some("gradle.lockfile".to_string()),
+                },
+            ],
+            best_practices: vec![
+                "Prefer constructor injection over field injection".to_string(),
+                "Keep controllers thin and put business logic in services".to_string(),
+                "Use Optional instead of returning null".to_string(),
+                "Follow naming conventions (camelCase for methods, PascalCase for classes)".to_string(),
+            ],
+        });
+
+        // Ruby
+        self.register(LanguageConfig {
+            name: "ruby".to_string(),
+            extensions: vec!["rb".to_string(), "rake".to_string()],
+            parser_type: ParserType::Custom {
+                handler: "miow_parsers::RubyParser".to_string(),
+            },
+            framework_indicators: vec![
+                FrameworkIndicator {
+                    name: "Rails".to_string(),
+                    files: vec!["config/routes.rb".to_string(), "bin/rails".to_string()],
+                    dependencies: vec!["rails".to_string()],
+                    directory_patterns: vec!["app/models".to_string(), "app/controllers".to_string()],
+                },
+                FrameworkIndicator {
+                    name: "Sinatra".to_string(),
+                    files: vec![],
+                    dependencies: vec!["sinatra".to_string()],
+                    directory_patterns: vec![],
+                },
+            ],
+            package_managers: vec![PackageManager {
+                name: "bundler".to_string(),
+                manifest_file: "Gemfile".to_string(),
+                lock_file: Some("Gemfile.lock".to_string()),
+            }],
+            best_practices: vec![
+                "Keep controllers skinny and models focused".to_string(),
+                "Prefer scopes and associations over hand-written SQL".to_string(),
+                "Validate data in models, not only in forms".to_string(),
+                "Follow naming conventions (snake_case for methods and files, CamelCase for classes)".to_string(),
+            ],
+        });
+
+        // PHP
+        self.register(LanguageConfig {
+            name: "php".to_string(),
+            extensions: vec!["php".to_string()],
+            parser_type: ParserType::Custom {
+                handler: "miow_parsers::PhpParser".to_string(),
+            },
+            framework_indicators: vec![
+                FrameworkIndicator {
+                    name: "Laravel".to_string(),
+                    files: vec!["artisan".to_string()],
+                    dependencies: vec!["laravel/framework".to_string()],
+                    directory_patterns: vec!["app/Models".to_string(), "app/Http/Controllers".to_string()],
+                },
+                FrameworkIndicator {
+                    name: "Symfony".to_string(),
+                    files: vec!["bin/console".to_string()],
+                    dependencies: vec!["symfony/framework-bundle".to_string()],
+                    directory_patterns: vec![],
+                },
+            ],
+            package_managers: vec![PackageManager {
+                name: "composer".to_string(),
+                manifest_file: "composer.json".to_string(),
+                lock_file: Some("composer.lock".to_string()),
+            }],
+            best_practices: vec![
+                "Validate input with form requests before it reaches controllers".to_string(),
+                "Prefer Eloquent relationships and scopes over raw queries".to_string(),
+                "Guard mass assignment with $fillable".to_string(),
+                "Follow PSR-12 (camelCase for methods, PascalCase for classes)".to_string(),
+            ],
+        });
+
+        // C / C++
+        for (name, extensions, grammar) in [
+            ("c", vec!["c", "h"], "tree-sitter-c"),
+            ("cpp", vec!["cpp", "cc", "cxx", "hpp", "hh", "hxx"], "tree-sitter-cpp"),
+        ] {
+            self.register(LanguageConfig {
+                name: name.to_string(),
+                extensions: extensions.into_iter().map(String::from).collect(),
+                parser_type: ParserType::TreeSitter {
+                    grammar: grammar.to_string(),
+                },
+                framework_indicators: vec![],
+                package_managers: vec![PackageManager {
+                    name: "cmake".to_string(),
+                    manifest_file: "CMakeLists.txt".to_string(),
+                    lock_file: None,
+                }],
+                best_practices: vec![
+                    "Check every allocation and return value".to_string(),
+                    "Keep headers minimal and use include guards".to_string(),
+                    "Prefer const-correct signatures".to_string(),
+                ],
+            });
+        }
     }
     
+    fn register_default_parsers(&mut self) {
+        let builtin: [(&[&str], ParseFn); 14] = [
+            (&["ts"], Arc::new(|content: &str| parse_typescript(content, false))),
+            (&["tsx"], Arc::new(|content: &str| parse_typescript(content, true))),
+            (&["rs"], Arc::new(parse_rust)),
+            (&["py"], Arc::new(parse_python)),
+            (&["java"], Arc::new(parse_java)),
+            (&["rb", "rake"], Arc::new(parse_ruby)),
+            (&["php"], Arc::new(parse_php)),
+            (&["c", "h"], Arc::new(parse_c)),
+            (&["cpp", "cc", "cxx", "hpp", "hh", "hxx"], Arc::new(parse_cpp)),
+            (&["css"], Arc::new(|content: &str| parse_stylesheet(content, "css"))),
+            (&["scss", "sass"], Arc::new(|content: &str| parse_stylesheet(content, "scss"))),
+            (&["sql"], Arc::new(parse_sql)),
+            (&["prisma"], Arc::new(parse_prisma)),
+            (&["tf", "tfvars"], Arc::new(parse_terraform)),
+        ];
+        for (extensions, parser) in builtin {
+            for extension in extensions {
+                self.parsers.insert(extension.to_string(), parser.clone());
+            }
+        }
+    }
+
+    /// Register a parser for one or more file extensions, replacing any
+    /// existing parser for them. Files with these extensions are indexed
+    /// even when they aren't in `IndexConfig::include_extensions`.
+    pub fn register_parser<F>(&mut self, extensions: &[&str], parser: F)
+    where
+        F: Fn(&str) -> Result<ParsedFile> + Send + Sync + 'static,
+    {
+        let parser: ParseFn = Arc::new(parser);
+        for extension in extensions {
+            let extension = extension.trim_start_matches('.').to_string();
+            self.plugin_extensions.insert(extension.clone());
+            self.parsers.insert(extension, parser.clone());
+        }
+    }
+
+    /// Parse files with these extensions using a tree-sitter grammar
+    pub fn register_grammar(&mut self, grammar: ExternalGrammar, extensions: &[&str]) {
+        self.register_parser(extensions, move |content| grammar.parse(content));
+    }
+
+    /// Load a compiled tree-sitter grammar (`.so` / `.dylib` / `.dll`) and
+    /// parse files with these extensions with it
+    pub fn load_grammar_library(&mut self, name: &str, extensions: &[&str], library_path: &Path) -> Result<()> {
+        let grammar = ExternalGrammar::load(name, library_path)?;
+        self.register_grammar(grammar, extensions);
+        Ok(())
+    }
+
+    /// Load a parser plugin compiled to WebAssembly and parse files with
+    /// these extensions with it
+    #[cfg(feature = "wasm")]
+    pub fn load_wasm_parser(&mut self, name: &str, extensions: &[&str], module_path: &Path) -> Result<()> {
+        let parser = miow_parsers::WasmParser::load(name, module_path)?;
+        self.register_parser(extensions, move |content| parser.parse(content));
+        Ok(())
+    }
+
+    /// Parser registered for a file extension
+    pub fn parser_for(&self, extension: &str) -> Option<&ParseFn> {
+        self.parsers.get(extension)
+    }
+
+    /// Whether the extension was registered at runtime rather than built in
+    pub fn is_plugin_extension(&self, extension: &str) -> bool {
+        self.plugin_extensions.contains(extension)
+    }
+
+    /// Parse a file with the parser for its name or extension. Returns `None`
+    /// when no parser handles it.
+    pub fn parse_file(&self, path: &Path, content: &str) -> Result<Option<ParsedFile>> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+        // Configs and manifests share extensions with source files but have dedicated parsers
+        if is_tailwind_config(file_name) {
+            return parse_tailwind_config(content).map(Some);
+        }
+        if is_manifest(file_name) {
+            return parse_manifest(path, content).map(Some);
+        }
+        if is_container_config(file_name) {
+            return parse_container_config(file_name, content).map(Some);
+        }
+
+        match self.parser_for(extension) {
+            Some(parser) => parser(content).map(Some),
+            None => Ok(None),
+        }
+    }
+
     /// Register a new language
     pub fn register(&mut self, config: LanguageConfig) {
         self.languages.insert(config.name.clone(), config);
@@ -233,12 +530,46 @@ impl LanguageRegistry {
         self.languages.values().collect()
     }
     
-    /// Load additional languages from JSON file
-    pub fn load_from_file(&mut self, path: &Path) -> Result<()> {
+    /// Load additional languages from JSON file. A `TreeSitter` grammar that
+    /// names a library file, or a `Wasm` module (both relative to the JSON
+    /// file), is loaded and used to parse the language's extensions. Without
+    /// `LanguageTrust::Native`, languages needing a library are skipped.
+    pub fn load_from_file(&mut self, path: &Path, trust: LanguageTrust) -> Result<()> {
         let content = fs::read_to_string(path)?;
         let configs: Vec<LanguageConfig> = serde_json::from_str(&content)?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
         
         for config in configs {
+            if let ParserType::TreeSitter { grammar } = &config.parser_type {
+                let library = base_dir.join(grammar);
+                let is_library = matches!(
+                    library.extension().and_then(|e| e.to_str()),
+                    Some("so" | "dylib" | "dll")
+                );
+                if is_library && trust != LanguageTrust::Native {
+                    warn!(
+                        "Not loading the native grammar {} declared in {}; native grammars load only from the user's \
+                         {} or with --trust-project-languages",
+                        library.display(),
+                        path.display(),
+                        USER_LANGUAGES_FILE
+                    );
+                    continue;
+                }
+                if is_library {
+                    let extensions: Vec<&str> = config.extensions.iter().map(String::as_str).collect();
+                    self.load_grammar_library(&config.name, &extensions, &library)?;
+                }
+            }
+            if let ParserType::Wasm { module } = &config.parser_type {
+                #[cfg(feature = "wasm")]
+                {
+                    let extensions: Vec<&str> = config.extensions.iter().map(String::as_str).collect();
+                    self.load_wasm_parser(&config.name, &extensions, &base_dir.join(module))?;
+                }
+                #[cfg(not(feature = "wasm"))]
+                anyhow::bail!("{} needs the parser plugin {}, but miow was built without the wasm feature", config.name, module);
+            }
             self.register(config);
         }
         
@@ -310,6 +641,78 @@ mod tests {
         assert_eq!(rs_lang.unwrap().name, "rust");
     }
     
+    #[test]
+    fn test_register_parser_for_new_extension() {
+        let mut registry = LanguageRegistry::new();
+        assert!(registry.parse_file(Path::new("main.zig"), "pub fn main() void {}").unwrap().is_none());
+
+        registry.register_parser(&[".zig"], |content| {
+            let mut parsed = parse_rust(content)?;
+            parsed.language = "zig".to_string();
+            Ok(parsed)
+        });
+        assert!(registry.is_plugin_extension("zig"));
+        assert!(!registry.is_plugin_extension("rs"));
+
+        let parsed = registry.parse_file(Path::new("main.zig"), "pub fn main() {}").unwrap().unwrap();
+        assert_eq!(parsed.language, "zig");
+        assert!(registry.parse_file(Path::new("src/lib.rs"), "fn a() {}").unwrap().is_some());
+    }
+    
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_wasm_plugin_from_languages_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir_all(dir.join("plugins")).unwrap();
+        // Answers every file with one `main` function
+        let output = r#"{"symbols":[{"name":"main","kind":"Function","range":{"start_line":1,"end_line":1}}]}"#;
+        let module = format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 0) "{}")
+                (func (export "miow_abi_version") (result i32) i32.const 1)
+                (func (export "alloc") (param i32) (result i32) i32.const 1024)
+                (func (export "parse") (param i32 i32) (result i64) i64.const {}))"#,
+            output.replace('"', "\\22"),
+            output.len()
+        );
+        fs::write(dir.join("plugins/toy.wat"), module).unwrap();
+        fs::write(
+            dir.join("languages.json"),
+            r#"[{"name": "toy", "extensions": ["toy"], "parser_type": {"Wasm": {"module": "plugins/toy.wat"}},
+                "framework_indicators": [], "package_managers": [], "best_practices": []}]"#,
+        )
+        .unwrap();
+
+        let mut registry = LanguageRegistry::new();
+        registry.load_from_file(&dir.join("languages.json"), LanguageTrust::WasmOnly).unwrap();
+        let parsed = registry.parse_file(Path::new("src/app.toy"), "main = 1").unwrap().unwrap();
+        assert_eq!(parsed.language, "toy");
+        assert_eq!(parsed.symbols[0].name, "main");
+        assert!(registry.is_plugin_extension("toy"));
+    }
+
+    #[test]
+    fn test_project_file_cannot_load_native_grammars() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let languages = temp_dir.path().join("languages.json");
+        fs::write(
+            &languages,
+            r#"[{"name": "toy", "extensions": ["toy"], "parser_type": {"TreeSitter": {"grammar": "libtoy.so"}},
+                "framework_indicators": [], "package_managers": [], "best_practices": []}]"#,
+        )
+        .unwrap();
+
+        let mut registry = LanguageRegistry::new();
+        registry.load_from_file(&languages, LanguageTrust::WasmOnly).unwrap();
+        assert!(!registry.is_plugin_extension("toy"));
+        assert!(registry.get("toy").is_none());
+
+        // Trusted, the library is loaded, and this one doesn't exist
+        assert!(LanguageRegistry::new().load_from_file(&languages, LanguageTrust::Native).is_err());
+    }
+
     #[test]
     fn test_get_best_practices() {
         let registry = LanguageRegistry::new();
@@ -1,18 +1,54 @@
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+pub mod file_guard;
+pub mod git;
+pub mod graph_data;
 pub mod indexer;
 pub mod types;
 pub mod project_signature;
 pub mod intelligent_detector;
 pub mod language_registry;
+pub mod ownership;
 
-pub use indexer::CodebaseIndexer;
+pub use graph_data::to_graph_data;
+pub use indexer::{CodebaseIndexer, ProgressSender};
 pub use types::*;
 pub use project_signature::ProjectSignature;
 pub use intelligent_detector::IntelligentSignatureDetector;
-pub use language_registry::{LanguageRegistry, LanguageConfig};
+pub use language_registry::{LanguageRegistry, LanguageConfig, LanguageTrust, ParseFn};
+
+/// Environment variable overriding where user-level settings live
+pub const USER_CONFIG_DIR_ENV: &str = "MIOW_CONFIG_DIR";
+
+/// Directory holding the user's own settings, which a checked-out project
+/// can't change: `$MIOW_CONFIG_DIR`, else `miow` in the platform's config directory
+/// (`~/.config/miow`, `~/Library/Application Support/miow`, `%APPDATA%\miow`), kept
+/// apart from the `.miow` a project rooted at `$HOME` would use
+pub fn user_config_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(USER_CONFIG_DIR_ENV) {
+        return Some(PathBuf::from(dir));
+    }
+    platform_config_dir().map(|dir| dir.join("miow"))
+}
+
+#[cfg(windows)]
+fn platform_config_dir() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn platform_config_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn platform_config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
 
 /// Main entry point for indexing a codebase
 pub async fn index_codebase(path: PathBuf) -> Result<IndexReport> {
@@ -29,6 +65,55 @@ pub async fn index_codebase_with_vector(
     indexer.index().await
 }
 
+/// Index a codebase and write every parsed file into the knowledge graph,
+/// optionally also embedding symbols into the vector store
+pub async fn index_codebase_into_graph(
+    path: PathBuf,
+    graph: std::sync::Arc<miow_graph::KnowledgeGraph>,
+    vector_store: Option<std::sync::Arc<miow_vector::VectorStore>>,
+) -> Result<IndexReport> {
+    index_into_graph(path, graph, vector_store, IndexConfig::default(), None, None).await
+}
+
+/// Reindex only the files git reports as changed since `rev` (committed,
+/// uncommitted or untracked) and remove the ones deleted since
+pub async fn reindex_since_into_graph(
+    path: PathBuf,
+    rev: &str,
+    graph: std::sync::Arc<miow_graph::KnowledgeGraph>,
+    vector_store: Option<std::sync::Arc<miow_vector::VectorStore>>,
+) -> Result<IndexReport> {
+    let config = IndexConfig {
+        since: Some(rev.to_string()),
+        ..IndexConfig::default()
+    };
+    index_into_graph(path, graph, vector_store, config, None, None).await
+}
+
+/// Index into the knowledge graph as `config` says (full, incremental or since a
+/// revision). `languages` replaces the project's registry; `progress` receives
+/// events until indexing ends, when the sender is dropped.
+pub async fn index_into_graph(
+    path: PathBuf,
+    graph: std::sync::Arc<miow_graph::KnowledgeGraph>,
+    vector_store: Option<std::sync::Arc<miow_vector::VectorStore>>,
+    config: IndexConfig,
+    languages: Option<std::sync::Arc<LanguageRegistry>>,
+    progress: Option<ProgressSender>,
+) -> Result<IndexReport> {
+    let mut indexer = CodebaseIndexer::new(path)?.with_config(config).with_graph(graph);
+    if let Some(languages) = languages {
+        indexer = indexer.with_language_registry(languages);
+    }
+    if let Some(store) = vector_store {
+        indexer = indexer.with_vector_store(store);
+    }
+    if let Some(progress) = progress {
+        indexer = indexer.with_progress(progress);
+    }
+    indexer.index().await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,107 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use miow_graph::FileOwnerData;
+use std::path::Path;
+use tracing::warn;
+
+use crate::git;
+
+/// Where GitHub and GitLab look for the file, in order
+const CODEOWNERS_PATHS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS", ".gitlab/CODEOWNERS"];
+
+/// Commits of history read to find each file's most frequent author
+const AUTHOR_HISTORY: usize = 2000;
+
+/// A parsed CODEOWNERS file. Patterns use gitignore syntax and the last
+/// matching rule decides a file's owners; a rule without owners unassigns.
+pub struct Codeowners {
+    rules: Vec<(Gitignore, Vec<String>)>,
+}
+
+impl Codeowners {
+    /// The CODEOWNERS file of the repository at `root`, if it has one
+    pub fn load(root: &Path) -> Option<Self> {
+        let path = CODEOWNERS_PATHS.iter().map(|p| root.join(p)).find(|p| p.is_file())?;
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Some(Self::parse(root, &text)),
+            Err(e) => {
+                warn!("Failed to read {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn parse(root: &Path, text: &str) -> Self {
+        let mut rules = Vec::new();
+        for line in text.lines() {
+            let line = line.split(" #").next().unwrap_or_default().trim();
+            // GitLab section headers (`[Docs]`) group rules; their rules apply as usual
+            if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let Some(pattern) = fields.next() else { continue };
+            let owners = fields.map(String::from).collect();
+
+            let mut builder = GitignoreBuilder::new(root);
+            let matcher = builder.add_line(None, pattern).ok().and_then(|b| b.build().ok());
+            match matcher {
+                Some(matcher) => rules.push((matcher, owners)),
+                None => warn!("Skipping CODEOWNERS pattern {}", pattern),
+            }
+        }
+        Self { rules }
+    }
+
+    /// Owners of `path`, relative to the repository root
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(matcher, _)| matcher.matched_path_or_any_parents(path, false).is_ignore())
+            .map_or(&[], |(_, owners)| owners.as_slice())
+    }
+}
+
+/// Owners of each of `files` (relative to `root`): their CODEOWNERS entries
+/// and their most frequent author in recent history
+pub fn file_owners(root: &Path, files: &[String]) -> Vec<FileOwnerData> {
+    let codeowners = Codeowners::load(root);
+    let authors = git::top_authors(root, AUTHOR_HISTORY).unwrap_or_default();
+
+    let mut owners = Vec::new();
+    for file in files {
+        for owner in codeowners.iter().flat_map(|c| c.owners_of(file)) {
+            owners.push(FileOwnerData { file_path: file.clone(), owner: owner.clone(), source: "codeowners".to_string() });
+        }
+        if let Some(author) = authors.get(file) {
+            owners.push(FileOwnerData { file_path: file.clone(), owner: author.clone(), source: "git".to_string() });
+        }
+    }
+    owners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_matching_rule_wins() {
+        let codeowners = Codeowners::parse(
+            Path::new("/repo"),
+            "# Default owners\n\
+             *       @org/core\n\
+             *.md    docs@example.com # inline comment\n\
+             /src/payments/ @org/payments @alice\n\
+             src/payments/legacy.ts\n\
+             apps/   @org/apps\n",
+        );
+        assert_eq!(codeowners.owners_of("Cargo.toml"), ["@org/core"]);
+        assert_eq!(codeowners.owners_of("guide/intro.md"), ["docs@example.com"]);
+        assert_eq!(codeowners.owners_of("src/payments/charge.ts"), ["@org/payments", "@alice"]);
+        assert_eq!(codeowners.owners_of("src/payments/stripe/client.ts"), ["@org/payments", "@alice"]);
+        assert!(codeowners.owners_of("src/payments/legacy.ts").is_empty());
+        assert_eq!(codeowners.owners_of("web/apps/main.ts"), ["@org/apps"]);
+        // Anchored patterns only match from the root
+        assert_eq!(codeowners.owners_of("lib/src/payments/charge.ts"), ["@org/core"]);
+    }
+}
@@ -1,8 +1,9 @@
 use anyhow::{Context, Result};
+use miow_parsers::{ContainerConfig, ContainerService, Manifest};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct ProjectSignature {
@@ -16,8 +17,18 @@ pub struct ProjectSignature {
     pub dependencies: HashMap<String, String>,
     pub dev_dependencies: HashMap<String, String>,
     pub features: Vec<String>,
+    #[serde(default)]
+    pub scripts: HashMap<String, String>,
+    #[serde(default)]
+    pub workspace_members: Vec<String>,
+    /// Root Compose services, or the Dockerfile's final stage: `db (postgres:16, ports 5432:5432)`
+    #[serde(default)]
+    pub containers: Vec<String>,
 }
 
+/// Compose files looked for at the project root, in the order Compose prefers them
+const COMPOSE_FILES: &[&str] = &["compose.yaml", "compose.yml", "docker-compose.yaml", "docker-compose.yml"];
+
 impl ProjectSignature {
     pub fn detect(root_path: &Path) -> Result<Self> {
         let mut signature = ProjectSignature::default();
@@ -32,23 +43,63 @@ impl ProjectSignature {
                     }
                 }
                 "cargo" => {
-                    if let Ok(cargo_toml) = Self::parse_cargo_toml(root_path) {
-                        signature = Self::analyze_rust_package(&cargo_toml, signature);
+                    signature.language = "rust".to_string();
+                    if let Some(manifest) = Self::read_manifest(root_path, "cargo") {
+                        signature = Self::apply_manifest(&manifest, signature);
                     }
                 }
                 "pip" => {
-                    // Python detection
                     signature.language = "python".to_string();
+                    if let Some(manifest) = Self::read_manifest(root_path, "pip") {
+                        signature = Self::apply_manifest(&manifest, signature);
+                    }
+                }
+                "bundler" => {
+                    signature.language = "ruby".to_string();
+                    if let Some(manifest) = Self::read_manifest(root_path, "bundler") {
+                        signature = Self::apply_manifest(&manifest, signature);
+                    }
+                }
+                "composer" => {
+                    signature.language = "php".to_string();
+                    if let Some(manifest) = Self::read_manifest(root_path, "composer") {
+                        signature = Self::apply_manifest(&manifest, signature);
+                    }
+                }
+                "maven" | "gradle" => {
+                    if let Ok(build_file) = Self::parse_java_build_file(root_path) {
+                        signature = Self::analyze_java_build(&build_file, signature);
+                    }
                 }
                 _ => {}
             }
         }
 
-        // Detect language from file extensions
-        signature.language = Self::detect_language_from_files(root_path)?;
+        // Detect language from file extensions, keeping the manifest's answer when files are inconclusive
+        let file_language = Self::detect_language_from_files(root_path)?;
+        if file_language != "unknown" || signature.language.is_empty() {
+            signature.language = file_language;
+        }
+
+        // A containerized project with nothing else to go on still names its runtime in a base image
+        let containers = Self::read_containers(root_path);
+        if signature.language == "unknown" {
+            if let Some(language) = containers.iter().find_map(|c| c.image.as_deref().and_then(Self::language_from_image)) {
+                signature.language = language.to_string();
+            }
+        }
+        signature.containers = Self::describe_containers(root_path, &containers);
 
-        // Detect framework from files/config
-        signature.framework = Self::detect_framework(root_path, &signature.language)?;
+        // Detect framework from the dependency list, then files/config
+        signature.framework = Self::detect_framework(root_path, &signature)?;
+
+        // Libraries declared in the manifest
+        if signature.validation_library.is_none() {
+            signature.validation_library = Self::detect_validation_library(&signature.dependencies);
+        }
+        if signature.auth_library.is_none() {
+            signature.auth_library = Self::detect_auth_library(&signature.dependencies);
+        }
 
         // Autonomous detection will be handled by LLM in orchestrator
 
@@ -64,11 +115,18 @@ impl ProjectSignature {
     }
 
     fn detect_package_manager(root_path: &Path) -> Result<Option<String>> {
+        // Rails and Laravel apps usually ship a package.json for their assets;
+        // the Gemfile or composer.json is the real manifest
         let candidates = [
+            ("Gemfile", "bundler"),
+            ("composer.json", "composer"),
             ("package.json", "npm"),
             ("Cargo.toml", "cargo"),
             ("pyproject.toml", "pip"),
             ("requirements.txt", "pip"),
+            ("pom.xml", "maven"),
+            ("build.gradle", "gradle"),
+            ("build.gradle.kts", "gradle"),
             ("yarn.lock", "yarn"),
             ("pnpm-lock.yaml", "pnpm"),
         ];
@@ -88,58 +146,162 @@ impl ProjectSignature {
         Ok(parsed)
     }
 
-    fn parse_cargo_toml(root_path: &Path) -> Result<String> {
-        let path = root_path.join("Cargo.toml");
-        fs::read_to_string(&path).context("Failed to read Cargo.toml")
+    /// Read the root manifest for a package manager
+    pub(crate) fn read_manifest(root_path: &Path, package_manager: &str) -> Option<Manifest> {
+        let candidates: &[&str] = match package_manager {
+            "npm" | "yarn" | "pnpm" => &["package.json"],
+            "cargo" => &["Cargo.toml"],
+            "pip" => &["pyproject.toml", "requirements.txt"],
+            "bundler" => &["Gemfile"],
+            "composer" => &["composer.json"],
+            _ => &[],
+        };
+
+        candidates.iter().find_map(|file| Manifest::read(&root_path.join(file)).ok())
     }
 
-    fn analyze_npm_package(package_json: &Value, mut signature: ProjectSignature) -> ProjectSignature {
-        if let Some(deps) = package_json["dependencies"].as_object() {
-            for (name, version) in deps {
-                signature.dependencies.insert(name.clone(), version.as_str().unwrap_or("").to_string());
-            }
+    /// Described containers of the project at `root_path`, see [`Self::describe_containers`]
+    pub(crate) fn detect_containers(root_path: &Path) -> Vec<String> {
+        Self::describe_containers(root_path, &Self::read_containers(root_path))
+    }
+
+    /// Services of the root Compose file, or else the stages of the root Dockerfile
+    fn read_containers(root_path: &Path) -> Vec<ContainerService> {
+        COMPOSE_FILES
+            .iter()
+            .chain(&["Dockerfile", "Containerfile"])
+            .find_map(|file| {
+                let content = fs::read_to_string(root_path.join(file)).ok()?;
+                ContainerConfig::parse(file, &content).ok()
+            })
+            .map(|config| config.services)
+            .unwrap_or_default()
+    }
+
+    /// `web (build ./web, ports 8080:80)` per Compose service; a Dockerfile is
+    /// described by the stage it ships
+    pub(crate) fn describe_containers(root_path: &Path, containers: &[ContainerService]) -> Vec<String> {
+        let has_compose = COMPOSE_FILES.iter().any(|file| root_path.join(file).exists());
+        let shipped = if has_compose { containers } else { containers.last().map(std::slice::from_ref).unwrap_or(&[]) };
+
+        shipped
+            .iter()
+            .map(|service| {
+                let name = if has_compose { service.name.as_str() } else { "Dockerfile" };
+                let mut details = Vec::new();
+                match (&service.image, &service.build) {
+                    (Some(image), _) => details.push(image.clone()),
+                    (None, Some(build)) => details.push(format!("build {}", build)),
+                    _ => {}
+                }
+                if !service.ports.is_empty() {
+                    details.push(format!("ports {}", service.ports.join(" ")));
+                }
+                match details.is_empty() {
+                    true => name.to_string(),
+                    false => format!("{} ({})", name, details.join(", ")),
+                }
+            })
+            .collect()
+    }
+
+    /// The language an official base image runs: `node:20-alpine` -> typescript/javascript
+    fn language_from_image(image: &str) -> Option<&'static str> {
+        let name = image.rsplit('/').next().unwrap_or(image);
+        let name = name.split([':', '@']).next().unwrap_or(name);
+        match name {
+            "node" | "bun" | "deno" => Some("javascript"),
+            "python" => Some("python"),
+            "rust" => Some("rust"),
+            "golang" => Some("go"),
+            "ruby" => Some("ruby"),
+            "php" => Some("php"),
+            "openjdk" | "eclipse-temurin" | "amazoncorretto" | "maven" | "gradle" => Some("java"),
+            _ => None,
         }
-        if let Some(dev_deps) = package_json["devDependencies"].as_object() {
-            for (name, version) in dev_deps {
-                signature.dev_dependencies.insert(name.clone(), version.as_str().unwrap_or("").to_string());
+    }
+
+    fn apply_manifest(manifest: &Manifest, mut signature: ProjectSignature) -> ProjectSignature {
+        signature.dependencies.extend(manifest.dependencies.clone());
+        signature.dev_dependencies.extend(manifest.dev_dependencies.clone());
+        signature.scripts.extend(manifest.scripts.clone());
+        signature.workspace_members.extend(manifest.workspace_members.iter().cloned());
+        signature
+    }
+
+    fn parse_java_build_file(root_path: &Path) -> Result<String> {
+        ["pom.xml", "build.gradle", "build.gradle.kts"]
+            .iter()
+            .map(|f| root_path.join(f))
+            .find(|p| p.exists())
+            .context("No Maven or Gradle build file found")
+            .and_then(|p| fs::read_to_string(&p).context("Failed to read Java build file"))
+    }
+
+    fn analyze_java_build(build_file: &str, mut signature: ProjectSignature) -> ProjectSignature {
+        // Record well-known artifacts; full POM/Gradle parsing is out of scope here
+        let artifacts = [
+            "spring-boot-starter-web",
+            "spring-boot-starter-data-jpa",
+            "spring-boot-starter-security",
+            "spring-boot-starter-validation",
+            "spring-boot",
+            "quarkus",
+            "lombok",
+            "hibernate-validator",
+        ];
+        for artifact in artifacts {
+            if build_file.contains(artifact) {
+                signature.dependencies.insert(artifact.to_string(), String::new());
             }
         }
 
-        // Detect Next.js specifically
-        if signature.dependencies.contains_key("next") || signature.dev_dependencies.contains_key("next") {
-            signature.framework = "Next.js".to_string();
-            signature.features.push("app-router".to_string()); // Assume modern Next.js
+        if signature.dependencies.contains_key("spring-boot-starter-validation")
+            || signature.dependencies.contains_key("hibernate-validator")
+        {
+            signature.validation_library = Some("Bean Validation".to_string());
+        }
+        if signature.dependencies.contains_key("spring-boot-starter-security") {
+            signature.auth_library = Some("Spring Security".to_string());
         }
 
         signature
     }
 
-    fn analyze_rust_package(cargo_toml: &str, mut signature: ProjectSignature) -> ProjectSignature {
-        // Parse Cargo.toml for Rust crates
-        // Basic parsing - could use toml crate for better parsing
-        for line in cargo_toml.lines() {
-            if line.trim().starts_with('[') {
-                continue;
-            }
-            if line.contains(" = ") {
-                let parts: Vec<&str> = line.split(" = ").collect();
-                if parts.len() == 2 {
-                    let name = parts[0].trim().trim_end_matches('"').trim_start_matches('"');
-                    let version = parts[1].trim().trim_matches('"');
-                    signature.dependencies.insert(name.to_string(), version.to_string());
-                }
-            }
+    fn analyze_npm_package(package_json: &Value, signature: ProjectSignature) -> ProjectSignature {
+        let mut signature = Self::apply_manifest(&Manifest::from_package_json(package_json), signature);
+
+        if signature.dependencies.contains_key("typescript") || signature.dev_dependencies.contains_key("typescript") {
+            signature.language = "typescript".to_string();
+        }
+
+        // Detect Next.js specifically
+        if signature.dependencies.contains_key("next") || signature.dev_dependencies.contains_key("next") {
+            signature.framework = "Next.js".to_string();
+            signature.features.push("app-router".to_string()); // Assume modern Next.js
         }
+
         signature
     }
 
     fn detect_language_from_files(root_path: &Path) -> Result<String> {
         let mut counts = HashMap::new();
-        let extensions = vec![".ts", ".tsx", ".js", ".jsx", ".rs", ".py", ".go", ".java"];
+        let extensions = vec![".ts", ".tsx", ".js", ".jsx", ".rs", ".py", ".go", ".java", ".rb", ".php"];
+
+        // Maven/Gradle layout keeps sources deep under src/main/java
+        if root_path.join("src/main/java").exists() {
+            return Ok("java".to_string());
+        }
+        // So does Rails, under app/ and lib/
+        if root_path.join("config/routes.rb").exists() {
+            return Ok("ruby".to_string());
+        }
+        // And Laravel, under app/ with `artisan` at the root
+        if root_path.join("artisan").exists() {
+            return Ok("php".to_string());
+        }
 
         for ext in extensions {
-            let pattern = format!("**/*{}", ext);
-            let _glob_path = root_path.join(&pattern.replace("**/", ""));
             // Simple count - could use walkdir for accuracy
             if root_path.join(format!("src/*{}", ext)).exists() || root_path.join(format!("lib/*{}", ext)).exists() {
                 *counts.entry(ext).or_insert(0) += 1;
@@ -153,15 +315,33 @@ impl ProjectSignature {
             Ok("rust".to_string())
         } else if *counts.get(&".py").unwrap_or(&0) > 0 {
             Ok("python".to_string())
+        } else if *counts.get(&".java").unwrap_or(&0) > 0 {
+            Ok("java".to_string())
+        } else if *counts.get(&".rb").unwrap_or(&0) > 0 {
+            Ok("ruby".to_string())
+        } else if *counts.get(&".php").unwrap_or(&0) > 0 {
+            Ok("php".to_string())
         } else {
             Ok("unknown".to_string())
         }
     }
 
-    fn detect_framework(root_path: &Path, language: &str) -> Result<String> {
-        match language {
-            "typescript" => {
-                if root_path.join("next.config.js").exists() || root_path.join("next.config.mjs").exists() {
+    fn detect_framework(root_path: &Path, signature: &ProjectSignature) -> Result<String> {
+        let has_dep = |name: &str| {
+            signature.dependencies.contains_key(name) || signature.dev_dependencies.contains_key(name)
+        };
+
+        match signature.language.as_str() {
+            "typescript" | "javascript" => {
+                if has_dep("next") {
+                    Ok("Next.js".to_string())
+                } else if has_dep("@nestjs/core") {
+                    Ok("NestJS".to_string())
+                } else if has_dep("vite") && has_dep("react") {
+                    Ok("Vite + React".to_string())
+                } else if has_dep("express") {
+                    Ok("Express".to_string())
+                } else if root_path.join("next.config.js").exists() || root_path.join("next.config.mjs").exists() {
                     Ok("Next.js".to_string())
                 } else if root_path.join("vite.config.ts").exists() {
                     Ok("Vite + React".to_string())
@@ -172,50 +352,57 @@ impl ProjectSignature {
                 }
             }
             "rust" => {
-                if root_path.join("Cargo.toml").exists() {
-                    // Check for web frameworks
-                    if fs::read_to_string(root_path.join("Cargo.toml"))?
-                        .contains("actix-web") || fs::read_to_string(root_path.join("Cargo.toml"))?.contains("axum") {
-                        Ok("Rust Web".to_string())
-                    } else {
-                        Ok("Rust CLI".to_string())
-                    }
+                if ["axum", "actix-web", "rocket", "warp"].iter().any(|d| has_dep(d)) {
+                    Ok("Rust Web".to_string())
+                } else if root_path.join("Cargo.toml").exists() {
+                    Ok("Rust CLI".to_string())
                 } else {
                     Ok("unknown".to_string())
                 }
             }
-            _ => Ok("unknown".to_string()),
-        }
-    }
-
-    fn detect_ui_library(root_path: &Path, dependencies: &HashMap<String, String>) -> Option<String> {
-        // Check dependencies first
-        let ui_indicators = vec![
-            ("@shadcn/ui", "shadcn/ui"),
-            ("headlessui", "Headless UI"),
-            ("@headlessui/react", "Headless UI"),
-            ("@radix-ui", "Radix UI"),
-            ("mantine", "Mantine"),
-            ("chakra-ui", "Chakra UI"),
-            ("antd", "Ant Design"),
-            ("material-ui", "Material-UI"),
-        ];
-
-        for (dep, name) in ui_indicators {
-            if dependencies.contains_key(dep) {
-                return Some(name.to_string());
+            "python" => {
+                if has_dep("fastapi") {
+                    Ok("FastAPI".to_string())
+                } else if has_dep("django") {
+                    Ok("Django".to_string())
+                } else if has_dep("flask") {
+                    Ok("Flask".to_string())
+                } else {
+                    Ok("Python".to_string())
+                }
             }
-        }
-
-        // Fallback: scan for usage patterns
-        let common_components = vec!["InputBox", "Button", "Form", "Modal", "Dialog"];
-        for component in common_components {
-            if Self::scan_for_component_usage(root_path, component).is_some() {
-                return Some(format!("Custom UI (uses {})", component));
+            "java" => {
+                let build_file = Self::parse_java_build_file(root_path).unwrap_or_default();
+                if build_file.contains("spring-boot") {
+                    Ok("Spring Boot".to_string())
+                } else if build_file.contains("quarkus") {
+                    Ok("Quarkus".to_string())
+                } else {
+                    Ok("Java".to_string())
+                }
+            }
+            "ruby" => {
+                if has_dep("rails") || root_path.join("config/routes.rb").exists() {
+                    Ok("Rails".to_string())
+                } else if has_dep("sinatra") {
+                    Ok("Sinatra".to_string())
+                } else if has_dep("hanami") {
+                    Ok("Hanami".to_string())
+                } else {
+                    Ok("Ruby".to_string())
+                }
             }
+            "php" => {
+                if has_dep("laravel/framework") || root_path.join("artisan").exists() {
+                    Ok("Laravel".to_string())
+                } else if has_dep("symfony/framework-bundle") {
+                    Ok("Symfony".to_string())
+                } else {
+                    Ok("PHP".to_string())
+                }
+            }
+            _ => Ok("unknown".to_string()),
         }
-
-        None
     }
 
     fn detect_validation_library(dependencies: &HashMap<String, String>) -> Option<String> {
@@ -243,6 +430,9 @@ impl ProjectSignature {
             ("@supabase/auth-helpers-nextjs", "Supabase Auth"),
             ("firebase", "Firebase Auth"),
             ("jsonwebtoken", "JWT"),
+            ("devise", "Devise"),
+            ("laravel/sanctum", "Laravel Sanctum"),
+            ("laravel/passport", "Laravel Passport"),
         ];
 
         for (dep, name) in auth_indicators {
@@ -307,9 +497,29 @@ impl ProjectSignature {
             "React" => {
                 features.push("Client-Side Rendering".to_string());
             }
+            "Rails" => {
+                features.push("Active Record".to_string());
+                if !root_path.join("app/views").exists() {
+                    features.push("API-only".to_string());
+                }
+            }
+            "Laravel" => {
+                features.push("Eloquent ORM".to_string());
+                if root_path.join("resources/views").exists() {
+                    features.push("Blade Templates".to_string());
+                }
+            }
             _ => {}
         }
 
+        // Container features
+        if root_path.join("Dockerfile").exists() || root_path.join("Containerfile").exists() {
+            features.push("Docker".to_string());
+        }
+        if COMPOSE_FILES.iter().any(|file| root_path.join(file).exists()) {
+            features.push("Docker Compose".to_string());
+        }
+
         // UI and validation features
         if let Some(ui) = &signature.ui_library {
             features.push(format!("UI: {}", ui));
@@ -329,29 +539,6 @@ impl ProjectSignature {
         features
     }
 
-    fn scan_for_component_usage(root_path: &Path, component_name: &str) -> Option<PathBuf> {
-        // Simple scan - could be enhanced with git grep or tree-sitter
-        let pattern = format!("{}(", component_name); // Usage like InputBox(props)
-        let search_paths = vec!["src", "components", "app", "."];
-
-        for search_path in search_paths {
-            let full_path = root_path.join(search_path);
-            if full_path.exists() {
-                // This is a simple placeholder - in production, use git grep or walkdir
-                if let Ok(content) = fs::read_dir(&full_path) {
-                    for entry in content.flatten() {
-                        if let Ok(file_content) = fs::read_to_string(entry.path()) {
-                            if file_content.contains(&pattern) {
-                                return Some(entry.path());
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        None
-    }
-
     /// Get a human-readable description of the project signature
     pub fn to_description(&self) -> String {
         let mut parts = vec![];
@@ -370,6 +557,25 @@ impl ProjectSignature {
         if let Some(ref val) = self.validation_library {
             parts.push(format!("Validation: {}", val));
         }
+        if !self.dependencies.is_empty() {
+            let mut names: Vec<&str> = self.dependencies.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            let more = names.len().saturating_sub(15);
+            names.truncate(15);
+            let suffix = if more > 0 { format!(" (+{} more)", more) } else { String::new() };
+            parts.push(format!("Dependencies: {}{}", names.join(" "), suffix));
+        }
+        if !self.scripts.is_empty() {
+            let mut scripts: Vec<&str> = self.scripts.keys().map(String::as_str).collect();
+            scripts.sort_unstable();
+            parts.push(format!("Scripts: {}", scripts.join(" ")));
+        }
+        if !self.workspace_members.is_empty() {
+            parts.push(format!("Workspace: {}", self.workspace_members.join(" ")));
+        }
+        if !self.containers.is_empty() {
+            parts.push(format!("Containers: {}", self.containers.join("; ")));
+        }
         parts.join(", ")
     }
 
@@ -399,6 +605,21 @@ impl ProjectSignature {
                 questions.push("What functions are available?".to_string());
                 questions.push("What modules are imported?".to_string());
             }
+            "java" => {
+                questions.push("What classes and interfaces are defined?".to_string());
+                questions.push("What services and repositories exist?".to_string());
+                questions.push("What annotations are used on controllers?".to_string());
+            }
+            "ruby" => {
+                questions.push("What classes and modules are defined?".to_string());
+                questions.push("What methods do the models and services expose?".to_string());
+                questions.push("What gems are used?".to_string());
+            }
+            "php" => {
+                questions.push("What classes, interfaces and traits are defined?".to_string());
+                questions.push("What services and helpers are available?".to_string());
+                questions.push("What Composer packages are used?".to_string());
+            }
             _ => {
                 questions.push("What components are available?".to_string());
                 questions.push("What types are defined?".to_string());
@@ -412,10 +633,23 @@ impl ProjectSignature {
         if self.framework.contains("Next.js") {
             questions.push("What Next.js pages or API routes exist?".to_string());
         }
+        if self.framework.contains("Spring") {
+            questions.push("What @RestController endpoints and @Service beans exist?".to_string());
+        }
+        if self.framework == "Rails" {
+            questions.push("What ActiveRecord models, associations and routes exist?".to_string());
+        }
+        if self.framework == "Laravel" {
+            questions.push("What Eloquent models, relationships and routes exist?".to_string());
+        }
         if self.framework.contains("NestJS") {
             questions.push("What NestJS controllers and services exist?".to_string());
         }
 
+        if !self.containers.is_empty() {
+            questions.push("What services, images, ports and environment variables does the deployment use?".to_string());
+        }
+
         // Validation library questions
         if let Some(ref val_lib) = self.validation_library {
             if val_lib == "Zod" {
@@ -447,4 +681,94 @@ mod tests {
         let signature = ProjectSignature::analyze_npm_package(&package_json, ProjectSignature::default());
         assert_eq!(signature.framework, "Next.js".to_string());
     }
+
+    #[test]
+    fn test_detect_spring_boot() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join("src/main/java")).unwrap();
+        fs::write(
+            temp_dir.path().join("pom.xml"),
+            "<artifactId>spring-boot-starter-web</artifactId>",
+        )
+        .unwrap();
+
+        let signature = ProjectSignature::detect(temp_dir.path()).unwrap();
+        assert_eq!(signature.package_manager, "maven");
+        assert_eq!(signature.language, "java");
+        assert_eq!(signature.framework, "Spring Boot");
+    }
+
+    #[test]
+    fn test_detect_rails() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join("config")).unwrap();
+        fs::write(temp_dir.path().join("config/routes.rb"), "Rails.application.routes.draw do\nend\n").unwrap();
+        fs::write(temp_dir.path().join("package.json"), r#"{"dependencies":{"esbuild":"0.19.0"}}"#).unwrap();
+        fs::write(
+            temp_dir.path().join("Gemfile"),
+            "source \"https://rubygems.org\"\ngem \"rails\", \"~> 7.1\"\ngem \"devise\"\n",
+        )
+        .unwrap();
+
+        let signature = ProjectSignature::detect(temp_dir.path()).unwrap();
+        assert_eq!(signature.package_manager, "bundler");
+        assert_eq!(signature.language, "ruby");
+        assert_eq!(signature.framework, "Rails");
+        assert_eq!(signature.auth_library.as_deref(), Some("Devise"));
+        assert!(signature.features.contains(&"API-only".to_string()));
+    }
+
+    #[test]
+    fn test_detect_laravel() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("artisan"), "#!/usr/bin/env php\n<?php\n").unwrap();
+        fs::write(temp_dir.path().join("package.json"), r#"{"devDependencies":{"vite":"5.0.0"}}"#).unwrap();
+        fs::write(
+            temp_dir.path().join("composer.json"),
+            r#"{"require":{"php":"^8.2","laravel/framework":"^11.0","laravel/sanctum":"^4.0"}}"#,
+        )
+        .unwrap();
+
+        let signature = ProjectSignature::detect(temp_dir.path()).unwrap();
+        assert_eq!(signature.package_manager, "composer");
+        assert_eq!(signature.language, "php");
+        assert_eq!(signature.framework, "Laravel");
+        assert_eq!(signature.auth_library.as_deref(), Some("Laravel Sanctum"));
+        assert!(signature.features.contains(&"Eloquent ORM".to_string()));
+    }
+
+    #[test]
+    fn test_detect_containers() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("Dockerfile"), "FROM python:3.12-slim AS app\nEXPOSE 8000\n").unwrap();
+        let signature = ProjectSignature::detect(temp_dir.path()).unwrap();
+        assert_eq!(signature.language, "python");
+        assert_eq!(signature.containers, vec!["Dockerfile (python:3.12-slim, ports 8000)"]);
+        assert!(signature.features.contains(&"Docker".to_string()));
+
+        fs::write(
+            temp_dir.path().join("docker-compose.yml"),
+            "services:\n  api:\n    build: .\n    ports: [\"8000:8000\"]\n  cache:\n    image: redis:7\n",
+        )
+        .unwrap();
+        let signature = ProjectSignature::detect(temp_dir.path()).unwrap();
+        assert_eq!(signature.containers, vec!["api (build ., ports 8000:8000)", "cache (redis:7)"]);
+        assert!(signature.features.contains(&"Docker Compose".to_string()));
+        assert!(signature.to_description().contains("Containers: api (build ., ports 8000:8000); cache (redis:7)"));
+    }
+
+    #[test]
+    fn test_framework_from_pyproject_dependencies() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("pyproject.toml"),
+            "[project]\nname = \"api\"\ndependencies = [\"fastapi>=0.100\", \"pydantic\"]\n",
+        )
+        .unwrap();
+
+        let signature = ProjectSignature::detect(temp_dir.path()).unwrap();
+        assert_eq!(signature.language, "python");
+        assert_eq!(signature.framework, "FastAPI");
+        assert!(signature.dependencies.contains_key("pydantic"));
+    }
 }
@@ -9,6 +9,9 @@ pub struct CodeFile {
     pub language: Language,
     pub size: u64,
     pub content: String,
+    /// Modification time in seconds since the Unix epoch
+    #[serde(default)]
+    pub modified_at: Option<i64>,
 }
 
 /// Supported programming languages
@@ -20,7 +23,16 @@ pub enum Language {
     JSX,
     Python,
     Rust,
+    Java,
+    Ruby,
+    Php,
+    C,
+    Cpp,
     CSS,
+    SCSS,
+    Sql,
+    Prisma,
+    Terraform,
     JSON,
     Unknown,
 }
@@ -34,7 +46,16 @@ impl Language {
             "jsx" => Language::JSX,
             "py" => Language::Python,
             "rs" => Language::Rust,
+            "java" => Language::Java,
+            "rb" | "rake" => Language::Ruby,
+            "php" => Language::Php,
+            "c" | "h" => Language::C,
+            "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => Language::Cpp,
             "css" => Language::CSS,
+            "scss" | "sass" => Language::SCSS,
+            "sql" => Language::Sql,
+            "prisma" => Language::Prisma,
+            "tf" | "tfvars" => Language::Terraform,
             "json" => Language::JSON,
             _ => Language::Unknown,
         }
@@ -49,6 +70,16 @@ impl Language {
                 | Language::JSX
                 | Language::Python
                 | Language::Rust
+                | Language::Java
+                | Language::Ruby
+                | Language::Php
+                | Language::C
+                | Language::Cpp
+                | Language::CSS
+                | Language::SCSS
+                | Language::Sql
+                | Language::Prisma
+                | Language::Terraform
         )
     }
 }
@@ -61,20 +92,129 @@ pub struct IndexReport {
     pub total_size: u64,
     pub duration_ms: u128,
     pub files: Vec<CodeFile>,
+    /// What was written to the knowledge graph (zero when indexing without one)
+    #[serde(default)]
+    pub symbols_indexed: usize,
+    #[serde(default)]
+    pub design_tokens_indexed: usize,
+    #[serde(default)]
+    pub routes_indexed: usize,
+    /// Files that failed to parse or parsed with syntax errors
+    #[serde(default)]
+    pub parse_errors: Vec<FileParseErrors>,
+    /// Set by incremental reindexing: what changed since the previous index
+    #[serde(default)]
+    pub changes: Option<IndexChanges>,
+    /// Files passed over before parsing: too large, binary or generated
+    #[serde(default)]
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// A file the indexer did not read or parse, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub relative_path: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SkipReason {
+    TooLarge { size: u64, limit: u64 },
+    /// Known binary extension, NUL bytes or invalid UTF-8
+    Binary,
+    /// Lockfiles, minified bundles and source maps, recognized by name
+    Generated,
+    /// A line longer than `IndexConfig::max_line_length`
+    Minified { longest_line: usize },
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::TooLarge { size, limit } => write!(f, "too large ({} bytes, limit {})", size, limit),
+            SkipReason::Binary => write!(f, "binary"),
+            SkipReason::Generated => write!(f, "generated"),
+            SkipReason::Minified { longest_line } => write!(f, "minified ({} character line)", longest_line),
+        }
+    }
+}
+
+/// What `CodebaseIndexer::sync_file` did with a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileSync {
+    /// Re-parsed and written to the graph (and vector store) with this many symbols
+    Indexed { symbols: usize },
+    /// Gone from disk, so dropped from the graph and vector store
+    Removed,
+    /// Not a file the index covers
+    Skipped,
+}
+
+/// Files added, changed and removed since the previous index (relative paths)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexChanges {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+/// Progress of an indexing run, sent as files move through the pipeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum IndexProgress {
+    /// The walk finished; this many files will be parsed
+    Discovered { files: usize },
+    /// A file was parsed (or skipped for lack of a parser)
+    Parsed { done: usize, total: usize, path: String },
+    /// A file could not be parsed at all
+    Failed { path: String, message: String },
+    /// A parsed file's symbols were written to the vector store
+    Embedded { done: usize, total: usize },
+    Finished { files: usize, symbols: usize, duration_ms: u128 },
+}
+
+/// Parse diagnostics for one file; `failed` means nothing could be extracted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileParseErrors {
+    pub relative_path: String,
+    pub failed: bool,
+    pub diagnostics: Vec<miow_parsers::ParseDiagnostic>,
 }
 
 /// Configuration for indexing
 #[derive(Debug, Clone)]
 pub struct IndexConfig {
+    /// Files larger than this many bytes are not read
     pub max_file_size: u64,
+    /// Files with a longer line are treated as minified bundles; 0 disables the check
+    pub max_line_length: usize,
+    /// Skip lockfiles, minified bundles and files with binary extensions by name and
+    /// line length; files with binary content are skipped regardless
+    pub skip_generated: bool,
     pub ignore_patterns: Vec<String>,
     pub include_extensions: Vec<String>,
+    /// Parser threads; defaults to the number of available cores
+    pub workers: usize,
+    /// Only reparse files whose content changed since the graph was last written,
+    /// and drop files that no longer exist; needs a graph
+    pub incremental: bool,
+    /// Only reindex files git reports as changed since this revision (plus
+    /// untracked files) and drop deleted ones; needs a graph
+    pub since: Option<String>,
 }
 
 impl Default for IndexConfig {
     fn default() -> Self {
         Self {
             max_file_size: 1024 * 1024, // 1MB
+            max_line_length: 5000,
+            skip_generated: true,
+            workers: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+            incremental: false,
+            since: None,
             ignore_patterns: vec![
                 "node_modules".to_string(),
                 "target".to_string(),
@@ -91,7 +231,22 @@ impl Default for IndexConfig {
                 "jsx".to_string(),
                 "py".to_string(),
                 "rs".to_string(),
+                "java".to_string(),
+                "rb".to_string(),
+                "rake".to_string(),
+                "php".to_string(),
+                "c".to_string(),
+                "h".to_string(),
+                "cpp".to_string(),
+                "cc".to_string(),
+                "hpp".to_string(),
                 "css".to_string(),
+                "scss".to_string(),
+                "sass".to_string(),
+                "sql".to_string(),
+                "prisma".to_string(),
+                "tf".to_string(),
+                "tfvars".to_string(),
                 "json".to_string(),
             ],
         }
@@ -0,0 +1,392 @@
+//! Catalog of UI components and the props they accept. A component's props
+//! come from destructuring in its signature and from its props type, which is
+//! an inline object type or an interface/type alias that may live in another
+//! file; the catalog merges both so prompts can list them without the code.
+
+use crate::KnowledgeGraph;
+use anyhow::Result;
+use regex::Regex;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// How deep `interface A extends B` chains are followed
+const MAX_EXTENDS_DEPTH: usize = 3;
+
+/// Wrapper types whose first argument is the props type
+const PROPS_WRAPPERS: &[&str] = &["Readonly", "PropsWithChildren", "Partial", "Required"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogProp {
+    pub name: String,
+    pub type_annotation: Option<String>,
+    pub optional: bool,
+    pub default_value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentCatalogEntry {
+    pub name: String,
+    pub file_path: String,
+    pub start_line: i64,
+    /// The props type as written in the signature, e.g. `ButtonProps`
+    pub props_type: Option<String>,
+    pub props: Vec<CatalogProp>,
+    /// Symbols that reference the component
+    pub usages: usize,
+}
+
+impl KnowledgeGraph {
+    /// Rebuild the catalog from every Component symbol; returns how many were cataloged
+    pub fn rebuild_component_catalog(&self) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let components: Vec<(i64, String, String, String, Option<String>)> = conn
+            .prepare(
+                "SELECT s.id, s.name, f.path, s.content, s.metadata FROM symbols s
+                 JOIN files f ON s.file_id = f.id
+                 WHERE s.kind = 'Component' AND s.parent_id IS NULL",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let resolved: Vec<(i64, Option<String>, Vec<CatalogProp>)> = components
+            .into_iter()
+            .map(|(id, _, file_path, content, metadata)| {
+                let (props_type, props) = resolve_props(&conn, &file_path, &content, metadata.as_deref())?;
+                Ok((id, props_type, props))
+            })
+            .collect::<Result<_>>()?;
+
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM component_catalog", [])?;
+        for (id, props_type, props) in &resolved {
+            tx.execute(
+                "INSERT INTO component_catalog (symbol_id, props_type, props) VALUES (?1, ?2, ?3)",
+                params![id, props_type, serde_json::to_string(props)?],
+            )?;
+        }
+        tx.commit()?;
+        Ok(resolved.len())
+    }
+
+    /// Every cataloged component, most used first
+    pub fn component_catalog(&self) -> Result<Vec<ComponentCatalogEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT s.name, f.path, s.start_line, c.props_type, c.props,
+                   (SELECT COUNT(DISTINCT r.from_symbol_id) FROM symbol_references r WHERE r.to_symbol_name = s.name) AS usages
+            FROM component_catalog c
+            JOIN symbols s ON c.symbol_id = s.id
+            JOIN files f ON s.file_id = f.id
+            ORDER BY usages DESC, s.name, f.path
+            "#,
+        )?;
+        let results = stmt.query_map([], |row| {
+            let props: String = row.get(4)?;
+            Ok(ComponentCatalogEntry {
+                name: row.get(0)?,
+                file_path: row.get(1)?,
+                start_line: row.get(2)?,
+                props_type: row.get(3)?,
+                props: serde_json::from_str(&props).unwrap_or_default(),
+                usages: row.get::<_, i64>(5)? as usize,
+            })
+        })?;
+
+        let mut entries = Vec::new();
+        for result in results {
+            entries.push(result?);
+        }
+        Ok(entries)
+    }
+}
+
+/// The props type named in a component's signature and its merged props
+fn resolve_props(
+    conn: &Connection,
+    file_path: &str,
+    content: &str,
+    metadata: Option<&str>,
+) -> Result<(Option<String>, Vec<CatalogProp>)> {
+    let first_param = metadata.and_then(first_parameter);
+    let destructured = first_param.as_ref().map(|(name, _)| destructured_props(name)).unwrap_or_default();
+    let props_type = first_param
+        .and_then(|(_, type_annotation)| type_annotation)
+        .map(|t| t.trim_start_matches(':').trim().to_string())
+        .filter(|t| !t.is_empty())
+        .or_else(|| wrapper_props_type(content));
+
+    let mut declared = Vec::new();
+    if let Some(props_type) = &props_type {
+        declared = type_members(conn, file_path, props_type, 0)?;
+    }
+
+    // Destructured order first, typed by the declaration; then props only the type declares
+    let mut props: Vec<CatalogProp> = destructured
+        .into_iter()
+        .map(|(name, default_value)| {
+            let declared = declared.iter().find(|p| p.name == name);
+            CatalogProp {
+                type_annotation: declared.and_then(|p| p.type_annotation.clone()),
+                optional: default_value.is_some() || declared.is_some_and(|p| p.optional),
+                name,
+                default_value,
+            }
+        })
+        .collect();
+    for prop in declared {
+        if !props.iter().any(|p| p.name == prop.name) {
+            props.push(prop);
+        }
+    }
+    Ok((props_type, props))
+}
+
+/// Name (or destructuring pattern) and type annotation of the first parameter
+/// in serialized symbol metadata. Older indexes stored the metadata JSON as a
+/// JSON string, so that is unwrapped first.
+fn first_parameter(metadata: &str) -> Option<(String, Option<String>)> {
+    let mut value: serde_json::Value = serde_json::from_str(metadata).ok()?;
+    if let Some(inner) = value.as_str() {
+        value = serde_json::from_str(inner).ok()?;
+    }
+    let param = value.get("parameters")?.as_array()?.first()?;
+    let name = param.get("name")?.as_str()?.to_string();
+    let type_annotation = param.get("type_annotation").and_then(|t| t.as_str()).map(String::from);
+    Some((name, type_annotation))
+}
+
+/// Props and default values of a destructuring pattern: `{ label, size = "md", onClick: handle }`
+fn destructured_props(pattern: &str) -> Vec<(String, Option<String>)> {
+    let Some(inner) = pattern.trim().strip_prefix('{').and_then(|p| p.strip_suffix('}')) else {
+        return Vec::new();
+    };
+    split_top_level(inner, &[','])
+        .into_iter()
+        .filter(|entry| !entry.starts_with("..."))
+        .filter_map(|entry| {
+            let (binding, default_value) = match split_once_top_level(entry, '=') {
+                Some((binding, value)) => (binding, Some(value.trim().to_string())),
+                None => (entry, None),
+            };
+            let name = binding.split(':').next()?.trim();
+            is_identifier(name).then(|| (name.to_string(), default_value))
+        })
+        .collect()
+}
+
+/// Props type given through a wrapper rather than the parameter:
+/// `const Button: React.FC<ButtonProps> = ...` or `forwardRef<HTMLButtonElement, ButtonProps>(...)`
+fn wrapper_props_type(content: &str) -> Option<String> {
+    static WRAPPER: OnceLock<Regex> = OnceLock::new();
+    let wrapper = WRAPPER.get_or_init(|| {
+        Regex::new(r"(?:\bFC|\bVFC|\bFunctionComponent|forwardRef<[^,<>]+,)\s*<?\s*([A-Za-z_$][\w$.]*)").unwrap()
+    });
+    let head = content.lines().next().unwrap_or_default();
+    wrapper.captures(head).map(|c| c[1].to_string())
+}
+
+/// Members of a type expression: an object type literal, an intersection, a
+/// wrapper like `Readonly<Props>`, or the name of an interface or type alias
+fn type_members(conn: &Connection, file_path: &str, type_expr: &str, depth: usize) -> Result<Vec<CatalogProp>> {
+    let type_expr = type_expr.trim();
+    if type_expr.starts_with('{') {
+        return Ok(object_members(type_expr));
+    }
+    if depth > MAX_EXTENDS_DEPTH {
+        return Ok(Vec::new());
+    }
+
+    let parts = split_top_level(type_expr, &['&']);
+    if parts.len() > 1 {
+        let mut members = Vec::new();
+        for part in parts {
+            members.extend(type_members(conn, file_path, part, depth + 1)?);
+        }
+        return Ok(members);
+    }
+
+    let (name, arguments) = match type_expr.split_once('<') {
+        Some((name, rest)) => (name.trim(), rest.strip_suffix('>').unwrap_or(rest)),
+        None => (type_expr, ""),
+    };
+    let name = name.rsplit('.').next().unwrap_or(name);
+    if PROPS_WRAPPERS.contains(&name) {
+        let argument = split_top_level(arguments, &[',']).into_iter().next().unwrap_or_default();
+        return type_members(conn, file_path, argument, depth + 1);
+    }
+
+    let Some(definition) = find_type_definition(conn, file_path, name)? else {
+        return Ok(Vec::new());
+    };
+    let body_start = definition.find('{');
+    let mut members = Vec::new();
+    // `interface Props extends BaseProps, Other { ... }`
+    if let Some(extends) = definition[..body_start.unwrap_or(definition.len())].split(" extends ").nth(1) {
+        for parent in split_top_level(extends, &[',']) {
+            members.extend(type_members(conn, file_path, parent, depth + 1)?);
+        }
+    }
+    match definition.split_once('=') {
+        // `type Props = Base & { ... }`
+        Some((head, aliased)) if !head.contains('{') => members.extend(type_members(conn, file_path, aliased.trim_end_matches(';'), depth + 1)?),
+        _ => {
+            if let Some(start) = body_start {
+                members.extend(object_members(&definition[start..]));
+            }
+        }
+    }
+    Ok(members)
+}
+
+/// Text of the interface or type alias `name`, preferring one in `file_path`
+fn find_type_definition(conn: &Connection, file_path: &str, name: &str) -> Result<Option<String>> {
+    let definition = conn
+        .query_row(
+            r#"
+            SELECT definition FROM (
+                SELECT t.definition, f.path FROM type_definitions t JOIN files f ON t.file_id = f.id WHERE t.name = ?1
+                UNION ALL
+                SELECT s.content, f.path FROM symbols s JOIN files f ON s.file_id = f.id
+                WHERE s.name = ?1 AND s.kind = 'Interface'
+            )
+            ORDER BY path = ?2 DESC
+            LIMIT 1
+            "#,
+            params![name, file_path],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(definition)
+}
+
+/// Members of an object type literal `{ label: string; size?: Size }`, ignoring
+/// index and call signatures
+fn object_members(body: &str) -> Vec<CatalogProp> {
+    let body = strip_comments(body);
+    let body = body.trim();
+    let inner = body.strip_prefix('{').unwrap_or(body);
+    let inner = inner.strip_suffix('}').unwrap_or_else(|| inner.rsplit_once('}').map_or(inner, |(inner, _)| inner));
+
+    split_top_level(inner, &[';', ',', '\n'])
+        .into_iter()
+        .filter_map(|member| {
+            let member = member.trim_start_matches("readonly ").trim();
+            let name_end = member.find([':', '?', '('])?;
+            let name = member[..name_end].trim().trim_matches(|c| c == '"' || c == '\'');
+            if name.is_empty() || name.starts_with('[') {
+                return None;
+            }
+            let rest = &member[name_end..];
+            let optional = rest.starts_with('?');
+            let rest = rest.trim_start_matches('?');
+            let type_annotation = match rest.strip_prefix(':') {
+                Some(type_annotation) => type_annotation.trim().to_string(),
+                // Method signature: `onClick(event: MouseEvent): void`
+                None => rest.trim().to_string(),
+            };
+            Some(CatalogProp {
+                name: name.to_string(),
+                type_annotation: (!type_annotation.is_empty()).then_some(type_annotation),
+                optional,
+                default_value: None,
+            })
+        })
+        .collect()
+}
+
+fn strip_comments(text: &str) -> String {
+    static COMMENT: OnceLock<Regex> = OnceLock::new();
+    let comment = COMMENT.get_or_init(|| Regex::new(r"(?s)/\*.*?\*/|//[^\n]*").unwrap());
+    comment.replace_all(text, "").into_owned()
+}
+
+/// Split on any of `separators` outside brackets, dropping empty pieces
+fn split_top_level<'a>(text: &'a str, separators: &[char]) -> Vec<&'a str> {
+    let mut pieces = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    let mut previous = ' ';
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' | '(' | '[' | '<' => depth += 1,
+            // The `>` of an arrow doesn't close anything
+            '>' if previous == '=' => {}
+            '}' | ')' | ']' | '>' => depth -= 1,
+            _ if depth == 0 && separators.contains(&c) => {
+                pieces.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+    pieces.push(&text[start..]);
+    pieces.into_iter().map(str::trim).filter(|p| !p.is_empty()).collect()
+}
+
+fn split_once_top_level(text: &str, separator: char) -> Option<(&str, &str)> {
+    let first = split_top_level(text, &[separator]).into_iter().next()?;
+    let offset = first.as_ptr() as usize - text.as_ptr() as usize + first.len();
+    let rest = text[offset..].trim_start().strip_prefix(separator)?;
+    Some((first, rest))
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prop(name: &str, type_annotation: Option<&str>, optional: bool, default_value: Option<&str>) -> CatalogProp {
+        CatalogProp {
+            name: name.to_string(),
+            type_annotation: type_annotation.map(String::from),
+            optional,
+            default_value: default_value.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_object_members() {
+        let members = object_members(
+            "{\n  /** Text on the button */\n  label: string;\n  size?: 'sm' | 'md', // default md\n  onClick(event: MouseEvent): void\n  readonly render: (props: { a: number }) => Node;\n  [key: string]: unknown\n}",
+        );
+        assert_eq!(
+            members,
+            vec![
+                prop("label", Some("string"), false, None),
+                prop("size", Some("'sm' | 'md'"), true, None),
+                prop("onClick", Some("(event: MouseEvent): void"), false, None),
+                prop("render", Some("(props: { a: number }) => Node"), false, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_destructured_props() {
+        assert_eq!(
+            destructured_props("{ label, size = \"md\", onClick: handle, style = { margin: 0 }, ...rest }"),
+            vec![
+                ("label".to_string(), None),
+                ("size".to_string(), Some("\"md\"".to_string())),
+                ("onClick".to_string(), None),
+                ("style".to_string(), Some("{ margin: 0 }".to_string())),
+            ]
+        );
+        assert!(destructured_props("props").is_empty());
+    }
+
+    #[test]
+    fn test_wrapper_props_type() {
+        assert_eq!(wrapper_props_type("const Button: React.FC<ButtonProps> = ({ label }) => {").as_deref(), Some("ButtonProps"));
+        assert_eq!(
+            wrapper_props_type("const Input = forwardRef<HTMLInputElement, InputProps>((props, ref) => {").as_deref(),
+            Some("InputProps")
+        );
+        assert_eq!(wrapper_props_type("function Card(props: CardProps) {"), None);
+    }
+}
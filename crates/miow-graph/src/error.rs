@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+/// Knowledge graph failures callers may want to tell apart. They travel
+/// inside `anyhow::Error`.
+#[derive(Debug, thiserror::Error)]
+pub enum GraphError {
+    /// The project was never indexed
+    #[error("Knowledge graph {} not found. Run 'miow-context init' first.", .0.display())]
+    NotIndexed(PathBuf),
+    /// Another process holds the database (an index or watch in progress)
+    #[error("Knowledge graph is busy")]
+    Busy(#[source] rusqlite::Error),
+    #[error("Knowledge graph database error")]
+    Database(#[source] rusqlite::Error),
+}
+
+impl From<rusqlite::Error> for GraphError {
+    fn from(error: rusqlite::Error) -> Self {
+        match error.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => Self::Busy(error),
+            _ => Self::Database(error),
+        }
+    }
+}
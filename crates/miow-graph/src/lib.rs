@@ -1,22 +1,32 @@
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
+pub mod error;
 pub mod query;
 pub mod schema;
 pub mod semantic_search;
 pub mod relationship_inference;
 pub mod query_expansion;
+pub mod component_catalog;
+pub mod similarity;
 
+pub use error::GraphError;
 pub use query::*;
 pub use schema::*;
 pub use semantic_search::{SemanticGraphSearch, SemanticSearchResult};
 pub use relationship_inference::{RelationshipInferencer, InferredRelationship, RelationshipType};
 pub use query_expansion::{QueryExpander, ExpandedQuery};
+pub use component_catalog::{CatalogProp, ComponentCatalogEntry};
+pub use similarity::SimilarSymbol;
 
 use std::sync::Mutex;
 
+/// `index_metadata` key of the counter every write to the index bumps
+pub const GENERATION_KEY: &str = "generation";
+
 /// Knowledge graph for storing and querying code symbols
 pub struct KnowledgeGraph {
     conn: Mutex<Connection>,
@@ -25,12 +35,34 @@ pub struct KnowledgeGraph {
 impl KnowledgeGraph {
     /// Create a new knowledge graph with the given database path
     pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
-        let conn = Connection::open(db_path)?;
+        let conn = Connection::open(db_path).map_err(GraphError::from)?;
         let graph = Self { conn: Mutex::new(conn) };
         graph.initialize_schema()?;
         Ok(graph)
     }
 
+    /// Open the graph of an indexed project, failing with
+    /// [`GraphError::NotIndexed`] rather than creating an empty one
+    pub fn open_existing<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        let db_path = db_path.as_ref();
+        if !db_path.exists() {
+            return Err(GraphError::NotIndexed(db_path.to_path_buf()).into());
+        }
+        Self::new(db_path)
+    }
+
+    /// Open another project's index to search without changing it: no schema
+    /// setup, and writes fail
+    pub fn open_read_only<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        let db_path = db_path.as_ref();
+        if !db_path.exists() {
+            return Err(GraphError::NotIndexed(db_path.to_path_buf()).into());
+        }
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI;
+        let conn = Connection::open_with_flags(db_path, flags).map_err(GraphError::from)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
     /// Create an in-memory knowledge graph (useful for testing)
     pub fn in_memory() -> Result<Self> {
         let conn = Connection::open_in_memory()?;
@@ -39,6 +71,12 @@ impl KnowledgeGraph {
         Ok(graph)
     }
 
+    /// Close the database now, reporting a failure instead of ignoring it as a drop would
+    pub fn close(self) -> Result<()> {
+        let conn = self.conn.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+        conn.close().map_err(|(_, e)| e.into())
+    }
+
     /// Initialize the database schema
     fn initialize_schema(&self) -> Result<()> {
         self.conn.lock().unwrap().execute_batch(
@@ -47,7 +85,10 @@ impl KnowledgeGraph {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 path TEXT NOT NULL UNIQUE,
                 language TEXT NOT NULL,
-                indexed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+                indexed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                content_hash TEXT,
+                modified_at INTEGER,
+                line_count INTEGER
             );
 
             CREATE TABLE IF NOT EXISTS symbols (
@@ -84,6 +125,18 @@ impl KnowledgeGraph {
                 FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
             );
 
+            CREATE TABLE IF NOT EXISTS exports (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                file_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                alias TEXT,
+                source TEXT,
+                is_default INTEGER NOT NULL DEFAULT 0,
+                is_type INTEGER NOT NULL DEFAULT 0,
+                start_line INTEGER NOT NULL,
+                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+            );
+
             CREATE TABLE IF NOT EXISTS design_tokens (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 file_id INTEGER NOT NULL,
@@ -126,119 +179,604 @@ impl KnowledgeGraph {
                 definition TEXT NOT NULL,
                 start_line INTEGER NOT NULL,
                 end_line INTEGER NOT NULL,
+                fields TEXT,
                 FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
             );
 
+            CREATE TABLE IF NOT EXISTS routes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                file_id INTEGER NOT NULL,
+                method TEXT NOT NULL,
+                path TEXT NOT NULL,
+                handler TEXT,
+                framework TEXT NOT NULL,
+                start_line INTEGER NOT NULL,
+                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS env_vars (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                file_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                accessor TEXT NOT NULL,
+                start_line INTEGER NOT NULL,
+                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS tests (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                file_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                framework TEXT NOT NULL,
+                content TEXT NOT NULL,
+                start_line INTEGER NOT NULL,
+                end_line INTEGER NOT NULL,
+                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS test_references (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                test_id INTEGER NOT NULL,
+                symbol_name TEXT NOT NULL,
+                FOREIGN KEY (test_id) REFERENCES tests(id) ON DELETE CASCADE
+            );
+
+            -- Keyed by path: files that failed to parse have no row in `files`
+            CREATE TABLE IF NOT EXISTS index_errors (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                file_path TEXT NOT NULL,
+                line INTEGER NOT NULL,
+                column INTEGER NOT NULL,
+                message TEXT NOT NULL
+            );
+
+            -- Facts about the index itself, such as the commit it was built from
+            CREATE TABLE IF NOT EXISTS index_metadata (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+
+            -- Resolved props of each Component symbol, as a JSON array; rebuilt after
+            -- every index run because a props interface may change in another file
+            CREATE TABLE IF NOT EXISTS component_catalog (
+                symbol_id INTEGER PRIMARY KEY,
+                props_type TEXT,
+                props TEXT NOT NULL,
+                FOREIGN KEY (symbol_id) REFERENCES symbols(id) ON DELETE CASCADE
+            );
+
+            -- Owners of each file from CODEOWNERS and git history. Keyed by path and
+            -- rewritten by every index run, since a CODEOWNERS edit changes files
+            -- that weren't reparsed.
+            CREATE TABLE IF NOT EXISTS file_owners (
+                file_path TEXT NOT NULL,
+                owner TEXT NOT NULL,
+                source TEXT NOT NULL,
+                PRIMARY KEY (file_path, owner, source)
+            );
+
+            -- Bearer tokens for the web server, kept as SHA-256 hashes. `paths` is
+            -- a JSON array of the codebases a token may use; empty allows any.
+            CREATE TABLE IF NOT EXISTS api_tokens (
+                name TEXT PRIMARY KEY,
+                token_hash TEXT NOT NULL UNIQUE,
+                paths TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            -- Whether users found a context item useful. Kept by path and name, not
+            -- symbol id, so it survives reindexing; an empty name is the whole file.
+            CREATE TABLE IF NOT EXISTS symbol_feedback (
+                file_path TEXT NOT NULL,
+                name TEXT NOT NULL,
+                useful INTEGER NOT NULL DEFAULT 0,
+                not_useful INTEGER NOT NULL DEFAULT 0,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY (file_path, name)
+            );
+
             CREATE INDEX IF NOT EXISTS idx_symbols_name ON symbols(name);
             CREATE INDEX IF NOT EXISTS idx_symbols_kind ON symbols(kind);
             CREATE INDEX IF NOT EXISTS idx_symbols_file ON symbols(file_id);
             CREATE INDEX IF NOT EXISTS idx_references_from ON symbol_references(from_symbol_id);
             CREATE INDEX IF NOT EXISTS idx_references_to ON symbol_references(to_symbol_name);
+            CREATE INDEX IF NOT EXISTS idx_exports_name ON exports(name);
             CREATE INDEX IF NOT EXISTS idx_design_tokens_name ON design_tokens(name);
             CREATE INDEX IF NOT EXISTS idx_type_definitions_name ON type_definitions(name);
             CREATE INDEX IF NOT EXISTS idx_constants_name ON constants(name);
             CREATE INDEX IF NOT EXISTS idx_schemas_name ON schemas(name);
+            CREATE INDEX IF NOT EXISTS idx_routes_path ON routes(path);
+            CREATE INDEX IF NOT EXISTS idx_env_vars_name ON env_vars(name);
+            CREATE INDEX IF NOT EXISTS idx_test_references_symbol ON test_references(symbol_name);
+            CREATE INDEX IF NOT EXISTS idx_index_errors_file ON index_errors(file_path);
+            CREATE INDEX IF NOT EXISTS idx_file_owners_owner ON file_owners(owner COLLATE NOCASE);
             "#,
-        )?;
+        ).map_err(GraphError::from)?;
+        self.migrate_schemas_fields()?;
+        self.migrate_files_fingerprint()?;
+        Ok(())
+    }
+
+    /// Databases created before schema fields were stored lack the `fields` column
+    fn migrate_schemas_fields(&self) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let has_fields = conn
+            .prepare("SELECT 1 FROM pragma_table_info('schemas') WHERE name = 'fields'")?
+            .exists([])?;
+        if !has_fields {
+            conn.execute("ALTER TABLE schemas ADD COLUMN fields TEXT", [])?;
+        }
+        Ok(())
+    }
+
+    /// Databases created before incremental reindexing lack the change-detection columns,
+    /// and ones created before `stats` lack the line count
+    fn migrate_files_fingerprint(&self) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        for (column, column_type) in [("content_hash", "TEXT"), ("modified_at", "INTEGER"), ("line_count", "INTEGER")] {
+            let exists = conn
+                .prepare("SELECT 1 FROM pragma_table_info('files') WHERE name = ?1")?
+                .exists(params![column])?;
+            if !exists {
+                conn.execute(&format!("ALTER TABLE files ADD COLUMN {} {}", column, column_type), [])?;
+            }
+        }
         Ok(())
     }
 
     /// Insert a file and its symbols into the graph
+    #[tracing::instrument(name = "graph.insert_file", skip_all, fields(path = file_path))]
     pub fn insert_file(&mut self, file_path: &str, parsed_file: &ParsedFileData) -> Result<i64> {
         let mut conn = self.conn.lock().unwrap();
         let tx = conn.transaction()?;
+        let file_id = insert_file_tx(&tx, file_path, parsed_file)?;
+        bump_generation_tx(&tx)?;
+        tx.commit()?;
+        Ok(file_id)
+    }
 
-        // Insert file
-        tx.execute(
-            "INSERT OR REPLACE INTO files (path, language) VALUES (?1, ?2)",
-            params![file_path, parsed_file.language],
-        )?;
+    /// Insert a batch of files in a single transaction; used by the indexer's graph writer
+    #[tracing::instrument(name = "graph.insert_files", skip_all, fields(files = files.len()))]
+    pub fn insert_files(&self, files: &[(String, ParsedFileData)]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for (file_path, parsed_file) in files {
+            insert_file_tx(&tx, file_path, parsed_file)?;
+        }
+        bump_generation_tx(&tx)?;
+        tx.commit()?;
+        Ok(())
+    }
 
-        let file_id = tx.last_insert_rowid();
+    /// Remove a file and everything extracted from it
+    #[tracing::instrument(name = "graph.remove_file", skip_all, fields(path = file_path))]
+    pub fn remove_file(&self, file_path: &str) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        delete_file_rows_tx(&tx, file_path)?;
+        tx.execute("DELETE FROM index_errors WHERE file_path = ?1", params![file_path])?;
+        tx.execute("DELETE FROM file_owners WHERE file_path = ?1", params![file_path])?;
+        bump_generation_tx(&tx)?;
+        tx.commit()?;
+        Ok(())
+    }
 
-        // Insert symbols
-        for symbol in &parsed_file.symbols {
-            insert_symbol_recursive(&tx, file_id, symbol, None)?;
+    /// Content hash and modification time stored for every indexed file, keyed by path
+    pub fn file_fingerprints(&self) -> Result<HashMap<String, FileFingerprint>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT path, content_hash, modified_at FROM files")?;
+        let results = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                FileFingerprint {
+                    content_hash: row.get(1)?,
+                    modified_at: row.get(2)?,
+                },
+            ))
+        })?;
+
+        let mut fingerprints = HashMap::new();
+        for result in results {
+            let (path, fingerprint) = result?;
+            fingerprints.insert(path, fingerprint);
         }
+        Ok(fingerprints)
+    }
 
-        // Insert imports
-        for import in &parsed_file.imports {
-            tx.execute(
-                "INSERT INTO imports (file_id, source, names, start_line, end_line) VALUES (?1, ?2, ?3, ?4, ?5)",
-                params![
-                    file_id,
-                    import.source,
-                    serde_json::to_string(&import.names)?,
-                    import.start_line,
-                    import.end_line
-                ],
-            )?;
+    /// Replace the owners recorded for every file
+    pub fn replace_file_owners(&self, owners: &[FileOwnerData]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM file_owners", [])?;
+        {
+            let mut stmt = tx.prepare("INSERT OR IGNORE INTO file_owners (file_path, owner, source) VALUES (?1, ?2, ?3)")?;
+            for owner in owners {
+                stmt.execute(params![owner.file_path, owner.owner, owner.source])?;
+            }
         }
+        tx.commit()?;
+        Ok(())
+    }
 
-        // Insert design tokens
-        for token in &parsed_file.design_tokens {
-            tx.execute(
-                "INSERT INTO design_tokens (file_id, token_type, name, value, context, start_line, end_line) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-                params![
-                    file_id,
-                    token.token_type,
-                    token.name,
-                    token.value,
-                    token.context,
-                    token.start_line,
-                    token.end_line
-                ],
-            )?;
+    /// Owners of a file, CODEOWNERS entries first
+    pub fn owners_of(&self, file_path: &str) -> Result<Vec<FileOwnerData>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT file_path, owner, source FROM file_owners WHERE file_path = ?1
+             ORDER BY source = 'git', owner",
+        )?;
+        let results = stmt.query_map(params![file_path], |row| {
+            Ok(FileOwnerData {
+                file_path: row.get(0)?,
+                owner: row.get(1)?,
+                source: row.get(2)?,
+            })
+        })?;
+
+        let mut owners = Vec::new();
+        for result in results {
+            owners.push(result?);
         }
+        Ok(owners)
+    }
 
-        // Insert type definitions
-        for type_def in &parsed_file.type_definitions {
-            tx.execute(
-                "INSERT INTO type_definitions (file_id, name, kind, definition, start_line, end_line) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                params![
-                    file_id,
-                    type_def.name,
-                    type_def.kind,
-                    type_def.definition,
-                    type_def.start_line,
-                    type_def.end_line
-                ],
-            )?;
+    /// Replace the recorded parse diagnostics for a file that could not be parsed at all
+    pub fn record_index_errors(&self, file_path: &str, errors: &[IndexErrorData]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        replace_index_errors_tx(&tx, file_path, errors)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Store a value in the index metadata, replacing any previous one
+    pub fn set_metadata(&self, key: &str, value: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO index_metadata (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    /// How many times the index has been written to; anything read from an
+    /// earlier generation may be out of date
+    pub fn generation(&self) -> Result<u64> {
+        Ok(self.get_metadata(GENERATION_KEY)?.and_then(|value| value.parse().ok()).unwrap_or(0))
+    }
+
+    pub fn get_metadata(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let value = conn
+            .query_row("SELECT value FROM index_metadata WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()?;
+        Ok(value)
+    }
+
+    /// Store an API token; names are unique
+    pub fn add_api_token(&self, token: &ApiTokenRecord) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO api_tokens (name, token_hash, paths, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![token.name, token.token_hash, serde_json::to_string(&token.paths)?, token.created_at],
+        )
+        .with_context(|| format!("Failed to add token '{}' (is the name taken?)", token.name))?;
+        Ok(())
+    }
+
+    pub fn list_api_tokens(&self) -> Result<Vec<ApiTokenRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT name, token_hash, paths, created_at FROM api_tokens ORDER BY name")?;
+        let results = stmt.query_map([], api_token_from_row)?;
+
+        let mut tokens = Vec::new();
+        for result in results {
+            tokens.push(result?);
         }
+        Ok(tokens)
+    }
 
-        // Insert constants
-        for constant in &parsed_file.constants {
-            tx.execute(
-                "INSERT INTO constants (file_id, name, value, category, start_line, end_line) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                params![
-                    file_id,
-                    constant.name,
-                    constant.value,
-                    constant.category,
-                    constant.start_line,
-                    constant.end_line
-                ],
-            )?;
+    /// The token with this hash, if it hasn't been revoked
+    pub fn find_api_token(&self, token_hash: &str) -> Result<Option<ApiTokenRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let token = conn
+            .query_row(
+                "SELECT name, token_hash, paths, created_at FROM api_tokens WHERE token_hash = ?1",
+                params![token_hash],
+                api_token_from_row,
+            )
+            .optional()?;
+        Ok(token)
+    }
+
+    /// Delete a token by name; `false` when there was none
+    pub fn revoke_api_token(&self, name: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.execute("DELETE FROM api_tokens WHERE name = ?1", params![name])? > 0)
+    }
+
+    /// Count a vote on whether `name` in `file_path` was useful context; an
+    /// empty name votes on the whole file
+    pub fn record_feedback(&self, file_path: &str, name: &str, useful: bool) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let (useful, not_useful) = if useful { (1, 0) } else { (0, 1) };
+        conn.execute(
+            "INSERT INTO symbol_feedback (file_path, name, useful, not_useful, updated_at)
+             VALUES (?1, ?2, ?3, ?4, strftime('%s', 'now'))
+             ON CONFLICT(file_path, name) DO UPDATE SET
+                useful = useful + excluded.useful,
+                not_useful = not_useful + excluded.not_useful,
+                updated_at = excluded.updated_at",
+            params![file_path, name, useful, not_useful],
+        )?;
+        Ok(())
+    }
+
+    /// Every item with feedback, most recently voted on first
+    pub fn list_feedback(&self) -> Result<Vec<FeedbackRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT file_path, name, useful, not_useful, updated_at FROM symbol_feedback
+             ORDER BY updated_at DESC, file_path, name",
+        )?;
+        let results = stmt.query_map([], |row| {
+            Ok(FeedbackRecord {
+                file_path: row.get(0)?,
+                name: row.get(1)?,
+                useful: row.get(2)?,
+                not_useful: row.get(3)?,
+                updated_at: row.get(4)?,
+            })
+        })?;
+
+        let mut feedback = Vec::new();
+        for result in results {
+            feedback.push(result?);
         }
+        Ok(feedback)
+    }
+
+    /// Ranking weights learned from feedback, by file path and name
+    pub fn feedback_weights(&self) -> Result<HashMap<(String, String), f32>> {
+        Ok(self
+            .list_feedback()?
+            .into_iter()
+            .map(|record| {
+                let weight = record.weight();
+                ((record.file_path, record.name), weight)
+            })
+            .collect())
+    }
+}
+
+fn api_token_from_row(row: &rusqlite::Row) -> rusqlite::Result<ApiTokenRecord> {
+    let paths: String = row.get(2)?;
+    Ok(ApiTokenRecord {
+        name: row.get(0)?,
+        token_hash: row.get(1)?,
+        paths: serde_json::from_str(&paths).unwrap_or_default(),
+        created_at: row.get(3)?,
+    })
+}
+
+fn replace_index_errors_tx(tx: &rusqlite::Transaction, file_path: &str, errors: &[IndexErrorData]) -> Result<()> {
+    tx.execute("DELETE FROM index_errors WHERE file_path = ?1", params![file_path])?;
+    for error in errors {
+        tx.execute(
+            "INSERT INTO index_errors (file_path, line, column, message) VALUES (?1, ?2, ?3, ?4)",
+            params![file_path, error.line, error.column, error.message],
+        )?;
+    }
+    Ok(())
+}
+
+fn bump_generation_tx(tx: &rusqlite::Transaction) -> Result<()> {
+    tx.execute(
+        "INSERT INTO index_metadata (key, value) VALUES (?1, '1')
+         ON CONFLICT(key) DO UPDATE SET value = CAST(value AS INTEGER) + 1",
+        params![GENERATION_KEY],
+    )?;
+    Ok(())
+}
+
+/// Foreign keys aren't enforced, so a file's rows are deleted table by table
+fn delete_file_rows_tx(tx: &rusqlite::Transaction, file_path: &str) -> Result<()> {
+    let file_id: Option<i64> = tx
+        .query_row("SELECT id FROM files WHERE path = ?1", params![file_path], |row| row.get(0))
+        .optional()?;
+    let Some(file_id) = file_id else {
+        return Ok(());
+    };
+
+    tx.execute(
+        "DELETE FROM symbol_references WHERE from_symbol_id IN (SELECT id FROM symbols WHERE file_id = ?1)",
+        params![file_id],
+    )?;
+    tx.execute(
+        "DELETE FROM test_references WHERE test_id IN (SELECT id FROM tests WHERE file_id = ?1)",
+        params![file_id],
+    )?;
+    tx.execute(
+        "DELETE FROM component_catalog WHERE symbol_id IN (SELECT id FROM symbols WHERE file_id = ?1)",
+        params![file_id],
+    )?;
+    for table in [
+        "symbols",
+        "imports",
+        "exports",
+        "design_tokens",
+        "type_definitions",
+        "constants",
+        "schemas",
+        "routes",
+        "env_vars",
+        "tests",
+    ] {
+        tx.execute(&format!("DELETE FROM {} WHERE file_id = ?1", table), params![file_id])?;
+    }
+    tx.execute("DELETE FROM files WHERE id = ?1", params![file_id])?;
+    Ok(())
+}
+
+fn insert_file_tx(tx: &rusqlite::Transaction, file_path: &str, parsed_file: &ParsedFileData) -> Result<i64> {
+    // Replace whatever an earlier index stored for this path
+    delete_file_rows_tx(tx, file_path)?;
+
+    // Insert file
+    tx.execute(
+        "INSERT INTO files (path, language, content_hash, modified_at, line_count) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![file_path, parsed_file.language, parsed_file.content_hash, parsed_file.modified_at, parsed_file.line_count],
+    )?;
+
+    let file_id = tx.last_insert_rowid();
+
+    // A clean reparse clears the file's old diagnostics
+    replace_index_errors_tx(tx, file_path, &parsed_file.index_errors)?;
 
-        // Insert schemas
-        for schema in &parsed_file.schemas {
+    // Insert symbols
+    for symbol in &parsed_file.symbols {
+        insert_symbol_recursive(tx, file_id, symbol, None)?;
+    }
+
+    // Insert imports
+    for import in &parsed_file.imports {
+        tx.execute(
+            "INSERT INTO imports (file_id, source, names, start_line, end_line) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                file_id,
+                import.source,
+                serde_json::to_string(&import.names)?,
+                import.start_line,
+                import.end_line
+            ],
+        )?;
+    }
+
+    // Insert exports
+    for export in &parsed_file.exports {
+        tx.execute(
+            "INSERT INTO exports (file_id, name, alias, source, is_default, is_type, start_line) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                file_id,
+                export.name,
+                export.alias,
+                export.source,
+                export.is_default,
+                export.is_type,
+                export.start_line
+            ],
+        )?;
+    }
+
+    // Insert design tokens
+    for token in &parsed_file.design_tokens {
+        tx.execute(
+            "INSERT INTO design_tokens (file_id, token_type, name, value, context, start_line, end_line) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                file_id,
+                token.token_type,
+                token.name,
+                token.value,
+                token.context,
+                token.start_line,
+                token.end_line
+            ],
+        )?;
+    }
+
+    // Insert type definitions
+    for type_def in &parsed_file.type_definitions {
+        tx.execute(
+            "INSERT INTO type_definitions (file_id, name, kind, definition, start_line, end_line) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                file_id,
+                type_def.name,
+                type_def.kind,
+                type_def.definition,
+                type_def.start_line,
+                type_def.end_line
+            ],
+        )?;
+    }
+
+    // Insert constants
+    for constant in &parsed_file.constants {
+        tx.execute(
+            "INSERT INTO constants (file_id, name, value, category, start_line, end_line) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                file_id,
+                constant.name,
+                constant.value,
+                constant.category,
+                constant.start_line,
+                constant.end_line
+            ],
+        )?;
+    }
+
+    // Insert schemas
+    for schema in &parsed_file.schemas {
+        tx.execute(
+            "INSERT INTO schemas (file_id, name, schema_type, definition, start_line, end_line, fields) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                file_id,
+                schema.name,
+                schema.schema_type,
+                schema.definition,
+                schema.start_line,
+                schema.end_line,
+                serde_json::to_string(&schema.fields)?
+            ],
+        )?;
+    }
+
+    // Insert routes
+    for route in &parsed_file.routes {
+        tx.execute(
+            "INSERT INTO routes (file_id, method, path, handler, framework, start_line) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                file_id,
+                route.method,
+                route.path,
+                route.handler,
+                route.framework,
+                route.start_line
+            ],
+        )?;
+    }
+
+    // Insert environment variable reads
+    for env_var in &parsed_file.env_vars {
+        tx.execute(
+            "INSERT INTO env_vars (file_id, name, accessor, start_line) VALUES (?1, ?2, ?3, ?4)",
+            params![file_id, env_var.name, env_var.accessor, env_var.start_line],
+        )?;
+    }
+
+    // Insert tests and the names they reference
+    for test in &parsed_file.tests {
+        tx.execute(
+            "INSERT INTO tests (file_id, name, framework, content, start_line, end_line) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                file_id,
+                test.name,
+                test.framework,
+                test.content,
+                test.start_line,
+                test.end_line
+            ],
+        )?;
+        let test_id = tx.last_insert_rowid();
+        for symbol_name in &test.references {
             tx.execute(
-                "INSERT INTO schemas (file_id, name, schema_type, definition, start_line, end_line) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                params![
-                    file_id,
-                    schema.name,
-                    schema.schema_type,
-                    schema.definition,
-                    schema.start_line,
-                    schema.end_line
-                ],
+                "INSERT INTO test_references (test_id, symbol_name) VALUES (?1, ?2)",
+                params![test_id, symbol_name],
             )?;
         }
-
-        tx.commit()?;
-        Ok(file_id)
     }
+
+    Ok(file_id)
 }
 
 fn insert_symbol_recursive(
@@ -247,8 +785,6 @@ fn insert_symbol_recursive(
     symbol: &SymbolData,
     parent_id: Option<i64>,
 ) -> Result<i64> {
-    let metadata_json = serde_json::to_string(&symbol.metadata)?;
-
     tx.execute(
         "INSERT INTO symbols (file_id, name, kind, start_line, end_line, start_byte, end_byte, content, metadata, parent_id) 
          VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
@@ -261,7 +797,7 @@ fn insert_symbol_recursive(
             symbol.start_byte,
             symbol.end_byte,
             symbol.content,
-            metadata_json,
+            symbol.metadata,
             parent_id
         ],
     )?;
@@ -320,6 +856,69 @@ impl KnowledgeGraph {
         Ok(symbols)
     }
 
+    /// `search_symbols` limited to `scope`
+    pub fn search_symbols_in(&self, query: &str, scope: &SearchScope) -> Result<Vec<SymbolSearchResult>> {
+        if scope.is_empty() {
+            return self.search_symbols(query);
+        }
+        self.query_symbols(&QueryBuilder::new().with_name(query).within(scope).limit(50))
+    }
+
+    /// Run a [`QueryBuilder`] query
+    pub fn query_symbols(&self, query: &QueryBuilder) -> Result<Vec<SymbolSearchResult>> {
+        let (sql, query_params) = query.build();
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+
+        let results = stmt.query_map(rusqlite::params_from_iter(query_params.iter()), |row| {
+            Ok(SymbolSearchResult {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                kind: row.get(2)?,
+                content: row.get(3)?,
+                file_path: row.get(4)?,
+                start_line: row.get(5)?,
+                end_line: row.get(6)?,
+                metadata: row.get(7)?,
+            })
+        })?;
+
+        let mut symbols = Vec::new();
+        for result in results {
+            symbols.push(result?);
+        }
+        Ok(symbols)
+    }
+
+    /// The symbol with the given id, if it is still indexed
+    pub fn get_symbol(&self, symbol_id: i64) -> Result<Option<SymbolSearchResult>> {
+        let conn = self.conn.lock().unwrap();
+        let symbol = conn
+            .query_row(
+                r#"
+                SELECT s.id, s.name, s.kind, s.content, f.path, s.start_line, s.end_line, s.metadata
+                FROM symbols s
+                JOIN files f ON s.file_id = f.id
+                WHERE s.id = ?1
+                "#,
+                params![symbol_id],
+                |row| {
+                    Ok(SymbolSearchResult {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        kind: row.get(2)?,
+                        content: row.get(3)?,
+                        file_path: row.get(4)?,
+                        start_line: row.get(5)?,
+                        end_line: row.get(6)?,
+                        metadata: row.get(7)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(symbol)
+    }
+
     /// Find symbols by exact name
     pub fn find_symbols_by_name(&self, name: &str) -> Result<Vec<SymbolSearchResult>> {
         let conn = self.conn.lock().unwrap();
@@ -352,6 +951,100 @@ impl KnowledgeGraph {
         Ok(symbols)
     }
 
+    /// Symbols in files owned by `owner`, from CODEOWNERS or git history; the
+    /// leading `@` and case don't matter
+    pub fn find_symbols_by_owner(&self, owner: &str) -> Result<Vec<SymbolSearchResult>> {
+        self.query_symbols(&QueryBuilder::new().owned_by(owner))
+    }
+
+    /// Find exported symbols matching a name (fuzzy): declared with `export`, or
+    /// named in an `export { ... }` clause of their own file
+    pub fn find_exported_symbols(&self, query: &str) -> Result<Vec<SymbolSearchResult>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT s.id, s.name, s.kind, s.content, f.path, s.start_line, s.end_line, s.metadata
+            FROM symbols s
+            JOIN files f ON s.file_id = f.id
+            WHERE s.name LIKE ?1
+              AND s.parent_id IS NULL
+              AND (
+                json_extract(s.metadata, '$.is_exported') = 1
+                OR EXISTS (
+                    SELECT 1 FROM exports e
+                    WHERE e.file_id = s.file_id AND e.name = s.name AND e.source IS NULL
+                )
+              )
+            ORDER BY s.name
+            LIMIT 50
+            "#,
+        )?;
+
+        let pattern = format!("%{}%", query);
+        let results = stmt.query_map(params![pattern], |row| {
+            Ok(SymbolSearchResult {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                kind: row.get(2)?,
+                content: row.get(3)?,
+                file_path: row.get(4)?,
+                start_line: row.get(5)?,
+                end_line: row.get(6)?,
+                metadata: row.get(7)?,
+            })
+        })?;
+
+        let mut symbols = Vec::new();
+        for result in results {
+            symbols.push(result?);
+        }
+        Ok(symbols)
+    }
+
+    /// All custom React hooks (`useXxx` functions) in the codebase
+    pub fn find_hooks(&self) -> Result<Vec<SymbolSearchResult>> {
+        self.find_symbols_by_kind("Hook")
+    }
+
+    /// Find symbols carrying a decorator or attribute (fuzzy): `Injectable` matches
+    /// `@Injectable()`, `Serialize` matches `#[derive(Debug, Serialize)]`
+    pub fn find_symbols_by_decorator(&self, decorator: &str) -> Result<Vec<SymbolSearchResult>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT s.id, s.name, s.kind, s.content, f.path, s.start_line, s.end_line, s.metadata
+            FROM symbols s
+            JOIN files f ON s.file_id = f.id
+            WHERE EXISTS (
+                SELECT 1 FROM json_each(s.metadata, '$.decorators') d
+                WHERE d.value LIKE ?1
+            )
+            ORDER BY s.name
+            LIMIT 100
+            "#,
+        )?;
+
+        let pattern = format!("%{}%", decorator);
+        let results = stmt.query_map(params![pattern], |row| {
+            Ok(SymbolSearchResult {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                kind: row.get(2)?,
+                content: row.get(3)?,
+                file_path: row.get(4)?,
+                start_line: row.get(5)?,
+                end_line: row.get(6)?,
+                metadata: row.get(7)?,
+            })
+        })?;
+
+        let mut symbols = Vec::new();
+        for result in results {
+            symbols.push(result?);
+        }
+        Ok(symbols)
+    }
+
     /// Find symbols by kind (e.g., "Component", "Function")
     pub fn find_symbols_by_kind(&self, kind: &str) -> Result<Vec<SymbolSearchResult>> {
         let conn = self.conn.lock().unwrap();
@@ -448,6 +1141,119 @@ impl KnowledgeGraph {
         Ok(symbols)
     }
 
+    /// How many symbols reference a given symbol name
+    pub fn count_references_to(&self, symbol_name: &str) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(DISTINCT from_symbol_id) FROM symbol_references WHERE to_symbol_name = ?1",
+            params![symbol_name],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    /// Everything a rename of `name` touches: the symbols defining it, the
+    /// symbols referencing it, and the import and export statements naming it,
+    /// with the lines inside each that mention it. A line is attributed to the
+    /// narrowest symbol containing it, so a method isn't counted again under
+    /// its class.
+    pub fn impact_of_rename(&self, name: &str) -> Result<RenameImpact> {
+        let mut locations = Vec::new();
+        for (kind, symbols) in [
+            (ImpactKind::Definition, self.find_symbols_by_name(name)?),
+            (ImpactKind::Reference, self.find_references_to(name)?),
+        ] {
+            for symbol in symbols {
+                let lines = lines_mentioning(&symbol.content, name, symbol.start_line);
+                locations.push(ImpactLocation {
+                    kind,
+                    file_path: symbol.file_path,
+                    symbol: Some(symbol.name),
+                    source: None,
+                    start_line: symbol.start_line,
+                    end_line: symbol.end_line,
+                    lines,
+                });
+            }
+        }
+
+        let conn = self.conn.lock().unwrap();
+        // Names are stored as a JSON array; LIKE narrows the rows before the exact match
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT f.path, i.source, i.names, i.start_line, i.end_line
+            FROM imports i
+            JOIN files f ON i.file_id = f.id
+            WHERE i.names LIKE ?1
+            "#,
+        )?;
+        let rows = stmt.query_map(params![format!("%\"{}\"%", name)], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })?;
+        for row in rows {
+            let (file_path, source, names, start_line, end_line) = row?;
+            let names: Vec<String> = serde_json::from_str(&names).unwrap_or_default();
+            if !names.iter().any(|imported| imported == name) {
+                continue;
+            }
+            locations.push(ImpactLocation {
+                kind: ImpactKind::Import,
+                file_path,
+                symbol: None,
+                source: Some(source),
+                start_line,
+                end_line,
+                lines: (start_line..=end_line).collect(),
+            });
+        }
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT f.path, e.source, e.start_line
+            FROM exports e
+            JOIN files f ON e.file_id = f.id
+            WHERE e.name = ?1
+            "#,
+        )?;
+        let rows = stmt.query_map(params![name], |row| {
+            Ok(ImpactLocation {
+                kind: ImpactKind::Export,
+                file_path: row.get(0)?,
+                symbol: None,
+                source: row.get(1)?,
+                start_line: row.get(2)?,
+                end_line: row.get(2)?,
+                lines: vec![row.get(2)?],
+            })
+        })?;
+        for row in rows {
+            locations.push(row?);
+        }
+
+        // Narrowest first, so each line stays with the innermost symbol holding it
+        locations.sort_by_key(|l| l.end_line - l.start_line);
+        let mut claimed = std::collections::HashSet::new();
+        locations.retain_mut(|location| {
+            if location.symbol.is_none() || location.lines.is_empty() {
+                return true;
+            }
+            let file_path = location.file_path.clone();
+            location.lines.retain(|line| claimed.insert((file_path.clone(), *line)));
+            !location.lines.is_empty() || location.kind == ImpactKind::Definition
+        });
+        locations.sort_by(|a, b| {
+            (&a.file_path, a.start_line, a.kind).cmp(&(&b.file_path, b.start_line, b.kind))
+        });
+
+        Ok(RenameImpact { name: name.to_string(), locations })
+    }
+
     /// Get names of symbols referenced by a given symbol
     pub fn get_symbol_dependencies(&self, symbol_id: i64) -> Result<Vec<String>> {
         let conn = self.conn.lock().unwrap();
@@ -581,12 +1387,94 @@ impl KnowledgeGraph {
         Ok(count as usize)
     }
 
+    /// Paths of all indexed files, sorted
+    pub fn list_files(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT path FROM files ORDER BY path")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+
+        let mut paths = Vec::new();
+        for row in rows {
+            paths.push(row?);
+        }
+        Ok(paths)
+    }
+
+    /// Counts per language, the largest files, parse errors and when the index last changed
+    pub fn index_stats(&self, largest: usize) -> Result<IndexStats> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT f.language,
+                   COUNT(DISTINCT f.id),
+                   COUNT(DISTINCT s.id),
+                   (SELECT COUNT(*) FROM symbol_references r
+                    JOIN symbols rs ON r.from_symbol_id = rs.id
+                    JOIN files rf ON rs.file_id = rf.id
+                    WHERE rf.language = f.language)
+            FROM files f
+            LEFT JOIN symbols s ON s.file_id = f.id
+            GROUP BY f.language
+            ORDER BY COUNT(DISTINCT f.id) DESC, f.language
+            "#,
+        )?;
+        let languages = stmt
+            .query_map([], |row| {
+                Ok(LanguageStats {
+                    language: row.get(0)?,
+                    files: row.get::<_, i64>(1)? as usize,
+                    symbols: row.get::<_, i64>(2)? as usize,
+                    references: row.get::<_, i64>(3)? as usize,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // Files indexed before line counts were stored fall back to the last line a symbol ends on
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT f.path, f.language, COALESCE(f.line_count, MAX(s.end_line), 0), COUNT(s.id)
+            FROM files f
+            LEFT JOIN symbols s ON s.file_id = f.id
+            GROUP BY f.id
+            ORDER BY 3 DESC, 4 DESC, f.path
+            LIMIT ?1
+            "#,
+        )?;
+        let largest_files = stmt
+            .query_map(params![largest as i64], |row| {
+                Ok(FileStats {
+                    path: row.get(0)?,
+                    language: row.get(1)?,
+                    lines: row.get::<_, i64>(2)? as usize,
+                    symbols: row.get::<_, i64>(3)? as usize,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let (index_errors, files_with_errors): (i64, i64) = conn.query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT file_path) FROM index_errors",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let last_indexed_at: Option<String> =
+            conn.query_row("SELECT MAX(indexed_at) FROM files", [], |row| row.get(0))?;
+
+        Ok(IndexStats {
+            languages,
+            largest_files,
+            index_errors: index_errors as usize,
+            files_with_errors: files_with_errors as usize,
+            last_indexed_at,
+        })
+    }
+
     /// Find schemas by name
     pub fn find_schemas(&self, query: &str) -> Result<Vec<SchemaResult>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
             r#"
-            SELECT s.name, s.schema_type, s.definition, f.path, s.start_line, s.end_line
+            SELECT s.name, s.schema_type, s.definition, f.path, s.start_line, s.end_line, s.fields
             FROM schemas s
             JOIN files f ON s.file_id = f.id
             WHERE s.name LIKE ?1
@@ -602,6 +1490,10 @@ impl KnowledgeGraph {
                 file_path: row.get(3)?,
                 start_line: row.get(4)?,
                 end_line: row.get(5)?,
+                fields: row
+                    .get::<_, Option<String>>(6)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
             })
         })?;
 
@@ -611,6 +1503,139 @@ impl KnowledgeGraph {
         }
         Ok(schemas)
     }
+
+    /// Find routes whose path or handler matches (fuzzy); an empty query lists every route
+    pub fn find_routes(&self, query: &str) -> Result<Vec<RouteResult>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT r.method, r.path, r.handler, r.framework, f.path, r.start_line
+            FROM routes r
+            JOIN files f ON r.file_id = f.id
+            WHERE r.path LIKE ?1 OR r.handler LIKE ?1
+            ORDER BY r.path, r.method
+            "#,
+        )?;
+
+        let pattern = format!("%{}%", query);
+        let results = stmt.query_map(params![pattern], |row| {
+            Ok(RouteResult {
+                method: row.get(0)?,
+                path: row.get(1)?,
+                handler: row.get(2)?,
+                framework: row.get(3)?,
+                file_path: row.get(4)?,
+                start_line: row.get(5)?,
+            })
+        })?;
+
+        let mut routes = Vec::new();
+        for result in results {
+            routes.push(result?);
+        }
+        Ok(routes)
+    }
+
+    /// Find environment variable reads by name (fuzzy); an empty query lists every read
+    pub fn find_env_vars(&self, query: &str) -> Result<Vec<EnvVarResult>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT e.name, e.accessor, f.path, e.start_line
+            FROM env_vars e
+            JOIN files f ON e.file_id = f.id
+            WHERE e.name LIKE ?1
+            ORDER BY e.name, f.path, e.start_line
+            "#,
+        )?;
+
+        let pattern = format!("%{}%", query);
+        let results = stmt.query_map(params![pattern], |row| {
+            Ok(EnvVarResult {
+                name: row.get(0)?,
+                accessor: row.get(1)?,
+                file_path: row.get(2)?,
+                start_line: row.get(3)?,
+            })
+        })?;
+
+        let mut env_vars = Vec::new();
+        for result in results {
+            env_vars.push(result?);
+        }
+        Ok(env_vars)
+    }
+
+    /// Find tests that exercise a symbol: tests whose body references it by
+    /// name, plus tests named after it (`test_parse_config` for `parse_config`)
+    pub fn find_tests_for(&self, symbol_name: &str) -> Result<Vec<TestResult>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT t.name, t.framework, t.content, f.path, t.start_line, t.end_line,
+                   t.id IN (SELECT test_id FROM test_references WHERE symbol_name = ?1)
+            FROM tests t
+            JOIN files f ON t.file_id = f.id
+            WHERE t.id IN (SELECT test_id FROM test_references WHERE symbol_name = ?1)
+               OR t.name LIKE ?2 ESCAPE '\'
+            ORDER BY f.path, t.start_line
+            "#,
+        )?;
+
+        // `_` and `%` are LIKE wildcards and common in identifiers; the name is checked exactly below
+        let escaped = symbol_name.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let pattern = format!("%{}%", escaped);
+        let results = stmt.query_map(params![symbol_name, pattern], |row| {
+            let referenced: bool = row.get(6)?;
+            Ok((
+                referenced,
+                TestResult {
+                    name: row.get(0)?,
+                    framework: row.get(1)?,
+                    content: row.get(2)?,
+                    file_path: row.get(3)?,
+                    start_line: row.get(4)?,
+                    end_line: row.get(5)?,
+                },
+            ))
+        })?;
+
+        let mut tests = Vec::new();
+        for result in results {
+            let (referenced, test) = result?;
+            if referenced || test_named_after(&test.name, symbol_name) {
+                tests.push(test);
+            }
+        }
+        Ok(tests)
+    }
+
+    /// List the parse diagnostics recorded by the last index, grouped by file
+    pub fn list_index_errors(&self) -> Result<Vec<IndexErrorResult>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT file_path, line, column, message
+            FROM index_errors
+            ORDER BY file_path, line, column
+            "#,
+        )?;
+
+        let results = stmt.query_map([], |row| {
+            Ok(IndexErrorResult {
+                file_path: row.get(0)?,
+                line: row.get(1)?,
+                column: row.get(2)?,
+                message: row.get(3)?,
+            })
+        })?;
+
+        let mut errors = Vec::new();
+        for result in results {
+            errors.push(result?);
+        }
+        Ok(errors)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -662,4 +1687,185 @@ pub struct SchemaResult {
     pub file_path: String,
     pub start_line: i64,
     pub end_line: i64,
+    pub fields: Vec<SchemaFieldData>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteResult {
+    pub method: String,
+    pub path: String,
+    pub handler: Option<String>,
+    pub framework: String,
+    pub file_path: String,
+    pub start_line: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvVarResult {
+    pub name: String,
+    pub accessor: String,
+    pub file_path: String,
+    pub start_line: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestResult {
+    pub name: String,
+    pub framework: String,
+    pub content: String,
+    pub file_path: String,
+    pub start_line: i64,
+    pub end_line: i64,
+}
+
+/// Health report of an index, see [`KnowledgeGraph::index_stats`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexStats {
+    pub languages: Vec<LanguageStats>,
+    pub largest_files: Vec<FileStats>,
+    pub index_errors: usize,
+    pub files_with_errors: usize,
+    /// UTC time a file was last (re)indexed, as SQLite writes it
+    pub last_indexed_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageStats {
+    pub language: String,
+    pub files: usize,
+    pub symbols: usize,
+    pub references: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileStats {
+    pub path: String,
+    pub language: String,
+    pub lines: usize,
+    pub symbols: usize,
+}
+
+/// An API token for the web server; the token itself is never stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiTokenRecord {
+    pub name: String,
+    /// Hex SHA-256 of the token
+    pub token_hash: String,
+    /// Codebases the token may use; empty allows any
+    pub paths: Vec<String>,
+    /// Unix time
+    pub created_at: i64,
+}
+
+/// Votes on whether a context item was useful
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackRecord {
+    pub file_path: String,
+    /// Empty for the whole file
+    pub name: String,
+    pub useful: u32,
+    pub not_useful: u32,
+    /// Unix time of the last vote
+    pub updated_at: i64,
+}
+
+impl FeedbackRecord {
+    /// Largest boost or penalty feedback can add to an item's relevance
+    pub const MAX_WEIGHT: f32 = 0.3;
+
+    /// Relevance adjustment: the net share of useful votes, scaled to
+    /// `MAX_WEIGHT` and damped so a single vote only moves it a third of the way
+    pub fn weight(&self) -> f32 {
+        let net = self.useful as f32 - self.not_useful as f32;
+        Self::MAX_WEIGHT * net / (self.useful + self.not_useful + 2) as f32
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexErrorResult {
+    pub file_path: String,
+    pub line: i64,
+    pub column: i64,
+    pub message: String,
+}
+
+/// The places a rename of a symbol has to touch, sorted by file and line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameImpact {
+    pub name: String,
+    pub locations: Vec<ImpactLocation>,
+}
+
+impl RenameImpact {
+    /// Distinct files touched, sorted
+    pub fn files(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self.locations.iter().map(|l| l.file_path.as_str()).collect();
+        files.dedup();
+        files
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImpactKind {
+    Definition,
+    Import,
+    Export,
+    Reference,
+}
+
+impl ImpactKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImpactKind::Definition => "definition",
+            ImpactKind::Import => "import",
+            ImpactKind::Export => "export",
+            ImpactKind::Reference => "reference",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactLocation {
+    pub kind: ImpactKind,
+    pub file_path: String,
+    /// The defining or referencing symbol; `None` for imports and exports
+    pub symbol: Option<String>,
+    /// Module an import or re-export comes from
+    pub source: Option<String>,
+    pub start_line: i64,
+    pub end_line: i64,
+    /// Lines within the location that mention the name
+    pub lines: Vec<i64>,
+}
+
+/// Whether a test is named after `symbol`: `test_parse_config`, `parse_config_test`,
+/// or a described test with it as a whole word (`parse_config rejects tabs`)
+fn test_named_after(test_name: &str, symbol: &str) -> bool {
+    let bare = test_name
+        .strip_prefix("test_")
+        .or_else(|| test_name.strip_suffix("_test"))
+        .unwrap_or(test_name);
+    bare == symbol
+        || test_name
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .any(|word| word == symbol)
+}
+
+/// Line numbers of `content` (which starts at `start_line`) mentioning `name`
+/// as a whole word
+fn lines_mentioning(content: &str, name: &str, start_line: i64) -> Vec<i64> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            line.match_indices(name).any(|(at, _)| {
+                let before = line[..at].chars().next_back();
+                let after = line[at + name.len()..].chars().next();
+                !before.is_some_and(is_word) && !after.is_some_and(is_word)
+            })
+        })
+        .map(|(i, _)| start_line + i as i64)
+        .collect()
 }
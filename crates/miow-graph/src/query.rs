@@ -1,12 +1,12 @@
 // Query utilities for the knowledge graph
 // This module can be expanded with more complex query logic
 
-use anyhow::Result;
-
 /// Query builder for complex symbol searches
 pub struct QueryBuilder {
     conditions: Vec<String>,
     params: Vec<String>,
+    limit: Option<usize>,
+    offset: Option<usize>,
 }
 
 impl QueryBuilder {
@@ -14,6 +14,8 @@ impl QueryBuilder {
         Self {
             conditions: Vec::new(),
             params: Vec::new(),
+            limit: None,
+            offset: None,
         }
     }
 
@@ -24,11 +26,64 @@ impl QueryBuilder {
     }
 
     pub fn with_kind(mut self, kind: &str) -> Self {
-        self.conditions.push("s.kind = ?".to_string());
+        self.conditions.push("s.kind = ? COLLATE NOCASE".to_string());
         self.params.push(kind.to_string());
         self
     }
 
+    /// Symbols in files whose path contains `path`
+    pub fn with_file(mut self, path: &str) -> Self {
+        self.conditions.push("f.path LIKE ?".to_string());
+        self.params.push(format!("%{}%", path));
+        self
+    }
+
+    /// Symbols that reference a symbol named `name`
+    pub fn referencing(mut self, name: &str) -> Self {
+        self.conditions
+            .push("s.id IN (SELECT from_symbol_id FROM symbol_references WHERE to_symbol_name = ?)".to_string());
+        self.params.push(name.to_string());
+        self
+    }
+
+    /// Symbols in files owned by `owner`, ignoring case and a leading `@`
+    pub fn owned_by(mut self, owner: &str) -> Self {
+        self.conditions.push(
+            "f.path IN (SELECT file_path FROM file_owners WHERE LTRIM(owner, '@') = LTRIM(?, '@') COLLATE NOCASE)"
+                .to_string(),
+        );
+        self.params.push(owner.to_string());
+        self
+    }
+
+    /// Symbols inside `scope`'s directories and of its kinds
+    pub fn within(mut self, scope: &SearchScope) -> Self {
+        if !scope.paths.is_empty() {
+            let any_path = vec!["(f.path = ? OR substr(f.path, 1, length(?)) = ?)"; scope.paths.len()].join(" OR ");
+            self.conditions.push(format!("({})", any_path));
+            for path in &scope.paths {
+                let dir = format!("{}/", path);
+                self.params.extend([path.clone(), dir.clone(), dir]);
+            }
+        }
+        if !scope.kinds.is_empty() {
+            self.conditions.push(format!("s.kind COLLATE NOCASE IN ({})", vec!["?"; scope.kinds.len()].join(", ")));
+            self.params.extend(scope.kinds.iter().cloned());
+        }
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skip the first `offset` results, for paging
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
     pub fn build(&self) -> (String, Vec<String>) {
         let where_clause = if self.conditions.is_empty() {
             String::new()
@@ -36,18 +91,71 @@ impl QueryBuilder {
             format!("WHERE {}", self.conditions.join(" AND "))
         };
 
+        let mut limit_clause = self.limit.map(|n| format!(" LIMIT {}", n)).unwrap_or_default();
+        if let Some(offset) = self.offset {
+            // SQLite only takes OFFSET after a LIMIT; -1 is no limit
+            if self.limit.is_none() {
+                limit_clause.push_str(" LIMIT -1");
+            }
+            limit_clause.push_str(&format!(" OFFSET {}", offset));
+        }
+
         let query = format!(
-            "SELECT s.id, s.name, s.kind, s.content, f.path, s.start_line, s.end_line \
+            "SELECT s.id, s.name, s.kind, s.content, f.path, s.start_line, s.end_line, s.metadata \
              FROM symbols s \
              JOIN files f ON s.file_id = f.id \
-             {}",
-            where_clause
+             {} \
+             ORDER BY s.name, f.path{}",
+            where_clause, limit_clause
         );
 
         (query, self.params.clone())
     }
 }
 
+/// Part of the codebase a search is limited to: files under any of `paths`
+/// (relative to the project root) and symbols of any of `kinds`. Empty lists
+/// don't restrict anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchScope {
+    pub paths: Vec<String>,
+    pub kinds: Vec<String>,
+}
+
+impl SearchScope {
+    pub fn new(paths: &[String], kinds: &[String]) -> Self {
+        let paths = paths
+            .iter()
+            .map(|p| p.trim().trim_start_matches("./").trim_end_matches('/').to_string())
+            .filter(|p| !p.is_empty() && p != ".")
+            .collect();
+        let kinds = kinds.iter().map(|k| k.trim().to_string()).filter(|k| !k.is_empty()).collect();
+        Self { paths, kinds }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.kinds.is_empty()
+    }
+
+    /// Whether `file_path` is in one of the scope's directories
+    pub fn contains_path(&self, file_path: &str) -> bool {
+        let file_path = file_path.trim_start_matches("./");
+        self.paths.is_empty()
+            || self.paths.iter().any(|p| {
+                file_path == p || file_path.strip_prefix(p.as_str()).is_some_and(|rest| rest.starts_with('/'))
+            })
+    }
+
+    /// Whether a symbol of `kind` is one of the scope's kinds
+    pub fn contains_kind(&self, kind: &str) -> bool {
+        self.kinds.is_empty() || self.kinds.iter().any(|k| k.eq_ignore_ascii_case(kind))
+    }
+
+    pub fn matches(&self, file_path: &str, kind: &str) -> bool {
+        self.contains_path(file_path) && self.contains_kind(kind)
+    }
+}
+
 impl Default for QueryBuilder {
     fn default() -> Self {
         Self::new()
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use super::relationship_inference::{LLMProvider, LLMResponse};
+use super::relationship_inference::LLMProvider;
 
 /// Query expansion for better search coverage
 pub struct QueryExpander {
@@ -145,11 +145,21 @@ Respond ONLY with valid JSON."#,
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::relationship_inference::LLMResponse;
+
+    struct MockLLM;
+
+    #[async_trait::async_trait]
+    impl LLMProvider for MockLLM {
+        async fn generate(&self, _prompt: &str) -> Result<LLMResponse> {
+            Ok(LLMResponse { content: "{}".to_string() })
+        }
+    }
     
     #[test]
     fn test_get_all_terms() {
         let expander = QueryExpander {
-            llm: Arc::new(crate::tests::MockLLM),
+            llm: Arc::new(MockLLM),
             cache: HashMap::new(),
         };
         
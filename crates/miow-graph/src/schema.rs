@@ -7,11 +7,37 @@ use serde::{Deserialize, Serialize};
 pub struct ParsedFileData {
     pub symbols: Vec<SymbolData>,
     pub imports: Vec<ImportData>,
+    #[serde(default)]
+    pub exports: Vec<ExportData>,
     pub design_tokens: Vec<DesignTokenData>,
     pub type_definitions: Vec<TypeDefinitionData>,
     pub constants: Vec<ConstantData>,
     pub schemas: Vec<SchemaData>,
+    #[serde(default)]
+    pub routes: Vec<RouteData>,
+    #[serde(default)]
+    pub env_vars: Vec<EnvVarData>,
+    #[serde(default)]
+    pub tests: Vec<TestData>,
+    /// Syntax errors tree-sitter recovered from; symbols outside them were still extracted
+    #[serde(default)]
+    pub index_errors: Vec<IndexErrorData>,
     pub language: String,
+    /// Used by incremental reindexing to skip unchanged files
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    #[serde(default)]
+    pub modified_at: Option<i64>,
+    /// Lines in the file, reported by `stats`
+    #[serde(default)]
+    pub line_count: Option<i64>,
+}
+
+/// What an earlier index recorded about a file, for change detection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileFingerprint {
+    pub content_hash: Option<String>,
+    pub modified_at: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +63,64 @@ pub struct ImportData {
     pub end_line: usize,
 }
 
+/// An export statement; `source` is set for re-exports (`export * from './x'`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportData {
+    pub name: String,
+    pub alias: Option<String>,
+    pub source: Option<String>,
+    pub is_default: bool,
+    pub is_type: bool,
+    pub start_line: usize,
+}
+
+/// An HTTP route or file-system page (`method` is `PAGE` for pages, `ANY` for catch-all handlers)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteData {
+    pub method: String,
+    pub path: String,
+    pub handler: Option<String>,
+    pub framework: String,
+    pub start_line: usize,
+}
+
+/// An environment variable read; `accessor` is how it was read (`process.env`, `env::var`, ...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvVarData {
+    pub name: String,
+    pub accessor: String,
+    pub start_line: usize,
+}
+
+/// A test case; `references` are identifiers its body uses, matched against symbol names
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestData {
+    pub name: String,
+    pub framework: String,
+    pub content: String,
+    pub references: Vec<String>,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Who owns a file: a CODEOWNERS entry (`@org/team`, `@user` or an email) or,
+/// for `source` `git`, the email of the author of most of its recent commits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileOwnerData {
+    pub file_path: String,
+    pub owner: String,
+    /// `codeowners` or `git`
+    pub source: String,
+}
+
+/// A parse diagnostic recorded while indexing a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexErrorData {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DesignTokenData {
     pub token_type: String,
@@ -72,4 +156,16 @@ pub struct SchemaData {
     pub definition: String,
     pub start_line: usize,
     pub end_line: usize,
+    #[serde(default)]
+    pub fields: Vec<SchemaFieldData>,
+}
+
+/// A schema field or database column; `references` holds the relation target (e.g. `users.id`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaFieldData {
+    pub name: String,
+    pub field_type: Option<String>,
+    pub is_required: bool,
+    pub default_value: Option<String>,
+    pub references: Option<String>,
 }
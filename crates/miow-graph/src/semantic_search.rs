@@ -90,7 +90,7 @@ impl SemanticGraphSearch {
     }
     
     /// Get symbol dependencies up to specified depth
-    fn get_dependencies(&self, symbol_id: i64, depth: usize) -> Result<Vec<String>> {
+    fn get_dependencies(&self, _symbol_id: i64, depth: usize) -> Result<Vec<String>> {
         if depth == 0 {
             return Ok(vec![]);
         }
@@ -101,7 +101,7 @@ impl SemanticGraphSearch {
     }
     
     /// Get symbols that depend on this symbol
-    fn get_dependents(&self, symbol_id: i64, depth: usize) -> Result<Vec<String>> {
+    fn get_dependents(&self, _symbol_id: i64, depth: usize) -> Result<Vec<String>> {
         if depth == 0 {
             return Ok(vec![]);
         }
@@ -123,9 +123,9 @@ impl SemanticGraphSearch {
     /// Find related symbols using graph structure
     pub fn find_related(
         &self,
-        symbol_id: i64,
-        relationship_types: &[RelationshipType],
-        limit: usize,
+        _symbol_id: i64,
+        _relationship_types: &[RelationshipType],
+        _limit: usize,
     ) -> Result<Vec<SemanticSearchResult>> {
         // This would traverse the graph following specific relationship types
         // For now, return empty
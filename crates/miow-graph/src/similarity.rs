@@ -0,0 +1,313 @@
+//! Structural similarity between symbols: what they reference, which modules
+//! their files import, and the props or parameters they take. Features are
+//! weighted by rarity, so two components that both import `react` are barely
+//! alike while two that both call `useCheckout` are.
+
+use crate::{CatalogProp, KnowledgeGraph, SymbolSearchResult};
+use anyhow::Result;
+use rusqlite::params;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// How much each kind of feature counts towards the score
+const REFERENCE_WEIGHT: f32 = 0.4;
+const IMPORT_WEIGHT: f32 = 0.3;
+const SHAPE_WEIGHT: f32 = 0.3;
+
+/// A symbol structurally like one of the seeds
+#[derive(Debug, Clone)]
+pub struct SimilarSymbol {
+    pub symbol: SymbolSearchResult,
+    /// 0.0–1.0: rarity-weighted overlap with the most similar seed
+    pub score: f32,
+    /// Name of that seed
+    pub similar_to: String,
+    pub shared_references: Vec<String>,
+    pub shared_imports: Vec<String>,
+    /// Prop names for components, parameter names otherwise
+    pub shared_shape: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct Profile {
+    references: HashSet<String>,
+    imports: HashSet<String>,
+    shape: HashSet<String>,
+}
+
+impl KnowledgeGraph {
+    /// Top-level symbols of the seeds' kinds most like the seeds, which are
+    /// given as `(file_path, name)`. The seeds themselves and symbols sharing
+    /// nothing with them are left out.
+    pub fn similar_symbols(&self, seeds: &[(String, String)], limit: usize) -> Result<Vec<SimilarSymbol>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT s.id, s.name, s.kind, s.content, f.path, s.start_line, s.end_line, s.metadata, s.file_id
+            FROM symbols s
+            JOIN files f ON s.file_id = f.id
+            WHERE s.parent_id IS NULL AND s.kind IN (
+                SELECT s2.kind FROM symbols s2 JOIN files f2 ON s2.file_id = f2.id
+                WHERE f2.path = ?1 AND s2.name = ?2
+            )
+            "#,
+        )?;
+        let mut symbols: Vec<(SymbolSearchResult, i64)> = Vec::new();
+        let mut seen = HashSet::new();
+        for (file_path, name) in seeds {
+            let rows = stmt.query_map(params![file_path, name], |row| {
+                Ok((
+                    SymbolSearchResult {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        kind: row.get(2)?,
+                        content: row.get(3)?,
+                        file_path: row.get(4)?,
+                        start_line: row.get(5)?,
+                        end_line: row.get(6)?,
+                        metadata: row.get(7)?,
+                    },
+                    row.get::<_, i64>(8)?,
+                ))
+            })?;
+            for row in rows {
+                let row = row?;
+                if seen.insert(row.0.id) {
+                    symbols.push(row);
+                }
+            }
+        }
+        if symbols.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut profiles: HashMap<i64, Profile> = symbols.iter().map(|(s, _)| (s.id, Profile::default())).collect();
+
+        let mut stmt = conn.prepare("SELECT from_symbol_id, to_symbol_name FROM symbol_references")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (id, name) = row?;
+            if let Some(profile) = profiles.get_mut(&id) {
+                profile.references.insert(name);
+            }
+        }
+
+        let mut file_imports: HashMap<i64, HashSet<String>> = HashMap::new();
+        let mut stmt = conn.prepare("SELECT i.file_id, f.path, i.source FROM imports i JOIN files f ON i.file_id = f.id")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?;
+        for row in rows {
+            let (file_id, file_path, source) = row?;
+            file_imports.entry(file_id).or_default().insert(resolve_import(&file_path, &source));
+        }
+
+        let mut catalog: HashMap<i64, HashSet<String>> = HashMap::new();
+        let mut stmt = conn.prepare("SELECT symbol_id, props FROM component_catalog")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (id, props) = row?;
+            let props: Vec<CatalogProp> = serde_json::from_str(&props).unwrap_or_default();
+            catalog.insert(id, props.into_iter().map(|p| p.name).collect());
+        }
+
+        for (symbol, file_id) in &symbols {
+            let Some(profile) = profiles.get_mut(&symbol.id) else { continue };
+            // Recursion says nothing about what a symbol is like
+            profile.references.remove(&symbol.name);
+            profile.imports = file_imports.get(file_id).cloned().unwrap_or_default();
+            profile.shape = catalog.remove(&symbol.id).unwrap_or_else(|| parameter_names(symbol.metadata.as_deref()));
+        }
+
+        let rarity = Rarity::of(profiles.values());
+        let seed_ids: Vec<(i64, String)> = symbols
+            .iter()
+            .filter(|(s, _)| seeds.iter().any(|(path, name)| *path == s.file_path && *name == s.name))
+            .map(|(s, _)| (s.id, s.name.clone()))
+            .collect();
+
+        let shared = |a: &HashSet<String>, b: &HashSet<String>| {
+            let mut shared: Vec<String> = a.intersection(b).cloned().collect();
+            shared.sort();
+            shared
+        };
+        let mut similar = Vec::new();
+        for (symbol, _) in symbols {
+            if seed_ids.iter().any(|(id, _)| *id == symbol.id) {
+                continue;
+            }
+            let profile = &profiles[&symbol.id];
+            let best = seed_ids
+                .iter()
+                .map(|(id, name)| (&profiles[id], name, rarity.score(&profiles[id], profile)))
+                .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal));
+            let Some((seed, seed_name, score)) = best.filter(|(_, _, score)| *score > 0.0) else { continue };
+            similar.push(SimilarSymbol {
+                score,
+                similar_to: seed_name.clone(),
+                shared_references: shared(&seed.references, &profile.references),
+                shared_imports: shared(&seed.imports, &profile.imports),
+                shared_shape: shared(&seed.shape, &profile.shape),
+                symbol,
+            });
+        }
+        // Stable, so equally similar symbols keep the index's order
+        similar.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+        similar.truncate(limit);
+        Ok(similar)
+    }
+}
+
+/// How many profiles have each feature, to weight features by rarity
+struct Rarity {
+    profiles: usize,
+    references: HashMap<String, usize>,
+    imports: HashMap<String, usize>,
+    shape: HashMap<String, usize>,
+}
+
+impl Rarity {
+    fn of<'a>(profiles: impl Iterator<Item = &'a Profile>) -> Self {
+        let mut rarity = Self { profiles: 0, references: HashMap::new(), imports: HashMap::new(), shape: HashMap::new() };
+        for profile in profiles {
+            rarity.profiles += 1;
+            for (counts, features) in [
+                (&mut rarity.references, &profile.references),
+                (&mut rarity.imports, &profile.imports),
+                (&mut rarity.shape, &profile.shape),
+            ] {
+                for feature in features {
+                    *counts.entry(feature.clone()).or_default() += 1;
+                }
+            }
+        }
+        rarity
+    }
+
+    /// Weighted mean of the overlap of each kind of feature the seed has
+    fn score(&self, seed: &Profile, other: &Profile) -> f32 {
+        let mut total = 0.0;
+        let mut weights = 0.0;
+        for (weight, counts, a, b) in [
+            (REFERENCE_WEIGHT, &self.references, &seed.references, &other.references),
+            (IMPORT_WEIGHT, &self.imports, &seed.imports, &other.imports),
+            (SHAPE_WEIGHT, &self.shape, &seed.shape, &other.shape),
+        ] {
+            if a.is_empty() {
+                continue;
+            }
+            let idf = |feature: &String| (1.0 + self.profiles as f32 / counts.get(feature).copied().unwrap_or(1) as f32).ln();
+            total += weight * weighted_jaccard(a, b, idf);
+            weights += weight;
+        }
+        if weights == 0.0 {
+            0.0
+        } else {
+            total / weights
+        }
+    }
+}
+
+/// Jaccard overlap where each feature counts its weight instead of one
+fn weighted_jaccard(a: &HashSet<String>, b: &HashSet<String>, weight: impl Fn(&String) -> f32) -> f32 {
+    let shared: f32 = a.intersection(b).map(&weight).sum();
+    let all: f32 = a.union(b).map(&weight).sum();
+    if all == 0.0 {
+        0.0
+    } else {
+        shared / all
+    }
+}
+
+/// A relative import as a path from the repository root without extension,
+/// so `./Button` in `src/Card.tsx` and `../Button` in `src/forms/Login.tsx`
+/// compare equal; package and alias imports are kept as written
+fn resolve_import(file_path: &str, source: &str) -> String {
+    if !source.starts_with('.') {
+        return source.to_string();
+    }
+    let mut parts: Vec<&str> = file_path.split('/').collect();
+    parts.pop();
+    for segment in source.split('/') {
+        match segment {
+            "." | "" => {}
+            ".." => {
+                parts.pop();
+            }
+            segment => parts.push(segment),
+        }
+    }
+    let resolved = parts.join("/");
+    match resolved.rsplit_once('.') {
+        Some((stem, extension)) if !stem.ends_with('/') && !extension.contains('/') => stem.to_string(),
+        _ => resolved,
+    }
+}
+
+/// Parameter names in serialized symbol metadata. Older indexes stored the
+/// metadata JSON as a JSON string, so that is unwrapped first.
+fn parameter_names(metadata: Option<&str>) -> HashSet<String> {
+    let Some(mut value) = metadata.and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok()) else {
+        return HashSet::new();
+    };
+    if let Some(inner) = value.as_str().and_then(|inner| serde_json::from_str(inner).ok()) {
+        value = inner;
+    }
+    value
+        .get("parameters")
+        .and_then(|p| p.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.get("name").and_then(|n| n.as_str()))
+        // Destructuring patterns and `props` say nothing about the shape
+        .filter(|name| *name != "props" && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$'))
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(features: &[&str]) -> HashSet<String> {
+        features.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve_import() {
+        assert_eq!(resolve_import("src/components/Card.tsx", "./Button"), "src/components/Button");
+        assert_eq!(resolve_import("src/forms/Login.tsx", "../components/Button.tsx"), "src/components/Button");
+        assert_eq!(resolve_import("src/Card.tsx", "react"), "react");
+        assert_eq!(resolve_import("src/Card.tsx", "@/lib/api"), "@/lib/api");
+    }
+
+    #[test]
+    fn test_rare_features_count_more() {
+        let profile = |references: &[&str], imports: &[&str]| Profile {
+            references: set(references),
+            imports: set(imports),
+            shape: HashSet::new(),
+        };
+        let profiles = [
+            profile(&["useCheckout", "Button"], &["react", "@/lib/cart"]),
+            profile(&["useCheckout"], &["react", "@/lib/cart"]),
+            profile(&["Button"], &["react"]),
+            profile(&["Button"], &["react"]),
+        ];
+        let rarity = Rarity::of(profiles.iter());
+        let seed = &profiles[0];
+        let checkout = rarity.score(seed, &profiles[1]);
+        let button = rarity.score(seed, &profiles[2]);
+        assert!(checkout > button, "{} <= {}", checkout, button);
+        assert_eq!(rarity.score(seed, &profile(&[], &[])), 0.0);
+        assert!((rarity.score(seed, seed) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_parameter_names() {
+        let metadata = serde_json::to_string(
+            &r#"{"parameters": [{"name": "email"}, {"name": "{ strict }"}, {"name": "props"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(parameter_names(Some(&metadata)), set(&["email"]));
+        assert!(parameter_names(None).is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+use super::*;
+use crate::error::with_deadline;
+use crate::openai::{chat_completion_body, parse_chat_completion, stream_chat_completion};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const DEFAULT_API_VERSION: &str = "2024-06-01";
+
+/// How requests to Azure OpenAI are authenticated
+#[derive(Debug, Clone)]
+pub enum AzureAuth {
+    /// Resource key sent as the `api-key` header
+    ApiKey(String),
+    /// Microsoft Entra ID (AAD) access token sent as a bearer token
+    BearerToken(String),
+}
+
+/// Azure OpenAI client. Requests are routed to a deployment rather than a model:
+/// `{endpoint}/openai/deployments/{deployment}/chat/completions?api-version=...`
+pub struct AzureOpenAIClient {
+    client: Client,
+    endpoint: String,
+    api_version: String,
+    auth: AzureAuth,
+    /// Model name -> deployment name
+    deployments: HashMap<String, String>,
+    model: String,
+    temperature: f32,
+    max_tokens: usize,
+    limiter: Arc<RateLimiter>,
+    /// Deadline for a whole call, rate-limit wait included
+    timeout: Option<std::time::Duration>,
+}
+
+impl AzureOpenAIClient {
+    /// `endpoint` is the resource URL, e.g. `https://my-resource.openai.azure.com`
+    pub fn new(endpoint: impl Into<String>, auth: AzureAuth, deployment: impl Into<String>) -> Self {
+        let deployment = deployment.into();
+        let mut deployments = HashMap::new();
+        deployments.insert(deployment.clone(), deployment.clone());
+
+        Self {
+            client: Client::new(),
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            api_version: DEFAULT_API_VERSION.to_string(),
+            auth,
+            deployments,
+            model: deployment,
+            temperature: 0.7,
+            max_tokens: 4096,
+            limiter: RateLimiter::shared("azure", RateLimitConfig::for_provider("azure")),
+            timeout: LLMConfig::default().timeout,
+        }
+    }
+
+    /// Build a client from `AZURE_OPENAI_ENDPOINT`, `AZURE_OPENAI_DEPLOYMENT`,
+    /// `AZURE_OPENAI_API_VERSION` and either `AZURE_OPENAI_API_KEY` or `AZURE_OPENAI_AD_TOKEN`
+    pub fn from_env() -> Result<Self> {
+        let endpoint = std::env::var("AZURE_OPENAI_ENDPOINT")
+            .context("AZURE_OPENAI_ENDPOINT environment variable not set")?;
+        let deployment = std::env::var("AZURE_OPENAI_DEPLOYMENT")
+            .context("AZURE_OPENAI_DEPLOYMENT environment variable not set")?;
+
+        let auth = if let Ok(key) = std::env::var("AZURE_OPENAI_API_KEY") {
+            AzureAuth::ApiKey(key)
+        } else if let Ok(token) = std::env::var("AZURE_OPENAI_AD_TOKEN") {
+            AzureAuth::BearerToken(token)
+        } else {
+            return Err(LlmError::MissingCredentials(
+                "Set AZURE_OPENAI_API_KEY or AZURE_OPENAI_AD_TOKEN for Azure OpenAI".to_string(),
+            )
+            .into());
+        };
+
+        let mut client = Self::new(endpoint, auth, deployment);
+        if let Ok(version) = std::env::var("AZURE_OPENAI_API_VERSION") {
+            client = client.with_api_version(version);
+        }
+        Ok(client)
+    }
+
+    pub fn with_api_version(mut self, api_version: impl Into<String>) -> Self {
+        self.api_version = api_version.into();
+        self
+    }
+
+    /// Route requests for `model` to `deployment`
+    pub fn with_deployment(mut self, model: impl Into<String>, deployment: impl Into<String>) -> Self {
+        self.deployments.insert(model.into(), deployment.into());
+        self
+    }
+
+    /// Select the model to use; it must map to a deployment (or be a deployment name itself)
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Apply model, temperature, token limit, timeout and rate limit from an `LLMConfig`
+    pub fn with_config(mut self, config: &LLMConfig) -> Self {
+        self.model = config.model.clone();
+        self.temperature = config.temperature;
+        self.max_tokens = config.max_tokens;
+        if config.timeout.is_some() {
+            self.timeout = config.timeout;
+        }
+        if let Some(rate_limit) = config.rate_limit {
+            self.limiter = Arc::new(RateLimiter::new(rate_limit));
+        }
+        self
+    }
+
+    fn request(&self, body: &serde_json::Value) -> reqwest::RequestBuilder {
+        let request = self.client.post(self.chat_completions_url()).json(body);
+        match &self.auth {
+            AzureAuth::ApiKey(key) => request.header("api-key", key),
+            AzureAuth::BearerToken(token) => request.bearer_auth(token),
+        }
+    }
+
+    fn deployment(&self) -> &str {
+        self.deployments
+            .get(&self.model)
+            .map(|d| d.as_str())
+            .unwrap_or(&self.model)
+    }
+
+    fn chat_completions_url(&self) -> String {
+        format!(
+            "{}/openai/deployments/{}/chat/completions?api-version={}",
+            self.endpoint,
+            self.deployment(),
+            self.api_version
+        )
+    }
+}
+
+#[async_trait]
+impl LLMProvider for AzureOpenAIClient {
+    async fn generate(&self, prompt: &str) -> Result<LLMResponse> {
+        let messages = vec![Message {
+            role: Role::User,
+            content: prompt.to_string(),
+        }];
+        self.generate_with_context(messages).await
+    }
+
+    #[tracing::instrument(name = "llm", skip_all, fields(
+        gen_ai.system = "az.ai.openai",
+        gen_ai.request.model = %self.model,
+        gen_ai.usage.input_tokens = tracing::field::Empty,
+        gen_ai.usage.output_tokens = tracing::field::Empty,
+    ))]
+    async fn generate_with_context(&self, messages: Vec<Message>) -> Result<LLMResponse> {
+        // Azure selects the model through the deployment in the URL
+        let body = chat_completion_body(None, messages, self.temperature, self.max_tokens);
+
+        let response = with_deadline("Azure OpenAI", self.timeout, async {
+            let _permit = self.limiter.acquire().await;
+            let response = self
+                .request(&body)
+                .send()
+                .await
+                .map_err(|source| LlmError::Unreachable { provider: "Azure OpenAI".to_string(), source })?;
+            parse_chat_completion(response, "Azure OpenAI").await
+        })
+        .await?;
+        record_usage(response.usage.as_ref());
+        Ok(response)
+    }
+
+    async fn stream_generate(&self, prompt: &str) -> Result<TextStream> {
+        let messages = vec![Message { role: Role::User, content: prompt.to_string() }];
+        let mut body = chat_completion_body(None, messages, self.temperature, self.max_tokens);
+        body["stream"] = serde_json::json!(true);
+
+        // The deadline bounds getting the stream started, not reading it
+        with_deadline("Azure OpenAI", self.timeout, async {
+            let _permit = self.limiter.acquire().await;
+            stream_chat_completion(self.request(&body), "Azure OpenAI").await
+        })
+        .await
+    }
+
+    async fn generate_multi_step(&self, steps: Vec<String>, context: &str) -> Result<LLMResponse> {
+        let mut final_content = String::new();
+
+        for (i, step_prompt) in steps.iter().enumerate() {
+            let full_prompt = format!("Step {}/{}: {}\nContext: {}", i + 1, steps.len(), step_prompt, context);
+            let response = self.generate(&full_prompt).await?;
+            final_content += &format!("Step {}: {}\n", i + 1, response.content);
+        }
+
+        Ok(LLMResponse {
+            content: final_content,
+            finish_reason: None,
+            usage: None,
+        })
+    }
+
+    async fn generate_with_framework(&self, prompt: &str, framework: &str, lang: &str) -> Result<LLMResponse> {
+        let enhanced_prompt = format!(
+            "You are an expert {} developer using {} framework.\n\n{}",
+            lang, framework, prompt
+        );
+        self.generate(&enhanced_prompt).await
+    }
+
+    fn provider_name(&self) -> &str {
+        "azure"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+    fn cache_identity(&self) -> String {
+        format!(
+            "{} deployment={} api-version={} temperature={} max_tokens={}",
+            self.endpoint,
+            self.deployment(),
+            self.api_version,
+            self.temperature,
+            self.max_tokens
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deployment_routing() {
+        let client = AzureOpenAIClient::new(
+            "https://example.openai.azure.com/",
+            AzureAuth::ApiKey("key".to_string()),
+            "gpt4o-prod",
+        )
+        .with_deployment("gpt-4o", "gpt4o-prod")
+        .with_model("gpt-4o");
+
+        assert_eq!(
+            client.chat_completions_url(),
+            "https://example.openai.azure.com/openai/deployments/gpt4o-prod/chat/completions?api-version=2024-06-01"
+        );
+        assert!(client.cache_identity().contains("deployment=gpt4o-prod"));
+
+        let other = client.with_deployment("gpt-4o", "gpt4o-eu");
+        assert!(other.cache_identity().starts_with("https://example.openai.azure.com deployment=gpt4o-eu "));
+    }
+
+    #[test]
+    fn test_config_without_timeout_keeps_the_clients() {
+        let client = AzureOpenAIClient::new("https://example.openai.azure.com", AzureAuth::ApiKey("key".to_string()), "gpt4o")
+            .with_config(&LLMConfig { timeout: Some(std::time::Duration::from_secs(5)), ..Default::default() })
+            .with_config(&LLMConfig { timeout: None, ..Default::default() });
+        assert_eq!(client.timeout, Some(std::time::Duration::from_secs(5)));
+    }
+}
@@ -1,63 +1,413 @@
+use crate::{LLMProvider, LLMResponse, Message, TextStream};
 use anyhow::Result;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
-use tokio::fs;
-use tracing::{info, debug};
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
 
+/// Default time-to-live for cached responses (7 days)
+const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Default maximum number of cached responses before eviction kicks in
+const DEFAULT_MAX_ENTRIES: usize = 5000;
+
+/// Disk-backed LLM response cache stored in SQLite under `.miow/cache`.
+///
+/// Entries are keyed by a SHA-256 of provider + model + settings + prompt and keep the prompt
+/// itself, so a hit is only served when the prompt matches. They expire after a TTL,
+/// and the least recently used entries are evicted once `max_entries` is exceeded.
 pub struct LLMCache {
-    cache_dir: PathBuf,
+    conn: Mutex<Connection>,
+    path: PathBuf,
+    ttl: Duration,
+    max_entries: usize,
 }
 
 impl LLMCache {
-    pub fn new() -> Self {
+    /// Open the cache in `.miow/cache` under the current directory
+    pub fn new() -> Result<Self> {
         let cache_dir = std::env::current_dir()
             .unwrap_or_else(|_| PathBuf::from("."))
-            .join(".cache")
-            .join("miow-llm");
-        
-        Self { cache_dir }
+            .join(".miow")
+            .join("cache");
+
+        Self::open(cache_dir)
     }
 
-    pub async fn init(&self) -> Result<()> {
-        if !self.cache_dir.exists() {
-            fs::create_dir_all(&self.cache_dir).await?;
+    /// Open (or create) the cache database inside `cache_dir`
+    pub fn open<P: AsRef<Path>>(cache_dir: P) -> Result<Self> {
+        let cache_dir = cache_dir.as_ref();
+        std::fs::create_dir_all(cache_dir)?;
+
+        let path = cache_dir.join("llm_cache.db");
+        let conn = Connection::open(&path)?;
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS llm_cache (
+                key TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                prompt TEXT NOT NULL DEFAULT '',
+                response TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                last_accessed INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_llm_cache_accessed ON llm_cache(last_accessed);
+            "#,
+        )?;
+
+        // Caches written before prompts were stored get the column; their rows never match
+        let has_prompt = conn
+            .prepare("SELECT 1 FROM pragma_table_info('llm_cache') WHERE name = 'prompt'")?
+            .exists([])?;
+        if !has_prompt {
+            conn.execute("ALTER TABLE llm_cache ADD COLUMN prompt TEXT NOT NULL DEFAULT ''", [])?;
         }
-        Ok(())
+
+        debug!("Opened LLM cache at {}", path.display());
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            path,
+            ttl: DEFAULT_TTL,
+            max_entries: DEFAULT_MAX_ENTRIES,
+        })
+    }
+
+    /// Set how long cached responses stay valid
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
     }
 
-    fn get_cache_key(&self, prompt: &str, model: &str) -> String {
-        let mut hasher = DefaultHasher::new();
-        prompt.hash(&mut hasher);
-        model.hash(&mut hasher);
-        let hash = hasher.finish();
-        format!("{}_{:x}.json", model, hash)
+    /// Set the maximum number of entries kept on disk
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
     }
 
-    pub async fn get(&self, prompt: &str, model: &str) -> Option<String> {
-        let key = self.get_cache_key(prompt, model);
-        let path = self.cache_dir.join(key);
+    /// Path to the underlying SQLite file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 
-        if path.exists() {
-            debug!("Cache hit for prompt hash");
-            if let Ok(content) = fs::read_to_string(path).await {
-                return Some(content);
-            }
+    /// SHA-256 of the NUL-separated provider, model, settings and prompt, which stays
+    /// stable across builds. `settings` is the provider's `cache_identity`.
+    fn get_cache_key(provider: &str, model: &str, settings: &str, prompt: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [provider, model, settings, prompt] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
         }
-        None
+        hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
     }
 
-    pub async fn set(&self, prompt: &str, model: &str, response: &str) -> Result<()> {
-        let key = self.get_cache_key(prompt, model);
-        let path = self.cache_dir.join(key);
-        
-        // Ensure dir exists (lazy init)
-        if !self.cache_dir.exists() {
-            fs::create_dir_all(&self.cache_dir).await?;
+    /// Look up a cached response; expired entries are treated as misses
+    pub fn get(&self, provider: &str, model: &str, settings: &str, prompt: &str) -> Option<String> {
+        let key = Self::get_cache_key(provider, model, settings, prompt);
+        let now = Self::now();
+        let min_created = now - self.ttl.as_secs() as i64;
+        let conn = self.conn.lock().unwrap();
+
+        let row: Option<(String, String)> = conn
+            .query_row(
+                "SELECT response, prompt FROM llm_cache WHERE key = ?1 AND created_at >= ?2",
+                params![key, min_created],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .unwrap_or_else(|e| {
+                warn!("LLM cache read failed: {}", e);
+                None
+            });
+
+        // A key shared with a different prompt is a collision, never an answer
+        let hit = row.and_then(|(response, stored)| (stored == prompt).then_some(response));
+
+        if hit.is_some() {
+            debug!("Cache hit for prompt hash {}", key);
+            let _ = conn.execute(
+                "UPDATE llm_cache SET last_accessed = ?1 WHERE key = ?2",
+                params![now, key],
+            );
         }
 
-        fs::write(path, response).await?;
-        debug!("Cached response for prompt hash");
+        hit
+    }
+
+    /// Store a response and evict expired / least recently used entries
+    pub fn set(&self, provider: &str, model: &str, settings: &str, prompt: &str, response: &str) -> Result<()> {
+        let key = Self::get_cache_key(provider, model, settings, prompt);
+        let now = Self::now();
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO llm_cache (key, provider, model, prompt, response, created_at, last_accessed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+            params![key, provider, model, prompt, response, now],
+        )?;
+
+        // Drop expired entries, then trim to max size by least recent access
+        conn.execute(
+            "DELETE FROM llm_cache WHERE created_at < ?1",
+            params![now - self.ttl.as_secs() as i64],
+        )?;
+        conn.execute(
+            "DELETE FROM llm_cache WHERE key IN (
+                SELECT key FROM llm_cache ORDER BY last_accessed DESC LIMIT -1 OFFSET ?1
+             )",
+            params![self.max_entries as i64],
+        )?;
+
+        debug!("Cached response for prompt hash {}", key);
         Ok(())
     }
+
+    /// Number of entries currently stored
+    pub fn len(&self) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM llm_cache", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Remove every cached response
+    pub fn clear(&self) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM llm_cache", [])?;
+        Ok(())
+    }
+}
+
+/// LLM provider wrapper that serves repeated requests from an `LLMCache`
+pub struct CachedLLMProvider {
+    inner: Arc<dyn LLMProvider>,
+    cache: Arc<LLMCache>,
+}
+
+impl CachedLLMProvider {
+    pub fn new(inner: Arc<dyn LLMProvider>, cache: Arc<LLMCache>) -> Self {
+        Self { inner, cache }
+    }
+
+    fn cached(&self, key: &str) -> Option<LLMResponse> {
+        self.cache
+            .get(self.inner.provider_name(), self.inner.model_name(), &self.inner.cache_identity(), key)
+            .map(|content| LLMResponse {
+                content,
+                finish_reason: None,
+                usage: None,
+            })
+    }
+
+    fn store(&self, key: &str, response: &LLMResponse) {
+        if let Err(e) = self.cache.set(
+            self.inner.provider_name(),
+            self.inner.model_name(),
+            &self.inner.cache_identity(),
+            key,
+            &response.content,
+        ) {
+            warn!("Failed to cache response: {}", e);
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for CachedLLMProvider {
+    async fn generate(&self, prompt: &str) -> Result<LLMResponse> {
+        if let Some(hit) = self.cached(prompt) {
+            info!("Returning cached response for prompt");
+            return Ok(hit);
+        }
+
+        let response = self.inner.generate(prompt).await?;
+        self.store(prompt, &response);
+        Ok(response)
+    }
+
+    async fn generate_with_context(&self, messages: Vec<Message>) -> Result<LLMResponse> {
+        let key = serde_json::to_string(&messages)?;
+        if let Some(hit) = self.cached(&key) {
+            info!("Returning cached response for conversation");
+            return Ok(hit);
+        }
+
+        let response = self.inner.generate_with_context(messages).await?;
+        self.store(&key, &response);
+        Ok(response)
+    }
+
+    async fn generate_n(&self, prompt: &str, n: usize) -> Result<Vec<LLMResponse>> {
+        // Candidates must be independent samples, so bypass the cache
+        self.inner.generate_n(prompt, n).await
+    }
+
+    async fn stream_generate(&self, prompt: &str) -> Result<TextStream> {
+        self.inner.stream_generate(prompt).await
+    }
+
+    async fn generate_multi_step(&self, steps: Vec<String>, context: &str) -> Result<LLMResponse> {
+        let key = format!("multi_step:{}\n{}", steps.join("\n"), context);
+        if let Some(hit) = self.cached(&key) {
+            return Ok(hit);
+        }
+
+        let response = self.inner.generate_multi_step(steps, context).await?;
+        self.store(&key, &response);
+        Ok(response)
+    }
+
+    async fn generate_with_framework(&self, prompt: &str, framework: &str, lang: &str) -> Result<LLMResponse> {
+        let key = format!("framework:{}:{}\n{}", framework, lang, prompt);
+        if let Some(hit) = self.cached(&key) {
+            return Ok(hit);
+        }
+
+        let response = self.inner.generate_with_framework(prompt, framework, lang).await?;
+        self.store(&key, &response);
+        Ok(response)
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn cache_identity(&self) -> String {
+        self.inner.cache_identity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_roundtrip_and_eviction() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = LLMCache::open(dir.path()).unwrap().with_max_entries(2);
+
+        cache.set("gemini", "flash", "", "a", "A").unwrap();
+        cache.set("gemini", "flash", "", "b", "B").unwrap();
+        assert_eq!(cache.get("gemini", "flash", "", "a"), Some("A".to_string()));
+        assert_eq!(cache.get("openai", "flash", "", "a"), None);
+
+        cache.set("gemini", "flash", "", "c", "C").unwrap();
+        assert_eq!(cache.len().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_expired_entries_are_misses() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = LLMCache::open(dir.path()).unwrap().with_ttl(Duration::from_secs(0));
+
+        {
+            let conn = cache.conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO llm_cache (key, provider, model, prompt, response, created_at, last_accessed)
+                 VALUES (?1, 'gemini', 'flash', 'p', 'old', 0, 0)",
+                params![LLMCache::get_cache_key("gemini", "flash", "", "p")],
+            )
+            .unwrap();
+        }
+        assert_eq!(cache.get("gemini", "flash", "", "p"), None);
+    }
+
+    #[test]
+    fn test_key_is_sha256_and_prompt_is_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = LLMCache::open(dir.path()).unwrap();
+        let key = LLMCache::get_cache_key("gemini", "flash", "", "p");
+        assert_eq!(key.len(), 64);
+        assert_ne!(key, LLMCache::get_cache_key("gemini", "flashp", "", ""));
+
+        cache.set("gemini", "flash", "", "p", "P").unwrap();
+        assert_eq!(cache.get("gemini", "flash", "", "p"), Some("P".to_string()));
+
+        // Simulate a colliding key holding another prompt's answer
+        {
+            let conn = cache.conn.lock().unwrap();
+            conn.execute("UPDATE llm_cache SET prompt = 'other' WHERE key = ?1", params![key])
+                .unwrap();
+        }
+        assert_eq!(cache.get("gemini", "flash", "", "p"), None);
+    }
+
+    /// Answers with how many calls it has had, under fixed settings
+    struct CountingLLM {
+        settings: String,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LLMProvider for CountingLLM {
+        async fn generate(&self, _prompt: &str) -> Result<LLMResponse> {
+            let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            Ok(LLMResponse { content: format!("answer {}", call), finish_reason: None, usage: None })
+        }
+        async fn generate_with_context(&self, _messages: Vec<Message>) -> Result<LLMResponse> {
+            self.generate("").await
+        }
+        async fn stream_generate(&self, _prompt: &str) -> Result<TextStream> {
+            unimplemented!()
+        }
+        async fn generate_multi_step(&self, _steps: Vec<String>, _context: &str) -> Result<LLMResponse> {
+            self.generate("").await
+        }
+        async fn generate_with_framework(&self, _prompt: &str, _framework: &str, _lang: &str) -> Result<LLMResponse> {
+            self.generate("").await
+        }
+        fn cache_identity(&self) -> String {
+            self.settings.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_different_settings_miss_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(LLMCache::open(dir.path()).unwrap());
+        let cached = |settings: &str| {
+            let inner = Arc::new(CountingLLM { settings: settings.to_string(), calls: Default::default() });
+            CachedLLMProvider::new(inner, cache.clone())
+        };
+
+        let cool = cached("temperature=0.2");
+        assert_eq!(cool.generate("p").await.unwrap().content, "answer 1");
+        assert_eq!(cool.generate("p").await.unwrap().content, "answer 1");
+        assert_eq!(cached("temperature=0.2").generate("p").await.unwrap().content, "answer 1");
+        assert_eq!(cached("temperature=0.9").generate("p").await.unwrap().content, "answer 1");
+        assert_eq!(cache.len().unwrap(), 2);
+
+        // What the OpenAI client reports changes with each setting that shapes answers
+        let base = crate::OpenAIClient::new(String::new());
+        let identities = [
+            base.cache_identity(),
+            crate::OpenAIClient::new(String::new()).with_base_url("http://localhost:1234/v1").cache_identity(),
+            crate::OpenAIClient::new(String::new())
+                .with_config(&crate::LLMConfig { model: "gpt-4o".into(), temperature: 0.1, ..Default::default() })
+                .cache_identity(),
+            crate::OpenAIClient::new(String::new())
+                .with_config(&crate::LLMConfig { model: "gpt-4o".into(), max_tokens: 512, ..Default::default() })
+                .cache_identity(),
+        ];
+        let distinct: std::collections::HashSet<&String> = identities.iter().collect();
+        assert_eq!(distinct.len(), identities.len(), "{:?}", identities);
+    }
 }
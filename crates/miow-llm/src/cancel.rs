@@ -0,0 +1,96 @@
+use crate::{LLMProvider, LLMResponse, Message, TextStream};
+use futures::StreamExt;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::Arc;
+pub use tokio_util::sync::CancellationToken;
+
+/// Error returned when an LLM call is aborted through its `CancellationToken`
+#[derive(Debug, thiserror::Error)]
+#[error("LLM request cancelled")]
+pub struct Cancelled;
+
+/// Run `fut` unless `token` is cancelled first
+pub async fn with_cancellation<T>(
+    token: &CancellationToken,
+    fut: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    if token.is_cancelled() {
+        return Err(Cancelled.into());
+    }
+
+    tokio::select! {
+        _ = token.cancelled() => Err(Cancelled.into()),
+        result = fut => result,
+    }
+}
+
+/// LLM provider wrapper that aborts every in-flight call once its token is cancelled
+pub struct CancellableLLMProvider {
+    inner: Arc<dyn LLMProvider>,
+    token: CancellationToken,
+}
+
+impl CancellableLLMProvider {
+    pub fn new(inner: Arc<dyn LLMProvider>, token: CancellationToken) -> Self {
+        Self { inner, token }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for CancellableLLMProvider {
+    async fn generate(&self, prompt: &str) -> Result<LLMResponse> {
+        self.inner.generate_cancellable(prompt, &self.token).await
+    }
+
+    async fn generate_with_context(&self, messages: Vec<Message>) -> Result<LLMResponse> {
+        self.inner
+            .generate_with_context_cancellable(messages, &self.token)
+            .await
+    }
+
+    async fn generate_n(&self, prompt: &str, n: usize) -> Result<Vec<LLMResponse>> {
+        with_cancellation(&self.token, self.inner.generate_n(prompt, n)).await
+    }
+
+    async fn stream_generate(&self, prompt: &str) -> Result<TextStream> {
+        let stream = with_cancellation(&self.token, self.inner.stream_generate(prompt)).await?;
+        // Cancelling also cuts off a stream already under way
+        let token = self.token.clone();
+        let stream = futures::stream::unfold(Some(stream), move |stream| {
+            let token = token.clone();
+            async move {
+                let mut stream = stream?;
+                tokio::select! {
+                    _ = token.cancelled() => Some((Err(Cancelled.into()), None)),
+                    chunk = stream.next() => chunk.map(|chunk| (chunk, Some(stream))),
+                }
+            }
+        });
+        Ok(Box::new(Box::pin(stream)))
+    }
+
+    async fn generate_multi_step(&self, steps: Vec<String>, context: &str) -> Result<LLMResponse> {
+        with_cancellation(&self.token, self.inner.generate_multi_step(steps, context)).await
+    }
+
+    async fn generate_with_framework(&self, prompt: &str, framework: &str, lang: &str) -> Result<LLMResponse> {
+        with_cancellation(
+            &self.token,
+            self.inner.generate_with_framework(prompt, framework, lang),
+        )
+        .await
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+    fn cache_identity(&self) -> String {
+        self.inner.cache_identity()
+    }
+}
@@ -0,0 +1,186 @@
+use crate::LLMProvider;
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashSet;
+use tracing::{debug, info};
+
+/// Extract the outermost JSON object or array from an LLM response
+pub fn extract_json(response: &str) -> Option<Value> {
+    let trimmed = response.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Some(value);
+    }
+
+    for (open, close) in [('{', '}'), ('[', ']')] {
+        if let (Some(start), Some(end)) = (trimmed.find(open), trimmed.rfind(close)) {
+            if start < end {
+                if let Ok(value) = serde_json::from_str(&trimmed[start..=end]) {
+                    return Some(value);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Lower-cased word set of every string/key in a JSON value
+fn json_terms(value: &Value, terms: &mut HashSet<String>) {
+    match value {
+        Value::String(s) => {
+            terms.extend(s.split(|c: char| !c.is_alphanumeric()).filter(|w| w.len() > 2).map(|w| w.to_lowercase()))
+        }
+        Value::Array(items) => items.iter().for_each(|v| json_terms(v, terms)),
+        Value::Object(map) => {
+            for (k, v) in map {
+                terms.insert(k.to_lowercase());
+                json_terms(v, terms);
+            }
+        }
+        other => {
+            terms.insert(other.to_string());
+        }
+    }
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+/// Pick the candidate that agrees most with the others.
+///
+/// Exact duplicates win by majority; otherwise the candidate with the highest
+/// average term overlap against the rest is chosen. Returns the index.
+pub fn most_consistent(candidates: &[Value]) -> Option<usize> {
+    if candidates.is_empty() {
+        return None;
+    }
+
+    let canonical: Vec<String> = candidates.iter().map(|c| c.to_string()).collect();
+    let terms: Vec<HashSet<String>> = candidates
+        .iter()
+        .map(|c| {
+            let mut t = HashSet::new();
+            json_terms(c, &mut t);
+            t
+        })
+        .collect();
+
+    let mut best = (0, 0usize, f32::MIN);
+    for i in 0..candidates.len() {
+        let votes = canonical.iter().filter(|c| **c == canonical[i]).count();
+        let agreement: f32 = (0..candidates.len())
+            .filter(|&j| j != i)
+            .map(|j| jaccard(&terms[i], &terms[j]))
+            .sum();
+
+        if votes > best.1 || (votes == best.1 && agreement > best.2) {
+            best = (i, votes, agreement);
+        }
+    }
+
+    Some(best.0)
+}
+
+/// Ask for `n` candidate JSON answers and return the most self-consistent one.
+/// Failed samples and candidates that don't parse as JSON are discarded; errors if none are left.
+pub async fn self_consistent_json(llm: &dyn LLMProvider, prompt: &str, n: usize) -> Result<Value> {
+    let responses = llm.generate_n(prompt, n.max(1)).await?;
+    let candidates: Vec<Value> = responses
+        .iter()
+        .filter_map(|r| extract_json(&r.content))
+        .collect();
+
+    debug!("Self-consistency: {}/{} candidates parsed as JSON", candidates.len(), responses.len());
+
+    let index = most_consistent(&candidates)
+        .ok_or_else(|| anyhow::anyhow!("No candidate returned valid JSON"))?;
+    info!("🗳️  Selected candidate {} of {} by self-consistency", index + 1, candidates.len());
+
+    Ok(candidates.into_iter().nth(index).unwrap())
+}
+
+/// Ask for `n` candidate free-text answers and return the one sharing the most terms
+/// with the others. Failed samples are discarded; errors if every sample fails.
+pub async fn self_consistent_text(llm: &dyn LLMProvider, prompt: &str, n: usize) -> Result<String> {
+    let responses = llm.generate_n(prompt, n.max(1)).await?;
+    let candidates: Vec<Value> = responses.into_iter().map(|r| Value::String(r.content)).collect();
+    let index = most_consistent(&candidates).ok_or_else(|| anyhow::anyhow!("No candidate was generated"))?;
+    info!("🗳️  Selected candidate {} of {} by self-consistency", index + 1, candidates.len());
+
+    match candidates.into_iter().nth(index) {
+        Some(Value::String(text)) => Ok(text),
+        _ => unreachable!("candidates are strings"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_majority_then_agreement() {
+        let a = json!({"plan": "reuse auth service", "requirements": ["login"]});
+        let b = json!({"plan": "write everything from scratch"});
+        let candidates = vec![b.clone(), a.clone(), a.clone()];
+        assert_eq!(most_consistent(&candidates), Some(1));
+
+        let c = json!({"plan": "reuse auth service and session", "requirements": ["login"]});
+        let candidates = vec![b, a, c];
+        assert_ne!(most_consistent(&candidates), Some(0));
+    }
+
+    struct FlakyLLM {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl LLMProvider for FlakyLLM {
+        async fn generate(&self, _prompt: &str) -> Result<crate::LLMResponse> {
+            let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if call == 0 {
+                anyhow::bail!("sample failed");
+            }
+            Ok(crate::LLMResponse { content: format!("{{\"plan\": \"reuse\", \"n\": {}}}", call % 2), finish_reason: None, usage: None })
+        }
+        async fn generate_with_context(&self, _messages: Vec<crate::Message>) -> Result<crate::LLMResponse> {
+            unimplemented!()
+        }
+        async fn stream_generate(&self, _prompt: &str) -> Result<crate::TextStream> {
+            unimplemented!()
+        }
+        async fn generate_multi_step(&self, _steps: Vec<String>, _context: &str) -> Result<crate::LLMResponse> {
+            unimplemented!()
+        }
+        async fn generate_with_framework(&self, _prompt: &str, _framework: &str, _lang: &str) -> Result<crate::LLMResponse> {
+            unimplemented!()
+        }
+        fn provider_name(&self) -> &str {
+            "flaky"
+        }
+        fn model_name(&self) -> &str {
+            "flaky"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vote_survives_a_failed_sample() {
+        let llm = FlakyLLM { calls: Default::default() };
+        let plan = self_consistent_json(&llm, "plan", 4).await.unwrap();
+        assert_eq!(plan["plan"], "reuse");
+        assert_eq!(llm.calls.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+        let text = self_consistent_text(&llm, "plan", 2).await.unwrap();
+        assert!(text.contains("reuse"));
+    }
+
+    #[test]
+    fn test_extract_json_from_fenced_response() {
+        let value = extract_json("```json\n{\"plan\": \"x\"}\n```").unwrap();
+        assert_eq!(value["plan"], "x");
+    }
+}
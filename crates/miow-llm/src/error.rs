@@ -0,0 +1,86 @@
+use reqwest::StatusCode;
+use std::time::Duration;
+
+/// Provider failures callers may want to tell apart, e.g. to answer "quota
+/// exceeded" differently from "bad API key". They travel inside
+/// `anyhow::Error`; find them with `downcast_ref` on the error's chain.
+#[derive(Debug, thiserror::Error)]
+pub enum LlmError {
+    /// No API key or token was configured
+    #[error("{0}")]
+    MissingCredentials(String),
+    /// The provider refused the key (401/403)
+    #[error("{provider} API error ({status}): {message}")]
+    Unauthorized { provider: String, status: StatusCode, message: String },
+    /// Rate limited or out of quota (429)
+    #[error("{provider} API error ({status}): {message}")]
+    QuotaExceeded { provider: String, status: StatusCode, message: String },
+    /// The provider failed on its side (5xx)
+    #[error("{provider} API error ({status}): {message}")]
+    Unavailable { provider: String, status: StatusCode, message: String },
+    /// Any other error response, usually a bad request
+    #[error("{provider} API error ({status}): {message}")]
+    Api { provider: String, status: StatusCode, message: String },
+    /// The request never got a response
+    #[error("Failed to send request to {provider} API")]
+    Unreachable {
+        provider: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("{provider} API request timed out after {after:?}")]
+    Timeout { provider: String, after: Duration },
+}
+
+/// Run `call` under one deadline that covers rate-limit waits, every retry and the
+/// backoff between them; `None` leaves it unbounded
+pub(crate) async fn with_deadline<T>(
+    provider: &str,
+    timeout: Option<Duration>,
+    call: impl std::future::Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    match timeout {
+        Some(after) => tokio::time::timeout(after, call)
+            .await
+            .unwrap_or_else(|_| Err(LlmError::Timeout { provider: provider.to_string(), after }.into())),
+        None => call.await,
+    }
+}
+
+impl LlmError {
+    /// The error for a non-success `status` from `provider`
+    pub fn from_status(provider: &str, status: StatusCode, message: String) -> Self {
+        let provider = provider.to_string();
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Unauthorized { provider, status, message },
+            StatusCode::TOO_MANY_REQUESTS => Self::QuotaExceeded { provider, status, message },
+            status if status.is_server_error() => Self::Unavailable { provider, status, message },
+            status => Self::Api { provider, status, message },
+        }
+    }
+
+    /// Whether trying again later may succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::QuotaExceeded { .. } | Self::Unavailable { .. } | Self::Unreachable { .. } | Self::Timeout { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status_sorts_by_cause() {
+        let error = |status| LlmError::from_status("Gemini", status, "nope".to_string());
+        assert!(matches!(error(StatusCode::UNAUTHORIZED), LlmError::Unauthorized { .. }));
+        assert!(matches!(error(StatusCode::TOO_MANY_REQUESTS), LlmError::QuotaExceeded { .. }));
+        assert!(matches!(error(StatusCode::BAD_GATEWAY), LlmError::Unavailable { .. }));
+        assert!(matches!(error(StatusCode::BAD_REQUEST), LlmError::Api { .. }));
+        assert!(error(StatusCode::TOO_MANY_REQUESTS).is_retryable());
+        assert!(!error(StatusCode::FORBIDDEN).is_retryable());
+        assert_eq!(error(StatusCode::BAD_REQUEST).to_string(), "Gemini API error (400 Bad Request): nope");
+    }
+}
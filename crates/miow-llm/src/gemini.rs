@@ -1,4 +1,6 @@
-use crate::{LLMConfig, LLMProvider, LLMResponse, Message, Role, LLMCache};
+use crate::error::with_deadline;
+use crate::{record_usage, stream, LLMConfig, LlmError, LLMProvider, LLMResponse, Message, RateLimitConfig, RateLimiter, Role, TextStream, Usage};
+use std::sync::Arc;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use serde_json::json;
@@ -13,13 +15,16 @@ pub struct GeminiClient {
     client: reqwest::Client,
     max_retries: u32,
     base_delay: Duration,
-    cache: LLMCache,
+    limiter: Arc<RateLimiter>,
+    timeout: Option<Duration>,
+    /// Cap on response tokens; the model's own limit when unset
+    max_tokens: Option<usize>,
 }
 
 impl GeminiClient {
     pub fn new(config: LLMConfig) -> Result<Self> {
         if config.api_key.is_empty() {
-            anyhow::bail!("Gemini API key is required");
+            return Err(LlmError::MissingCredentials("Gemini API key is required".to_string()).into());
         }
 
         Ok(Self {
@@ -29,13 +34,24 @@ impl GeminiClient {
             client: reqwest::Client::new(),
             max_retries: 5,
             base_delay: Duration::from_secs(2),
-            cache: LLMCache::new(),
+            limiter: RateLimiter::shared(
+                "gemini",
+                config.rate_limit.unwrap_or_else(|| RateLimitConfig::for_provider("gemini")),
+            ),
+            timeout: config.timeout,
+            max_tokens: None,
         })
     }
 
+    /// Cap each response at `max_tokens` output tokens
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
     pub fn from_env() -> Result<Self> {
         let api_key = std::env::var("GEMINI_API_KEY")
-            .context("GEMINI_API_KEY environment variable not set")?;
+            .map_err(|_| LlmError::MissingCredentials("GEMINI_API_KEY environment variable not set".to_string()))?;
 
         Self::new(LLMConfig {
             api_key,
@@ -49,14 +65,7 @@ impl GeminiClient {
         Duration::from_millis(seed)
     }
 
-    async fn call_api(&self, messages: Vec<Message>) -> Result<String> {
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            self.model, self.api_key
-        );
-
-        debug!("Calling Gemini API with model: {}", self.model);
-
+    fn request_body(&self, messages: Vec<Message>) -> serde_json::Value {
         let mut contents = Vec::new();
         for message in messages {
             let role = match message.role {
@@ -73,7 +82,7 @@ impl GeminiClient {
             }));
         }
 
-        let request_body = json!({
+        let mut request_body = json!({
             "contents": contents,
             "generationConfig": {
                 "temperature": self.temperature,
@@ -81,23 +90,54 @@ impl GeminiClient {
                 "topP": 0.95,
             }
         });
+        if let Some(max_tokens) = self.max_tokens {
+            request_body["generationConfig"]["maxOutputTokens"] = json!(max_tokens);
+        }
+        request_body
+    }
 
+    #[tracing::instrument(name = "llm", skip_all, fields(
+        gen_ai.system = "gemini",
+        gen_ai.request.model = %self.model,
+        gen_ai.usage.input_tokens = tracing::field::Empty,
+        gen_ai.usage.output_tokens = tracing::field::Empty,
+    ))]
+    async fn call_api(&self, messages: Vec<Message>) -> Result<(String, Option<Usage>)> {
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
+            self.model, self.api_key
+        );
+
+        debug!("Calling Gemini API with model: {}", self.model);
+        let request_body = self.request_body(messages);
+
+        with_deadline("Gemini", self.timeout, self.call_with_retries(&url, &request_body)).await
+    }
+
+    async fn call_with_retries(&self, url: &str, request_body: &serde_json::Value) -> Result<(String, Option<Usage>)> {
         let mut attempt = 0;
 
         while attempt <= self.max_retries {
             let start_time = Instant::now();
             let jitter = self.generate_jitter();
 
-            match self.perform_api_call(&url, &request_body).await {
-                Ok(response_text) => {
+            let permit = self.limiter.acquire().await;
+            let result = self.perform_api_call(url, request_body).await;
+            drop(permit);
+
+            match result {
+                Ok(result) => {
                     info!("Gemini API call successful on attempt {} (took {:?})", attempt + 1, start_time.elapsed());
-                    return Ok(response_text);
+                    record_usage(result.1.as_ref());
+                    return Ok(result);
                 }
                 Err(e) => {
                     attempt += 1;
                     warn!("Gemini API call failed on attempt {}: {}", attempt, e);
 
-                    if attempt > self.max_retries {
+                    // A bad key or request fails the same way every time
+                    let retryable = e.downcast_ref::<LlmError>().is_none_or(LlmError::is_retryable);
+                    if attempt > self.max_retries || !retryable {
                         error!("All {} retry attempts failed for Gemini API", self.max_retries);
                         return Err(e);
                     }
@@ -114,24 +154,19 @@ impl GeminiClient {
         anyhow::bail!("Unexpected error after retries")
     }
 
-    async fn perform_api_call(&self, url: &str, request_body: &serde_json::Value) -> Result<String> {
+    async fn perform_api_call(&self, url: &str, request_body: &serde_json::Value) -> Result<(String, Option<Usage>)> {
         let response = self
             .client
             .post(url)
             .json(request_body)
             .send()
             .await
-            .context("Failed to send request to Gemini API")?;
+            .map_err(|source| LlmError::Unreachable { provider: "Gemini".to_string(), source })?;
 
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
-            
-            if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
-                anyhow::bail!("Gemini API error ({}): {}. This is retryable.", status, error_text);
-            } else {
-                anyhow::bail!("Gemini API error ({}): {}", status, error_text);
-            }
+            return Err(LlmError::from_status("Gemini", status, error_text).into());
         }
 
         let response_json: serde_json::Value = response
@@ -144,23 +179,27 @@ impl GeminiClient {
             .context("Failed to extract text from Gemini response")?
             .to_string();
 
-        Ok(text)
+        let usage = response_json["usageMetadata"].as_object().map(|u| {
+            let prompt_tokens = u.get("promptTokenCount").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+            let completion_tokens = u.get("candidatesTokenCount").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+            Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: u
+                    .get("totalTokenCount")
+                    .and_then(|v| v.as_u64())
+                    .map(|t| t as usize)
+                    .unwrap_or(prompt_tokens + completion_tokens),
+            }
+        });
+
+        Ok((text, usage))
     }
 }
 
 #[async_trait]
 impl LLMProvider for GeminiClient {
     async fn generate(&self, prompt: &str) -> Result<LLMResponse> {
-        // Check cache first
-        if let Some(cached) = self.cache.get(prompt, &self.model).await {
-            info!("Returning cached response for prompt");
-            return Ok(LLMResponse {
-                content: cached,
-                finish_reason: None,
-                usage: None,
-            });
-        }
-
         info!("Generating response with Gemini");
 
         let messages = vec![Message {
@@ -168,38 +207,55 @@ impl LLMProvider for GeminiClient {
             content: prompt.to_string(),
         }];
 
-        let text = self.call_api(messages).await?;
-
-        // Cache the result
-        if let Err(e) = self.cache.set(prompt, &self.model, &text).await {
-            warn!("Failed to cache response: {}", e);
-        }
+        let (text, usage) = self.call_api(messages).await?;
 
         Ok(LLMResponse {
             content: text,
             finish_reason: None,
-            usage: None,
+            usage,
         })
     }
 
     async fn generate_with_context(&self, messages: Vec<Message>) -> Result<LLMResponse> {
         info!("Generating response with Gemini (with context)");
 
-        let text = self.call_api(messages).await?;
+        let (text, usage) = self.call_api(messages).await?;
 
         Ok(LLMResponse {
             content: text,
             finish_reason: None,
-            usage: None,
+            usage,
         })
     }
 
-    async fn stream_generate(
-        &self,
-        _prompt: &str,
-    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Unpin>> {
-        // TODO: Implement streaming for Gemini
-        unimplemented!("Streaming not yet implemented for Gemini")
+    /// Streams aren't retried: text already shown can't be taken back
+    async fn stream_generate(&self, prompt: &str) -> Result<TextStream> {
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:streamGenerateContent?alt=sse&key={}",
+            self.model, self.api_key
+        );
+        let messages = vec![Message { role: Role::User, content: prompt.to_string() }];
+        let request_body = self.request_body(messages);
+
+        // The deadline bounds getting the stream started, not reading it
+        let response = with_deadline("Gemini", self.timeout, async {
+            let _permit = self.limiter.acquire().await;
+            self.client
+                .post(&url)
+                .json(&request_body)
+                .send()
+                .await
+                .map_err(|source| LlmError::Unreachable { provider: "Gemini".to_string(), source }.into())
+        })
+        .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(LlmError::from_status("Gemini", status, error_text).into());
+        }
+        Ok(stream::sse_text_stream(response, "Gemini", |event| {
+            event["candidates"][0]["content"]["parts"][0]["text"].as_str()
+        }))
     }
 
     async fn generate_multi_step(&self, steps: Vec<String>, context: &str) -> Result<LLMResponse> {
@@ -225,6 +281,18 @@ impl LLMProvider for GeminiClient {
         );
         self.generate(&enhanced_prompt).await
     }
+
+    fn provider_name(&self) -> &str {
+        "gemini"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+    fn cache_identity(&self) -> String {
+        let max_tokens = self.max_tokens.map_or_else(|| "default".to_string(), |n| n.to_string());
+        format!("temperature={} max_tokens={}", self.temperature, max_tokens)
+    }
 }
 
 #[cfg(test)]
@@ -1,29 +1,92 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 
+mod azure;
+pub mod error;
 mod gemini;
 mod openai;
 pub mod question_loop;
 pub mod cache;
+pub mod usage;
+pub mod rate_limit;
+pub mod cancel;
+pub mod consistency;
+pub mod session;
+pub mod prompt_messages;
+pub mod settings;
+pub mod stream;
 
+pub use azure::{AzureAuth, AzureOpenAIClient};
 pub use gemini::GeminiClient;
 pub use openai::OpenAIClient;
 pub use question_loop::*;
-pub use cache::LLMCache;
+pub use cache::{CachedLLMProvider, LLMCache};
+pub use consistency::{extract_json, most_consistent, self_consistent_json, self_consistent_text};
+pub use error::LlmError;
+pub use cancel::{with_cancellation, CancellableLLMProvider, CancellationToken, Cancelled};
+pub use session::{context_window_for_model, ChatSession};
+pub use prompt_messages::{MessageSizing, ToMessages};
+pub use rate_limit::{RateLimitConfig, RateLimiter};
+pub use settings::{LLMSettings, ProviderKind};
+pub use usage::{estimate_cost, ModelUsage, UsageSummary, UsageTracker, UsageTrackingProvider};
+pub use stream::TextStream;
 
 /// LLM provider trait
 #[async_trait]
 pub trait LLMProvider: Send + Sync {
     async fn generate(&self, prompt: &str) -> Result<LLMResponse>;
     async fn generate_with_context(&self, messages: Vec<Message>) -> Result<LLMResponse>;
-    async fn stream_generate(
-        &self,
-        prompt: &str,
-    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Unpin>>;
+    async fn stream_generate(&self, prompt: &str) -> Result<TextStream>;
     async fn generate_multi_step(&self, steps: Vec<String>, context: &str) -> Result<LLMResponse>;
     async fn generate_with_framework(&self, prompt: &str, framework: &str, lang: &str) -> Result<LLMResponse>;
+
+    /// Generate `n` independent candidate responses for the same prompt. Samples
+    /// that fail are dropped; it errors only when every one does.
+    async fn generate_n(&self, prompt: &str, n: usize) -> Result<Vec<LLMResponse>> {
+        let mut responses = Vec::new();
+        let mut last_error = None;
+        for result in futures::future::join_all((0..n).map(|_| self.generate(prompt))).await {
+            match result {
+                Ok(response) => responses.push(response),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        match last_error {
+            Some(e) if responses.is_empty() => Err(e),
+            _ => Ok(responses),
+        }
+    }
+
+    /// `generate` that aborts with `Cancelled` as soon as `cancel` fires
+    async fn generate_cancellable(&self, prompt: &str, cancel: &CancellationToken) -> Result<LLMResponse> {
+        with_cancellation(cancel, self.generate(prompt)).await
+    }
+
+    /// `generate_with_context` that aborts with `Cancelled` as soon as `cancel` fires
+    async fn generate_with_context_cancellable(
+        &self,
+        messages: Vec<Message>,
+        cancel: &CancellationToken,
+    ) -> Result<LLMResponse> {
+        with_cancellation(cancel, self.generate_with_context(messages)).await
+    }
+
+    /// Provider identifier, used for cache keys and usage reporting
+    fn provider_name(&self) -> &str {
+        "unknown"
+    }
+
+    /// Model identifier, used for cache keys and usage reporting
+    fn model_name(&self) -> &str {
+        "unknown"
+    }
+
+    /// Settings besides provider and model that change answers, like the endpoint,
+    /// temperature and output cap, so cached answers don't outlive them
+    fn cache_identity(&self) -> String {
+        String::new()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +103,15 @@ pub struct Usage {
     pub total_tokens: usize,
 }
 
+/// Records a response's token counts on the current `llm` span
+pub(crate) fn record_usage(usage: Option<&Usage>) {
+    if let Some(usage) = usage {
+        let span = tracing::Span::current();
+        span.record("gen_ai.usage.input_tokens", usage.prompt_tokens);
+        span.record("gen_ai.usage.output_tokens", usage.completion_tokens);
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: Role,
@@ -59,6 +131,10 @@ pub struct LLMConfig {
     pub model: String,
     pub temperature: f32,
     pub max_tokens: usize,
+    /// Rate limit override; `None` uses the provider defaults (see `RateLimitConfig::for_provider`)
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Deadline for a whole call, including rate-limit waits and every retry
+    pub timeout: Option<std::time::Duration>,
 }
 
 impl Default for LLMConfig {
@@ -68,6 +144,8 @@ impl Default for LLMConfig {
             model: "gemini-2.5-flash".to_string(), // Using Gemini 2.5 Flash
             temperature: 0.7,
             max_tokens: 4096,
+            rate_limit: None,
+            timeout: Some(std::time::Duration::from_secs(120)),
         }
     }
 }
@@ -96,18 +174,8 @@ Respond in JSON format:
   "questions": ["What props should this component accept?", "Should it use existing design tokens?"]
 }"#;
 
-        let messages = vec![
-            Message {
-                role: Role::System,
-                content: system_prompt.to_string(),
-            },
-            Message {
-                role: Role::User,
-                content: user_prompt.to_string(),
-            },
-        ];
-
-        let response = self.provider.generate_with_context(messages).await?;
+        let mut session = ChatSession::for_model(self.provider.model_name()).with_system(system_prompt);
+        let response = session.send(self.provider.as_ref(), user_prompt).await?;
         let analysis: IntentAnalysis = serde_json::from_str(&response.content)?;
 
         Ok(analysis)
@@ -163,18 +231,8 @@ Make the prompt extremely detailed so no context is lost."#;
             user_prompt, context_json
         );
 
-        let messages = vec![
-            Message {
-                role: Role::System,
-                content: system_prompt.to_string(),
-            },
-            Message {
-                role: Role::User,
-                content: user_message,
-            },
-        ];
-
-        let response = self.provider.generate_with_context(messages).await?;
+        let mut session = ChatSession::for_model(self.provider.model_name()).with_system(system_prompt);
+        let response = session.send(self.provider.as_ref(), user_message).await?;
         Ok(response.content)
     }
 }
@@ -208,4 +266,10 @@ pub struct ContextItem {
     pub props: Vec<String>,
     #[serde(default)]
     pub references: Vec<String>,
+    /// Part of its module's public API (`export`ed)
+    #[serde(default)]
+    pub is_exported: bool,
+    /// Doc comment or docstring, which usually states intent better than the body
+    #[serde(default)]
+    pub documentation: Option<String>,
 }
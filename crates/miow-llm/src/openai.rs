@@ -1,13 +1,26 @@
 use super::*;
+use crate::error::with_deadline;
 use anyhow::Result;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::json;
+use std::sync::Arc;
 
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Client for OpenAI and OpenAI-compatible chat completion servers
+/// (LM Studio, vLLM, Groq, Together, Ollama, ...)
 pub struct OpenAIClient {
     client: Client,
     api_key: String,
     model: String,
+    base_url: String,
+    headers: Vec<(String, String)>,
+    temperature: f32,
+    max_tokens: usize,
+    limiter: Arc<RateLimiter>,
+    /// Deadline for a whole call, rate-limit wait included
+    timeout: Option<std::time::Duration>,
 }
 
 impl OpenAIClient {
@@ -16,13 +29,89 @@ impl OpenAIClient {
             client: Client::new(),
             api_key,
             model: "gpt-4-turbo-preview".to_string(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            headers: Vec::new(),
+            temperature: 0.7,
+            max_tokens: 4096,
+            limiter: RateLimiter::shared("openai", RateLimitConfig::for_provider("openai")),
+            timeout: LLMConfig::default().timeout,
+        }
+    }
+
+    /// Build a client from `OPENAI_API_KEY`, `OPENAI_BASE_URL`, `OPENAI_MODEL`
+    /// and `OPENAI_HEADERS` (`Name: value` pairs separated by `;`).
+    /// The API key may be empty for local servers that don't require auth.
+    pub fn from_env() -> Self {
+        let mut client = Self::new(std::env::var("OPENAI_API_KEY").unwrap_or_default());
+
+        if let Ok(base_url) = std::env::var("OPENAI_BASE_URL") {
+            client = client.with_base_url(base_url);
+        }
+        if let Ok(model) = std::env::var("OPENAI_MODEL") {
+            client = client.with_model(model);
+        }
+        if let Ok(headers) = std::env::var("OPENAI_HEADERS") {
+            for (name, value) in parse_header_list(&headers) {
+                client = client.with_header(name, value);
+            }
+        }
+
+        client
+    }
+
+    /// Apply model, temperature, token limit, timeout and rate limit from an `LLMConfig`
+    pub fn with_config(mut self, config: &LLMConfig) -> Self {
+        self.model = config.model.clone();
+        self.temperature = config.temperature;
+        self.max_tokens = config.max_tokens;
+        if let Some(timeout) = config.timeout {
+            self = self.with_timeout(timeout);
+        }
+        if let Some(rate_limit) = config.rate_limit {
+            self = self.with_rate_limit(rate_limit);
         }
+        self
     }
 
     pub fn with_model(mut self, model: String) -> Self {
         self.model = model;
         self
     }
+
+    /// Point the client at an OpenAI-compatible server (e.g. `http://localhost:1234/v1`)
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Send an extra header with every request (e.g. `HTTP-Referer`, `X-Api-Key`)
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Bound each call, rate-limit wait included, by `timeout`
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Use a dedicated rate limiter instead of the process-wide OpenAI one
+    pub fn with_rate_limit(mut self, config: RateLimitConfig) -> Self {
+        self.limiter = Arc::new(RateLimiter::new(config));
+        self
+    }
+
+    fn request(&self, url: &str, body: &serde_json::Value) -> reqwest::RequestBuilder {
+        let mut request = self.client.post(url).json(body);
+        if !self.api_key.is_empty() {
+            request = request.header("Authorization", format!("Bearer {}", self.api_key));
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        request
+    }
 }
 
 #[async_trait]
@@ -35,67 +124,42 @@ impl LLMProvider for OpenAIClient {
         self.generate_with_context(messages).await
     }
 
+    #[tracing::instrument(name = "llm", skip_all, fields(
+        gen_ai.system = "openai",
+        gen_ai.request.model = %self.model,
+        gen_ai.usage.input_tokens = tracing::field::Empty,
+        gen_ai.usage.output_tokens = tracing::field::Empty,
+    ))]
     async fn generate_with_context(&self, messages: Vec<Message>) -> Result<LLMResponse> {
-        let url = "https://api.openai.com/v1/chat/completions";
-
-        let openai_messages: Vec<serde_json::Value> = messages
-            .into_iter()
-            .map(|msg| {
-                let role = match msg.role {
-                    Role::System => "system",
-                    Role::User => "user",
-                    Role::Assistant => "assistant",
-                };
-                json!({
-                    "role": role,
-                    "content": msg.content
-                })
-            })
-            .collect();
+        let url = format!("{}/chat/completions", self.base_url);
+        let body = chat_completion_body(Some(&self.model), messages, self.temperature, self.max_tokens);
 
-        let body = json!({
-            "model": self.model,
-            "messages": openai_messages,
-            "temperature": 0.7,
-            "max_tokens": 4096,
-        });
-
-        let response = self
-            .client
-            .post(url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&body)
-            .send()
-            .await?;
-
-        let json: serde_json::Value = response.json().await?;
-
-        let content = json["choices"][0]["message"]["content"]
-            .as_str()
-            .unwrap_or("")
-            .to_string();
-
-        let usage = json["usage"].as_object().map(|u| Usage {
-            prompt_tokens: u["prompt_tokens"].as_u64().unwrap_or(0) as usize,
-            completion_tokens: u["completion_tokens"].as_u64().unwrap_or(0) as usize,
-            total_tokens: u["total_tokens"].as_u64().unwrap_or(0) as usize,
-        });
-
-        Ok(LLMResponse {
-            content,
-            finish_reason: json["choices"][0]["finish_reason"]
-                .as_str()
-                .map(|s| s.to_string()),
-            usage,
+        let response = with_deadline("OpenAI", self.timeout, async {
+            let _permit = self.limiter.acquire().await;
+            let response = self
+                .request(&url, &body)
+                .send()
+                .await
+                .map_err(|source| LlmError::Unreachable { provider: "OpenAI".to_string(), source })?;
+            parse_chat_completion(response, "OpenAI").await
         })
+        .await?;
+        record_usage(response.usage.as_ref());
+        Ok(response)
     }
 
-    async fn stream_generate(
-        &self,
-        _prompt: &str,
-    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Unpin>> {
-        // TODO: Implement streaming
-        unimplemented!("Streaming not yet implemented for OpenAI")
+    async fn stream_generate(&self, prompt: &str) -> Result<TextStream> {
+        let url = format!("{}/chat/completions", self.base_url);
+        let messages = vec![Message { role: Role::User, content: prompt.to_string() }];
+        let mut body = chat_completion_body(Some(&self.model), messages, self.temperature, self.max_tokens);
+        body["stream"] = json!(true);
+
+        // The deadline bounds getting the stream started, not reading it
+        with_deadline("OpenAI", self.timeout, async {
+            let _permit = self.limiter.acquire().await;
+            stream_chat_completion(self.request(&url, &body), "OpenAI").await
+        })
+        .await
     }
 
     async fn generate_multi_step(&self, steps: Vec<String>, context: &str) -> Result<LLMResponse> {
@@ -121,4 +185,161 @@ impl LLMProvider for OpenAIClient {
         );
         self.generate(&enhanced_prompt).await
     }
+
+    fn provider_name(&self) -> &str {
+        "openai"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+    fn cache_identity(&self) -> String {
+        format!("{} temperature={} max_tokens={}", self.base_url, self.temperature, self.max_tokens)
+    }
+}
+
+/// Parse `Name: value; Other: value` into header pairs
+fn parse_header_list(raw: &str) -> Vec<(String, String)> {
+    raw.split(';')
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once(':')?;
+            let name = name.trim();
+            (!name.is_empty()).then(|| (name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Request body for the chat completions API (shared with Azure OpenAI)
+pub(crate) fn chat_completion_body(
+    model: Option<&str>,
+    messages: Vec<Message>,
+    temperature: f32,
+    max_tokens: usize,
+) -> serde_json::Value {
+    let openai_messages: Vec<serde_json::Value> = messages
+        .into_iter()
+        .map(|msg| {
+            let role = match msg.role {
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant => "assistant",
+            };
+            json!({
+                "role": role,
+                "content": msg.content
+            })
+        })
+        .collect();
+
+    let mut body = json!({
+        "messages": openai_messages,
+        "temperature": temperature,
+        "max_tokens": max_tokens,
+    });
+    if let Some(model) = model {
+        body["model"] = json!(model);
+    }
+    body
+}
+
+/// Parse a chat completions response (shared with Azure OpenAI)
+pub(crate) async fn parse_chat_completion(response: reqwest::Response, provider: &str) -> Result<LLMResponse> {
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(LlmError::from_status(provider, status, error_text).into());
+    }
+
+    let json: serde_json::Value = response.json().await?;
+
+    let content = json["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap_or("")
+        .to_string();
+
+    let usage = json["usage"].as_object().map(|u| Usage {
+        prompt_tokens: u["prompt_tokens"].as_u64().unwrap_or(0) as usize,
+        completion_tokens: u["completion_tokens"].as_u64().unwrap_or(0) as usize,
+        total_tokens: u["total_tokens"].as_u64().unwrap_or(0) as usize,
+    });
+
+    Ok(LLMResponse {
+        content,
+        finish_reason: json["choices"][0]["finish_reason"]
+            .as_str()
+            .map(|s| s.to_string()),
+        usage,
+    })
+}
+
+/// Send a chat completions request with `"stream": true` and stream the
+/// text of its deltas (shared with Azure OpenAI)
+pub(crate) async fn stream_chat_completion(request: reqwest::RequestBuilder, provider: &'static str) -> Result<TextStream> {
+    let response = request
+        .send()
+        .await
+        .map_err(|source| LlmError::Unreachable { provider: provider.to_string(), source })?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(LlmError::from_status(provider, status, error_text).into());
+    }
+    Ok(stream::sse_text_stream(response, provider, |event| event["choices"][0]["delta"]["content"].as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header_list() {
+        let headers = parse_header_list("X-Title: miow; HTTP-Referer: https://example.com ;bad");
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers[0], ("X-Title".to_string(), "miow".to_string()));
+        assert_eq!(headers[1].1, "https://example.com");
+    }
+
+    #[tokio::test]
+    async fn test_stream_generate_yields_deltas() {
+        use futures::StreamExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 8192];
+            let _ = socket.read(&mut request).await;
+            let body = "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+                        data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n\
+                        data: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n\
+                        data: [DONE]\n\n";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let client = OpenAIClient::new(String::new()).with_base_url(format!("http://{}/v1", address));
+        let chunks: Vec<String> = client.stream_generate("hi").await.unwrap().map(|c| c.unwrap()).collect().await;
+        assert_eq!(chunks, vec!["Hel", "lo"]);
+    }
+    #[tokio::test]
+    async fn test_timeout_bounds_the_call() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        // Accept and never answer
+        tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        });
+
+        let client = OpenAIClient::new(String::new())
+            .with_base_url(format!("http://{}/v1", address))
+            .with_timeout(std::time::Duration::from_millis(100));
+        let error = client.generate("hi").await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<LlmError>(), Some(LlmError::Timeout { .. })));
+    }
 }
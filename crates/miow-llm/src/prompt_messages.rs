@@ -0,0 +1,202 @@
+use crate::session::context_window_for_model;
+use crate::{Message, Role};
+use miow_prompt::{GeneratedPrompt, TokenCounter};
+use tracing::warn;
+
+/// Tokens held back from the context window for the model's reply
+const DEFAULT_RESERVED_FOR_COMPLETION: usize = 4096;
+/// Largest share of the context window a single context message may take
+const MAX_MESSAGE_SHARE: usize = 4;
+
+/// How big the messages built from a prompt may get
+#[derive(Debug, Clone)]
+pub struct MessageSizing {
+    pub context_window: usize,
+    pub reserved_for_completion: usize,
+    /// Context is split into continuation messages of at most this many tokens
+    pub max_message_tokens: usize,
+    pub counter: TokenCounter,
+}
+
+impl MessageSizing {
+    pub fn for_model(model: &str) -> Self {
+        let context_window = context_window_for_model(model);
+        Self {
+            context_window,
+            reserved_for_completion: DEFAULT_RESERVED_FOR_COMPLETION,
+            max_message_tokens: context_window / MAX_MESSAGE_SHARE,
+            counter: TokenCounter::for_model(model),
+        }
+    }
+}
+
+/// Split a generated prompt into chat messages ready for a chat API
+pub trait ToMessages {
+    /// Messages sized for `model`'s context window
+    fn to_messages(&self, model: &str) -> Vec<Message> {
+        self.to_messages_sized(&MessageSizing::for_model(model))
+    }
+
+    fn to_messages_sized(&self, sizing: &MessageSizing) -> Vec<Message>;
+}
+
+impl ToMessages for GeneratedPrompt {
+    /// The system prompt, then the context block as one or more user messages
+    /// ("part i of n" when split), then the request and plan as the last user
+    /// message. Context that doesn't fit the window is dropped from the end.
+    fn to_messages_sized(&self, sizing: &MessageSizing) -> Vec<Message> {
+        let counter = &sizing.counter;
+        let request = [self.user_prompt.trim(), self.implementation_plan.trim()]
+            .iter()
+            .filter(|s| !s.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let fixed = counter.count_messages([self.system_prompt.as_str(), request.as_str()]);
+        let mut context_budget = sizing
+            .context_window
+            .saturating_sub(sizing.reserved_for_completion)
+            .saturating_sub(fixed);
+
+        let chunks = split_by_tokens(&self.context_block, sizing.max_message_tokens.max(1), counter);
+        let available = chunks.len();
+        let mut parts = Vec::new();
+        for chunk in chunks {
+            // Room for the "part i of n" header
+            let cost = counter.count(&chunk) + 16;
+            if cost > context_budget {
+                break;
+            }
+            context_budget -= cost;
+            parts.push(chunk);
+        }
+        if parts.len() < available {
+            warn!("Prompt context exceeds the context window; dropped {} of {} parts", available - parts.len(), available);
+        }
+
+        let mut messages = Vec::new();
+        if !self.system_prompt.trim().is_empty() {
+            messages.push(Message { role: Role::System, content: self.system_prompt.clone() });
+        }
+        let total = parts.len();
+        for (i, part) in parts.into_iter().enumerate() {
+            let content = if total == 1 {
+                part
+            } else {
+                format!("Codebase context (part {} of {}):\n\n{}", i + 1, total, part)
+            };
+            messages.push(Message { role: Role::User, content });
+        }
+        messages.push(Message { role: Role::User, content: request });
+        messages
+    }
+}
+
+/// Split `text` into chunks of at most `max_tokens`, breaking before Markdown
+/// headings where possible and between lines otherwise
+fn split_by_tokens(text: &str, max_tokens: usize, counter: &TokenCounter) -> Vec<String> {
+    let mut sections: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match sections.last_mut() {
+            Some(section) if !line.starts_with('#') => {
+                section.push('\n');
+                section.push_str(line);
+            }
+            _ => sections.push(line.to_string()),
+        }
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for section in sections {
+        if counter.count(&current) + counter.count(&section) <= max_tokens {
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(&section);
+            continue;
+        }
+        flush(&mut current, &mut chunks);
+        if counter.count(&section) <= max_tokens {
+            current = section;
+            continue;
+        }
+        // A section too big for one message is split between lines
+        for line in section.lines() {
+            if !current.is_empty() && counter.count(&current) + counter.count(line) > max_tokens {
+                flush(&mut current, &mut chunks);
+            }
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(line);
+        }
+    }
+    flush(&mut current, &mut chunks);
+    chunks
+}
+
+fn flush(current: &mut String, chunks: &mut Vec<String>) {
+    if !current.trim().is_empty() {
+        chunks.push(current.trim_end().to_string());
+    }
+    current.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(context_block: String) -> GeneratedPrompt {
+        GeneratedPrompt {
+            system_prompt: "You are an expert engineer.".to_string(),
+            context_block,
+            user_prompt: "## User Request\nAdd a login page".to_string(),
+            implementation_plan: "1. Add the route".to_string(),
+            full_prompt: String::new(),
+            citations: vec![],
+        }
+    }
+
+    #[test]
+    fn test_context_is_split_into_continuation_messages() {
+        let context: String = (0..12).map(|i| format!("## File {}\n{}\n", i, "let x = 1;\n".repeat(10))).collect();
+        let sizing = MessageSizing {
+            context_window: 100_000,
+            reserved_for_completion: 0,
+            max_message_tokens: 100,
+            counter: TokenCounter::default(),
+        };
+
+        let messages = prompt(context).to_messages_sized(&sizing);
+        assert!(matches!(messages[0].role, Role::System));
+        assert!(messages.len() > 3);
+        assert!(messages[1].content.starts_with("Codebase context (part 1 of "));
+        assert!(messages[1].content.contains("## File 0"));
+        let last = messages.last().unwrap();
+        assert!(last.content.starts_with("## User Request") && last.content.ends_with("1. Add the route"));
+
+        // A small context stays in one message without a part header
+        let messages = prompt("## File\nlet x = 1;".to_string()).to_messages("gpt-4o");
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].content, "## File\nlet x = 1;");
+    }
+
+    #[test]
+    fn test_context_beyond_the_window_is_dropped() {
+        let context: String = (0..50).map(|i| format!("## File {}\n{}\n", i, "let x = 1;\n".repeat(10))).collect();
+        let sizing = MessageSizing {
+            context_window: 400,
+            reserved_for_completion: 100,
+            max_message_tokens: 100,
+            counter: TokenCounter::default(),
+        };
+
+        let messages = prompt(context).to_messages_sized(&sizing);
+        let counter = TokenCounter::default();
+        let used = counter.count_messages(messages.iter().map(|m| m.content.as_str()));
+        assert!(used <= 300, "{} tokens", used);
+        assert!(messages.last().unwrap().content.contains("Add a login page"));
+    }
+}
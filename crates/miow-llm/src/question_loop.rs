@@ -1,11 +1,11 @@
 use anyhow::{Context, Result};
 use miow_graph::{KnowledgeGraph, SymbolSearchResult};
-use miow_vector::{VectorStore, SymbolSearchResult as VectorResult};
+use miow_vector::VectorStore;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::{debug, info, warn};
 
-use crate::{LLMProvider, Message, Role};
+use crate::{ChatSession, LLMProvider};
 
 /// Critical question for context gathering
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +46,10 @@ pub struct QuestionAnswer {
     pub confidence: f32,
 }
 
+const QUESTION_SYSTEM_PROMPT: &str = "You help locate existing code in a codebase. \
+You verify whether search results answer a question and propose better search queries when they don't. \
+Always answer with ONLY the requested JSON.";
+
 /// Question loop executor with rollback capability
 pub struct QuestionLoop {
     llm: Arc<dyn LLMProvider>,
@@ -113,6 +117,9 @@ impl QuestionLoop {
     
     /// Execute a single question with retry logic
     async fn execute_single_question(&self, mut question: CriticalQuestion) -> Result<QuestionResult> {
+        // One conversation per question so retries see earlier verdicts and queries
+        let mut session = ChatSession::for_model(self.llm.model_name()).with_system(QUESTION_SYSTEM_PROMPT);
+
         for attempt in 0..self.max_retries {
             debug!("🔄 Attempt {}/{}", attempt + 1, self.max_retries);
             
@@ -127,7 +134,7 @@ impl QuestionLoop {
             if search_results.is_empty() && attempt < self.max_retries - 1 {
                 // Try to reformulate before verifying
                 debug!("No results found, reformulating query...");
-                question = self.reformulate_question(question, &search_results).await?;
+                question = self.reformulate_question(&mut session, question, &search_results).await?;
                 continue;
             }
             
@@ -138,7 +145,7 @@ impl QuestionLoop {
             // 2. Verify results with LLM
             info!("💬 [LLM VERIFY] Verifying {} results against question...", search_results.len());
             let verify_start = std::time::Instant::now();
-            let verification = self.verify_results(&question, &search_results).await?;
+            let verification = self.verify_results(&mut session, &question, &search_results).await?;
             let verify_duration = verify_start.elapsed();
             info!("   Verification result: is_correct={}, reason: '{}' (took {:?})", 
                   verification.is_correct, verification.reason, verify_duration);
@@ -157,7 +164,7 @@ impl QuestionLoop {
                 debug!("🔙 Verification failed: {}", verification.reason);
                 debug!("Reformulating query...");
                 
-                question = self.reformulate_question(question, &search_results).await?;
+                question = self.reformulate_question(&mut session, question, &search_results).await?;
             } else {
                 // Last attempt failed, return partial if we have something
                 if !search_results.is_empty() {
@@ -223,6 +230,7 @@ impl QuestionLoop {
     /// Verify if search results answer the question
     async fn verify_results(
         &self,
+        session: &mut ChatSession,
         question: &CriticalQuestion,
         results: &[SymbolSearchResult],
     ) -> Result<VerificationResult> {
@@ -257,7 +265,7 @@ Return ONLY the JSON."#,
         
         info!("   [LLM] Calling LLM for verification...");
         let llm_start = std::time::Instant::now();
-        let response = self.llm.generate(&prompt).await?;
+        let response = session.send(self.llm.as_ref(), prompt).await?;
         let llm_duration = llm_start.elapsed();
         info!("   [LLM] Response received in {:?} ({} chars)", llm_duration, response.content.len());
         
@@ -282,6 +290,7 @@ Return ONLY the JSON."#,
     /// Reformulate question based on failed search
     async fn reformulate_question(
         &self,
+        session: &mut ChatSession,
         question: CriticalQuestion,
         _failed_results: &[SymbolSearchResult],
     ) -> Result<CriticalQuestion> {
@@ -305,7 +314,7 @@ Return ONLY the JSON."#,
         
         info!("   [LLM] Calling LLM for query reformulation...");
         let reformulate_start = std::time::Instant::now();
-        let response = self.llm.generate(&prompt).await?;
+        let response = session.send(self.llm.as_ref(), prompt).await?;
         let reformulate_duration = reformulate_start.elapsed();
         info!("   [LLM] Reformulation response received in {:?}", reformulate_duration);
         
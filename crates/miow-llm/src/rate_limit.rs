@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{sleep, Duration, Instant};
+use tracing::debug;
+
+/// Rate limit settings for a single provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RateLimitConfig {
+    /// Sustained requests per minute (token bucket refill rate)
+    pub requests_per_minute: u32,
+    /// Maximum burst size (token bucket capacity)
+    pub burst: u32,
+    /// Maximum number of concurrent in-flight requests
+    pub max_in_flight: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 60,
+            burst: 5,
+            max_in_flight: 4,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Defaults for a provider, overridable with `MIOW_<PROVIDER>_RPM`,
+    /// `MIOW_<PROVIDER>_BURST` and `MIOW_<PROVIDER>_MAX_IN_FLIGHT`
+    pub fn for_provider(provider: &str) -> Self {
+        let mut config = Self::default();
+        let prefix = format!("MIOW_{}", provider.to_uppercase());
+
+        if let Some(rpm) = env_number(&format!("{}_RPM", prefix)) {
+            config.requests_per_minute = rpm as u32;
+        }
+        if let Some(burst) = env_number(&format!("{}_BURST", prefix)) {
+            config.burst = burst as u32;
+        }
+        if let Some(max) = env_number(&format!("{}_MAX_IN_FLIGHT", prefix)) {
+            config.max_in_flight = max;
+        }
+
+        config
+    }
+}
+
+fn env_number(key: &str) -> Option<usize> {
+    std::env::var(key).ok()?.parse().ok().filter(|n| *n > 0)
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token-bucket rate limiter combined with a max-in-flight semaphore
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    bucket: Mutex<Bucket>,
+    in_flight: Arc<Semaphore>,
+}
+
+/// Process-wide limiters by provider and config
+type SharedLimiters = HashMap<(String, RateLimitConfig), Arc<RateLimiter>>;
+
+/// Held for the duration of a request; releases the in-flight slot on drop
+pub struct RateLimitPermit {
+    _permit: OwnedSemaphorePermit,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            bucket: Mutex::new(Bucket {
+                tokens: config.burst.max(1) as f64,
+                last_refill: Instant::now(),
+            }),
+            in_flight: Arc::new(Semaphore::new(config.max_in_flight.max(1))),
+        }
+    }
+
+    /// Limiter shared by every client of `provider` in this process that asks for the
+    /// same config; clients with another config share a limiter of their own
+    pub fn shared(provider: &str, config: RateLimitConfig) -> Arc<Self> {
+        static LIMITERS: OnceLock<Mutex<SharedLimiters>> = OnceLock::new();
+
+        let mut limiters = LIMITERS.get_or_init(Default::default).lock().unwrap();
+        limiters
+            .entry((provider.to_string(), config))
+            .or_insert_with(|| Arc::new(Self::new(config)))
+            .clone()
+    }
+
+    pub fn config(&self) -> RateLimitConfig {
+        self.config
+    }
+
+    /// Wait for an in-flight slot and a bucket token
+    pub async fn acquire(&self) -> RateLimitPermit {
+        let permit = self
+            .in_flight
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("rate limiter semaphore closed");
+
+        loop {
+            match self.try_take_token() {
+                None => break,
+                Some(wait) => {
+                    debug!("Rate limit reached, waiting {:?}", wait);
+                    sleep(wait).await;
+                }
+            }
+        }
+
+        RateLimitPermit { _permit: permit }
+    }
+
+    /// Take a token if available, otherwise return how long until one is
+    fn try_take_token(&self) -> Option<Duration> {
+        let rate_per_sec = self.config.requests_per_minute.max(1) as f64 / 60.0;
+        let capacity = self.config.burst.max(1) as f64;
+
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate_per_sec).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - bucket.tokens) / rate_per_sec))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_burst_then_wait() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_minute: 60,
+            burst: 2,
+            max_in_flight: 2,
+        });
+
+        drop(limiter.acquire().await);
+        drop(limiter.acquire().await);
+        assert!(limiter.try_take_token().is_some());
+    }
+
+    #[test]
+    fn test_shared_follows_config() {
+        let slow = RateLimitConfig { requests_per_minute: 1, burst: 1, max_in_flight: 1 };
+        let fast = RateLimitConfig { requests_per_minute: 600, ..slow };
+
+        let first = RateLimiter::shared("test-shared", slow);
+        assert!(Arc::ptr_eq(&first, &RateLimiter::shared("test-shared", slow)));
+
+        let other = RateLimiter::shared("test-shared", fast);
+        assert_eq!(other.config(), fast);
+        assert!(!Arc::ptr_eq(&first, &other));
+
+        // Asking for the first config again doesn't replace the limiter its clients share
+        assert!(Arc::ptr_eq(&first, &RateLimiter::shared("test-shared", slow)));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{LLMProvider, LLMResponse, Message, Role};
+use anyhow::Result;
+use miow_prompt::TokenCounter;
+use tracing::debug;
+
+/// Tokens held back from the context window for the model's reply
+const DEFAULT_RESERVED_FOR_COMPLETION: usize = 4096;
+
+/// Approximate context window (in tokens) for a model name
+pub fn context_window_for_model(model: &str) -> usize {
+    let model = model.to_lowercase();
+    if model.contains("gemini") {
+        1_000_000
+    } else if model.contains("claude") {
+        200_000
+    } else if model.contains("gpt-4o") || model.contains("gpt-4-turbo") || model.starts_with("o1") || model.starts_with("o3") {
+        128_000
+    } else if model.contains("gpt-4") {
+        8_192
+    } else if model.contains("gpt-3.5") {
+        16_385
+    } else {
+        32_768
+    }
+}
+
+/// Multi-turn conversation with an LLM.
+///
+/// Keeps the full message history and sends a sliding window of it: the system
+/// prompt is always kept, then the newest messages that fit in the model's
+/// context (minus the space reserved for the completion).
+#[derive(Debug, Clone)]
+pub struct ChatSession {
+    system: Option<String>,
+    history: Vec<Message>,
+    context_window: usize,
+    reserved_for_completion: usize,
+    counter: TokenCounter,
+}
+
+impl ChatSession {
+    pub fn new() -> Self {
+        Self::for_model("")
+    }
+
+    /// Size the window and token counting for `model`
+    pub fn for_model(model: &str) -> Self {
+        Self {
+            system: None,
+            history: Vec::new(),
+            context_window: context_window_for_model(model),
+            reserved_for_completion: DEFAULT_RESERVED_FOR_COMPLETION,
+            counter: TokenCounter::for_model(model),
+        }
+    }
+
+    pub fn with_system(mut self, system: impl Into<String>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    pub fn with_context_window(mut self, tokens: usize) -> Self {
+        self.context_window = tokens;
+        self
+    }
+
+    pub fn with_reserved_for_completion(mut self, tokens: usize) -> Self {
+        self.reserved_for_completion = tokens;
+        self
+    }
+
+    pub fn push_user(&mut self, content: impl Into<String>) {
+        self.history.push(Message {
+            role: Role::User,
+            content: content.into(),
+        });
+    }
+
+    pub fn push_assistant(&mut self, content: impl Into<String>) {
+        self.history.push(Message {
+            role: Role::Assistant,
+            content: content.into(),
+        });
+    }
+
+    /// Full history, excluding the system prompt
+    pub fn history(&self) -> &[Message] {
+        &self.history
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    /// Messages to send: system prompt plus the newest history that fits the budget.
+    /// The latest message is always included even if it alone exceeds the budget.
+    pub fn window(&self) -> Vec<Message> {
+        let budget = self.context_window.saturating_sub(self.reserved_for_completion);
+        let system = self.system.as_ref().map(|content| Message {
+            role: Role::System,
+            content: content.clone(),
+        });
+
+        let mut used = system
+            .as_ref()
+            .map(|m| self.counter.count_messages([m.content.as_str()]))
+            .unwrap_or(0);
+
+        let mut kept = 0;
+        for message in self.history.iter().rev() {
+            let cost = self.counter.count(&message.content) + 4;
+            if kept > 0 && used + cost > budget {
+                break;
+            }
+            used += cost;
+            kept += 1;
+        }
+
+        if kept < self.history.len() {
+            debug!(
+                "Chat session truncated {} of {} messages to fit {} tokens",
+                self.history.len() - kept,
+                self.history.len(),
+                budget
+            );
+        }
+
+        system
+            .into_iter()
+            .chain(self.history[self.history.len() - kept..].iter().cloned())
+            .collect()
+    }
+
+    /// Add a user turn, send the window to `llm` and record the reply
+    pub async fn send(&mut self, llm: &dyn LLMProvider, content: impl Into<String>) -> Result<LLMResponse> {
+        self.push_user(content);
+        let response = llm.generate_with_context(self.window()).await?;
+        self.push_assistant(response.content.clone());
+        Ok(response)
+    }
+}
+
+impl Default for ChatSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_keeps_system_and_newest_messages() {
+        let mut session = ChatSession::new()
+            .with_system("be brief")
+            .with_context_window(60)
+            .with_reserved_for_completion(0);
+
+        for i in 0..10 {
+            session.push_user(format!("question number {} {}", i, "x".repeat(40)));
+        }
+
+        let window = session.window();
+        assert!(matches!(window[0].role, Role::System));
+        assert!(window.len() < 11);
+        assert!(window.last().unwrap().content.starts_with("question number 9"));
+    }
+}
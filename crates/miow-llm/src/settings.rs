@@ -0,0 +1,218 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::{AzureOpenAIClient, GeminiClient, LLMConfig, LLMProvider, OpenAIClient};
+
+/// An LLM backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// Google Gemini, keyed by `GEMINI_API_KEY`
+    Gemini,
+    /// Azure OpenAI, from the `AZURE_OPENAI_*` variables
+    Azure,
+    /// OpenAI or any compatible server, from the `OPENAI_*` variables
+    OpenAI,
+}
+
+impl ProviderKind {
+    pub const NAMES: [&'static str; 3] = ["gemini", "azure", "openai"];
+
+    /// The first provider whose credentials are in the environment, in the
+    /// order Gemini, Azure OpenAI, OpenAI-compatible
+    pub fn from_env() -> Option<Self> {
+        if std::env::var("GEMINI_API_KEY").is_ok() {
+            Some(Self::Gemini)
+        } else if std::env::var("AZURE_OPENAI_ENDPOINT").is_ok() {
+            Some(Self::Azure)
+        } else if std::env::var("OPENAI_BASE_URL").is_ok() || std::env::var("OPENAI_API_KEY").is_ok() {
+            Some(Self::OpenAI)
+        } else {
+            None
+        }
+    }
+
+    /// Name for status messages
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Gemini => "Gemini",
+            Self::Azure => "Azure OpenAI",
+            Self::OpenAI => "OpenAI-compatible",
+        }
+    }
+}
+
+impl FromStr for ProviderKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "gemini" => Ok(Self::Gemini),
+            "azure" => Ok(Self::Azure),
+            "openai" => Ok(Self::OpenAI),
+            other => Err(anyhow!("Unknown provider '{}' (expected one of: {})", other, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+/// Which LLM to use and how, from the `[llm]` table of miow.toml and CLI flags.
+/// Anything left unset falls back to the provider's environment variables, then
+/// to the `LLMConfig` defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LLMSettings {
+    /// Provider to use; detected from the environment when unset
+    pub provider: Option<ProviderKind>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<usize>,
+    /// Server for the `openai` provider, overriding `OPENAI_BASE_URL`
+    pub base_url: Option<String>,
+    /// Extra headers sent with every `openai` request, on top of `OPENAI_HEADERS`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl LLMSettings {
+    /// These settings with every field `overrides` sets replaced
+    pub fn merged(self, overrides: LLMSettings) -> Self {
+        let mut headers = self.headers;
+        headers.extend(overrides.headers);
+        Self {
+            provider: overrides.provider.or(self.provider),
+            model: overrides.model.or(self.model),
+            temperature: overrides.temperature.or(self.temperature),
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            base_url: overrides.base_url.or(self.base_url),
+            headers,
+        }
+    }
+
+    /// The configured provider, else the one the environment has credentials for
+    pub fn provider(&self) -> Option<ProviderKind> {
+        self.provider.or_else(ProviderKind::from_env)
+    }
+
+    /// `LLMConfig` for these settings, with `default_model` when no model is set
+    pub fn llm_config(&self, api_key: String, default_model: &str) -> LLMConfig {
+        let defaults = LLMConfig::default();
+        LLMConfig {
+            api_key,
+            model: self.model.clone().unwrap_or_else(|| default_model.to_string()),
+            temperature: self.temperature.unwrap_or(defaults.temperature),
+            max_tokens: self.max_tokens.unwrap_or(defaults.max_tokens),
+            ..defaults
+        }
+    }
+
+    /// Build the client. `Ok(None)` when no provider is configured and none is
+    /// found in the environment.
+    pub fn build(&self) -> Result<Option<Arc<dyn LLMProvider>>> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                anyhow::bail!("Temperature must be between 0 and 2, got {}", temperature);
+            }
+        }
+
+        let Some(provider) = self.provider() else {
+            return Ok(None);
+        };
+        let client: Arc<dyn LLMProvider> = match provider {
+            ProviderKind::Gemini => {
+                let api_key = std::env::var("GEMINI_API_KEY").context("GEMINI_API_KEY environment variable not set")?;
+                let config = self.llm_config(api_key, &LLMConfig::default().model);
+                let mut client = GeminiClient::new(config)?;
+                if let Some(max_tokens) = self.max_tokens {
+                    client = client.with_max_tokens(max_tokens);
+                }
+                Arc::new(client)
+            }
+            ProviderKind::Azure => {
+                let client = AzureOpenAIClient::from_env()?;
+                let config = self.llm_config(String::new(), client.model_name());
+                Arc::new(client.with_config(&config))
+            }
+            ProviderKind::OpenAI => {
+                let mut client = OpenAIClient::from_env();
+                if let Some(base_url) = &self.base_url {
+                    client = client.with_base_url(base_url);
+                }
+                for (name, value) in &self.headers {
+                    client = client.with_header(name, value);
+                }
+                let config = self.llm_config(String::new(), client.model_name());
+                Arc::new(client.with_config(&config))
+            }
+        };
+        Ok(Some(client))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_win_where_set() {
+        let file = LLMSettings {
+            provider: Some(ProviderKind::OpenAI),
+            model: Some("gpt-4o".to_string()),
+            temperature: Some(0.2),
+            max_tokens: None,
+            base_url: None,
+            headers: BTreeMap::new(),
+        };
+        let flags = LLMSettings { model: Some("gpt-4o-mini".to_string()), max_tokens: Some(1024), ..Default::default() };
+        let merged = file.merged(flags);
+
+        assert_eq!(merged.provider, Some(ProviderKind::OpenAI));
+        assert_eq!(merged.model.as_deref(), Some("gpt-4o-mini"));
+        assert_eq!(merged.temperature, Some(0.2));
+        assert_eq!(merged.max_tokens, Some(1024));
+
+        let config = merged.llm_config(String::new(), "unused");
+        assert_eq!(config.model, "gpt-4o-mini");
+        assert_eq!(config.temperature, 0.2);
+
+        let config = LLMSettings::default().llm_config(String::new(), "gpt-4-turbo-preview");
+        assert_eq!(config.model, "gpt-4-turbo-preview");
+        assert_eq!(config.max_tokens, 4096);
+    }
+
+    #[test]
+    fn test_openai_server_settings_round_trip() {
+        let settings = LLMSettings {
+            provider: Some(ProviderKind::OpenAI),
+            base_url: Some("http://localhost:11434/v1".to_string()),
+            headers: BTreeMap::from([("HTTP-Referer".to_string(), "https://example.com".to_string())]),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(serde_json::from_str::<LLMSettings>(&json).unwrap(), settings);
+
+        let flags = LLMSettings {
+            headers: BTreeMap::from([("X-Title".to_string(), "miow".to_string())]),
+            ..Default::default()
+        };
+        let merged = settings.merged(flags);
+        assert_eq!(merged.base_url.as_deref(), Some("http://localhost:11434/v1"));
+        assert_eq!(merged.headers.len(), 2);
+
+        let client = merged.build().unwrap().unwrap();
+        assert!(client.cache_identity().starts_with("http://localhost:11434/v1 "));
+    }
+
+    #[test]
+    fn test_parses_provider_names() {
+        assert_eq!("Gemini".parse::<ProviderKind>().unwrap(), ProviderKind::Gemini);
+        assert_eq!("openai".parse::<ProviderKind>().unwrap(), ProviderKind::OpenAI);
+        assert!("claude".parse::<ProviderKind>().is_err());
+
+        let settings: LLMSettings = serde_json::from_str(r#"{"provider": "azure", "temperature": 0.1}"#).unwrap();
+        assert_eq!(settings.provider, Some(ProviderKind::Azure));
+        assert_eq!(settings.model, None);
+    }
+}
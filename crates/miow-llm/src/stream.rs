@@ -0,0 +1,125 @@
+//! Streamed responses. Providers send them as server-sent events, one JSON
+//! payload per `data:` line, which are decoded into the text they add.
+
+use anyhow::Result;
+use futures::Stream;
+use std::collections::VecDeque;
+
+/// Text of a response as it is generated, chunk by chunk
+pub type TextStream = Box<dyn Stream<Item = Result<String>> + Send + Unpin>;
+
+/// Splits a server-sent event body into the payloads of its `data:` lines.
+/// Bytes arrive in arbitrary chunks, so a line (or a UTF-8 character) may
+/// span several.
+#[derive(Default)]
+pub(crate) struct SseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDecoder {
+    /// Payloads of the lines `bytes` completes
+    pub(crate) fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut payloads = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            payloads.extend(data_payload(&line));
+        }
+        payloads
+    }
+
+    /// Payload of a last line the body didn't end with a newline
+    pub(crate) fn finish(&mut self) -> Option<String> {
+        let line = std::mem::take(&mut self.buffer);
+        data_payload(&line)
+    }
+}
+
+fn data_payload(line: &[u8]) -> Option<String> {
+    let line = String::from_utf8_lossy(line);
+    let payload = line.trim_end_matches(['\r', '\n']).strip_prefix("data:")?.trim_start();
+    (!payload.is_empty()).then(|| payload.to_string())
+}
+
+/// The text an SSE response adds, as `text_of` finds it in each event's JSON.
+/// OpenAI-style streams end with a `[DONE]` event; an event carrying an
+/// `error` object ends the stream with that error.
+pub(crate) fn sse_text_stream(
+    response: reqwest::Response,
+    provider: &'static str,
+    text_of: fn(&serde_json::Value) -> Option<&str>,
+) -> TextStream {
+    struct State {
+        response: reqwest::Response,
+        decoder: SseDecoder,
+        pending: VecDeque<String>,
+        done: bool,
+    }
+    let state = State { response, decoder: SseDecoder::default(), pending: VecDeque::new(), done: false };
+
+    let stream = futures::stream::unfold(state, move |mut state| async move {
+        loop {
+            if let Some(payload) = state.pending.pop_front() {
+                if payload == "[DONE]" {
+                    state.pending.clear();
+                    state.done = true;
+                    continue;
+                }
+                let event: serde_json::Value = match serde_json::from_str(&payload) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        state.done = true;
+                        return Some((Err(anyhow::anyhow!("Malformed {} stream event: {}", provider, e)), state));
+                    }
+                };
+                if let Some(error) = event.get("error") {
+                    state.pending.clear();
+                    state.done = true;
+                    let message = error.get("message").and_then(|m| m.as_str()).map_or_else(|| error.to_string(), String::from);
+                    return Some((Err(anyhow::anyhow!("{} stream failed: {}", provider, message)), state));
+                }
+                match text_of(&event) {
+                    Some(text) if !text.is_empty() => return Some((Ok(text.to_string()), state)),
+                    _ => continue,
+                }
+            }
+            if state.done {
+                return None;
+            }
+            match state.response.chunk().await {
+                Ok(Some(bytes)) => {
+                    let payloads = state.decoder.push(&bytes);
+                    state.pending.extend(payloads);
+                }
+                Ok(None) => {
+                    state.done = true;
+                    let last = state.decoder.finish();
+                    state.pending.extend(last);
+                }
+                Err(source) => {
+                    state.done = true;
+                    return Some((Err(crate::LlmError::Unreachable { provider: provider.to_string(), source }.into()), state));
+                }
+            }
+        }
+    });
+    Box::new(Box::pin(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_decoder_joins_split_lines() {
+        let mut decoder = SseDecoder::default();
+        let body = "event: message\ndata: {\"text\":\"héllo\"}\r\n\n: keep-alive\ndata: [DONE]";
+        let bytes = body.as_bytes();
+        // Split inside the `é`, which is two bytes
+        let split = body.find('é').unwrap() + 1;
+        assert!(decoder.push(&bytes[..split]).is_empty());
+        assert_eq!(decoder.push(&bytes[split..]), vec!["{\"text\":\"héllo\"}".to_string()]);
+        assert_eq!(decoder.finish(), Some("[DONE]".to_string()));
+        assert_eq!(decoder.finish(), None);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::{LLMProvider, LLMResponse, Message, TextStream, Usage};
+use futures::StreamExt;
+use anyhow::Result;
+use async_trait::async_trait;
+use miow_prompt::TokenCounter;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Price per million tokens (input, output) in USD for known models
+fn price_per_million(model: &str) -> (f64, f64) {
+    let model = model.to_lowercase();
+    if model.starts_with("gemini-2.5-pro") {
+        (1.25, 10.0)
+    } else if model.starts_with("gemini-2.5-flash-lite") {
+        (0.10, 0.40)
+    } else if model.starts_with("gemini-2.5-flash") {
+        (0.30, 2.50)
+    } else if model.starts_with("gemini") {
+        (0.10, 0.40)
+    } else if model.starts_with("gpt-4o-mini") {
+        (0.15, 0.60)
+    } else if model.starts_with("gpt-4o") {
+        (2.50, 10.0)
+    } else if model.starts_with("gpt-4") {
+        (10.0, 30.0)
+    } else if model.starts_with("gpt-3.5") {
+        (0.50, 1.50)
+    } else {
+        (0.0, 0.0)
+    }
+}
+
+/// Estimated cost in USD for a call to `model`
+pub fn estimate_cost(model: &str, prompt_tokens: usize, completion_tokens: usize) -> f64 {
+    let (input, output) = price_per_million(model);
+    (prompt_tokens as f64 * input + completion_tokens as f64 * output) / 1_000_000.0
+}
+
+/// Accumulated usage for a single provider/model pair
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub calls: usize,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub estimated_cost_usd: f64,
+    /// Calls where the provider did not report usage and tokens were estimated
+    pub estimated_calls: usize,
+}
+
+/// Snapshot of everything recorded by a `UsageTracker`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub calls: usize,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub total_tokens: usize,
+    pub estimated_cost_usd: f64,
+    pub by_model: Vec<ModelUsage>,
+}
+
+/// Accumulates prompt/completion tokens and estimated cost across LLM calls
+#[derive(Default)]
+pub struct UsageTracker {
+    models: Mutex<BTreeMap<(String, String), ModelUsage>>,
+    /// Also report calls as `miow_llm_*` metrics
+    metered: bool,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A tracker that reports to the `metrics` recorder as well. Calls pass
+    /// through several trackers (process, orchestrator, run), so only one
+    /// should be metered or they are counted more than once.
+    pub fn metered() -> Self {
+        Self { metered: true, ..Self::default() }
+    }
+
+    /// Record a completed call
+    pub fn record(&self, provider: &str, model: &str, usage: &Usage, estimated: bool) {
+        if self.metered {
+            let labels = [("provider", provider.to_string()), ("model", model.to_string())];
+            metrics::counter!("miow_llm_requests_total", &labels).increment(1);
+            metrics::counter!("miow_llm_prompt_tokens_total", &labels).increment(usage.prompt_tokens as u64);
+            metrics::counter!("miow_llm_completion_tokens_total", &labels).increment(usage.completion_tokens as u64);
+        }
+        let mut models = self.models.lock().unwrap();
+        let entry = models
+            .entry((provider.to_string(), model.to_string()))
+            .or_insert_with(|| ModelUsage {
+                provider: provider.to_string(),
+                model: model.to_string(),
+                ..Default::default()
+            });
+
+        entry.calls += 1;
+        entry.prompt_tokens += usage.prompt_tokens;
+        entry.completion_tokens += usage.completion_tokens;
+        entry.estimated_cost_usd += estimate_cost(model, usage.prompt_tokens, usage.completion_tokens);
+        if estimated {
+            entry.estimated_calls += 1;
+        }
+    }
+
+    /// Totals across all providers and models
+    pub fn summary(&self) -> UsageSummary {
+        let models = self.models.lock().unwrap();
+        let by_model: Vec<ModelUsage> = models.values().cloned().collect();
+
+        let mut summary = UsageSummary::default();
+        for m in &by_model {
+            summary.calls += m.calls;
+            summary.prompt_tokens += m.prompt_tokens;
+            summary.completion_tokens += m.completion_tokens;
+            summary.estimated_cost_usd += m.estimated_cost_usd;
+        }
+        summary.total_tokens = summary.prompt_tokens + summary.completion_tokens;
+        summary.by_model = by_model;
+        summary
+    }
+
+    /// Forget everything recorded so far
+    pub fn reset(&self) {
+        self.models.lock().unwrap().clear();
+    }
+}
+
+/// LLM provider wrapper that records token usage for every call into a `UsageTracker`
+pub struct UsageTrackingProvider {
+    inner: Arc<dyn LLMProvider>,
+    tracker: Arc<UsageTracker>,
+    /// Also records here, e.g. to read one request's usage apart from the total
+    extra: Option<Arc<UsageTracker>>,
+    counter: TokenCounter,
+}
+
+impl UsageTrackingProvider {
+    pub fn new(inner: Arc<dyn LLMProvider>, tracker: Arc<UsageTracker>) -> Self {
+        let counter = TokenCounter::for_model(inner.model_name());
+        Self { inner, tracker, extra: None, counter }
+    }
+
+    /// The same provider, recording every call into `extra` as well
+    pub fn with_extra_tracker(&self, extra: Arc<UsageTracker>) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            tracker: self.tracker.clone(),
+            extra: Some(extra),
+            counter: self.counter,
+        }
+    }
+
+    fn track(&self, prompt_tokens: usize, response: &LLMResponse) {
+        let (usage, estimated) = match &response.usage {
+            Some(usage) => (usage.clone(), false),
+            None => {
+                let completion_tokens = self.counter.count(&response.content);
+                (
+                    Usage {
+                        prompt_tokens,
+                        completion_tokens,
+                        total_tokens: prompt_tokens + completion_tokens,
+                    },
+                    true,
+                )
+            }
+        };
+
+        self.tracker
+            .record(self.inner.provider_name(), self.inner.model_name(), &usage, estimated);
+        if let Some(extra) = &self.extra {
+            extra.record(self.inner.provider_name(), self.inner.model_name(), &usage, estimated);
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for UsageTrackingProvider {
+    async fn generate(&self, prompt: &str) -> Result<LLMResponse> {
+        let response = self.inner.generate(prompt).await?;
+        self.track(self.counter.count(prompt), &response);
+        Ok(response)
+    }
+
+    async fn generate_with_context(&self, messages: Vec<Message>) -> Result<LLMResponse> {
+        let prompt_tokens = self
+            .counter
+            .count_messages(messages.iter().map(|m| m.content.as_str()));
+        let response = self.inner.generate_with_context(messages).await?;
+        self.track(prompt_tokens, &response);
+        Ok(response)
+    }
+
+    async fn generate_n(&self, prompt: &str, n: usize) -> Result<Vec<LLMResponse>> {
+        let responses = self.inner.generate_n(prompt, n).await?;
+        let prompt_tokens = self.counter.count(prompt);
+        for response in &responses {
+            self.track(prompt_tokens, response);
+        }
+        Ok(responses)
+    }
+
+    async fn stream_generate(&self, prompt: &str) -> Result<TextStream> {
+        let prompt_tokens = self.counter.count(prompt);
+        let stream = self.inner.stream_generate(prompt).await?;
+        // Streams report no usage, so the text is counted once the stream ends
+        let trackers: Vec<Arc<UsageTracker>> = std::iter::once(self.tracker.clone()).chain(self.extra.clone()).collect();
+        let (provider, model) = (self.inner.provider_name().to_string(), self.inner.model_name().to_string());
+        let counter = self.counter;
+        let stream = futures::stream::unfold((stream, String::new()), move |(mut stream, mut text)| {
+            let (trackers, provider, model) = (trackers.clone(), provider.clone(), model.clone());
+            async move {
+                match stream.next().await {
+                    Some(chunk) => {
+                        if let Ok(chunk) = &chunk {
+                            text.push_str(chunk);
+                        }
+                        Some((chunk, (stream, text)))
+                    }
+                    None => {
+                        let completion_tokens = counter.count(&text);
+                        let usage = Usage { prompt_tokens, completion_tokens, total_tokens: prompt_tokens + completion_tokens };
+                        for tracker in &trackers {
+                            tracker.record(&provider, &model, &usage, true);
+                        }
+                        None
+                    }
+                }
+            }
+        });
+        Ok(Box::new(Box::pin(stream)))
+    }
+
+    async fn generate_multi_step(&self, steps: Vec<String>, context: &str) -> Result<LLMResponse> {
+        let prompt_tokens = steps
+            .iter()
+            .map(|s| self.counter.count(s) + self.counter.count(context))
+            .sum();
+        let response = self.inner.generate_multi_step(steps, context).await?;
+        self.track(prompt_tokens, &response);
+        Ok(response)
+    }
+
+    async fn generate_with_framework(&self, prompt: &str, framework: &str, lang: &str) -> Result<LLMResponse> {
+        let response = self.inner.generate_with_framework(prompt, framework, lang).await?;
+        self.track(self.counter.count(prompt), &response);
+        Ok(response)
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+    fn cache_identity(&self) -> String {
+        self.inner.cache_identity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_accumulates_per_model() {
+        let tracker = UsageTracker::new();
+        let usage = Usage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 0,
+            total_tokens: 1_000_000,
+        };
+
+        tracker.record("gemini", "gemini-2.5-flash", &usage, false);
+        tracker.record("gemini", "gemini-2.5-flash", &usage, true);
+
+        let summary = tracker.summary();
+        assert_eq!(summary.calls, 2);
+        assert_eq!(summary.by_model.len(), 1);
+        assert_eq!(summary.by_model[0].estimated_calls, 1);
+        assert!((summary.estimated_cost_usd - 0.60).abs() < 1e-9);
+    }
+}
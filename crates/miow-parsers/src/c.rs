@@ -0,0 +1,673 @@
+use crate::diagnostics::{for_each_declaration, tree_diagnostics};
+use crate::types::*;
+use anyhow::{Context, Result};
+use tree_sitter::{Node, Parser, Tree};
+
+pub struct CParser;
+
+impl CParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn parse(&self, content: &str) -> Result<ParsedFile> {
+        parse_c_family(tree_sitter_c::language(), "c", content)
+    }
+
+    /// Extract from an already-parsed tree; lets callers reuse an incrementally reparsed tree
+    pub fn parse_tree(&self, tree: &Tree, content: &str) -> Result<ParsedFile> {
+        extract_c_family(tree, "c", content)
+    }
+}
+
+impl Default for CParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shared extraction for C and C++; the C++ grammar is a superset of the C one,
+/// so the same walker handles both and only picks up classes/namespaces for C++.
+pub(crate) fn parse_c_family(
+    language: tree_sitter::Language,
+    language_name: &str,
+    content: &str,
+) -> Result<ParsedFile> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(language)
+        .with_context(|| format!("Failed to set {} language", language_name))?;
+
+    let tree = parser
+        .parse(content, None)
+        .with_context(|| format!("Failed to parse {} content", language_name))?;
+
+    extract_c_family(&tree, language_name, content)
+}
+
+pub(crate) fn extract_c_family(tree: &Tree, language_name: &str, content: &str) -> Result<ParsedFile> {
+    let extractor = CFamilyExtractor {
+        source: content,
+        is_cpp: language_name == "cpp",
+    };
+    let mut out = Collected::default();
+    extractor.visit_items(&tree.root_node(), &mut out)?;
+
+    // Headers declare prototypes that are often defined in the same file;
+    // keep the definition when both are present.
+    let defined: Vec<String> = out
+        .symbols
+        .iter()
+        .filter(|s| s.kind == SymbolType::Function && !s.metadata.tags.iter().any(|t| t == "declaration"))
+        .map(|s| s.name.clone())
+        .collect();
+    out.symbols.retain(|s| {
+        !(s.metadata.tags.iter().any(|t| t == "declaration") && defined.contains(&s.name))
+    });
+
+    Ok(ParsedFile {
+        symbols: out.symbols,
+        imports: out.imports,
+        exports: vec![], // Linkage is not modelled; non-static symbols are effectively exported
+        design_tokens: vec![],
+        type_definitions: out.type_definitions,
+        constants: out.constants,
+        schemas: vec![],
+        language: language_name.to_string(),
+        syntax_errors: tree_diagnostics(tree, content),
+    })
+}
+
+#[derive(Default)]
+struct Collected {
+    symbols: Vec<Symbol>,
+    imports: Vec<Import>,
+    type_definitions: Vec<TypeDefinition>,
+    constants: Vec<Constant>,
+}
+
+struct CFamilyExtractor<'a> {
+    source: &'a str,
+    is_cpp: bool,
+}
+
+impl<'a> CFamilyExtractor<'a> {
+    /// Walk top-level items, descending into preprocessor conditionals,
+    /// `extern "C"` blocks, namespaces and templates
+    fn visit_items(&self, node: &Node, out: &mut Collected) -> Result<()> {
+        for_each_declaration(node, |child| {
+            match child.kind() {
+                "preproc_include" => {
+                    if let Some(import) = self.extract_include(child)? {
+                        out.imports.push(import);
+                    }
+                }
+                "preproc_def" => {
+                    if let Some((symbol, constant)) = self.extract_macro(child)? {
+                        out.symbols.push(symbol);
+                        out.constants.push(constant);
+                    }
+                }
+                "preproc_function_def" => out.symbols.push(self.extract_function_macro(child)?),
+                "preproc_ifdef" | "preproc_if" | "preproc_else" | "preproc_elif" | "linkage_specification"
+                | "declaration_list" | "template_declaration" => self.visit_items(child, out)?,
+                "namespace_definition" => {
+                    let mut inner = Collected::default();
+                    if let Some(body) = child.child_by_field_name("body") {
+                        self.visit_items(&body, &mut inner)?;
+                    }
+
+                    out.imports.append(&mut inner.imports);
+                    out.type_definitions.append(&mut inner.type_definitions);
+                    out.constants.append(&mut inner.constants);
+                    out.symbols.push(Symbol {
+                        name: self
+                            .get_child_text(child, "name", self.source)
+                            .unwrap_or_else(|| "(anonymous)".to_string()),
+                        kind: SymbolType::Namespace,
+                        range: self.get_range(child),
+                        content: self.text(child)?,
+                        metadata: self.extract_metadata(child)?,
+                        children: inner.symbols,
+                        references: vec![],
+                    });
+                }
+                "function_definition" => {
+                    out.symbols.push(self.extract_function(child, SymbolType::Function)?)
+                }
+                "declaration" => self.extract_declaration(child, out)?,
+                "type_definition" => self.extract_typedef(child, out)?,
+                "struct_specifier" | "union_specifier" | "enum_specifier" | "class_specifier" => {
+                    self.extract_record(child, None, out)?
+                }
+                _ => {}
+            }
+            Ok(())
+        });
+
+        Ok(())
+    }
+
+    fn extract_include(&self, node: &Node) -> Result<Option<Import>> {
+        let Some(path) = node.child_by_field_name("path") else {
+            return Ok(None);
+        };
+        let path = self.text(&path)?;
+
+        Ok(Some(Import {
+            source: path.trim_matches(|c| c == '"' || c == '<' || c == '>').to_string(),
+            names: vec![],
+            range: self.get_range(node),
+        }))
+    }
+
+    /// `#define NAME value` becomes a constant; bare guards like `#define FOO_H` are skipped
+    fn extract_macro(&self, node: &Node) -> Result<Option<(Symbol, Constant)>> {
+        let Some(name) = self.get_child_text(node, "name", self.source) else {
+            return Ok(None);
+        };
+        let Some(value) = self.get_child_text(node, "value", self.source) else {
+            return Ok(None);
+        };
+        let value = value.trim().to_string();
+
+        let mut metadata = self.extract_metadata(node)?;
+        metadata.tags.push("macro".to_string());
+
+        let symbol = Symbol {
+            name: name.clone(),
+            kind: SymbolType::Constant,
+            range: self.get_range(node),
+            content: self.text(node)?,
+            metadata,
+            children: vec![],
+            references: vec![],
+        };
+        let constant = Constant {
+            name,
+            value,
+            type_annotation: None,
+            category: ConstantCategory::Config,
+            range: self.get_range(node),
+        };
+
+        Ok(Some((symbol, constant)))
+    }
+
+    fn extract_function_macro(&self, node: &Node) -> Result<Symbol> {
+        let mut metadata = self.extract_metadata(node)?;
+        metadata.tags.push("macro".to_string());
+
+        if let Some(params) = node.child_by_field_name("parameters") {
+            let mut cursor = params.walk();
+            for param in params.children(&mut cursor) {
+                if param.kind() == "identifier" {
+                    metadata.parameters.push(Parameter {
+                        name: self.text(&param)?,
+                        type_annotation: None,
+                        default_value: None,
+                        is_optional: false,
+                    });
+                }
+            }
+        }
+
+        Ok(Symbol {
+            name: self
+                .get_child_text(node, "name", self.source)
+                .unwrap_or_else(|| "MACRO".to_string()),
+            kind: SymbolType::Function,
+            range: self.get_range(node),
+            content: self.text(node)?,
+            metadata,
+            children: vec![],
+            references: vec![],
+        })
+    }
+
+    fn extract_function(&self, node: &Node, kind: SymbolType) -> Result<Symbol> {
+        let declarator = node.child_by_field_name("declarator");
+        let name = declarator
+            .and_then(|d| self.declarator_name(&d))
+            .unwrap_or_else(|| "anonymous".to_string());
+
+        let mut metadata = self.extract_metadata(node)?;
+        metadata.return_type = self.get_child_text(node, "type", self.source);
+        if let Some(params) = declarator
+            .and_then(|d| self.function_declarator(d))
+            .and_then(|f| f.child_by_field_name("parameters"))
+        {
+            metadata.parameters = self.extract_parameters(&params)?;
+        }
+
+        // Constructors and destructors have no return type
+        let kind = if kind == SymbolType::Method && node.child_by_field_name("type").is_none() {
+            SymbolType::Constructor
+        } else {
+            kind
+        };
+
+        Ok(Symbol {
+            name,
+            kind,
+            range: self.get_range(node),
+            content: self.text(node)?,
+            metadata,
+            children: vec![],
+            references: vec![],
+        })
+    }
+
+    /// Prototypes, `const` globals and records declared alongside variables
+    fn extract_declaration(&self, node: &Node, out: &mut Collected) -> Result<()> {
+        if let Some(type_node) = node.child_by_field_name("type") {
+            if type_node.child_by_field_name("body").is_some() {
+                self.extract_record(&type_node, None, out)?;
+            }
+        }
+
+        let is_const = self.has_child_text(node, "type_qualifier", "const");
+        let mut cursor = node.walk();
+        for declarator in node.children_by_field_name("declarator", &mut cursor) {
+            if self.function_declarator(declarator).is_some() {
+                let mut symbol = self.extract_function(node, SymbolType::Function)?;
+                symbol.name = self
+                    .declarator_name(&declarator)
+                    .unwrap_or(symbol.name);
+                symbol.metadata.tags.push("declaration".to_string());
+                out.symbols.push(symbol);
+            } else if is_const && declarator.kind() == "init_declarator" {
+                let name = self
+                    .declarator_name(&declarator)
+                    .unwrap_or_else(|| "CONST".to_string());
+                out.constants.push(Constant {
+                    name,
+                    value: self
+                        .get_child_text(&declarator, "value", self.source)
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    type_annotation: self.get_child_text(node, "type", self.source),
+                    category: ConstantCategory::Config,
+                    range: self.get_range(node),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `typedef` becomes a type alias; `typedef struct { ... } Name;` also yields the record
+    fn extract_typedef(&self, node: &Node, out: &mut Collected) -> Result<()> {
+        let mut cursor = node.walk();
+        let alias = node
+            .children_by_field_name("declarator", &mut cursor)
+            .filter_map(|d| self.declarator_name(&d))
+            .next();
+        let Some(alias) = alias else {
+            return Ok(());
+        };
+
+        if let Some(type_node) = node.child_by_field_name("type") {
+            if type_node.child_by_field_name("body").is_some() {
+                self.extract_record(&type_node, Some(&alias), out)?;
+                if type_node.child_by_field_name("name").is_none() {
+                    return Ok(());
+                }
+            }
+        }
+
+        out.type_definitions.push(TypeDefinition {
+            name: alias,
+            kind: TypeKind::TypeAlias,
+            definition: self.text(node)?,
+            properties: vec![],
+            generic_params: vec![],
+            range: self.get_range(node),
+        });
+
+        Ok(())
+    }
+
+    /// struct / union / enum / class with a body
+    fn extract_record(&self, node: &Node, typedef_name: Option<&str>, out: &mut Collected) -> Result<()> {
+        let Some(body) = node.child_by_field_name("body") else {
+            return Ok(());
+        };
+        let name = self
+            .get_child_text(node, "name", self.source)
+            .or_else(|| typedef_name.map(|n| n.to_string()))
+            .unwrap_or_else(|| "(anonymous)".to_string());
+
+        let mut metadata = self.extract_metadata(node)?;
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            if child.kind() == "base_class_clause" {
+                let mut base_cursor = child.walk();
+                for base in child.children(&mut base_cursor) {
+                    if matches!(base.kind(), "type_identifier" | "qualified_identifier" | "template_type") {
+                        metadata.extends.push(self.text(&base)?);
+                    }
+                }
+            }
+        }
+
+        let (kind, type_kind, children) = match node.kind() {
+            "enum_specifier" => (SymbolType::Enum, TypeKind::Enum, self.extract_enumerators(&body)?),
+            "class_specifier" => (SymbolType::Class, TypeKind::Interface, self.extract_members(&body, "private")?),
+            _ => (SymbolType::Struct, TypeKind::Interface, self.extract_members(&body, "public")?),
+        };
+
+        let properties = children
+            .iter()
+            .filter(|c| matches!(c.kind, SymbolType::Field | SymbolType::EnumMember))
+            .map(|c| TypeProperty {
+                name: c.name.clone(),
+                type_annotation: c.metadata.return_type.clone().unwrap_or_default(),
+                is_optional: false,
+                description: c.metadata.documentation.clone(),
+            })
+            .collect();
+
+        out.type_definitions.push(TypeDefinition {
+            name: name.clone(),
+            kind: type_kind,
+            definition: self.text(node)?,
+            properties,
+            generic_params: vec![],
+            range: self.get_range(node),
+        });
+        out.symbols.push(Symbol {
+            name,
+            kind,
+            range: self.get_range(node),
+            content: self.text(node)?,
+            metadata,
+            children,
+            references: vec![],
+        });
+
+        Ok(())
+    }
+
+    /// Fields and (C++) methods of a struct/class body, tracking `public:`/`private:` sections
+    fn extract_members(&self, body: &Node, default_access: &str) -> Result<Vec<Symbol>> {
+        let mut members = Vec::new();
+        let mut access = default_access.to_string();
+        let mut cursor = body.walk();
+
+        for child in body.children(&mut cursor) {
+            match child.kind() {
+                "access_specifier" => {
+                    access = self.text(&child)?.trim_end_matches(':').trim().to_string();
+                }
+                "function_definition" => {
+                    let mut method = self.extract_function(&child, SymbolType::Method)?;
+                    method.metadata.access_modifier = Some(access.clone());
+                    members.push(method);
+                }
+                "template_declaration" => {
+                    let mut inner_cursor = child.walk();
+                    for item in child.children(&mut inner_cursor) {
+                        if item.kind() == "function_definition" {
+                            let mut method = self.extract_function(&item, SymbolType::Method)?;
+                            method.metadata.access_modifier = Some(access.clone());
+                            members.push(method);
+                        }
+                    }
+                }
+                "field_declaration" | "declaration" => {
+                    let field_type = self.get_child_text(&child, "type", self.source);
+                    let mut metadata = self.extract_metadata(&child)?;
+                    metadata.access_modifier = Some(access.clone());
+
+                    let mut decl_cursor = child.walk();
+                    for declarator in child.children_by_field_name("declarator", &mut decl_cursor) {
+                        let is_method = self.function_declarator(declarator).is_some();
+                        let mut member = if is_method {
+                            let mut method = self.extract_function(&child, SymbolType::Method)?;
+                            method.metadata.access_modifier = Some(access.clone());
+                            method
+                        } else {
+                            let mut metadata = metadata.clone();
+                            metadata.return_type = field_type.clone();
+                            Symbol {
+                                name: String::new(),
+                                kind: SymbolType::Field,
+                                range: self.get_range(&child),
+                                content: self.text(&child)?,
+                                metadata,
+                                children: vec![],
+                                references: vec![],
+                            }
+                        };
+                        member.name = self
+                            .declarator_name(&declarator)
+                            .unwrap_or_else(|| "field".to_string());
+                        members.push(member);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(members)
+    }
+
+    fn extract_enumerators(&self, body: &Node) -> Result<Vec<Symbol>> {
+        let mut members = Vec::new();
+        let mut cursor = body.walk();
+
+        for child in body.children(&mut cursor) {
+            if child.kind() == "enumerator" {
+                members.push(Symbol {
+                    name: self
+                        .get_child_text(&child, "name", self.source)
+                        .unwrap_or_else(|| "VALUE".to_string()),
+                    kind: SymbolType::EnumMember,
+                    range: self.get_range(&child),
+                    content: self.text(&child)?,
+                    metadata: SymbolMetadata::default(),
+                    children: vec![],
+                    references: vec![],
+                });
+            }
+        }
+
+        Ok(members)
+    }
+
+    fn extract_parameters(&self, node: &Node) -> Result<Vec<Parameter>> {
+        let mut params = Vec::new();
+        let mut cursor = node.walk();
+
+        for child in node.children(&mut cursor) {
+            match child.kind() {
+                "parameter_declaration" | "optional_parameter_declaration" => {
+                    let declarator = child.child_by_field_name("declarator");
+                    let name = declarator
+                        .and_then(|d| self.declarator_name(&d))
+                        .unwrap_or_default();
+
+                    // Keep pointer/reference markers with the type: `char *name` -> `char*`
+                    let markers: String = declarator
+                        .map(|d| self.text(&d).unwrap_or_default())
+                        .unwrap_or_default()
+                        .chars()
+                        .take_while(|c| matches!(c, '*' | '&' | ' '))
+                        .filter(|c| *c != ' ')
+                        .collect();
+                    let type_annotation = self
+                        .get_child_text(&child, "type", self.source)
+                        .map(|t| format!("{}{}", t, markers));
+
+                    params.push(Parameter {
+                        name,
+                        type_annotation,
+                        default_value: self.get_child_text(&child, "default_value", self.source),
+                        is_optional: child.kind() == "optional_parameter_declaration",
+                    });
+                }
+                "variadic_parameter" | "variadic_parameter_declaration" => params.push(Parameter {
+                    name: "...".to_string(),
+                    type_annotation: None,
+                    default_value: None,
+                    is_optional: true,
+                }),
+                _ => {}
+            }
+        }
+
+        Ok(params)
+    }
+
+    /// `static`, doc comment and, for C++, `virtual`
+    fn extract_metadata(&self, node: &Node) -> Result<SymbolMetadata> {
+        let mut metadata = SymbolMetadata {
+            is_static: self.has_child_text(node, "storage_class_specifier", "static"),
+            is_readonly: self.has_child_text(node, "type_qualifier", "const"),
+            ..Default::default()
+        };
+        if self.is_cpp && self.text(node)?.trim_start().starts_with("virtual") {
+            metadata.tags.push("virtual".to_string());
+        }
+
+        if let Some(prev) = node.prev_sibling() {
+            if prev.kind() == "comment" {
+                let text = self.text(&prev)?;
+                if text.starts_with("/**") || text.starts_with("///") || text.starts_with("//!") {
+                    metadata.documentation = Some(clean_doc_comment(&text));
+                }
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    /// Name at the bottom of a declarator chain (`*foo`, `foo(int)`, `Foo::bar`, `arr[3]`)
+    fn declarator_name(&self, node: &Node) -> Option<String> {
+        match node.kind() {
+            "identifier" | "field_identifier" | "type_identifier" | "qualified_identifier"
+            | "destructor_name" | "operator_name" => self.text(node).ok(),
+            _ => node
+                .child_by_field_name("declarator")
+                .and_then(|d| self.declarator_name(&d)),
+        }
+    }
+
+    fn function_declarator<'t>(&self, node: Node<'t>) -> Option<Node<'t>> {
+        if node.kind() == "function_declarator" {
+            return Some(node);
+        }
+        node.child_by_field_name("declarator")
+            .and_then(|d| self.function_declarator(d))
+    }
+
+    fn has_child_text(&self, node: &Node, kind: &str, text: &str) -> bool {
+        let mut cursor = node.walk();
+        let found = node
+            .children(&mut cursor)
+            .any(|c| c.kind() == kind && self.text(&c).map(|t| t == text).unwrap_or(false));
+        found
+    }
+
+    fn text(&self, node: &Node) -> Result<String> {
+        Ok(node.utf8_text(self.source.as_bytes())?.to_string())
+    }
+
+    fn get_child_text(&self, node: &Node, field: &str, source: &str) -> Option<String> {
+        node.child_by_field_name(field)
+            .map(|n| n.utf8_text(source.as_bytes()).unwrap().to_string())
+    }
+
+    fn get_range(&self, node: &Node) -> Range {
+        Range {
+            start_line: node.start_position().row + 1,
+            end_line: node.end_position().row + 1,
+            start_byte: node.start_byte(),
+            end_byte: node.end_byte(),
+        }
+    }
+}
+
+fn clean_doc_comment(comment: &str) -> String {
+    comment
+        .trim_start_matches("/**")
+        .trim_end_matches("*/")
+        .lines()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches("///")
+                .trim_start_matches("//!")
+                .trim_start_matches('*')
+                .trim()
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_c_header() {
+        let code = r#"
+#ifndef BUFFER_H
+#define BUFFER_H
+
+#include <stdlib.h>
+#include "config.h"
+
+#define BUFFER_MAX 4096
+#define MIN(a, b) ((a) < (b) ? (a) : (b))
+
+typedef struct {
+    char *data;
+    size_t len;
+} Buffer;
+
+/** Allocate an empty buffer. */
+Buffer *buffer_new(size_t capacity);
+
+static int buffer_len(const Buffer *buf) {
+    return buf->len;
+}
+
+#endif
+"#;
+
+        let parsed = CParser::new().parse(code).unwrap();
+        assert_eq!(parsed.language, "c");
+
+        let includes: Vec<&str> = parsed.imports.iter().map(|i| i.source.as_str()).collect();
+        assert_eq!(includes, vec!["stdlib.h", "config.h"]);
+
+        assert_eq!(parsed.constants.len(), 1);
+        assert_eq!(parsed.constants[0].name, "BUFFER_MAX");
+        assert_eq!(parsed.constants[0].value, "4096");
+
+        let buffer = parsed.symbols.iter().find(|s| s.name == "Buffer").unwrap();
+        assert_eq!(buffer.kind, SymbolType::Struct);
+        assert_eq!(buffer.children.len(), 2);
+
+        let proto = parsed.symbols.iter().find(|s| s.name == "buffer_new").unwrap();
+        assert_eq!(proto.metadata.documentation.as_deref(), Some("Allocate an empty buffer."));
+
+        let len = parsed.symbols.iter().find(|s| s.name == "buffer_len").unwrap();
+        assert!(len.metadata.is_static);
+        assert_eq!(len.metadata.parameters[0].name, "buf");
+        assert_eq!(len.metadata.parameters[0].type_annotation.as_deref(), Some("Buffer*"));
+
+        assert!(parsed.symbols.iter().any(|s| s.name == "MIN" && s.kind == SymbolType::Function));
+    }
+
+    #[test]
+    fn test_broken_file_keeps_symbols() {
+        let code = "int good(void) { return 1; }\n\nint broken( {\n\nint after(void) { return 2; }\n";
+
+        let parsed = CParser::new().parse(code).unwrap();
+        assert!(parsed.symbols.iter().any(|s| s.name == "good"));
+        assert!(!parsed.syntax_errors.is_empty());
+        assert!(parsed.syntax_errors.iter().all(|d| d.line >= 3));
+    }
+}
@@ -0,0 +1,70 @@
+use crate::c::{extract_c_family, parse_c_family};
+use crate::types::*;
+use anyhow::Result;
+use tree_sitter::Tree;
+
+pub struct CppParser;
+
+impl CppParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn parse(&self, content: &str) -> Result<ParsedFile> {
+        parse_c_family(tree_sitter_cpp::language(), "cpp", content)
+    }
+
+    /// Extract from an already-parsed tree; lets callers reuse an incrementally reparsed tree
+    pub fn parse_tree(&self, tree: &Tree, content: &str) -> Result<ParsedFile> {
+        extract_c_family(tree, "cpp", content)
+    }
+}
+
+impl Default for CppParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpp_class_in_namespace() {
+        let code = r#"
+#include <vector>
+
+namespace storage {
+
+class Cache : public Base {
+public:
+    Cache(int capacity);
+    virtual int get(const std::string& key) const;
+
+private:
+    int capacity_;
+};
+
+}
+"#;
+
+        let parsed = CppParser::new().parse(code).unwrap();
+        assert_eq!(parsed.language, "cpp");
+        assert_eq!(parsed.imports[0].source, "vector");
+
+        let namespace = &parsed.symbols[0];
+        assert_eq!(namespace.kind, SymbolType::Namespace);
+        assert_eq!(namespace.name, "storage");
+
+        let class = &namespace.children[0];
+        assert_eq!(class.kind, SymbolType::Class);
+        assert_eq!(class.metadata.extends, vec!["Base"]);
+
+        assert!(class.children.iter().any(|c| c.kind == SymbolType::Constructor));
+        let get = class.children.iter().find(|c| c.name == "get").unwrap();
+        assert_eq!(get.metadata.access_modifier.as_deref(), Some("public"));
+        let field = class.children.iter().find(|c| c.name == "capacity_").unwrap();
+        assert_eq!(field.metadata.access_modifier.as_deref(), Some("private"));
+    }
+}
@@ -0,0 +1,627 @@
+use crate::types::*;
+use anyhow::Result;
+use regex::Regex;
+
+/// Extracts database schemas (tables, columns, relations) from SQL migrations,
+/// Prisma schema files and Diesel `table!` definitions.
+pub struct DatabaseSchemaParser {
+    create_table: Regex,
+    alter_add_column: Regex,
+    prisma_model: Regex,
+}
+
+impl DatabaseSchemaParser {
+    pub fn new() -> Self {
+        Self {
+            create_table: Regex::new(
+                r#"(?i)\bCREATE\s+(?:TEMP(?:ORARY)?\s+)?TABLE\s+(?:IF\s+NOT\s+EXISTS\s+)?([`"\[\]\w.]+)\s*\("#,
+            )
+            .unwrap(),
+            alter_add_column: Regex::new(
+                r#"(?i)\bALTER\s+TABLE\s+(?:ONLY\s+)?([`"\[\]\w.]+)\s+ADD\s+(?:COLUMN\s+)?(?:IF\s+NOT\s+EXISTS\s+)?([^;]+);"#,
+            )
+            .unwrap(),
+            prisma_model: Regex::new(r"(?m)^\s*model\s+(\w+)\s*\{").unwrap(),
+        }
+    }
+
+    /// Parse a `.sql` file: `CREATE TABLE` statements plus `ALTER TABLE ... ADD COLUMN`
+    pub fn parse_sql(&self, content: &str) -> Result<ParsedFile> {
+        let cleaned = strip_sql_comments(content);
+        let mut schemas: Vec<ValidationSchema> = Vec::new();
+
+        for caps in self.create_table.captures_iter(&cleaned) {
+            let whole = caps.get(0).unwrap();
+            let open = whole.end() - 1;
+            let Some(close) = matching_paren(&cleaned, open) else {
+                continue;
+            };
+            let end = cleaned[close..].find(';').map(|i| close + i + 1).unwrap_or(close + 1);
+
+            let mut fields = Vec::new();
+            let mut constraints = Vec::new();
+            for item in split_top_level(&cleaned[open + 1..close]) {
+                if is_table_constraint(&item) {
+                    constraints.push(item);
+                } else if let Some(field) = parse_sql_column(&item) {
+                    fields.push(field);
+                }
+            }
+            for constraint in constraints {
+                apply_table_constraint(&constraint, &mut fields);
+            }
+
+            schemas.push(ValidationSchema {
+                name: unquote_identifier(&caps[1]),
+                schema_type: SchemaType::Sql,
+                definition: content[whole.start()..end].trim().to_string(),
+                fields,
+                range: range_for(content, whole.start(), end),
+            });
+        }
+
+        for caps in self.alter_add_column.captures_iter(&cleaned) {
+            let whole = caps.get(0).unwrap();
+            let table = unquote_identifier(&caps[1]);
+            if is_table_constraint(&caps[2]) {
+                continue;
+            }
+            let Some(field) = parse_sql_column(caps[2].trim()) else {
+                continue;
+            };
+
+            // Fold into the CREATE TABLE from the same migration when there is one
+            if let Some(existing) = schemas.iter_mut().find(|s| s.name == table) {
+                existing.fields.push(field);
+                continue;
+            }
+            schemas.push(ValidationSchema {
+                name: table,
+                schema_type: SchemaType::Sql,
+                definition: content[whole.start()..whole.end()].trim().to_string(),
+                fields: vec![field],
+                range: range_for(content, whole.start(), whole.end()),
+            });
+        }
+
+        Ok(schema_file(schemas, "sql"))
+    }
+
+    /// Parse a `schema.prisma` file; each `model` block becomes a schema
+    pub fn parse_prisma(&self, content: &str) -> Result<ParsedFile> {
+        let models: Vec<String> = self
+            .prisma_model
+            .captures_iter(content)
+            .map(|c| c[1].to_string())
+            .collect();
+        let mut schemas = Vec::new();
+
+        for caps in self.prisma_model.captures_iter(content) {
+            let whole = caps.get(0).unwrap();
+            let open = whole.end() - 1;
+            let Some(close) = content[open..].find('}').map(|i| open + i) else {
+                continue;
+            };
+
+            let mut fields: Vec<SchemaField> = Vec::new();
+            let mut foreign_keys: Vec<(String, String)> = Vec::new();
+
+            for line in content[open + 1..close].lines() {
+                let line = line.split("//").next().unwrap_or("").trim();
+                if line.is_empty() || line.starts_with("@@") {
+                    continue;
+                }
+
+                let mut parts = line.split_whitespace();
+                let (Some(name), Some(field_type)) = (parts.next(), parts.next()) else {
+                    continue;
+                };
+                let attributes: Vec<String> = prisma_attributes(line);
+
+                let is_optional = field_type.ends_with('?');
+                let is_list = field_type.ends_with("[]");
+                let base_type = field_type.trim_end_matches('?').trim_end_matches("[]");
+
+                let relation = attributes.iter().find(|a| a.starts_with("@relation"));
+                let mut references = None;
+                if let Some(relation) = relation {
+                    let target_fields = bracket_list(relation, "references");
+                    let target = format!("{}.{}", base_type, target_fields.first().cloned().unwrap_or_else(|| "id".to_string()));
+                    for local in bracket_list(relation, "fields") {
+                        foreign_keys.push((local, target.clone()));
+                    }
+                    references = Some(target);
+                } else if models.iter().any(|m| m == base_type) {
+                    references = Some(base_type.to_string());
+                }
+
+                fields.push(SchemaField {
+                    name: name.to_string(),
+                    validation_rules: attributes.clone(),
+                    is_required: !is_optional && !is_list,
+                    default_value: attributes
+                        .iter()
+                        .find_map(|a| a.strip_prefix("@default(").and_then(|d| d.strip_suffix(')')).map(str::to_string)),
+                    type_annotation: Some(field_type.to_string()),
+                    is_optional,
+                    validators: vec![],
+                    description: None,
+                    references,
+                });
+            }
+
+            for (local, target) in foreign_keys {
+                if let Some(field) = fields.iter_mut().find(|f| f.name == local) {
+                    field.references = Some(target);
+                }
+            }
+
+            schemas.push(ValidationSchema {
+                name: caps[1].to_string(),
+                schema_type: SchemaType::Prisma,
+                definition: content[whole.start()..=close].to_string(),
+                fields,
+                range: range_for(content, whole.start(), close + 1),
+            });
+        }
+
+        Ok(schema_file(schemas, "prisma"))
+    }
+}
+
+impl Default for DatabaseSchemaParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Diesel `table! { users (id) { id -> Int4, ... } }` blocks, with `joinable!` relations
+pub fn extract_diesel_tables(content: &str) -> Vec<ValidationSchema> {
+    let table_macro = Regex::new(r"\btable!\s*\{").unwrap();
+    let header = Regex::new(r"(?s)^\s*(?:#\[[^\]]*\]\s*)*(?:\w+\.)?(\w+)\s*\(([^)]*)\)\s*\{").unwrap();
+    let column = Regex::new(r"(?m)^\s*(?:#\[[^\]]*\]\s*)*(\w+)\s*->\s*([^,\n]+),?").unwrap();
+    let joinable = Regex::new(r"\bjoinable!\s*\(\s*(\w+)\s*->\s*(\w+)\s*\(\s*(\w+)\s*\)\s*\)").unwrap();
+
+    let mut schemas: Vec<ValidationSchema> = Vec::new();
+    let mut primary_keys: Vec<(String, String)> = Vec::new();
+
+    for m in table_macro.find_iter(content) {
+        let open = m.end() - 1;
+        let Some(close) = matching_brace(content, open) else {
+            continue;
+        };
+        let body = &content[open + 1..close];
+        let Some(caps) = header.captures(body) else {
+            continue;
+        };
+
+        let name = caps[1].to_string();
+        let keys: Vec<String> = caps[2].split(',').map(|k| k.trim().to_string()).filter(|k| !k.is_empty()).collect();
+        let columns_body = &body[caps.get(0).unwrap().end()..];
+
+        let fields = column
+            .captures_iter(columns_body)
+            .map(|c| {
+                let column_type = c[2].trim().to_string();
+                let is_optional = column_type.starts_with("Nullable<");
+                let mut validation_rules = vec![];
+                if keys.iter().any(|k| k == &c[1]) {
+                    validation_rules.push("primary key".to_string());
+                }
+                SchemaField {
+                    name: c[1].to_string(),
+                    validation_rules,
+                    is_required: !is_optional,
+                    default_value: None,
+                    type_annotation: Some(column_type),
+                    is_optional,
+                    validators: vec![],
+                    description: None,
+                    references: None,
+                }
+            })
+            .collect();
+
+        if let Some(pk) = keys.first() {
+            primary_keys.push((name.clone(), pk.clone()));
+        }
+        schemas.push(ValidationSchema {
+            name,
+            schema_type: SchemaType::Diesel,
+            definition: content[m.start()..=close].to_string(),
+            fields,
+            range: range_for(content, m.start(), close + 1),
+        });
+    }
+
+    for caps in joinable.captures_iter(content) {
+        let (child, parent, fk) = (&caps[1], &caps[2], &caps[3]);
+        let parent_key = primary_keys
+            .iter()
+            .find(|(t, _)| t == parent)
+            .map(|(_, k)| k.as_str())
+            .unwrap_or("id");
+        if let Some(field) = schemas
+            .iter_mut()
+            .find(|s| s.name == child)
+            .and_then(|s| s.fields.iter_mut().find(|f| f.name == fk))
+        {
+            field.references = Some(format!("{}.{}", parent, parent_key));
+        }
+    }
+
+    schemas
+}
+
+fn schema_file(schemas: Vec<ValidationSchema>, language: &str) -> ParsedFile {
+    ParsedFile {
+        symbols: vec![],
+        imports: vec![],
+        exports: vec![],
+        design_tokens: vec![],
+        type_definitions: vec![],
+        constants: vec![],
+        schemas,
+        language: language.to_string(),
+        syntax_errors: vec![],
+    }
+}
+
+/// Blank out `--` and `/* */` comments, keeping byte offsets stable
+fn strip_sql_comments(sql: &str) -> String {
+    let comments = Regex::new(r"(?s)--[^\n]*|/\*.*?\*/").unwrap();
+    comments
+        .replace_all(sql, |c: &regex::Captures| {
+            c[0].chars()
+                .map(|ch| if ch == '\n' { "\n".to_string() } else { " ".repeat(ch.len_utf8()) })
+                .collect::<String>()
+        })
+        .into_owned()
+}
+
+fn is_table_constraint(item: &str) -> bool {
+    let upper = item.trim_start().to_ascii_uppercase();
+    [
+        "CONSTRAINT ", "PRIMARY KEY", "FOREIGN KEY", "UNIQUE ", "UNIQUE(", "CHECK ", "CHECK(", "INDEX ", "KEY ", "EXCLUDE ",
+    ]
+        .iter()
+        .any(|kw| upper.starts_with(kw))
+}
+
+/// `email VARCHAR(255) NOT NULL UNIQUE DEFAULT '' REFERENCES users(id)`
+///
+/// Keywords are matched on an ASCII-uppercased copy so its offsets line up with `rest`
+fn parse_sql_column(item: &str) -> Option<SchemaField> {
+    let item = item.trim();
+    let (name, rest) = item.split_once(char::is_whitespace)?;
+    let upper = rest.to_ascii_uppercase();
+
+    // Type runs until the first constraint keyword
+    let type_end = [" NOT ", " NULL", " PRIMARY", " UNIQUE", " DEFAULT", " REFERENCES", " CHECK", " GENERATED", " COLLATE", " CONSTRAINT"]
+        .iter()
+        .filter_map(|kw| format!(" {}", upper).find(kw))
+        .min()
+        .unwrap_or(rest.len());
+    let column_type = rest[..type_end.min(rest.len())].trim().to_string();
+
+    let mut validation_rules = Vec::new();
+    if upper.contains("PRIMARY KEY") {
+        validation_rules.push("primary key".to_string());
+    }
+    if upper.contains("UNIQUE") {
+        validation_rules.push("unique".to_string());
+    }
+    if let Some(check) = upper.find("CHECK") {
+        validation_rules.push(rest[check..].trim().to_string());
+    }
+
+    let default_value = upper.find("DEFAULT").map(|i| {
+        let after = rest[i + "DEFAULT".len()..].trim_start();
+        match after.strip_prefix('\'').and_then(|s| s.find('\'')) {
+            Some(close) => after[..close + 2].to_string(),
+            None => after.split_whitespace().next().unwrap_or("").to_string(),
+        }
+    });
+    let references = upper.find("REFERENCES").map(|i| {
+        parse_reference(&rest[i + "REFERENCES".len()..])
+    });
+
+    let not_null = upper.contains("NOT NULL") || upper.contains("PRIMARY KEY");
+    Some(SchemaField {
+        name: unquote_identifier(name),
+        validation_rules,
+        is_required: not_null,
+        default_value,
+        type_annotation: Some(column_type),
+        is_optional: !not_null,
+        validators: vec![],
+        description: None,
+        references,
+    })
+}
+
+/// `FOREIGN KEY (user_id) REFERENCES users(id)` / `PRIMARY KEY (a, b)`
+fn apply_table_constraint(constraint: &str, fields: &mut [SchemaField]) {
+    let upper = constraint.to_ascii_uppercase();
+
+    let columns_after = |keyword: &str| -> Vec<String> {
+        upper
+            .find(keyword)
+            .and_then(|i| {
+                let rest = &constraint[i + keyword.len()..];
+                let open = rest.find('(')?;
+                let close = rest[open..].find(')')? + open;
+                Some(rest[open + 1..close].split(',').map(unquote_identifier).collect())
+            })
+            .unwrap_or_default()
+    };
+
+    if upper.contains("FOREIGN KEY") {
+        if let Some(i) = upper.find("REFERENCES") {
+            let target = parse_reference(&constraint[i + "REFERENCES".len()..]);
+            for column in columns_after("FOREIGN KEY") {
+                if let Some(field) = fields.iter_mut().find(|f| f.name == column) {
+                    field.references = Some(target.clone());
+                }
+            }
+        }
+    } else if upper.contains("PRIMARY KEY") {
+        for column in columns_after("PRIMARY KEY") {
+            if let Some(field) = fields.iter_mut().find(|f| f.name == column) {
+                field.validation_rules.push("primary key".to_string());
+                field.is_required = true;
+                field.is_optional = false;
+            }
+        }
+    }
+}
+
+/// ` users (id) ON DELETE CASCADE` -> `users.id`
+fn parse_reference(text: &str) -> String {
+    let text = text.trim_start();
+    let table_end = text.find(|c: char| c == '(' || c.is_whitespace()).unwrap_or(text.len());
+    let table = unquote_identifier(&text[..table_end]);
+
+    let column = text[table_end..]
+        .trim_start()
+        .strip_prefix('(')
+        .and_then(|r| r.split(')').next())
+        .map(|c| unquote_identifier(c.split(',').next().unwrap_or(c)));
+
+    match column {
+        Some(column) => format!("{}.{}", table, column),
+        None => table,
+    }
+}
+
+fn unquote_identifier(identifier: &str) -> String {
+    identifier
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, '`' | '"' | '[' | ']'))
+        .collect()
+}
+
+/// Split on commas that are not nested inside parentheses or quotes
+fn split_top_level(body: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut current = String::new();
+
+    for c in body.chars() {
+        match c {
+            '\'' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                if !current.trim().is_empty() {
+                    items.push(current.trim().to_string());
+                }
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        items.push(current.trim().to_string());
+    }
+    items
+}
+
+/// `@id @default(autoincrement()) @relation(fields: [a], references: [b])`
+fn prisma_attributes(line: &str) -> Vec<String> {
+    let mut attributes = Vec::new();
+    let mut rest = line;
+
+    while let Some(start) = rest.find('@') {
+        let attr = &rest[start..];
+        let name_end = attr[1..]
+            .find(|c: char| !(c.is_alphanumeric() || c == '.' || c == '_'))
+            .map(|i| i + 1)
+            .unwrap_or(attr.len());
+
+        let end = if attr[name_end..].starts_with('(') {
+            matching_paren(attr, name_end).map(|i| i + 1).unwrap_or(attr.len())
+        } else {
+            name_end
+        };
+        attributes.push(attr[..end].to_string());
+        rest = &attr[end..];
+    }
+
+    attributes
+}
+
+/// Values of `key: [a, b]` inside an attribute
+fn bracket_list(attribute: &str, key: &str) -> Vec<String> {
+    let Some(start) = attribute.find(&format!("{}:", key)) else {
+        return vec![];
+    };
+    let rest = &attribute[start..];
+    let (Some(open), Some(close)) = (rest.find('['), rest.find(']')) else {
+        return vec![];
+    };
+    rest[open + 1..close]
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn matching_paren(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn matching_brace(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn range_for(content: &str, start: usize, end: usize) -> Range {
+    Range {
+        start_line: content[..start].matches('\n').count() + 1,
+        end_line: content[..end].matches('\n').count() + 1,
+        start_byte: start,
+        end_byte: end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sql_migration() {
+        let sql = r#"
+-- users and their posts
+CREATE TABLE IF NOT EXISTS "users" (
+    id SERIAL PRIMARY KEY,
+    email VARCHAR(255) NOT NULL UNIQUE,
+    created_at TIMESTAMP DEFAULT now()
+);
+
+CREATE TABLE posts (
+    id BIGINT,
+    user_id INTEGER NOT NULL,
+    title TEXT,
+    PRIMARY KEY (id),
+    CONSTRAINT fk_user FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+ALTER TABLE posts ADD COLUMN published BOOLEAN NOT NULL DEFAULT false;
+"#;
+
+        let parsed = DatabaseSchemaParser::new().parse_sql(sql).unwrap();
+        assert_eq!(parsed.schemas.len(), 2);
+
+        let users = &parsed.schemas[0];
+        assert_eq!(users.name, "users");
+        assert_eq!(users.fields.len(), 3);
+        assert_eq!(users.fields[1].type_annotation.as_deref(), Some("VARCHAR(255)"));
+        assert!(users.fields[1].is_required);
+        assert!(users.fields[1].validation_rules.contains(&"unique".to_string()));
+        assert_eq!(users.fields[2].default_value.as_deref(), Some("now()"));
+
+        let posts = &parsed.schemas[1];
+        let user_id = posts.fields.iter().find(|f| f.name == "user_id").unwrap();
+        assert_eq!(user_id.references.as_deref(), Some("users.id"));
+        assert!(posts.fields[0].validation_rules.contains(&"primary key".to_string()));
+        assert!(posts.fields.iter().any(|f| f.name == "published"));
+    }
+
+    #[test]
+    fn test_sql_with_non_ascii_text() {
+        let sql = r#"
+-- Benutzer — größere Tabelle
+CREATE TABLE people (
+    name TEXT DEFAULT 'ɐɐɐɐɐɐɐɐ' CHECK (name <> 'é'),
+    nick TEXT DEFAULT 'ıı' CHECK (length(nick) > 0), /* ﬁ ß */
+    id INTEGER,
+    PRIMARY KEY (id)
+);
+"#;
+
+        let parsed = DatabaseSchemaParser::new().parse_sql(sql).unwrap();
+        let people = &parsed.schemas[0];
+        assert!(people.definition.starts_with("CREATE TABLE people"));
+        assert!(people.definition.ends_with(");"));
+
+        let name = &people.fields[0];
+        assert_eq!(name.type_annotation.as_deref(), Some("TEXT"));
+        assert_eq!(name.default_value.as_deref(), Some("'ɐɐɐɐɐɐɐɐ'"));
+        assert!(name.validation_rules.contains(&"CHECK (name <> 'é')".to_string()));
+
+        let nick = &people.fields[1];
+        assert_eq!(nick.default_value.as_deref(), Some("'ıı'"));
+        assert!(nick.validation_rules.contains(&"CHECK (length(nick) > 0)".to_string()));
+        assert!(people.fields[2].validation_rules.contains(&"primary key".to_string()));
+    }
+
+    #[test]
+    fn test_prisma_and_diesel_relations() {
+        let prisma = r#"
+model User {
+  id    Int    @id @default(autoincrement())
+  posts Post[]
+}
+
+model Post {
+  id       Int   @id
+  authorId Int
+  author   User  @relation(fields: [authorId], references: [id])
+  bio      String?
+}
+"#;
+        let parsed = DatabaseSchemaParser::new().parse_prisma(prisma).unwrap();
+        let post = &parsed.schemas[1];
+        assert_eq!(parsed.schemas[0].fields[0].default_value.as_deref(), Some("autoincrement()"));
+        assert_eq!(parsed.schemas[0].fields[1].references.as_deref(), Some("Post"));
+        assert_eq!(post.fields[1].references.as_deref(), Some("User.id"));
+        assert!(post.fields[3].is_optional);
+
+        let diesel = r#"
+diesel::table! {
+    posts (id) {
+        id -> Int4,
+        user_id -> Int4,
+        body -> Nullable<Text>,
+    }
+}
+
+joinable!(posts -> users (user_id));
+"#;
+        let tables = extract_diesel_tables(diesel);
+        assert_eq!(tables[0].name, "posts");
+        assert_eq!(tables[0].fields.len(), 3);
+        assert_eq!(tables[0].fields[1].references.as_deref(), Some("users.id"));
+        assert!(tables[0].fields[2].is_optional);
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use tree_sitter::{Language, Node, Parser, Tree};
+
+/// Cap per file so one mangled file can't flood the `index_errors` table
+const MAX_DIAGNOSTICS_PER_FILE: usize = 20;
+
+/// A syntax problem tree-sitter recovered from while parsing a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseDiagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// Collect the syntax errors in a source file. Returns nothing for extensions
+/// without a tree-sitter grammar or for files that parse cleanly.
+pub fn syntax_diagnostics(extension: &str, content: &str) -> Vec<ParseDiagnostic> {
+    let Some(language) = grammar_for(extension) else {
+        return vec![];
+    };
+
+    let mut parser = Parser::new();
+    if parser.set_language(language).is_err() {
+        return vec![];
+    }
+    let Some(tree) = parser.parse(content, None) else {
+        return vec![ParseDiagnostic {
+            line: 1,
+            column: 1,
+            message: "parser gave up on this file".to_string(),
+        }];
+    };
+
+    tree_diagnostics(&tree, content)
+}
+
+/// Collect the syntax errors tree-sitter recovered from in an already-built tree
+pub fn tree_diagnostics(tree: &Tree, content: &str) -> Vec<ParseDiagnostic> {
+    let root = tree.root_node();
+    let mut diagnostics = Vec::new();
+    if root.has_error() {
+        collect_errors(&root, content, &mut diagnostics);
+    }
+    diagnostics
+}
+
+/// Call `visit` on each child of `node`, including the declarations tree-sitter
+/// couldn't fit into the grammar and left inside ERROR nodes. A child that fails
+/// is skipped: one broken declaration shouldn't cost the rest of the file.
+pub(crate) fn for_each_declaration<'tree>(node: &Node<'tree>, mut visit: impl FnMut(&Node<'tree>) -> Result<()>) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        let candidates: Vec<Node> = if child.is_error() {
+            let mut inner = child.walk();
+            let recovered = child.children(&mut inner).collect();
+            recovered
+        } else {
+            vec![child]
+        };
+
+        for candidate in candidates {
+            if let Err(e) = visit(&candidate) {
+                debug!("Skipping unparseable {} node: {}", candidate.kind(), e);
+            }
+        }
+    }
+}
+
+pub(crate) fn grammar_for(extension: &str) -> Option<Language> {
+    match extension {
+        "ts" => Some(tree_sitter_typescript::language_typescript()),
+        "tsx" => Some(tree_sitter_typescript::language_tsx()),
+        "rs" => Some(tree_sitter_rust::language()),
+        "py" => Some(tree_sitter_python::language()),
+        "java" => Some(tree_sitter_java::language()),
+        "c" | "h" => Some(tree_sitter_c::language()),
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => Some(tree_sitter_cpp::language()),
+        _ => None,
+    }
+}
+
+fn collect_errors(node: &Node, source: &str, diagnostics: &mut Vec<ParseDiagnostic>) {
+    if diagnostics.len() >= MAX_DIAGNOSTICS_PER_FILE {
+        return;
+    }
+
+    let position = node.start_position();
+    if node.is_error() {
+        let snippet: String = source[node.byte_range()]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(40)
+            .collect();
+        diagnostics.push(ParseDiagnostic {
+            line: position.row + 1,
+            column: position.column + 1,
+            message: format!("syntax error near `{}`", snippet),
+        });
+        // Everything under an ERROR node is part of the same problem
+        return;
+    }
+    if node.is_missing() {
+        diagnostics.push(ParseDiagnostic {
+            line: position.row + 1,
+            column: position.column + 1,
+            message: format!("missing `{}`", node.kind()),
+        });
+        return;
+    }
+
+    if !node.has_error() {
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_errors(&child, source, diagnostics);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syntax_diagnostics() {
+        assert!(syntax_diagnostics("rs", "fn ok() {}\n").is_empty());
+        assert!(syntax_diagnostics("md", "fn broken( {").is_empty());
+
+        let broken = "fn ok() {}\n\nfn broken( {\n    let x = ;\n}\n";
+        let diagnostics = syntax_diagnostics("rs", broken);
+        assert!(!diagnostics.is_empty());
+        assert!(diagnostics.iter().all(|d| d.line >= 3));
+    }
+}
@@ -0,0 +1,429 @@
+use crate::manifest::line_range;
+use crate::types::*;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContainerConfigKind {
+    Dockerfile,
+    Compose,
+}
+
+/// Whether a file is a Dockerfile: `Dockerfile`, `Dockerfile.prod`, `api.dockerfile`, `Containerfile`
+pub fn is_dockerfile(file_name: &str) -> bool {
+    file_name == "Dockerfile"
+        || file_name == "Containerfile"
+        || file_name.starts_with("Dockerfile.")
+        || file_name.ends_with(".dockerfile")
+}
+
+/// Whether a file is a Compose file: `docker-compose.yml`, `compose.yaml`, `docker-compose.prod.yml`
+pub fn is_compose_file(file_name: &str) -> bool {
+    let Some(stem) = file_name.strip_suffix(".yml").or_else(|| file_name.strip_suffix(".yaml")) else {
+        return false;
+    };
+    ["docker-compose", "compose"].iter().any(|base| stem == *base || stem.starts_with(&format!("{}.", base)))
+}
+
+/// Whether a file is a Dockerfile or Compose file
+pub fn is_container_config(file_name: &str) -> bool {
+    is_dockerfile(file_name) || is_compose_file(file_name)
+}
+
+/// One container: a Compose service, or a Dockerfile build stage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContainerService {
+    /// Service name, or the stage's `AS` name (its index when unnamed)
+    pub name: String,
+    pub image: Option<String>,
+    /// Build context of a Compose service
+    pub build: Option<String>,
+    /// `8080:80` mappings in Compose, `EXPOSE`d ports in a Dockerfile
+    pub ports: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub depends_on: Vec<String>,
+    pub command: Option<String>,
+}
+
+/// Services, images, ports and environment declared by a Dockerfile or Compose file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerConfig {
+    pub kind: ContainerConfigKind,
+    pub services: Vec<ContainerService>,
+    /// Dockerfile `ARG`s and their defaults
+    pub build_args: BTreeMap<String, String>,
+}
+
+impl ContainerConfig {
+    /// Parse a container config; `file_name` selects the format
+    pub fn parse(file_name: &str, content: &str) -> Result<Self> {
+        if is_dockerfile(file_name) {
+            Ok(Self::from_dockerfile(content))
+        } else if is_compose_file(file_name) {
+            let value: Value = serde_yaml::from_str(content).with_context(|| format!("Failed to parse {}", file_name))?;
+            Ok(Self::from_compose(&value))
+        } else {
+            anyhow::bail!("Not a Dockerfile or Compose file: {}", file_name)
+        }
+    }
+
+    /// `FROM` starts a stage; `EXPOSE`, `ENV`, `CMD` and `ENTRYPOINT` apply to it.
+    /// Instructions may continue over lines ending in `\`.
+    pub fn from_dockerfile(content: &str) -> Self {
+        let mut config = Self { kind: ContainerConfigKind::Dockerfile, services: vec![], build_args: BTreeMap::new() };
+
+        for instruction in dockerfile_instructions(content) {
+            let (keyword, arguments) = instruction.split_once(char::is_whitespace).unwrap_or((&instruction, ""));
+            let arguments = arguments.trim();
+            if keyword.eq_ignore_ascii_case("FROM") {
+                // `FROM --platform=$BUILDPLATFORM node:20 AS build`
+                let words: Vec<&str> = arguments.split_whitespace().filter(|w| !w.starts_with("--")).collect();
+                let name = match words.as_slice() {
+                    [_, as_keyword, name] if as_keyword.eq_ignore_ascii_case("AS") => name.to_string(),
+                    _ => config.services.len().to_string(),
+                };
+                config.services.push(ContainerService {
+                    name,
+                    image: words.first().map(|w| w.to_string()),
+                    ..Default::default()
+                });
+                continue;
+            }
+            if keyword.eq_ignore_ascii_case("ARG") {
+                // Before the first `FROM` too, so they're the file's rather than a stage's
+                let (name, default) = arguments.split_once('=').unwrap_or((arguments, ""));
+                config.build_args.insert(name.trim().to_string(), unquote(default));
+                continue;
+            }
+
+            let Some(stage) = config.services.last_mut() else {
+                continue;
+            };
+            match keyword.to_ascii_uppercase().as_str() {
+                "EXPOSE" => stage.ports.extend(arguments.split_whitespace().map(str::to_string)),
+                "ENV" => stage.env.extend(env_assignments(arguments)),
+                "CMD" | "ENTRYPOINT" => {
+                    let command = exec_form(arguments);
+                    stage.command = Some(match (&stage.command, keyword.eq_ignore_ascii_case("CMD")) {
+                        (Some(entrypoint), true) => format!("{} {}", entrypoint, command),
+                        _ => command,
+                    });
+                }
+                _ => {}
+            }
+        }
+        config
+    }
+
+    /// `services:` with their image or build context, ports, environment
+    /// (a list of `KEY=value` or a map), `depends_on` and command
+    pub fn from_compose(value: &Value) -> Self {
+        let mut config = Self { kind: ContainerConfigKind::Compose, services: vec![], build_args: BTreeMap::new() };
+        let Some(services) = value.get("services").and_then(Value::as_mapping) else {
+            return config;
+        };
+
+        for (name, service) in services {
+            let Some(name) = name.as_str() else {
+                continue;
+            };
+            let strings = |key: &str| -> Vec<String> {
+                match service.get(key) {
+                    Some(Value::Sequence(items)) => items.iter().filter_map(scalar).collect(),
+                    // `depends_on: { db: { condition: service_healthy } }`
+                    Some(Value::Mapping(map)) => map.keys().filter_map(scalar).collect(),
+                    Some(other) => scalar(other).into_iter().collect(),
+                    None => vec![],
+                }
+            };
+
+            let env = match service.get("environment") {
+                Some(Value::Mapping(map)) => map
+                    .iter()
+                    .filter_map(|(k, v)| Some((scalar(k)?, scalar(v).unwrap_or_default())))
+                    .collect(),
+                Some(Value::Sequence(items)) => items
+                    .iter()
+                    .filter_map(scalar)
+                    .map(|item| match item.split_once('=') {
+                        Some((k, v)) => (k.to_string(), v.to_string()),
+                        None => (item, String::new()),
+                    })
+                    .collect(),
+                _ => BTreeMap::new(),
+            };
+            // Long-syntax ports are `{ target: 80, published: 8080 }`
+            let ports = match service.get("ports") {
+                Some(Value::Sequence(items)) => items
+                    .iter()
+                    .filter_map(|port| match port {
+                        Value::Mapping(_) => {
+                            let target = port.get("target").and_then(scalar)?;
+                            Some(match port.get("published").and_then(scalar) {
+                                Some(published) => format!("{}:{}", published, target),
+                                None => target,
+                            })
+                        }
+                        other => scalar(other),
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+            let build = service.get("build").and_then(|b| scalar(b).or_else(|| b.get("context").and_then(scalar)));
+            let command = match service.get("command") {
+                Some(Value::Sequence(_)) => Some(strings("command").join(" ")),
+                Some(other) => scalar(other),
+                None => None,
+            };
+
+            config.services.push(ContainerService {
+                name: name.to_string(),
+                image: service.get("image").and_then(scalar),
+                build,
+                ports,
+                env,
+                depends_on: strings("depends_on"),
+                command,
+            });
+        }
+        config
+    }
+
+    /// Record the config as graph constants: `services.db.image = postgres:16`,
+    /// `services.web.ports = 8080:80`, `stages.build.env.NODE_ENV = production`
+    pub fn to_parsed_file(&self, content: &str) -> ParsedFile {
+        let prefix = match self.kind {
+            ContainerConfigKind::Compose => "services",
+            ContainerConfigKind::Dockerfile => "stages",
+        };
+        let mut constants = Vec::new();
+        let mut push = |name: String, value: String, kind: &str, needle: &str| {
+            constants.push(Constant {
+                range: line_range(content, needle),
+                name,
+                value,
+                type_annotation: Some(kind.to_string()),
+                category: ConstantCategory::Config,
+            });
+        };
+
+        for (name, default) in &self.build_args {
+            push(format!("args.{}", name), default.clone(), "build-arg", name);
+        }
+        for service in &self.services {
+            let key = format!("{}.{}", prefix, service.name);
+            if let Some(image) = &service.image {
+                push(format!("{}.image", key), image.clone(), "image", image);
+            }
+            if let Some(build) = &service.build {
+                push(format!("{}.build", key), build.clone(), "build", &service.name);
+            }
+            if !service.ports.is_empty() {
+                push(format!("{}.ports", key), service.ports.join(", "), "port", &service.ports[0]);
+            }
+            for (name, value) in &service.env {
+                push(format!("{}.env.{}", key, name), value.clone(), "env", name);
+            }
+            if !service.depends_on.is_empty() {
+                push(format!("{}.depends_on", key), service.depends_on.join(", "), "depends_on", "depends_on");
+            }
+            if let Some(command) = &service.command {
+                push(format!("{}.command", key), command.clone(), "command", &service.name);
+            }
+        }
+
+        ParsedFile {
+            symbols: vec![],
+            imports: vec![],
+            exports: vec![],
+            design_tokens: vec![],
+            type_definitions: vec![],
+            constants,
+            schemas: vec![],
+            language: match self.kind {
+                ContainerConfigKind::Compose => "compose",
+                ContainerConfigKind::Dockerfile => "dockerfile",
+            }
+            .to_string(),
+            syntax_errors: vec![],
+        }
+    }
+
+    /// Environment variables the config sets, as usages: `ENV`/`ARG` in a
+    /// Dockerfile, `environment:` in Compose
+    pub fn env_vars(&self, content: &str) -> Vec<EnvVarUsage> {
+        let (accessor, arg_accessor) = match self.kind {
+            ContainerConfigKind::Compose => ("compose environment", "compose environment"),
+            ContainerConfigKind::Dockerfile => ("Dockerfile ENV", "Dockerfile ARG"),
+        };
+        let usage = |name: &str, accessor: &str| EnvVarUsage {
+            name: name.to_string(),
+            accessor: accessor.to_string(),
+            range: line_range(content, name),
+        };
+
+        let mut usages: Vec<EnvVarUsage> = self.build_args.keys().map(|name| usage(name, arg_accessor)).collect();
+        for service in &self.services {
+            usages.extend(service.env.keys().map(|name| usage(name, accessor)));
+        }
+        usages.sort_by_key(|u| u.range.start_byte);
+        usages.dedup_by(|a, b| a.name == b.name);
+        usages
+    }
+}
+
+/// Instructions without comments, their continuation lines joined
+fn dockerfile_instructions(content: &str) -> Vec<String> {
+    let mut instructions = Vec::new();
+    let mut current = String::new();
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') || (trimmed.is_empty() && !current.is_empty()) {
+            continue;
+        }
+        match trimmed.strip_suffix('\\') {
+            Some(continued) => {
+                current.push_str(continued);
+                current.push(' ');
+            }
+            None => {
+                current.push_str(trimmed);
+                if !current.trim().is_empty() {
+                    instructions.push(current.trim().to_string());
+                }
+                current.clear();
+            }
+        }
+    }
+    if !current.trim().is_empty() {
+        instructions.push(current.trim().to_string());
+    }
+    instructions
+}
+
+/// `ENV A=1 B="two words"`, or the legacy `ENV A one value`
+fn env_assignments(arguments: &str) -> Vec<(String, String)> {
+    let first = arguments.split_whitespace().next().unwrap_or("");
+    if !first.contains('=') {
+        let (name, value) = arguments.split_once(char::is_whitespace).unwrap_or((arguments, ""));
+        return vec![(name.to_string(), unquote(value))];
+    }
+
+    let mut assignments = Vec::new();
+    let mut rest = arguments.trim();
+    while let Some((name, value)) = rest.split_once('=') {
+        let value = value.trim_start();
+        let end = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].find(quote).map(|i| i + 2).unwrap_or(value.len()),
+            _ => value.find(char::is_whitespace).unwrap_or(value.len()),
+        };
+        assignments.push((name.trim().to_string(), unquote(&value[..end])));
+        rest = value[end..].trim_start();
+    }
+    assignments
+}
+
+/// `["npm", "start"]` -> `npm start`; shell form is kept as written
+fn exec_form(arguments: &str) -> String {
+    match serde_json::from_str::<Vec<String>>(arguments) {
+        Ok(words) => words.join(" "),
+        Err(_) => arguments.to_string(),
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+/// A YAML string, number or boolean as text
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dockerfile() {
+        let dockerfile = r#"ARG NODE_VERSION=20
+FROM --platform=$BUILDPLATFORM node:${NODE_VERSION}-alpine AS build
+# dependencies first, for the layer cache
+RUN npm ci \
+    && npm run build
+
+FROM nginx:1.25
+ENV APP_ENV=production API_URL="https://api.example.com"
+ENV LEGACY some value
+EXPOSE 80 443/tcp
+ENTRYPOINT ["nginx"]
+CMD ["-g", "daemon off;"]
+"#;
+        let config = ContainerConfig::parse("Dockerfile", dockerfile).unwrap();
+        assert_eq!(config.build_args.get("NODE_VERSION").map(String::as_str), Some("20"));
+        let stages: Vec<(&str, Option<&str>)> =
+            config.services.iter().map(|s| (s.name.as_str(), s.image.as_deref())).collect();
+        assert_eq!(stages, vec![("build", Some("node:${NODE_VERSION}-alpine")), ("1", Some("nginx:1.25"))]);
+
+        let runtime = &config.services[1];
+        assert_eq!(runtime.ports, vec!["80", "443/tcp"]);
+        assert_eq!(runtime.env.get("API_URL").map(String::as_str), Some("https://api.example.com"));
+        assert_eq!(runtime.env.get("LEGACY").map(String::as_str), Some("some value"));
+        assert_eq!(runtime.command.as_deref(), Some("nginx -g daemon off;"));
+
+        let parsed = config.to_parsed_file(dockerfile);
+        let ports = parsed.constants.iter().find(|c| c.name == "stages.1.ports").unwrap();
+        assert_eq!(ports.value, "80, 443/tcp");
+        assert_eq!(ports.range.start_line, 10);
+        let env: Vec<String> = config.env_vars(dockerfile).into_iter().map(|u| u.name).collect();
+        assert_eq!(env, vec!["NODE_VERSION", "APP_ENV", "API_URL", "LEGACY"]);
+    }
+
+    #[test]
+    fn test_compose_file() {
+        let compose = r#"services:
+  web:
+    build:
+      context: ./web
+    ports:
+      - "8080:80"
+      - target: 443
+        published: 8443
+    environment:
+      - DATABASE_URL=postgres://db/app
+      - DEBUG
+    depends_on:
+      db:
+        condition: service_healthy
+    command: ["npm", "start"]
+  db:
+    image: postgres:16
+    environment:
+      POSTGRES_PASSWORD: secret
+      POSTGRES_PORT: 5432
+"#;
+        assert!(is_compose_file("docker-compose.prod.yml") && is_compose_file("compose.yaml"));
+        assert!(!is_compose_file("compose-helpers.yml"));
+
+        let config = ContainerConfig::parse("docker-compose.yml", compose).unwrap();
+        let web = &config.services[0];
+        assert_eq!(web.build.as_deref(), Some("./web"));
+        assert_eq!(web.ports, vec!["8080:80", "8443:443"]);
+        assert_eq!(web.env.get("DATABASE_URL").map(String::as_str), Some("postgres://db/app"));
+        assert_eq!(web.depends_on, vec!["db"]);
+        assert_eq!(web.command.as_deref(), Some("npm start"));
+        assert_eq!(config.services[1].env.get("POSTGRES_PORT").map(String::as_str), Some("5432"));
+
+        let parsed = config.to_parsed_file(compose);
+        assert_eq!(parsed.language, "compose");
+        let image = parsed.constants.iter().find(|c| c.name == "services.db.image").unwrap();
+        assert_eq!((image.value.as_str(), image.range.start_line), ("postgres:16", 17));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::docker::{is_container_config, ContainerConfig};
+use crate::types::*;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Compile `(accessor, pattern)` pairs; the patterns are fixed, so a bad one is a bug
+fn compile(patterns: &[(&'static str, &str)]) -> Vec<(&'static str, Regex)> {
+    patterns
+        .iter()
+        .map(|(accessor, pattern)| (*accessor, Regex::new(pattern).expect("invalid env var pattern")))
+        .collect()
+}
+
+static JS_READS: LazyLock<Vec<(&str, Regex)>> = LazyLock::new(|| {
+    compile(&[
+        ("process.env", r"\bprocess\.env\.([A-Za-z_][A-Za-z0-9_]*)"),
+        ("process.env", r#"\bprocess\.env\[\s*['"`]([A-Za-z_][A-Za-z0-9_]*)['"`]\s*\]"#),
+        ("import.meta.env", r"\bimport\.meta\.env\.([A-Za-z_][A-Za-z0-9_]*)"),
+    ])
+});
+
+static RUST_READS: LazyLock<Vec<(&str, Regex)>> = LazyLock::new(|| {
+    compile(&[
+        ("env::var", r#"\benv::var(?:_os)?\(\s*"([A-Za-z_][A-Za-z0-9_]*)"\s*\)"#),
+        ("env!", r#"\b(?:option_)?env!\(\s*"([A-Za-z_][A-Za-z0-9_]*)""#),
+        ("dotenvy::var", r#"\bdotenvy?::var\(\s*"([A-Za-z_][A-Za-z0-9_]*)"\s*\)"#),
+    ])
+});
+
+static PYTHON_READS: LazyLock<Vec<(&str, Regex)>> = LazyLock::new(|| {
+    compile(&[
+        ("os.environ", r#"\benviron\[\s*['"]([A-Za-z_][A-Za-z0-9_]*)['"]\s*\]"#),
+        ("os.environ", r#"\benviron\.get\(\s*['"]([A-Za-z_][A-Za-z0-9_]*)['"]"#),
+        ("os.getenv", r#"\bgetenv\(\s*['"]([A-Za-z_][A-Za-z0-9_]*)['"]"#),
+    ])
+});
+
+static JS_DESTRUCTURE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{([^{}]*)\}\s*=\s*process\.env\b").expect("invalid env var pattern"));
+
+/// Extract environment variable reads: `process.env.X` / `import.meta.env.X`
+/// in JS/TS, `std::env::var("X")` / `env!("X")` in Rust and
+/// `os.environ["X"]` / `os.getenv("X")` in Python, plus the variables a
+/// Dockerfile or Compose file sets. Only names are recorded.
+pub fn extract_env_vars(relative_path: &str, content: &str) -> Vec<EnvVarUsage> {
+    // Dockerfiles and Compose files set variables rather than read them
+    let file_name = relative_path.rsplit(['/', '\\']).next().unwrap_or(relative_path);
+    if is_container_config(file_name) {
+        return ContainerConfig::parse(file_name, content).map(|c| c.env_vars(content)).unwrap_or_default();
+    }
+
+    let extension = relative_path.rsplit('.').next().unwrap_or("");
+    let is_js = matches!(extension, "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs");
+
+    let patterns: &[(&str, Regex)] = match extension {
+        _ if is_js => &JS_READS,
+        "rs" => &RUST_READS,
+        "py" => &PYTHON_READS,
+        _ => &[],
+    };
+
+    let mut usages: Vec<EnvVarUsage> = Vec::new();
+    for (accessor, re) in patterns {
+        for c in re.captures_iter(content) {
+            let Some(m) = c.get(0) else { continue };
+            usages.push(EnvVarUsage {
+                name: c[1].to_string(),
+                accessor: accessor.to_string(),
+                range: range_for(content, m.start(), m.end()),
+            });
+        }
+    }
+
+    // `const { API_URL, PORT = 3000 } = process.env`
+    if is_js {
+        for c in JS_DESTRUCTURE.captures_iter(content) {
+            let Some(m) = c.get(0) else { continue };
+            for binding in c[1].split(',') {
+                let name = binding.split([':', '=']).next().unwrap_or("").trim();
+                if !name.is_empty() && name.chars().all(|ch| ch.is_alphanumeric() || ch == '_') {
+                    usages.push(EnvVarUsage {
+                        name: name.to_string(),
+                        accessor: "process.env".to_string(),
+                        range: range_for(content, m.start(), m.end()),
+                    });
+                }
+            }
+        }
+    }
+
+    usages.sort_by_key(|u| u.range.start_byte);
+    usages
+}
+
+fn range_for(content: &str, start: usize, end: usize) -> Range {
+    Range {
+        start_line: content[..start].matches('\n').count() + 1,
+        end_line: content[..end].matches('\n').count() + 1,
+        start_byte: start,
+        end_byte: end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(usages: &[EnvVarUsage]) -> Vec<&str> {
+        usages.iter().map(|u| u.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_extract_env_vars() {
+        let ts = "const url = process.env.DATABASE_URL;\nconst { API_KEY, PORT = 3000 } = process.env;\nconst k = import.meta.env.VITE_KEY;";
+        assert_eq!(names(&extract_env_vars("src/config.ts", ts)), vec!["DATABASE_URL", "API_KEY", "PORT", "VITE_KEY"]);
+
+        let rs = r#"let url = std::env::var("DATABASE_URL")?; let v = env!("CARGO_PKG_VERSION");"#;
+        assert_eq!(names(&extract_env_vars("src/main.rs", rs)), vec!["DATABASE_URL", "CARGO_PKG_VERSION"]);
+
+        let py = "import os\nkey = os.environ[\"SECRET_KEY\"]\ndebug = os.getenv('DEBUG', '0')\n";
+        let usages = extract_env_vars("settings.py", py);
+        assert_eq!(names(&usages), vec!["SECRET_KEY", "DEBUG"]);
+        assert_eq!(usages[1].range.start_line, 3);
+    }
+}
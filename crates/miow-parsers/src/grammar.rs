@@ -0,0 +1,169 @@
+use crate::diagnostics::tree_diagnostics;
+use crate::types::*;
+use anyhow::{Context, Result};
+use std::path::Path;
+use tree_sitter::{Language, Node, Parser};
+
+/// A tree-sitter grammar miow has no dedicated parser for, loaded from a
+/// compiled grammar library (`libtree-sitter-<name>.so` / `.dylib` / `.dll`).
+/// Symbols are picked up generically: any definition or declaration node
+/// with a `name` field.
+#[derive(Clone)]
+pub struct ExternalGrammar {
+    name: String,
+    language: Language,
+}
+
+impl ExternalGrammar {
+    /// Wrap a grammar that is already linked in
+    pub fn new(name: &str, language: Language) -> Self {
+        Self {
+            name: name.to_string(),
+            language,
+        }
+    }
+
+    /// Load the `tree_sitter_<name>` entry point from a compiled grammar library
+    pub fn load(name: &str, library_path: &Path) -> Result<Self> {
+        let symbol = format!("tree_sitter_{}", name.replace('-', "_"));
+
+        // SAFETY: grammar libraries export `const TSLanguage *tree_sitter_<name>(void)`,
+        // which is what `Language` wraps. The library is leaked so the language
+        // tables stay mapped for the rest of the process.
+        let language = unsafe {
+            let library = libloading::Library::new(library_path)
+                .with_context(|| format!("Failed to load grammar library {}", library_path.display()))?;
+            let constructor: libloading::Symbol<unsafe extern "C" fn() -> Language> = library
+                .get(symbol.as_bytes())
+                .with_context(|| format!("{} does not export {}", library_path.display(), symbol))?;
+            let language = constructor();
+            std::mem::forget(library);
+            language
+        };
+
+        Ok(Self::new(name, language))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parse(&self, content: &str) -> Result<ParsedFile> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(self.language)
+            .with_context(|| format!("Grammar {} is incompatible with this tree-sitter version", self.name))?;
+        let tree = parser
+            .parse(content, None)
+            .with_context(|| format!("Failed to parse {} source", self.name))?;
+
+        Ok(ParsedFile {
+            symbols: collect_symbols(&tree.root_node(), content),
+            imports: vec![],
+            exports: vec![],
+            design_tokens: vec![],
+            type_definitions: vec![],
+            constants: vec![],
+            schemas: vec![],
+            language: self.name.clone(),
+            syntax_errors: tree_diagnostics(&tree, content),
+        })
+    }
+}
+
+/// Definitions found under `node`; nested definitions become children of the enclosing one
+fn collect_symbols(node: &Node, source: &str) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match definition(&child, source) {
+            Some(mut symbol) => {
+                symbol.children = collect_symbols(&child, source);
+                symbols.push(symbol);
+            }
+            None => symbols.extend(collect_symbols(&child, source)),
+        }
+    }
+    symbols
+}
+
+fn definition(node: &Node, source: &str) -> Option<Symbol> {
+    let kind = node.kind();
+    if !(kind.ends_with("_definition") || kind.ends_with("_declaration") || kind.ends_with("_item")) {
+        return None;
+    }
+    let name = node.child_by_field_name("name")?;
+    let symbol_type = symbol_type_for(kind)?;
+
+    Some(Symbol {
+        name: source[name.byte_range()].to_string(),
+        kind: symbol_type,
+        range: Range {
+            start_line: node.start_position().row + 1,
+            end_line: node.end_position().row + 1,
+            start_byte: node.start_byte(),
+            end_byte: node.end_byte(),
+        },
+        content: source[node.byte_range()].to_string(),
+        metadata: SymbolMetadata::default(),
+        children: vec![],
+        references: vec![],
+    })
+}
+
+/// Grammars name their nodes differently, so go by the words in the kind
+fn symbol_type_for(kind: &str) -> Option<SymbolType> {
+    let words: Vec<&str> = kind.split('_').collect();
+    let has = |word: &str| words.contains(&word);
+
+    if has("type") && has("parameter") {
+        Some(SymbolType::TypeParameter)
+    } else if has("method") {
+        Some(SymbolType::Method)
+    } else if has("function") || has("fn") || has("func") || has("def") {
+        Some(SymbolType::Function)
+    } else if has("class") {
+        Some(SymbolType::Class)
+    } else if has("struct") || has("record") {
+        Some(SymbolType::Struct)
+    } else if has("interface") || has("trait") || has("protocol") {
+        Some(SymbolType::Interface)
+    } else if has("enum") {
+        Some(SymbolType::Enum)
+    } else if has("module") || has("namespace") || has("package") {
+        Some(SymbolType::Module)
+    } else if has("const") || has("constant") {
+        Some(SymbolType::Constant)
+    } else if has("type") || has("alias") {
+        // A named type (`type Props = ...`); shapes are looked up as interfaces
+        Some(SymbolType::Interface)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generic_extraction_with_linked_grammar() {
+        let grammar = ExternalGrammar::new("python", tree_sitter_python::language());
+        let parsed = grammar
+            .parse("class Cart:\n    def total(self):\n        return 0\n\ndef helper():\n    pass\n")
+            .unwrap();
+
+        let names: Vec<_> = parsed.symbols.iter().map(|s| (s.name.as_str(), s.kind.clone())).collect();
+        assert_eq!(names, vec![("Cart", SymbolType::Class), ("helper", SymbolType::Function)]);
+        assert_eq!(parsed.symbols[0].children[0].name, "total");
+        assert_eq!(parsed.language, "python");
+    }
+
+    #[test]
+    fn test_symbol_type_for_type_declarations() {
+        assert_eq!(symbol_type_for("type_alias_declaration"), Some(SymbolType::Interface));
+        assert_eq!(symbol_type_for("type_definition"), Some(SymbolType::Interface));
+        assert_eq!(symbol_type_for("type_parameter_declaration"), Some(SymbolType::TypeParameter));
+        assert_eq!(symbol_type_for("function_definition"), Some(SymbolType::Function));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::diagnostics::grammar_for;
+use crate::types::*;
+use crate::{CParser, CppParser, JavaParser, PythonParser, RustParser, TypeScriptParser};
+use anyhow::{Context, Result};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tree_sitter::{InputEdit, Parser, Point, Tree};
+
+/// Keeps the last tree-sitter tree for each file so that re-parsing a file
+/// after a small save only re-reads the edited region instead of the whole
+/// file. Used by the watch pipeline; one-shot indexing doesn't need it.
+#[derive(Default)]
+pub struct IncrementalParser {
+    trees: HashMap<PathBuf, CachedTree>,
+    /// A tree-sitter parser per extension, kept across calls
+    parsers: HashMap<String, Parser>,
+    extractors: Extractors,
+}
+
+/// The language parsers whose `parse_tree` pulls symbols out of a tree
+#[derive(Default)]
+struct Extractors {
+    typescript: TypeScriptParser,
+    rust: RustParser,
+    python: PythonParser,
+    java: JavaParser,
+    c: CParser,
+    cpp: CppParser,
+}
+
+struct CachedTree {
+    content: String,
+    tree: Tree,
+}
+
+impl IncrementalParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse `content` as the new contents of `path`, reusing the cached tree
+    /// when there is one. Returns `None` for extensions without a tree-sitter grammar.
+    pub fn parse(&mut self, path: &Path, content: &str) -> Result<Option<ParsedFile>> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let Some(language) = grammar_for(extension) else {
+            return Ok(None);
+        };
+
+        let parser = match self.parsers.entry(extension.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let mut parser = Parser::new();
+                parser
+                    .set_language(language)
+                    .with_context(|| format!("Failed to set language for .{}", extension))?;
+                entry.insert(parser)
+            }
+        };
+
+        // Tell the old tree what changed so tree-sitter can reuse the untouched subtrees
+        let old_tree = self.trees.remove(path).map(|cached| {
+            let mut tree = cached.tree;
+            tree.edit(&compute_edit(&cached.content, content));
+            tree
+        });
+
+        let tree = parser
+            .parse(content, old_tree.as_ref())
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        let parsed = self.extractors.extract(extension, &tree, content)?;
+
+        self.trees.insert(
+            path.to_path_buf(),
+            CachedTree {
+                content: content.to_string(),
+                tree,
+            },
+        );
+        Ok(Some(parsed))
+    }
+
+    /// Drop the cached tree for a deleted or renamed file
+    pub fn forget(&mut self, path: &Path) {
+        self.trees.remove(path);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+}
+
+impl Extractors {
+    fn extract(&self, extension: &str, tree: &Tree, content: &str) -> Result<ParsedFile> {
+        match extension {
+            "ts" => self.typescript.parse_tree(tree, content, false),
+            "tsx" => self.typescript.parse_tree(tree, content, true),
+            "rs" => self.rust.parse_tree(tree, content),
+            "py" => self.python.parse_tree(tree, content),
+            "java" => self.java.parse_tree(tree, content),
+            "c" | "h" => self.c.parse_tree(tree, content),
+            "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => self.cpp.parse_tree(tree, content),
+            _ => anyhow::bail!("No symbol extractor for .{} files", extension),
+        }
+    }
+}
+
+/// Describe the change from `old` to `new` as a single edit covering
+/// everything between their common prefix and common suffix
+fn compute_edit(old: &str, new: &str) -> InputEdit {
+    let (old_bytes, new_bytes) = (old.as_bytes(), new.as_bytes());
+    let start = old_bytes.iter().zip(new_bytes).take_while(|(a, b)| a == b).count();
+    let suffix = old_bytes[start..]
+        .iter()
+        .rev()
+        .zip(new_bytes[start..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_end = old_bytes.len() - suffix;
+    let new_end = new_bytes.len() - suffix;
+
+    InputEdit {
+        start_byte: start,
+        old_end_byte: old_end,
+        new_end_byte: new_end,
+        start_position: point_at(old_bytes, start),
+        old_end_position: point_at(old_bytes, old_end),
+        new_end_position: point_at(new_bytes, new_end),
+    }
+}
+
+/// Tree-sitter points count columns in bytes
+fn point_at(text: &[u8], byte: usize) -> Point {
+    let before = &text[..byte];
+    let row = before.iter().filter(|&&b| b == b'\n').count();
+    let line_start = before.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    Point::new(row, byte - line_start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_edit() {
+        let edit = compute_edit("fn a() {}\nfn b() {}\n", "fn a() {}\nfn bee() {}\n");
+        assert_eq!(edit.start_byte, 14);
+        assert_eq!(edit.old_end_byte, 14);
+        assert_eq!(edit.new_end_byte, 16);
+        assert_eq!(edit.start_position, Point::new(1, 4));
+    }
+
+    #[test]
+    fn test_incremental_reparse_matches_full_parse() {
+        let path = Path::new("src/lib.rs");
+        let mut parser = IncrementalParser::new();
+
+        let first = parser.parse(path, "pub fn a() {}\n").unwrap().unwrap();
+        assert_eq!(first.symbols.len(), 1);
+        assert!(!parser.is_empty());
+
+        let edited = "pub fn a() {}\n\npub fn b() -> u8 { 1 }\n";
+        let second = parser.parse(path, edited).unwrap().unwrap();
+        let full = RustParser::new().parse(edited).unwrap();
+        let names = |p: &ParsedFile| p.symbols.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&second), names(&full));
+
+        parser.forget(path);
+        assert!(parser.is_empty());
+        assert!(parser.parse(Path::new("README.md"), "# hi").unwrap().is_none());
+    }
+}
@@ -0,0 +1,524 @@
+use crate::diagnostics::{for_each_declaration, tree_diagnostics};
+use crate::types::*;
+use anyhow::{Context, Result};
+use tree_sitter::{Node, Parser, Tree};
+
+/// Spring / JPA stereotype annotations and the tag attached to the annotated class
+const STEREOTYPE_TAGS: &[(&str, &str)] = &[
+    ("RestController", "spring-controller"),
+    ("Controller", "spring-controller"),
+    ("Service", "spring-service"),
+    ("Repository", "spring-repository"),
+    ("Component", "spring-component"),
+    ("Configuration", "spring-configuration"),
+    ("SpringBootApplication", "spring-boot-application"),
+    ("Entity", "jpa-entity"),
+];
+
+pub struct JavaParser;
+
+impl JavaParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn parse(&self, content: &str) -> Result<ParsedFile> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(tree_sitter_java::language())
+            .context("Failed to set Java language")?;
+
+        let tree = parser
+            .parse(content, None)
+            .context("Failed to parse Java content")?;
+
+        self.parse_tree(&tree, content)
+    }
+
+    /// Extract from an already-parsed tree; lets callers reuse an incrementally reparsed tree
+    pub fn parse_tree(&self, tree: &Tree, content: &str) -> Result<ParsedFile> {
+        let root_node = tree.root_node();
+
+        let symbols = self.extract_symbols(&root_node, content)?;
+        let imports = self.extract_imports(&root_node, content)?;
+        let type_definitions = self.extract_type_definitions(&root_node, content)?;
+        let constants = self.extract_constants(&symbols);
+
+        Ok(ParsedFile {
+            symbols,
+            imports,
+            exports: vec![], // Visibility is carried by access_modifier instead
+            design_tokens: vec![],
+            type_definitions,
+            constants,
+            schemas: vec![],
+            language: "java".to_string(),
+            syntax_errors: tree_diagnostics(tree, content),
+        })
+    }
+
+    fn extract_symbols(&self, node: &Node, source: &str) -> Result<Vec<Symbol>> {
+        let mut symbols = Vec::new();
+        for_each_declaration(node, |candidate| {
+            symbols.extend(self.process_type_declaration(candidate, source)?);
+            Ok(())
+        });
+
+        Ok(symbols)
+    }
+
+    /// Class, interface, enum, record and @interface declarations (top-level or nested)
+    fn process_type_declaration(&self, node: &Node, source: &str) -> Result<Option<Symbol>> {
+        let kind = match node.kind() {
+            "class_declaration" | "record_declaration" => SymbolType::Class,
+            "interface_declaration" | "annotation_type_declaration" => SymbolType::Interface,
+            "enum_declaration" => SymbolType::Enum,
+            _ => return Ok(None),
+        };
+
+        let name = self
+            .get_child_text(node, "name", source)
+            .unwrap_or_else(|| "Anonymous".to_string());
+        let mut metadata = self.extract_metadata(node, source)?;
+
+        if let Some(superclass) = node.child_by_field_name("superclass") {
+            let text = superclass.utf8_text(source.as_bytes())?;
+            metadata
+                .extends
+                .push(text.trim_start_matches("extends").trim().to_string());
+        }
+        if let Some(interfaces) = node.child_by_field_name("interfaces") {
+            metadata.implements = self.type_list(&interfaces, source)?;
+        }
+        // Interfaces use `extends` for their super-interfaces
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            if child.kind() == "extends_interfaces" {
+                metadata.extends.extend(self.type_list(&child, source)?);
+            }
+        }
+        if let Some(type_params) = node.child_by_field_name("type_parameters") {
+            metadata.generic_params = self.extract_type_parameters(&type_params, source)?;
+        }
+
+        for decorator in &metadata.decorators {
+            let annotation = annotation_name(decorator);
+            if let Some((_, tag)) = STEREOTYPE_TAGS.iter().find(|(a, _)| *a == annotation) {
+                metadata.tags.push(tag.to_string());
+            }
+        }
+
+        let children = match node.child_by_field_name("body") {
+            Some(body) => self.extract_members(&body, source)?,
+            None => vec![],
+        };
+
+        Ok(Some(Symbol {
+            name,
+            kind,
+            range: self.get_range(node),
+            content: node.utf8_text(source.as_bytes())?.to_string(),
+            metadata,
+            children,
+            references: vec![],
+        }))
+    }
+
+    fn extract_members(&self, body: &Node, source: &str) -> Result<Vec<Symbol>> {
+        let mut members = Vec::new();
+        let mut cursor = body.walk();
+
+        for child in body.children(&mut cursor) {
+            match child.kind() {
+                "method_declaration" | "constructor_declaration" | "annotation_type_element_declaration" => {
+                    let name = self
+                        .get_child_text(&child, "name", source)
+                        .unwrap_or_else(|| "method".to_string());
+                    let mut metadata = self.extract_metadata(&child, source)?;
+                    if let Some(params) = child.child_by_field_name("parameters") {
+                        metadata.parameters = self.extract_parameters(&params, source)?;
+                    }
+                    metadata.return_type = self.get_child_text(&child, "type", source);
+
+                    members.push(Symbol {
+                        name,
+                        kind: if child.kind() == "constructor_declaration" {
+                            SymbolType::Constructor
+                        } else {
+                            SymbolType::Method
+                        },
+                        range: self.get_range(&child),
+                        content: child.utf8_text(source.as_bytes())?.to_string(),
+                        metadata,
+                        children: vec![],
+                        references: vec![],
+                    });
+                }
+                "field_declaration" | "constant_declaration" => {
+                    let metadata = self.extract_metadata(&child, source)?;
+                    let field_type = self.get_child_text(&child, "type", source);
+
+                    // `int a, b = 2;` declares several fields
+                    let mut decl_cursor = child.walk();
+                    for declarator in child.children_by_field_name("declarator", &mut decl_cursor) {
+                        let name = self
+                            .get_child_text(&declarator, "name", source)
+                            .unwrap_or_else(|| "field".to_string());
+                        let mut metadata = metadata.clone();
+                        metadata.return_type = field_type.clone();
+
+                        members.push(Symbol {
+                            name,
+                            kind: SymbolType::Field,
+                            range: self.get_range(&child),
+                            content: declarator.utf8_text(source.as_bytes())?.to_string(),
+                            metadata,
+                            children: vec![],
+                            references: vec![],
+                        });
+                    }
+                }
+                "enum_constant" => {
+                    let name = self
+                        .get_child_text(&child, "name", source)
+                        .unwrap_or_else(|| "CONSTANT".to_string());
+                    members.push(Symbol {
+                        name,
+                        kind: SymbolType::EnumMember,
+                        range: self.get_range(&child),
+                        content: child.utf8_text(source.as_bytes())?.to_string(),
+                        metadata: SymbolMetadata::default(),
+                        children: vec![],
+                        references: vec![],
+                    });
+                }
+                // Enum bodies keep methods/fields in a nested declarations block
+                "enum_body_declarations" => {
+                    members.extend(self.extract_members(&child, source)?);
+                }
+                _ => {
+                    if let Some(nested) = self.process_type_declaration(&child, source)? {
+                        members.push(nested);
+                    }
+                }
+            }
+        }
+
+        Ok(members)
+    }
+
+    /// Annotations, access modifier, static/final flags and Javadoc
+    fn extract_metadata(&self, node: &Node, source: &str) -> Result<SymbolMetadata> {
+        let mut metadata = SymbolMetadata::default();
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            if child.kind() != "modifiers" {
+                continue;
+            }
+
+            let mut mod_cursor = child.walk();
+            for modifier in child.children(&mut mod_cursor) {
+                let text = modifier.utf8_text(source.as_bytes())?;
+                match modifier.kind() {
+                    "marker_annotation" | "annotation" => metadata.decorators.push(text.to_string()),
+                    "public" | "private" | "protected" => {
+                        metadata.access_modifier = Some(text.to_string())
+                    }
+                    "static" => metadata.is_static = true,
+                    "final" => metadata.is_readonly = true,
+                    _ => {}
+                }
+            }
+        }
+
+        // Javadoc is the `/** ... */` comment immediately before the declaration
+        if let Some(prev) = node.prev_sibling() {
+            if matches!(prev.kind(), "comment" | "block_comment") {
+                let text = prev.utf8_text(source.as_bytes())?;
+                if text.starts_with("/**") {
+                    metadata.documentation = Some(clean_javadoc(text));
+                }
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    fn extract_parameters(&self, node: &Node, source: &str) -> Result<Vec<Parameter>> {
+        let mut params = Vec::new();
+        let mut cursor = node.walk();
+
+        for child in node.children(&mut cursor) {
+            if matches!(child.kind(), "formal_parameter" | "spread_parameter") {
+                let name = self.get_child_text(&child, "name", source).unwrap_or_else(|| {
+                    // spread_parameter has no name field: `String... args`
+                    child
+                        .utf8_text(source.as_bytes())
+                        .unwrap_or("")
+                        .rsplit(' ')
+                        .next()
+                        .unwrap_or("")
+                        .to_string()
+                });
+
+                params.push(Parameter {
+                    name,
+                    type_annotation: self.get_child_text(&child, "type", source),
+                    default_value: None,
+                    is_optional: false,
+                });
+            }
+        }
+        Ok(params)
+    }
+
+    fn extract_imports(&self, node: &Node, source: &str) -> Result<Vec<Import>> {
+        let mut imports = Vec::new();
+        let mut cursor = node.walk();
+
+        for child in node.children(&mut cursor) {
+            if child.kind() == "import_declaration" {
+                let text = child.utf8_text(source.as_bytes())?;
+                let path = text
+                    .trim_start_matches("import")
+                    .trim()
+                    .trim_start_matches("static ")
+                    .trim_end_matches(';')
+                    .trim();
+
+                // `java.util.List` -> source `java.util`, name `List`
+                let (package, name) = path.rsplit_once('.').unwrap_or(("", path));
+                imports.push(Import {
+                    source: package.to_string(),
+                    names: vec![ImportName {
+                        name: name.to_string(),
+                        alias: None,
+                        is_default: false,
+                        is_namespace: name == "*",
+                        is_type: false,
+                    }],
+                    range: self.get_range(&child),
+                });
+            }
+        }
+        Ok(imports)
+    }
+
+    fn extract_type_definitions(&self, node: &Node, source: &str) -> Result<Vec<TypeDefinition>> {
+        let mut definitions = Vec::new();
+        let mut cursor = node.walk();
+
+        for child in node.children(&mut cursor) {
+            let kind = match child.kind() {
+                "interface_declaration" => TypeKind::Interface,
+                "enum_declaration" => TypeKind::Enum,
+                _ => continue,
+            };
+
+            let name = self
+                .get_child_text(&child, "name", source)
+                .unwrap_or_else(|| "Anonymous".to_string());
+            let generic_params = match child.child_by_field_name("type_parameters") {
+                Some(tp) => self.extract_type_parameters(&tp, source)?,
+                None => vec![],
+            };
+
+            let mut properties = Vec::new();
+            if let Some(body) = child.child_by_field_name("body") {
+                for member in self.extract_members(&body, source)? {
+                    let type_annotation = match member.kind {
+                        SymbolType::Method => {
+                            let params: Vec<String> = member
+                                .metadata
+                                .parameters
+                                .iter()
+                                .map(|p| format!("{} {}", p.type_annotation.clone().unwrap_or_default(), p.name))
+                                .collect();
+                            format!(
+                                "({}) -> {}",
+                                params.join(", "),
+                                member.metadata.return_type.clone().unwrap_or_else(|| "void".to_string())
+                            )
+                        }
+                        SymbolType::EnumMember => name.clone(),
+                        _ => member.metadata.return_type.clone().unwrap_or_default(),
+                    };
+
+                    properties.push(TypeProperty {
+                        name: member.name,
+                        type_annotation,
+                        is_optional: false,
+                        description: member.metadata.documentation,
+                    });
+                }
+            }
+
+            definitions.push(TypeDefinition {
+                name,
+                kind,
+                definition: child.utf8_text(source.as_bytes())?.to_string(),
+                properties,
+                generic_params,
+                range: self.get_range(&child),
+            });
+        }
+
+        Ok(definitions)
+    }
+
+    /// `static final` fields of top-level types
+    fn extract_constants(&self, symbols: &[Symbol]) -> Vec<Constant> {
+        let mut constants = Vec::new();
+
+        for symbol in symbols {
+            for member in &symbol.children {
+                let is_constant = member.kind == SymbolType::Field
+                    && ((member.metadata.is_static && member.metadata.is_readonly)
+                        || symbol.kind == SymbolType::Interface);
+                if !is_constant {
+                    continue;
+                }
+
+                let value = member
+                    .content
+                    .split_once('=')
+                    .map(|(_, v)| v.trim().to_string())
+                    .unwrap_or_else(|| "unknown".to_string());
+
+                constants.push(Constant {
+                    name: format!("{}.{}", symbol.name, member.name),
+                    value,
+                    type_annotation: member.metadata.return_type.clone(),
+                    category: ConstantCategory::Config,
+                    range: member.range.clone(),
+                });
+            }
+        }
+
+        constants
+    }
+
+    fn type_list(&self, node: &Node, source: &str) -> Result<Vec<String>> {
+        let mut types = Vec::new();
+        let mut stack = vec![*node];
+
+        while let Some(current) = stack.pop() {
+            let mut cursor = current.walk();
+            for child in current.children(&mut cursor) {
+                match child.kind() {
+                    "type_list" => stack.push(child),
+                    "type_identifier" | "scoped_type_identifier" | "generic_type" => {
+                        types.push(child.utf8_text(source.as_bytes())?.to_string())
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(types)
+    }
+
+    fn extract_type_parameters(&self, node: &Node, source: &str) -> Result<Vec<String>> {
+        let mut params = Vec::new();
+        let mut cursor = node.walk();
+
+        for child in node.children(&mut cursor) {
+            if child.kind() == "type_parameter" {
+                params.push(child.utf8_text(source.as_bytes())?.to_string());
+            }
+        }
+        Ok(params)
+    }
+
+    fn get_child_text(&self, node: &Node, field: &str, source: &str) -> Option<String> {
+        node.child_by_field_name(field)
+            .map(|n| n.utf8_text(source.as_bytes()).unwrap().to_string())
+    }
+
+    fn get_range(&self, node: &Node) -> Range {
+        Range {
+            start_line: node.start_position().row + 1,
+            end_line: node.end_position().row + 1,
+            start_byte: node.start_byte(),
+            end_byte: node.end_byte(),
+        }
+    }
+}
+
+impl Default for JavaParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `@RequestMapping("/api")` -> `RequestMapping`
+fn annotation_name(annotation: &str) -> &str {
+    let name = annotation.trim_start_matches('@');
+    let name = name.split('(').next().unwrap_or(name).trim();
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+fn clean_javadoc(comment: &str) -> String {
+    comment
+        .trim_start_matches("/**")
+        .trim_end_matches("*/")
+        .lines()
+        .map(|line| line.trim().trim_start_matches('*').trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spring_controller() {
+        let code = r#"
+package com.example.users;
+
+import java.util.List;
+import org.springframework.web.bind.annotation.*;
+
+/** Exposes user endpoints. */
+@RestController
+@RequestMapping("/api/users")
+public class UserController extends BaseController implements Auditable {
+    public static final String VERSION = "v1";
+
+    private final UserService userService;
+
+    public UserController(UserService userService) {
+        this.userService = userService;
+    }
+
+    @GetMapping("/{id}")
+    public User getUser(@PathVariable Long id) {
+        return userService.find(id);
+    }
+}
+"#;
+
+        let parsed = JavaParser::new().parse(code).unwrap();
+        assert_eq!(parsed.language, "java");
+        assert_eq!(parsed.imports.len(), 2);
+        assert_eq!(parsed.imports[0].source, "java.util");
+
+        let class = &parsed.symbols[0];
+        assert_eq!(class.name, "UserController");
+        assert!(class.metadata.decorators.iter().any(|d| d == "@RestController"));
+        assert!(class.metadata.tags.contains(&"spring-controller".to_string()));
+        assert_eq!(class.metadata.extends, vec!["BaseController"]);
+        assert_eq!(class.metadata.implements, vec!["Auditable"]);
+        assert_eq!(class.metadata.documentation.as_deref(), Some("Exposes user endpoints."));
+
+        let method = class.children.iter().find(|c| c.name == "getUser").unwrap();
+        assert_eq!(method.kind, SymbolType::Method);
+        assert_eq!(method.metadata.parameters[0].name, "id");
+        assert!(class.children.iter().any(|c| c.kind == SymbolType::Constructor));
+
+        assert_eq!(parsed.constants.len(), 1);
+        assert_eq!(parsed.constants[0].name, "UserController.VERSION");
+    }
+}
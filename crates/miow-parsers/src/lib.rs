@@ -1,21 +1,56 @@
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
 
+pub mod c;
+pub mod cpp;
+pub mod database;
+pub mod diagnostics;
+pub mod docker;
+pub mod env_vars;
+pub mod grammar;
+pub mod incremental;
+pub mod java;
+pub mod manifest;
+pub mod php;
 pub mod python;
+pub mod routes;
+pub mod ruby;
 pub mod rust;
 pub mod types;
 pub mod typescript;
 pub mod style_analyzer;
 pub mod semantic;
+pub mod stylesheet;
+pub mod terraform;
+pub mod test_cases;
 pub mod pattern_discovery;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
+pub use c::CParser;
+pub use cpp::CppParser;
+pub use database::DatabaseSchemaParser;
+pub use diagnostics::{syntax_diagnostics, ParseDiagnostic};
+pub use docker::{is_compose_file, is_container_config, is_dockerfile, ContainerConfig, ContainerConfigKind, ContainerService};
+pub use env_vars::extract_env_vars;
+pub use grammar::ExternalGrammar;
+pub use incremental::IncrementalParser;
+pub use java::JavaParser;
+pub use manifest::{is_manifest, Manifest, ManifestKind};
+pub use php::PhpParser;
 pub use python::PythonParser;
+pub use routes::extract_routes;
+pub use ruby::RubyParser;
 pub use rust::RustParser;
 pub use types::*;
 pub use typescript::TypeScriptParser;
 pub use style_analyzer::{StyleAnalyzer, StyleAnalysis};
+pub use stylesheet::{is_tailwind_config, StylesheetParser};
+pub use terraform::TerraformParser;
+pub use test_cases::{extract_tests, is_test_file};
 pub use semantic::{SemanticAnalyzer, SemanticInfo, BestPractice, ComplianceStatus};
 pub use pattern_discovery::{PatternDiscovery, DiscoveredPattern};
+#[cfg(feature = "wasm")]
+pub use wasm::{WasmParser, WASM_ABI_VERSION};
 
 /// Parse a TypeScript/TSX file and extract symbols
 pub fn parse_typescript(content: &str, is_tsx: bool) -> Result<ParsedFile> {
@@ -35,6 +70,78 @@ pub fn parse_python(content: &str) -> Result<ParsedFile> {
     parser.parse(content)
 }
 
+/// Parse a Java file and extract symbols
+pub fn parse_java(content: &str) -> Result<ParsedFile> {
+    let parser = JavaParser::new();
+    parser.parse(content)
+}
+
+/// Parse a Ruby file and extract symbols
+pub fn parse_ruby(content: &str) -> Result<ParsedFile> {
+    let parser = RubyParser::new();
+    parser.parse(content)
+}
+
+/// Parse a PHP file and extract symbols
+pub fn parse_php(content: &str) -> Result<ParsedFile> {
+    let parser = PhpParser::new();
+    parser.parse(content)
+}
+
+/// Parse a C file and extract symbols
+pub fn parse_c(content: &str) -> Result<ParsedFile> {
+    let parser = CParser::new();
+    parser.parse(content)
+}
+
+/// Parse a C++ file and extract symbols
+pub fn parse_cpp(content: &str) -> Result<ParsedFile> {
+    let parser = CppParser::new();
+    parser.parse(content)
+}
+
+/// Parse a CSS/SCSS stylesheet and extract design tokens
+pub fn parse_stylesheet(content: &str, language: &str) -> Result<ParsedFile> {
+    let parser = StylesheetParser::new();
+    parser.parse(content, language)
+}
+
+/// Parse a `tailwind.config.*` file and extract theme design tokens
+pub fn parse_tailwind_config(content: &str) -> Result<ParsedFile> {
+    let parser = StylesheetParser::new();
+    parser.parse_tailwind_config(content)
+}
+
+/// Parse a Terraform `.tf` or `.tfvars` file into resource schemas and constants
+pub fn parse_terraform(content: &str) -> Result<ParsedFile> {
+    let parser = TerraformParser::new();
+    parser.parse(content)
+}
+
+/// Parse a Dockerfile or Compose file into image/port/env constants
+pub fn parse_container_config(file_name: &str, content: &str) -> Result<ParsedFile> {
+    Ok(ContainerConfig::parse(file_name, content)?.to_parsed_file(content))
+}
+
+/// Parse a package manifest (`package.json`, `Cargo.toml`, `pyproject.toml`,
+/// `requirements.txt`, `Gemfile`, `composer.json`) into dependency/script/workspace constants.
+/// `path` locates a Cargo member's workspace root, whose versions replace `workspace = true`.
+pub fn parse_manifest(path: &std::path::Path, content: &str) -> Result<ParsedFile> {
+    Ok(Manifest::parse_at(path, content)?.to_parsed_file(content))
+}
+
+/// Parse a `.sql` migration and extract table schemas
+pub fn parse_sql(content: &str) -> Result<ParsedFile> {
+    let parser = DatabaseSchemaParser::new();
+    parser.parse_sql(content)
+}
+
+/// Parse a Prisma schema file and extract its models
+pub fn parse_prisma(content: &str) -> Result<ParsedFile> {
+    let parser = DatabaseSchemaParser::new();
+    parser.parse_prisma(content)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
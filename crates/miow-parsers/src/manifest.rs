@@ -0,0 +1,611 @@
+use crate::types::*;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Which package manifest a file is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ManifestKind {
+    PackageJson,
+    CargoToml,
+    Pyproject,
+    Requirements,
+    Gemfile,
+    ComposerJson,
+}
+
+impl ManifestKind {
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        match file_name {
+            "package.json" => Some(ManifestKind::PackageJson),
+            "Cargo.toml" => Some(ManifestKind::CargoToml),
+            "pyproject.toml" => Some(ManifestKind::Pyproject),
+            "requirements.txt" | "requirements-dev.txt" | "dev-requirements.txt" => Some(ManifestKind::Requirements),
+            "Gemfile" => Some(ManifestKind::Gemfile),
+            "composer.json" => Some(ManifestKind::ComposerJson),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a file name is a package manifest we extract metadata from
+pub fn is_manifest(file_name: &str) -> bool {
+    ManifestKind::from_file_name(file_name).is_some()
+}
+
+/// Stands in for a Cargo version inherited with `workspace = true` until the
+/// workspace root resolves it
+const WORKSPACE_PLACEHOLDER: &str = "workspace";
+
+/// Dependencies, scripts and workspace layout declared by a package manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub kind: ManifestKind,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub dependencies: BTreeMap<String, String>,
+    pub dev_dependencies: BTreeMap<String, String>,
+    pub scripts: BTreeMap<String, String>,
+    pub workspace_members: Vec<String>,
+}
+
+impl Manifest {
+    fn empty(kind: ManifestKind) -> Self {
+        Self {
+            kind,
+            name: None,
+            version: None,
+            dependencies: BTreeMap::new(),
+            dev_dependencies: BTreeMap::new(),
+            scripts: BTreeMap::new(),
+            workspace_members: vec![],
+        }
+    }
+
+    /// Parse a manifest; `file_name` selects the format
+    pub fn parse(file_name: &str, content: &str) -> Result<Self> {
+        let kind = ManifestKind::from_file_name(file_name)
+            .with_context(|| format!("Not a known manifest: {}", file_name))?;
+
+        match kind {
+            ManifestKind::PackageJson => {
+                let value: Value = serde_json::from_str(content).context("Failed to parse package.json")?;
+                Ok(Self::from_package_json(&value))
+            }
+            ManifestKind::CargoToml => {
+                let value: toml::Value = toml::from_str(content).context("Failed to parse Cargo.toml")?;
+                Ok(Self::from_cargo_toml(&value))
+            }
+            ManifestKind::Pyproject => {
+                let value: toml::Value = toml::from_str(content).context("Failed to parse pyproject.toml")?;
+                Ok(Self::from_pyproject(&value))
+            }
+            ManifestKind::Requirements => Ok(Self::from_requirements(content)),
+            ManifestKind::Gemfile => Ok(Self::from_gemfile(content)),
+            ManifestKind::ComposerJson => {
+                let value: Value = serde_json::from_str(content).context("Failed to parse composer.json")?;
+                Ok(Self::from_composer_json(&value))
+            }
+        }
+    }
+
+    /// Parse the manifest at `path`. A Cargo member's `workspace = true` versions
+    /// are resolved from the nearest ancestor `Cargo.toml` with a `[workspace]`.
+    pub fn parse_at(path: &Path, content: &str) -> Result<Self> {
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let mut manifest = Self::parse(file_name, content)?;
+
+        if manifest.kind == ManifestKind::CargoToml && manifest.inherits_from_workspace() {
+            let root = path
+                .parent()
+                .into_iter()
+                .flat_map(Path::ancestors)
+                .skip(1)
+                .filter_map(|dir| std::fs::read_to_string(dir.join("Cargo.toml")).ok())
+                .filter_map(|content| toml::from_str::<toml::Value>(&content).ok())
+                .find(|value| value.get("workspace").is_some());
+            if let Some(root) = root {
+                manifest.resolve_cargo_workspace(&root);
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Read and parse the manifest at `path`, see [`Self::parse_at`]
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse_at(path, &content)
+    }
+
+    fn inherits_from_workspace(&self) -> bool {
+        self.version.as_deref() == Some(WORKSPACE_PLACEHOLDER)
+            || self.dependencies.values().chain(self.dev_dependencies.values()).any(|v| v == WORKSPACE_PLACEHOLDER)
+    }
+
+    /// Replace `workspace = true` versions with `[workspace.package]` and
+    /// `[workspace.dependencies]` of the workspace root `root`
+    pub fn resolve_cargo_workspace(&mut self, root: &toml::Value) {
+        let Some(workspace) = root.get("workspace") else {
+            return;
+        };
+        if self.version.as_deref() == Some(WORKSPACE_PLACEHOLDER) {
+            self.version = workspace
+                .get("package")
+                .and_then(|p| p.get("version"))
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+        }
+
+        let versions = cargo_dependencies(workspace.get("dependencies"));
+        for (name, version) in self.dependencies.iter_mut().chain(self.dev_dependencies.iter_mut()) {
+            if version == WORKSPACE_PLACEHOLDER {
+                if let Some(resolved) = versions.get(name) {
+                    *version = resolved.clone();
+                }
+            }
+        }
+    }
+
+    pub fn from_package_json(value: &Value) -> Self {
+        let mut manifest = Self::empty(ManifestKind::PackageJson);
+        manifest.name = value["name"].as_str().map(str::to_string);
+        manifest.version = value["version"].as_str().map(str::to_string);
+
+        let string_map = |v: &Value| -> BTreeMap<String, String> {
+            v.as_object()
+                .map(|o| {
+                    o.iter()
+                        .map(|(k, v)| (k.clone(), v.as_str().unwrap_or("").to_string()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        manifest.dependencies = string_map(&value["dependencies"]);
+        manifest.dependencies.extend(string_map(&value["peerDependencies"]));
+        manifest.dev_dependencies = string_map(&value["devDependencies"]);
+        manifest.scripts = string_map(&value["scripts"]);
+
+        // `workspaces` is either an array or `{ packages: [...] }` (yarn)
+        let workspaces = match &value["workspaces"] {
+            Value::Array(items) => items.clone(),
+            other => other["packages"].as_array().cloned().unwrap_or_default(),
+        };
+        manifest.workspace_members = workspaces
+            .iter()
+            .filter_map(|w| w.as_str().map(str::to_string))
+            .collect();
+
+        manifest
+    }
+
+    pub fn from_cargo_toml(value: &toml::Value) -> Self {
+        let mut manifest = Self::empty(ManifestKind::CargoToml);
+        manifest.name = value
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str())
+            .map(str::to_string);
+        manifest.version = match value.get("package").and_then(|p| p.get("version")) {
+            Some(toml::Value::String(version)) => Some(version.clone()),
+            Some(toml::Value::Table(t)) if t.contains_key("workspace") => Some(WORKSPACE_PLACEHOLDER.to_string()),
+            _ => None,
+        };
+
+        manifest.dependencies = cargo_dependencies(value.get("dependencies"));
+        manifest.dev_dependencies = cargo_dependencies(value.get("dev-dependencies"));
+
+        if let Some(workspace) = value.get("workspace") {
+            // A workspace root resolves its own `workspace = true` entries, and its
+            // shared dependencies count as its dependencies too
+            manifest.resolve_cargo_workspace(value);
+            for (name, version) in cargo_dependencies(workspace.get("dependencies")) {
+                manifest.dependencies.entry(name).or_insert(version);
+            }
+            manifest.workspace_members = string_list(workspace.get("members"));
+        }
+
+        manifest
+    }
+
+    pub fn from_pyproject(value: &toml::Value) -> Self {
+        let mut manifest = Self::empty(ManifestKind::Pyproject);
+        let project = value.get("project");
+        let poetry = value.get("tool").and_then(|t| t.get("poetry"));
+
+        let field = |key: &str| {
+            project
+                .and_then(|p| p.get(key))
+                .or_else(|| poetry.and_then(|p| p.get(key)))
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+        manifest.name = field("name");
+        manifest.version = field("version");
+
+        // PEP 621: `dependencies = ["fastapi>=0.100", ...]`
+        for requirement in string_list(project.and_then(|p| p.get("dependencies"))) {
+            let (name, version) = split_requirement(&requirement);
+            manifest.dependencies.insert(name, version);
+        }
+        if let Some(groups) = project
+            .and_then(|p| p.get("optional-dependencies"))
+            .and_then(|g| g.as_table())
+        {
+            for requirement in groups.values().flat_map(|g| string_list(Some(g))) {
+                let (name, version) = split_requirement(&requirement);
+                manifest.dev_dependencies.insert(name, version);
+            }
+        }
+        if let Some(scripts) = project.and_then(|p| p.get("scripts")).and_then(|s| s.as_table()) {
+            for (name, target) in scripts {
+                manifest.scripts.insert(name.clone(), target.as_str().unwrap_or("").to_string());
+            }
+        }
+
+        // Poetry keeps dependencies as tables
+        if let Some(poetry) = poetry {
+            for (name, version) in cargo_dependencies(poetry.get("dependencies")) {
+                if name != "python" {
+                    manifest.dependencies.insert(name, version);
+                }
+            }
+            manifest
+                .dev_dependencies
+                .extend(cargo_dependencies(poetry.get("dev-dependencies")));
+            if let Some(groups) = poetry.get("group").and_then(|g| g.as_table()) {
+                for group in groups.values() {
+                    manifest
+                        .dev_dependencies
+                        .extend(cargo_dependencies(group.get("dependencies")));
+                }
+            }
+            if let Some(scripts) = poetry.get("scripts").and_then(|s| s.as_table()) {
+                for (name, target) in scripts {
+                    manifest.scripts.insert(name.clone(), target.as_str().unwrap_or("").to_string());
+                }
+            }
+        }
+
+        manifest.workspace_members = string_list(
+            value
+                .get("tool")
+                .and_then(|t| t.get("uv"))
+                .and_then(|u| u.get("workspace"))
+                .and_then(|w| w.get("members")),
+        );
+
+        manifest
+    }
+
+    pub fn from_requirements(content: &str) -> Self {
+        let mut manifest = Self::empty(ManifestKind::Requirements);
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() || line.starts_with('-') {
+                continue;
+            }
+            let (name, version) = split_requirement(line);
+            manifest.dependencies.insert(name, version);
+        }
+        manifest
+    }
+
+    /// `gem "rails", "~> 7.1"` lines; gems in `group :development, :test do`
+    /// blocks or with `group: :test` are dev dependencies
+    pub fn from_gemfile(content: &str) -> Self {
+        let mut manifest = Self::empty(ManifestKind::Gemfile);
+        let gem = Regex::new(r#"^gem\s*\(?\s*['"]([^'"]+)['"]((?:\s*,\s*['"][^'"]*['"])*)(.*)$"#).unwrap();
+        let quoted = Regex::new(r#"['"]([^'"]*)['"]"#).unwrap();
+        let is_dev_group = |groups: &str| !groups.contains(":production") && !groups.contains(":default");
+        // One entry per open `do` block: whether it's a dev-only group
+        let mut blocks: Vec<bool> = Vec::new();
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line == "end" {
+                blocks.pop();
+                continue;
+            }
+            if line.ends_with(" do") || line.contains(" do |") {
+                blocks.push(line.starts_with("group") && is_dev_group(line));
+                continue;
+            }
+            let Some(caps) = gem.captures(line) else {
+                continue;
+            };
+
+            let version = quoted
+                .captures_iter(&caps[2])
+                .map(|c| c[1].to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let options = &caps[3];
+            let dev = blocks.iter().any(|dev| *dev)
+                || ((options.contains("group:") || options.contains(":group =>")) && is_dev_group(options));
+            let target = if dev { &mut manifest.dev_dependencies } else { &mut manifest.dependencies };
+            target.insert(caps[1].to_string(), version);
+        }
+        manifest
+    }
+
+    /// `require`/`require-dev` packages, leaving out platform requirements
+    /// (`php`, `ext-*`); a script is a command or a list of them
+    pub fn from_composer_json(value: &Value) -> Self {
+        let mut manifest = Self::empty(ManifestKind::ComposerJson);
+        manifest.name = value["name"].as_str().map(str::to_string);
+        manifest.version = value["version"].as_str().map(str::to_string);
+
+        let packages = |v: &Value| -> BTreeMap<String, String> {
+            v.as_object()
+                .map(|o| {
+                    o.iter()
+                        .filter(|(k, _)| k.contains('/'))
+                        .map(|(k, v)| (k.clone(), v.as_str().unwrap_or("").to_string()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        manifest.dependencies = packages(&value["require"]);
+        manifest.dev_dependencies = packages(&value["require-dev"]);
+        if let Some(scripts) = value["scripts"].as_object() {
+            for (name, script) in scripts {
+                let command = match script {
+                    Value::Array(commands) => commands.iter().filter_map(|c| c.as_str()).collect::<Vec<_>>().join(" && "),
+                    other => other.as_str().unwrap_or("").to_string(),
+                };
+                manifest.scripts.insert(name.clone(), command);
+            }
+        }
+        manifest
+    }
+
+    /// Record the manifest as graph constants: `dependencies.react = ^18.2.0`,
+    /// `scripts.build = next build`, `workspace.members = crates/*`
+    pub fn to_parsed_file(&self, content: &str) -> ParsedFile {
+        let mut constants = Vec::new();
+        let mut push = |name: String, value: String, kind: &str, needle: &str| {
+            constants.push(Constant {
+                range: line_range(content, needle),
+                name,
+                value,
+                type_annotation: Some(kind.to_string()),
+                category: ConstantCategory::Config,
+            });
+        };
+
+        if let Some(name) = &self.name {
+            let version = self.version.clone().unwrap_or_default();
+            push(format!("package.{}", name), version, "package", name);
+        }
+        for (name, version) in &self.dependencies {
+            push(format!("dependencies.{}", name), version.clone(), "dependency", name);
+        }
+        for (name, version) in &self.dev_dependencies {
+            push(format!("devDependencies.{}", name), version.clone(), "dev-dependency", name);
+        }
+        for (name, command) in &self.scripts {
+            push(format!("scripts.{}", name), command.clone(), "script", name);
+        }
+        if !self.workspace_members.is_empty() {
+            let first = self.workspace_members[0].clone();
+            push(
+                "workspace.members".to_string(),
+                self.workspace_members.join(", "),
+                "workspace",
+                &first,
+            );
+        }
+
+        ParsedFile {
+            symbols: vec![],
+            imports: vec![],
+            exports: vec![],
+            design_tokens: vec![],
+            type_definitions: vec![],
+            constants,
+            schemas: vec![],
+            language: "manifest".to_string(),
+            syntax_errors: vec![],
+        }
+    }
+}
+
+/// `[dependencies]`-style tables: `name = "1.0"` or `name = { version = "1.0", ... }`
+fn cargo_dependencies(table: Option<&toml::Value>) -> BTreeMap<String, String> {
+    let Some(table) = table.and_then(|t| t.as_table()) else {
+        return BTreeMap::new();
+    };
+
+    table
+        .iter()
+        .map(|(name, spec)| {
+            let version = match spec {
+                toml::Value::String(v) => v.clone(),
+                toml::Value::Table(t) => t
+                    .get("version")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .or_else(|| t.get("path").and_then(|p| p.as_str()).map(|p| format!("path:{}", p)))
+                    .or_else(|| t.get("git").and_then(|g| g.as_str()).map(|g| format!("git:{}", g)))
+                    .or_else(|| t.get("workspace").map(|_| WORKSPACE_PLACEHOLDER.to_string()))
+                    .unwrap_or_default(),
+                _ => String::new(),
+            };
+            (name.clone(), version)
+        })
+        .collect()
+}
+
+fn string_list(value: Option<&toml::Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|items| items.iter().filter_map(|i| i.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+/// `fastapi[all]>=0.100; python_version > "3.8"` -> (`fastapi`, `>=0.100`)
+fn split_requirement(requirement: &str) -> (String, String) {
+    let requirement = requirement.split(';').next().unwrap_or(requirement).trim();
+    let name_end = requirement
+        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .unwrap_or(requirement.len());
+    let name = requirement[..name_end].to_lowercase();
+
+    let rest = requirement[name_end..].trim_start();
+    let rest = match rest.strip_prefix('[') {
+        Some(extras) => extras.split_once(']').map(|(_, r)| r).unwrap_or(""),
+        None => rest,
+    };
+    (name, rest.trim().to_string())
+}
+
+/// Line of the key: prefer the quoted JSON form, then a TOML `key =`, then any mention
+pub(crate) fn line_range(content: &str, needle: &str) -> Range {
+    let (line, start_byte) = [format!("\"{}\"", needle), format!("{} =", needle), needle.to_string()]
+        .iter()
+        .find_map(|candidate| content.find(candidate.as_str()))
+        .map(|i| (content[..i].matches('\n').count() + 1, i))
+        .unwrap_or((1, 0));
+    Range {
+        start_line: line,
+        end_line: line,
+        start_byte,
+        end_byte: start_byte + needle.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_json_and_cargo_workspace() {
+        let package_json = r#"{
+  "name": "web",
+  "version": "1.2.0",
+  "workspaces": { "packages": ["apps/*", "packages/*"] },
+  "scripts": { "build": "next build" },
+  "dependencies": { "next": "14.0.0", "zod": "^3.22.0" },
+  "devDependencies": { "vitest": "^1.0.0" }
+}"#;
+        let manifest = Manifest::parse("package.json", package_json).unwrap();
+        assert_eq!(manifest.dependencies.get("zod").map(String::as_str), Some("^3.22.0"));
+        assert_eq!(manifest.scripts.get("build").map(String::as_str), Some("next build"));
+        assert_eq!(manifest.workspace_members, vec!["apps/*", "packages/*"]);
+
+        let parsed = manifest.to_parsed_file(package_json);
+        let next = parsed.constants.iter().find(|c| c.name == "dependencies.next").unwrap();
+        assert_eq!(next.value, "14.0.0");
+        assert_eq!(next.range.start_line, 6);
+
+        let cargo = r#"
+[workspace]
+members = ["crates/*"]
+
+[workspace.dependencies]
+tokio = { version = "1", features = ["full"] }
+
+[workspace.package]
+version = "0.3.0"
+
+[package]
+name = "app"
+version.workspace = true
+
+[dependencies]
+anyhow = "1.0"
+tokio = { workspace = true }
+core = { path = "crates/core" }
+"#;
+        let manifest = Manifest::parse("Cargo.toml", cargo).unwrap();
+        assert_eq!(manifest.name.as_deref(), Some("app"));
+        assert_eq!(manifest.version.as_deref(), Some("0.3.0"));
+        assert_eq!(manifest.dependencies.get("tokio").map(String::as_str), Some("1"));
+        assert_eq!(manifest.dependencies.get("core").map(String::as_str), Some("path:crates/core"));
+        assert_eq!(manifest.workspace_members, vec!["crates/*"]);
+    }
+
+    #[test]
+    fn test_cargo_member_inherits_from_workspace_root() {
+        let root = tempfile::tempdir().unwrap();
+        let member = root.path().join("crates").join("core");
+        std::fs::create_dir_all(&member).unwrap();
+        std::fs::write(
+            root.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nversion = \"2.1.0\"\n\n[workspace.dependencies]\nserde = \"1.0.190\"\n",
+        )
+        .unwrap();
+        let member_toml = "[package]\nname = \"core\"\nversion = { workspace = true }\n\n[dependencies]\nserde = { workspace = true }\n";
+        std::fs::write(member.join("Cargo.toml"), member_toml).unwrap();
+
+        let manifest = Manifest::read(&member.join("Cargo.toml")).unwrap();
+        assert_eq!(manifest.version.as_deref(), Some("2.1.0"));
+        assert_eq!(manifest.dependencies.get("serde").map(String::as_str), Some("1.0.190"));
+
+        // Without the workspace root the placeholder is all there is
+        let unresolved = Manifest::parse("Cargo.toml", member_toml).unwrap();
+        assert_eq!(unresolved.version.as_deref(), Some("workspace"));
+    }
+
+    #[test]
+    fn test_pyproject_and_requirements() {
+        let pyproject = r#"
+[project]
+name = "api"
+dependencies = ["fastapi[all]>=0.100", "SQLAlchemy==2.0.0; python_version > '3.8'"]
+
+[project.optional-dependencies]
+dev = ["pytest"]
+"#;
+        let manifest = Manifest::parse("pyproject.toml", pyproject).unwrap();
+        assert_eq!(manifest.dependencies.get("fastapi").map(String::as_str), Some(">=0.100"));
+        assert_eq!(manifest.dependencies.get("sqlalchemy").map(String::as_str), Some("==2.0.0"));
+        assert!(manifest.dev_dependencies.contains_key("pytest"));
+
+        let requirements = Manifest::parse("requirements.txt", "# web\nflask==3.0\n-r base.txt\n").unwrap();
+        assert_eq!(requirements.dependencies.len(), 1);
+        assert_eq!(requirements.dependencies.get("flask").map(String::as_str), Some("==3.0"));
+    }
+
+    #[test]
+    fn test_gemfile() {
+        let gemfile = r#"source "https://rubygems.org"
+ruby "3.3.0"
+
+gem "rails", "~> 7.1", ">= 7.1.2"
+gem 'pg' # database
+gem "debug", platforms: %i[mri windows], group: :development
+
+group :development, :test do
+  gem "rspec-rails"
+end
+"#;
+        let manifest = Manifest::parse("Gemfile", gemfile).unwrap();
+        assert_eq!(manifest.dependencies.get("rails").map(String::as_str), Some("~> 7.1, >= 7.1.2"));
+        assert_eq!(manifest.dependencies.get("pg").map(String::as_str), Some(""));
+        assert!(manifest.dev_dependencies.contains_key("debug"));
+        assert!(manifest.dev_dependencies.contains_key("rspec-rails"));
+        assert_eq!(manifest.dependencies.len(), 2);
+    }
+
+    #[test]
+    fn test_composer_json() {
+        let composer = r#"{
+            "name": "acme/shop",
+            "require": {"php": "^8.2", "ext-json": "*", "laravel/framework": "^11.0", "laravel/sanctum": "^4.0"},
+            "require-dev": {"phpunit/phpunit": "^11.0"},
+            "scripts": {"test": "phpunit", "post-update-cmd": ["@php artisan vendor:publish", "@php artisan optimize"]}
+        }"#;
+        let manifest = Manifest::parse("composer.json", composer).unwrap();
+        assert_eq!(manifest.name.as_deref(), Some("acme/shop"));
+        assert_eq!(manifest.dependencies.keys().collect::<Vec<_>>(), vec!["laravel/framework", "laravel/sanctum"]);
+        assert!(manifest.dev_dependencies.contains_key("phpunit/phpunit"));
+        assert_eq!(
+            manifest.scripts.get("post-update-cmd").map(String::as_str),
+            Some("@php artisan vendor:publish && @php artisan optimize")
+        );
+    }
+}
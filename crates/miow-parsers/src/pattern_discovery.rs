@@ -1,8 +1,6 @@
-use crate::types::*;
 use anyhow::{Context, Result};
 use miow_llm::LLMProvider;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
 
@@ -121,7 +119,6 @@ impl PatternDiscovery {
                 samples.push(FileSample {
                     path: path.to_string_lossy().to_string(),
                     content,
-                    language: extension.unwrap_or("unknown").to_string(),
                 });
                 
                 file_count += 1;
@@ -324,7 +321,6 @@ Focus on patterns that appear in multiple files. Ignore one-off code."#,
 struct FileSample {
     path: String,
     content: String,
-    language: String,
 }
 
 #[cfg(test)]
@@ -353,7 +349,7 @@ mod tests {
             metadata_rules: vec![],
         };
         
-        let patterns = vec![pattern1.clone(), pattern2];
+        let patterns = [pattern1.clone(), pattern2];
         let filtered: Vec<_> = patterns
             .iter()
             .filter(|p| p.confidence >= 0.7)
@@ -0,0 +1,1000 @@
+use crate::types::*;
+use anyhow::Result;
+use regex::Regex;
+
+/// Laravel base classes and the tag attached to classes extending them
+const SUPERCLASS_TAGS: &[(&str, &str)] = &[
+    ("Model", "laravel-model"),
+    ("Pivot", "laravel-model"),
+    ("Authenticatable", "laravel-model"),
+    ("Controller", "laravel-controller"),
+    ("FormRequest", "laravel-form-request"),
+    ("Mailable", "laravel-mailable"),
+    ("ServiceProvider", "laravel-provider"),
+    ("Migration", "laravel-migration"),
+    ("Command", "laravel-command"),
+    ("Seeder", "laravel-seeder"),
+    ("Factory", "laravel-factory"),
+];
+
+/// Interfaces that mark a Laravel class, and the tag they attach
+const INTERFACE_TAGS: &[(&str, &str)] = &[("ShouldQueue", "laravel-job")];
+
+/// Eloquent relationship methods
+const RELATIONS: &[&str] = &[
+    "hasOne",
+    "hasMany",
+    "belongsTo",
+    "belongsToMany",
+    "hasOneThrough",
+    "hasManyThrough",
+    "morphTo",
+    "morphOne",
+    "morphMany",
+    "morphToMany",
+    "morphedByMany",
+];
+
+const MODIFIERS: &[&str] = &["public", "protected", "private", "static", "abstract", "final", "readonly", "var"];
+
+/// Extracts classes, interfaces, traits, enums, functions, `use` imports and
+/// constants from PHP, plus the fillable attributes, casts and relationships
+/// of Eloquent models. Comments, strings and inline HTML are blanked first,
+/// so declarations are found by splitting the file into statements on `;`
+/// and balanced braces.
+pub struct PhpParser {
+    class_like: Regex,
+    function: Regex,
+    property: Regex,
+    constant: Regex,
+    define: Regex,
+    enum_case: Regex,
+    relation: Regex,
+    array_entry: Regex,
+}
+
+impl PhpParser {
+    pub fn new() -> Self {
+        Self {
+            class_like: Regex::new(r"^(class|interface|trait|enum)\s+(\w+)").unwrap(),
+            function: Regex::new(r"^function\s+&?(\w+)\s*\(").unwrap(),
+            property: Regex::new(r"^(?:(\??[\w\\|]+)\s+)?\$(\w+)\s*(?:=\s*([\s\S]*))?$").unwrap(),
+            constant: Regex::new(r"^const\s+(?:[\w\\?]+\s+)?(\w+)\s*=\s*([\s\S]*)$").unwrap(),
+            define: Regex::new(r#"^define\s*\(\s*['"](\w+)['"]\s*,\s*([\s\S]*)\)$"#).unwrap(),
+            enum_case: Regex::new(r"^case\s+(\w+)").unwrap(),
+            relation: Regex::new(&format!(r"\$this\s*->\s*({})\s*\(\s*\\?([\w\\]+)::class", RELATIONS.join("|"))).unwrap(),
+            array_entry: Regex::new(r#"['"](\w+)['"]\s*(?:=>\s*(?:['"]([^'"]+)['"]|\\?([\w\\]+)::class))?"#).unwrap(),
+        }
+    }
+
+    pub fn parse(&self, content: &str) -> Result<ParsedFile> {
+        let source = Source::new(content);
+        let mut parsed = ParsedFile {
+            symbols: vec![],
+            imports: vec![],
+            exports: vec![], // Visibility is carried by access_modifier instead
+            design_tokens: vec![],
+            type_definitions: vec![],
+            constants: vec![],
+            schemas: vec![],
+            language: "php".to_string(),
+            syntax_errors: vec![],
+        };
+        self.extract_top_level(&source, 0, content.len(), &mut parsed);
+        Ok(parsed)
+    }
+
+    /// Declarations between `start` and `end`: the file, or a braced `namespace { ... }`
+    fn extract_top_level(&self, source: &Source, start: usize, end: usize, parsed: &mut ParsedFile) {
+        for statement in source.statements(start, end) {
+            let head = statement.head(source);
+
+            if let Some(rest) = head.strip_prefix("namespace") {
+                if let Some(open) = statement.body_start(source).filter(|_| rest.starts_with(char::is_whitespace)) {
+                    self.extract_top_level(source, open + 1, statement.end - 1, parsed);
+                }
+            } else if head.starts_with("use ") {
+                let raw = source.raw(statement.head_start, statement.end);
+                let raw = raw.trim_start_matches("use").trim().trim_end_matches(';');
+                parsed.imports.extend(imports(raw, source.range(statement.head_start, statement.end)));
+            } else if self.class_like.is_match(head) {
+                if let Some(symbol) = self.class_like_symbol(source, &statement, parsed) {
+                    parsed.symbols.push(symbol);
+                }
+            } else if let Some(caps) = self.function.captures(head) {
+                parsed.symbols.push(self.function_symbol(source, &statement, &caps[1], SymbolType::Function));
+            } else if let Some(caps) = self.constant.captures(&source.raw_head(&statement)) {
+                parsed.constants.push(Constant {
+                    name: caps[1].to_string(),
+                    value: caps[2].trim().trim_end_matches(';').trim().to_string(),
+                    type_annotation: None,
+                    category: ConstantCategory::Config,
+                    range: source.range(statement.head_start, statement.end),
+                });
+            } else if let Some(caps) = self.define.captures(source.raw_head(&statement).trim_end_matches(';').trim()) {
+                parsed.constants.push(Constant {
+                    name: caps[1].to_string(),
+                    value: caps[2].trim().to_string(),
+                    type_annotation: None,
+                    category: ConstantCategory::Config,
+                    range: source.range(statement.head_start, statement.end),
+                });
+            }
+        }
+    }
+
+    /// A class, interface, trait or enum with its members
+    fn class_like_symbol(&self, source: &Source, statement: &Statement, parsed: &mut ParsedFile) -> Option<Symbol> {
+        let head = statement.head(source);
+        let caps = self.class_like.captures(head)?;
+        let open = statement.body_start(source)?;
+        let (keyword, name) = (caps[1].to_string(), caps[2].to_string());
+        let kind = match keyword.as_str() {
+            "interface" => SymbolType::Interface,
+            "enum" => SymbolType::Enum,
+            "trait" => SymbolType::Module,
+            _ => SymbolType::Class,
+        };
+
+        let mut metadata = self.declaration_metadata(source, statement);
+        // `class A extends B implements C, D`; interfaces extend several
+        let header = &source.code[statement.head_start + caps.get(0).unwrap().end()..open];
+        let clause = |keyword: &str| -> Vec<String> {
+            let Some(start) = find_word(header, keyword) else {
+                return vec![];
+            };
+            let rest = &header[start + keyword.len()..];
+            let end = find_word(rest, "implements").unwrap_or(rest.len());
+            rest[..end].split(',').map(|t| t.trim().trim_start_matches('\\').to_string()).filter(|t| !t.is_empty()).collect()
+        };
+        metadata.extends = clause("extends");
+        metadata.implements = clause("implements");
+        if keyword == "enum" {
+            metadata.return_type = header.split_once(':').map(|(_, t)| t.trim().to_string()).filter(|t| !t.is_empty());
+        }
+        for class in &metadata.extends {
+            if let Some((_, tag)) = SUPERCLASS_TAGS.iter().find(|(base, _)| *base == short_name(class)) {
+                metadata.tags.push(tag.to_string());
+            }
+        }
+        for interface in &metadata.implements {
+            if let Some((_, tag)) = INTERFACE_TAGS.iter().find(|(base, _)| *base == short_name(interface)) {
+                metadata.tags.push(tag.to_string());
+            }
+        }
+
+        let mut symbol = Symbol {
+            name: name.clone(),
+            kind,
+            range: source.range(statement.head_start, statement.end),
+            content: source.raw(statement.head_start, statement.end).to_string(),
+            metadata,
+            children: vec![],
+            references: vec![],
+        };
+        let mut fields: Vec<SchemaField> = Vec::new();
+        self.extract_members(source, open + 1, statement.end - 1, &mut symbol, &mut fields, parsed);
+
+        if symbol.metadata.tags.iter().any(|t| t == "laravel-model") {
+            parsed.schemas.push(ValidationSchema {
+                name: name.clone(),
+                schema_type: SchemaType::Eloquent,
+                definition: symbol.content.clone(),
+                fields,
+                range: symbol.range.clone(),
+            });
+        }
+        if matches!(symbol.kind, SymbolType::Interface | SymbolType::Enum) {
+            parsed.type_definitions.push(type_definition(&symbol));
+        }
+        Some(symbol)
+    }
+
+    /// Methods, properties, constants, trait uses and enum cases of a class body
+    fn extract_members(
+        &self,
+        source: &Source,
+        start: usize,
+        end: usize,
+        owner: &mut Symbol,
+        fields: &mut Vec<SchemaField>,
+        parsed: &mut ParsedFile,
+    ) {
+        let is_model = owner.metadata.tags.iter().any(|t| t == "laravel-model");
+
+        for statement in source.statements(start, end) {
+            let head = statement.head(source);
+            if let Some(traits) = head.strip_prefix("use ") {
+                let traits = traits.split(['{', ';']).next().unwrap_or("");
+                owner.metadata.implements.extend(traits.split(',').map(|t| t.trim().trim_start_matches('\\').to_string()));
+                continue;
+            }
+            if let Some(caps) = self.enum_case.captures(head) {
+                owner.children.push(Symbol {
+                    name: caps[1].to_string(),
+                    kind: SymbolType::EnumMember,
+                    range: source.range(statement.head_start, statement.end),
+                    content: source.raw(statement.head_start, statement.end).to_string(),
+                    metadata: SymbolMetadata::default(),
+                    children: vec![],
+                    references: vec![],
+                });
+                continue;
+            }
+
+            let mut metadata = self.declaration_metadata(source, &statement);
+            let declaration = strip_modifiers(head);
+            if let Some(caps) = self.function.captures(declaration) {
+                let name = caps[1].to_string();
+                let kind = if name == "__construct" { SymbolType::Constructor } else { SymbolType::Method };
+                let method = self.function_symbol(source, &statement, &name, kind);
+
+                // Promoted constructor parameters are properties too
+                if method.kind == SymbolType::Constructor {
+                    for param in method.metadata.parameters.iter().filter(|p| p.name.contains(' ')) {
+                        let (visibility, name) = param.name.rsplit_once(' ').unwrap_or(("", &param.name));
+                        owner.children.push(Symbol {
+                            name: name.trim_start_matches('$').to_string(),
+                            kind: SymbolType::Field,
+                            range: method.range.clone(),
+                            content: param.name.clone(),
+                            metadata: SymbolMetadata {
+                                access_modifier: visibility.split_whitespace().find(|m| is_visibility(m)).map(str::to_string),
+                                is_readonly: visibility.contains("readonly"),
+                                return_type: param.type_annotation.clone(),
+                                ..Default::default()
+                            },
+                            children: vec![],
+                            references: vec![],
+                        });
+                    }
+                }
+                if is_model {
+                    let body = source.raw(statement.head_start, statement.end);
+                    if let Some(caps) = self.relation.captures(body) {
+                        merge_field(fields, &name, |field| {
+                            field.type_annotation = Some(caps[1].to_string());
+                            field.references = Some(short_name(&caps[2]).to_string());
+                        });
+                    }
+                    // Laravel 11 declares casts in a `casts()` method
+                    if name == "casts" {
+                        self.model_property(&name, body, fields);
+                    }
+                }
+                owner.children.push(method);
+                continue;
+            }
+
+            let raw = strip_modifiers(&source.raw_head(&statement)).to_string();
+            let owner_name = owner.name.clone();
+            if let Some(caps) = self.constant.captures(&raw) {
+                let value = caps[2].trim().trim_end_matches(';').trim().to_string();
+                metadata.is_static = true;
+                metadata.is_readonly = true;
+                owner.children.push(Symbol {
+                    name: caps[1].to_string(),
+                    kind: SymbolType::Field,
+                    range: source.range(statement.head_start, statement.end),
+                    content: raw.clone(),
+                    metadata,
+                    children: vec![],
+                    references: vec![],
+                });
+                parsed.constants.push(Constant {
+                    name: format!("{}::{}", owner_name, &caps[1]),
+                    value,
+                    type_annotation: None,
+                    category: ConstantCategory::Config,
+                    range: source.range(statement.head_start, statement.end),
+                });
+            } else if let Some(caps) = self.property.captures(raw.trim_end_matches(';').trim()) {
+                metadata.return_type = caps.get(1).map(|t| t.as_str().to_string());
+                if is_model {
+                    self.model_property(&caps[2], &raw, fields);
+                }
+                owner.children.push(Symbol {
+                    name: caps[2].to_string(),
+                    kind: SymbolType::Field,
+                    range: source.range(statement.head_start, statement.end),
+                    content: raw.clone(),
+                    metadata,
+                    children: vec![],
+                    references: vec![],
+                });
+            }
+        }
+    }
+
+    /// `$fillable`, `$hidden` and `$casts` of an Eloquent model, as schema fields
+    fn model_property(&self, property: &str, declaration: &str, fields: &mut Vec<SchemaField>) {
+        let Some(array) = declaration.find(['[', '(']).map(|i| &declaration[i..]) else {
+            return;
+        };
+        for caps in self.array_entry.captures_iter(array) {
+            let name = &caps[1];
+            match property {
+                "fillable" | "hidden" | "guarded" => {
+                    // Only the keys of a list; `'a' => 'b'` pairs aren't attributes
+                    if caps.get(2).is_none() && caps.get(3).is_none() {
+                        merge_field(fields, name, |field| field.validation_rules.push(property.to_string()));
+                    }
+                }
+                "casts" => {
+                    let cast = caps.get(2).or(caps.get(3)).map(|c| short_name(c.as_str()).to_string());
+                    if let Some(cast) = cast {
+                        merge_field(fields, name, |field| field.type_annotation = Some(cast));
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// A function or method: signature, parameters, return type and body
+    fn function_symbol(&self, source: &Source, statement: &Statement, name: &str, kind: SymbolType) -> Symbol {
+        let mut metadata = self.declaration_metadata(source, statement);
+        let head = statement.head(source);
+        if let Some(open) = head.find('(') {
+            let params_start = statement.head_start + open + 1;
+            if let Some(close) = source.matching(params_start, b'(', b')') {
+                metadata.parameters = self.parameters(source.raw(params_start, close));
+                // `): ?User {` or `): void;`
+                let after = &source.code[close + 1..statement.end];
+                let return_type = after.trim_start().strip_prefix(':').map(|r| r.split(['{', ';']).next().unwrap_or("").trim());
+                metadata.return_type = return_type.filter(|r| !r.is_empty()).map(str::to_string);
+            }
+        }
+
+        Symbol {
+            name: name.to_string(),
+            kind,
+            range: source.range(statement.head_start, statement.end),
+            content: source.raw(statement.head_start, statement.end).to_string(),
+            metadata,
+            children: vec![],
+            references: vec![],
+        }
+    }
+
+    /// `int $a = 1, ?User ...$rest`; promoted parameters keep their modifiers
+    /// in the name: `private readonly $repository`
+    fn parameters(&self, params: &str) -> Vec<Parameter> {
+        split_top_level(params)
+            .into_iter()
+            .filter_map(|param| {
+                let param = strip_attributes(param);
+                let (declaration, default_value) = match param.split_once('=') {
+                    Some((d, v)) => (d.trim(), Some(v.trim().to_string())),
+                    None => (param.trim(), None),
+                };
+                let variable = declaration.rfind('$')?;
+                let words: Vec<&str> = declaration[..variable].split_whitespace().collect();
+                let modifiers: Vec<&str> = words.iter().copied().filter(|w| MODIFIERS.contains(w)).collect();
+                let type_annotation = words
+                    .iter()
+                    .copied()
+                    .filter(|w| !MODIFIERS.contains(w))
+                    .map(|w| w.trim_end_matches(['&', '.']))
+                    .find(|w| !w.is_empty())
+                    .map(str::to_string);
+                let variable = &declaration[variable..];
+                let name = if modifiers.is_empty() { variable.to_string() } else { format!("{} {}", modifiers.join(" "), variable) };
+
+                Some(Parameter {
+                    is_optional: default_value.is_some() || declaration.contains("..."),
+                    name,
+                    type_annotation,
+                    default_value,
+                })
+            })
+            .collect()
+    }
+
+    /// Modifiers, attributes and docblock of the declaration a statement starts with
+    fn declaration_metadata(&self, source: &Source, statement: &Statement) -> SymbolMetadata {
+        let mut metadata = SymbolMetadata::default();
+        for word in statement.head(source).split_whitespace().take_while(|w| MODIFIERS.contains(w)) {
+            match word {
+                "public" | "protected" | "private" => metadata.access_modifier = Some(word.to_string()),
+                "var" => metadata.access_modifier = Some("public".to_string()),
+                "static" => metadata.is_static = true,
+                "readonly" | "final" => metadata.is_readonly = true,
+                _ => {}
+            }
+        }
+
+        // Attributes sit between the docblock and the declaration
+        let prefix = source.raw(statement.start, statement.head_start);
+        let mut rest = prefix;
+        while let Some(open) = rest.find("#[") {
+            let Some(close) = matching_bracket(&rest[open + 1..]) else {
+                break;
+            };
+            metadata.decorators.push(rest[open..open + 1 + close + 1].to_string());
+            rest = &rest[open + close + 2..];
+        }
+        if let Some(doc) = prefix.rfind("/**") {
+            if let Some(end) = prefix[doc..].find("*/") {
+                metadata.documentation = Some(clean_docblock(&prefix[doc..doc + end + 2]));
+            }
+        }
+        metadata
+    }
+}
+
+impl Default for PhpParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The file alongside `code`, a copy with comments, strings and inline HTML
+/// blanked to spaces, byte for byte, so offsets into one hold in the other
+struct Source<'a> {
+    raw: &'a str,
+    code: String,
+    line_starts: Vec<usize>,
+}
+
+/// A statement, from the end of the previous one; the declaration itself
+/// starts at `head_start`, after whitespace, docblocks and attributes
+struct Statement {
+    start: usize,
+    head_start: usize,
+    end: usize,
+}
+
+impl Statement {
+    /// The statement's code, from its declaration on
+    fn head<'s>(&self, source: &'s Source) -> &'s str {
+        source.code[self.head_start..self.end].trim_end()
+    }
+
+    /// The `{` opening the statement's body, after its parameter list if any
+    fn body_start(&self, source: &Source) -> Option<usize> {
+        let code = &source.code.as_bytes()[self.head_start..self.end];
+        let mut depth = 0;
+        for (i, c) in code.iter().enumerate() {
+            match c {
+                b'(' | b'[' => depth += 1,
+                b')' | b']' => depth -= 1,
+                b'{' if depth == 0 => return Some(self.head_start + i),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Source<'a> {
+    fn new(raw: &'a str) -> Self {
+        let code = blank_php(raw, false);
+        let line_starts = std::iter::once(0).chain(raw.match_indices('\n').map(|(i, _)| i + 1)).collect();
+        Self { raw, code, line_starts }
+    }
+
+    fn raw(&self, start: usize, end: usize) -> &'a str {
+        &self.raw[start..end]
+    }
+
+    /// The raw text of a statement's declaration, without its trailing `;`
+    fn raw_head(&self, statement: &Statement) -> String {
+        self.raw(statement.head_start, statement.end).trim().trim_end_matches(';').trim().to_string()
+    }
+
+    fn range(&self, start: usize, end: usize) -> Range {
+        let line = |offset: usize| self.line_starts.partition_point(|&s| s <= offset);
+        Range {
+            start_line: line(start),
+            end_line: line(end.saturating_sub(1).max(start)),
+            start_byte: start,
+            end_byte: end,
+        }
+    }
+
+    /// Index of the bracket closing the one opened just before `start`
+    fn matching(&self, start: usize, open: u8, close: u8) -> Option<usize> {
+        let mut depth = 0;
+        for (i, &c) in self.code.as_bytes()[start..].iter().enumerate() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                if depth == 0 {
+                    return Some(start + i);
+                }
+                depth -= 1;
+            }
+        }
+        None
+    }
+
+    /// Split `start..end` into statements: each ends at a `;` outside
+    /// brackets, or at the `}` closing a body (`class A { }`, `function f() { }`)
+    /// unless the expression goes on (`$f = function () { };`)
+    fn statements(&self, start: usize, end: usize) -> Vec<Statement> {
+        let code = self.code.as_bytes();
+        let mut statements = Vec::new();
+        let (mut braces, mut brackets) = (0i32, 0i32);
+        let mut statement_start = start;
+        let mut head_start: Option<usize> = None;
+        let mut i = start;
+
+        while i < end {
+            let c = code[i];
+            // Attributes belong to the declaration's prefix, like docblocks
+            if head_start.is_none() && c == b'#' && code.get(i + 1) == Some(&b'[') {
+                i = match matching_bracket(&self.code[i + 1..end]) {
+                    Some(close) => i + 1 + close + 1,
+                    None => end,
+                };
+                continue;
+            }
+            if head_start.is_none() && !c.is_ascii_whitespace() {
+                head_start = Some(i);
+            }
+
+            let mut finished = false;
+            match c {
+                b'(' | b'[' => brackets += 1,
+                b')' | b']' => brackets -= 1,
+                b'{' => braces += 1,
+                b'}' => {
+                    braces -= 1;
+                    if braces == 0 && brackets == 0 {
+                        let next = self.code[i + 1..end].trim_start().bytes().next();
+                        finished = !matches!(next, Some(b';' | b')' | b',' | b'-' | b'.' | b'(' | b'?' | b':' | b'['));
+                    }
+                }
+                b';' if braces == 0 && brackets == 0 => finished = true,
+                _ => {}
+            }
+            i += 1;
+
+            if finished {
+                if let Some(head) = head_start.take() {
+                    statements.push(Statement { start: statement_start, head_start: head, end: i });
+                }
+                statement_start = i;
+            }
+        }
+        if let Some(head) = head_start {
+            statements.push(Statement { start: statement_start, head_start: head, end });
+        }
+        statements
+    }
+}
+
+/// `use App\Models\User;`, `use App\Models\{User, Post as Article};`, `use function App\helper;`
+fn imports(raw: &str, range: Range) -> Vec<Import> {
+    let raw = raw.strip_prefix("function ").or_else(|| raw.strip_prefix("const ")).unwrap_or(raw).trim();
+    let name = |spec: &str| {
+        let (path, alias) = match spec.split_once(" as ") {
+            Some((path, alias)) => (path.trim(), Some(alias.trim().to_string())),
+            None => (spec.trim(), None),
+        };
+        let (namespace, name) = path.trim_start_matches('\\').rsplit_once('\\').unwrap_or(("", path));
+        (namespace.to_string(), ImportName { name: name.to_string(), alias, is_default: false, is_namespace: false, is_type: false })
+    };
+
+    if let Some((prefix, group)) = raw.split_once('{') {
+        let prefix = prefix.trim().trim_end_matches('\\');
+        let names = group.trim_end_matches('}').split(',').filter(|s| !s.trim().is_empty()).map(|s| name(s).1).collect();
+        return vec![Import { source: prefix.trim_start_matches('\\').to_string(), names, range }];
+    }
+    raw.split(',')
+        .map(|spec| {
+            let (source, imported) = name(spec);
+            Import { source, names: vec![imported], range: range.clone() }
+        })
+        .collect()
+}
+
+fn type_definition(symbol: &Symbol) -> TypeDefinition {
+    TypeDefinition {
+        name: symbol.name.clone(),
+        kind: if symbol.kind == SymbolType::Enum { TypeKind::Enum } else { TypeKind::Interface },
+        definition: symbol.content.clone(),
+        properties: symbol
+            .children
+            .iter()
+            .map(|member| TypeProperty {
+                name: member.name.clone(),
+                type_annotation: match member.kind {
+                    SymbolType::Method => {
+                        let params: Vec<String> = member
+                            .metadata
+                            .parameters
+                            .iter()
+                            .map(|p| format!("{} {}", p.type_annotation.clone().unwrap_or_default(), p.name).trim().to_string())
+                            .collect();
+                        format!("({}) -> {}", params.join(", "), member.metadata.return_type.clone().unwrap_or_else(|| "mixed".to_string()))
+                    }
+                    SymbolType::EnumMember => symbol.name.clone(),
+                    _ => member.metadata.return_type.clone().unwrap_or_default(),
+                },
+                is_optional: false,
+                description: member.metadata.documentation.clone(),
+            })
+            .collect(),
+        generic_params: vec![],
+        range: symbol.range.clone(),
+    }
+}
+
+fn merge_field(fields: &mut Vec<SchemaField>, name: &str, update: impl FnOnce(&mut SchemaField)) {
+    let index = match fields.iter().position(|f| f.name == name) {
+        Some(index) => index,
+        None => {
+            fields.push(SchemaField { name: name.to_string(), is_optional: true, ..Default::default() });
+            fields.len() - 1
+        }
+    };
+    update(&mut fields[index]);
+}
+
+fn is_visibility(word: &str) -> bool {
+    matches!(word, "public" | "protected" | "private")
+}
+
+/// `\Illuminate\Database\Eloquent\Model` -> `Model`
+fn short_name(name: &str) -> &str {
+    name.rsplit('\\').next().unwrap_or(name)
+}
+
+fn strip_modifiers(head: &str) -> &str {
+    let mut rest = head.trim_start();
+    while let Some(word) = rest.split_whitespace().next().filter(|w| MODIFIERS.contains(w)) {
+        rest = rest[word.len()..].trim_start();
+    }
+    rest
+}
+
+fn strip_attributes(param: &str) -> &str {
+    let mut rest = param.trim_start();
+    while rest.starts_with("#[") {
+        match matching_bracket(&rest[1..]) {
+            Some(close) => rest = rest[close + 2..].trim_start(),
+            None => break,
+        }
+    }
+    rest
+}
+
+/// Byte offset of `word` in `text` as a whole word
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    text.match_indices(word).map(|(i, _)| i).find(|&i| {
+        let before = text[..i].chars().next_back().is_none_or(|c| !c.is_alphanumeric() && c != '_');
+        let after = text[i + word.len()..].chars().next().is_none_or(|c| !c.is_alphanumeric() && c != '_');
+        before && after
+    })
+}
+
+/// Index of the `]` closing a `[` at the start of `text`
+fn matching_bracket(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split on commas outside brackets and strings
+pub(crate) fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quote, mut start) = (0i32, None, 0);
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+fn clean_docblock(comment: &str) -> String {
+    comment
+        .trim_start_matches("/**")
+        .trim_end_matches("*/")
+        .lines()
+        .map(|line| line.trim().trim_start_matches('*').trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Blank comments, inline HTML and `<?php`/`?>` tags to spaces, and string
+/// contents too unless `keep_strings`, keeping every byte offset and newline
+pub(crate) fn blank_php(content: &str, keep_strings: bool) -> String {
+    #[derive(PartialEq)]
+    enum State {
+        Html,
+        Code,
+        LineComment,
+        BlockComment,
+        Str(u8),
+        Heredoc,
+    }
+
+    let bytes = content.as_bytes();
+    let mut out = bytes.to_vec();
+    let mut state = State::Html;
+    let mut heredoc_end = String::new();
+    let mut i = 0;
+
+    let blank = |out: &mut Vec<u8>, from: usize, to: usize| {
+        for b in &mut out[from..to] {
+            if *b != b'\n' {
+                *b = b' ';
+            }
+        }
+    };
+
+    while i < bytes.len() {
+        // Bytes, not `&str`: `i` walks through multi-byte characters too
+        let rest = &bytes[i..];
+        match state {
+            State::Html => {
+                if rest.starts_with(b"<?php") {
+                    blank(&mut out, i, i + 5);
+                    i += 5;
+                    state = State::Code;
+                    continue;
+                }
+                if rest.starts_with(b"<?=") || rest.starts_with(b"<?") {
+                    let len = if rest.starts_with(b"<?=") { 3 } else { 2 };
+                    blank(&mut out, i, i + len);
+                    i += len;
+                    state = State::Code;
+                    continue;
+                }
+                blank(&mut out, i, i + 1);
+            }
+            State::Code => {
+                if rest.starts_with(b"?>") {
+                    blank(&mut out, i, i + 2);
+                    i += 2;
+                    state = State::Html;
+                    continue;
+                }
+                if rest.starts_with(b"//") || (rest.starts_with(b"#") && !rest.starts_with(b"#[")) {
+                    state = State::LineComment;
+                    continue;
+                }
+                if rest.starts_with(b"/*") {
+                    blank(&mut out, i, i + 2);
+                    i += 2;
+                    state = State::BlockComment;
+                    continue;
+                }
+                if rest.starts_with(b"<<<") {
+                    let label: String = content[i + 3..]
+                        .trim_start_matches([' ', '\t'])
+                        .trim_start_matches(['\'', '"'])
+                        .chars()
+                        .take_while(|c| c.is_alphanumeric() || *c == '_')
+                        .collect();
+                    if let (false, Some(newline)) = (label.is_empty(), rest.iter().position(|&b| b == b'\n')) {
+                        heredoc_end = label;
+                        i += newline + 1;
+                        state = State::Heredoc;
+                        continue;
+                    }
+                }
+                if bytes[i] == b'\'' || bytes[i] == b'"' {
+                    state = State::Str(bytes[i]);
+                }
+            }
+            State::LineComment => {
+                if bytes[i] == b'\n' {
+                    state = State::Code;
+                } else if rest.starts_with(b"?>") {
+                    state = State::Code;
+                    continue;
+                } else {
+                    blank(&mut out, i, i + 1);
+                }
+            }
+            State::BlockComment => {
+                if rest.starts_with(b"*/") {
+                    blank(&mut out, i, i + 2);
+                    i += 2;
+                    state = State::Code;
+                    continue;
+                }
+                blank(&mut out, i, i + 1);
+            }
+            State::Str(quote) => {
+                if bytes[i] == b'\\' && i + 1 < bytes.len() {
+                    if !keep_strings {
+                        blank(&mut out, i, i + 2);
+                    }
+                    i += 2;
+                    continue;
+                }
+                if bytes[i] == quote {
+                    state = State::Code;
+                } else if !keep_strings {
+                    blank(&mut out, i, i + 1);
+                }
+            }
+            State::Heredoc => {
+                let line_end = rest.iter().position(|&b| b == b'\n').map(|n| i + n).unwrap_or(bytes.len());
+                let line = &content[i..line_end];
+                if line.trim_start().starts_with(heredoc_end.as_str()) {
+                    // The terminator line keeps whatever follows the label: `EOT;`
+                    let label_end = i + (line.len() - line.trim_start().len()) + heredoc_end.len();
+                    i = label_end;
+                    state = State::Code;
+                    continue;
+                }
+                if !keep_strings {
+                    blank(&mut out, i, line_end);
+                }
+                i = line_end + 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    // Only ASCII bytes were replaced, and whole multi-byte characters at that
+    String::from_utf8(out).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_eloquent_model() {
+        let code = r#"<?php
+
+namespace App\Models;
+
+use Illuminate\Database\Eloquent\Factories\HasFactory;
+use Illuminate\Database\Eloquent\{Model, Relations\HasMany as Many};
+
+/**
+ * A blog post.
+ */
+#[ObservedBy([PostObserver::class])]
+class Post extends Model implements HasMedia
+{
+    use HasFactory, SoftDeletes;
+
+    public const STATUS_DRAFT = 'draft';
+
+    protected $fillable = ['title', 'body', 'author_id'];
+
+    protected $casts = [
+        'published_at' => 'datetime',
+        'status' => PostStatus::class,
+    ];
+
+    public function __construct(private readonly SlugService $slugs, array $attributes = [])
+    {
+        parent::__construct($attributes);
+    }
+
+    public function author(): BelongsTo
+    {
+        // the author; see } and { in comments
+        return $this->belongsTo(User::class, 'author_id');
+    }
+
+    public function comments(): HasMany
+    {
+        return $this->hasMany(Comment::class)->where('body', '!=', '}');
+    }
+
+    public static function published(?int $limit = null): Collection
+    {
+        $query = static::query()->whereNotNull('published_at');
+        return $limit ? $query->take($limit)->get() : $query->get();
+    }
+}
+
+interface Publishable
+{
+    public function publish(string $at): void;
+}
+
+enum PostStatus: string
+{
+    case Draft = 'draft';
+    case Published = 'published';
+}
+
+function excerpt(string $text, int $length = 80): string
+{
+    return mb_substr($text, 0, $length);
+}
+"#;
+
+        let parsed = PhpParser::new().parse(code).unwrap();
+        assert_eq!(parsed.language, "php");
+        assert_eq!(parsed.imports.len(), 2);
+        assert_eq!(parsed.imports[0].source, "Illuminate\\Database\\Eloquent\\Factories");
+        assert_eq!(parsed.imports[0].names[0].name, "HasFactory");
+        let group: Vec<(&str, Option<&str>)> =
+            parsed.imports[1].names.iter().map(|n| (n.name.as_str(), n.alias.as_deref())).collect();
+        assert_eq!(group, vec![("Model", None), ("HasMany", Some("Many"))]);
+
+        let names: Vec<&str> = parsed.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Post", "Publishable", "PostStatus", "excerpt"]);
+
+        let post = &parsed.symbols[0];
+        assert_eq!((post.range.start_line, post.range.end_line), (12, 46));
+        assert_eq!(post.metadata.extends, vec!["Model"]);
+        assert_eq!(post.metadata.implements, vec!["HasMedia", "HasFactory", "SoftDeletes"]);
+        assert_eq!(post.metadata.tags, vec!["laravel-model"]);
+        assert_eq!(post.metadata.decorators, vec!["#[ObservedBy([PostObserver::class])]"]);
+        assert_eq!(post.metadata.documentation.as_deref(), Some("A blog post."));
+
+        let members: Vec<&str> = post.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            members,
+            vec!["STATUS_DRAFT", "fillable", "casts", "slugs", "__construct", "author", "comments", "published"]
+        );
+        let published = post.children.iter().find(|c| c.name == "published").unwrap();
+        assert!(published.metadata.is_static);
+        assert_eq!(published.metadata.return_type.as_deref(), Some("Collection"));
+        assert_eq!(published.metadata.parameters[0].name, "$limit");
+        assert_eq!(published.metadata.parameters[0].type_annotation.as_deref(), Some("?int"));
+        assert_eq!((published.range.start_line, published.range.end_line), (41, 45));
+
+        assert_eq!(parsed.constants[0].name, "Post::STATUS_DRAFT");
+        assert_eq!(parsed.constants[0].value, "'draft'");
+
+        let schema = &parsed.schemas[0];
+        assert!(matches!(schema.schema_type, SchemaType::Eloquent));
+        let field = |name: &str| schema.fields.iter().find(|f| f.name == name).unwrap();
+        assert_eq!(field("title").validation_rules, vec!["fillable"]);
+        assert_eq!(field("published_at").type_annotation.as_deref(), Some("datetime"));
+        assert_eq!(field("status").type_annotation.as_deref(), Some("PostStatus"));
+        assert_eq!(field("author").type_annotation.as_deref(), Some("belongsTo"));
+        assert_eq!(field("comments").references.as_deref(), Some("Comment"));
+
+        let kinds: Vec<&str> = parsed.type_definitions.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(kinds, vec!["Publishable", "PostStatus"]);
+        assert_eq!(parsed.type_definitions[0].properties[0].type_annotation, "(string $at) -> void");
+        assert_eq!(parsed.symbols[2].children.len(), 2);
+        assert_eq!(parsed.symbols[3].kind, SymbolType::Function);
+    }
+
+    #[test]
+    fn test_non_ascii_comments_strings_and_names() {
+        let code = "<?php\n// Café helper\n/* naïve — ok */\nclass Crème\n{\n    public function grüßen(string $name = 'José'): string\n    {\n        return \"¡Hola, $name!\";\n    }\n}\n?>\n<p>Ünïcode</p>\n";
+
+        let parsed = PhpParser::new().parse(code).unwrap();
+        let class = &parsed.symbols[0];
+        assert_eq!(class.name, "Crème");
+        assert_eq!(class.children[0].name, "grüßen");
+        assert_eq!((class.range.start_line, class.range.end_line), (4, 10));
+
+        let blanked = blank_php(code, false);
+        assert_eq!(blanked.len(), code.len());
+        assert!(!blanked.contains("Café") && !blanked.contains("José") && !blanked.contains("Ünïcode"));
+        assert!(blanked.contains("class Crème"));
+    }
+}
@@ -1,19 +1,13 @@
+use crate::diagnostics::{for_each_declaration, tree_diagnostics};
 use crate::types::*;
 use anyhow::{Context, Result};
-use tree_sitter::{Node, Parser, Query, QueryCursor};
+use tree_sitter::{Node, Parser, Tree};
 
-pub struct PythonParser {
-    parser: Parser,
-}
+pub struct PythonParser;
 
 impl PythonParser {
     pub fn new() -> Self {
-        let mut parser = Parser::new();
-        let language = tree_sitter_python::language();
-        parser
-            .set_language(language)
-            .expect("Error loading Python grammar");
-        Self { parser }
+        Self
     }
 
     pub fn parse(&self, content: &str) -> Result<ParsedFile> {
@@ -26,6 +20,11 @@ impl PythonParser {
             .parse(content, None)
             .context("Failed to parse Python content")?;
 
+        self.parse_tree(&tree, content)
+    }
+
+    /// Extract from an already-parsed tree; lets callers reuse an incrementally reparsed tree
+    pub fn parse_tree(&self, tree: &Tree, content: &str) -> Result<ParsedFile> {
         let root_node = tree.root_node();
 
         let symbols = self.extract_symbols(&root_node, content)?;
@@ -43,18 +42,16 @@ impl PythonParser {
             constants,
             schemas,
             language: "python".to_string(),
+            syntax_errors: tree_diagnostics(tree, content),
         })
     }
 
     fn extract_symbols(&self, node: &Node, source: &str) -> Result<Vec<Symbol>> {
         let mut symbols = Vec::new();
-        let mut cursor = node.walk();
-
-        for child in node.children(&mut cursor) {
-            if let Some(symbol) = self.process_node(&child, source)? {
-                symbols.push(symbol);
-            }
-        }
+        for_each_declaration(node, |candidate| {
+            symbols.extend(self.process_node(candidate, source)?);
+            Ok(())
+        });
 
         Ok(symbols)
     }
@@ -69,7 +66,10 @@ impl PythonParser {
                     .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "Anonymous".to_string());
                 let range = self.get_range(node);
-                let metadata = self.extract_metadata(node, source)?;
+                let mut metadata = self.extract_metadata(node, source)?;
+                if let Some(body) = node.child_by_field_name("body") {
+                    metadata.documentation = self.extract_docstring(&body, source)?;
+                }
 
                 Ok(Some(Symbol {
                     name,
@@ -148,10 +148,7 @@ impl PythonParser {
                             // Determine method type based on decorators
                             let symbol_kind = if metadata.decorators.iter().any(|d| d.contains("@property")) {
                                 SymbolType::Property
-                            } else if metadata.decorators.iter().any(|d| d.contains("@classmethod")) {
-                                metadata.is_static = true;
-                                SymbolType::Method
-                            } else if metadata.decorators.iter().any(|d| d.contains("@staticmethod")) {
+                            } else if metadata.decorators.iter().any(|d| d.contains("@classmethod") || d.contains("@staticmethod")) {
                                 metadata.is_static = true;
                                 SymbolType::Method
                             } else {
@@ -345,9 +342,13 @@ impl PythonParser {
                             .trim_end_matches("'''")
                             .trim_end_matches('"')
                             .trim_end_matches('\'')
+                            .lines()
+                            .map(str::trim) // Continuation lines carry the body's indentation
+                            .collect::<Vec<_>>()
+                            .join("\n")
                             .trim()
                             .to_string();
-                        return Ok(Some(cleaned));
+                        return Ok((!cleaned.is_empty()).then_some(cleaned));
                     }
                 }
                 break; // Only check first statement
@@ -581,6 +582,7 @@ impl PythonParser {
                                                     is_optional: false,
                                                     validators: vec![],
                                                     description: None,
+                                                    references: None,
                                                 });
                                             }
                                         }
@@ -596,6 +598,8 @@ impl PythonParser {
                             fields,
                             range: self.get_range(&child),
                         });
+                    } else if let Some(schema) = self.extract_sqlalchemy_model(&child, source)? {
+                        schemas.push(schema);
                     }
                 }
             }
@@ -603,6 +607,120 @@ impl PythonParser {
 
         Ok(schemas)
     }
+
+    /// SQLAlchemy declarative models: classes assigning `Column(...)` / `mapped_column(...)`
+    /// or declaring `__tablename__`. Foreign keys and `relationship()` targets become references.
+    fn extract_sqlalchemy_model(&self, class: &Node, source: &str) -> Result<Option<ValidationSchema>> {
+        let Some(body) = class.child_by_field_name("body") else {
+            return Ok(None);
+        };
+
+        let mut fields = Vec::new();
+        let mut has_tablename = false;
+        let mut body_cursor = body.walk();
+
+        for body_child in body.children(&mut body_cursor) {
+            if body_child.kind() != "expression_statement" {
+                continue;
+            }
+            let Some(assignment) = body_child.child(0).filter(|a| a.kind() == "assignment") else {
+                continue;
+            };
+            let (Some(left), Some(right)) = (
+                assignment.child_by_field_name("left"),
+                assignment.child_by_field_name("right"),
+            ) else {
+                continue;
+            };
+
+            let field_name = left.utf8_text(source.as_bytes())?.to_string();
+            let value = right.utf8_text(source.as_bytes())?;
+            if field_name == "__tablename__" {
+                has_tablename = true;
+                continue;
+            }
+
+            let Some((callee, args)) = value.split_once('(') else {
+                continue;
+            };
+            let callee = callee.rsplit('.').next().unwrap_or(callee).trim();
+            let args = args.strip_suffix(')').unwrap_or(args);
+            let is_relationship = callee == "relationship";
+            if !matches!(callee, "Column" | "mapped_column") && !is_relationship {
+                continue;
+            }
+
+            let mut validation_rules = Vec::new();
+            if args.contains("primary_key=True") {
+                validation_rules.push("primary key".to_string());
+            }
+            if args.contains("unique=True") {
+                validation_rules.push("unique".to_string());
+            }
+            if args.contains("index=True") {
+                validation_rules.push("index".to_string());
+            }
+
+            let references = if is_relationship {
+                first_string_literal(args)
+            } else {
+                args.find("ForeignKey(").and_then(|i| first_string_literal(&args[i..]))
+            };
+
+            // Column type: first positional argument, or the `Mapped[...]` annotation
+            let type_annotation = assignment
+                .child_by_field_name("type")
+                .map(|n| n.utf8_text(source.as_bytes()).unwrap().to_string())
+                .or_else(|| {
+                    args.split(',')
+                        .next()
+                        .map(str::trim)
+                        .filter(|a| !a.is_empty() && !a.contains('=') && !a.starts_with("ForeignKey"))
+                        .map(str::to_string)
+                });
+
+            let default_value = args
+                .split(',')
+                .find_map(|a| a.trim().strip_prefix("default="))
+                .map(|d| d.trim().to_string());
+            let is_required = !is_relationship
+                && (args.contains("nullable=False") || args.contains("primary_key=True"));
+
+            fields.push(SchemaField {
+                name: field_name,
+                validation_rules,
+                is_required,
+                default_value,
+                type_annotation,
+                is_optional: !is_required,
+                validators: vec![],
+                description: None,
+                references,
+            });
+        }
+
+        if fields.is_empty() && !has_tablename {
+            return Ok(None);
+        }
+
+        Ok(Some(ValidationSchema {
+            name: self
+                .get_child_text(class, "name", source)
+                .unwrap_or_else(|| "Model".to_string()),
+            schema_type: SchemaType::SqlAlchemy,
+            definition: class.utf8_text(source.as_bytes())?.to_string(),
+            fields,
+            range: self.get_range(class),
+        }))
+    }
+}
+
+/// First `"..."` / `'...'` literal in a call's argument list
+fn first_string_literal(args: &str) -> Option<String> {
+    let start = args.find(['"', '\''])?;
+    let quote = args[start..].chars().next()?;
+    let rest = &args[start + 1..];
+    rest.find(quote).map(|end| rest[..end].to_string())
 }
 
 impl Default for PythonParser {
@@ -610,3 +728,36 @@ impl Default for PythonParser {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docstrings() {
+        let code = r#"class Invoice:
+    """An issued invoice.
+
+    Amounts are in cents.
+    """
+
+    def total(self) -> int:
+        '''Sum of the line items.'''
+        return 0
+
+
+def plain():
+    # A comment is not a docstring
+    return 1
+"#;
+        let parsed = PythonParser::new().parse(code).unwrap();
+        let invoice = parsed.symbols.iter().find(|s| s.name == "Invoice").unwrap();
+        assert_eq!(invoice.metadata.documentation.as_deref(), Some("An issued invoice.\n\nAmounts are in cents."));
+
+        let total = invoice.children.iter().find(|s| s.name == "total").unwrap();
+        assert_eq!(total.metadata.documentation.as_deref(), Some("Sum of the line items."));
+
+        let plain = parsed.symbols.iter().find(|s| s.name == "plain").unwrap();
+        assert_eq!(plain.metadata.documentation, None);
+    }
+}
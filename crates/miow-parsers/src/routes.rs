@@ -0,0 +1,822 @@
+use crate::types::*;
+use regex::Regex;
+
+const HTTP_METHODS: &str = "GET|POST|PUT|PATCH|DELETE|HEAD|OPTIONS";
+
+/// Extract routes from a file: Next.js file-system routes (app and pages
+/// routers), Express/Fastify registrations, Axum routers, Actix attributes,
+/// FastAPI/Flask decorators, Rails `config/routes.rb` and Laravel `routes/*.php`.
+/// `relative_path` drives file-system routing.
+pub fn extract_routes(relative_path: &str, content: &str) -> Vec<Route> {
+    let path = relative_path.replace('\\', "/");
+    let extension = path.rsplit('.').next().unwrap_or("");
+
+    match extension {
+        "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" => {
+            let mut routes = nextjs_routes(&path, content);
+            routes.extend(express_routes(content));
+            routes
+        }
+        "rs" => {
+            let mut routes = axum_routes(content);
+            routes.extend(actix_routes(content));
+            routes
+        }
+        "py" => python_routes(content),
+        "rb" if path.ends_with("config/routes.rb") || path.contains("config/routes/") => rails_routes(content),
+        "php" if path.starts_with("routes/") || path.contains("/routes/") => laravel_routes(&path, content),
+        _ => vec![],
+    }
+}
+
+/// `app/(shop)/products/[id]/page.tsx` -> `PAGE /products/[id]`;
+/// `app/api/users/route.ts` -> one route per exported method handler;
+/// `pages/blog/index.tsx` -> `PAGE /blog`, `pages/api/*` -> `ANY /api/*`
+fn nextjs_routes(path: &str, content: &str) -> Vec<Route> {
+    let parts: Vec<&str> = path.split('/').collect();
+    let Some((file, dirs)) = parts.split_last() else {
+        return vec![];
+    };
+    let stem = file.split('.').next().unwrap_or(file);
+    let whole_file = range_for(content, 0, content.len());
+
+    if let Some(app) = dirs.iter().rposition(|d| *d == "app") {
+        if stem != "page" && stem != "route" {
+            return vec![];
+        }
+        // Route groups `(group)`, parallel slots `@slot` and private `_folders` don't add segments
+        let segments: Vec<&str> = dirs[app + 1..]
+            .iter()
+            .copied()
+            .filter(|s| !(s.starts_with(['@', '_']) || (s.starts_with('(') && s.ends_with(')'))))
+            .collect();
+        let url = format!("/{}", segments.join("/"));
+
+        if stem == "page" {
+            return vec![Route {
+                method: "PAGE".to_string(),
+                path: url,
+                handler: default_export_name(content),
+                framework: "nextjs".to_string(),
+                range: whole_file,
+            }];
+        }
+
+        let handler = Regex::new(&format!(
+            r"export\s+(?:async\s+function\s+|function\s+|const\s+)({})\b",
+            HTTP_METHODS
+        ))
+        .unwrap();
+        return handler
+            .captures_iter(content)
+            .map(|c| {
+                let m = c.get(0).unwrap();
+                Route {
+                    method: c[1].to_string(),
+                    path: url.clone(),
+                    handler: Some(c[1].to_string()),
+                    framework: "nextjs".to_string(),
+                    range: range_for(content, m.start(), m.end()),
+                }
+            })
+            .collect();
+    }
+
+    if let Some(pages) = dirs.iter().rposition(|d| *d == "pages") {
+        if stem.starts_with('_') {
+            return vec![];
+        }
+        let mut segments: Vec<&str> = dirs[pages + 1..].to_vec();
+        if stem != "index" {
+            segments.push(stem);
+        }
+        let is_api = segments.first() == Some(&"api");
+
+        return vec![Route {
+            method: if is_api { "ANY" } else { "PAGE" }.to_string(),
+            path: format!("/{}", segments.join("/")),
+            handler: default_export_name(content),
+            framework: "nextjs".to_string(),
+            range: whole_file,
+        }];
+    }
+
+    vec![]
+}
+
+/// `app.get('/users/:id', auth, showUser)` / `router.post(...)` / `fastify.put(...)`
+fn express_routes(content: &str) -> Vec<Route> {
+    let registration = Regex::new(
+        r#"\b(\w+)\.(get|post|put|patch|delete|all|options|head)\(\s*['"`](/[^'"`]*)['"`]\s*(,[^\n]*)?"#,
+    )
+    .unwrap();
+
+    registration
+        .captures_iter(content)
+        .filter(|c| {
+            let receiver = c[1].to_lowercase();
+            matches!(receiver.as_str(), "app" | "router" | "server" | "fastify" | "api")
+                || receiver.ends_with("router")
+                || receiver.ends_with("app")
+        })
+        .map(|c| {
+            let m = c.get(0).unwrap();
+            let method = match &c[2] {
+                "all" => "ANY".to_string(),
+                other => other.to_uppercase(),
+            };
+            // The last argument is the handler when it is a plain identifier
+            let handler = c.get(4).and_then(|args| {
+                let last = args.as_str().rsplit(',').next()?.trim();
+                let last = last.trim_end_matches(';').trim_end_matches(')').trim();
+                let is_identifier = !last.is_empty()
+                    && last.chars().all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '.' || ch == '$');
+                is_identifier.then(|| last.to_string())
+            });
+
+            Route {
+                method,
+                path: c[3].to_string(),
+                handler,
+                framework: "express".to_string(),
+                range: range_for(content, m.start(), m.end()),
+            }
+        })
+        .collect()
+}
+
+/// `.route("/users/:id", get(show_user).post(update_user))`
+fn axum_routes(content: &str) -> Vec<Route> {
+    let route_call = Regex::new(r#"\.route\(\s*"([^"]+)"\s*,"#).unwrap();
+    let method_router = Regex::new(r"\b(get|post|put|patch|delete|head|options|any)\(\s*([\w:]+)\s*\)").unwrap();
+
+    let mut routes = Vec::new();
+    for caps in route_call.captures_iter(content) {
+        let whole = caps.get(0).unwrap();
+        let open = whole.start() + ".route".len();
+        let Some(close) = matching_paren(content, open) else {
+            continue;
+        };
+        let handlers = &content[whole.end()..close];
+
+        for m in method_router.captures_iter(handlers) {
+            routes.push(Route {
+                method: m[1].to_uppercase(),
+                path: caps[1].to_string(),
+                handler: Some(m[2].to_string()),
+                framework: "axum".to_string(),
+                range: range_for(content, whole.start(), close + 1),
+            });
+        }
+    }
+    routes
+}
+
+/// `#[get("/users/{id}")] async fn show_user(...)`
+fn actix_routes(content: &str) -> Vec<Route> {
+    let attribute = Regex::new(
+        r#"#\[(get|post|put|patch|delete|head|options)\(\s*"([^"]+)"[^\]]*\]\s*(?:pub\s+)?(?:async\s+)?fn\s+(\w+)"#,
+    )
+    .unwrap();
+
+    attribute
+        .captures_iter(content)
+        .map(|c| {
+            let m = c.get(0).unwrap();
+            Route {
+                method: c[1].to_uppercase(),
+                path: c[2].to_string(),
+                handler: Some(c[3].to_string()),
+                framework: "actix".to_string(),
+                range: range_for(content, m.start(), m.end()),
+            }
+        })
+        .collect()
+}
+
+/// FastAPI `@app.get("/items/{id}")` / `@router.post(...)` and Flask `@app.route("/x", methods=[...])`
+fn python_routes(content: &str) -> Vec<Route> {
+    let decorator = Regex::new(
+        r#"(?m)^[ \t]*@(\w+)\.(get|post|put|patch|delete|head|options|route|api_route)\(\s*['"]([^'"]+)['"]([^\n]*)\n(?:[ \t]*@[^\n]*\n)*[ \t]*(?:async\s+)?def\s+(\w+)"#,
+    )
+    .unwrap();
+    let methods_kwarg = Regex::new(r#"methods\s*=\s*\[([^\]]*)\]"#).unwrap();
+
+    let mut routes = Vec::new();
+    for c in decorator.captures_iter(content) {
+        let m = c.get(0).unwrap();
+        let kind = &c[2];
+        let is_generic = kind == "route" || kind == "api_route";
+
+        let methods: Vec<String> = if is_generic {
+            methods_kwarg
+                .captures(&c[4])
+                .map(|list| {
+                    list[1]
+                        .split(',')
+                        .map(|m| m.trim().trim_matches(|ch| ch == '"' || ch == '\'').to_uppercase())
+                        .filter(|m| !m.is_empty())
+                        .collect()
+                })
+                .unwrap_or_else(|| vec!["GET".to_string()])
+        } else {
+            vec![kind.to_uppercase()]
+        };
+
+        for method in methods {
+            routes.push(Route {
+                method,
+                path: c[3].to_string(),
+                handler: Some(c[5].to_string()),
+                framework: if kind == "route" { "flask" } else { "fastapi" }.to_string(),
+                range: range_for(content, m.start(), m.end()),
+            });
+        }
+    }
+    routes
+}
+
+/// The RESTful actions `resources` generates: (action, method, member?)
+const RAILS_ACTIONS: &[(&str, &str, bool)] = &[
+    ("index", "GET", false),
+    ("new", "GET", false),
+    ("create", "POST", false),
+    ("show", "GET", true),
+    ("edit", "GET", true),
+    ("update", "PATCH", true),
+    ("destroy", "DELETE", true),
+];
+
+/// A `do ... end` block in a Rails routes file and the scope it routes under
+#[derive(Clone, Default)]
+struct RailsScope {
+    path: String,
+    /// Controller namespace from `namespace`/`scope module:`, e.g. `admin/`
+    module: String,
+    /// Controller of the enclosing `resources`, for routes that only name an action
+    controller: Option<String>,
+    /// Paths `member do` and `collection do` route under
+    member: Option<String>,
+    collection: Option<String>,
+}
+
+/// Rails routes: verb routes (`get "/about", to: "pages#about"`), `root`,
+/// `match ... via:`, and the RESTful routes of `resources`/`resource`, nested
+/// under `namespace`, `scope`, parent resources, `member` and `collection`.
+/// Handlers are `controller#action`.
+fn rails_routes(content: &str) -> Vec<Route> {
+    let call = Regex::new(r#"^(get|post|put|patch|delete|match|root|resources|resource|namespace|scope|member|collection|concern)\b\s*\(?\s*(?:(:\w+)|['"]([^'"]*)['"])?"#).unwrap();
+    let option = |rest: &str, key: &str| {
+        Regex::new(&format!(r#"(?:^|[,\s]){}:\s*(?:['"]([^'"]*)['"]|:(\w+)|(\[[^\]]*\]|%i\[[^\]]*\]))"#, key))
+            .unwrap()
+            .captures(rest)
+            .and_then(|c| c.get(1).or(c.get(2)).or(c.get(3)).map(|m| m.as_str().to_string()))
+    };
+    let word = Regex::new(r"\w+").unwrap();
+    let symbols = |list: &str| -> Vec<String> {
+        word.find_iter(list).map(|m| m.as_str().to_string()).filter(|w| w != "i").collect()
+    };
+    let opens_block = Regex::new(r"\bdo(\s*\|[^|]*\|)?$").unwrap();
+    let hash_rocket = Regex::new(r#"=>\s*['"]([^'"]*)['"]"#).unwrap();
+
+    let mut scopes = vec![RailsScope::default()];
+    let mut routes = Vec::new();
+    let mut offset = 0;
+
+    for raw in content.split_inclusive('\n') {
+        let start = offset;
+        offset += raw.len();
+        let line = strip_ruby_comment(raw).trim();
+        let opens_block = opens_block.is_match(line);
+        if line == "end" || line.starts_with("end ") || line.starts_with("end.") {
+            if scopes.len() > 1 {
+                scopes.pop();
+            }
+            continue;
+        }
+
+        let scope = scopes.last().cloned().unwrap_or_default();
+        let mut inner = scope.clone();
+        let range = range_for(content, start, start + raw.trim_end().len());
+        let Some(c) = call.captures(line) else {
+            if opens_block {
+                scopes.push(inner);
+            }
+            continue;
+        };
+        let keyword = &c[1];
+        let argument = c.get(2).map(|m| m.as_str().trim_start_matches(':')).or(c.get(3).map(|m| m.as_str()));
+        let rest = &line[c.get(0).unwrap().end()..];
+        let mut push = |method: &str, path: String, handler: Option<String>| {
+            routes.push(Route {
+                method: method.to_string(),
+                path,
+                handler,
+                framework: "rails".to_string(),
+                range: range.clone(),
+            });
+        };
+
+        match keyword {
+            "namespace" => {
+                if let Some(name) = argument {
+                    inner.path = join_path(&scope.path, option(rest, "path").as_deref().unwrap_or(name));
+                    inner.module = format!("{}{}/", scope.module, name);
+                }
+            }
+            "scope" => {
+                if let Some(path) = argument.map(str::to_string).or_else(|| option(rest, "path")) {
+                    inner.path = join_path(&scope.path, &path);
+                }
+                if let Some(module) = option(rest, "module") {
+                    inner.module = format!("{}{}/", scope.module, module);
+                }
+            }
+            "member" => inner.path = scope.member.clone().unwrap_or(scope.path.clone()),
+            "collection" => inner.path = scope.collection.clone().unwrap_or(scope.path.clone()),
+            "resources" | "resource" => {
+                let Some(name) = argument else {
+                    continue;
+                };
+                let plural = keyword == "resources";
+                let controller = format!(
+                    "{}{}",
+                    scope.module,
+                    option(rest, "controller").unwrap_or_else(|| if plural { name.to_string() } else { format!("{}s", name) })
+                );
+                let base = join_path(&scope.path, option(rest, "path").as_deref().unwrap_or(name));
+                let member = if plural { format!("{}/:id", base) } else { base.clone() };
+                let only = option(rest, "only").map(|o| symbols(&o));
+                let except = option(rest, "except").map(|e| symbols(&e)).unwrap_or_default();
+
+                for (action, method, on_member) in RAILS_ACTIONS {
+                    let wanted = only.as_ref().is_none_or(|o| o.iter().any(|a| a == action)) && !except.iter().any(|a| a == action);
+                    if !wanted || (!plural && *action == "index") {
+                        continue;
+                    }
+                    let path = match (*action, *on_member) {
+                        ("new", _) => format!("{}/new", base),
+                        ("edit", _) => format!("{}/edit", member),
+                        (_, true) => member.clone(),
+                        _ => base.clone(),
+                    };
+                    push(method, path, Some(format!("{}#{}", controller, action)));
+                }
+
+                // Nested resources route under `/users/:user_id`
+                let singular = name.strip_suffix("ies").map(|s| format!("{}y", s)).unwrap_or_else(|| name.trim_end_matches('s').to_string());
+                inner.path = if plural { format!("{}/:{}_id", base, singular) } else { base.clone() };
+                inner.controller = Some(controller);
+                inner.member = Some(member);
+                inner.collection = Some(base);
+            }
+            "root" => {
+                let target = argument.map(str::to_string).or_else(|| option(rest, "to"));
+                push("GET", join_path(&scope.path, ""), target.map(|t| format!("{}{}", scope.module, t)));
+            }
+            "concern" => {}
+            verb => {
+                let Some(segment) = argument else {
+                    continue;
+                };
+                let target = option(rest, "to").or_else(|| hash_rocket.captures(rest).map(|c| c[1].to_string()));
+                let handler = match (target, option(rest, "controller"), option(rest, "action")) {
+                    (Some(target), _, _) => Some(format!("{}{}", scope.module, target)),
+                    (None, Some(controller), action) => {
+                        Some(format!("{}{}#{}", scope.module, controller, action.unwrap_or_else(|| segment.to_string())))
+                    }
+                    (None, None, action) => {
+                        let action = action.unwrap_or_else(|| segment.trim_matches('/').replace('/', "_"));
+                        scope.controller.as_ref().map(|c| format!("{}#{}", c, action))
+                    }
+                };
+                let methods = if verb == "match" {
+                    match option(rest, "via") {
+                        Some(via) if via != "all" => symbols(&via).iter().map(|m| m.to_uppercase()).collect(),
+                        _ => vec!["ANY".to_string()],
+                    }
+                } else {
+                    vec![verb.to_uppercase()]
+                };
+                for method in methods {
+                    push(&method, join_path(&scope.path, segment), handler.clone());
+                }
+            }
+        }
+
+        if opens_block {
+            scopes.push(inner);
+        }
+    }
+    routes
+}
+
+/// The routes `Route::resource` generates: (action, method, path suffix, member?);
+/// `apiResource` leaves out the `create` and `edit` forms
+const LARAVEL_ACTIONS: &[(&str, &str, &str, bool)] = &[
+    ("index", "GET", "", false),
+    ("create", "GET", "/create", false),
+    ("store", "POST", "", false),
+    ("show", "GET", "", true),
+    ("edit", "GET", "/edit", true),
+    ("update", "PUT", "", true),
+    ("destroy", "DELETE", "", true),
+];
+
+/// A `group(function () { ... })` in a Laravel routes file and what it routes under
+#[derive(Clone, Default)]
+struct LaravelGroup {
+    prefix: String,
+    /// From `Route::controller(X::class)`, for routes that only name a method
+    controller: Option<String>,
+    /// Brace depth inside the group's closure
+    depth: usize,
+}
+
+/// Laravel routes: `Route::get('/users/{id}', [UserController::class, 'show'])`,
+/// `Route::match`, and the routes of `Route::resource`/`apiResource`, nested
+/// under `prefix` and `controller` groups. Handlers are `Controller@method`;
+/// routes in `routes/api.php` are served under `/api`.
+fn laravel_routes(path: &str, content: &str) -> Vec<Route> {
+    // Comments blanked, strings kept, at the same offsets as `content`
+    let code = crate::php::blank_php(content, true);
+    let route = Regex::new(r"\bRoute::").unwrap();
+    let link = Regex::new(r"^\s*(?:->|::)?\s*(\w+)\s*\(").unwrap();
+    let string = Regex::new(r#"^\s*['"]([^'"]*)['"]"#).unwrap();
+    let class = Regex::new(r"^\s*\\?(?:[\w]+\\)*(\w+)::class").unwrap();
+    let option = |args: &str, key: &str| {
+        Regex::new(&format!(r#"['"]{}['"]\s*=>\s*(\[[^\]]*\]|['"][^'"]*['"]|[\w\\]+::class)"#, key))
+            .unwrap()
+            .captures(args)
+            .map(|c| c[1].to_string())
+    };
+    let words = |list: &str| -> Vec<String> {
+        list.split(|c: char| !c.is_alphanumeric() && c != '_').filter(|w| !w.is_empty()).map(str::to_string).collect()
+    };
+    // `[UserController::class, 'show']`, `'UserController@show'`, `'show'` in a controller group
+    let handler = |action: Option<&str>, controller: &Option<String>| -> Option<String> {
+        let action = action?.trim();
+        if let Some(list) = action.strip_prefix('[') {
+            let parts = crate::php::split_top_level(list.trim_end_matches(']'));
+            let controller = class.captures(parts.first()?)?[1].to_string();
+            return Some(match parts.get(1).and_then(|m| string.captures(m)) {
+                Some(method) => format!("{}@{}", controller, &method[1]),
+                None => controller,
+            });
+        }
+        if let Some(c) = class.captures(action) {
+            return Some(format!("{}@__invoke", &c[1]));
+        }
+        let name = string.captures(action)?[1].to_string();
+        match controller {
+            Some(controller) if !name.contains('@') => Some(format!("{}@{}", controller, name)),
+            _ => Some(name),
+        }
+    };
+
+    let root = if path.ends_with("routes/api.php") { "/api" } else { "" };
+    let mut groups = vec![LaravelGroup { prefix: root.to_string(), ..Default::default() }];
+    let mut routes = Vec::new();
+    let (mut depth, mut quote, mut scanned, mut consumed) = (0usize, None, 0, 0);
+    let bytes = code.as_bytes();
+    // Brace depth outside strings (`'/users/{id}'`) up to `to`
+    let mut advance = |to: usize, depth: &mut usize, scanned: &mut usize| {
+        for &c in &bytes[*scanned..to] {
+            match (quote, c) {
+                (Some(q), _) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, b'\'' | b'"') => quote = Some(c),
+                (None, b'{') => *depth += 1,
+                (None, b'}') => *depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        *scanned = to.max(*scanned);
+    };
+
+    for m in route.find_iter(&code) {
+        if m.start() < consumed {
+            continue;
+        }
+        advance(m.start(), &mut depth, &mut scanned);
+        while groups.len() > 1 && groups.last().is_some_and(|g| depth < g.depth) {
+            groups.pop();
+        }
+        let group = groups.last().cloned().unwrap_or_default();
+
+        // The chain of calls: `Route::prefix('admin')->controller(X::class)->group(function () {`
+        let mut calls: Vec<(String, String)> = Vec::new();
+        let mut position = m.end();
+        let mut body = None;
+        while let Some(c) = link.captures(&code[position..]) {
+            let open = position + c.get(0).unwrap().end() - 1;
+            let Some(close) = matching_paren(&code, open) else {
+                break;
+            };
+            let name = c[1].to_string();
+            let args = &code[open + 1..close];
+            if let (true, Some(closure)) = (name == "group", args.find("function")) {
+                body = args[closure..].find('{').map(|b| open + 1 + closure + b);
+                calls.push((name, args[..closure].to_string()));
+                break;
+            }
+            calls.push((name, args.to_string()));
+            position = close + 1;
+        }
+        let Some((verb, args)) = calls.first().cloned() else {
+            continue;
+        };
+        let end = body.map(|b| b + 1).unwrap_or(position);
+        consumed = end;
+        let range = range_for(content, m.start(), end);
+
+        if let Some(body) = body {
+            let mut inner = group.clone();
+            for (name, args) in &calls {
+                match name.as_str() {
+                    "prefix" => {
+                        if let Some(prefix) = string.captures(args) {
+                            inner.prefix = join_path(&inner.prefix, &prefix[1]);
+                        }
+                    }
+                    "controller" => inner.controller = class.captures(args).map(|c| c[1].to_string()),
+                    "group" => {
+                        if let Some(prefix) = option(args, "prefix").and_then(|p| string.captures(&p).map(|c| c[1].to_string())) {
+                            inner.prefix = join_path(&inner.prefix, &prefix);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            advance(body + 1, &mut depth, &mut scanned);
+            inner.depth = depth;
+            groups.push(inner);
+            continue;
+        }
+
+        let arguments = crate::php::split_top_level(&args);
+        let mut push = |method: &str, path: String, handler: Option<String>| {
+            routes.push(Route {
+                method: method.to_string(),
+                path,
+                handler,
+                framework: "laravel".to_string(),
+                range: range.clone(),
+            });
+        };
+        match verb.as_str() {
+            "get" | "post" | "put" | "patch" | "delete" | "options" | "any" | "view" => {
+                let Some(uri) = arguments.first().and_then(|a| string.captures(a)) else {
+                    continue;
+                };
+                let method = match verb.as_str() {
+                    "view" => "GET".to_string(),
+                    verb => verb.to_uppercase(),
+                };
+                let handler = if verb == "view" { None } else { handler(arguments.get(1).copied(), &group.controller) };
+                push(&method, join_path(&group.prefix, &uri[1]), handler);
+            }
+            "match" => {
+                let Some(uri) = arguments.get(1).and_then(|a| string.captures(a)) else {
+                    continue;
+                };
+                let handler = handler(arguments.get(2).copied(), &group.controller);
+                for method in words(arguments.first().copied().unwrap_or("")) {
+                    push(&method.to_uppercase(), join_path(&group.prefix, &uri[1]), handler.clone());
+                }
+            }
+            "resource" | "apiResource" => {
+                let Some(name) = arguments.first().and_then(|a| string.captures(a)) else {
+                    continue;
+                };
+                let controller = arguments.get(1).and_then(|a| class.captures(a)).map(|c| c[1].to_string());
+                let options = arguments.get(2).copied().unwrap_or("");
+                let chained = |key: &str| calls.iter().find(|(n, _)| n == key).map(|(_, a)| a.clone());
+                let only = chained("only").or_else(|| option(options, "only")).map(|o| words(&o));
+                let except = chained("except").or_else(|| option(options, "except")).map(|e| words(&e)).unwrap_or_default();
+
+                // `photos.comments` -> `/photos/{photo}/comments/{comment}`
+                let segments: Vec<&str> = name[1].split('.').collect();
+                let singular = |s: &str| s.strip_suffix("ies").map(|s| format!("{}y", s)).unwrap_or_else(|| s.trim_end_matches('s').to_string());
+                let mut base = group.prefix.clone();
+                for (i, segment) in segments.iter().enumerate() {
+                    base = join_path(&base, segment);
+                    if i + 1 < segments.len() {
+                        base = format!("{}/{{{}}}", base, singular(segment));
+                    }
+                }
+                let member = format!("{}/{{{}}}", base, singular(segments.last().unwrap_or(&"")));
+
+                for (action, method, suffix, on_member) in LARAVEL_ACTIONS {
+                    let wanted = only.as_ref().is_none_or(|o| o.iter().any(|a| a == action)) && !except.iter().any(|a| a == action);
+                    if !wanted || (verb == "apiResource" && matches!(*action, "create" | "edit")) {
+                        continue;
+                    }
+                    let path = format!("{}{}", if *on_member { &member } else { &base }, suffix);
+                    push(method, path, controller.as_ref().map(|c| format!("{}@{}", c, action)));
+                }
+            }
+            _ => {}
+        }
+    }
+    routes
+}
+
+/// `/admin` + `users` -> `/admin/users`; an empty segment is the prefix itself
+fn join_path(prefix: &str, segment: &str) -> String {
+    let segment = segment.trim_matches('/');
+    match (prefix.trim_end_matches('/'), segment) {
+        ("", "") => "/".to_string(),
+        (prefix, "") => prefix.to_string(),
+        (prefix, segment) => format!("{}/{}", prefix, segment),
+    }
+}
+
+/// A Ruby line without its `#` comment; `#` inside strings (`to: "pages#about"`) stays
+fn strip_ruby_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn default_export_name(content: &str) -> Option<String> {
+    let default_export = Regex::new(r"export\s+default\s+(?:async\s+)?(?:function\s+|class\s+)?([A-Za-z_$][\w$]*)").unwrap();
+    default_export
+        .captures(content)
+        .map(|c| c[1].to_string())
+        .filter(|name| name != "function" && name != "class")
+}
+
+fn matching_paren(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn range_for(content: &str, start: usize, end: usize) -> Range {
+    Range {
+        start_line: content[..start].matches('\n').count() + 1,
+        end_line: content[..end].matches('\n').count() + 1,
+        start_byte: start,
+        end_byte: end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(routes: &[Route]) -> Vec<String> {
+        routes
+            .iter()
+            .map(|r| format!("{} {} {}", r.method, r.path, r.handler.as_deref().unwrap_or("-")))
+            .collect()
+    }
+
+    #[test]
+    fn test_nextjs_file_system_routes() {
+        let page = "export default function ProductPage() { return null }";
+        let routes = extract_routes("src/app/(shop)/products/[id]/page.tsx", page);
+        assert_eq!(summary(&routes), vec!["PAGE /products/[id] ProductPage"]);
+
+        let handler = "export async function GET(req: Request) {}\nexport const POST = async () => {}";
+        let routes = extract_routes("app/api/users/route.ts", handler);
+        assert_eq!(summary(&routes), vec!["GET /api/users GET", "POST /api/users POST"]);
+
+        let routes = extract_routes("pages/blog/index.tsx", "export default Blog;");
+        assert_eq!(summary(&routes), vec!["PAGE /blog Blog"]);
+        assert!(extract_routes("pages/_app.tsx", "").is_empty());
+    }
+
+    #[test]
+    fn test_server_framework_routes() {
+        let express = "router.get('/users/:id', auth, showUser);\napp.post(\"/users\", (req, res) => {});";
+        assert_eq!(
+            summary(&extract_routes("src/routes.ts", express)),
+            vec!["GET /users/:id showUser", "POST /users -"]
+        );
+
+        let axum = r#"Router::new().route("/users/:id", get(handlers::show).delete(remove))"#;
+        assert_eq!(
+            summary(&extract_routes("src/main.rs", axum)),
+            vec!["GET /users/:id handlers::show", "DELETE /users/:id remove"]
+        );
+
+        let fastapi = "@router.get(\"/items/{item_id}\")\nasync def read_item(item_id: int):\n    pass\n\n@app.route(\"/login\", methods=[\"GET\", \"POST\"])\ndef login():\n    pass\n";
+        assert_eq!(
+            summary(&extract_routes("api/main.py", fastapi)),
+            vec!["GET /items/{item_id} read_item", "GET /login login", "POST /login login"]
+        );
+    }
+
+    #[test]
+    fn test_rails_routes() {
+        let routes_rb = r#"Rails.application.routes.draw do
+  root "pages#home" # landing page
+  get "/about", to: "pages#about"
+  match "search", to: "search#index", via: [:get, :post]
+
+  resources :users, only: [:index, :show] do
+    resources :posts, except: %i[new edit destroy]
+    member do
+      get :preview
+    end
+  end
+  resource :profile, only: :show
+
+  namespace :admin do
+    resources :reports, only: :index
+    get "stats" => "dashboard#stats"
+  end
+end
+"#;
+        assert_eq!(
+            summary(&extract_routes("config/routes.rb", routes_rb)),
+            vec![
+                "GET / pages#home",
+                "GET /about pages#about",
+                "GET /search search#index",
+                "POST /search search#index",
+                "GET /users users#index",
+                "GET /users/:id users#show",
+                "GET /users/:user_id/posts posts#index",
+                "POST /users/:user_id/posts posts#create",
+                "GET /users/:user_id/posts/:id posts#show",
+                "PATCH /users/:user_id/posts/:id posts#update",
+                "GET /users/:id/preview users#preview",
+                "GET /profile profiles#show",
+                "GET /admin/reports admin/reports#index",
+                "GET /admin/stats admin/dashboard#stats",
+            ]
+        );
+        assert!(extract_routes("app/models/user.rb", routes_rb).is_empty());
+    }
+
+    #[test]
+    fn test_laravel_routes() {
+        let web = r#"<?php
+
+use App\Http\Controllers\{OrderController, PhotoController};
+use Illuminate\Support\Facades\Route;
+
+Route::get('/', function () {
+    return view('welcome');
+});
+Route::get('/users/{id}', [UserController::class, 'show'])->name('users.show');
+// Route::get('/old', [OldController::class, 'index']);
+Route::match(['get', 'post'], '/search', 'SearchController@index');
+
+Route::resource('photos', PhotoController::class)->only(['index', 'show']);
+
+Route::prefix('admin')->middleware('auth')->group(function () {
+    Route::apiResource('posts.comments', CommentController::class, ['except' => ['destroy']]);
+
+    Route::controller(OrderController::class)->group(function () {
+        Route::post('/orders/{order}', 'store');
+    });
+    Route::delete('/cache', ClearCacheController::class);
+});
+
+Route::view('/about', 'about');
+"#;
+        assert_eq!(
+            summary(&extract_routes("routes/web.php", web)),
+            vec![
+                "GET / -",
+                "GET /users/{id} UserController@show",
+                "GET /search SearchController@index",
+                "POST /search SearchController@index",
+                "GET /photos PhotoController@index",
+                "GET /photos/{photo} PhotoController@show",
+                "GET /admin/posts/{post}/comments CommentController@index",
+                "POST /admin/posts/{post}/comments CommentController@store",
+                "GET /admin/posts/{post}/comments/{comment} CommentController@show",
+                "PUT /admin/posts/{post}/comments/{comment} CommentController@update",
+                "POST /admin/orders/{order} OrderController@store",
+                "DELETE /admin/cache ClearCacheController@__invoke",
+                "GET /about -",
+            ]
+        );
+
+        let api = "<?php\nRoute::group(['prefix' => 'v1'], function () {\n    Route::get('status', [StatusController::class, 'show']);\n});\n";
+        assert_eq!(summary(&extract_routes("routes/api.php", api)), vec!["GET /api/v1/status StatusController@show"]);
+    }
+}
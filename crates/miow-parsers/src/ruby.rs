@@ -0,0 +1,814 @@
+use crate::types::*;
+use anyhow::Result;
+use regex::Regex;
+
+/// Rails base classes and the tag attached to classes inheriting from them
+const SUPERCLASS_TAGS: &[(&str, &str)] = &[
+    ("ApplicationRecord", "rails-model"),
+    ("ActiveRecord::Base", "rails-model"),
+    ("ApplicationController", "rails-controller"),
+    ("ActionController::Base", "rails-controller"),
+    ("ActionController::API", "rails-controller"),
+    ("ApplicationJob", "rails-job"),
+    ("ActiveJob::Base", "rails-job"),
+    ("ApplicationMailer", "rails-mailer"),
+    ("ActionMailer::Base", "rails-mailer"),
+    ("ActiveRecord::Migration", "rails-migration"),
+];
+
+/// ActiveRecord association macros
+const ASSOCIATIONS: &[&str] = &["belongs_to", "has_one", "has_many", "has_and_belongs_to_many"];
+
+/// Keywords that open a block closed by `end` when they start a statement
+const BLOCK_KEYWORDS: &[&str] = &["class", "module", "def", "if", "unless", "while", "until", "case", "begin", "for"];
+
+/// `validates` options that qualify a validation rather than name one
+const VALIDATION_CONDITIONS: &[&str] = &["if", "unless", "on", "allow_nil", "allow_blank", "message", "strict"];
+
+/// Extracts classes, modules, methods, requires and constants from Ruby
+/// source, plus the associations and validations of ActiveRecord models.
+/// Ruby blocks are keyword-delimited, so the file is read line by line,
+/// pairing `class`/`module`/`def`/`do`/`if`/... with their `end`.
+pub struct RubyParser {
+    class: Regex,
+    module: Regex,
+    def: Regex,
+    word: Regex,
+    heredoc: Regex,
+    require: Regex,
+    constant: Regex,
+    attribute: Regex,
+    mixin: Regex,
+    callback: Regex,
+    scope: Regex,
+    validates_of: Regex,
+    symbol: Regex,
+}
+
+/// A block opened by a line and not yet closed by its `end`
+struct Block {
+    /// The class, module or method the block defines; `None` for `do`, `if`, ...
+    symbol: Option<Symbol>,
+    /// `class << self`, where every `def` is a class method
+    singleton: bool,
+    /// Visibility set by a bare `private`/`protected`/`public` in a class body
+    visibility: Option<String>,
+    /// Associations and validations of an ActiveRecord model
+    fields: Vec<SchemaField>,
+}
+
+impl Block {
+    fn plain() -> Self {
+        Self { symbol: None, singleton: false, visibility: None, fields: vec![] }
+    }
+
+    fn defining(symbol: Symbol) -> Self {
+        Self { symbol: Some(symbol), ..Self::plain() }
+    }
+
+    fn is_namespace(&self) -> bool {
+        self.symbol
+            .as_ref()
+            .is_some_and(|s| matches!(s.kind, SymbolType::Class | SymbolType::Module))
+    }
+}
+
+/// A source line, plus `code`: the line with strings blanked and the comment
+/// cut off, so keywords inside them don't open or close blocks. `code` is
+/// byte-aligned with `raw`.
+struct SourceLine<'a> {
+    number: usize,
+    start: usize,
+    raw: &'a str,
+    code: String,
+}
+
+impl SourceLine<'_> {
+    /// The line without its comment
+    fn statement(&self) -> &str {
+        self.raw[..self.code.len()].trim()
+    }
+
+    fn range(&self) -> Range {
+        Range {
+            start_line: self.number,
+            end_line: self.number,
+            start_byte: self.start,
+            end_byte: self.start + self.raw.len(),
+        }
+    }
+}
+
+impl RubyParser {
+    pub fn new() -> Self {
+        Self {
+            class: Regex::new(r"^class\s+((?:::)?[A-Z][\w:]*)(?:\s*<\s*((?:::)?[A-Z][\w:]*(?:\[[^\]]*\])?))?").unwrap(),
+            module: Regex::new(r"^module\s+((?:::)?[A-Z][\w:]*)").unwrap(),
+            def: Regex::new(r"^def\s+(?:(self|[A-Z]\w*)\s*\.\s*)?([A-Za-z_]\w*[?!=]?|\[\]=?|[-+*/%<>=!~^&|]+@?)").unwrap(),
+            word: Regex::new(r"[A-Za-z_]\w*[?!]?").unwrap(),
+            heredoc: Regex::new(r#"<<[~-]?(['"`]?)([A-Z_][A-Z0-9_]*)['"`]?"#).unwrap(),
+            require: Regex::new(r#"^require(?:_relative)?\s*\(?\s*['"]([^'"]+)['"]"#).unwrap(),
+            constant: Regex::new(r"^([A-Z][A-Z0-9_]*)\s*=\s*([^=~>].*)$").unwrap(),
+            attribute: Regex::new(r"^attr_(reader|writer|accessor)\b(.*)$").unwrap(),
+            mixin: Regex::new(r"^(?:include|extend|prepend)\s*\(?\s*([A-Z][\w:]*(?:\s*,\s*[A-Z][\w:]*)*)").unwrap(),
+            callback: Regex::new(r"^(?:before|after|around)_\w+\b").unwrap(),
+            scope: Regex::new(r"^scope\s*\(?\s*:(\w+[?!]?)").unwrap(),
+            validates_of: Regex::new(r"^validates_(\w+)_of\b(.*)$").unwrap(),
+            symbol: Regex::new(r"^:(\w+[?!]?)$").unwrap(),
+        }
+    }
+
+    pub fn parse(&self, content: &str) -> Result<ParsedFile> {
+        let lines = self.source_lines(content);
+        let mut file = FileSymbols::default();
+        let mut imports = Vec::new();
+        let mut constants = Vec::new();
+        let mut blocks: Vec<Block> = Vec::new();
+
+        for (index, line) in lines.iter().enumerate() {
+            let statement = line.statement();
+            if statement.is_empty() {
+                continue;
+            }
+
+            if let Some(caps) = self.require.captures(statement) {
+                imports.push(Import {
+                    source: caps[1].to_string(),
+                    names: vec![],
+                    range: line.range(),
+                });
+            }
+
+            if blocks.last().is_none_or(|b| b.is_namespace()) {
+                if let Some(caps) = self.constant.captures(statement) {
+                    let owner = qualified_name(&blocks);
+                    constants.push(Constant {
+                        name: if owner.is_empty() { caps[1].to_string() } else { format!("{}::{}", owner, &caps[1]) },
+                        value: caps[2].trim().to_string(),
+                        type_annotation: None,
+                        category: ConstantCategory::Config,
+                        range: line.range(),
+                    });
+                }
+            }
+
+            if let Some(block) = blocks.last_mut() {
+                self.class_body_line(statement, line, block);
+            }
+            self.track_blocks(&lines, index, &mut blocks, &mut file, content);
+        }
+
+        // Blocks still open at the end of a broken file close at its last line
+        let last_line = lines.last().map(|l| l.number).unwrap_or(1);
+        while !blocks.is_empty() {
+            file.close(&mut blocks, content, last_line, content.len());
+        }
+
+        Ok(ParsedFile {
+            symbols: file.symbols,
+            imports,
+            exports: vec![], // Visibility is carried by access_modifier instead
+            design_tokens: vec![],
+            type_definitions: vec![],
+            constants,
+            schemas: file.schemas,
+            language: "ruby".to_string(),
+            syntax_errors: vec![],
+        })
+    }
+
+    /// Open and close the blocks a line's keywords call for, in the order they appear
+    fn track_blocks(&self, lines: &[SourceLine], index: usize, blocks: &mut Vec<Block>, file: &mut FileSymbols, content: &str) {
+        let line = &lines[index];
+        let code = line.code.as_str();
+        let first_word = self.word.find(code.trim_start()).map(|m| m.as_str()).unwrap_or("");
+        // `while x do` needs one `end`, not two
+        let is_loop = matches!(first_word, "while" | "until" | "for");
+
+        for m in self.word.find_iter(code) {
+            let word = m.as_str();
+            let before = code[..m.start()].trim_end();
+            let after = &code[m.end()..];
+            // `x.end`, `:end`, `@class` and `end:` hash keys aren't keywords
+            if before.ends_with(['.', ':', '@', '$']) || (after.starts_with(':') && !after.starts_with("::")) {
+                continue;
+            }
+
+            match word {
+                "end" => file.close(blocks, content, line.number, line.start + m.end()),
+                "do" if !is_loop => blocks.push(Block::plain()),
+                _ if BLOCK_KEYWORDS.contains(&word) => {
+                    let previous = before.rsplit(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or("");
+                    // Anywhere else `if`/`while`/... are modifiers: `return if x`
+                    let starts_statement = before.is_empty()
+                        || before.ends_with(['=', '(', ';', '|', '&', ',', '[', '{'])
+                        || matches!(previous, "else" | "then" | "do")
+                        || (word == "def" && visibility(previous).is_some());
+                    if !starts_statement {
+                        continue;
+                    }
+
+                    let declaration = &line.raw[m.start()..line.code.len()];
+                    let (block, endless) = self.open_block(word, declaration, previous, lines, index, blocks);
+                    blocks.push(block);
+                    // `def full_name = "..."` has no `end`; it ends with its line
+                    if endless {
+                        file.close(blocks, content, line.number, line.start + line.code.len());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The block a keyword opens, and whether it ends on the same line (an endless `def`)
+    fn open_block(
+        &self,
+        keyword: &str,
+        declaration: &str,
+        previous: &str,
+        lines: &[SourceLine],
+        index: usize,
+        blocks: &[Block],
+    ) -> (Block, bool) {
+        let line = &lines[index];
+        let start_byte = line.start + (line.raw.len() - line.raw.trim_start().len());
+        let symbol = |name: &str, kind: SymbolType, metadata: SymbolMetadata| Symbol {
+            name: name.trim_start_matches("::").to_string(),
+            kind,
+            range: Range { start_line: line.number, end_line: line.number, start_byte, end_byte: start_byte },
+            content: String::new(),
+            metadata,
+            children: vec![],
+            references: vec![],
+        };
+        let documented = || SymbolMetadata { documentation: documentation(lines, index), ..Default::default() };
+
+        match keyword {
+            "class" => {
+                if declaration["class".len()..].trim_start().starts_with("<<") {
+                    return (Block { singleton: true, ..Block::plain() }, false);
+                }
+                let Some(caps) = self.class.captures(declaration) else {
+                    return (Block::plain(), false);
+                };
+                let mut metadata = documented();
+                if let Some(superclass) = caps.get(2) {
+                    let superclass = superclass.as_str().trim_start_matches("::");
+                    let base = superclass.split('[').next().unwrap_or(superclass);
+                    if let Some((_, tag)) = SUPERCLASS_TAGS.iter().find(|(class, _)| *class == base) {
+                        metadata.tags.push(tag.to_string());
+                    }
+                    metadata.extends.push(superclass.to_string());
+                }
+                (Block::defining(symbol(&caps[1], SymbolType::Class, metadata)), false)
+            }
+            "module" => match self.module.captures(declaration) {
+                Some(caps) => (Block::defining(symbol(&caps[1], SymbolType::Module, documented())), false),
+                None => (Block::plain(), false),
+            },
+            "def" => {
+                let Some(caps) = self.def.captures(declaration) else {
+                    return (Block::plain(), false);
+                };
+                let rest = &declaration[caps.get(0).unwrap().end()..];
+                let (params, rest) = match rest.trim_start().strip_prefix('(') {
+                    Some(inner) => match matching_paren(inner) {
+                        Some(close) => (&inner[..close], &inner[close + 1..]),
+                        None => (inner, ""),
+                    },
+                    // `def greet name, greeting = "hi"`
+                    None => {
+                        let end = rest.find([';', '=']).unwrap_or(rest.len());
+                        (&rest[..end], &rest[end..])
+                    }
+                };
+                let rest = rest.trim_start();
+                let endless = rest.starts_with('=') && !rest.starts_with("==") && !rest.starts_with("=>");
+
+                let enclosing = blocks.iter().rev().find(|b| b.is_namespace() || b.singleton);
+                let metadata = SymbolMetadata {
+                    access_modifier: visibility(previous)
+                        .map(str::to_string)
+                        .or_else(|| enclosing.and_then(|b| b.visibility.clone())),
+                    is_static: caps.get(1).is_some() || blocks.last().is_some_and(|b| b.singleton),
+                    parameters: parameters(params),
+                    ..documented()
+                };
+                let kind = if &caps[2] == "initialize" {
+                    SymbolType::Constructor
+                } else if blocks.iter().any(Block::is_namespace) {
+                    SymbolType::Method
+                } else {
+                    SymbolType::Function
+                };
+                (Block::defining(symbol(&caps[2], kind, metadata)), endless)
+            }
+            _ => (Block::plain(), false),
+        }
+    }
+
+    /// Class-level declarations: visibility, mixins, attributes, callbacks,
+    /// scopes, and the associations and validations of models
+    fn class_body_line(&self, statement: &str, line: &SourceLine, block: &mut Block) {
+        if !(block.is_namespace() || block.singleton) {
+            return;
+        }
+        if let Some(visibility) = visibility(statement).filter(|_| statement != "private_class_method") {
+            block.visibility = Some(visibility.to_string());
+            return;
+        }
+        let Some(owner) = block.symbol.as_mut() else {
+            return;
+        };
+
+        let member = |name: &str, kind: SymbolType, metadata: SymbolMetadata| Symbol {
+            name: name.to_string(),
+            kind,
+            range: line.range(),
+            content: statement.to_string(),
+            metadata,
+            children: vec![],
+            references: vec![],
+        };
+
+        if let Some(caps) = self.mixin.captures(statement) {
+            owner.metadata.implements.extend(caps[1].split(',').map(|m| m.trim().to_string()));
+        } else if let Some(caps) = self.attribute.captures(statement) {
+            for argument in split_arguments(caps[2].trim_start_matches('(').trim_end_matches(')')) {
+                if let Some(name) = self.symbol.captures(argument) {
+                    let metadata = SymbolMetadata {
+                        is_readonly: &caps[1] == "reader",
+                        access_modifier: block.visibility.clone(),
+                        ..Default::default()
+                    };
+                    owner.children.push(member(&name[1], SymbolType::Property, metadata));
+                }
+            }
+        } else if self.callback.is_match(statement) {
+            owner.metadata.decorators.push(statement.to_string());
+        } else if let Some(caps) = self.scope.captures(statement) {
+            let metadata = SymbolMetadata { is_static: true, tags: vec!["rails-scope".to_string()], ..Default::default() };
+            owner.children.push(member(&caps[1], SymbolType::Method, metadata));
+        } else if owner.metadata.tags.iter().any(|t| t == "rails-model") {
+            self.model_macro(statement, &mut block.fields);
+        }
+    }
+
+    /// `has_many :posts, dependent: :destroy`, `validates :email, presence: true`
+    fn model_macro(&self, statement: &str, fields: &mut Vec<SchemaField>) {
+        let name_end = statement.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(statement.len());
+        let (macro_name, args) = statement.split_at(name_end);
+        let args = split_arguments(args.trim().trim_start_matches('(').trim_end_matches(')'));
+
+        if ASSOCIATIONS.contains(&macro_name) {
+            if let Some(name) = args.first().and_then(|a| self.symbol.captures(a)) {
+                fields.push(association(macro_name, &name[1], &args[1..]));
+            }
+            return;
+        }
+
+        let rules: Vec<String> = if macro_name == "validates" {
+            args.iter()
+                .filter_map(|a| option(a))
+                .map(|(key, _)| key)
+                .filter(|key| !VALIDATION_CONDITIONS.contains(key))
+                .map(str::to_string)
+                .collect()
+        } else if let Some(caps) = self.validates_of.captures(statement) {
+            vec![caps[1].to_string()]
+        } else {
+            return;
+        };
+
+        for argument in &args {
+            let Some(name) = self.symbol.captures(argument) else {
+                continue;
+            };
+            let index = match fields.iter().position(|f| f.name == name[1]) {
+                Some(index) => index,
+                None => {
+                    fields.push(SchemaField { name: name[1].to_string(), is_optional: true, ..Default::default() });
+                    fields.len() - 1
+                }
+            };
+            let field = &mut fields[index];
+            for rule in &rules {
+                if !field.validators.contains(rule) {
+                    field.validators.push(rule.clone());
+                }
+            }
+            if rules.iter().any(|r| r == "presence") {
+                field.is_required = true;
+                field.is_optional = false;
+            }
+        }
+    }
+
+    /// Split `content` into lines, blanking strings, heredoc bodies and `=begin` blocks
+    fn source_lines<'a>(&self, content: &'a str) -> Vec<SourceLine<'a>> {
+        let mut lines = Vec::new();
+        let mut heredocs: Vec<String> = Vec::new();
+        let mut in_block_comment = false;
+        let mut start = 0;
+
+        for (i, raw) in content.split_inclusive('\n').enumerate() {
+            let line_start = start;
+            start += raw.len();
+            let raw = raw.trim_end_matches(['\n', '\r']);
+
+            let code = if let Some(terminator) = heredocs.first() {
+                if raw.trim() == terminator {
+                    heredocs.remove(0);
+                }
+                String::new()
+            } else if in_block_comment {
+                in_block_comment = !raw.starts_with("=end");
+                String::new()
+            } else if raw.starts_with("=begin") {
+                in_block_comment = true;
+                String::new()
+            } else if raw == "__END__" {
+                break;
+            } else {
+                let code = blank_strings(raw);
+                heredocs.extend(self.heredoc.captures_iter(&raw[..code.len()]).map(|c| c[2].to_string()));
+                code
+            };
+
+            lines.push(SourceLine { number: i + 1, start: line_start, raw, code });
+        }
+        lines
+    }
+}
+
+impl Default for RubyParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Top-level symbols and model schemas, filled in as blocks close
+#[derive(Default)]
+struct FileSymbols {
+    symbols: Vec<Symbol>,
+    schemas: Vec<ValidationSchema>,
+}
+
+impl FileSymbols {
+    /// Finish the innermost block at `end_byte`, attaching its symbol to the
+    /// enclosing class or module, or to the file
+    fn close(&mut self, blocks: &mut Vec<Block>, content: &str, end_line: usize, end_byte: usize) {
+        let Some(block) = blocks.pop() else {
+            return;
+        };
+        let Some(mut symbol) = block.symbol else {
+            return;
+        };
+
+        symbol.range.end_line = end_line;
+        symbol.range.end_byte = end_byte;
+        symbol.content = content[symbol.range.start_byte..end_byte].to_string();
+
+        if symbol.metadata.tags.iter().any(|t| t == "rails-model") {
+            self.schemas.push(ValidationSchema {
+                name: symbol.name.clone(),
+                schema_type: SchemaType::ActiveRecord,
+                definition: symbol.content.clone(),
+                fields: block.fields,
+                range: symbol.range.clone(),
+            });
+        }
+
+        match blocks.iter_mut().rev().find(|b| b.is_namespace()) {
+            Some(owner) => owner.symbol.as_mut().unwrap().children.push(symbol),
+            None => self.symbols.push(symbol),
+        }
+    }
+}
+
+/// `private`, `protected` and `public`; `private_class_method` makes a class method private
+fn visibility(word: &str) -> Option<&'static str> {
+    match word {
+        "private" | "private_class_method" => Some("private"),
+        "protected" => Some("protected"),
+        "public" => Some("public"),
+        _ => None,
+    }
+}
+
+/// `Outer::Inner` for the classes and modules enclosing the current line
+fn qualified_name(blocks: &[Block]) -> String {
+    blocks
+        .iter()
+        .filter_map(|b| b.symbol.as_ref().filter(|s| matches!(s.kind, SymbolType::Class | SymbolType::Module)))
+        .map(|s| s.name.as_str())
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// The `#` comment lines directly above a declaration, minus magic comments
+fn documentation(lines: &[SourceLine], index: usize) -> Option<String> {
+    let mut comment: Vec<&str> = lines[..index]
+        .iter()
+        .rev()
+        .map(|l| l.raw.trim())
+        .take_while(|l| l.starts_with('#'))
+        .map(|l| l.trim_start_matches('#').trim())
+        .filter(|l| !l.starts_with("frozen_string_literal:") && !l.starts_with("typed:") && !l.starts_with("encoding:"))
+        .collect();
+    comment.reverse();
+    let comment = comment.join("\n").trim().to_string();
+    (!comment.is_empty()).then_some(comment)
+}
+
+/// `name`, `name = 1`, `key:`, `key: 1`, `*args`, `**opts`, `&block`
+fn parameters(params: &str) -> Vec<Parameter> {
+    split_arguments(params)
+        .into_iter()
+        .map(|param| {
+            let (name, default_value) = match option(param) {
+                Some((key, value)) => (key.to_string(), value),
+                None => match param.split_once('=') {
+                    Some((name, value)) => (name.trim().to_string(), Some(value.trim().to_string())),
+                    None => (param.trim_end_matches(':').to_string(), None),
+                },
+            };
+            Parameter {
+                is_optional: default_value.is_some() || name.starts_with(['*', '&']),
+                name,
+                type_annotation: None,
+                default_value,
+            }
+        })
+        .collect()
+}
+
+/// `key: value` (or a bare `key:`) -> (`key`, the value if any); `:sym` and `Foo::Bar` aren't options
+fn option(argument: &str) -> Option<(&str, Option<String>)> {
+    let (key, value) = argument.split_once(':')?;
+    let is_key = !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+    let separated = value.is_empty() || value.starts_with([' ', '\t']);
+    let value = value.trim();
+    (is_key && separated).then(|| (key, (!value.is_empty()).then(|| value.to_string())))
+}
+
+/// An association as a schema field pointing at the associated model
+fn association(kind: &str, name: &str, options: &[&str]) -> SchemaField {
+    let options: Vec<(&str, Option<String>)> = options.iter().filter_map(|o| option(o)).collect();
+    let value = |key: &str| options.iter().find(|(k, _)| *k == key).and_then(|(_, v)| v.clone());
+
+    let class_name = value("class_name").map(|c| c.trim_matches(['"', '\'']).to_string()).unwrap_or_else(|| {
+        let singular = match kind {
+            "has_many" | "has_and_belongs_to_many" => singularize(name),
+            _ => name.to_string(),
+        };
+        camelize(&singular)
+    });
+    let is_required = kind == "belongs_to" && value("optional").as_deref() != Some("true");
+
+    SchemaField {
+        name: name.to_string(),
+        validation_rules: options
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k, v.clone().unwrap_or_default()))
+            .collect(),
+        is_required,
+        is_optional: !is_required,
+        type_annotation: Some(kind.to_string()),
+        references: Some(class_name),
+        ..Default::default()
+    }
+}
+
+fn singularize(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if name.ends_with("sses") || name.ends_with("ches") || name.ends_with("shes") || name.ends_with("xes") {
+        name[..name.len() - 2].to_string()
+    } else {
+        name.strip_suffix('s').unwrap_or(name).to_string()
+    }
+}
+
+/// `line_item` -> `LineItem`
+fn camelize(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Split call arguments on top-level commas
+fn split_arguments(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quote, mut start) = (0i32, None, 0);
+    for (i, c) in args.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+/// Index of the `)` closing a paren whose contents start `text`
+fn matching_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Replace the contents of string literals and `%w[...]`-style lists with
+/// spaces and cut the line at its comment, keeping byte offsets
+fn blank_strings(line: &str) -> String {
+    let bytes = line.as_bytes();
+    let mut code = String::with_capacity(line.len());
+    let mut close: Option<u8> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        match close {
+            Some(end) => {
+                if c == b'\\' && i + 1 < bytes.len() {
+                    code.push_str("  ");
+                    i += 2;
+                    continue;
+                }
+                if c == end {
+                    close = None;
+                    code.push(c as char);
+                } else {
+                    // Byte by byte, so multi-byte characters keep their width
+                    code.push(' ');
+                }
+            }
+            None => match c {
+                b'#' => break,
+                b'"' | b'\'' | b'`' => {
+                    close = Some(c);
+                    code.push(c as char);
+                }
+                b'%' if i + 2 < bytes.len()
+                    && b"wWiIqQ".contains(&bytes[i + 1])
+                    && bytes[i + 2].is_ascii_punctuation() =>
+                {
+                    let end = match bytes[i + 2] {
+                        b'[' => b']',
+                        b'(' => b')',
+                        b'{' => b'}',
+                        b'<' => b'>',
+                        other => other,
+                    };
+                    close = Some(end);
+                    code.push_str(&line[i..i + 3]);
+                    i += 3;
+                    continue;
+                }
+                _ => {
+                    // Copy whole characters so `code` stays valid UTF-8
+                    let len = line[i..].chars().next().map(char::len_utf8).unwrap_or(1);
+                    code.push_str(&line[i..i + len]);
+                    i += len;
+                    continue;
+                }
+            },
+        }
+        i += 1;
+    }
+
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rails_model() {
+        let code = r#"# frozen_string_literal: true
+
+require "digest"
+require_relative "concerns/searchable"
+
+module Billing
+  # A customer's order.
+  # Totals are kept in cents.
+  class Order < ApplicationRecord
+    include Searchable
+    STATUSES = %w[pending paid shipped].freeze
+
+    belongs_to :customer, class_name: "User"
+    has_many :line_items, dependent: :destroy
+    validates :number, :customer, presence: true, uniqueness: { scope: :customer_id }
+    before_save :compute_total
+    scope :recent, -> { where("created_at > ?", 1.week.ago) }
+
+    attr_reader :discount
+
+    def self.for_number(number)
+      find_by(number: number) || raise(ActiveRecord::RecordNotFound, "no #{number} end")
+    end
+
+    def total(currency = "USD", rounded: true)
+      items = line_items.map do |item|
+        item.price if item.active?
+      end
+      return 0 if items.empty?
+      items.sum
+    end
+
+    def label = "Order #{number}"
+
+    private
+
+    def compute_total
+      self.total_cents = <<~SQL
+        SELECT sum(price) FROM line_items where end_date is null
+        end
+      SQL
+    end
+  end
+end
+
+def helper; end
+"#;
+
+        let parsed = RubyParser::new().parse(code).unwrap();
+        assert_eq!(parsed.language, "ruby");
+        let sources: Vec<&str> = parsed.imports.iter().map(|i| i.source.as_str()).collect();
+        assert_eq!(sources, vec!["digest", "concerns/searchable"]);
+
+        assert_eq!(parsed.symbols.len(), 2);
+        let module = &parsed.symbols[0];
+        assert_eq!(module.kind, SymbolType::Module);
+        assert_eq!(module.range.end_line, 44);
+        let helper = &parsed.symbols[1];
+        assert_eq!((helper.name.as_str(), &helper.kind), ("helper", &SymbolType::Function));
+
+        let order = &module.children[0];
+        assert_eq!(order.name, "Order");
+        assert_eq!((order.range.start_line, order.range.end_line), (9, 43));
+        assert_eq!(order.metadata.extends, vec!["ApplicationRecord"]);
+        assert_eq!(order.metadata.implements, vec!["Searchable"]);
+        assert!(order.metadata.tags.contains(&"rails-model".to_string()));
+        assert_eq!(order.metadata.decorators, vec!["before_save :compute_total"]);
+        assert_eq!(order.metadata.documentation.as_deref(), Some("A customer's order.\nTotals are kept in cents."));
+
+        let names: Vec<&str> = order.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["recent", "discount", "for_number", "total", "label", "compute_total"]);
+        let for_number = &order.children[2];
+        assert!(for_number.metadata.is_static);
+        let total = &order.children[3];
+        assert_eq!((total.range.start_line, total.range.end_line), (25, 31));
+        let params: Vec<(&str, Option<&str>)> = total
+            .metadata
+            .parameters
+            .iter()
+            .map(|p| (p.name.as_str(), p.default_value.as_deref()))
+            .collect();
+        assert_eq!(params, vec![("currency", Some("\"USD\"")), ("rounded", Some("true"))]);
+        assert_eq!(order.children[4].range.end_line, 33);
+        assert_eq!(order.children[5].metadata.access_modifier.as_deref(), Some("private"));
+
+        assert_eq!(parsed.constants[0].name, "Billing::Order::STATUSES");
+
+        let schema = &parsed.schemas[0];
+        assert!(matches!(schema.schema_type, SchemaType::ActiveRecord));
+        let customer = schema.fields.iter().find(|f| f.name == "customer").unwrap();
+        assert_eq!(customer.references.as_deref(), Some("User"));
+        assert!(customer.is_required);
+        assert_eq!(customer.validators, vec!["presence", "uniqueness"]);
+        let items = schema.fields.iter().find(|f| f.name == "line_items").unwrap();
+        assert_eq!(items.type_annotation.as_deref(), Some("has_many"));
+        assert_eq!(items.references.as_deref(), Some("LineItem"));
+        assert!(schema.fields.iter().any(|f| f.name == "number" && f.is_required));
+    }
+    #[test]
+    fn test_blank_strings_keeps_utf8_boundaries() {
+        assert_eq!(blank_strings("tags = %w[a b] # note"), "tags = %w[   ] ");
+        // Not a literal: `é` can't delimit one, and slicing into it would panic
+        assert_eq!(blank_strings("x = %wé + 1"), "x = %wé + 1");
+        assert_eq!(blank_strings("s = \"héllo\""), "s = \"      \"");
+    }
+}
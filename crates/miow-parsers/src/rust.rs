@@ -1,19 +1,14 @@
+use crate::database::extract_diesel_tables;
+use crate::diagnostics::{for_each_declaration, tree_diagnostics};
 use crate::types::*;
 use anyhow::{Context, Result};
-use tree_sitter::{Node, Parser, Query, QueryCursor};
+use tree_sitter::{Node, Parser, Tree};
 
-pub struct RustParser {
-    parser: Parser,
-}
+pub struct RustParser;
 
 impl RustParser {
     pub fn new() -> Self {
-        let mut parser = Parser::new();
-        let language = tree_sitter_rust::language();
-        parser
-            .set_language(language)
-            .expect("Error loading Rust grammar");
-        Self { parser }
+        Self
     }
 
     pub fn parse(&self, content: &str) -> Result<ParsedFile> {
@@ -26,6 +21,11 @@ impl RustParser {
             .parse(content, None)
             .context("Failed to parse Rust content")?;
 
+        self.parse_tree(&tree, content)
+    }
+
+    /// Extract from an already-parsed tree; lets callers reuse an incrementally reparsed tree
+    pub fn parse_tree(&self, tree: &Tree, content: &str) -> Result<ParsedFile> {
         let root_node = tree.root_node();
 
         let symbols = self.extract_symbols(&root_node, content)?;
@@ -40,20 +40,18 @@ impl RustParser {
             design_tokens: vec![],
             type_definitions,
             constants,
-            schemas: vec![],          // Rust doesn't have runtime validation schemas like Zod
+            schemas: extract_diesel_tables(content),
             language: "rust".to_string(),
+            syntax_errors: tree_diagnostics(tree, content),
         })
     }
 
     fn extract_symbols(&self, node: &Node, source: &str) -> Result<Vec<Symbol>> {
         let mut symbols = Vec::new();
-        let mut cursor = node.walk();
-
-        for child in node.children(&mut cursor) {
-            if let Some(symbol) = self.process_node(&child, source)? {
-                symbols.push(symbol);
-            }
-        }
+        for_each_declaration(node, |candidate| {
+            symbols.extend(self.process_node(candidate, source)?);
+            Ok(())
+        });
 
         Ok(symbols)
     }
@@ -65,7 +63,7 @@ impl RustParser {
         match kind {
             "struct_item" => {
                 let name = self
-                    .get_child_text(node, "type_identifier", source)
+                    .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "Anonymous".to_string());
                 let range = self.get_range(node);
                 let metadata = self.extract_metadata(node, source)?;
@@ -82,7 +80,7 @@ impl RustParser {
             }
             "enum_item" => {
                 let name = self
-                    .get_child_text(node, "type_identifier", source)
+                    .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "Anonymous".to_string());
                 let range = self.get_range(node);
                 let metadata = self.extract_metadata(node, source)?;
@@ -99,7 +97,7 @@ impl RustParser {
             }
             "function_item" => {
                 let name = self
-                    .get_child_text(node, "identifier", source)
+                    .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "anonymous".to_string());
                 let range = self.get_range(node);
                 let metadata = self.extract_function_metadata(node, source)?;
@@ -116,7 +114,7 @@ impl RustParser {
             }
             "impl_item" => {
                 let type_name = self
-                    .get_child_text(node, "type_identifier", source)
+                    .get_child_text(node, "type", source)
                     .unwrap_or_default();
                 let trait_name = if let Some(trait_node) = node.child_by_field_name("trait") {
                     trait_node.utf8_text(source.as_bytes())?.to_string()
@@ -142,7 +140,7 @@ impl RustParser {
             }
             "macro_definition" => {
                 let name = self
-                    .get_child_text(node, "identifier", source)
+                    .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "anonymous".to_string());
                 Ok(Some(Symbol {
                     name,
@@ -156,7 +154,7 @@ impl RustParser {
             }
             "mod_item" => {
                 let name = self
-                    .get_child_text(node, "identifier", source)
+                    .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "anonymous".to_string());
                 Ok(Some(Symbol {
                     name,
@@ -170,7 +168,7 @@ impl RustParser {
             }
             "trait_item" => {
                 let name = self
-                    .get_child_text(node, "type_identifier", source)
+                    .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "Anonymous".to_string());
                 let range = self.get_range(node);
                 let mut metadata = self.extract_metadata(node, source)?;
@@ -193,7 +191,7 @@ impl RustParser {
             "type_item" => {
                 // Type alias: type MyType = SomeType;
                 let name = self
-                    .get_child_text(node, "type_identifier", source)
+                    .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "Anonymous".to_string());
                 Ok(Some(Symbol {
                     name,
@@ -207,7 +205,7 @@ impl RustParser {
             }
             "const_item" => {
                 let name = self
-                    .get_child_text(node, "identifier", source)
+                    .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "CONST".to_string());
                 let mut metadata = self.extract_metadata(node, source)?;
                 
@@ -228,7 +226,7 @@ impl RustParser {
             }
             "static_item" => {
                 let name = self
-                    .get_child_text(node, "identifier", source)
+                    .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "STATIC".to_string());
                 let mut metadata = self.extract_metadata(node, source)?;
                 metadata.is_static = true;
@@ -264,14 +262,17 @@ impl RustParser {
                     let type_annotation =
                         type_node.map(|n| n.utf8_text(source.as_bytes()).unwrap().to_string());
 
-                    let mut metadata = SymbolMetadata::default();
-                    metadata.return_type = type_annotation;
-                    metadata.access_modifier =
+                    let access_modifier =
                         if child.utf8_text(source.as_bytes())?.starts_with("pub") {
                             Some("public".to_string())
                         } else {
                             Some("private".to_string())
                         };
+                    let metadata = SymbolMetadata {
+                        return_type: type_annotation,
+                        access_modifier,
+                        ..Default::default()
+                    };
 
                     fields.push(Symbol {
                         name,
@@ -349,9 +350,74 @@ impl RustParser {
             Some("private".to_string())
         };
 
+        metadata.documentation = self.extract_doc_comment(node, source)?;
+        metadata.decorators = self.extract_attributes(node, source)?;
+        for attribute in &metadata.decorators {
+            // `#[derive(Debug, Serialize)]` is a trait implementation in all but syntax
+            if let Some(derives) = attribute
+                .strip_prefix("#[derive(")
+                .and_then(|rest| rest.strip_suffix(")]"))
+            {
+                metadata
+                    .implements
+                    .extend(derives.split(',').map(|d| d.trim().to_string()).filter(|d| !d.is_empty()));
+            } else if attribute.ends_with("::main]") {
+                metadata.tags.push("entrypoint".to_string());
+            } else if attribute == "#[test]" || attribute.ends_with("::test]") {
+                metadata.tags.push("test".to_string());
+            }
+        }
+
         Ok(metadata)
     }
 
+    /// `///` and `/** */` doc comments preceding the item, attributes may sit in between
+    fn extract_doc_comment(&self, node: &Node, source: &str) -> Result<Option<String>> {
+        let mut lines = Vec::new();
+        let mut prev = node.prev_sibling();
+        while let Some(sibling) = prev {
+            match sibling.kind() {
+                "attribute_item" => {}
+                "line_comment" | "block_comment" => {
+                    let text = sibling.utf8_text(source.as_bytes())?.trim();
+                    if let Some(doc) = text.strip_prefix("///") {
+                        lines.push(doc.trim().to_string());
+                    } else if let Some(doc) = text.strip_prefix("/**") {
+                        let doc = doc.trim_end_matches("*/");
+                        for line in doc.lines().rev() {
+                            lines.push(line.trim().trim_start_matches('*').trim().to_string());
+                        }
+                    } else {
+                        break; // A plain comment ends the doc block
+                    }
+                }
+                _ => break,
+            }
+            prev = sibling.prev_sibling();
+        }
+
+        lines.reverse();
+        let doc = lines.join("\n").trim().to_string();
+        Ok((!doc.is_empty()).then_some(doc))
+    }
+
+    /// Outer attributes (`#[derive(...)]`, `#[tokio::main]`) are siblings preceding the item;
+    /// doc comments may be interleaved with them
+    fn extract_attributes(&self, node: &Node, source: &str) -> Result<Vec<String>> {
+        let mut attributes = Vec::new();
+        let mut prev = node.prev_sibling();
+        while let Some(sibling) = prev {
+            match sibling.kind() {
+                "attribute_item" => attributes.push(sibling.utf8_text(source.as_bytes())?.to_string()),
+                "line_comment" | "block_comment" => {}
+                _ => break,
+            }
+            prev = sibling.prev_sibling();
+        }
+        attributes.reverse();
+        Ok(attributes)
+    }
+
     fn extract_function_metadata(&self, node: &Node, source: &str) -> Result<SymbolMetadata> {
         let mut metadata = self.extract_metadata(node, source)?;
 
@@ -458,7 +524,7 @@ impl RustParser {
                     }
                     "associated_type" => {
                         let name = self
-                            .get_child_text(&child, "type_identifier", source)
+                            .get_child_text(&child, "name", source)
                             .unwrap_or_else(|| "AssociatedType".to_string());
                         
                         members.push(Symbol {
@@ -526,7 +592,7 @@ impl RustParser {
 
     fn extract_struct_type_def(&self, node: &Node, source: &str) -> Result<Option<TypeDefinition>> {
         let name = self
-            .get_child_text(node, "type_identifier", source)
+            .get_child_text(node, "name", source)
             .unwrap_or_else(|| "Anonymous".to_string());
         
         let mut properties = Vec::new();
@@ -572,7 +638,7 @@ impl RustParser {
 
     fn extract_enum_type_def(&self, node: &Node, source: &str) -> Result<Option<TypeDefinition>> {
         let name = self
-            .get_child_text(node, "type_identifier", source)
+            .get_child_text(node, "name", source)
             .unwrap_or_else(|| "Anonymous".to_string());
         
         let mut properties = Vec::new();
@@ -616,7 +682,7 @@ impl RustParser {
 
     fn extract_type_alias(&self, node: &Node, source: &str) -> Result<Option<TypeDefinition>> {
         let name = self
-            .get_child_text(node, "type_identifier", source)
+            .get_child_text(node, "name", source)
             .unwrap_or_else(|| "Anonymous".to_string());
         
         let mut generic_params = Vec::new();
@@ -638,7 +704,7 @@ impl RustParser {
 
     fn extract_trait_type_def(&self, node: &Node, source: &str) -> Result<Option<TypeDefinition>> {
         let name = self
-            .get_child_text(node, "type_identifier", source)
+            .get_child_text(node, "name", source)
             .unwrap_or_else(|| "Anonymous".to_string());
         
         let mut properties = Vec::new();
@@ -670,7 +736,7 @@ impl RustParser {
                     }
                     "associated_type" => {
                         let type_name = self
-                            .get_child_text(&child, "type_identifier", source)
+                            .get_child_text(&child, "name", source)
                             .unwrap_or_else(|| "AssociatedType".to_string());
                         
                         properties.push(TypeProperty {
@@ -703,7 +769,7 @@ impl RustParser {
             match child.kind() {
                 "const_item" => {
                     let name = self
-                        .get_child_text(&child, "identifier", source)
+                        .get_child_text(&child, "name", source)
                         .unwrap_or_else(|| "CONST".to_string());
                     
                     let type_annotation = child
@@ -725,7 +791,7 @@ impl RustParser {
                 }
                 "static_item" => {
                     let name = self
-                        .get_child_text(&child, "identifier", source)
+                        .get_child_text(&child, "name", source)
                         .unwrap_or_else(|| "STATIC".to_string());
                     
                     let type_annotation = child
@@ -758,3 +824,48 @@ impl Default for RustParser {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_comments() {
+        let code = r#"/// Parses invoices.
+///
+/// Amounts are in cents.
+#[derive(Debug)]
+pub struct Invoice {
+    total: u64,
+}
+
+/** Sum of the line items. */
+pub fn total() -> u64 {
+    0
+}
+
+// Not documentation
+fn plain() {}
+"#;
+        let parsed = RustParser::new().parse(code).unwrap();
+        let symbol = |name: &str| parsed.symbols.iter().find(|s| s.name == name).unwrap();
+        let invoice = symbol("Invoice");
+        assert_eq!(invoice.metadata.documentation.as_deref(), Some("Parses invoices.\n\nAmounts are in cents."));
+        assert_eq!(invoice.metadata.implements, vec!["Debug"]);
+
+        assert_eq!(symbol("total").metadata.documentation.as_deref(), Some("Sum of the line items."));
+        assert_eq!(symbol("plain").metadata.documentation, None);
+    }
+
+    #[test]
+    fn test_item_names() {
+        let code = "pub enum Status { Open }\ntrait Shape {}\nimpl Shape for Status {}\nmod billing {}\nmacro_rules! log { () => {} }\ntype Id = u64;\nconst LIMIT: u32 = 3;\nstatic NAME: &str = \"x\";\n";
+        let parsed = RustParser::new().parse(code).unwrap();
+        let names: Vec<&str> = parsed.symbols.iter().map(|s| s.name.as_str()).collect();
+        for expected in ["Status", "Shape", "impl Shape for Status", "billing", "log", "Id", "LIMIT", "NAME"] {
+            assert!(names.contains(&expected), "{} missing from {:?}", expected, names);
+        }
+        assert!(parsed.type_definitions.iter().any(|t| t.name == "Status"));
+        assert!(parsed.constants.iter().any(|c| c.name == "LIMIT"));
+    }
+}
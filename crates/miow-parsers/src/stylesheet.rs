@@ -0,0 +1,334 @@
+use crate::types::*;
+use anyhow::Result;
+use regex::Regex;
+
+/// Tailwind theme sections and the token type their entries map to
+const TAILWIND_SECTIONS: &[(&str, DesignTokenType)] = &[
+    ("colors", DesignTokenType::Color),
+    ("backgroundColor", DesignTokenType::Color),
+    ("textColor", DesignTokenType::Color),
+    ("spacing", DesignTokenType::Spacing),
+    ("fontFamily", DesignTokenType::FontFamily),
+    ("fontSize", DesignTokenType::FontSize),
+    ("fontWeight", DesignTokenType::FontWeight),
+    ("borderRadius", DesignTokenType::BorderRadius),
+    ("boxShadow", DesignTokenType::Shadow),
+    ("screens", DesignTokenType::Breakpoint),
+    ("zIndex", DesignTokenType::ZIndex),
+    ("opacity", DesignTokenType::Opacity),
+    ("transitionDuration", DesignTokenType::Transition),
+    ("animation", DesignTokenType::Animation),
+];
+
+/// Extracts design tokens from stylesheets and Tailwind configs: CSS custom
+/// properties, SCSS variables and `theme` / `theme.extend` values.
+pub struct StylesheetParser {
+    custom_property: Regex,
+    scss_variable: Regex,
+}
+
+impl StylesheetParser {
+    pub fn new() -> Self {
+        Self {
+            custom_property: Regex::new(r"(--[A-Za-z0-9_-]+)\s*:\s*([^;}]+)").unwrap(),
+            scss_variable: Regex::new(r"(?m)^\s*\$([A-Za-z0-9_-]+)\s*:\s*([^;]+?)\s*(?:!default\s*)?;").unwrap(),
+        }
+    }
+
+    /// Parse a `.css` / `.scss` / `.sass` file; `language` is recorded on the result
+    pub fn parse(&self, content: &str, language: &str) -> Result<ParsedFile> {
+        let mut design_tokens = Vec::new();
+
+        for caps in self.custom_property.captures_iter(content) {
+            let whole = caps.get(0).unwrap();
+            let name = caps[1].to_string();
+            let value = caps[2].trim().to_string();
+
+            design_tokens.push(DesignToken {
+                token_type: classify_token(&name, &value),
+                name,
+                value,
+                context: enclosing_selector(content, whole.start()),
+                range: range_for(content, whole.start(), whole.end()),
+            });
+        }
+
+        for caps in self.scss_variable.captures_iter(content) {
+            let whole = caps.get(0).unwrap();
+            let name = format!("${}", &caps[1]);
+            let value = caps[2].trim().to_string();
+
+            design_tokens.push(DesignToken {
+                token_type: classify_token(&name, &value),
+                name,
+                value,
+                context: "scss variable".to_string(),
+                range: range_for(content, whole.start(), whole.end()),
+            });
+        }
+
+        Ok(ParsedFile {
+            symbols: vec![],
+            imports: vec![],
+            exports: vec![],
+            design_tokens,
+            type_definitions: vec![],
+            constants: vec![],
+            schemas: vec![],
+            language: language.to_string(),
+            syntax_errors: vec![],
+        })
+    }
+
+    /// Parse `tailwind.config.{js,ts,cjs,mjs}`. Nested palettes are flattened the way
+    /// Tailwind names its classes: `colors.primary.500` becomes `primary-500`.
+    pub fn parse_tailwind_config(&self, content: &str) -> Result<ParsedFile> {
+        let mut design_tokens = Vec::new();
+
+        for (section, token_type) in TAILWIND_SECTIONS {
+            let pattern = Regex::new(&format!(r"\b{}\s*:\s*\{{", section)).unwrap();
+            for m in pattern.find_iter(content) {
+                let open = m.end() - 1;
+                let Some(close) = matching_brace(content, open) else {
+                    continue;
+                };
+
+                let mut entries = Vec::new();
+                collect_entries(&content[open + 1..close], "", &mut entries);
+
+                for (name, value) in entries {
+                    design_tokens.push(DesignToken {
+                        token_type: token_type.clone(),
+                        name,
+                        value,
+                        context: format!("tailwind.config: {}", section),
+                        range: range_for(content, m.start(), close + 1),
+                    });
+                }
+            }
+        }
+
+        Ok(ParsedFile {
+            symbols: vec![],
+            imports: vec![],
+            exports: vec![],
+            design_tokens,
+            type_definitions: vec![],
+            constants: vec![],
+            schemas: vec![],
+            language: "tailwind".to_string(),
+            syntax_errors: vec![],
+        })
+    }
+}
+
+impl Default for StylesheetParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether a file name is a Tailwind config (`tailwind.config.js`, `.ts`, `.cjs`, `.mjs`)
+pub fn is_tailwind_config(file_name: &str) -> bool {
+    file_name.starts_with("tailwind.config.")
+}
+
+/// Guess the token type from the variable name, falling back to the shape of the value
+fn classify_token(name: &str, value: &str) -> DesignTokenType {
+    let name = name.to_lowercase();
+    let value = value.to_lowercase();
+
+    if value.starts_with('#') || value.starts_with("rgb") || value.starts_with("hsl") || value.starts_with("oklch") || name.contains("color") {
+        DesignTokenType::Color
+    } else if name.contains("shadow") {
+        DesignTokenType::Shadow
+    } else if name.contains("radius") || name.contains("rounded") {
+        DesignTokenType::BorderRadius
+    } else if name.contains("font-family") || name.contains("font-sans") || name.contains("font-mono") {
+        DesignTokenType::FontFamily
+    } else if name.contains("font-size") || name.contains("text-") {
+        DesignTokenType::FontSize
+    } else if name.contains("font-weight") || name.contains("weight") {
+        DesignTokenType::FontWeight
+    } else if name.contains("z-index") || name.starts_with("--z-") || name.starts_with("$z-") {
+        DesignTokenType::ZIndex
+    } else if name.contains("breakpoint") || name.contains("screen") {
+        DesignTokenType::Breakpoint
+    } else if name.contains("duration") || name.contains("transition") || name.contains("ease") {
+        DesignTokenType::Transition
+    } else if name.contains("opacity") {
+        DesignTokenType::Opacity
+    } else if name.contains("space") || name.contains("spacing") || name.contains("gap") || name.contains("padding") || name.contains("margin") {
+        DesignTokenType::Spacing
+    } else if name.contains("font") || name.contains("line-height") || name.contains("letter") {
+        DesignTokenType::Typography
+    } else {
+        DesignTokenType::CSSVariable
+    }
+}
+
+/// Selector of the rule block containing `offset` (e.g. `:root`, `.dark`)
+fn enclosing_selector(content: &str, offset: usize) -> String {
+    let before = &content[..offset];
+    let Some(open) = before.rfind('{') else {
+        return "global".to_string();
+    };
+    if before[open..].contains('}') {
+        return "global".to_string();
+    }
+
+    let selector_start = before[..open]
+        .rfind(['}', ';', '{'])
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    before[selector_start..open].trim().to_string()
+}
+
+/// Index of the `}` closing the `{` at `open`, skipping string literals
+fn matching_brace(content: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_string: Option<char> = None;
+
+    for (i, c) in content[open..].char_indices() {
+        match (in_string, c) {
+            (Some(q), c) if c == q => in_string = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'' | '`') => in_string = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Flatten `key: value` pairs of a JS object body; `DEFAULT` keys collapse onto the parent
+fn collect_entries(body: &str, prefix: &str, out: &mut Vec<(String, String)>) {
+    let entry = Regex::new(r#"^\s*['"]?([A-Za-z0-9_.\-/]+)['"]?\s*:\s*"#).unwrap();
+    let mut rest = body;
+
+    while let Some(caps) = entry.captures(rest) {
+        let key = caps[1].to_string();
+        let name = match (prefix.is_empty(), key.as_str()) {
+            (true, _) => key.clone(),
+            (false, "DEFAULT") => prefix.to_string(),
+            (false, _) => format!("{}-{}", prefix, key),
+        };
+        let value_start = caps.get(0).unwrap().end();
+        let after = &rest[value_start..];
+
+        let consumed = if after.starts_with('{') {
+            let Some(close) = matching_brace(after, 0) else {
+                return;
+            };
+            collect_entries(&after[1..close], &name, out);
+            close + 1
+        } else {
+            let end = value_end(after);
+            let value = after[..end]
+                .trim()
+                .trim_matches(|c| c == '\'' || c == '"' || c == '`')
+                .to_string();
+            if !value.is_empty() {
+                out.push((name, value));
+            }
+            end
+        };
+
+        rest = after[consumed..].trim_start().trim_start_matches(',');
+        // Skip comments between entries
+        while let Some(stripped) = rest.trim_start().strip_prefix("//") {
+            rest = stripped.split_once('\n').map(|(_, r)| r).unwrap_or("");
+        }
+    }
+}
+
+/// End of a scalar/array value: the first top-level `,` or end of input
+fn value_end(text: &str) -> usize {
+    let mut depth = 0i32;
+    let mut in_string: Option<char> = None;
+
+    for (i, c) in text.char_indices() {
+        match (in_string, c) {
+            (Some(q), c) if c == q => in_string = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'' | '`') => in_string = Some(c),
+            (None, '[' | '(' | '{') => depth += 1,
+            (None, ']' | ')' | '}') => depth -= 1,
+            (None, ',' | '\n') if depth == 0 => return i,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+fn range_for(content: &str, start: usize, end: usize) -> Range {
+    Range {
+        start_line: content[..start].matches('\n').count() + 1,
+        end_line: content[..end].matches('\n').count() + 1,
+        start_byte: start,
+        end_byte: end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_css_and_scss_variables() {
+        let css = r#"
+:root {
+  --color-primary: #2563eb;
+  --space-4: 1rem;
+}
+.dark { --color-primary: #60a5fa; }
+$radius-lg: 12px !default;
+"#;
+
+        let parsed = StylesheetParser::new().parse(css, "scss").unwrap();
+        let tokens = &parsed.design_tokens;
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[0].name, "--color-primary");
+        assert_eq!(tokens[0].context, ":root");
+        assert!(matches!(tokens[0].token_type, DesignTokenType::Color));
+        assert!(matches!(tokens[1].token_type, DesignTokenType::Spacing));
+        assert_eq!(tokens[2].context, ".dark");
+        assert_eq!(tokens[3].name, "$radius-lg");
+        assert_eq!(tokens[3].value, "12px");
+    }
+
+    #[test]
+    fn test_tailwind_theme_is_flattened() {
+        let config = r##"
+module.exports = {
+  content: ['./src/**/*.tsx'],
+  theme: {
+    extend: {
+      colors: {
+        brand: {
+          DEFAULT: '#0ea5e9',
+          500: '#0ea5e9', // main
+          700: "#0369a1",
+        },
+        danger: 'rgb(239 68 68)',
+      },
+      spacing: { '18': '4.5rem' },
+      fontFamily: { sans: ['Inter', 'sans-serif'] },
+    },
+  },
+};
+"##;
+
+        let parsed = StylesheetParser::new().parse_tailwind_config(config).unwrap();
+        let names: Vec<&str> = parsed.design_tokens.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["brand", "brand-500", "brand-700", "danger", "18", "sans"]);
+        assert_eq!(parsed.design_tokens[2].value, "#0369a1");
+        assert_eq!(parsed.design_tokens[5].value, "['Inter', 'sans-serif']");
+    }
+}
@@ -0,0 +1,468 @@
+use crate::types::*;
+use anyhow::Result;
+use regex::Regex;
+
+/// Extracts Terraform configuration. `resource`, `data` and `module` blocks
+/// become schemas whose fields are their arguments; variables, outputs and
+/// locals become constants (`var.region`, `output.bucket_arn`, `local.env`),
+/// as do the assignments of a `.tfvars` file. Module sources and required
+/// providers are recorded as imports.
+pub struct TerraformParser {
+    provider_source: Regex,
+    reference: Regex,
+}
+
+/// A top-level or nested item of an HCL body
+enum Item {
+    /// `name = value`, with the raw text of the value
+    Attribute { name: String, value: String, start: usize, end: usize },
+    /// `kind "label" "label" { ... }`; `open` and `close` are its braces
+    Block { kind: String, labels: Vec<String>, start: usize, open: usize, close: usize },
+}
+
+impl TerraformParser {
+    pub fn new() -> Self {
+        Self {
+            provider_source: Regex::new(r#"\bsource\s*=\s*"([^"]+)""#).unwrap(),
+            // `aws_vpc.main.id`, `data.aws_ami.ubuntu.id`, `module.network.vpc_id`
+            reference: Regex::new(r"\b(data\.[a-z]\w*\.[\w-]+|module\.[\w-]+|[a-z][a-z0-9]*_\w+\.[\w-]+)").unwrap(),
+        }
+    }
+
+    pub fn parse(&self, content: &str) -> Result<ParsedFile> {
+        let source = Source::new(content);
+        let mut parsed = ParsedFile {
+            symbols: vec![],
+            imports: vec![],
+            exports: vec![],
+            design_tokens: vec![],
+            type_definitions: vec![],
+            constants: vec![],
+            schemas: vec![],
+            language: "terraform".to_string(),
+            syntax_errors: vec![],
+        };
+
+        for item in source.items(0, content.len()) {
+            match item {
+                // Only `.tfvars` files assign at the top level
+                Item::Attribute { name, value, start, end } => parsed.constants.push(Constant {
+                    name: format!("var.{}", name),
+                    value,
+                    type_annotation: None,
+                    category: ConstantCategory::Config,
+                    range: source.range(start, end),
+                }),
+                block => self.extract_block(&source, &block, &mut parsed),
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn extract_block(&self, source: &Source, block: &Item, parsed: &mut ParsedFile) {
+        let Item::Block { kind, labels, start, open, close } = block else {
+            return;
+        };
+        let (start, close) = (*start, *close);
+        let range = source.range(start, close + 1);
+        let body = source.items(open + 1, close);
+        let attribute = |key: &str| {
+            body.iter().find_map(|item| match item {
+                Item::Attribute { name, value, .. } if name == key => Some(value.clone()),
+                _ => None,
+            })
+        };
+        let mut constant = |name: String, value: String, type_annotation: Option<String>, range: Range| {
+            parsed.constants.push(Constant { name, value, type_annotation, category: ConstantCategory::Config, range });
+        };
+
+        match (kind.as_str(), labels.as_slice()) {
+            ("resource", [resource_type, name]) => {
+                parsed.schemas.push(self.schema(source, format!("{}.{}", resource_type, name), &body, start, close));
+            }
+            ("data", [data_type, name]) => {
+                parsed.schemas.push(self.schema(source, format!("data.{}.{}", data_type, name), &body, start, close));
+            }
+            ("module", [name]) => {
+                if let Some(module_source) = attribute("source") {
+                    parsed.imports.push(Import {
+                        source: unquote(&module_source),
+                        names: vec![import_name(name)],
+                        range: range.clone(),
+                    });
+                }
+                parsed.schemas.push(self.schema(source, format!("module.{}", name), &body, start, close));
+            }
+            ("variable", [name]) => {
+                let value = attribute("default").unwrap_or_default();
+                constant(format!("var.{}", name), value, attribute("type"), range);
+            }
+            ("output", [name]) => {
+                let value = attribute("value").unwrap_or_default();
+                constant(format!("output.{}", name), value, None, range);
+            }
+            ("locals", []) => {
+                for item in &body {
+                    if let Item::Attribute { name, value, start, end } = item {
+                        constant(format!("local.{}", name), value.clone(), None, source.range(*start, *end));
+                    }
+                }
+            }
+            ("terraform", []) => {
+                // `required_providers { aws = { source = "hashicorp/aws", version = "~> 5.0" } }`
+                for item in &body {
+                    let Item::Block { kind, open, close, .. } = item else {
+                        continue;
+                    };
+                    if kind != "required_providers" {
+                        continue;
+                    }
+                    for provider in source.items(open + 1, *close) {
+                        let Item::Attribute { name, value, start, end } = provider else {
+                            continue;
+                        };
+                        let provider_source = self.provider_source.captures(&value).map(|c| c[1].to_string());
+                        parsed.imports.push(Import {
+                            source: provider_source.unwrap_or_else(|| format!("hashicorp/{}", name)),
+                            names: vec![import_name(&name)],
+                            range: source.range(start, end),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// A resource, data source or module and its arguments
+    fn schema(&self, source: &Source, name: String, body: &[Item], start: usize, close: usize) -> ValidationSchema {
+        ValidationSchema {
+            name,
+            schema_type: SchemaType::Terraform,
+            definition: source.raw[start..close + 1].to_string(),
+            fields: body.iter().map(|item| self.field(item)).collect(),
+            range: source.range(start, close + 1),
+        }
+    }
+
+    /// An argument, or a nested block such as `versioning { ... }`
+    fn field(&self, item: &Item) -> SchemaField {
+        match item {
+            Item::Attribute { name, value, .. } => SchemaField {
+                name: name.clone(),
+                default_value: Some(value.clone()),
+                references: self.reference(value),
+                ..Default::default()
+            },
+            Item::Block { kind, labels, .. } => SchemaField {
+                name: std::iter::once(kind).chain(labels).cloned().collect::<Vec<_>>().join(" "),
+                type_annotation: Some("block".to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The resource, data source or module an expression reads from
+    fn reference(&self, value: &str) -> Option<String> {
+        self.reference
+            .find_iter(value)
+            .find(|m| !value[..m.start()].ends_with('.'))
+            .map(|m| m.as_str().to_string())
+    }
+}
+
+impl Default for TerraformParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The file alongside `code`, a copy with comments, string contents and
+/// heredocs blanked to spaces, byte for byte
+struct Source<'a> {
+    raw: &'a str,
+    code: String,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> Source<'a> {
+    fn new(raw: &'a str) -> Self {
+        let line_starts = std::iter::once(0).chain(raw.match_indices('\n').map(|(i, _)| i + 1)).collect();
+        Self { raw, code: blank_hcl(raw), line_starts }
+    }
+
+    fn range(&self, start: usize, end: usize) -> Range {
+        let line = |offset: usize| self.line_starts.partition_point(|&s| s <= offset);
+        Range {
+            start_line: line(start),
+            end_line: line(end.saturating_sub(1).max(start)),
+            start_byte: start,
+            end_byte: end,
+        }
+    }
+
+    /// Attributes and blocks of the body between `start` and `end`
+    fn items(&self, start: usize, end: usize) -> Vec<Item> {
+        let code = self.code.as_bytes();
+        // HCL names may hold any letter; bytes from 0x80 are parts of non-ASCII characters
+        let is_identifier = |c: u8| c.is_ascii_alphanumeric() || c == b'_' || c == b'-' || c >= 0x80;
+        let mut items = Vec::new();
+        let mut i = start;
+
+        while i < end {
+            // 0xC0 and up start a multi-byte character; 0x80..0xC0 continue one
+            if !(code[i].is_ascii_alphabetic() || code[i] == b'_' || code[i] >= 0xC0) {
+                i += 1;
+                continue;
+            }
+            let name_end = (i..end).find(|&j| !is_identifier(code[j])).unwrap_or(end);
+            let name = self.raw[i..name_end].to_string();
+            let line_end = self.code[name_end..end].find('\n').map(|n| name_end + n).unwrap_or(end);
+            let header = &self.code[name_end..line_end];
+
+            if header.trim_start().starts_with('=') && !header.trim_start().starts_with("==") {
+                let value_start = name_end + header.find('=').unwrap_or(0) + 1;
+                let value_end = self.value_end(value_start, end);
+                // Up to the last code on the line, leaving out a trailing comment
+                let code_end = value_start + self.code[value_start..value_end].trim_end().len();
+                items.push(Item::Attribute {
+                    name,
+                    value: self.raw[value_start..code_end].trim().to_string(),
+                    start: i,
+                    end: value_end,
+                });
+                i = value_end;
+                continue;
+            }
+
+            let Some(open) = header.find('{').map(|o| name_end + o) else {
+                i = line_end;
+                continue;
+            };
+            let close = self.matching_brace(open, end).unwrap_or(end.saturating_sub(1));
+            // `"aws_s3_bucket" "logs"`; bare labels are allowed too
+            let labels = self.raw[name_end..open]
+                .split('"')
+                .enumerate()
+                .flat_map(|(n, part)| match n % 2 {
+                    1 => vec![part.to_string()],
+                    _ => part.split_whitespace().map(str::to_string).collect(),
+                })
+                .collect();
+            items.push(Item::Block { kind: name, labels, start: i, open, close });
+            i = close + 1;
+        }
+        items
+    }
+
+    /// Where a value ends: the first newline outside brackets
+    fn value_end(&self, start: usize, end: usize) -> usize {
+        let mut depth = 0;
+        for (i, c) in self.code[start..end].bytes().enumerate() {
+            match c {
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' | b'}' => depth -= 1,
+                b'\n' if depth <= 0 => return start + i,
+                _ => {}
+            }
+        }
+        end
+    }
+
+    fn matching_brace(&self, open: usize, end: usize) -> Option<usize> {
+        let mut depth = 0;
+        for (i, c) in self.code[open..end].bytes().enumerate() {
+            match c {
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(open + i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+fn import_name(name: &str) -> ImportName {
+    ImportName { name: name.to_string(), alias: None, is_default: false, is_namespace: false, is_type: false }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches('"').to_string()
+}
+
+/// Blank `#`, `//` and `/* */` comments, the contents of strings (so
+/// `"${var.env}-logs"` doesn't count as braces) and heredoc bodies,
+/// keeping every byte offset; heredoc newlines go too, so a heredoc
+/// value reads as one line
+fn blank_hcl(content: &str) -> String {
+    let bytes = content.as_bytes();
+    let mut out = bytes.to_vec();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        // Bytes, not `&str`: `i` walks through multi-byte characters too
+        let rest = &bytes[i..];
+        let skip_to = if rest.starts_with(b"#") || rest.starts_with(b"//") {
+            let end = rest.iter().position(|&b| b == b'\n').map(|n| i + n).unwrap_or(bytes.len());
+            out[i..end].fill(b' ');
+            end
+        } else if rest.starts_with(b"/*") {
+            let end = rest.windows(2).position(|w| w == b"*/").map(|n| i + n + 2).unwrap_or(bytes.len());
+            for b in &mut out[i..end] {
+                if *b != b'\n' {
+                    *b = b' ';
+                }
+            }
+            end
+        } else if bytes[i] == b'"' {
+            let mut j = i + 1;
+            while j < bytes.len() && bytes[j] != b'"' && bytes[j] != b'\n' {
+                j += if bytes[j] == b'\\' { 2 } else { 1 };
+            }
+            let close = j.min(bytes.len());
+            out[i + 1..close].fill(b' ');
+            close + 1
+        } else if rest.starts_with(b"<<") {
+            // `<<EOT` or `<<-EOT`, up to a line holding only the label
+            let label: String = content[i + 2..].trim_start_matches('-').chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+            let body = rest.iter().position(|&b| b == b'\n').map(|n| i + n);
+            match (label.is_empty(), body) {
+                (false, Some(body)) => {
+                    let mut end = bytes.len();
+                    let mut line = body + 1;
+                    while line < bytes.len() {
+                        let line_end = content[line..].find('\n').map(|n| line + n).unwrap_or(bytes.len());
+                        if content[line..line_end].trim() == label {
+                            end = line + (content[line..line_end].len() - content[line..line_end].trim_start().len());
+                            break;
+                        }
+                        line = line_end + 1;
+                    }
+                    out[body..end].fill(b' ');
+                    end
+                }
+                _ => i + 2,
+            }
+        } else {
+            i + 1
+        };
+        i = skip_to.max(i + 1);
+    }
+
+    // Only ASCII bytes were replaced, and whole multi-byte characters at that
+    String::from_utf8(out).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_terraform() {
+        let code = r#"terraform {
+  required_providers {
+    aws = { source = "hashicorp/aws", version = "~> 5.0" }
+  }
+}
+
+variable "environment" {
+  type    = string
+  default = "staging"
+}
+
+locals {
+  prefix = "${var.environment}-app" # e.g. staging-app {
+  tags = {
+    Environment = var.environment
+  }
+}
+
+/* Buckets are named {prefix}-{purpose} */
+resource "aws_s3_bucket" "logs" {
+  bucket = "${local.prefix}-logs"
+  tags   = local.tags
+
+  versioning {
+    enabled = true
+  }
+}
+
+resource "aws_s3_bucket_policy" "logs" {
+  bucket = aws_s3_bucket.logs.id
+  policy = <<EOF
+{ "Version": "2012-10-17" }
+EOF
+}
+
+data "aws_iam_policy_document" "read" {}
+
+module "network" {
+  source = "./modules/network"
+  cidr   = "10.0.0.0/16"
+}
+
+output "logs_bucket_arn" {
+  value = aws_s3_bucket.logs.arn
+}
+"#;
+
+        let parsed = TerraformParser::new().parse(code).unwrap();
+        assert_eq!(parsed.language, "terraform");
+
+        let schemas: Vec<&str> = parsed.schemas.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            schemas,
+            vec!["aws_s3_bucket.logs", "aws_s3_bucket_policy.logs", "data.aws_iam_policy_document.read", "module.network"]
+        );
+        let bucket = &parsed.schemas[0];
+        assert!(matches!(bucket.schema_type, SchemaType::Terraform));
+        assert_eq!((bucket.range.start_line, bucket.range.end_line), (20, 27));
+        let fields: Vec<&str> = bucket.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(fields, vec!["bucket", "tags", "versioning"]);
+        assert_eq!(bucket.fields[0].default_value.as_deref(), Some("\"${local.prefix}-logs\""));
+        assert_eq!(bucket.fields[2].type_annotation.as_deref(), Some("block"));
+
+        let policy = &parsed.schemas[1];
+        assert_eq!(policy.fields[0].references.as_deref(), Some("aws_s3_bucket.logs"));
+        assert!(policy.fields[1].default_value.as_deref().unwrap().contains("2012-10-17"));
+
+        let constants: Vec<(&str, &str)> = parsed.constants.iter().map(|c| (c.name.as_str(), c.value.as_str())).collect();
+        assert_eq!(
+            constants,
+            vec![
+                ("var.environment", "\"staging\""),
+                ("local.prefix", "\"${var.environment}-app\""),
+                ("local.tags", "{\n    Environment = var.environment\n  }"),
+                ("output.logs_bucket_arn", "aws_s3_bucket.logs.arn"),
+            ]
+        );
+        assert_eq!(parsed.constants[0].type_annotation.as_deref(), Some("string"));
+
+        let imports: Vec<(&str, &str)> =
+            parsed.imports.iter().map(|i| (i.source.as_str(), i.names[0].name.as_str())).collect();
+        assert_eq!(imports, vec![("hashicorp/aws", "aws"), ("./modules/network", "network")]);
+
+        let tfvars = TerraformParser::new().parse("environment = \"production\"\ninstance_count = 3\n").unwrap();
+        assert_eq!(tfvars.constants[1].name, "var.instance_count");
+        assert_eq!(tfvars.constants[1].value, "3");
+    }
+
+    #[test]
+    fn test_non_ascii_outside_strings_and_comments() {
+        let code = "# Café settings\nlocals {\n  café = 1\n  name = \"José\" /* naïve */\n}\n";
+
+        let parsed = TerraformParser::new().parse(code).unwrap();
+        let names: Vec<&str> = parsed.constants.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["local.café", "local.name"]);
+        assert_eq!(parsed.constants[1].value, "\"José\"");
+
+        let blanked = blank_hcl(code);
+        assert_eq!(blanked.len(), code.len());
+        assert!(blanked.contains("café = 1") && !blanked.contains("José") && !blanked.contains("naïve"));
+    }
+}
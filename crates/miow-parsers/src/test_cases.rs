@@ -0,0 +1,242 @@
+use crate::types::*;
+use regex::Regex;
+use std::collections::HashSet;
+
+/// Names kept per test; enough to cover what a test exercises without storing every local
+const MAX_REFERENCES_PER_TEST: usize = 50;
+
+/// Quotes that open a string in JS/TS, template literals included
+const JS_QUOTES: &[u8] = b"\"'`";
+const RUST_QUOTES: &[u8] = b"\"";
+
+const IGNORED_IDENTIFIERS: &[&str] = &[
+    "assert", "assert_eq", "assert_ne", "async", "await", "const", "describe", "else", "expect", "false", "for",
+    "from", "let", "mut", "new", "None", "null", "return", "self", "Self", "Some", "test", "this", "true",
+    "undefined", "unwrap", "var", "while", "with",
+];
+
+/// Whether a path looks like a test file: `*.test.ts`, `*.spec.tsx`, `test_*.py`,
+/// `*_test.py`, or anything under a `tests/` / `__tests__/` directory
+pub fn is_test_file(relative_path: &str) -> bool {
+    let path = relative_path.replace('\\', "/");
+    let file_name = path.rsplit('/').next().unwrap_or(&path);
+
+    path.split('/').any(|dir| dir == "tests" || dir == "__tests__")
+        || file_name.contains(".test.")
+        || file_name.contains(".spec.")
+        || (file_name.ends_with(".py") && (file_name.starts_with("test_") || file_name.ends_with("_test.py")))
+}
+
+/// Extract test cases with the identifiers their bodies use, which the graph
+/// resolves against symbol names to find the tests exercising a symbol.
+/// Covers `it`/`test` blocks in JS/TS test files, `#[test]`-style functions
+/// in Rust and `test_*` functions in Python test files.
+pub fn extract_tests(relative_path: &str, content: &str) -> Vec<TestCase> {
+    let extension = relative_path.rsplit('.').next().unwrap_or("");
+    match extension {
+        "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" if is_test_file(relative_path) => js_tests(content),
+        "rs" => rust_tests(content),
+        "py" if is_test_file(relative_path) => python_tests(content),
+        _ => vec![],
+    }
+}
+
+/// `it('renders', () => { ... })` / `test("adds", async () => { ... })`
+fn js_tests(content: &str) -> Vec<TestCase> {
+    let call = Regex::new(r#"\b(?:it|test)(?:\.(?:only|skip|each\([^)]*\)))?\s*\(\s*['"`]([^'"`]+)['"`]"#).unwrap();
+
+    call.captures_iter(content)
+        .filter_map(|c| {
+            let m = c.get(0).unwrap();
+            let open = m.start() + content[m.start()..].find('(')?;
+            let close = matching_delimiter(content, open, '(', ')', JS_QUOTES)?;
+            Some(test_case(c[1].to_string(), "jest", content, m.start(), m.end(), close + 1))
+        })
+        .collect()
+}
+
+/// `#[test]` / `#[tokio::test]` / `#[rstest]` functions
+fn rust_tests(content: &str) -> Vec<TestCase> {
+    let attribute =
+        Regex::new(r"#\[(?:[\w:]+::)?(?:test|rstest)(?:\([^\]]*\))?\]\s*(?:#\[[^\]]*\]\s*)*(?:pub\s+)?(?:async\s+)?fn\s+(\w+)")
+            .unwrap();
+
+    attribute
+        .captures_iter(content)
+        .filter_map(|c| {
+            let m = c.get(0).unwrap();
+            let open = m.end() + content[m.end()..].find('{')?;
+            let close = matching_delimiter(content, open, '{', '}', RUST_QUOTES)?;
+            Some(test_case(c[1].to_string(), "rust", content, m.start(), open, close + 1))
+        })
+        .collect()
+}
+
+/// `def test_*` functions, top-level or in `Test*` classes; the body is every
+/// following line indented deeper than the `def`
+fn python_tests(content: &str) -> Vec<TestCase> {
+    let def = Regex::new(r"(?m)^([ \t]*)(?:async\s+)?def\s+(test_\w*)\s*\(").unwrap();
+
+    def.captures_iter(content)
+        .map(|c| {
+            let m = c.get(0).unwrap();
+            let indent = c[1].len();
+            let mut offset = content[m.start()..].find('\n').map_or(content.len(), |i| m.start() + i);
+            let mut end = offset;
+            for line in content[offset..].split_inclusive('\n') {
+                let trimmed = line.trim_start();
+                if !trimmed.is_empty() {
+                    if line.len() - trimmed.len() <= indent {
+                        break;
+                    }
+                    // Trailing blank lines belong to whatever comes next
+                    end = offset + line.trim_end().len();
+                }
+                offset += line.len();
+            }
+            test_case(c[2].to_string(), "pytest", content, m.start(), m.end(), end)
+        })
+        .collect()
+}
+
+/// `body_start` skips attributes and the test's own name so they aren't taken for references
+fn test_case(name: String, framework: &str, content: &str, start: usize, body_start: usize, end: usize) -> TestCase {
+    TestCase {
+        references: referenced_identifiers(&content[body_start..end], &name),
+        name,
+        framework: framework.to_string(),
+        content: content[start..end].trim_end().to_string(),
+        range: Range {
+            start_line: content[..start].matches('\n').count() + 1,
+            end_line: content[..end].matches('\n').count() + 1,
+            start_byte: start,
+            end_byte: end,
+        },
+    }
+}
+
+fn referenced_identifiers(body: &str, test_name: &str) -> Vec<String> {
+    let identifier = Regex::new(r"\b[A-Za-z_][A-Za-z0-9_]*\b").unwrap();
+    let mut seen = HashSet::new();
+
+    identifier
+        .find_iter(body)
+        .map(|m| m.as_str())
+        .filter(|name| name.len() > 2 && *name != test_name && !IGNORED_IDENTIFIERS.contains(name))
+        .filter(|name| seen.insert(*name))
+        .take(MAX_REFERENCES_PER_TEST)
+        .map(String::from)
+        .collect()
+}
+
+/// Offset of the delimiter closing the one at `open`, skipping `//` and `/* */`
+/// comments and the string literals `quotes` open. Without `'` among them a `'`
+/// only starts a char literal, since in Rust it also marks lifetimes.
+fn matching_delimiter(text: &str, open: usize, open_char: char, close_char: char, quotes: &[u8]) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let (open_byte, close_byte) = (open_char as u8, close_char as u8);
+    let mut depth = 0;
+    let mut i = open;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let next = bytes.get(i + 1).copied();
+        if c == b'/' && next == Some(b'/') {
+            i = text[i..].find('\n').map_or(bytes.len(), |n| i + n);
+            continue;
+        }
+        if c == b'/' && next == Some(b'*') {
+            i = text[i + 2..].find("*/").map_or(bytes.len(), |n| i + 2 + n + 2);
+            continue;
+        }
+        if quotes.contains(&c) {
+            i = string_end(bytes, i);
+            continue;
+        }
+        if c == b'\'' {
+            if let Some(end) = char_literal_end(text, i) {
+                i = end;
+                continue;
+            }
+        }
+        if c == open_byte {
+            depth += 1;
+        } else if c == close_byte {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Offset just past the string literal opened at `start`, honoring backslash escapes
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Offset just past a char literal (`'{'`, `'\''`, `'\u{7d}'`) at `start`; `None` for a lifetime
+fn char_literal_end(text: &str, start: usize) -> Option<usize> {
+    let rest = &text[start + 1..];
+    if rest.starts_with('\\') {
+        return rest.char_indices().skip(2).take(10).find(|(_, c)| *c == '\'').map(|(n, _)| start + 1 + n + 1);
+    }
+    let c = rest.chars().next()?;
+    rest[c.len_utf8()..].starts_with('\'').then(|| start + 1 + c.len_utf8() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_test_file() {
+        assert!(is_test_file("src/components/Button.test.tsx"));
+        assert!(is_test_file("src/__tests__/api.ts"));
+        assert!(is_test_file("tests/test_models.py"));
+        assert!(!is_test_file("src/components/Button.tsx"));
+        assert!(!is_test_file("src/testing_utils.py"));
+    }
+
+    #[test]
+    fn test_extract_tests_with_references() {
+        let ts = "import { formatPrice } from './price';\n\ndescribe('price', () => {\n  it('formats cents', () => {\n    expect(formatPrice(100)).toBe('$1.00');\n  });\n});\n";
+        let tests = extract_tests("src/price.test.ts", ts);
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].name, "formats cents");
+        assert_eq!(tests[0].range.start_line, 4);
+        assert!(tests[0].references.contains(&"formatPrice".to_string()));
+
+        let rs = "fn add(a: i32, b: i32) -> i32 { a + b }\n\n#[cfg(test)]\nmod tests {\n    #[tokio::test]\n    async fn test_add() {\n        assert_eq!(add(1, 2), 3);\n    }\n}\n";
+        let tests = extract_tests("src/math.rs", rs);
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].name, "test_add");
+        assert_eq!(tests[0].references, vec!["add"]);
+
+        let py = "class TestCart:\n    def test_total(self):\n        cart = Cart()\n        assert cart.total() == 0\n\ndef helper():\n    pass\n";
+        let tests = extract_tests("tests/test_cart.py", py);
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].range.end_line, 4);
+        assert!(tests[0].references.contains(&"Cart".to_string()));
+    }
+
+    #[test]
+    fn test_matching_delimiter_skips_strings_and_comments() {
+        let rs = "{ let s = \"}\"; let c = '}'; // }\n /* } */ fn f<'a>(x: &'a str) {} }";
+        assert_eq!(matching_delimiter(rs, 0, '{', '}', RUST_QUOTES), Some(rs.len() - 1));
+
+        let ts = "it('closes })', () => { expect(`${a})`).toBe(')'); })";
+        let open = ts.find('(').unwrap();
+        assert_eq!(matching_delimiter(ts, open, '(', ')', JS_QUOTES), Some(ts.len() - 1));
+    }
+}
@@ -1,17 +1,28 @@
+use crate::diagnostics::ParseDiagnostic;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
-/// Represents a parsed file with extracted symbols and metadata
+/// Represents a parsed file with extracted symbols and metadata. Parser
+/// plugins send it as JSON, so everything but the symbols may be left out.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedFile {
     pub symbols: Vec<Symbol>,
+    #[serde(default)]
     pub imports: Vec<Import>,
+    #[serde(default)]
     pub exports: Vec<Export>,
+    #[serde(default)]
     pub design_tokens: Vec<DesignToken>,
+    #[serde(default)]
     pub type_definitions: Vec<TypeDefinition>,
+    #[serde(default)]
     pub constants: Vec<Constant>,
+    #[serde(default)]
     pub schemas: Vec<ValidationSchema>,
+    #[serde(default)]
     pub language: String,
+    /// Syntax errors tree-sitter recovered from while building the tree
+    #[serde(default)]
+    pub syntax_errors: Vec<ParseDiagnostic>,
 }
 
 /// A generic symbol (class, function, interface, variable, etc.)
@@ -20,9 +31,13 @@ pub struct Symbol {
     pub name: String,
     pub kind: SymbolType,
     pub range: Range,
+    #[serde(default)]
     pub content: String,
+    #[serde(default)]
     pub metadata: SymbolMetadata,
+    #[serde(default)]
     pub children: Vec<Symbol>,
+    #[serde(default)]
     pub references: Vec<String>, // Names of other symbols referenced by this one
 }
 
@@ -30,7 +45,9 @@ pub struct Symbol {
 pub struct Range {
     pub start_line: usize,
     pub end_line: usize,
+    #[serde(default)]
     pub start_byte: usize,
+    #[serde(default)]
     pub end_byte: usize,
 }
 
@@ -67,6 +84,7 @@ pub enum SymbolType {
 }
 
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct SymbolMetadata {
     pub documentation: Option<String>,
     pub jsdoc: Option<String>,
@@ -87,6 +105,9 @@ pub struct SymbolMetadata {
     pub props: Vec<PropDefinition>,
     pub hooks_used: Vec<String>,
     pub state_variables: Vec<String>,
+    // Declared with `export` or listed in an `export { ... }` clause
+    #[serde(default)]
+    pub is_exported: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -126,11 +147,42 @@ pub struct ImportName {
 /// An export statement
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Export {
-    pub name: String,
+    pub name: String, // `*` for `export * from`
     pub alias: Option<String>,
     pub is_default: bool,
     pub is_type: bool,
     pub range: Range,
+    // Module re-exported from (`export { x } from './x'`)
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// An HTTP route or page: `GET /users/:id` handled by `show_user`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Route {
+    pub method: String, // GET, POST, ..., ANY, or PAGE for file-system pages
+    pub path: String,
+    pub handler: Option<String>,
+    pub framework: String,
+    pub range: Range,
+}
+
+/// A test and the identifiers its body uses, resolved against symbol names in the graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCase {
+    pub name: String,
+    pub framework: String, // jest, rust, pytest
+    pub content: String,
+    pub references: Vec<String>,
+    pub range: Range,
+}
+
+/// A read of an environment variable: `process.env.DATABASE_URL`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvVarUsage {
+    pub name: String,
+    pub accessor: String, // process.env, import.meta.env, env::var, os.environ, ...
+    pub range: Range,
 }
 
 /// Design tokens (colors, spacing, etc.)
@@ -252,7 +304,17 @@ pub struct ValidationSchema {
 pub enum SchemaType {
     Zod,
     Yup,
-    JoiCustom,
+    Joi,
+    ClassValidator,
+    // Database schemas: SQL migrations, ORM models
+    Sql,
+    Prisma,
+    SqlAlchemy,
+    Diesel,
+    ActiveRecord,
+    Eloquent,
+    // Infrastructure as code: Terraform resources, data sources and modules
+    Terraform,
     Other(String),
 }
 
@@ -267,4 +329,7 @@ pub struct SchemaField {
     pub is_optional: bool,
     pub validators: Vec<String>,
     pub description: Option<String>,
+    // Foreign key / relation target for database columns, e.g. `users.id`
+    #[serde(default)]
+    pub references: Option<String>,
 }
@@ -1,15 +1,13 @@
+use crate::diagnostics::{for_each_declaration, tree_diagnostics};
 use crate::types::*;
 use anyhow::{Context, Result};
-use tree_sitter::{Node, Parser, Query, QueryCursor};
+use tree_sitter::{Node, Parser, Query, QueryCursor, Tree};
 
-pub struct TypeScriptParser {
-    parser: Parser,
-}
+pub struct TypeScriptParser;
 
 impl TypeScriptParser {
     pub fn new() -> Self {
-        let mut parser = Parser::new();
-        Self { parser }
+        Self
     }
 
     pub fn parse(&self, content: &str, is_tsx: bool) -> Result<ParsedFile> {
@@ -29,11 +27,23 @@ impl TypeScriptParser {
             .parse(content, None)
             .context("Failed to parse TypeScript content")?;
 
+        self.parse_tree(&tree, content, is_tsx)
+    }
+
+    /// Extract from an already-parsed tree; lets callers reuse an incrementally reparsed tree
+    pub fn parse_tree(&self, tree: &Tree, content: &str, is_tsx: bool) -> Result<ParsedFile> {
         let root_node = tree.root_node();
 
-        let symbols = self.extract_symbols(&root_node, content, is_tsx)?;
+        let mut symbols = self.extract_symbols(&root_node, content, is_tsx)?;
         let imports = self.extract_imports(&root_node, content)?;
         let exports = self.extract_exports(&root_node, content)?;
+
+        // Mark symbols that are part of the module's public API
+        for symbol in &mut symbols {
+            if exports.iter().any(|e| e.source.is_none() && e.name == symbol.name) {
+                symbol.metadata.is_exported = true;
+            }
+        }
         let design_tokens = self.extract_design_tokens(&root_node, content)?;
         let type_definitions = self.extract_type_definitions(&root_node, content)?;
         let constants = self.extract_constants(&root_node, content)?;
@@ -52,18 +62,16 @@ impl TypeScriptParser {
             } else {
                 "typescript".to_string()
             },
+            syntax_errors: tree_diagnostics(tree, content),
         })
     }
 
     fn extract_symbols(&self, root_node: &Node, source: &str, is_tsx: bool) -> Result<Vec<Symbol>> {
         let mut symbols = Vec::new();
-        let mut cursor = root_node.walk();
-
-        for child in root_node.children(&mut cursor) {
-            if let Some(symbol) = self.process_node(&child, source, is_tsx)? {
-                symbols.push(symbol);
-            }
-        }
+        for_each_declaration(root_node, |candidate| {
+            symbols.extend(self.process_node(candidate, source)?);
+            Ok(())
+        });
 
         // Additional extraction for common UI components in JSX/TSX
         if is_tsx {
@@ -125,9 +133,11 @@ impl TypeScriptParser {
                 let range = self.get_range_expanded(&node, 5); // Expand 5 lines for context
                 let content = self.extract_node_content_with_context(&node, source, &range);
 
-                let mut metadata = SymbolMetadata::default();
-                metadata.tags = vec!["ui-component".to_string(), "common".to_string()];
-                metadata.priority = Some(1.0); // High priority for common components
+                let metadata = SymbolMetadata {
+                    tags: vec!["ui-component".to_string(), "common".to_string()],
+                    priority: Some(1.0), // High priority for common components
+                    ..Default::default()
+                };
 
                 ui_symbols.push(Symbol {
                     name,
@@ -172,8 +182,8 @@ impl TypeScriptParser {
     }
 
     fn get_range_expanded(&self, node: &Node, lines: usize) -> Range {
-        let start_row = node.start_position().row.saturating_sub(lines as usize);
-        let end_row = node.end_position().row + lines as usize;
+        let start_row = node.start_position().row.saturating_sub(lines);
+        let end_row = node.end_position().row + lines;
         Range {
             start_line: start_row + 1,
             end_line: end_row + 1,
@@ -182,16 +192,16 @@ impl TypeScriptParser {
         }
     }
 
-    fn extract_node_content_with_context(&self, node: &Node, source: &str, range: &Range) -> String {
+    fn extract_node_content_with_context(&self, _node: &Node, source: &str, range: &Range) -> String {
         let full_source = source.as_bytes();
-        let start_byte = range.start_byte as usize;
-        let end_byte = range.end_byte as usize;
+        let start_byte = range.start_byte;
+        let end_byte = range.end_byte;
         let start = std::cmp::max(0, start_byte.saturating_sub(1000));
         let end = std::cmp::min(full_source.len(), end_byte + 1000);
         String::from_utf8_lossy(&full_source[start..end]).to_string()
     }
 
-    fn process_node(&self, node: &Node, source: &str, is_tsx: bool) -> Result<Option<Symbol>> {
+    fn process_node(&self, node: &Node, source: &str) -> Result<Option<Symbol>> {
         let kind = node.kind();
         let text = node.utf8_text(source.as_bytes())?;
 
@@ -201,15 +211,18 @@ impl TypeScriptParser {
                     .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "anonymous".to_string());
                 let range = self.get_range(node);
-                let metadata = self.extract_function_metadata(node, source)?;
+                let mut metadata = self.extract_function_metadata(node, source)?;
 
-                // Check if it's a component (starts with Uppercase and returns JSX)
+                // Check if it's a component (starts with Uppercase and returns JSX) or a custom hook
                 let symbol_type = if self.is_component_name(&name) && self.returns_jsx(node, source)
                 {
                     SymbolType::Component
+                } else if self.is_hook_name(&name) {
+                    SymbolType::Hook
                 } else {
                     SymbolType::Function
                 };
+                self.record_hook_usage(node, source, &symbol_type, &mut metadata)?;
 
                 Ok(Some(Symbol {
                     name,
@@ -221,12 +234,20 @@ impl TypeScriptParser {
                     references: self.extract_references(node, source)?,
                 }))
             }
-            "class_declaration" => {
+            "class_declaration" | "abstract_class_declaration" => {
                 let name = self
                     .get_child_text(node, "name", source)
                     .unwrap_or_else(|| "Anonymous".to_string());
                 let range = self.get_range(node);
-                let metadata = SymbolMetadata::default(); // TODO: Extract class metadata
+                // `@Injectable() export class X` puts the decorators on the export statement
+                let mut metadata = SymbolMetadata {
+                    decorators: self.extract_decorators(node, source)?,
+                    ..Default::default()
+                };
+                if let Some(parent) = node.parent().filter(|p| p.kind() == "export_statement") {
+                    metadata.decorators.splice(0..0, self.extract_decorators(&parent, source)?);
+                }
+                tag_framework_roles(&mut metadata);
 
                 Ok(Some(Symbol {
                     name,
@@ -264,8 +285,8 @@ impl TypeScriptParser {
                 // Recurse into export statement
                 let mut cursor = node.walk();
                 for child in node.children(&mut cursor) {
-                    if child.kind() != "export" && child.kind() != "default" {
-                        return self.process_node(&child, source, is_tsx);
+                    if !matches!(child.kind(), "export" | "default" | "decorator") {
+                        return self.process_node(&child, source);
                     }
                 }
                 Ok(None)
@@ -288,16 +309,19 @@ impl TypeScriptParser {
                         // Check if it's an arrow function
                         if value_node.kind() == "arrow_function" {
                             let range = self.get_range(node);
-                            let metadata =
+                            let mut metadata =
                                 self.extract_arrow_function_metadata(&value_node, source)?;
 
                             let symbol_type = if self.is_component_name(&name)
                                 && self.returns_jsx(&value_node, source)
                             {
                                 SymbolType::Component
+                            } else if self.is_hook_name(&name) {
+                                SymbolType::Hook
                             } else {
                                 SymbolType::Function
                             };
+                            self.record_hook_usage(&value_node, source, &symbol_type, &mut metadata)?;
 
                             return Ok(Some(Symbol {
                                 name,
@@ -331,33 +355,47 @@ impl TypeScriptParser {
         let mut members = Vec::new();
         if let Some(body) = node.child_by_field_name("body") {
             let mut cursor = body.walk();
+            // Method decorators are siblings preceding the method in the class body
+            let mut pending_decorators = Vec::new();
             for child in body.children(&mut cursor) {
                 let kind = child.kind();
                 match kind {
+                    "decorator" => {
+                        pending_decorators.push(child.utf8_text(source.as_bytes())?.to_string());
+                    }
                     "method_definition" => {
                         let name = self
-                            .get_child_text(&child, "property_identifier", source)
+                            .get_child_text(&child, "name", source)
                             .unwrap_or_default();
+                        let metadata = SymbolMetadata {
+                            decorators: std::mem::take(&mut pending_decorators),
+                            ..Default::default()
+                        };
                         members.push(Symbol {
                             name,
                             kind: SymbolType::Method,
                             range: self.get_range(&child),
                             content: child.utf8_text(source.as_bytes())?.to_string(),
-                            metadata: SymbolMetadata::default(),
+                            metadata,
                             children: vec![],
                             references: vec![],
                         });
                     }
                     "public_field_definition" => {
                         let name = self
-                            .get_child_text(&child, "property_identifier", source)
+                            .get_child_text(&child, "name", source)
                             .unwrap_or_default();
+                        let mut metadata = SymbolMetadata {
+                            decorators: std::mem::take(&mut pending_decorators),
+                            ..Default::default()
+                        };
+                        metadata.decorators.extend(self.extract_decorators(&child, source)?);
                         members.push(Symbol {
                             name,
                             kind: SymbolType::Field,
                             range: self.get_range(&child),
                             content: child.utf8_text(source.as_bytes())?.to_string(),
-                            metadata: SymbolMetadata::default(),
+                            metadata,
                             children: vec![],
                             references: vec![],
                         });
@@ -369,6 +407,18 @@ impl TypeScriptParser {
         Ok(members)
     }
 
+    /// Direct `decorator` children of a node, e.g. `@Injectable()` or `@Input() label`
+    fn extract_decorators(&self, node: &Node, source: &str) -> Result<Vec<String>> {
+        let mut decorators = Vec::new();
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            if child.kind() == "decorator" {
+                decorators.push(child.utf8_text(source.as_bytes())?.to_string());
+            }
+        }
+        Ok(decorators)
+    }
+
     fn extract_interface_members(&self, node: &Node, source: &str) -> Result<Vec<Symbol>> {
         let mut members = Vec::new();
         if let Some(body) = node.child_by_field_name("body") {
@@ -376,7 +426,7 @@ impl TypeScriptParser {
             for child in body.children(&mut cursor) {
                 if child.kind() == "property_signature" {
                     let name = self
-                        .get_child_text(&child, "property_identifier", source)
+                        .get_child_text(&child, "name", source)
                         .unwrap_or_default();
                     members.push(Symbol {
                         name,
@@ -418,6 +468,72 @@ impl TypeScriptParser {
         Ok(metadata)
     }
 
+    /// React hook naming convention: `use` followed by an uppercase letter
+    fn is_hook_name(&self, name: &str) -> bool {
+        name.strip_prefix("use")
+            .and_then(|rest| rest.chars().next())
+            .is_some_and(|c| c.is_ascii_uppercase())
+    }
+
+    /// Record the hooks a component or hook calls, plus `useState` state variables
+    fn record_hook_usage(
+        &self,
+        node: &Node,
+        source: &str,
+        symbol_type: &SymbolType,
+        metadata: &mut SymbolMetadata,
+    ) -> Result<()> {
+        if !matches!(symbol_type, SymbolType::Component | SymbolType::Hook) {
+            return Ok(());
+        }
+
+        self.collect_hook_calls(node, source, metadata)?;
+        if *symbol_type == SymbolType::Hook {
+            metadata.tags.push("custom-hook".to_string());
+        }
+        Ok(())
+    }
+
+    fn collect_hook_calls(&self, node: &Node, source: &str, metadata: &mut SymbolMetadata) -> Result<()> {
+        if node.kind() == "call_expression" {
+            if let Some(function) = node.child_by_field_name("function") {
+                // `useState(...)` or `React.useState(...)`
+                let callee = match function.kind() {
+                    "identifier" => Some(function.utf8_text(source.as_bytes())?),
+                    "member_expression" => function
+                        .child_by_field_name("property")
+                        .map(|p| p.utf8_text(source.as_bytes()))
+                        .transpose()?,
+                    _ => None,
+                };
+
+                if let Some(hook) = callee.filter(|c| self.is_hook_name(c)) {
+                    if !metadata.hooks_used.iter().any(|h| h == hook) {
+                        metadata.hooks_used.push(hook.to_string());
+                    }
+
+                    // const [value, setValue] = useState(...)
+                    if matches!(hook, "useState" | "useReducer") {
+                        let pattern = node
+                            .parent()
+                            .filter(|p| p.kind() == "variable_declarator")
+                            .and_then(|p| p.child_by_field_name("name"))
+                            .filter(|n| n.kind() == "array_pattern");
+                        if let Some(state) = pattern.and_then(|p| p.named_child(0)) {
+                            metadata.state_variables.push(state.utf8_text(source.as_bytes())?.to_string());
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.collect_hook_calls(&child, source, metadata)?;
+        }
+        Ok(())
+    }
+
     fn is_component_node(&self, node: &Node, source: &str) -> bool {
         if let Some(name_node) = node.child_by_field_name("name") {
              if let Ok(name) = name_node.utf8_text(source.as_bytes()) {
@@ -549,8 +665,10 @@ impl TypeScriptParser {
 
                 let mut names = Vec::new();
 
-                // Handle import clause
-                if let Some(clause) = child.child_by_field_name("clause") {
+                // Handle import clause; the grammar doesn't name it as a field
+                let mut clause_cursor = child.walk();
+                let clause = child.children(&mut clause_cursor).find(|sub| sub.kind() == "import_clause");
+                if let Some(clause) = clause {
                     // Default import?
                     let mut cursor2 = clause.walk();
                     for sub in clause.children(&mut cursor2) {
@@ -595,9 +713,92 @@ impl TypeScriptParser {
 
     fn extract_exports(&self, node: &Node, source: &str) -> Result<Vec<Export>> {
         let mut exports = Vec::new();
-        // TODO: Implement robust export extraction
-        // For now, we rely on symbols being marked as exported if we were to add that flag
-        // But here we want explicit export statements
+        let mut cursor = node.walk();
+
+        for child in node.children(&mut cursor) {
+            if child.kind() != "export_statement" {
+                continue;
+            }
+
+            let range = self.get_range(&child);
+            let reexport_source = self
+                .get_child_text(&child, "source", source)
+                .map(|s| s.trim_matches(|c| c == '\'' || c == '"').to_string());
+            let mut is_default = false;
+            let mut is_type = false;
+
+            let mut export = |name: String, alias: Option<String>, is_default: bool, is_type: bool| {
+                exports.push(Export {
+                    name,
+                    alias,
+                    is_default,
+                    is_type,
+                    range: range.clone(),
+                    source: reexport_source.clone(),
+                });
+            };
+
+            let mut inner = child.walk();
+            for part in child.children(&mut inner) {
+                match part.kind() {
+                    "default" => is_default = true,
+                    "type" => is_type = true,
+                    // export * from './x'
+                    "*" => export("*".to_string(), None, false, is_type),
+                    // export * as ns from './x'
+                    "namespace_export" => {
+                        let alias = part
+                            .named_child(0)
+                            .map(|n| n.utf8_text(source.as_bytes()).unwrap_or_default().to_string());
+                        export("*".to_string(), alias, false, is_type);
+                    }
+                    // export { a, b as c } [from './x']
+                    "export_clause" => {
+                        let mut specs = part.walk();
+                        for spec in part.children(&mut specs) {
+                            if spec.kind() != "export_specifier" {
+                                continue;
+                            }
+                            let name = self.get_child_text(&spec, "name", source).unwrap_or_default();
+                            let alias = self.get_child_text(&spec, "alias", source);
+                            let is_default = alias.as_deref() == Some("default");
+                            export(name, alias, is_default, is_type);
+                        }
+                    }
+                    "interface_declaration" | "type_alias_declaration" => {
+                        if let Some(name) = self.get_child_text(&part, "name", source) {
+                            export(name, None, is_default, true);
+                        }
+                    }
+                    "function_declaration" | "generator_function_declaration" | "class_declaration"
+                    | "abstract_class_declaration" | "enum_declaration" | "function" | "class" => {
+                        let name = self
+                            .get_child_text(&part, "name", source)
+                            .unwrap_or_else(|| "default".to_string());
+                        export(name, None, is_default, is_type);
+                    }
+                    "lexical_declaration" | "variable_declaration" => {
+                        let mut decls = part.walk();
+                        for declarator in part.children(&mut decls) {
+                            if let Some(name) = self.get_child_text(&declarator, "name", source) {
+                                export(name, None, is_default, is_type);
+                            }
+                        }
+                    }
+                    // export default foo;
+                    "identifier" if is_default => {
+                        let name = part.utf8_text(source.as_bytes())?.to_string();
+                        export(name, None, true, is_type);
+                    }
+                    // export default () => ..., export default { ... }
+                    _ if is_default && part.is_named() && part.kind() != "comment" => {
+                        export("default".to_string(), None, true, is_type);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
         Ok(exports)
     }
 
@@ -771,6 +972,14 @@ impl TypeScriptParser {
         let mut cursor = node.walk();
 
         for child in node.children(&mut cursor) {
+            // `export interface Props { ... }` wraps the declaration
+            let child = match child.kind() {
+                "export_statement" => match child.child_by_field_name("declaration") {
+                    Some(declaration) => declaration,
+                    None => continue,
+                },
+                _ => child,
+            };
             match child.kind() {
                 "interface_declaration" => {
                     if let Some(type_def) = self.extract_interface(&child, source)? {
@@ -796,7 +1005,7 @@ impl TypeScriptParser {
 
     fn extract_interface(&self, node: &Node, source: &str) -> Result<Option<TypeDefinition>> {
         let name = self
-            .get_child_text(node, "type_identifier", source)
+            .get_child_text(node, "name", source)
             .unwrap_or_default();
         let definition = node.utf8_text(source.as_bytes())?.to_string();
 
@@ -806,7 +1015,7 @@ impl TypeScriptParser {
             for child in body.children(&mut cursor) {
                 if child.kind() == "property_signature" {
                     let prop_name = self
-                        .get_child_text(&child, "property_identifier", source)
+                        .get_child_text(&child, "name", source)
                         .unwrap_or_default();
                     let type_annotation = child
                         .child_by_field_name("type")
@@ -836,7 +1045,7 @@ impl TypeScriptParser {
 
     fn extract_type_alias(&self, node: &Node, source: &str) -> Result<Option<TypeDefinition>> {
         let name = self
-            .get_child_text(node, "type_identifier", source)
+            .get_child_text(node, "name", source)
             .unwrap_or_default();
         let definition = node.utf8_text(source.as_bytes())?.to_string();
 
@@ -950,77 +1159,117 @@ impl TypeScriptParser {
         let mut schemas = Vec::new();
         let text = node.utf8_text(source.as_bytes())?;
 
-        // Look for Zod schemas
-        if text.contains("z.object") || text.contains("zod") {
-            schemas.extend(self.extract_zod_schemas(node, source)?);
+        let has_object_schemas = text.contains("z.object")
+            || text.contains("zod")
+            || text.contains("yup")
+            || text.contains("Joi.object");
+        let has_dto_classes = text.contains("class-validator");
+        if !has_object_schemas && !has_dto_classes {
+            return Ok(schemas);
         }
 
-        Ok(schemas)
-    }
-
-    fn extract_zod_schemas(&self, node: &Node, source: &str) -> Result<Vec<ValidationSchema>> {
-        let mut schemas = Vec::new();
         let mut cursor = node.walk();
-
         for child in node.children(&mut cursor) {
-            if child.kind() == "lexical_declaration" || child.kind() == "variable_declaration" {
-                let text = child.utf8_text(source.as_bytes())?;
-                if text.contains("z.object") {
-                    if let Some(schema) =
-                        self.extract_zod_schema_from_declaration(&child, source)?
-                    {
+            // Schemas are usually exported: look through `export const` / `export class`
+            let declaration = if child.kind() == "export_statement" {
+                child.child_by_field_name("declaration").unwrap_or(child)
+            } else {
+                child
+            };
+
+            match declaration.kind() {
+                "lexical_declaration" | "variable_declaration" if has_object_schemas => {
+                    if let Some(schema) = self.extract_object_schema_from_declaration(&declaration, source)? {
+                        schemas.push(schema);
+                    }
+                }
+                "class_declaration" if has_dto_classes => {
+                    if let Some(schema) = self.extract_class_validator_dto(&declaration, source)? {
                         schemas.push(schema);
                     }
                 }
+                _ => {}
             }
         }
 
         Ok(schemas)
     }
 
-    fn extract_zod_schema_from_declaration(
+    /// `z.object({...})`, `yup.object({...})` / `object().shape({...})`, `Joi.object({...})` / `.keys({...})`
+    fn extract_object_schema_from_declaration(
         &self,
         node: &Node,
         source: &str,
     ) -> Result<Option<ValidationSchema>> {
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
-            if child.kind() == "variable_declarator" {
-                if let Some(name_node) = child.child_by_field_name("name") {
-                    let name = name_node.utf8_text(source.as_bytes())?.to_string();
-                    let definition = child.utf8_text(source.as_bytes())?.to_string();
-
-                    // Enhanced field extraction for Zod schemas
-                    let mut fields = Vec::new();
-                    if let Some(value_node) = child.child_by_field_name("value") {
-                        if value_node.kind() == "call_expression" {
-                            // Look for z.object({ ... })
-                            if let Some(arg_list) = value_node.child_by_field_name("arguments") {
-                                let mut arg_cursor = arg_list.walk();
-                                for arg in arg_list.children(&mut arg_cursor) {
-                                    if arg.kind() == "object" {
-                                        // Parse object literal for fields
-                                        fields = self.parse_zod_object_fields(&arg, source)?;
-                                    }
-                                }
-                            }
-                        }
-                    }
+            if child.kind() != "variable_declarator" {
+                continue;
+            }
+            let (Some(name_node), Some(value_node)) =
+                (child.child_by_field_name("name"), child.child_by_field_name("value"))
+            else {
+                continue;
+            };
+
+            let value = value_node.utf8_text(source.as_bytes())?;
+            let schema_type = if value.starts_with("z.") {
+                SchemaType::Zod
+            } else if value.starts_with("yup.") || (value.starts_with("object(") && source.contains("yup")) {
+                SchemaType::Yup
+            } else if value.starts_with("Joi.") {
+                SchemaType::Joi
+            } else {
+                continue;
+            };
+
+            let Some(shape) = self.find_schema_shape(&value_node, source) else {
+                continue;
+            };
+            // Zod fields are required unless marked optional; Yup and Joi fields are optional unless `.required()`
+            let required_by_default = matches!(schema_type, SchemaType::Zod);
+
+            return Ok(Some(ValidationSchema {
+                name: name_node.utf8_text(source.as_bytes())?.to_string(),
+                schema_type,
+                definition: child.utf8_text(source.as_bytes())?.to_string(),
+                fields: self.parse_object_schema_fields(&shape, source, required_by_default)?,
+                range: self.get_range(node),
+            }));
+        }
+        Ok(None)
+    }
 
-                    return Ok(Some(ValidationSchema {
-                        name,
-                        schema_type: SchemaType::Zod,
-                        definition,
-                        fields,
-                        range: self.get_range(node),
-                    }));
+    /// The object literal passed to `object(...)`, `.shape(...)` or `.keys(...)` anywhere in a call chain
+    fn find_schema_shape<'a>(&self, node: &Node<'a>, source: &str) -> Option<Node<'a>> {
+        if node.kind() == "call_expression" {
+            let callee = node
+                .child_by_field_name("function")
+                .and_then(|f| f.utf8_text(source.as_bytes()).ok())
+                .unwrap_or_default();
+            let method = callee.rsplit('.').next().unwrap_or(callee);
+            if matches!(method, "object" | "shape" | "keys") {
+                if let Some(args) = node.child_by_field_name("arguments") {
+                    let mut cursor = args.walk();
+                    let found = args.children(&mut cursor).find(|a| a.kind() == "object");
+                    if let Some(object) = found {
+                        return Some(object);
+                    }
                 }
             }
         }
-        Ok(None)
+
+        let mut cursor = node.walk();
+        let children: Vec<Node<'a>> = node.children(&mut cursor).collect();
+        children.iter().find_map(|child| self.find_schema_shape(child, source))
     }
 
-    fn parse_zod_object_fields(&self, node: &Node, source: &str) -> Result<Vec<SchemaField>> {
+    fn parse_object_schema_fields(
+        &self,
+        node: &Node,
+        source: &str,
+        required_by_default: bool,
+    ) -> Result<Vec<SchemaField>> {
         let mut fields = Vec::new();
         let mut cursor = node.walk();
 
@@ -1030,19 +1279,29 @@ impl TypeScriptParser {
                 let value_node = child.child_by_field_name("value");
 
                 if let (Some(key_node), Some(value_node)) = (key_node, value_node) {
-                    let field_name = key_node.utf8_text(source.as_bytes())?.trim_matches('"').to_string();
+                    let field_name = key_node
+                        .utf8_text(source.as_bytes())?
+                        .trim_matches(|c| c == '"' || c == '\'')
+                        .to_string();
                     let field_type = value_node.utf8_text(source.as_bytes())?.to_string();
-                    let is_optional = field_name.ends_with('?');
+                    let validators = self.extract_zod_validators(&value_node, source)?;
+
+                    let is_optional = if required_by_default {
+                        field_name.ends_with('?') || field_type.contains(".optional()") || field_type.contains(".nullish()")
+                    } else {
+                        !validators.iter().any(|v| v == "required")
+                    };
 
                     fields.push(SchemaField {
                         name: field_name.trim_end_matches('?').to_string(),
-                        validation_rules: self.extract_zod_validators(&value_node, source)?,
+                        validation_rules: validators.clone(),
                         is_required: !is_optional,
                         default_value: None,
                         type_annotation: Some(field_type),
                         is_optional,
-                        validators: self.extract_zod_validators(&value_node, source)?,
+                        validators,
                         description: None,
+                        references: None,
                     });
                 }
             }
@@ -1051,6 +1310,69 @@ impl TypeScriptParser {
         Ok(fields)
     }
 
+    /// A DTO class whose properties carry class-validator decorators (`@IsEmail() email: string`)
+    fn extract_class_validator_dto(&self, node: &Node, source: &str) -> Result<Option<ValidationSchema>> {
+        let Some(body) = node.child_by_field_name("body") else {
+            return Ok(None);
+        };
+
+        let mut fields = Vec::new();
+        let mut pending_decorators = Vec::new();
+        let mut cursor = body.walk();
+        for child in body.children(&mut cursor) {
+            match child.kind() {
+                "decorator" => pending_decorators.push(child.utf8_text(source.as_bytes())?.to_string()),
+                "public_field_definition" => {
+                    let mut decorators = std::mem::take(&mut pending_decorators);
+                    decorators.extend(self.extract_decorators(&child, source)?);
+                    if decorators.is_empty() {
+                        continue;
+                    }
+
+                    let name = self
+                        .get_child_text(&child, "name", source)
+                        .unwrap_or_default();
+                    let type_annotation = self
+                        .get_child_text(&child, "type", source)
+                        .map(|t| t.trim_start_matches(':').trim().to_string());
+                    let rules: Vec<String> = decorators
+                        .iter()
+                        .map(|d| d.trim_start_matches('@').to_string())
+                        .collect();
+                    let is_optional = child.utf8_text(source.as_bytes())?.contains("?:")
+                        || rules.iter().any(|r| decorator_name(r) == "IsOptional");
+
+                    fields.push(SchemaField {
+                        name: name.trim_end_matches('?').to_string(),
+                        validation_rules: rules.clone(),
+                        is_required: !is_optional,
+                        default_value: self.get_child_text(&child, "value", source),
+                        type_annotation,
+                        is_optional,
+                        validators: rules.iter().map(|r| decorator_name(r).to_string()).collect(),
+                        description: None,
+                        references: None,
+                    });
+                }
+                _ => pending_decorators.clear(),
+            }
+        }
+
+        if fields.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(ValidationSchema {
+            name: self
+                .get_child_text(node, "name", source)
+                .unwrap_or_else(|| "Anonymous".to_string()),
+            schema_type: SchemaType::ClassValidator,
+            definition: node.utf8_text(source.as_bytes())?.to_string(),
+            fields,
+            range: self.get_range(node),
+        }))
+    }
+
     fn extract_zod_validators(&self, node: &Node, source: &str) -> Result<Vec<String>> {
         let mut validators = Vec::new();
         let text = node.utf8_text(source.as_bytes())?;
@@ -1076,6 +1398,38 @@ impl TypeScriptParser {
     }
 }
 
+/// NestJS / Angular / TypeORM class decorators and the role they give a class
+const FRAMEWORK_DECORATORS: &[(&str, &str)] = &[
+    ("Injectable", "service"),
+    ("Controller", "controller"),
+    ("Module", "module"),
+    ("NgModule", "module"),
+    ("Component", "component"),
+    ("Directive", "directive"),
+    ("Pipe", "pipe"),
+    ("Resolver", "resolver"),
+    ("Entity", "entity"),
+];
+
+/// Tag classes with the role their decorator declares, so "find all services" is a tag lookup
+fn tag_framework_roles(metadata: &mut SymbolMetadata) {
+    for decorator in &metadata.decorators {
+        let name = decorator_name(decorator);
+        if let Some((_, role)) = FRAMEWORK_DECORATORS.iter().find(|(d, _)| *d == name) {
+            if !metadata.tags.iter().any(|t| t == role) {
+                metadata.tags.push(role.to_string());
+            }
+        }
+    }
+}
+
+/// `@Injectable({ providedIn: 'root' })` -> `Injectable`
+fn decorator_name(decorator: &str) -> &str {
+    let name = decorator.trim_start_matches('@');
+    let end = name.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.')).unwrap_or(name.len());
+    name[..end].rsplit('.').next().unwrap_or("")
+}
+
 impl Default for TypeScriptParser {
     fn default() -> Self {
         Self::new()
@@ -1109,6 +1463,19 @@ mod tests {
         // assert!(!symbol.references.contains(&"x".to_string())); 
     }
 
+    #[test]
+    fn test_import_names() {
+        let parser = TypeScriptParser::new();
+        let content = "import Layout, { LoginForm as Form, useAuth } from './auth';\n";
+        let parsed = parser.parse(content, false).unwrap();
+        let names: Vec<(&str, Option<&str>, bool)> = parsed.imports[0]
+            .names
+            .iter()
+            .map(|n| (n.name.as_str(), n.alias.as_deref(), n.is_default))
+            .collect();
+        assert_eq!(names, vec![("Layout", None, true), ("LoginForm", Some("Form"), false), ("useAuth", None, false)]);
+    }
+
     #[test]
     fn test_extract_props() {
         let parser = TypeScriptParser::new();
@@ -1126,4 +1493,144 @@ mod tests {
         assert!(symbol.metadata.props.iter().any(|p| p.name == "title"));
         assert!(symbol.metadata.props.iter().any(|p| p.name == "isActive"));
     }
+
+    #[test]
+    fn test_extract_exports() {
+        let parser = TypeScriptParser::new();
+        let content = r#"
+            export function publicApi() {}
+            function internalHelper() {}
+            const later = 1;
+            export { later as renamed };
+            export type { Props } from './types';
+            export * from './utils';
+            export * as icons from './icons';
+            export default function Page() {}
+        "#;
+
+        let parsed = parser.parse(content, false).unwrap();
+        let names: Vec<&str> = parsed.exports.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["publicApi", "later", "Props", "*", "*", "Page"]);
+        assert_eq!(parsed.exports[1].alias.as_deref(), Some("renamed"));
+        assert!(parsed.exports[2].is_type);
+        assert_eq!(parsed.exports[3].source.as_deref(), Some("./utils"));
+        assert_eq!(parsed.exports[4].alias.as_deref(), Some("icons"));
+        assert!(parsed.exports[5].is_default);
+
+        let exported = |name: &str| parsed.symbols.iter().find(|s| s.name == name).unwrap().metadata.is_exported;
+        assert!(exported("publicApi"));
+        assert!(exported("later"));
+        assert!(!exported("internalHelper"));
+    }
+
+    #[test]
+    fn test_exported_type_definitions() {
+        let parser = TypeScriptParser::new();
+        let content = r#"
+            export interface ButtonProps { label: string; size?: "sm" | "md" }
+            export type Variant = "primary" | "ghost";
+            interface Internal { id: number }
+        "#;
+
+        let parsed = parser.parse(content, false).unwrap();
+        let names: Vec<&str> = parsed.type_definitions.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["ButtonProps", "Variant", "Internal"]);
+        assert!(parsed.type_definitions[0].definition.starts_with("interface ButtonProps {"));
+    }
+
+    #[test]
+    fn test_detect_custom_hooks() {
+        let parser = TypeScriptParser::new();
+        let content = r#"
+            export function useUser(id: string) {
+                const [user, setUser] = useState(null);
+                useEffect(() => { fetchUser(id).then(setUser); }, [id]);
+                return user;
+            }
+
+            const Profile = ({ id }: { id: string }) => {
+                const user = useUser(id);
+                const theme = React.useContext(ThemeContext);
+                return <div>{user.name}</div>;
+            };
+        "#;
+
+        let parsed = parser.parse(content, true).unwrap();
+        let hook = parsed.symbols.iter().find(|s| s.name == "useUser").unwrap();
+        assert_eq!(hook.kind, SymbolType::Hook);
+        assert_eq!(hook.metadata.hooks_used, vec!["useState", "useEffect"]);
+        assert_eq!(hook.metadata.state_variables, vec!["user"]);
+
+        let component = parsed.symbols.iter().find(|s| s.name == "Profile").unwrap();
+        assert_eq!(component.kind, SymbolType::Component);
+        assert_eq!(component.metadata.hooks_used, vec!["useUser", "useContext"]);
+    }
+
+    #[test]
+    fn test_extract_decorators() {
+        let parser = TypeScriptParser::new();
+        let content = r#"
+            @Injectable()
+            export class UsersService {
+                @Inject(REPO) private readonly repo: Repo;
+
+                @Get(':id')
+                findOne(id: string) {}
+            }
+        "#;
+
+        let parsed = parser.parse(content, false).unwrap();
+        let class = parsed.symbols.iter().find(|s| s.name == "UsersService").unwrap();
+        assert_eq!(class.metadata.decorators, vec!["@Injectable()"]);
+        assert_eq!(class.metadata.tags, vec!["service"]);
+        assert!(class.metadata.is_exported);
+
+        let method = class.children.iter().find(|c| c.kind == SymbolType::Method).unwrap();
+        assert_eq!(method.metadata.decorators, vec!["@Get(':id')"]);
+    }
+
+    #[test]
+    fn test_extract_yup_joi_and_class_validator_schemas() {
+        let parser = TypeScriptParser::new();
+        let content = r#"
+            import * as yup from 'yup';
+            import Joi from 'joi';
+            import { IsEmail, IsOptional, MinLength } from 'class-validator';
+
+            export const signupSchema = yup.object({
+                email: yup.string().email().required(),
+                nickname: yup.string(),
+            }).required();
+
+            const querySchema = Joi.object().keys({ page: Joi.number().min(1) });
+
+            export class CreateUserDto {
+                @IsEmail()
+                email: string;
+
+                @IsOptional()
+                @MinLength(2)
+                name?: string;
+            }
+        "#;
+
+        let parsed = parser.parse(content, false).unwrap();
+        let schema = |name: &str| parsed.schemas.iter().find(|s| s.name == name).unwrap();
+
+        let signup = schema("signupSchema");
+        assert!(matches!(signup.schema_type, SchemaType::Yup));
+        assert_eq!(signup.fields.len(), 2);
+        assert!(signup.fields[0].is_required);
+        assert!(!signup.fields[1].is_required);
+
+        let query = schema("querySchema");
+        assert!(matches!(query.schema_type, SchemaType::Joi));
+        assert_eq!(query.fields[0].name, "page");
+
+        let dto = schema("CreateUserDto");
+        assert!(matches!(dto.schema_type, SchemaType::ClassValidator));
+        assert_eq!(dto.fields[0].validators, vec!["IsEmail"]);
+        assert_eq!(dto.fields[1].name, "name");
+        assert!(dto.fields[1].is_optional);
+    }
 }
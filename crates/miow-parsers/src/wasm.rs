@@ -0,0 +1,171 @@
+use crate::types::*;
+use anyhow::{Context, Result};
+use std::path::Path;
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+
+/// Version of the plugin ABI this host speaks; plugins export it as `miow_abi_version`
+pub const WASM_ABI_VERSION: i32 = 1;
+
+/// Instructions (roughly) a plugin may run for one file before it is stopped
+const DEFAULT_FUEL: u64 = 2_000_000_000;
+/// Largest linear memory a plugin may grow to
+const MAX_MEMORY_BYTES: usize = 256 << 20;
+
+/// A parser compiled to WebAssembly, for languages and DSLs miow has no
+/// parser for. The module imports nothing and exports:
+///
+/// - `memory`
+/// - `miow_abi_version() -> i32`, returning [`WASM_ABI_VERSION`]
+/// - `alloc(len: i32) -> i32`, a buffer of `len` bytes for the host to write into
+/// - `parse(ptr: i32, len: i32) -> i64`, given the file's UTF-8 contents, returning
+///   `(out_ptr << 32) | out_len` of a JSON [`ParsedFile`], or of `{"error": "..."}`
+///
+/// Every file gets a fresh instance, so plugins can leak freely and one file
+/// can't affect the next. Runaway plugins are stopped by a fuel limit.
+#[derive(Clone)]
+pub struct WasmParser {
+    name: String,
+    engine: Engine,
+    module: Module,
+    fuel: u64,
+}
+
+impl WasmParser {
+    /// Load a `.wasm` module (or `.wat` text) from disk
+    pub fn load(name: &str, module_path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(module_path)
+            .with_context(|| format!("Failed to read parser plugin {}", module_path.display()))?;
+        Self::from_bytes(name, &bytes).with_context(|| format!("Invalid parser plugin {}", module_path.display()))
+    }
+
+    /// Compile a module and check that it speaks this ABI
+    pub fn from_bytes(name: &str, bytes: &[u8]) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::new(&engine, bytes)?;
+        if let Some(import) = module.imports().next() {
+            anyhow::bail!("Parser plugins can't import anything, but this one imports {}::{}", import.module(), import.name());
+        }
+
+        let parser = Self { name: name.to_string(), engine, module, fuel: DEFAULT_FUEL };
+        let (mut store, instance) = parser.instantiate()?;
+        let version = instance
+            .get_typed_func::<(), i32>(&mut store, "miow_abi_version")
+            .context("Missing export miow_abi_version")?
+            .call(&mut store, ())?;
+        if version != WASM_ABI_VERSION {
+            anyhow::bail!("Plugin speaks ABI version {}, this build speaks {}", version, WASM_ABI_VERSION);
+        }
+        Ok(parser)
+    }
+
+    /// Cap the work a plugin may do per file
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parse(&self, content: &str) -> Result<ParsedFile> {
+        let len = i32::try_from(content.len()).context("File too large for a parser plugin")?;
+        let (mut store, instance) = self.instantiate()?;
+        let memory = instance.get_memory(&mut store, "memory").context("Missing export memory")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc").context("Missing export alloc")?;
+        let parse = instance.get_typed_func::<(i32, i32), i64>(&mut store, "parse").context("Missing export parse")?;
+
+        let input = alloc.call(&mut store, len).map_err(|e| self.trap(e))?;
+        memory
+            .write(&mut store, input as u32 as usize, content.as_bytes())
+            .with_context(|| format!("Plugin {} allocated outside its memory", self.name))?;
+        let packed = parse.call(&mut store, (input, len)).map_err(|e| self.trap(e))? as u64;
+
+        let (output, output_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        let mut json = vec![0; output_len];
+        memory
+            .read(&store, output, &mut json)
+            .with_context(|| format!("Plugin {} returned output outside its memory", self.name))?;
+
+        let value: serde_json::Value =
+            serde_json::from_slice(&json).with_context(|| format!("Plugin {} returned invalid JSON", self.name))?;
+        if let Some(error) = value.get("error").and_then(|e| e.as_str()) {
+            anyhow::bail!("Plugin {} failed: {}", self.name, error);
+        }
+        let mut parsed: ParsedFile = serde_json::from_value(value)
+            .with_context(|| format!("Plugin {} returned something other than a ParsedFile", self.name))?;
+        if parsed.language.is_empty() {
+            parsed.language = self.name.clone();
+        }
+        Ok(parsed)
+    }
+
+    fn instantiate(&self) -> Result<(Store<StoreLimits>, Instance)> {
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY_BYTES).build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel)?;
+        let instance = Instance::new(&mut store, &self.module, &[])?;
+        Ok((store, instance))
+    }
+
+    fn trap(&self, error: anyhow::Error) -> anyhow::Error {
+        match error.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => anyhow::anyhow!("Plugin {} ran out of fuel; the file may be too large for it", self.name),
+            _ => error.context(format!("Plugin {} crashed", self.name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bump allocator; `parse` is given as a body returning the packed output
+    fn plugin(parse: &str) -> String {
+        format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (global $next (mut i32) (i32.const 1024))
+                (func (export "miow_abi_version") (result i32) i32.const 1)
+                (func (export "alloc") (param $len i32) (result i32)
+                    global.get $next
+                    global.get $next
+                    local.get $len
+                    i32.add
+                    global.set $next)
+                (func (export "parse") (param $ptr i32) (param $len i32) (result i64) {}))"#,
+            parse
+        )
+    }
+
+    #[test]
+    fn test_plugin_output_becomes_parsed_file() {
+        // Echoes its input, so the file's contents are the plugin's answer
+        let echo = plugin("local.get $ptr i64.extend_i32_u i64.const 32 i64.shl local.get $len i64.extend_i32_u i64.or");
+        let parser = WasmParser::from_bytes("toy", echo.as_bytes()).unwrap();
+        let parsed = parser
+            .parse(r#"{"symbols": [{"name": "main", "kind": "Function", "range": {"start_line": 1, "end_line": 3}}]}"#)
+            .unwrap();
+        assert_eq!(parsed.language, "toy");
+        assert_eq!(parsed.symbols[0].name, "main");
+        assert_eq!(parsed.symbols[0].kind, SymbolType::Function);
+        assert_eq!(parsed.symbols[0].range.end_line, 3);
+
+        let error = parser.parse(r#"{"error": "unexpected token"}"#).unwrap_err();
+        assert!(error.to_string().contains("unexpected token"), "{}", error);
+    }
+
+    #[test]
+    fn test_runaway_plugins_are_stopped() {
+        let spin = plugin("(loop $forever br $forever) i64.const 0");
+        let parser = WasmParser::from_bytes("spin", spin.as_bytes()).unwrap().with_fuel(100_000);
+        let error = parser.parse("anything").unwrap_err();
+        assert!(error.to_string().contains("ran out of fuel"), "{}", error);
+
+        let wrong_version = plugin("i64.const 0").replace("i32.const 1)", "i32.const 2)");
+        assert!(WasmParser::from_bytes("old", wrong_version.as_bytes()).is_err());
+    }
+}
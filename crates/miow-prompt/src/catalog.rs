@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+/// Longest prop type shown in the table; unions of string literals get long
+const MAX_TYPE_CHARS: usize = 60;
+
+/// A component from the project's catalog, with its resolved props
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentInfo {
+    pub name: String,
+    pub file_path: String,
+    pub start_line: i64,
+    pub props: Vec<ComponentProp>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentProp {
+    pub name: String,
+    pub type_annotation: Option<String>,
+    pub optional: bool,
+    pub default_value: Option<String>,
+}
+
+/// The components as a table of names, files and props, in place of their code
+pub fn render_component_catalog(components: &[ComponentInfo]) -> String {
+    let mut section = String::from("# Available Components\n");
+    section.push_str("Reuse these before writing new ones. `?` marks an optional prop and `=` its default.\n\n");
+    section.push_str("| Component | File | Props |\n|---|---|---|\n");
+    for component in components {
+        let props: Vec<String> = component.props.iter().map(|p| format!("`{}`", prop_signature(p))).collect();
+        let props = if props.is_empty() { "none".to_string() } else { props.join(", ") };
+        section.push_str(&format!(
+            "| `{}` | `{}:{}` | {} |\n",
+            component.name,
+            component.file_path,
+            component.start_line,
+            props.replace('|', "\\|")
+        ));
+    }
+    section.push('\n');
+    section
+}
+
+/// `variant?: "primary" | "ghost" = "primary"`
+fn prop_signature(prop: &ComponentProp) -> String {
+    let mut signature = prop.name.clone();
+    if prop.optional {
+        signature.push('?');
+    }
+    if let Some(type_annotation) = &prop.type_annotation {
+        let type_annotation = type_annotation.split_whitespace().collect::<Vec<_>>().join(" ");
+        signature.push_str(": ");
+        match type_annotation.char_indices().nth(MAX_TYPE_CHARS) {
+            Some((cut, _)) => {
+                signature.push_str(&type_annotation[..cut]);
+                signature.push('…');
+            }
+            None => signature.push_str(&type_annotation),
+        }
+    }
+    if let Some(default_value) = &prop.default_value {
+        signature.push_str(&format!(" = {}", default_value));
+    }
+    signature
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_component_catalog() {
+        let prop = |name: &str, type_annotation: &str, optional: bool, default_value: Option<&str>| ComponentProp {
+            name: name.to_string(),
+            type_annotation: Some(type_annotation.to_string()),
+            optional,
+            default_value: default_value.map(String::from),
+        };
+        let components = vec![
+            ComponentInfo {
+                name: "Button".to_string(),
+                file_path: "src/components/Button.tsx".to_string(),
+                start_line: 2,
+                props: vec![
+                    prop("label", "string", false, None),
+                    prop("variant", "\"primary\" | \"ghost\"", true, Some("\"primary\"")),
+                ],
+            },
+            ComponentInfo { name: "Spacer".to_string(), file_path: "src/Spacer.tsx".to_string(), start_line: 1, props: vec![] },
+        ];
+        let section = render_component_catalog(&components);
+        assert!(section.contains(
+            "| `Button` | `src/components/Button.tsx:2` | `label: string`, `variant?: \"primary\" \\| \"ghost\" = \"primary\"` |\n"
+        ));
+        assert!(section.contains("| `Spacer` | `src/Spacer.tsx:1` | none |\n"));
+
+        let long = prop("size", &format!("\"{}\"", "x".repeat(80)), false, None);
+        assert!(prop_signature(&long).ends_with("xx…"));
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ContextData, SymbolInfo};
+
+/// Where a context snippet came from, so the model can cite it and UIs can
+/// link back into the repository
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Citation {
+    /// Anchor the prompt cites the snippet by: `path:start-end`, or `path` when the lines are unknown
+    pub anchor: String,
+    pub path: String,
+    pub start_line: Option<i64>,
+    pub end_line: Option<i64>,
+    pub symbol: String,
+    pub kind: String,
+}
+
+impl Citation {
+    /// Citation for a symbol, if it points at a file. The plan and snippets
+    /// from tool calls without a `path` argument have none.
+    pub fn for_symbol(symbol: &SymbolInfo) -> Option<Self> {
+        if symbol.kind == "plan" {
+            return None;
+        }
+        let (path, args) = source_path(&symbol.file_path)?;
+        let (start_line, end_line) = match args {
+            // Tool output: only a line range passed to the tool is known
+            Some(args) => (args["start_line"].as_i64(), args["end_line"].as_i64()),
+            None if symbol.start_line > 0 && symbol.end_line >= symbol.start_line => {
+                (Some(symbol.start_line), Some(symbol.end_line))
+            }
+            None => (None, None),
+        };
+        let anchor = match (start_line, end_line) {
+            (Some(start), Some(end)) => format!("{}:{}-{}", path, start, end),
+            _ => path.clone(),
+        };
+        Some(Self {
+            anchor,
+            path,
+            start_line,
+            end_line,
+            symbol: symbol.name.clone(),
+            kind: symbol.kind.clone(),
+        })
+    }
+}
+
+/// Citations for every snippet in `context`, in prompt order, without duplicates
+pub fn collect_citations(context: &ContextData) -> Vec<Citation> {
+    let mut citations: Vec<Citation> = Vec::new();
+    for symbol in context.relevant_symbols.iter().chain(&context.similar_symbols) {
+        if let Some(citation) = Citation::for_symbol(symbol) {
+            if !citations.iter().any(|c| c.anchor == citation.anchor) {
+                citations.push(citation);
+            }
+        }
+    }
+    citations
+}
+
+/// The file a snippet's source refers to. Agent snippets are labelled with the
+/// tool call that produced them (`Tool: view_file Args: {"path": ...}`); their
+/// `path` argument is the file, and the parsed arguments are returned too.
+pub(crate) fn source_path(source: &str) -> Option<(String, Option<serde_json::Value>)> {
+    if let Some(rest) = source.strip_prefix("Tool: ") {
+        let args: serde_json::Value = serde_json::from_str(rest.split_once("Args: ")?.1).ok()?;
+        let path = args["path"].as_str()?.to_string();
+        return Some((path, Some(args)));
+    }
+    let source = source.trim();
+    (!source.is_empty()).then(|| (source.to_string(), None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, kind: &str, file_path: &str, lines: (i64, i64)) -> SymbolInfo {
+        SymbolInfo {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            content: String::new(),
+            file_path: file_path.to_string(),
+            start_line: lines.0,
+            end_line: lines.1,
+            props: vec![],
+            references: vec![],
+            is_exported: true,
+            documentation: None,
+            language: None,
+        }
+    }
+
+    #[test]
+    fn test_citation_anchors() {
+        let anchor = |s: &SymbolInfo| Citation::for_symbol(s).map(|c| c.anchor);
+
+        assert_eq!(anchor(&symbol("LoginForm", "component", "src/login.tsx", (12, 40))).as_deref(), Some("src/login.tsx:12-40"));
+        assert_eq!(anchor(&symbol("ContextItem", "snippet", "src/login.tsx", (0, 0))).as_deref(), Some("src/login.tsx"));
+        assert_eq!(
+            anchor(&symbol("ContextItem", "snippet", r#"Tool: git_blame Args: {"path":"src/a.rs","start_line":3,"end_line":9}"#, (0, 0))).as_deref(),
+            Some("src/a.rs:3-9")
+        );
+        assert_eq!(anchor(&symbol("ContextItem", "snippet", r#"Tool: grep Args: {"pattern":"login"}"#, (0, 0))), None);
+        assert_eq!(anchor(&symbol("ImplementationPlan", "plan", "implementation_plan.md", (0, 0))), None);
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{code_block, Citation, SymbolInfo};
+
+/// Dependents shown with an edit target; more only repeats the same call pattern
+const MAX_DEPENDENTS: usize = 8;
+
+/// The symbol a modification task changes, and the code that uses it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditTarget {
+    pub symbol: SymbolInfo,
+    /// Symbols that reference the target directly; an edit must keep them working
+    #[serde(default)]
+    pub dependents: Vec<SymbolInfo>,
+}
+
+impl EditTarget {
+    pub fn new(symbol: SymbolInfo, mut dependents: Vec<SymbolInfo>) -> Self {
+        dependents.retain(|d| !(d.name == symbol.name && d.file_path == symbol.file_path));
+        dependents.truncate(MAX_DEPENDENTS);
+        Self { symbol, dependents }
+    }
+
+    /// The target's current code, exactly as it is in the file
+    pub fn render(&self) -> String {
+        let symbol = &self.symbol;
+        let mut section = String::from("# Code to Change\n");
+        section.push_str("This is the current code of the symbol the task changes. Your diff applies to it.\n\n");
+        section.push_str(&format!("## `{}` ({})\n", symbol.name, symbol.kind));
+        match Citation::for_symbol(symbol) {
+            Some(citation) if citation.start_line.is_some() => {
+                section.push_str(&format!("**File:** `{}` (lines {}-{})\n", citation.path, symbol.start_line, symbol.end_line))
+            }
+            _ => section.push_str(&format!("**File:** `{}`\n", symbol.file_path)),
+        }
+        section.push_str(&code_block(symbol.language(), &symbol.content));
+        section.push('\n');
+        section
+    }
+
+    /// Direct dependents of the target, which the change must not break
+    pub fn render_dependents(&self) -> String {
+        let mut section = String::from("# Direct Dependents\n");
+        section.push_str(&format!(
+            "These use `{}`. If the change alters its signature or behaviour, update them in the same diff.\n\n",
+            self.symbol.name
+        ));
+        for dependent in &self.dependents {
+            let source = Citation::for_symbol(dependent).map(|c| c.anchor).unwrap_or_else(|| dependent.file_path.clone());
+            section.push_str(&format!("## `{}` ({}) in `{}`\n", dependent.name, dependent.kind, source));
+            section.push_str(&code_block(dependent.language(), &dependent.content));
+            section.push('\n');
+        }
+        section
+    }
+}
+
+/// Instructions asking for a unified diff instead of whole files
+pub fn diff_instructions(target: Option<&EditTarget>) -> String {
+    let mut section = String::from("## OUTPUT FORMAT: UNIFIED DIFF 🩹\n\n");
+    section.push_str("Do NOT rewrite whole files. Answer with a single unified diff that `git apply` accepts:\n\n");
+    section.push_str("1. **HEADERS**: `--- a/<path>` and `+++ b/<path>` with paths relative to the repository root\n");
+    section.push_str("2. **HUNKS**: `@@ -start,count +start,count @@` headers with the original file's line numbers\n");
+    section.push_str("3. **CONTEXT**: up to 3 unchanged lines around each change, copied exactly from the code above\n");
+    section.push_str("4. **MINIMAL**: change only the lines the task needs; keep formatting and unrelated code as is\n");
+    if let Some(target) = target {
+        section.push_str(&format!(
+            "5. **SCOPE**: the change centres on `{}` in `{}`",
+            target.symbol.name,
+            Citation::for_symbol(&target.symbol).map(|c| c.path).unwrap_or_else(|| target.symbol.file_path.clone())
+        ));
+        if target.dependents.is_empty() {
+            section.push('\n');
+        } else {
+            section.push_str("; include hunks for any dependents it breaks\n");
+        }
+    }
+    section.push_str("\nBefore the diff, explain the change in at most three sentences. Put the diff in a ```diff block.\n\n");
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, file_path: &str, start_line: i64, content: &str) -> SymbolInfo {
+        SymbolInfo {
+            name: name.to_string(),
+            kind: "function".to_string(),
+            content: content.to_string(),
+            file_path: file_path.to_string(),
+            start_line,
+            end_line: start_line + content.lines().count() as i64 - 1,
+            props: vec![],
+            references: vec![],
+            is_exported: true,
+            documentation: None,
+            language: None,
+        }
+    }
+
+    #[test]
+    fn test_edit_target_sections() {
+        let parse = symbol("parse_config", "src/config.rs", 12, "pub fn parse_config() {\n    todo!()\n}");
+        let target = EditTarget::new(
+            parse.clone(),
+            vec![parse, symbol("main", "src/main.rs", 3, "fn main() {\n    parse_config();\n}")],
+        );
+        assert_eq!(target.dependents.len(), 1);
+
+        let code = target.render();
+        assert!(code.contains("**File:** `src/config.rs` (lines 12-14)\n```rust\npub fn parse_config() {"));
+
+        let dependents = target.render_dependents();
+        assert!(dependents.contains("These use `parse_config`."));
+        assert!(dependents.contains("## `main` (function) in `src/main.rs:3-5`"));
+
+        let instructions = diff_instructions(Some(&target));
+        assert!(instructions.contains("`--- a/<path>`"));
+        assert!(instructions.contains("centres on `parse_config` in `src/config.rs`; include hunks"));
+        assert!(!diff_instructions(None).contains("SCOPE"));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{code_block, Citation, ContextData, SymbolInfo};
+
+/// Kinds a task can ask for, with the words that ask for them
+const KIND_KEYWORDS: &[(&str, &[&str])] = &[
+    ("component", &["component", "widget", "button", "form", "modal", "card"]),
+    ("hook", &["hook"]),
+    ("page", &["page", "screen", "view"]),
+    ("class", &["class", "service", "controller"]),
+    ("struct", &["struct"]),
+    ("interface", &["interface", "trait"]),
+    ("enum", &["enum"]),
+    ("function", &["function", "helper", "util", "utility", "handler", "endpoint"]),
+];
+
+/// Picks a few complete, small and widely used implementations from the
+/// context to show as few-shot examples, rather than many partial snippets
+#[derive(Debug, Clone)]
+pub struct ExampleSelector {
+    max_examples: usize,
+    min_lines: usize,
+    max_lines: usize,
+}
+
+impl Default for ExampleSelector {
+    fn default() -> Self {
+        Self { max_examples: 3, min_lines: 3, max_lines: 80 }
+    }
+}
+
+impl ExampleSelector {
+    pub fn new(max_examples: usize) -> Self {
+        Self { max_examples, ..Default::default() }
+    }
+
+    /// Only consider implementations between `min` and `max` lines long
+    pub fn with_line_range(mut self, min: usize, max: usize) -> Self {
+        self.min_lines = min;
+        self.max_lines = max.max(min);
+        self
+    }
+
+    /// The symbol kind a task asks for, e.g. "component" for "Add a settings form"
+    pub fn infer_kind(task: &str) -> Option<&'static str> {
+        let words: Vec<String> = task
+            .split(|c: char| !c.is_alphanumeric())
+            .map(|w| w.to_lowercase())
+            .collect();
+        KIND_KEYWORDS
+            .iter()
+            .find(|(_, keywords)| {
+                words.iter().any(|w| keywords.iter().any(|k| w == k || w.strip_suffix('s') == Some(k)))
+            })
+            .map(|(kind, _)| *kind)
+    }
+
+    /// Best examples for `task`, best first: kind match first, then how often
+    /// the rest of the context uses the symbol, then how close it is to a
+    /// comfortable size. At most one example per file.
+    pub fn select<'a>(&self, task: &str, context: &'a ContextData) -> Vec<&'a SymbolInfo> {
+        let kind = Self::infer_kind(task);
+        let candidates: Vec<&SymbolInfo> = context
+            .relevant_symbols
+            .iter()
+            .chain(&context.similar_symbols)
+            .filter(|s| self.is_complete(s))
+            .collect();
+
+        let mut scored: Vec<(f32, &SymbolInfo)> = candidates
+            .iter()
+            .map(|symbol| {
+                let kind_match = kind.is_some_and(|k| symbol.kind.to_lowercase().contains(k));
+                let used_by = candidates
+                    .iter()
+                    .filter(|other| other.name != symbol.name)
+                    .filter(|other| other.references.contains(&symbol.name) || other.content.contains(symbol.name.as_str()))
+                    .count();
+                let lines = symbol.content.lines().count() as f32;
+                let ideal = (self.min_lines + self.max_lines) as f32 / 3.0;
+                let size = 1.0 - ((lines - ideal).abs() / self.max_lines as f32).min(1.0);
+
+                let mut score = size + (used_by as f32).min(5.0) * 0.5;
+                if kind_match {
+                    score += 3.0;
+                }
+                if symbol.is_exported {
+                    score += 0.5;
+                }
+                (score, *symbol)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut examples: Vec<&SymbolInfo> = Vec::new();
+        for (_, symbol) in scored {
+            if examples.len() >= self.max_examples {
+                break;
+            }
+            let same = |e: &&SymbolInfo| e.file_path == symbol.file_path || e.name == symbol.name;
+            if !examples.iter().any(same) {
+                examples.push(symbol);
+            }
+        }
+        examples
+    }
+
+    /// Whole definitions of a reasonable size. Plans and agent snippets are
+    /// excerpts, and unbalanced brackets mean the code was cut off.
+    fn is_complete(&self, symbol: &SymbolInfo) -> bool {
+        if matches!(symbol.kind.as_str(), "plan" | "snippet") || symbol.name.trim().is_empty() {
+            return false;
+        }
+        let lines = symbol.content.lines().count();
+        if lines < self.min_lines || lines > self.max_lines {
+            return false;
+        }
+        let balance = |open: char, close: char| {
+            symbol.content.matches(open).count() == symbol.content.matches(close).count()
+        };
+        balance('{', '}') && balance('(', ')') && balance('[', ']')
+    }
+
+    /// Render examples as a few-shot section; empty when there are none
+    pub fn render(examples: &[&SymbolInfo]) -> String {
+        if examples.is_empty() {
+            return String::new();
+        }
+        let mut section = String::from("# Examples to Follow\n");
+        section.push_str(
+            "These are complete implementations from this codebase. Follow their structure: \
+             file layout, naming, imports, error handling and exports.\n\n",
+        );
+        for (i, symbol) in examples.iter().enumerate() {
+            let source = Citation::for_symbol(symbol).map(|c| c.anchor).unwrap_or_else(|| symbol.file_path.clone());
+            section.push_str(&format!(
+                "## Example {}: `{}` ({}) from `{}`\n{}\n",
+                i + 1,
+                symbol.name,
+                symbol.kind,
+                source,
+                code_block(symbol.language(), symbol.content.trim_end())
+            ));
+        }
+        section
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, kind: &str, file_path: &str, content: &str) -> SymbolInfo {
+        SymbolInfo {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            content: content.to_string(),
+            file_path: file_path.to_string(),
+            start_line: 1,
+            end_line: content.lines().count() as i64,
+            props: vec![],
+            references: vec![],
+            is_exported: true,
+            documentation: None,
+            language: None,
+        }
+    }
+
+    #[test]
+    fn test_selects_complete_examples_of_the_requested_kind() {
+        let component = "export function Card() {\n  return (\n    <div />\n  );\n}";
+        let helper = "export function formatDate(d) {\n  const s = d.toISOString();\n  return s;\n}";
+        let context = ContextData {
+            relevant_symbols: vec![
+                symbol("formatDate", "function", "src/utils/date.ts", helper),
+                symbol("Card", "component", "src/components/Card.tsx", component),
+                symbol("Truncated", "component", "src/components/Big.tsx", "export function Big() {\n  return (\n    <div>"),
+                symbol("ContextItem", "snippet", "Tool: grep Args: {}", helper),
+            ],
+            similar_symbols: vec![symbol("CardCopy", "component", "src/components/Card.tsx", component)],
+            types: vec![],
+            constants: vec![],
+            design_tokens: vec![],
+            schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
+            common_imports: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
+        };
+
+        assert_eq!(ExampleSelector::infer_kind("Add a settings form"), Some("component"));
+        assert_eq!(ExampleSelector::infer_kind("Write the migration"), None);
+
+        let examples = ExampleSelector::default().select("Create a profile card component", &context);
+        let names: Vec<&str> = examples.iter().map(|s| s.name.as_str()).collect();
+        // Incomplete code and snippets are skipped, and one file gives one example
+        assert_eq!(names, vec!["Card", "formatDate"]);
+
+        let rendered = ExampleSelector::render(&examples);
+        assert!(rendered.starts_with("# Examples to Follow\n"));
+        assert!(rendered.contains("## Example 1: `Card` (component) from `src/components/Card.tsx:1-5`"));
+    }
+}
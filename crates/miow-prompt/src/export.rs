@@ -0,0 +1,261 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::citations::source_path;
+use crate::{code_block, ContextData};
+
+/// Coding assistant whose context format an export targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportTarget {
+    /// A Markdown block of `@file` mentions to paste into Cursor's chat
+    Cursor,
+    /// The `ContextItem[]` JSON an HTTP context provider returns to Continue
+    Continue,
+    /// A command file for `aider --load` that adds the files and reads the instructions
+    Aider,
+}
+
+impl ExportTarget {
+    pub const NAMES: [&'static str; 3] = ["cursor", "continue", "aider"];
+}
+
+impl FromStr for ExportTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "cursor" => Ok(Self::Cursor),
+            "continue" => Ok(Self::Continue),
+            "aider" => Ok(Self::Aider),
+            other => Err(anyhow!("Unknown export target '{}' (expected one of: {})", other, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+/// Writes gathered context in the formats other coding assistants read
+pub struct ContextExporter<'a> {
+    task: &'a str,
+    context: &'a ContextData,
+    project_root: &'a Path,
+}
+
+impl<'a> ContextExporter<'a> {
+    pub fn new(task: &'a str, context: &'a ContextData, project_root: &'a Path) -> Self {
+        Self { task, context, project_root }
+    }
+
+    /// Write the export for `target` into `dir`, returning the files written
+    pub fn export(&self, target: ExportTarget, dir: &Path) -> Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let files = match target {
+            ExportTarget::Cursor => vec![("cursor-context.md", self.cursor())],
+            ExportTarget::Continue => vec![("continue-context.json", self.continue_items()?)],
+            ExportTarget::Aider => {
+                let instructions = dir.join("aider-instructions.md");
+                vec![
+                    ("aider-instructions.md", self.aider_instructions()),
+                    ("aider-commands.txt", self.aider_commands(&instructions)),
+                ]
+            }
+        };
+
+        let mut written = Vec::new();
+        for (name, content) in files {
+            let path = dir.join(name);
+            std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    /// Project files the context came from, relative to the project root and in
+    /// order of first appearance. Only files that exist are listed.
+    pub fn files(&self) -> Vec<String> {
+        let c = self.context;
+        let candidates = c
+            .relevant_symbols
+            .iter()
+            .chain(&c.similar_symbols)
+            .map(|s| s.file_path.as_str())
+            .chain(c.tests.iter().map(|t| t.file_path.as_str()))
+            .chain(c.routes.iter().map(|r| r.file_path.as_str()));
+
+        let mut files: Vec<String> = Vec::new();
+        for candidate in candidates {
+            if let Some(file) = self.project_file(candidate) {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+        }
+        files
+    }
+
+    /// `candidate` as a path relative to the project root, if it names a file in the project
+    fn project_file(&self, candidate: &str) -> Option<String> {
+        let (candidate, _) = source_path(candidate)?;
+        let path = Path::new(&candidate);
+        let absolute = if path.is_absolute() { path.to_path_buf() } else { self.project_root.join(path) };
+        if !absolute.is_file() {
+            return None;
+        }
+        let relative = absolute.strip_prefix(self.project_root).ok()?;
+        Some(relative.to_string_lossy().replace('\\', "/"))
+    }
+
+    fn plan(&self) -> Option<&str> {
+        self.context
+            .relevant_symbols
+            .iter()
+            .find(|s| s.kind == "plan")
+            .map(|s| s.content.trim())
+    }
+
+    fn cursor(&self) -> String {
+        let mut out = format!("{}\n\n", self.task);
+        let files = self.files();
+        if !files.is_empty() {
+            out.push_str("Relevant files:\n");
+            for file in &files {
+                out.push_str(&format!("@{}\n", file));
+            }
+            out.push('\n');
+        }
+        if let Some(plan) = self.plan() {
+            out.push_str(&format!("Plan:\n{}\n", plan));
+        }
+        out
+    }
+
+    fn continue_items(&self) -> Result<String> {
+        let mut items = vec![json!({
+            "name": "Task",
+            "description": "What to implement",
+            "content": self.task,
+        })];
+        for symbol in &self.context.relevant_symbols {
+            let (name, description) = match symbol.kind.as_str() {
+                "plan" => ("Implementation plan".to_string(), "Plan from miow-context".to_string()),
+                _ => (
+                    self.project_file(&symbol.file_path).unwrap_or_else(|| symbol.name.clone()),
+                    format!("{} ({})", symbol.name, symbol.kind),
+                ),
+            };
+            items.push(json!({ "name": name, "description": description, "content": symbol.content }));
+        }
+        for symbol in &self.context.similar_symbols {
+            items.push(json!({
+                "name": symbol.name,
+                "description": format!("Similar {} in {}", symbol.kind, symbol.file_path),
+                "content": symbol.content,
+            }));
+        }
+        for type_info in &self.context.types {
+            items.push(json!({ "name": type_info.name, "description": format!("{} definition", type_info.kind), "content": type_info.definition }));
+        }
+        for test in &self.context.tests {
+            items.push(json!({ "name": test.name, "description": format!("Test of {} in {}", test.exercises, test.file_path), "content": test.content }));
+        }
+        Ok(serde_json::to_string_pretty(&items)?)
+    }
+
+    fn aider_instructions(&self) -> String {
+        let mut out = format!("# Task\n\n{}\n\n", self.task);
+        if let Some(plan) = self.plan() {
+            out.push_str(&format!("# Plan\n\n{}\n\n", plan));
+        }
+        if !self.context.types.is_empty() {
+            out.push_str("# Types to reuse\n\n");
+            let language = self.context.primary_language();
+            for type_info in &self.context.types {
+                out.push_str(&format!("{}\n", code_block(language, type_info.definition.trim())));
+            }
+        }
+        out
+    }
+
+    fn aider_commands(&self, instructions: &Path) -> String {
+        let mut out = String::new();
+        for file in self.files() {
+            out.push_str(&format!("/add {}\n", file));
+        }
+        out.push_str(&format!("/read-only {}\n", instructions.display()));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SymbolInfo;
+
+    fn symbol(name: &str, kind: &str, file_path: &str) -> SymbolInfo {
+        SymbolInfo {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            content: format!("// {}", name),
+            file_path: file_path.to_string(),
+            start_line: 0,
+            end_line: 0,
+            props: vec![],
+            references: vec![],
+            is_exported: false,
+            documentation: None,
+            language: None,
+        }
+    }
+
+    #[test]
+    fn test_exports_list_existing_project_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/login.ts"), "export {}").unwrap();
+
+        let context = ContextData {
+            relevant_symbols: vec![
+                symbol("ContextItem", "snippet", "Tool: view_file Args: {\"path\":\"src/login.ts\"}"),
+                symbol("ContextItem", "snippet", "Tool: grep Args: {\"pattern\":\"login\"}"),
+                symbol("ImplementationPlan", "plan", "implementation_plan.md"),
+            ],
+            similar_symbols: vec![symbol("LoginForm", "component", "src/login.ts")],
+            types: vec![],
+            constants: vec![],
+            design_tokens: vec![],
+            schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
+            common_imports: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
+        };
+        let exporter = ContextExporter::new("Add a login page", &context, root);
+        assert_eq!(exporter.files(), vec!["src/login.ts"]);
+
+        let out = root.join("exports");
+        exporter.export(ExportTarget::Cursor, &out).unwrap();
+        let cursor = std::fs::read_to_string(out.join("cursor-context.md")).unwrap();
+        assert!(cursor.contains("@src/login.ts\n"));
+
+        exporter.export(ExportTarget::Continue, &out).unwrap();
+        let items: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(out.join("continue-context.json")).unwrap()).unwrap();
+        assert_eq!(items[0]["content"], "Add a login page");
+        assert_eq!(items[1]["name"], "src/login.ts");
+
+        exporter.export(ExportTarget::Aider, &out).unwrap();
+        let commands = std::fs::read_to_string(out.join("aider-commands.txt")).unwrap();
+        assert!(commands.starts_with("/add src/login.ts\n/read-only "));
+        assert!(std::fs::read_to_string(out.join("aider-instructions.md")).unwrap().contains("// ImplementationPlan"));
+
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::str::FromStr;
+
+use crate::{collect_citations, ContextData};
+
+/// Shape of the generated prompt, for piping it into different tools
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptFormat {
+    /// One Markdown document (the default)
+    #[default]
+    Markdown,
+    /// Each section wrapped in an XML tag, the layout Claude models are tuned for
+    Xml,
+    /// A JSON array of `{role, content}` chat messages: rules as the system message, the rest as the user message
+    Chatml,
+    /// A JSON object with the task, every section by name and the raw context
+    Json,
+}
+
+impl PromptFormat {
+    pub const NAMES: [&'static str; 4] = ["markdown", "xml", "chatml", "json"];
+
+    /// File extension for a prompt in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Xml => "xml",
+            Self::Chatml | Self::Json => "json",
+        }
+    }
+}
+
+impl FromStr for PromptFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "xml" => Ok(Self::Xml),
+            "chatml" => Ok(Self::Chatml),
+            "json" => Ok(Self::Json),
+            other => Err(anyhow!("Unknown prompt format '{}' (expected one of: {})", other, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+/// One named part of a meta-prompt, holding its Markdown
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptPart {
+    pub name: &'static str,
+    pub content: String,
+    /// Standing instructions rather than task material; becomes the system message in ChatML
+    #[serde(skip)]
+    pub instructions: bool,
+}
+
+impl PromptPart {
+    pub(crate) fn new(name: &'static str, content: String) -> Self {
+        Self { name, content, instructions: false }
+    }
+
+    pub(crate) fn instructions(name: &'static str, content: String) -> Self {
+        Self { name, content, instructions: true }
+    }
+}
+
+/// Render the parts of a meta-prompt in `format`
+pub fn render_parts(
+    format: PromptFormat,
+    task: &str,
+    project_info: Option<&str>,
+    parts: &[PromptPart],
+    context: &ContextData,
+) -> Result<String> {
+    match format {
+        PromptFormat::Markdown => Ok(parts.iter().map(|p| p.content.as_str()).collect()),
+        PromptFormat::Xml => {
+            let mut out = String::new();
+            for part in parts.iter().filter(|p| !p.content.trim().is_empty()) {
+                out.push_str(&format!("<{}>\n{}\n</{}>\n\n", part.name, part.content.trim(), part.name));
+            }
+            Ok(out)
+        }
+        PromptFormat::Chatml => {
+            let join = |instructions: bool| -> String {
+                parts
+                    .iter()
+                    .filter(|p| p.instructions == instructions)
+                    .map(|p| p.content.as_str())
+                    .collect::<String>()
+                    .trim()
+                    .to_string()
+            };
+            let messages = json!([
+                { "role": "system", "content": join(true) },
+                { "role": "user", "content": join(false) },
+            ]);
+            Ok(serde_json::to_string_pretty(&messages)?)
+        }
+        PromptFormat::Json => {
+            let document = json!({
+                "task": task,
+                "project": project_info,
+                "sections": parts,
+                "citations": collect_citations(context),
+                "context": context,
+            });
+            Ok(serde_json::to_string_pretty(&document)?)
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+/// Sites listed in a prompt; past this the list stops helping and only costs tokens
+const MAX_SITES: usize = 40;
+
+/// Everything a rename or refactor of one symbol has to touch, from the graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefactorImpact {
+    pub name: String,
+    pub sites: Vec<ImpactSite>,
+}
+
+/// One definition, reference, import or export of the symbol
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactSite {
+    /// `definition`, `reference`, `import` or `export`
+    pub kind: String,
+    pub file_path: String,
+    /// The defining or referencing symbol, or the module an import comes from
+    pub detail: Option<String>,
+    /// Lines that mention the name
+    pub lines: Vec<i64>,
+}
+
+impl RefactorImpact {
+    /// A checklist of the sites, grouped by file
+    pub fn render(&self) -> String {
+        let mut files: Vec<&str> = self.sites.iter().map(|s| s.file_path.as_str()).collect();
+        files.sort_unstable();
+        files.dedup();
+        let shown = &self.sites[..self.sites.len().min(MAX_SITES)];
+
+        let mut section = String::from("# Rename Impact\n");
+        section.push_str(&format!(
+            "Renaming or changing `{}` touches {} place(s) in {} file(s). Update every one in the same change, or explain why it can stay.\n\n",
+            self.name,
+            self.sites.len(),
+            files.len()
+        ));
+        for file in files {
+            let sites: Vec<String> =
+                shown.iter().filter(|site| site.file_path == file).map(ImpactSite::describe).collect();
+            if !sites.is_empty() {
+                section.push_str(&format!("- `{}`: {}\n", file, sites.join("; ")));
+            }
+        }
+        if self.sites.len() > shown.len() {
+            section.push_str(&format!("\n...and {} more site(s).\n", self.sites.len() - shown.len()));
+        }
+        section.push('\n');
+        section
+    }
+}
+
+impl ImpactSite {
+    fn describe(&self) -> String {
+        let mut description = self.kind.clone();
+        match (self.kind.as_str(), &self.detail) {
+            ("import", Some(source)) => description.push_str(&format!(" from `{}`", source)),
+            (_, Some(symbol)) => description.push_str(&format!(" in `{}`", symbol)),
+            (_, None) => {}
+        }
+        match self.lines.as_slice() {
+            [] => {}
+            [line] => description.push_str(&format!(" (line {})", line)),
+            lines => {
+                let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+                description.push_str(&format!(" (lines {})", lines.join(", ")));
+            }
+        }
+        description
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(kind: &str, file_path: &str, detail: Option<&str>, lines: Vec<i64>) -> ImpactSite {
+        ImpactSite {
+            kind: kind.to_string(),
+            file_path: file_path.to_string(),
+            detail: detail.map(str::to_string),
+            lines,
+        }
+    }
+
+    #[test]
+    fn test_render_groups_sites_by_file() {
+        let impact = RefactorImpact {
+            name: "validateEmail".to_string(),
+            sites: vec![
+                site("definition", "src/utils/validation.ts", Some("validateEmail"), vec![4]),
+                site("export", "src/utils/validation.ts", None, vec![]),
+                site("import", "src/components/LoginForm.tsx", Some("../utils/validation"), vec![2]),
+                site("reference", "src/components/LoginForm.tsx", Some("LoginForm"), vec![14, 21]),
+            ],
+        };
+        let section = impact.render();
+        assert!(section.contains("touches 4 place(s) in 2 file(s)"));
+        assert!(section.contains(
+            "- `src/components/LoginForm.tsx`: import from `../utils/validation` (line 2); reference in `LoginForm` (lines 14, 21)\n"
+        ));
+        assert!(section.contains("- `src/utils/validation.ts`: definition in `validateEmail` (line 4); export\n"));
+    }
+}
@@ -0,0 +1,112 @@
+use std::cmp::Ordering;
+
+/// A context item competing for the token budget
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate {
+    /// How much the item helps the task, e.g. its relevance score
+    pub value: f32,
+    /// Tokens it takes up in the prompt
+    pub tokens: usize,
+    /// Tokens of the whole item, when this candidate is a slice of it
+    pub full_tokens: Option<usize>,
+}
+
+impl Candidate {
+    pub fn new(value: f32, tokens: usize) -> Self {
+        Self { value, tokens, full_tokens: None }
+    }
+
+    /// A slice of an item of `full_tokens` tokens, competing at its own size
+    pub fn sliced(value: f32, tokens: usize, full_tokens: usize) -> Self {
+        Self { value, tokens, full_tokens: Some(full_tokens) }
+    }
+
+    /// Value per token; an empty item counts as one token
+    fn density(&self) -> f32 {
+        self.value / self.tokens.max(1) as f32
+    }
+}
+
+/// Indices of the candidates that fit in `budget` tokens, in their original
+/// order. A greedy knapsack: candidates are taken by value per token, and one
+/// that no longer fits is skipped rather than ending the selection, so a large
+/// file can't push out the small, relevant items behind it.
+pub fn select_within_budget(candidates: &[Candidate], budget: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (&candidates[a], &candidates[b]);
+        b.density()
+            .partial_cmp(&a.density())
+            .unwrap_or(Ordering::Equal)
+            .then(b.value.partial_cmp(&a.value).unwrap_or(Ordering::Equal))
+    });
+
+    let mut remaining = budget;
+    let mut selected: Vec<usize> = order
+        .into_iter()
+        .filter(|&i| {
+            let fits = candidates[i].tokens <= remaining;
+            if fits {
+                remaining -= candidates[i].tokens;
+            }
+            fits
+        })
+        .collect();
+    selected.sort_unstable();
+    selected
+}
+
+/// Of the `selected` candidates that are slices, the ones whose whole item fits
+/// in what the selection leaves of `budget`, most valuable first
+pub fn restore_within_budget(candidates: &[Candidate], selected: &[usize], budget: usize) -> Vec<usize> {
+    let used: usize = selected.iter().map(|&i| candidates[i].tokens).sum();
+    let mut remaining = budget.saturating_sub(used);
+    let mut sliced: Vec<usize> = selected.iter().copied().filter(|&i| candidates[i].full_tokens.is_some()).collect();
+    sliced.sort_by(|&a, &b| candidates[b].value.partial_cmp(&candidates[a].value).unwrap_or(Ordering::Equal));
+
+    let mut restored: Vec<usize> = sliced
+        .into_iter()
+        .filter(|&i| {
+            let extra = candidates[i].full_tokens.unwrap_or_default().saturating_sub(candidates[i].tokens);
+            let fits = extra <= remaining;
+            if fits {
+                remaining -= extra;
+            }
+            fits
+        })
+        .collect();
+    restored.sort_unstable();
+    restored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_item_does_not_evict_small_relevant_ones() {
+        let candidates = [
+            Candidate::new(0.9, 300),
+            Candidate::new(0.95, 5000),
+            Candidate::new(0.8, 200),
+            Candidate::new(0.3, 400),
+        ];
+        // Truncating the list by relevance would keep the 5000-token file and blow the budget
+        assert_eq!(select_within_budget(&candidates, 1000), vec![0, 2, 3]);
+        assert_eq!(select_within_budget(&candidates, 600), vec![0, 2]);
+        assert_eq!(select_within_budget(&candidates, 6000), vec![0, 1, 2, 3]);
+        assert!(select_within_budget(&candidates, 100).is_empty());
+    }
+
+    #[test]
+    fn test_slices_get_their_whole_item_back_when_the_budget_allows() {
+        let candidates = [Candidate::sliced(0.95, 300, 5000), Candidate::new(0.8, 200), Candidate::sliced(0.6, 100, 900)];
+        let selected = select_within_budget(&candidates, 1500);
+        assert_eq!(selected, vec![0, 1, 2]);
+        // 900 tokens are left: the more valuable slice's item doesn't fit, the other one does
+        assert_eq!(restore_within_budget(&candidates, &selected, 1500), vec![2]);
+        // The most valuable item is restored first, even if that leaves no room for the rest
+        assert_eq!(restore_within_budget(&candidates, &selected, 6000), vec![0]);
+        assert_eq!(restore_within_budget(&candidates, &selected, 7000), vec![0, 2]);
+    }
+}
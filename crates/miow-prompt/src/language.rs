@@ -0,0 +1,167 @@
+use crate::{citations::source_path, ContextData, SymbolInfo};
+
+/// File extensions, the Markdown fence tag for them and a display name
+const LANGUAGES: &[(&[&str], &str, &str)] = &[
+    (&["rs"], "rust", "Rust"),
+    (&["ts", "mts", "cts"], "typescript", "TypeScript"),
+    (&["tsx"], "tsx", "TypeScript (TSX)"),
+    (&["js", "mjs", "cjs"], "javascript", "JavaScript"),
+    (&["jsx"], "jsx", "JavaScript (JSX)"),
+    (&["py", "pyi"], "python", "Python"),
+    (&["go"], "go", "Go"),
+    (&["java"], "java", "Java"),
+    (&["kt", "kts"], "kotlin", "Kotlin"),
+    (&["swift"], "swift", "Swift"),
+    (&["rb", "rake"], "ruby", "Ruby"),
+    (&["php"], "php", "PHP"),
+    (&["cs"], "csharp", "C#"),
+    (&["c", "h"], "c", "C"),
+    (&["cc", "cpp", "cxx", "hpp", "hh"], "cpp", "C++"),
+    (&["vue"], "vue", "Vue"),
+    (&["svelte"], "svelte", "Svelte"),
+    (&["sql"], "sql", "SQL"),
+    (&["sh", "bash", "zsh"], "bash", "Shell"),
+    (&["css"], "css", "CSS"),
+    (&["scss", "sass"], "scss", "SCSS"),
+    (&["html", "htm"], "html", "HTML"),
+    (&["json"], "json", "JSON"),
+    (&["yaml", "yml"], "yaml", "YAML"),
+    (&["toml"], "toml", "TOML"),
+    (&["tf", "hcl"], "hcl", "HCL"),
+    (&["md", "mdx"], "markdown", "Markdown"),
+    (&["prisma"], "prisma", "Prisma"),
+    (&["graphql", "gql"], "graphql", "GraphQL"),
+];
+
+/// Fence tag for a file, from its extension (or name, for `Dockerfile`s)
+pub fn language_for_path(path: &str) -> Option<&'static str> {
+    let file_name = path.rsplit(['/', '\\']).next()?;
+    if file_name == "Dockerfile" || file_name.starts_with("Dockerfile.") {
+        return Some("dockerfile");
+    }
+    let extension = file_name.rsplit_once('.')?.1.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(extensions, _, _)| extensions.contains(&extension.as_str()))
+        .map(|(_, tag, _)| *tag)
+}
+
+/// Human-readable name for a fence tag, e.g. "Rust" for `rust`
+pub fn language_name(tag: &str) -> &str {
+    match tag {
+        "dockerfile" => "Dockerfile",
+        _ => LANGUAGES.iter().find(|(_, t, _)| *t == tag).map(|(_, _, name)| *name).unwrap_or(tag),
+    }
+}
+
+/// A fenced code block tagged with `language` when it's known
+pub fn code_block(language: Option<&str>, code: &str) -> String {
+    format!("```{}\n{}\n```\n", language.unwrap_or_default(), code)
+}
+
+/// `**Language:** Rust` line shown with a snippet; empty when unknown
+pub(crate) fn language_tag(symbol: &SymbolInfo) -> String {
+    symbol
+        .language()
+        .map(|language| format!("**Language:** {}\n", language_name(language)))
+        .unwrap_or_default()
+}
+
+impl SymbolInfo {
+    /// The symbol's language: as recorded, or inferred from its file path
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref().or_else(|| {
+            let (path, _) = source_path(&self.file_path)?;
+            language_for_path(&path)
+        })
+    }
+}
+
+impl ContextData {
+    /// The language most of the relevant code is in, for snippets without a path
+    pub fn primary_language(&self) -> Option<&str> {
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for language in self.relevant_symbols.iter().chain(&self.similar_symbols).filter_map(SymbolInfo::language) {
+            match counts.iter_mut().find(|(l, _)| *l == language) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((language, 1)),
+            }
+        }
+        // Ties go to the language seen first, i.e. the most relevant
+        counts.iter().rev().max_by_key(|(_, n)| *n).map(|(l, _)| *l)
+    }
+}
+
+/// Symbols grouped by language, keeping relevance order within each group and
+/// ordering groups by their most relevant symbol. Unknown languages come last.
+pub fn group_by_language<'a, I>(symbols: I) -> Vec<(Option<&'a str>, Vec<&'a SymbolInfo>)>
+where
+    I: IntoIterator<Item = &'a SymbolInfo>,
+{
+    let mut groups: Vec<(Option<&str>, Vec<&SymbolInfo>)> = Vec::new();
+    for symbol in symbols {
+        let language = symbol.language();
+        match groups.iter_mut().find(|(l, _)| *l == language) {
+            Some((_, group)) => group.push(symbol),
+            None => groups.push((language, vec![symbol])),
+        }
+    }
+    groups.sort_by_key(|(language, _)| language.is_none());
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, file_path: &str) -> SymbolInfo {
+        SymbolInfo {
+            name: name.to_string(),
+            kind: "function".to_string(),
+            content: String::new(),
+            file_path: file_path.to_string(),
+            start_line: 1,
+            end_line: 2,
+            props: vec![],
+            references: vec![],
+            is_exported: true,
+            documentation: None,
+            language: None,
+        }
+    }
+
+    #[test]
+    fn test_languages_are_inferred_and_grouped() {
+        assert_eq!(language_for_path("src/lib.rs"), Some("rust"));
+        assert_eq!(language_for_path("app/page.TSX"), Some("tsx"));
+        assert_eq!(language_for_path("docker/Dockerfile"), Some("dockerfile"));
+        assert_eq!(language_for_path("Makefile"), None);
+        assert_eq!(language_name("csharp"), "C#");
+        assert_eq!(code_block(Some("python"), "pass"), "```python\npass\n```\n");
+
+        let agent_snippet = symbol("ContextItem", r#"Tool: read_file Args: {"path":"api/views.py"}"#);
+        assert_eq!(agent_snippet.language(), Some("python"));
+        let mut recorded = symbol("schema", "db/schema");
+        recorded.language = Some("sql".to_string());
+        assert_eq!(recorded.language(), Some("sql"));
+
+        let symbols = [
+            symbol("handler", "server/src/api.rs"),
+            symbol("Button", "web/Button.tsx"),
+            symbol("notes", "NOTES"),
+            symbol("routes", "server/src/routes.rs"),
+        ];
+        let groups: Vec<(Option<&str>, Vec<&str>)> = group_by_language(&symbols)
+            .into_iter()
+            .map(|(l, g)| (l, g.iter().map(|s| s.name.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (Some("rust"), vec!["handler", "routes"]),
+                (Some("tsx"), vec!["Button"]),
+                (None, vec!["notes"]),
+            ]
+        );
+    }
+}
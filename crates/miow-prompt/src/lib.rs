@@ -1,30 +1,82 @@
+use miow_analyzer::Intent;
 use serde::{Deserialize, Serialize};
 
 pub mod meta_prompt;
+pub mod format;
+pub mod export;
 pub mod pruner;
+pub mod knapsack;
+pub mod slicing;
 pub mod deduplication;
+pub mod tokens;
+pub mod templates;
+pub mod citations;
+pub mod examples;
+pub mod redaction;
+pub mod language;
+pub mod diff;
+pub mod impact;
+pub mod catalog;
 
 pub use meta_prompt::*;
+pub use format::*;
+pub use export::{ContextExporter, ExportTarget};
 pub use pruner::*;
+pub use knapsack::{restore_within_budget, select_within_budget, Candidate};
+pub use slicing::SnippetSlicer;
 pub use deduplication::*;
+pub use tokens::{TokenCounter, TokenEncoding};
+pub use templates::{PromptTemplates, TemplateData};
+pub use citations::{collect_citations, Citation};
+pub use examples::ExampleSelector;
+pub use redaction::{redact_context, Redaction};
+pub use miow_common::SecretRedactor;
+pub use language::{code_block, group_by_language, language_for_path, language_name};
+pub use diff::{diff_instructions, EditTarget};
+pub use impact::{ImpactSite, RefactorImpact};
+pub use catalog::{render_component_catalog, ComponentInfo, ComponentProp};
+use language::language_tag;
 
 /// Prompt generator - creates context-aware prompts for LLMs
-pub struct PromptGenerator;
+#[derive(Default)]
+pub struct PromptGenerator {
+    templates: PromptTemplates,
+}
 
 impl PromptGenerator {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Replace built-in blocks with the user's template overrides
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
     }
 
     /// Generate a complete prompt with context
     pub fn generate(&self, request: &PromptRequest) -> GeneratedPrompt {
-        let system_prompt = self.build_system_prompt(&request.intent);
-        let context_block = self.build_context_block(&request.context);
-        let user_prompt = self.build_user_prompt(&request.original_prompt, &request.context);
-        let implementation_plan = request
-            .implementation_plan
-            .clone()
-            .unwrap_or_else(|| self.build_implementation_plan(&request.context, &request.intent));
+        let data = TemplateData {
+            task: &request.original_prompt,
+            project_info: None,
+            intent: Some(request.intent.as_str()),
+            context: &request.context,
+        };
+        // A template that fails to render falls back to the built-in block
+        let render = |name: &str, default: String| {
+            self.templates.render_or(name, default.clone(), &data).unwrap_or_else(|e| {
+                tracing::warn!("{:#}", e);
+                default
+            })
+        };
+
+        let system_prompt = render("system_prompt", self.build_system_prompt(request.intent));
+        let context_block = render("context_block", self.build_context_block(&request.context));
+        let user_prompt = render("user_prompt", self.build_user_prompt(&request.original_prompt, &request.context));
+        let implementation_plan = match &request.implementation_plan {
+            Some(plan) => plan.clone(),
+            None => render("implementation_plan", self.build_implementation_plan(&request.context, request.intent)),
+        };
         let full_prompt = self.combine_all(
             &system_prompt,
             &context_block,
@@ -38,10 +90,11 @@ impl PromptGenerator {
             user_prompt,
             implementation_plan,
             full_prompt,
+            citations: collect_citations(&request.context),
         }
     }
 
-    fn build_system_prompt(&self, intent: &str) -> String {
+    fn build_system_prompt(&self, intent: Intent) -> String {
         let base = r#"You are an expert software engineer with deep knowledge of the codebase.
 You have been provided with comprehensive context about existing code, components, utilities, and design patterns.
 
@@ -54,9 +107,11 @@ CRITICAL INSTRUCTIONS:
 "#;
 
         let intent_specific = match intent {
-            "CreateComponent" => "\n6. When creating components, check for similar existing components and reuse their patterns\n7. Use the same prop patterns and naming conventions as existing components",
-            "CreateFunction" => "\n6. Check for existing utility functions that solve similar problems\n7. Follow the same function signature patterns",
-            "CreatePage" => "\n6. Reuse existing layout components and page structures\n7. Follow the same routing and navigation patterns",
+            Intent::CreateComponent => "\n6. When creating components, check for similar existing components and reuse their patterns\n7. Use the same prop patterns and naming conventions as existing components",
+            Intent::CreateFunction => "\n6. Check for existing utility functions that solve similar problems\n7. Follow the same function signature patterns",
+            Intent::CreatePage => "\n6. Reuse existing layout components and page structures\n7. Follow the same routing and navigation patterns",
+            Intent::Fix => "\n6. Find the root cause before changing code, and keep the fix minimal\n7. Add a test that fails without the fix",
+            Intent::Test => "\n6. Use the project's existing test framework, helpers and fixtures\n7. Put the tests where the existing tests for that code live",
             _ => "",
         };
 
@@ -66,18 +121,29 @@ CRITICAL INSTRUCTIONS:
     fn build_context_block(&self, context: &ContextData) -> String {
         let mut blocks = Vec::new();
 
-        // Add relevant symbols
-        if !context.relevant_symbols.is_empty() {
-            blocks.push("## Relevant Existing Code\n".to_string());
-            for symbol in &context.relevant_symbols {
+        // Add relevant symbols, one section per language in polyglot repos
+        let groups = group_by_language(&context.relevant_symbols);
+        let polyglot = groups.len() > 1;
+        for (language, symbols) in groups {
+            match language.filter(|_| polyglot) {
+                Some(language) => blocks.push(format!("## Relevant Existing Code ({})\n", language_name(language))),
+                None => blocks.push("## Relevant Existing Code\n".to_string()),
+            }
+            for symbol in symbols {
+                let cite = Citation::for_symbol(symbol)
+                    .map(|c| format!("**Cite as:** `{}`\n", c.anchor))
+                    .unwrap_or_default();
                 blocks.push(format!(
-                    "### {} ({})\n**File:** {}\n**Lines:** {}-{}\n```\n{}\n```\n",
+                    "### {} ({})\n**File:** {}\n**Lines:** {}-{}\n{}{}{}{}",
                     symbol.name,
                     symbol.kind,
                     symbol.file_path,
                     symbol.start_line,
                     symbol.end_line,
-                    symbol.content
+                    language_tag(symbol),
+                    cite,
+                    format_documentation(symbol.documentation.as_deref(), &symbol.content),
+                    code_block(symbol.language(), &symbol.content)
                 ));
             }
         }
@@ -87,8 +153,13 @@ CRITICAL INSTRUCTIONS:
             blocks.push("\n## Similar Existing Patterns\n".to_string());
             for symbol in &context.similar_symbols {
                 blocks.push(format!(
-                    "### {} ({})\n**File:** {}\n```\n{}\n```\n",
-                    symbol.name, symbol.kind, symbol.file_path, symbol.content
+                    "### {} ({})\n**File:** {}\n{}{}{}",
+                    symbol.name,
+                    symbol.kind,
+                    symbol.file_path,
+                    language_tag(symbol),
+                    format_documentation(symbol.documentation.as_deref(), &symbol.content),
+                    code_block(symbol.language(), &symbol.content)
                 ));
             }
         }
@@ -109,8 +180,10 @@ CRITICAL INSTRUCTIONS:
             blocks.push("\n## Type Definitions\n".to_string());
             for type_info in &context.types {
                 blocks.push(format!(
-                    "### {} ({})\n```typescript\n{}\n```\n",
-                    type_info.name, type_info.kind, type_info.definition
+                    "### {} ({})\n{}",
+                    type_info.name,
+                    type_info.kind,
+                    code_block(context.primary_language(), &type_info.definition)
                 ));
             }
         }
@@ -131,8 +204,10 @@ CRITICAL INSTRUCTIONS:
             blocks.push("\n## Validation Schemas\n".to_string());
             for schema in &context.schemas {
                 blocks.push(format!(
-                    "### {} ({})\n```typescript\n{}\n```\n",
-                    schema.name, schema.schema_type, schema.definition
+                    "### {} ({})\n{}",
+                    schema.name,
+                    schema.schema_type,
+                    code_block(context.primary_language(), &schema.definition)
                 ));
             }
         }
@@ -163,29 +238,39 @@ CRITICAL INSTRUCTIONS:
         prompt
     }
 
-    fn build_implementation_plan(&self, context: &ContextData, intent: &str) -> String {
+    fn build_implementation_plan(&self, context: &ContextData, intent: Intent) -> String {
         let mut plan = String::from("## Suggested Implementation Plan\n\n");
 
         match intent {
-            "CreateComponent" => {
+            Intent::CreateComponent => {
                 plan.push_str("1. Review similar existing components for patterns\n");
                 plan.push_str("2. Identify reusable sub-components\n");
                 plan.push_str("3. Use existing design tokens for styling\n");
                 plan.push_str("4. Follow the same prop patterns as similar components\n");
                 plan.push_str("5. Add proper TypeScript types\n");
             }
-            "CreateFunction" => {
+            Intent::CreateFunction => {
                 plan.push_str("1. Check if a similar utility function exists\n");
                 plan.push_str("2. Follow the same function signature patterns\n");
                 plan.push_str("3. Add proper type annotations\n");
                 plan.push_str("4. Include error handling\n");
             }
-            "CreatePage" => {
+            Intent::CreatePage => {
                 plan.push_str("1. Reuse existing layout components\n");
                 plan.push_str("2. Follow the same page structure pattern\n");
                 plan.push_str("3. Use existing components for UI elements\n");
                 plan.push_str("4. Apply consistent styling with design tokens\n");
             }
+            Intent::Fix => {
+                plan.push_str("1. Reproduce the bug and find its root cause in the provided context\n");
+                plan.push_str("2. Make the smallest change that fixes it\n");
+                plan.push_str("3. Add a test that fails without the fix\n");
+            }
+            Intent::Test => {
+                plan.push_str("1. Review the existing tests for the code under test\n");
+                plan.push_str("2. Reuse their framework, helpers and fixtures\n");
+                plan.push_str("3. Cover the main behavior and its edge cases\n");
+            }
             _ => {
                 plan.push_str("1. Review the provided context\n");
                 plan.push_str("2. Identify reusable patterns and components\n");
@@ -193,6 +278,14 @@ CRITICAL INSTRUCTIONS:
             }
         }
 
+        if !context.owned_elsewhere.is_empty() {
+            plan.push_str("\n### Files Owned by Other Teams:\n");
+            plan.push_str("Changes to these need their owners' review:\n");
+            for file in &context.owned_elsewhere {
+                plan.push_str(&format!("- `{}` ({})\n", file.file_path, file.owners.join(", ")));
+            }
+        }
+
         if !context.relevant_symbols.is_empty() {
             plan.push_str("\n### Components/Functions to Reuse:\n");
             for symbol in &context.relevant_symbols {
@@ -211,16 +304,10 @@ CRITICAL INSTRUCTIONS:
     }
 }
 
-impl Default for PromptGenerator {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PromptRequest {
     pub original_prompt: String,
-    pub intent: String,
+    pub intent: Intent,
     pub context: ContextData,
     pub implementation_plan: Option<String>,
 }
@@ -234,6 +321,30 @@ pub struct ContextData {
     pub types: Vec<TypeInfo>,
     pub constants: Vec<ConstantInfo>,
     pub schemas: Vec<SchemaInfo>,
+    #[serde(default)]
+    pub routes: Vec<RouteInfo>,
+    #[serde(default)]
+    pub env_vars: Vec<EnvVarInfo>,
+    #[serde(default)]
+    pub tests: Vec<TestInfo>,
+    #[serde(default)]
+    pub external_references: Vec<ExternalReference>,
+    /// Symbol a modification task changes, for diff-style prompts
+    #[serde(default)]
+    pub edit_target: Option<EditTarget>,
+    /// Every site a rename or refactor of the target touches, for refactor tasks
+    #[serde(default)]
+    pub refactor_impact: Option<RefactorImpact>,
+    /// Files in the context whose CODEOWNERS don't include the user's teams
+    #[serde(default)]
+    pub owned_elsewhere: Vec<OwnedFile>,
+    /// Components and their props, listed in a table instead of as code
+    #[serde(default)]
+    pub components: Vec<ComponentInfo>,
+    /// Names of the read-only indexes of other repositories that items came
+    /// from; their paths are prefixed `name:`
+    #[serde(default)]
+    pub linked_indexes: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -241,6 +352,9 @@ pub struct TypeInfo {
     pub name: String,
     pub kind: String,
     pub definition: String,
+    /// File the definition is in; empty when unknown
+    #[serde(default)]
+    pub file_path: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -255,6 +369,48 @@ pub struct SchemaInfo {
     pub name: String,
     pub schema_type: String,
     pub definition: String,
+    /// File the schema is in; empty when unknown
+    #[serde(default)]
+    pub file_path: String,
+}
+
+/// An existing route or page; `method` is `PAGE` for file-system pages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteInfo {
+    pub method: String,
+    pub path: String,
+    pub handler: Option<String>,
+    pub file_path: String,
+}
+
+/// An environment variable the project reads, with the files that read it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvVarInfo {
+    pub name: String,
+    pub file_paths: Vec<String>,
+}
+
+/// An existing test exercising one of the relevant symbols, shown as a pattern to imitate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestInfo {
+    pub name: String,
+    pub exercises: String,
+    pub file_path: String,
+    pub content: String,
+}
+
+/// A file owned by another team, with its CODEOWNERS entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnedFile {
+    pub file_path: String,
+    pub owners: Vec<String>,
+}
+
+/// Notes found outside the codebase (e.g. by web search) about a dependency's API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalReference {
+    pub source: String,
+    pub content: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -269,6 +425,13 @@ pub struct SymbolInfo {
     pub props: Vec<String>,
     #[serde(default)]
     pub references: Vec<String>,
+    #[serde(default)]
+    pub is_exported: bool,
+    #[serde(default)]
+    pub documentation: Option<String>,
+    /// Markdown fence tag for the code, e.g. `rust`; see [`SymbolInfo::language`]
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -285,4 +448,23 @@ pub struct GeneratedPrompt {
     pub user_prompt: String,
     pub implementation_plan: String,
     pub full_prompt: String,
+    /// Sources of the context snippets, by the anchors the prompt cites them with
+    #[serde(default)]
+    pub citations: Vec<Citation>,
+}
+
+/// Render a symbol's documentation as a blockquote placed above its code block,
+/// unless the code already shows it (Python docstrings live inside the body)
+pub(crate) fn format_documentation(documentation: Option<&str>, content: &str) -> String {
+    let first_line = |doc: &str| doc.lines().next().unwrap_or("").trim().to_string();
+    match documentation
+        .map(str::trim)
+        .filter(|d| !d.is_empty() && !content.contains(&first_line(d)))
+    {
+        Some(doc) => {
+            let quoted: Vec<String> = doc.lines().map(|line| format!("> {}", line).trim_end().to_string()).collect();
+            format!("{}\n\n", quoted.join("\n"))
+        }
+        None => String::new(),
+    }
 }
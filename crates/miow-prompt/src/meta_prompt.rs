@@ -1,48 +1,8 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use crate::{ConstantInfo, ContextData, SchemaInfo, SymbolInfo, TypeInfo};
-
-/// Simple token counter (rough approximation: 1 token ≈ 4 characters)
-struct TokenCounter;
-
-impl TokenCounter {
-    fn count(text: &str) -> usize {
-        // Rough approximation: 4 characters per token
-        (text.len() + 3) / 4
-    }
-
-    fn count_context(context: &ContextData) -> usize {
-        let mut total = 0;
-
-        for symbol in &context.relevant_symbols {
-            total += Self::count(&symbol.content);
-            total += Self::count(&symbol.name);
-        }
-
-        for type_info in &context.types {
-            total += Self::count(&type_info.definition);
-            total += Self::count(&type_info.name);
-        }
-
-        for constant in &context.constants {
-            total += Self::count(&constant.value);
-            total += Self::count(&constant.name);
-        }
-
-        for schema in &context.schemas {
-            total += Self::count(&schema.definition);
-            total += Self::count(&schema.name);
-        }
-
-        for token in &context.design_tokens {
-            total += Self::count(&token.value);
-            total += Self::count(&token.name);
-        }
-
-        total
-    }
-}
+use crate::language::language_tag;
+use crate::{code_block, collect_citations, diff_instructions, format_documentation, group_by_language, language_for_path, language_name, render_component_catalog, render_parts, Citation, ExampleSelector, PromptFormat, PromptPart, PromptTemplates, TemplateData, ContextData, SymbolInfo, TokenCounter};
 
 /// Meta-prompt generator - creates comprehensive, copy-paste ready prompts
 pub struct MetaPromptGenerator;
@@ -54,6 +14,18 @@ pub struct MetaPromptConfig {
     pub include_implementation_plan: bool,
     pub max_examples_per_type: usize,
     pub token_budget: Option<usize>,
+    #[serde(default)]
+    pub format: PromptFormat,
+    /// Complete implementations shown as few-shot examples (0 turns them off)
+    #[serde(default = "default_few_shot_examples")]
+    pub few_shot_examples: usize,
+    /// Ask for a unified diff against the edit target instead of whole files
+    #[serde(default)]
+    pub diff: bool,
+}
+
+fn default_few_shot_examples() -> usize {
+    3
 }
 
 impl Default for MetaPromptConfig {
@@ -64,6 +36,9 @@ impl Default for MetaPromptConfig {
             include_implementation_plan: true,
             max_examples_per_type: 5,
             token_budget: Some(16000),
+            format: PromptFormat::default(),
+            few_shot_examples: default_few_shot_examples(),
+            diff: false,
         }
     }
 }
@@ -76,35 +51,127 @@ impl MetaPromptGenerator {
         project_info: Option<&str>,
         config: MetaPromptConfig,
     ) -> Result<String> {
-        let mut prompt = String::new();
+        Self::generate_with_templates(user_request, context, project_info, config, &PromptTemplates::default())
+    }
+
+    /// Generate a meta-prompt, rendering sections that have an override from `templates`
+    pub fn generate_with_templates(
+        user_request: &str,
+        context: &ContextData,
+        project_info: Option<&str>,
+        config: MetaPromptConfig,
+        templates: &PromptTemplates,
+    ) -> Result<String> {
+        let mut parts = Vec::new();
 
         // ===== HEADER =====
-        prompt.push_str(&Self::build_header(user_request, project_info));
+        parts.push(PromptPart::new("task", Self::build_header(user_request, project_info)));
 
         // ===== FILE STRUCTURE =====
-        prompt.push_str(&build_file_structure(context));
+        parts.push(PromptPart::new("file_structure", build_file_structure(context)));
+
+        // ===== EXISTING ROUTES =====
+        if !context.routes.is_empty() {
+            parts.push(PromptPart::new("routes", build_routes_section(context)));
+        }
+
+        // ===== ENVIRONMENT VARIABLES =====
+        if !context.env_vars.is_empty() {
+            parts.push(PromptPart::new("env_vars", build_env_vars_section(context)));
+        }
+
+        // ===== CODE TO CHANGE =====
+        let edit_target = context.edit_target.as_ref().filter(|_| config.diff);
+        if let Some(target) = edit_target {
+            parts.push(PromptPart::new("edit_target", target.render()));
+            if !target.dependents.is_empty() {
+                parts.push(PromptPart::new("dependents", target.render_dependents()));
+            }
+        }
+
+        // ===== RENAME IMPACT =====
+        if let Some(impact) = &context.refactor_impact {
+            parts.push(PromptPart::new("refactor_impact", impact.render()));
+        }
+
+        // ===== FEW-SHOT EXAMPLES =====
+        // A diff edits existing code, so complete examples of new code don't help
+        let examples = if config.diff {
+            Vec::new()
+        } else {
+            ExampleSelector::new(config.few_shot_examples).select(user_request, context)
+        };
+        if !examples.is_empty() {
+            parts.push(PromptPart::new("examples", ExampleSelector::render(&examples)));
+        }
+
+        // ===== AVAILABLE COMPONENTS =====
+        if !context.components.is_empty() {
+            parts.push(PromptPart::new("components", render_component_catalog(&context.components)));
+        }
+
+        // ===== LINKED REPOSITORIES =====
+        if !context.linked_indexes.is_empty() {
+            parts.push(PromptPart::new("linked_indexes", build_linked_indexes_section(context)));
+        }
 
         // ===== RELEVANT CODEBASE =====
-        let context_section = build_relevant_codebase(context, &config);
-        prompt.push_str(&context_section);
+        // Examples and the code to change are shown in full above, and cataloged
+        // components by their props, so they are not repeated here
+        let mut remaining = context.clone();
+        remaining.relevant_symbols.retain(|s| {
+            let edited = edit_target.into_iter().flat_map(|t| std::iter::once(&t.symbol).chain(&t.dependents));
+            let cataloged = context.components.iter().any(|c| c.name == s.name && c.file_path == s.file_path);
+            !cataloged && !examples.iter().copied().chain(edited).any(|e| e.name == s.name && e.file_path == s.file_path)
+        });
+        parts.push(PromptPart::new("codebase", build_relevant_codebase(&remaining, &config)));
+
+        // ===== CITATION INDEX =====
+        let citations = collect_citations(context);
+        if !citations.is_empty() {
+            parts.push(PromptPart::new("citations", build_citation_index(&citations)));
+        }
+
+        // ===== EXISTING TESTS =====
+        if !context.tests.is_empty() {
+            parts.push(PromptPart::new("tests", build_tests_section(context)));
+        }
+
+        // ===== EXTERNAL REFERENCES =====
+        if !context.external_references.is_empty() {
+            parts.push(PromptPart::new("external_references", build_external_references_section(context)));
+        }
 
         // ===== CONSTRAINTS =====
-        prompt.push_str(&Self::build_constraints());
+        parts.push(PromptPart::instructions("constraints", Self::build_constraints()));
 
         // ===== STYLE GUIDE =====
         if config.include_style_guide {
-            prompt.push_str(&build_style_guide(context));
+            parts.push(PromptPart::instructions("style_guide", build_style_guide(context)));
         }
         
         // ===== IMPLEMENTATION PLAN =====
         if config.include_implementation_plan {
-            prompt.push_str(&build_implementation_plan(user_request, context));
+            parts.push(PromptPart::new("implementation_plan", build_implementation_plan(user_request, context)));
         }
         
         // ===== EXECUTION INSTRUCTIONS =====
-        prompt.push_str(&build_execution_instructions());
+        if config.diff {
+            parts.push(PromptPart::instructions("diff_instructions", diff_instructions(edit_target)));
+        } else {
+            parts.push(PromptPart::instructions("execution_instructions", build_execution_instructions()));
+        }
         
-        Ok(prompt)
+        let data = TemplateData { task: user_request, project_info, intent: None, context };
+        for part in &mut parts {
+            // A template that fails to render falls back to the built-in section
+            match templates.render_or(part.name, part.content.clone(), &data) {
+                Ok(content) => part.content = content,
+                Err(e) => tracing::warn!("{:#}", e),
+            }
+        }
+
+        render_parts(config.format, user_request, project_info, &parts, context)
     }
     
     fn build_header(user_request: &str, project_info: Option<&str>) -> String {
@@ -167,138 +234,6 @@ impl MetaPromptGenerator {
 
 "#.to_string()
     }
-    
-    fn build_existing_context(context: &ContextData, config: &MetaPromptConfig) -> String {
-        // This method is deprecated, use build_relevant_codebase instead
-        build_relevant_codebase(context, config)
-    }
-    }
-
-    fn build_design_tokens_section(context: &ContextData) -> String {
-        let mut section = String::from("### Design Tokens 🎨\n\nUse these EXACT values for styling:\n\n");
-
-        let colors: Vec<_> = context.design_tokens.iter()
-            .filter(|t| t.token_type.contains("Color"))
-            .take(10)
-            .collect();
-        let spacing: Vec<_> = context.design_tokens.iter()
-            .filter(|t| t.token_type.contains("Spacing") || t.token_type.contains("Size"))
-            .take(10)
-            .collect();
-
-        if !colors.is_empty() {
-            section.push_str("**Colors:**\n");
-            for color in colors {
-                section.push_str(&format!("- `{}`: `{}`\n", color.name, color.value));
-            }
-            section.push('\n');
-        }
-
-        if !spacing.is_empty() {
-            section.push_str("**Spacing/Sizes:**\n");
-            for space in spacing {
-                section.push_str(&format!("- `{}`: `{}`\n", space.name, space.value));
-            }
-            section.push('\n');
-        }
-
-        section
-    }
-
-    fn build_constants_section(context: &ContextData, config: &MetaPromptConfig) -> String {
-        let mut section = String::from("### Constants & Configuration\n\n");
-        for (i, constant) in context.constants.iter().take(config.max_examples_per_type).enumerate() {
-            section.push_str(&format_constant(constant, i + 1));
-        }
-        section.push('\n');
-        section
-    }
-
-    fn build_types_section_with_budget(context: &ContextData, config: &MetaPromptConfig, token_budget: usize) -> String {
-        let mut section = String::from("### Type Definitions\n\n");
-        let mut used_tokens = TokenCounter::count(&section);
-
-        for (i, type_info) in context.types.iter().enumerate() {
-            if i >= config.max_examples_per_type {
-                break;
-            }
-
-            let formatted = format_type(type_info, i + 1);
-            let type_tokens = TokenCounter::count(&formatted);
-
-            if used_tokens + type_tokens > token_budget {
-                section.push_str(&format!("... ({} more types omitted due to token limit)\n\n", context.types.len() - i));
-                break;
-            }
-
-            section.push_str(&formatted);
-            used_tokens += type_tokens;
-        }
-
-        section
-    }
-
-    fn build_components_section_with_budget(context: &ContextData, config: &MetaPromptConfig, token_budget: usize) -> String {
-        let mut section = String::from("### Components & Functions\n\n");
-        let mut used_tokens = TokenCounter::count(&section);
-
-        for (i, symbol) in context.relevant_symbols.iter().enumerate() {
-            if i >= config.max_examples_per_type {
-                break;
-            }
-
-            let formatted = format_symbol(symbol, i + 1);
-            let symbol_tokens = TokenCounter::count(&formatted);
-
-            if used_tokens + symbol_tokens > token_budget {
-                section.push_str(&format!("... ({} more components omitted due to token limit)\n\n", context.relevant_symbols.len() - i));
-                break;
-            }
-
-            section.push_str(&formatted);
-            used_tokens += symbol_tokens;
-        }
-
-        section
-    }
-
-    fn build_schemas_section_with_budget(context: &ContextData, config: &MetaPromptConfig, token_budget: usize) -> String {
-        let mut section = String::from("### Validation Schemas\n\n");
-        let mut used_tokens = TokenCounter::count(&section);
-
-        for (i, schema) in context.schemas.iter().enumerate() {
-            if i >= config.max_examples_per_type {
-                break;
-            }
-
-            let formatted = format_schema(schema, i + 1);
-            let schema_tokens = TokenCounter::count(&formatted);
-
-            if used_tokens + schema_tokens > token_budget {
-                section.push_str(&format!("... ({} more schemas omitted due to token limit)\n\n", context.schemas.len() - i));
-                break;
-            }
-
-            section.push_str(&formatted);
-            used_tokens += schema_tokens;
-        }
-
-        section
-    }
-
-    fn summarize_design_tokens(context: &ContextData) -> String {
-        let color_count = context.design_tokens.iter()
-            .filter(|t| t.token_type.contains("Color"))
-            .count();
-        let spacing_count = context.design_tokens.iter()
-            .filter(|t| t.token_type.contains("Spacing") || t.token_type.contains("Size"))
-            .count();
-
-        format!("### Design Tokens 🎨\n\nAvailable: {} colors, {} spacing tokens. Use existing design system patterns.\n\n", color_count, spacing_count)
-    }
-
-    fn summarize_constants(context: &ContextData) -> String {
-        format!("### Constants & Configuration\n\n{} configuration constants available. Follow existing patterns.\n\n", context.constants.len())
     }
 
     fn build_file_structure(context: &ContextData) -> String {
@@ -338,12 +273,80 @@ impl MetaPromptGenerator {
         structure
     }
 
+    fn build_routes_section(context: &ContextData) -> String {
+        let mut section = String::from("# Existing Routes\n");
+        section.push_str("Follow these when adding an endpoint or page; don't duplicate a path that already exists.\n\n");
+
+        for route in &context.routes {
+            match &route.handler {
+                Some(handler) => section.push_str(&format!(
+                    "- `{} {}` → `{}` ({})\n",
+                    route.method, route.path, handler, route.file_path
+                )),
+                None => section.push_str(&format!("- `{} {}` ({})\n", route.method, route.path, route.file_path)),
+            }
+        }
+
+        section.push('\n');
+        section
+    }
+
+    fn build_env_vars_section(context: &ContextData) -> String {
+        let mut section = String::from("# Environment Variables\n");
+        section.push_str("The project already reads these; reuse them instead of introducing new names for the same setting.\n\n");
+
+        for env_var in &context.env_vars {
+            section.push_str(&format!("- `{}` ({})\n", env_var.name, env_var.file_paths.join(", ")));
+        }
+
+        section.push('\n');
+        section
+    }
+
+    fn build_tests_section(context: &ContextData) -> String {
+        let mut section = String::from("# Existing Tests\n");
+        section.push_str("Match the structure, naming and assertion style of these when adding tests.\n\n");
+
+        for test in &context.tests {
+            section.push_str(&format!(
+                "## `{}` ({}) exercises `{}`\n{}\n",
+                test.name,
+                test.file_path,
+                test.exercises,
+                code_block(language_for_path(&test.file_path), &test.content)
+            ));
+        }
+
+        section
+    }
+
+    fn build_external_references_section(context: &ContextData) -> String {
+        let mut section = String::from("# External References (not from this codebase)\n");
+        section.push_str("Found on the web about libraries the task uses. Verify against the installed versions; the codebase wins where they disagree.\n\n");
+
+        for reference in &context.external_references {
+            section.push_str(&format!("## {}\n```\n{}\n```\n\n", reference.source, reference.content.trim()));
+        }
+
+        section
+    }
+
+    fn build_linked_indexes_section(context: &ContextData) -> String {
+        let mut section = String::from("# Linked Repositories\n");
+        section.push_str("Code whose path starts with one of these names comes from another repository, indexed read-only. Import it through that package's public exports and don't edit its files in this change.\n\n");
+        for name in &context.linked_indexes {
+            section.push_str(&format!("- `{}:`\n", name));
+        }
+        section.push('\n');
+        section
+    }
+
     fn build_relevant_codebase(context: &ContextData, config: &MetaPromptConfig) -> String {
         let mut codebase = String::from("# Relevant Codebase\n");
 
         // Build with token budget if specified
         let content = if let Some(token_budget) = config.token_budget {
-            let current_tokens = TokenCounter::count(&codebase);
+            let current_tokens = TokenCounter::default().count(&codebase);
             let remaining_budget = token_budget.saturating_sub(current_tokens);
             build_relevant_codebase_with_budget(context, config, remaining_budget)
         } else {
@@ -358,29 +361,24 @@ impl MetaPromptGenerator {
         let mut content = String::new();
 
         // Components/Symbols - use relevant_symbols
-        if !context.relevant_symbols.is_empty() {
-            for symbol in context.relevant_symbols.iter().take(config.max_examples_per_type) {
-                content.push_str(&format!("## File: {}\n", symbol.file_path));
-                content.push_str(&format!("```\n{}\n```\n\n", symbol.content));
+        let symbols: Vec<&SymbolInfo> = context.relevant_symbols.iter().take(config.max_examples_per_type).collect();
+        for (language, group) in codebase_groups(symbols) {
+            content.push_str(&language_group_heading(language));
+            for symbol in group {
+                content.push_str(&format_codebase_symbol(symbol));
             }
         }
 
+        let language = context.primary_language();
+
         // Types
-        if !context.types.is_empty() {
-            for type_info in context.types.iter().take(config.max_examples_per_type) {
-                // Since types don't have file paths, create a generic one
-                content.push_str("## File: types/definitions.ts\n");
-                content.push_str(&format!("```\n{}\n```\n\n", type_info.definition));
-            }
+        for type_info in context.types.iter().take(config.max_examples_per_type) {
+            content.push_str(&format_definition(&type_info.name, &type_info.file_path, &type_info.definition, language));
         }
 
         // Schemas
-        if !context.schemas.is_empty() {
-            for schema in context.schemas.iter().take(config.max_examples_per_type) {
-                // Since schemas don't have file paths, create a generic one
-                content.push_str("## File: schemas/validation.ts\n");
-                content.push_str(&format!("```\n{}\n```\n\n", schema.definition));
-            }
+        for schema in context.schemas.iter().take(config.max_examples_per_type) {
+            content.push_str(&format_definition(&schema.name, &schema.file_path, &schema.definition, language));
         }
 
         content
@@ -394,36 +392,39 @@ impl MetaPromptGenerator {
 
         // Components (highest priority)
         if !context.relevant_symbols.is_empty() && used_tokens < token_budget {
-            for (i, symbol) in context.relevant_symbols.iter().enumerate() {
-                if i >= config.max_examples_per_type {
-                    break;
-                }
-
-                let formatted = format!("## File: {}\n```\n{}\n```\n\n", symbol.file_path, symbol.content);
-                let symbol_tokens = TokenCounter::count(&formatted);
-
-                if used_tokens + symbol_tokens > token_budget {
-                    content.push_str(&format!("... ({} more files omitted due to token limit)\n\n", context.relevant_symbols.len() - i));
-                    break;
+            let symbols: Vec<&SymbolInfo> = context.relevant_symbols.iter().take(config.max_examples_per_type).collect();
+            let mut shown = 0;
+            'groups: for (language, group) in codebase_groups(symbols) {
+                let mut heading = language_group_heading(language);
+                for symbol in group {
+                    let formatted = format!("{}{}", heading, format_codebase_symbol(symbol));
+                    let symbol_tokens = TokenCounter::default().count(&formatted);
+
+                    if used_tokens + symbol_tokens > token_budget {
+                        content.push_str(&format!("... ({} more files omitted due to token limit)\n\n", context.relevant_symbols.len() - shown));
+                        break 'groups;
+                    }
+
+                    content.push_str(&formatted);
+                    used_tokens += symbol_tokens;
+                    shown += 1;
+                    heading.clear();
                 }
-
-                content.push_str(&formatted);
-                used_tokens += symbol_tokens;
             }
         }
 
+        let language = context.primary_language();
+
         // Types (medium priority)
         if !context.types.is_empty() && used_tokens < token_budget {
             let _remaining_budget = token_budget - used_tokens;
-            let mut type_count = 0;
-
-            for type_info in context.types.iter() {
+            for (type_count, type_info) in context.types.iter().enumerate() {
                 if type_count >= config.max_examples_per_type {
                     break;
                 }
 
-                let formatted = format!("## File: types/definitions.ts\n```\n{}\n```\n\n", type_info.definition);
-                let type_tokens = TokenCounter::count(&formatted);
+                let formatted = format_definition(&type_info.name, &type_info.file_path, &type_info.definition, language);
+                let type_tokens = TokenCounter::default().count(&formatted);
 
                 if used_tokens + type_tokens > token_budget {
                     content.push_str(&format!("... ({} more type definitions omitted due to token limit)\n\n", context.types.len() - type_count));
@@ -432,22 +433,19 @@ impl MetaPromptGenerator {
 
                 content.push_str(&formatted);
                 used_tokens += type_tokens;
-                type_count += 1;
             }
         }
 
         // Schemas (lowest priority)
         if !context.schemas.is_empty() && used_tokens < token_budget {
             let _remaining_budget = token_budget - used_tokens;
-            let mut schema_count = 0;
-
-            for schema in context.schemas.iter() {
+            for (schema_count, schema) in context.schemas.iter().enumerate() {
                 if schema_count >= config.max_examples_per_type {
                     break;
                 }
 
-                let formatted = format!("## File: schemas/validation.ts\n```\n{}\n```\n\n", schema.definition);
-                let schema_tokens = TokenCounter::count(&formatted);
+                let formatted = format_definition(&schema.name, &schema.file_path, &schema.definition, language);
+                let schema_tokens = TokenCounter::default().count(&formatted);
 
                 if used_tokens + schema_tokens > token_budget {
                     content.push_str(&format!("... ({} more schemas omitted due to token limit)\n\n", context.schemas.len() - schema_count));
@@ -456,60 +454,74 @@ impl MetaPromptGenerator {
 
                 content.push_str(&formatted);
                 used_tokens += schema_tokens;
-                schema_count += 1;
             }
         }
 
         content
     }
     
-    pub(crate) fn format_symbol(symbol: &SymbolInfo, index: usize) -> String {
-        let mut info = format!(
-            "#### {}. `{}` ({})\n\
-            **File**: `{}`\n",
-            index, symbol.name, symbol.kind, symbol.file_path
-        );
+    /// Codebase symbols grouped by language; a single group when the context
+    /// is all one language
+    fn codebase_groups(symbols: Vec<&SymbolInfo>) -> Vec<(Option<&str>, Vec<&SymbolInfo>)> {
+        let groups = group_by_language(symbols.iter().copied());
+        if groups.len() > 1 {
+            groups
+        } else {
+            vec![(None, symbols)]
+        }
+    }
+
+    fn language_group_heading(language: Option<&str>) -> String {
+        language.map(|l| format!("## {} code\n\n", language_name(l))).unwrap_or_default()
+    }
 
+    fn format_codebase_symbol(symbol: &SymbolInfo) -> String {
+        let mut metadata = String::new();
         if !symbol.props.is_empty() {
-            info.push_str(&format!("**Props**: {}\n", symbol.props.join(", ")));
+            metadata.push_str(&format!("**Props:** {}\n", symbol.props.join(", ")));
         }
-
         if !symbol.references.is_empty() {
             // Limit references to avoid noise
-            let refs: Vec<_> = symbol.references.iter().take(10).collect();
-            info.push_str(&format!("**References**: {}\n", refs.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")));
-            if symbol.references.len() > 10 {
-                info.push_str("... (more)\n");
-            }
+            let refs: Vec<&str> = symbol.references.iter().take(10).map(String::as_str).collect();
+            let more = if symbol.references.len() > 10 { ", ..." } else { "" };
+            metadata.push_str(&format!("**References:** {}{}\n", refs.join(", "), more));
         }
-
-        info.push_str(&format!("\n```\n{}\n```\n\n", symbol.content));
-        info
-    }
-    
-    fn format_type(type_info: &TypeInfo, index: usize) -> String {
         format!(
-            "#### {}. `{}`\n\n\
-            ```\n{}\n```\n\n",
-            index, type_info.name, type_info.definition
+            "## File: {}\n{}{}{}{}\n",
+            symbol_heading(symbol),
+            language_tag(symbol),
+            metadata,
+            format_documentation(symbol.documentation.as_deref(), &symbol.content),
+            code_block(symbol.language(), &symbol.content)
         )
     }
-    
-    fn format_constant(constant: &ConstantInfo, index: usize) -> String {
-        format!(
-            "{}. `{}` = `{}` (Category: {})\n",
-            index, constant.name, constant.value, constant.category
-        )
+
+    /// A type or schema under its file's heading (its name when the file is unknown),
+    /// fenced in the file's language, else `fallback_language`
+    fn format_definition(name: &str, file_path: &str, definition: &str, fallback_language: Option<&str>) -> String {
+        let heading = if file_path.is_empty() { format!("## {}", name) } else { format!("## File: {}", file_path) };
+        let language = language_for_path(file_path).or(fallback_language);
+        format!("{}\n{}\n", heading, code_block(language, definition))
     }
-    
-    fn format_schema(schema: &SchemaInfo, index: usize) -> String {
-        format!(
-            "#### {}. `{}` ({})\n\n\
-            ```\n{}\n```\n\n",
-            index, schema.name, schema.schema_type, schema.definition
-        )
+
+    /// A snippet's citation anchor, or its file path when it has none
+    fn symbol_heading(symbol: &SymbolInfo) -> String {
+        Citation::for_symbol(symbol).map(|c| c.anchor).unwrap_or_else(|| symbol.file_path.clone())
     }
-    
+
+    fn build_citation_index(citations: &[Citation]) -> String {
+        let mut section = String::from("# Citation Index\n");
+        section.push_str(&format!(
+            "When your answer relies on existing code, cite it by its anchor (e.g. `{}`). Only cite code listed here.\n\n",
+            citations[0].anchor
+        ));
+        for citation in citations {
+            section.push_str(&format!("- `{}` {} ({})\n", citation.anchor, citation.symbol, citation.kind));
+        }
+        section.push('\n');
+        section
+    }
+
     fn build_style_guide(context: &ContextData) -> String {
         let mut guide = String::from("## STYLE GUIDE 📐\n\n");
         guide.push_str("Extracted patterns from the existing codebase. Your code MUST match this style:\n\n");
@@ -616,6 +628,19 @@ impl MetaPromptGenerator {
             step += 1;
         }
         
+        // Step 6: Other teams' code
+        if !context.owned_elsewhere.is_empty() {
+            plan.push_str(&format!(
+                "{}. **Coordinate with owners**\n   - These files belong to other teams; keep changes to them minimal and ask for their review\n",
+                step
+            ));
+            for file in &context.owned_elsewhere {
+                plan.push_str(&format!("   - `{}` ({})\n", file.file_path, file.owners.join(", ")));
+            }
+            plan.push('\n');
+            step += 1;
+        }
+
         plan.push_str(&format!(
             "{}. **Test and verify**\n   - Ensure imports work\n   - Check type safety\n   - Verify styling matches design tokens\n\n",
             step
@@ -654,7 +679,16 @@ mod tests {
             constants: vec![],
             design_tokens: vec![],
             schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
             common_imports: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
         };
         
         let config = MetaPromptConfig::default();
@@ -670,7 +704,171 @@ mod tests {
     }
 
     #[test]
-    fn test_format_symbol_with_metadata() {
+    fn test_external_references_are_labeled() {
+        let mut context = ContextData {
+            relevant_symbols: vec![],
+            similar_symbols: vec![],
+            types: vec![],
+            constants: vec![],
+            design_tokens: vec![],
+            schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
+            common_imports: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
+        };
+        let prompt = MetaPromptGenerator::generate("Add a zod schema", &context, None, MetaPromptConfig::default()).unwrap();
+        assert!(!prompt.contains("External References"));
+
+        context.external_references.push(crate::ExternalReference {
+            source: "Tool: web_search Args: {\"query\":\"zod refine\"}".to_string(),
+            content: "1. zod\n   https://zod.dev".to_string(),
+        });
+        let prompt = MetaPromptGenerator::generate("Add a zod schema", &context, None, MetaPromptConfig::default()).unwrap();
+        assert!(prompt.contains("# External References (not from this codebase)"));
+        assert!(prompt.contains("https://zod.dev"));
+    }
+
+    #[test]
+    fn test_diff_mode_shows_the_target_and_asks_for_a_diff() {
+        let symbol = |name: &str, file_path: &str, content: &str| SymbolInfo {
+            name: name.to_string(),
+            kind: "function".to_string(),
+            content: content.to_string(),
+            file_path: file_path.to_string(),
+            start_line: 10,
+            end_line: 12,
+            props: vec![],
+            references: vec![],
+            is_exported: true,
+            documentation: None,
+            language: None,
+        };
+        let target = symbol("total", "src/cart.rs", "pub fn total(items: &[Item]) -> u32 {\n    0\n}");
+        let checkout = symbol("checkout", "src/checkout.rs", "fn checkout(cart: &Cart) {\n    total(&cart.items);\n}");
+        let context = ContextData {
+            relevant_symbols: vec![target.clone(), checkout.clone()],
+            similar_symbols: vec![],
+            types: vec![],
+            constants: vec![],
+            design_tokens: vec![],
+            schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
+            common_imports: vec![],
+            edit_target: Some(crate::EditTarget::new(target, vec![checkout])),
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
+        };
+
+        let whole_files = MetaPromptGenerator::generate("Fix the total", &context, None, MetaPromptConfig::default()).unwrap();
+        assert!(!whole_files.contains("# Code to Change"));
+        assert!(whole_files.contains("EXECUTION INSTRUCTIONS"));
+
+        let config = MetaPromptConfig { diff: true, ..Default::default() };
+        let prompt = MetaPromptGenerator::generate("Fix the total", &context, None, config).unwrap();
+        assert!(prompt.contains("# Code to Change"));
+        assert!(prompt.contains("# Direct Dependents"));
+        assert!(prompt.contains("UNIFIED DIFF"));
+        assert!(!prompt.contains("EXECUTION INSTRUCTIONS"));
+        // The target and its dependents are not repeated in the codebase section
+        assert_eq!(prompt.matches("pub fn total(items").count(), 1);
+        assert_eq!(prompt.matches("fn checkout(cart").count(), 1);
+    }
+
+    #[test]
+    fn test_prompt_formats() {
+        let context = ContextData {
+            relevant_symbols: vec![],
+            similar_symbols: vec![],
+            types: vec![],
+            constants: vec![],
+            design_tokens: vec![],
+            schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
+            common_imports: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
+        };
+        let generate = |format| {
+            let config = MetaPromptConfig { format, ..Default::default() };
+            MetaPromptGenerator::generate("Create a login page", &context, Some("TypeScript + React"), config).unwrap()
+        };
+
+        let xml = generate(PromptFormat::Xml);
+        assert!(xml.starts_with("<task>\n# TASK: Create a login page"));
+        assert!(xml.contains("</constraints>"));
+
+        let messages: serde_json::Value = serde_json::from_str(&generate(PromptFormat::Chatml)).unwrap();
+        assert_eq!(messages[0]["role"], "system");
+        assert!(messages[0]["content"].as_str().unwrap().contains("CONSTRAINTS"));
+        assert!(messages[1]["content"].as_str().unwrap().starts_with("# TASK"));
+
+        let document: serde_json::Value = serde_json::from_str(&generate(PromptFormat::Json)).unwrap();
+        assert_eq!(document["task"], "Create a login page");
+        assert_eq!(document["sections"][0]["name"], "task");
+        assert!(document["context"]["relevant_symbols"].is_array());
+
+        assert_eq!("XML".parse::<PromptFormat>().unwrap(), PromptFormat::Xml);
+        assert!("yaml".parse::<PromptFormat>().is_err());
+    }
+
+    #[test]
+    fn test_snippets_are_cited() {
+        let context = ContextData {
+            relevant_symbols: vec![SymbolInfo {
+                name: "LoginForm".to_string(),
+                kind: "component".to_string(),
+                content: "export function LoginForm() {}".to_string(),
+                file_path: "src/login.tsx".to_string(),
+                start_line: 12,
+                end_line: 40,
+                props: vec![],
+                references: vec![],
+                is_exported: true,
+                documentation: None,
+                language: None,
+            }],
+            similar_symbols: vec![],
+            types: vec![],
+            constants: vec![],
+            design_tokens: vec![],
+            schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
+            common_imports: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
+        };
+        let prompt = MetaPromptGenerator::generate("Add a signup form", &context, None, MetaPromptConfig::default()).unwrap();
+        assert!(prompt.contains("## File: src/login.tsx:12-40\n"));
+        assert!(prompt.contains("# Citation Index\n"));
+        assert!(prompt.contains("- `src/login.tsx:12-40` LoginForm (component)\n"));
+    }
+
+    #[test]
+    fn test_format_codebase_symbol_with_metadata() {
         let symbol = SymbolInfo {
             name: "TestComponent".to_string(),
             kind: "component".to_string(),
@@ -680,14 +878,75 @@ mod tests {
             end_line: 1,
             props: vec!["title: string".to_string(), "isActive: boolean".to_string()],
             references: vec!["Button".to_string(), "useState".to_string()],
+            is_exported: false,
+            documentation: Some("Summary card for the dashboard.".to_string()),
+            language: None,
         };
 
-        let formatted = format_symbol(&symbol, 1);
-        
-        assert!(formatted.contains("#### 1. `TestComponent` (component)"));
-        assert!(formatted.contains("**File**: `src/components/TestComponent.tsx`"));
-        assert!(formatted.contains("**Props**: title: string, isActive: boolean"));
-        assert!(formatted.contains("**References**: Button, useState"));
-        assert!(formatted.contains("```\nfunction TestComponent() {}\n```"));
+        let formatted = format_codebase_symbol(&symbol);
+        assert!(formatted.contains("> Summary card for the dashboard.\n\n```tsx"));
+
+        assert!(formatted.starts_with("## File: src/components/TestComponent.tsx:1-1\n"));
+        assert!(formatted.contains("**Props:** title: string, isActive: boolean\n"));
+        assert!(formatted.contains("**References:** Button, useState\n"));
+        assert!(formatted.contains("**Language:** TypeScript (TSX)\n"));
+        assert!(formatted.contains("```tsx\nfunction TestComponent() {}\n```"));
+    }
+
+    #[test]
+    fn test_definitions_are_headed_by_their_file() {
+        let formatted = format_definition("UserSchema", "src/schemas/user.py", "class UserSchema: ...", Some("typescript"));
+        assert_eq!(formatted, "## File: src/schemas/user.py\n```python\nclass UserSchema: ...\n```\n\n");
+
+        let formatted = format_definition("Props", "", "type Props = {}", Some("typescript"));
+        assert!(formatted.starts_with("## Props\n```typescript\n"));
+    }
+
+    #[test]
+    fn test_polyglot_codebase_is_grouped_by_language() {
+        let symbol = |name: &str, file_path: &str, content: &str| SymbolInfo {
+            name: name.to_string(),
+            kind: "function".to_string(),
+            content: content.to_string(),
+            file_path: file_path.to_string(),
+            start_line: 1,
+            end_line: 1,
+            props: vec![],
+            references: vec![],
+            is_exported: true,
+            documentation: None,
+            language: None,
+        };
+        let context = ContextData {
+            relevant_symbols: vec![
+                symbol("create_user", "server/src/users.rs", "pub fn create_user() {}"),
+                symbol("useUser", "web/hooks/useUser.ts", "export function useUser() {}"),
+                symbol("delete_user", "server/src/users.rs", "pub fn delete_user() {}"),
+            ],
+            similar_symbols: vec![],
+            types: vec![],
+            constants: vec![],
+            design_tokens: vec![],
+            schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
+            common_imports: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
+        };
+
+        let config = MetaPromptConfig { token_budget: None, ..Default::default() };
+        let codebase = build_relevant_codebase(&context, &config);
+        let rust = codebase.find("## Rust code").unwrap();
+        let typescript = codebase.find("## TypeScript code").unwrap();
+        assert!(rust < codebase.find("delete_user").unwrap());
+        assert!(codebase.find("delete_user").unwrap() < typescript);
+        assert!(codebase.contains("**Language:** Rust\n```rust\npub fn create_user() {}\n```"));
+        assert!(codebase.contains("```typescript\nexport function useUser() {}\n```"));
     }
 }
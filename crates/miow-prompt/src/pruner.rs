@@ -1,27 +1,173 @@
-use crate::ContextData;
+use crate::{ContextData, SymbolInfo, TokenCounter};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use tracing::{info, debug};
 
+/// Part of the prompt that gets its own slice of the token budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptSection {
+    /// Relevant and similar symbols, tests and external references
+    Symbols,
+    /// Type definitions and schemas
+    Types,
+    /// Constants, design tokens, routes and environment variables
+    Constants,
+    /// The implementation plan
+    Plan,
+}
+
+impl PromptSection {
+    const ALL: [PromptSection; 4] = [Self::Symbols, Self::Types, Self::Constants, Self::Plan];
+}
+
+/// Share of the token budget for each section. Shares are relative, and what a
+/// section leaves unused goes to the sections that need more.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SectionBudgets {
+    pub symbols: f32,
+    pub types: f32,
+    pub constants: f32,
+    pub plan: f32,
+}
+
+impl Default for SectionBudgets {
+    fn default() -> Self {
+        Self { symbols: 0.4, types: 0.2, constants: 0.1, plan: 0.3 }
+    }
+}
+
+impl SectionBudgets {
+    fn share(&self, section: PromptSection) -> f32 {
+        match section {
+            PromptSection::Symbols => self.symbols,
+            PromptSection::Types => self.types,
+            PromptSection::Constants => self.constants,
+            PromptSection::Plan => self.plan,
+        }
+        .max(0.0)
+    }
+
+    /// Token limit per section for `budget` tokens, given what each section would use
+    fn allocate(&self, budget: usize, usage: &HashMap<PromptSection, usize>) -> HashMap<PromptSection, usize> {
+        let total: f32 = PromptSection::ALL.iter().map(|s| self.share(*s)).sum();
+        let allotted = |section: PromptSection| {
+            if total <= 0.0 { budget / PromptSection::ALL.len() } else { (budget as f32 * self.share(section) / total) as usize }
+        };
+        let used = |section: PromptSection| usage.get(&section).copied().unwrap_or(0);
+
+        let slack: usize = PromptSection::ALL.iter().map(|s| allotted(*s).saturating_sub(used(*s))).sum();
+        let shortfall: usize = PromptSection::ALL.iter().map(|s| used(*s).saturating_sub(allotted(*s))).sum();
+        PromptSection::ALL
+            .iter()
+            .map(|&section| {
+                let need = used(section).saturating_sub(allotted(section));
+                // Hand out the slack in proportion to how far over each section is
+                let extra = if shortfall == 0 { 0 } else { (slack as u128 * need as u128 / shortfall as u128) as usize };
+                (section, allotted(section) + extra.min(need))
+            })
+            .collect()
+    }
+}
+
+/// Something the pruner removed from the context
+#[derive(Debug, Clone, Serialize)]
+pub struct DroppedItem {
+    pub section: PromptSection,
+    /// Which list it came from, e.g. "similar symbol" or "design token"
+    pub category: &'static str,
+    pub name: String,
+    pub tokens: usize,
+}
+
+/// What pruning did to a context
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PruneReport {
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+    pub dropped: Vec<DroppedItem>,
+    /// Items cut short rather than dropped (only the plan, for now)
+    pub truncated: Vec<String>,
+}
+
+impl PruneReport {
+    /// One line such as "dropped 3 similar symbols, 1 type (1840 tokens)"
+    pub fn summary(&self) -> String {
+        if self.dropped.is_empty() && self.truncated.is_empty() {
+            return "nothing dropped".to_string();
+        }
+        let mut counts: Vec<(&'static str, usize)> = Vec::new();
+        for item in &self.dropped {
+            match counts.iter_mut().find(|(category, _)| *category == item.category) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((item.category, 1)),
+            }
+        }
+        let mut parts: Vec<String> = counts
+            .iter()
+            .map(|(category, n)| format!("{} {}{}", n, category, if *n == 1 { "" } else { "s" }))
+            .collect();
+        parts.extend(self.truncated.iter().map(|name| format!("truncated {}", name)));
+        format!("dropped {} ({} tokens)", parts.join(", "), self.tokens_before.saturating_sub(self.tokens_after))
+    }
+}
+
 /// Smart context pruner to manage token budget and relevance
 pub struct SmartPruner {
     token_budget: usize,
+    counter: TokenCounter,
+    sections: Option<SectionBudgets>,
 }
 
 impl SmartPruner {
     pub fn new(token_budget: usize) -> Self {
-        Self { token_budget }
+        Self {
+            token_budget,
+            counter: TokenCounter::default(),
+            sections: None,
+        }
     }
 
-    /// Prune context to fit within token budget
-    pub fn prune(&self, context: &mut ContextData) {
-        let current_usage = self.calculate_usage(context);
-        
+    /// Use a model-specific token counter for budgeting
+    pub fn with_counter(mut self, counter: TokenCounter) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    /// Give each section its own share of the budget and trim each one to fit,
+    /// least relevant items first
+    pub fn with_sections(mut self, sections: SectionBudgets) -> Self {
+        self.sections = Some(sections);
+        self
+    }
+
+    /// Prune context to fit within token budget, reporting what was removed
+    pub fn prune(&self, context: &mut ContextData) -> PruneReport {
+        let before = self.inventory(context);
+        let current_usage: usize = before.iter().map(|item| item.tokens).sum();
+        let mut report = PruneReport { tokens_before: current_usage, tokens_after: current_usage, ..Default::default() };
+
         if current_usage <= self.token_budget {
             debug!("Context usage {} within budget {}", current_usage, self.token_budget);
-            return;
+            return report;
         }
 
         info!("✂️ Pruning context: usage {} > budget {}", current_usage, self.token_budget);
 
+        match self.sections {
+            Some(sections) => self.prune_sections(context, &sections, &before, &mut report),
+            None => self.prune_graduated(context),
+        }
+
+        let after = self.inventory(context);
+        report.tokens_after = after.iter().map(|item| item.tokens).sum();
+        report.dropped = dropped_items(before, &after);
+        info!("✂️ Pruned context to {} tokens: {}", report.tokens_after, report.summary());
+        report
+    }
+
+    fn prune_graduated(&self, context: &mut ContextData) {
         // Strategy 1: Remove test files and mocks
         self.remove_test_files(context);
         
@@ -40,19 +186,141 @@ impl SmartPruner {
         // For now, just remove lowest priority items
         self.aggressive_prune(context);
     }
+
+    fn prune_sections(&self, context: &mut ContextData, sections: &SectionBudgets, items: &[ContextEntry], report: &mut PruneReport) {
+        let mut usage: HashMap<PromptSection, usize> = HashMap::new();
+        for item in items {
+            *usage.entry(item.section).or_default() += item.tokens;
+        }
+        let limits = sections.allocate(self.token_budget, &usage);
+
+        for section in PromptSection::ALL {
+            let limit = limits[&section];
+            let mut used = usage.get(&section).copied().unwrap_or(0);
+            if used <= limit {
+                continue;
+            }
+            debug!("Section {:?} uses {} tokens, limit {}", section, used, limit);
+            if section == PromptSection::Plan {
+                self.truncate_plan(context, used - limit, report);
+                continue;
+            }
+            while used > limit {
+                match self.drop_least_relevant(context, section) {
+                    Some(tokens) => used = used.saturating_sub(tokens),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// Remove the least relevant item of `section`, returning its token count.
+    /// Lists are emptied least useful first, each from the end (lists are
+    /// ordered most relevant first), internal symbols before exported ones.
+    fn drop_least_relevant(&self, context: &mut ContextData, section: PromptSection) -> Option<usize> {
+        let symbol_tokens = |s: &SymbolInfo| self.symbol_tokens(s);
+        match section {
+            PromptSection::Symbols => {
+                if let Some(symbol) = pop_preferring_internal(&mut context.similar_symbols, |_| true) {
+                    return Some(symbol_tokens(&symbol));
+                }
+                if let Some(r) = context.external_references.pop() {
+                    return Some(self.counter.count(&r.content) + self.counter.count(&r.source));
+                }
+                if let Some(t) = context.tests.pop() {
+                    return Some(self.counter.count(&t.content) + self.counter.count(&t.name) + self.counter.count(&t.file_path));
+                }
+                pop_preferring_internal(&mut context.relevant_symbols, |s| s.kind != "plan").map(|s| symbol_tokens(&s))
+            }
+            PromptSection::Types => {
+                if let Some(s) = context.schemas.pop() {
+                    return Some(self.counter.count(&s.definition) + self.counter.count(&s.name));
+                }
+                context.types.pop().map(|t| self.counter.count(&t.definition) + self.counter.count(&t.name))
+            }
+            PromptSection::Constants => {
+                if let Some(d) = context.design_tokens.pop() {
+                    return Some(self.counter.count(&d.value) + self.counter.count(&d.name));
+                }
+                if let Some(e) = context.env_vars.pop() {
+                    return Some(self.counter.count(&e.name) + e.file_paths.iter().map(|p| self.counter.count(p)).sum::<usize>());
+                }
+                if let Some(r) = context.routes.pop() {
+                    return Some(self.counter.count(&r.path) + self.counter.count(&r.file_path) + 2);
+                }
+                context.constants.pop().map(|c| self.counter.count(&c.value) + self.counter.count(&c.name))
+            }
+            PromptSection::Plan => None,
+        }
+    }
+
+    /// Cut whole lines off the end of the plan until `excess` tokens are gone
+    fn truncate_plan(&self, context: &mut ContextData, excess: usize, report: &mut PruneReport) {
+        if let Some(plan) = context.relevant_symbols.iter_mut().rfind(|s| s.kind == "plan") {
+            let tokens = self.counter.count(&plan.content);
+            let target = tokens.saturating_sub(excess);
+            let mut kept = String::new();
+            for line in plan.content.lines() {
+                if self.counter.count(&kept) + self.counter.count(line) + 1 > target {
+                    break;
+                }
+                kept.push_str(line);
+                kept.push('\n');
+            }
+            kept.push_str("... (rest of the plan cut to fit the token budget)");
+            plan.content = kept;
+            report.truncated.push(format!("plan `{}`", plan.name));
+        }
+    }
+
+    fn symbol_tokens(&self, symbol: &SymbolInfo) -> usize {
+        self.counter.count(&symbol.content)
+            + self.counter.count(&symbol.name)
+            + symbol.documentation.as_deref().map_or(0, |d| self.counter.count(d))
+    }
+
+    /// Every countable item with its section and size, counted like `TokenCounter::count_context`
+    pub fn inventory(&self, context: &ContextData) -> Vec<ContextEntry> {
+        let c = &self.counter;
+        let mut items = Vec::new();
+
+        for s in &context.relevant_symbols {
+            let (section, category) = if s.kind == "plan" { (PromptSection::Plan, "plan") } else { (PromptSection::Symbols, "symbol") };
+            items.push(ContextEntry::new(section, category, symbol_key(s), self.symbol_tokens(s)).located(s));
+        }
+        for s in &context.similar_symbols {
+            items.push(ContextEntry::new(PromptSection::Symbols, "similar symbol", symbol_key(s), c.count(&s.content) + c.count(&s.name)).located(s));
+        }
+        for t in &context.tests {
+            let tokens = c.count(&t.content) + c.count(&t.name) + c.count(&t.file_path);
+            items.push(ContextEntry::new(PromptSection::Symbols, "test", format!("{} ({})", t.name, t.file_path), tokens).in_file(&t.file_path));
+        }
+        for r in &context.external_references {
+            items.push(ContextEntry::new(PromptSection::Symbols, "external reference", r.source.clone(), c.count(&r.content) + c.count(&r.source)));
+        }
+        for t in &context.types {
+            items.push(ContextEntry::new(PromptSection::Types, "type", t.name.clone(), c.count(&t.definition) + c.count(&t.name)));
+        }
+        for s in &context.schemas {
+            items.push(ContextEntry::new(PromptSection::Types, "schema", s.name.clone(), c.count(&s.definition) + c.count(&s.name)));
+        }
+        for k in &context.constants {
+            items.push(ContextEntry::new(PromptSection::Constants, "constant", k.name.clone(), c.count(&k.value) + c.count(&k.name)));
+        }
+        for d in &context.design_tokens {
+            items.push(ContextEntry::new(PromptSection::Constants, "design token", d.name.clone(), c.count(&d.value) + c.count(&d.name)));
+        }
+        for r in &context.routes {
+            items.push(ContextEntry::new(PromptSection::Constants, "route", format!("{} {}", r.method, r.path), c.count(&r.path) + c.count(&r.file_path) + 2).in_file(&r.file_path));
+        }
+        for e in &context.env_vars {
+            items.push(ContextEntry::new(PromptSection::Constants, "env var", e.name.clone(), c.count(&e.name) + e.file_paths.iter().map(|p| c.count(p)).sum::<usize>()));
+        }
+        items
+    }
     
     fn calculate_usage(&self, context: &ContextData) -> usize {
-        let mut chars = 0;
-        
-        for s in &context.relevant_symbols { chars += s.content.len() + s.name.len(); }
-        for s in &context.similar_symbols { chars += s.content.len() + s.name.len(); }
-        for t in &context.types { chars += t.definition.len() + t.name.len(); }
-        for c in &context.constants { chars += c.value.len() + c.name.len(); }
-        for d in &context.design_tokens { chars += d.value.len() + d.name.len(); }
-        for s in &context.schemas { chars += s.definition.len() + s.name.len(); }
-        
-        // Approx 4 chars per token
-        chars / 4
+        self.counter.count_context(context)
     }
     
     fn remove_test_files(&self, context: &mut ContextData) {
@@ -74,12 +342,8 @@ impl SmartPruner {
         // Keep top N items
         const MAX_ITEMS: usize = 10;
         
-        if context.relevant_symbols.len() > MAX_ITEMS {
-            context.relevant_symbols.truncate(MAX_ITEMS);
-        }
-        if context.similar_symbols.len() > MAX_ITEMS {
-            context.similar_symbols.truncate(MAX_ITEMS);
-        }
+        truncate_preferring_exported(&mut context.relevant_symbols, MAX_ITEMS);
+        truncate_preferring_exported(&mut context.similar_symbols, MAX_ITEMS);
         if context.types.len() > MAX_ITEMS {
             context.types.truncate(MAX_ITEMS);
         }
@@ -91,7 +355,7 @@ impl SmartPruner {
         
         // 1. Reduce similar symbols (keep top 5)
         if context.similar_symbols.len() > 5 {
-            context.similar_symbols.truncate(5);
+            truncate_preferring_exported(&mut context.similar_symbols, 5);
             if self.calculate_usage(context) <= self.token_budget { return; }
         }
 
@@ -117,10 +381,98 @@ impl SmartPruner {
         context.design_tokens.clear();
         if self.calculate_usage(context) <= self.token_budget { return; }
         
-        // 5. Finally, prune relevant symbols from the end (assuming least relevant are at the end)
-        // Note: In a real scenario, we should sort by relevance score first if not already sorted.
+        // 5. Finally, prune relevant symbols from the end (assuming least relevant are at the end),
+        // dropping internal helpers before exported APIs
         while self.calculate_usage(context) > self.token_budget && !context.relevant_symbols.is_empty() {
-            context.relevant_symbols.pop();
+            let len = context.relevant_symbols.len();
+            truncate_preferring_exported(&mut context.relevant_symbols, len - 1);
+        }
+    }
+}
+
+/// One entry of `SmartPruner::inventory`
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextEntry {
+    pub section: PromptSection,
+    /// Which list it came from, e.g. "similar symbol" or "design token"
+    pub category: &'static str,
+    /// Name, with the file for symbols and tests
+    pub key: String,
+    /// File the item was taken from, when there is exactly one
+    pub file_path: Option<String>,
+    /// First and last line in `file_path`, for symbols
+    pub lines: Option<(i64, i64)>,
+    pub tokens: usize,
+}
+
+impl ContextEntry {
+    fn new(section: PromptSection, category: &'static str, key: String, tokens: usize) -> Self {
+        Self { section, category, key, file_path: None, lines: None, tokens }
+    }
+
+    fn in_file(mut self, path: &str) -> Self {
+        self.file_path = (!path.is_empty()).then(|| path.to_string());
+        self
+    }
+
+    fn located(self, symbol: &SymbolInfo) -> Self {
+        let mut entry = self.in_file(&symbol.file_path);
+        if entry.file_path.is_some() && symbol.end_line > 0 {
+            entry.lines = Some((symbol.start_line, symbol.end_line));
+        }
+        entry
+    }
+}
+
+fn symbol_key(symbol: &SymbolInfo) -> String {
+    if symbol.file_path.is_empty() {
+        symbol.name.clone()
+    } else {
+        format!("{} ({})", symbol.name, symbol.file_path)
+    }
+}
+
+/// Items of `before` with no counterpart in `after`
+fn dropped_items(before: Vec<ContextEntry>, after: &[ContextEntry]) -> Vec<DroppedItem> {
+    let mut remaining: HashMap<(&'static str, String), usize> = HashMap::new();
+    for item in after {
+        *remaining.entry((item.category, item.key.clone())).or_default() += 1;
+    }
+    before
+        .into_iter()
+        .filter(|item| {
+            // Kept items are matched off one by one, so duplicates are counted right
+            match remaining.get_mut(&(item.category, item.key.clone())) {
+                Some(n) if *n > 0 => {
+                    *n -= 1;
+                    false
+                }
+                _ => true,
+            }
+        })
+        .map(|item| DroppedItem { section: item.section, category: item.category, name: item.key, tokens: item.tokens })
+        .collect()
+}
+
+/// Remove the last symbol matching `eligible`, preferring one that isn't exported
+fn pop_preferring_internal(symbols: &mut Vec<SymbolInfo>, eligible: impl Fn(&SymbolInfo) -> bool) -> Option<SymbolInfo> {
+    let index = symbols
+        .iter()
+        .rposition(|s| eligible(s) && !s.is_exported)
+        .or_else(|| symbols.iter().rposition(&eligible))?;
+    Some(symbols.remove(index))
+}
+
+/// Shrink to `max` items, removing the lowest-ranked non-exported symbols first
+fn truncate_preferring_exported(symbols: &mut Vec<SymbolInfo>, max: usize) {
+    while symbols.len() > max {
+        match symbols.iter().rposition(|s| !s.is_exported) {
+            Some(index) => {
+                symbols.remove(index);
+            }
+            None => {
+                symbols.pop();
+            }
         }
     }
 }
@@ -139,7 +491,16 @@ mod tests {
             constants: vec![],
             design_tokens: vec![],
             schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
             common_imports: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
         };
 
         // Add 10 constants
@@ -161,4 +522,80 @@ mod tests {
         // Should be reduced to 5, not 0
         assert_eq!(context.constants.len(), 5);
     }
+
+    #[test]
+    fn test_section_budgets_trim_over_budget_sections() {
+        let symbol = |name: String, kind: &str, content: String| SymbolInfo {
+            name,
+            kind: kind.to_string(),
+            content,
+            file_path: "src/lib.ts".to_string(),
+            start_line: 0,
+            end_line: 0,
+            props: vec![],
+            references: vec![],
+            is_exported: false,
+            documentation: None,
+            language: None,
+        };
+        let mut context = ContextData {
+            relevant_symbols: vec![symbol("plan".to_string(), "plan", "1. Add the route\n".repeat(20))],
+            similar_symbols: (0..20).map(|i| symbol(format!("similar_{}", i), "function", "return value + 1;".repeat(10))).collect(),
+            types: vec![],
+            constants: vec![ConstantInfo { name: "MAX".to_string(), value: "10".to_string(), category: "limits".to_string() }],
+            design_tokens: vec![],
+            schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
+            common_imports: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
+        };
+
+        let report = SmartPruner::new(300).with_sections(SectionBudgets::default()).prune(&mut context);
+
+        // The symbols get the slack other sections leave, and lose their tail first
+        assert!(report.tokens_after <= 300, "{} tokens left", report.tokens_after);
+        assert!(!context.similar_symbols.is_empty());
+        assert_eq!(context.similar_symbols[0].name, "similar_0");
+        assert!(report.dropped.iter().all(|item| item.category == "similar symbol"));
+        assert!(report.dropped.iter().any(|item| item.name.starts_with("similar_19")));
+        // Sections within their share are left alone
+        assert_eq!(context.constants.len(), 1);
+        assert!(report.summary().starts_with(&format!("dropped {} similar symbols", report.dropped.len())));
+
+        let pruner = SmartPruner::new(300);
+        let inventory = pruner.inventory(&context);
+        assert_eq!(inventory.iter().map(|item| item.tokens).sum::<usize>(), report.tokens_after);
+        assert_eq!(inventory[1].file_path.as_deref(), Some("src/lib.ts"));
+        assert_eq!(inventory.last().map(|item| item.category), Some("constant"));
+    }
+
+    #[test]
+    fn test_exported_symbols_survive_pruning() {
+        let mut symbols: Vec<SymbolInfo> = (0..4)
+            .map(|i| SymbolInfo {
+                name: format!("symbol_{}", i),
+                kind: "function".to_string(),
+                content: String::new(),
+                file_path: "src/lib.ts".to_string(),
+                start_line: 0,
+                end_line: 0,
+                props: vec![],
+                references: vec![],
+                is_exported: i % 2 == 0,
+                documentation: None,
+                language: None,
+            })
+            .collect();
+
+        truncate_preferring_exported(&mut symbols, 2);
+        let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["symbol_0", "symbol_2"]);
+    }
 }
@@ -0,0 +1,112 @@
+use miow_common::redaction::{is_secret_name, looks_like_secret_value, placeholder};
+use miow_common::SecretRedactor;
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+use crate::ContextData;
+
+/// One secret replaced by a placeholder; the secret itself is never kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redaction {
+    pub kind: &'static str,
+    /// Where it was found, e.g. a file path or constant name
+    pub location: String,
+}
+
+/// Redact every snippet in `context` in place and log what was removed
+pub fn redact_context(context: &mut ContextData) -> Vec<Redaction> {
+    let redactor = SecretRedactor::global();
+    let mut redactions = Vec::new();
+    let clean = |text: &mut String, location: &str, redactions: &mut Vec<Redaction>| {
+        let (cleaned, kinds) = redactor.redact(text);
+        if !kinds.is_empty() {
+            *text = cleaned;
+            redactions.extend(kinds.into_iter().map(|kind| Redaction { kind, location: location.to_string() }));
+        }
+    };
+
+    let edit_target = context.edit_target.iter_mut().flat_map(|t| std::iter::once(&mut t.symbol).chain(t.dependents.iter_mut()));
+    for symbol in context.relevant_symbols.iter_mut().chain(context.similar_symbols.iter_mut()).chain(edit_target) {
+        let location = symbol.file_path.clone();
+        clean(&mut symbol.content, &location, &mut redactions);
+        if let Some(doc) = symbol.documentation.as_mut() {
+            clean(doc, &location, &mut redactions);
+        }
+    }
+    for constant in &mut context.constants {
+        let location = format!("constant {}", constant.name);
+        clean(&mut constant.value, &location, &mut redactions);
+        // A bare value has no `KEY =` in front of it to go by
+        if is_secret_name(&constant.name) && !constant.value.contains("REDACTED") && looks_like_secret_value(&constant.value) {
+            constant.value = placeholder("secret_value");
+            redactions.push(Redaction { kind: "secret_value", location });
+        }
+    }
+    for token in &mut context.design_tokens {
+        let location = format!("design token {}", token.name);
+        clean(&mut token.value, &location, &mut redactions);
+    }
+    for type_info in &mut context.types {
+        let location = format!("type {}", type_info.name);
+        clean(&mut type_info.definition, &location, &mut redactions);
+    }
+    for schema in &mut context.schemas {
+        let location = format!("schema {}", schema.name);
+        clean(&mut schema.definition, &location, &mut redactions);
+    }
+    for test in &mut context.tests {
+        let location = test.file_path.clone();
+        clean(&mut test.content, &location, &mut redactions);
+    }
+    for reference in &mut context.external_references {
+        let location = reference.source.clone();
+        clean(&mut reference.content, &location, &mut redactions);
+    }
+
+    if !redactions.is_empty() {
+        let mut by_kind: BTreeMap<&str, usize> = BTreeMap::new();
+        for r in &redactions {
+            *by_kind.entry(r.kind).or_default() += 1;
+        }
+        let summary: Vec<String> = by_kind.iter().map(|(kind, n)| format!("{} {}", n, kind)).collect();
+        warn!("🔒 Redacted {} secrets from context: {}", redactions.len(), summary.join(", "));
+        for r in &redactions {
+            info!("🔒 Redacted {} in {}", r.kind, r.location);
+        }
+    }
+    redactions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_constants_are_redacted() {
+        let mut context = ContextData {
+            relevant_symbols: vec![],
+            similar_symbols: vec![],
+            types: vec![],
+            constants: vec![
+                crate::ConstantInfo { name: "JWT_SECRET".to_string(), value: "9fK2mQ7xR4vT8wZ1".to_string(), category: "config".to_string() },
+                crate::ConstantInfo { name: "MAX_RETRIES".to_string(), value: "3".to_string(), category: "config".to_string() },
+            ],
+            design_tokens: vec![],
+            schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
+            common_imports: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
+        };
+        let redactions = redact_context(&mut context);
+        assert_eq!(context.constants[0].value, "[REDACTED:secret_value]");
+        assert_eq!(context.constants[1].value, "3");
+        assert_eq!(redactions, vec![Redaction { kind: "secret_value", location: "constant JWT_SECRET".to_string() }]);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{language_for_path, TokenCounter};
+
+/// Tokens a slice aims for; the signature and declarations are kept even past it
+const DEFAULT_SLICE_TOKENS: usize = 300;
+
+/// Cuts a large symbol down to what a task needs from it: the signature, the
+/// lines declaring its props and types, and the inner lines densest in the
+/// task's terms, each with a line of context. Cut lines are replaced by a
+/// comment saying how many were left out.
+#[derive(Debug, Clone)]
+pub struct SnippetSlicer {
+    terms: Vec<String>,
+    max_tokens: usize,
+    counter: TokenCounter,
+}
+
+impl SnippetSlicer {
+    /// A slicer ranking lines by `queries`; their words of three letters or more count
+    pub fn new(queries: &[String]) -> Self {
+        let mut terms: Vec<String> = queries
+            .iter()
+            .flat_map(|q| q.split(|c: char| !c.is_alphanumeric() && c != '_'))
+            .filter(|term| term.len() >= 3)
+            .map(str::to_lowercase)
+            .collect();
+        terms.sort();
+        terms.dedup();
+        Self { terms, max_tokens: DEFAULT_SLICE_TOKENS, counter: TokenCounter::default() }
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn with_counter(mut self, counter: TokenCounter) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    /// `content` sliced down to about the slicer's token limit, or `None` when
+    /// it already fits or slicing wouldn't save anything
+    pub fn slice(&self, content: &str, file_path: &str) -> Option<String> {
+        if self.counter.count(content) <= self.max_tokens {
+            return None;
+        }
+        let lines: Vec<&str> = content.lines().collect();
+        let mut keep = vec![false; lines.len()];
+
+        // The signature runs to the line that opens the body
+        let opens_body = |line: &str| {
+            let line = line.trim_end();
+            line.ends_with('{') || line.ends_with(':') || line.ends_with("=>") || line.ends_with(" do")
+        };
+        let signature_end = lines.iter().take(8).position(|line| opens_body(line)).unwrap_or(0);
+        keep[..=signature_end].iter_mut().for_each(|k| *k = true);
+        if let Some(last) = keep.last_mut() {
+            *last = true;
+        }
+        for (i, line) in lines.iter().enumerate() {
+            if is_declaration(line) {
+                keep[i] = true;
+            }
+        }
+
+        // Then the lines with the most task terms per token, while the budget lasts
+        let mut used: usize = lines.iter().zip(&keep).filter(|(_, k)| **k).map(|(line, _)| self.counter.count(line)).sum();
+        let mut ranked: Vec<(usize, f32)> = lines
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !keep[*i])
+            .filter_map(|(i, line)| {
+                let lower = line.to_lowercase();
+                let hits = self.terms.iter().filter(|term| lower.contains(term.as_str())).count();
+                (hits > 0).then(|| (i, hits as f32 / self.counter.count(line).max(1) as f32))
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0)));
+        for (i, _) in ranked {
+            let window = i.saturating_sub(1)..=(i + 1).min(lines.len() - 1);
+            let cost: usize = window.clone().filter(|j| !keep[*j]).map(|j| self.counter.count(lines[j])).sum();
+            if used + cost > self.max_tokens {
+                continue;
+            }
+            used += cost;
+            window.for_each(|j| keep[j] = true);
+        }
+
+        let comment = comment_prefix(file_path);
+        let mut sliced = Vec::new();
+        let mut omitted = 0;
+        let mut i = 0;
+        while i < lines.len() {
+            if keep[i] {
+                sliced.push(lines[i].to_string());
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < lines.len() && !keep[i] {
+                i += 1;
+            }
+            // A marker in place of a single line would save nothing
+            if i - start == 1 {
+                sliced.push(lines[start].to_string());
+                continue;
+            }
+            let indent: String = lines[start].chars().take_while(|c| c.is_whitespace()).collect();
+            sliced.push(format!("{}{} ... {} lines omitted", indent, comment, i - start));
+            omitted += i - start;
+        }
+        (omitted > 0).then(|| sliced.join("\n"))
+    }
+}
+
+/// Lines declaring props, types or fields, which say how a symbol is used
+fn is_declaration(line: &str) -> bool {
+    let line = line.trim_start();
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let line = line.strip_prefix("pub ").unwrap_or(line);
+    ["interface ", "type ", "struct ", "enum ", "props", "@Input", "@Prop", "defineProps", "propTypes"]
+        .iter()
+        .any(|start| line.starts_with(start))
+        || line.contains("Props")
+}
+
+fn comment_prefix(file_path: &str) -> &'static str {
+    match language_for_path(file_path) {
+        Some("python" | "ruby" | "bash" | "yaml" | "toml" | "dockerfile") => "#",
+        Some("sql") => "--",
+        _ => "//",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice_keeps_signature_props_and_matching_lines() {
+        let mut body = vec![
+            "export function CheckoutForm({ cart, onSubmit }: CheckoutFormProps) {".to_string(),
+            "  const [coupon, setCoupon] = useState('')".to_string(),
+        ];
+        body.extend((0..40).map(|i| format!("  const row{} = renderRow(cart.items[{}])", i, i)));
+        body.push("  const discount = applyCoupon(cart.total, coupon)".to_string());
+        body.extend((40..80).map(|i| format!("  const row{} = renderRow(cart.items[{}])", i, i)));
+        body.push("  return <form onSubmit={onSubmit}>{discount}</form>".to_string());
+        body.push("}".to_string());
+        let content = body.join("\n");
+
+        let slicer = SnippetSlicer::new(&["coupon discount".to_string()]).with_max_tokens(120);
+        let sliced = slicer.slice(&content, "src/CheckoutForm.tsx").unwrap();
+        let lines: Vec<&str> = sliced.lines().collect();
+        assert_eq!(lines[0], body[0]);
+        assert_eq!(*lines.last().unwrap(), "}");
+        assert!(sliced.contains("applyCoupon(cart.total, coupon)"));
+        assert!(sliced.contains("setCoupon"));
+        assert!(sliced.contains("  // ... "));
+        assert!(sliced.lines().count() < 15);
+
+        // Small bodies are left alone
+        assert!(slicer.slice("fn tiny() {}", "src/lib.rs").is_none());
+    }
+
+    #[test]
+    fn test_elision_marker_follows_the_language() {
+        let content = format!("def handler(event):\n{}    return total\n", "    total += step(event)\n".repeat(200));
+        let sliced = SnippetSlicer::new(&[]).with_max_tokens(50).slice(&content, "app/handler.py").unwrap();
+        assert!(sliced.contains("    # ... 200 lines omitted"));
+    }
+}
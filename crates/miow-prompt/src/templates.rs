@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use serde_json::json;
+use std::path::Path;
+use tracing::{debug, warn};
+
+use crate::ContextData;
+
+/// Directory under the project root that holds template overrides
+pub const TEMPLATES_DIR: &str = ".miow/templates";
+
+/// Handlebars overrides for prompt sections, loaded from `<name>.hbs` files.
+///
+/// A template replaces the built-in text of the section with the same name:
+/// a meta-prompt part (`task`, `codebase`, `constraints`, `style_guide`,
+/// `implementation_plan`, `execution_instructions`, `diff_instructions`, ...) or a `PromptGenerator`
+/// block (`system_prompt`, `context_block`, `user_prompt`, `implementation_plan`).
+/// Templates see `default` (the built-in text), `task`, `project_info`,
+/// `intent` and `context` (the gathered `ContextData`).
+#[derive(Debug, Clone)]
+pub struct PromptTemplates {
+    registry: Handlebars<'static>,
+}
+
+impl Default for PromptTemplates {
+    fn default() -> Self {
+        let mut registry = Handlebars::new();
+        // Prompts are plain text; HTML escaping would mangle code
+        registry.register_escape_fn(handlebars::no_escape);
+        Self { registry }
+    }
+}
+
+/// Values a template can refer to
+pub struct TemplateData<'a> {
+    pub task: &'a str,
+    pub project_info: Option<&'a str>,
+    pub intent: Option<&'a str>,
+    pub context: &'a ContextData,
+}
+
+impl PromptTemplates {
+    /// Load every `*.hbs` file in `dir`; a missing directory means no overrides
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut templates = Self::default();
+        if !dir.is_dir() {
+            return Ok(templates);
+        }
+        for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("hbs") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            let source = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            templates.add(name, &source).with_context(|| format!("Invalid template {}", path.display()))?;
+            debug!("Loaded prompt template override {}", path.display());
+        }
+        Ok(templates)
+    }
+
+    /// Overrides in the project's `.miow/templates`. A broken template is
+    /// logged and the built-in prompt is used instead.
+    pub fn for_project(project_root: &Path) -> Self {
+        Self::load(&project_root.join(TEMPLATES_DIR)).unwrap_or_else(|e| {
+            warn!("Ignoring prompt templates: {:#}", e);
+            Self::default()
+        })
+    }
+
+    /// Override section `name` with a Handlebars template
+    pub fn add(&mut self, name: &str, source: &str) -> Result<()> {
+        self.registry.register_template_string(name, source)?;
+        Ok(())
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.registry.has_template(name)
+    }
+
+    /// Section `name` from its override, or `default` when there is none
+    pub fn render_or(&self, name: &str, default: String, data: &TemplateData) -> Result<String> {
+        if !self.has(name) {
+            return Ok(default);
+        }
+        let values = json!({
+            "default": default,
+            "task": data.task,
+            "project_info": data.project_info,
+            "intent": data.intent,
+            "context": data.context,
+        });
+        self.registry
+            .render(name, &values)
+            .with_context(|| format!("Failed to render prompt template '{}'", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_override_sees_default_and_context() {
+        let context = ContextData {
+            relevant_symbols: vec![],
+            similar_symbols: vec![],
+            types: vec![],
+            constants: vec![],
+            design_tokens: vec![],
+            schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
+            common_imports: vec!["import React from 'react'".to_string()],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
+        };
+        let data = TemplateData { task: "Add a <Login> page", project_info: None, intent: None, context: &context };
+
+        let mut templates = PromptTemplates::default();
+        assert_eq!(templates.render_or("constraints", "built-in".to_string(), &data).unwrap(), "built-in");
+
+        templates
+            .add("constraints", "{{default}}\nTask: {{task}}\n{{#each context.common_imports}}- {{this}}\n{{/each}}")
+            .unwrap();
+        let rendered = templates.render_or("constraints", "built-in".to_string(), &data).unwrap();
+        assert_eq!(rendered, "built-in\nTask: Add a <Login> page\n- import React from 'react'\n");
+        assert!(templates.add("broken", "{{#each}}").is_err());
+    }
+}
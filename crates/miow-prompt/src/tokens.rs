@@ -0,0 +1,135 @@
+use std::sync::OnceLock;
+use tiktoken_rs::CoreBPE;
+
+use crate::ContextData;
+
+/// BPE vocabulary used to count tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenEncoding {
+    /// GPT-4 / GPT-3.5; also the estimate for models without a public tokenizer
+    Cl100k,
+    /// GPT-4o, GPT-4.1, GPT-5 and the o-series
+    O200k,
+}
+
+impl TokenEncoding {
+    /// Loaded once per process; `None` if the vocabulary failed to load
+    fn bpe(self) -> Option<&'static CoreBPE> {
+        static CL100K: OnceLock<Option<CoreBPE>> = OnceLock::new();
+        static O200K: OnceLock<Option<CoreBPE>> = OnceLock::new();
+        match self {
+            TokenEncoding::Cl100k => CL100K.get_or_init(|| tiktoken_rs::cl100k_base().ok()),
+            TokenEncoding::O200k => O200K.get_or_init(|| tiktoken_rs::o200k_base().ok()),
+        }
+        .as_ref()
+    }
+}
+
+/// Token counter using the model's BPE tokenizer.
+///
+/// OpenAI models are counted exactly. Gemini and Claude tokenizers aren't
+/// public; cl100k tracks them far more closely on source code than a
+/// characters-per-token ratio, which remains only as a fallback.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenCounter {
+    encoding: TokenEncoding,
+    chars_per_token: f32,
+}
+
+impl TokenCounter {
+    /// Counter for the given model name (e.g. "gemini-2.5-flash", "gpt-4o")
+    pub fn for_model(model: &str) -> Self {
+        let model = model.to_lowercase();
+        let o200k = ["gpt-4o", "gpt-4.1", "gpt-5", "o1", "o3", "o4"];
+        let encoding = if o200k.iter().any(|prefix| model.starts_with(prefix)) {
+            TokenEncoding::O200k
+        } else {
+            TokenEncoding::Cl100k
+        };
+        let chars_per_token = if model.starts_with("claude") { 3.5 } else { 4.0 };
+
+        Self { encoding, chars_per_token }
+    }
+
+    pub fn encoding(&self) -> TokenEncoding {
+        self.encoding
+    }
+
+    /// Count tokens in a piece of text
+    pub fn count(&self, text: &str) -> usize {
+        if text.is_empty() {
+            return 0;
+        }
+        match self.encoding.bpe() {
+            Some(bpe) => bpe.encode_ordinary(text).len(),
+            None => (text.chars().count() as f32 / self.chars_per_token).ceil() as usize,
+        }
+    }
+
+    /// Count tokens for a chat conversation (adds per-message framing overhead)
+    pub fn count_messages<'a, I>(&self, messages: I) -> usize
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        messages.into_iter().map(|m| self.count(m) + 4).sum::<usize>() + 2
+    }
+
+    /// Count tokens used by all content in a context
+    pub fn count_context(&self, context: &ContextData) -> usize {
+        let mut total = 0;
+
+        for s in &context.relevant_symbols {
+            total += self.count(&s.content) + self.count(&s.name);
+            total += s.documentation.as_deref().map_or(0, |d| self.count(d));
+        }
+        for s in &context.similar_symbols {
+            total += self.count(&s.content) + self.count(&s.name);
+        }
+        for t in &context.types {
+            total += self.count(&t.definition) + self.count(&t.name);
+        }
+        for c in &context.constants {
+            total += self.count(&c.value) + self.count(&c.name);
+        }
+        for d in &context.design_tokens {
+            total += self.count(&d.value) + self.count(&d.name);
+        }
+        for s in &context.schemas {
+            total += self.count(&s.definition) + self.count(&s.name);
+        }
+        for r in &context.routes {
+            total += self.count(&r.path) + self.count(&r.file_path) + 2;
+        }
+        for e in &context.env_vars {
+            total += self.count(&e.name) + e.file_paths.iter().map(|p| self.count(p)).sum::<usize>();
+        }
+        for t in &context.tests {
+            total += self.count(&t.content) + self.count(&t.name) + self.count(&t.file_path);
+        }
+        if let Some(target) = &context.edit_target {
+            for s in std::iter::once(&target.symbol).chain(&target.dependents) {
+                total += self.count(&s.content) + self.count(&s.name);
+            }
+        }
+        for c in &context.components {
+            total += self.count(&c.name) + self.count(&c.file_path);
+            for p in &c.props {
+                total += self.count(&p.name) + p.type_annotation.as_deref().map_or(0, |t| self.count(t));
+            }
+        }
+        if let Some(impact) = &context.refactor_impact {
+            total += impact.sites.iter().map(|s| self.count(&s.file_path) + s.lines.len()).sum::<usize>();
+        }
+        for r in &context.external_references {
+            total += self.count(&r.content) + self.count(&r.source);
+        }
+
+        total
+    }
+}
+
+impl Default for TokenCounter {
+    fn default() -> Self {
+        Self { encoding: TokenEncoding::Cl100k, chars_per_token: 4.0 }
+    }
+}
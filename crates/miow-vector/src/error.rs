@@ -0,0 +1,24 @@
+/// Qdrant failures callers may want to tell apart: the server being down
+/// versus a request it refused. They travel inside `anyhow::Error`.
+#[derive(Debug, thiserror::Error)]
+pub enum VectorError {
+    /// No response from Qdrant at all
+    #[error("Qdrant at {url} is unreachable")]
+    Unavailable {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    /// Qdrant answered with an error
+    #[error("Failed to {action}: {message}")]
+    Request { action: String, status: reqwest::StatusCode, message: String },
+}
+
+impl VectorError {
+    /// The error for a failed `action` from its response
+    pub(crate) async fn from_response(action: impl Into<String>, response: reqwest::Response) -> Self {
+        let status = response.status();
+        let message = response.text().await.unwrap_or_default();
+        Self::Request { action: action.into(), status, message }
+    }
+}
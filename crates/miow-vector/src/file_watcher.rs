@@ -2,25 +2,19 @@ use anyhow::Result;
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver};
-use std::sync::Arc;
-use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
-use crate::VectorStore;
-
 /// File watcher for auto-indexing
 pub struct FileWatcher {
     watcher: Option<RecommendedWatcher>,
-    vector_store: Arc<RwLock<VectorStore>>,
     watched_paths: Vec<PathBuf>,
 }
 
 impl FileWatcher {
     /// Create a new file watcher
-    pub fn new(vector_store: Arc<RwLock<VectorStore>>) -> Self {
+    pub fn new() -> Self {
         Self {
             watcher: None,
-            vector_store,
             watched_paths: Vec::new(),
         }
     }
@@ -100,7 +94,9 @@ impl FileWatcher {
         if let Some(ext) = path.extension() {
             matches!(
                 ext.to_str(),
-                Some("rs") | Some("ts") | Some("tsx") | Some("js") | Some("jsx") | Some("py")
+                Some("rs") | Some("ts") | Some("tsx") | Some("js") | Some("jsx") | Some("py") | Some("java")
+                    | Some("c") | Some("h") | Some("cpp") | Some("cc") | Some("hpp")
+                    | Some("sql") | Some("prisma")
             )
         } else {
             false
@@ -126,19 +122,19 @@ impl FileWatcher {
     }
 }
 
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
     #[test]
     fn test_should_index() {
-        let store = Arc::new(RwLock::new(
-            tokio::runtime::Runtime::new()
-                .unwrap()
-                .block_on(VectorStore::new("http://localhost:6333", "test"))
-                .unwrap(),
-        ));
-        let watcher = FileWatcher::new(store);
+        let watcher = FileWatcher::new();
         
         assert!(watcher.should_index(Path::new("test.rs")));
         assert!(watcher.should_index(Path::new("test.ts")));
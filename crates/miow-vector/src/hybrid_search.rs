@@ -21,9 +21,6 @@ struct KeywordIndex {
 struct SymbolEntry {
     id: String,
     name: String,
-    kind: String,
-    file_path: String,
-    content: String,
 }
 
 /// Tracks recently accessed symbols
@@ -141,14 +138,8 @@ impl HybridSearch {
     }
     
     /// Index a symbol for keyword search
-    pub fn index_symbol(&mut self, id: String, name: String, kind: String, file_path: String, content: String) {
-        self.keyword_index.add(SymbolEntry {
-            id,
-            name,
-            kind,
-            file_path,
-            content,
-        });
+    pub fn index_symbol(&mut self, id: String, name: String) {
+        self.keyword_index.add(SymbolEntry { id, name });
     }
 }
 
@@ -163,7 +154,7 @@ impl KeywordIndex {
         // Index by name
         self.symbols
             .entry(entry.name.to_lowercase())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(entry.clone());
         
         // Index by words in name (for partial matching)
@@ -171,7 +162,7 @@ impl KeywordIndex {
             if !word.is_empty() {
                 self.symbols
                     .entry(word.to_lowercase())
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(entry.clone());
             }
         }
@@ -287,9 +278,6 @@ mod tests {
         index.add(SymbolEntry {
             id: "1".to_string(),
             name: "test_function".to_string(),
-            kind: "function".to_string(),
-            file_path: "test.rs".to_string(),
-            content: "fn test_function() {}".to_string(),
         });
         
         let results = index.search("test", 10);
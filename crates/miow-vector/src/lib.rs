@@ -1,16 +1,42 @@
 use anyhow::{bail, Result};
+use miow_common::SecretRedactor;
 use reqwest::{Client, StatusCode};
 use serde::Serialize;
 use serde_json::Value;
-use tracing::{debug, info, warn};
+use std::path::Path;
+use tracing::{debug, info, info_span, warn, Instrument};
 
+pub mod error;
 pub mod file_watcher;
 pub mod hybrid_search;
 pub mod smart_chunking;
+pub mod sqlite_index;
 
+pub use error::VectorError;
 pub use file_watcher::FileWatcher;
 pub use hybrid_search::{HybridSearch, HybridSearchConfig};
 pub use smart_chunking::{SmartChunker, ChunkingStrategy, CodeChunk};
+pub use sqlite_index::SqliteIndex;
+
+/// Send a Qdrant request, recording its latency and failures for `/metrics`
+/// and a `qdrant` span for tracing
+async fn timed(operation: &'static str, request: reqwest::RequestBuilder) -> Result<reqwest::Response, VectorError> {
+    let started = std::time::Instant::now();
+    let span = info_span!("qdrant", otel.name = format!("qdrant.{}", operation), operation, http.status_code = tracing::field::Empty);
+    let result = request.send().instrument(span.clone()).await.map_err(|source| VectorError::Unavailable {
+        url: source.url().map(|url| url.origin().ascii_serialization()).unwrap_or_default(),
+        source,
+    });
+    if let Ok(resp) = &result {
+        span.record("http.status_code", resp.status().as_u16());
+    }
+    metrics::histogram!("miow_qdrant_request_duration_seconds", "operation" => operation)
+        .record(started.elapsed().as_secs_f64());
+    if !result.as_ref().map(|resp| resp.status().is_success() || resp.status() == StatusCode::NOT_FOUND).unwrap_or(false) {
+        metrics::counter!("miow_qdrant_request_errors_total", "operation" => operation).increment(1);
+    }
+    result
+}
 
 /// Vector store for semantic search using Qdrant
 pub struct VectorStore {
@@ -20,6 +46,8 @@ pub struct VectorStore {
     embedding_client: Client,
     embedding_url: Option<String>,
     gemini_api_key: Option<String>,
+    /// Set when Qdrant was unreachable; the store then reads and writes only this
+    fallback: Option<SqliteIndex>,
 }
 
 impl VectorStore {
@@ -32,17 +60,80 @@ impl VectorStore {
             embedding_client: Client::new(),
             embedding_url: std::env::var("EMBEDDING_URL").ok(),
             gemini_api_key: std::env::var("GEMINI_API_KEY").ok(),
+            fallback: None,
         };
 
         store.ensure_collection().await?;
         Ok(store)
     }
 
+    /// The collection in Qdrant at `url`, or, when Qdrant is unreachable, the
+    /// same collection in the SQLite index at `fallback_db` (see [`SqliteIndex`]).
+    /// Qdrant refusing a request is still an error.
+    pub async fn with_fallback(url: &str, collection_name: &str, fallback_db: &Path) -> Result<Self> {
+        match Self::new(url, collection_name).await {
+            Err(e) if matches!(e.downcast_ref::<VectorError>(), Some(VectorError::Unavailable { .. })) => {
+                warn!("{}; using the SQLite vector index in {}", e, fallback_db.display());
+                Self::sqlite(fallback_db, collection_name)
+            }
+            result => result,
+        }
+    }
+
+    /// A store kept in the SQLite database at `path`, without Qdrant
+    pub fn sqlite(path: &Path, collection_name: &str) -> Result<Self> {
+        Ok(Self {
+            qdrant_url: String::new(),
+            collection_name: collection_name.to_string(),
+            qdrant_client: Client::new(),
+            embedding_client: Client::new(),
+            embedding_url: std::env::var("EMBEDDING_URL").ok(),
+            gemini_api_key: std::env::var("GEMINI_API_KEY").ok(),
+            fallback: Some(SqliteIndex::open(path, collection_name)?),
+        })
+    }
+
+    /// Whether this store is the SQLite index rather than Qdrant
+    pub fn is_fallback(&self) -> bool {
+        self.fallback.is_some()
+    }
+
+    /// Points stored in a collection, or `None` if it doesn't exist. Unlike
+    /// [`VectorStore::new`] this never creates the collection.
+    pub async fn collection_size(url: &str, collection_name: &str) -> Result<Option<u64>> {
+        let collection_url = format!("{}/collections/{}", url.trim_end_matches('/'), collection_name);
+        let resp = timed("get_collection", Client::new().get(&collection_url)).await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            return Err(VectorError::from_response("check collection", resp).await.into());
+        }
+
+        let json: Value = resp.json().await?;
+        Ok(Some(json["result"]["points_count"].as_u64().unwrap_or(0)))
+    }
+
+    /// Drop a collection with all its points. Returns `false` if it didn't exist.
+    pub async fn delete_collection(url: &str, collection_name: &str) -> Result<bool> {
+        let collection_url = format!("{}/collections/{}", url.trim_end_matches('/'), collection_name);
+        let resp = timed("delete_collection", Client::new().delete(&collection_url)).await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        if !resp.status().is_success() {
+            return Err(VectorError::from_response("delete collection", resp).await.into());
+        }
+
+        let json: Value = resp.json().await?;
+        Ok(json["result"].as_bool().unwrap_or(true))
+    }
+
     /// Ensure the collection exists
     async fn ensure_collection(&self) -> Result<()> {
         let collection_url = format!("{}/collections/{}", self.qdrant_url, self.collection_name);
 
-        let resp = self.qdrant_client.get(&collection_url).send().await?;
+        let resp = timed("get_collection", self.qdrant_client.get(&collection_url)).await?;
         if resp.status() == StatusCode::NOT_FOUND {
             info!("Creating Qdrant collection: {}", self.collection_name);
                 // Use 768 dimensions for Gemini text-embedding-004, fallback to 384 for other services
@@ -55,22 +146,15 @@ impl VectorStore {
                 }
             });
 
-            let create_resp = self
-                .qdrant_client
-                .put(&collection_url)
-                .json(&body)
-                .send()
-                .await?;
+            let create_resp = timed("create_collection", self.qdrant_client.put(&collection_url).json(&body)).await?;
 
             if !create_resp.status().is_success() {
-                let text = create_resp.text().await.unwrap_or_default();
-                bail!("Failed to create collection: {}", text);
+                return Err(VectorError::from_response("create collection", create_resp).await.into());
             }
 
             info!("Collection created successfully");
         } else if !resp.status().is_success() {
-            let text = resp.text().await.unwrap_or_default();
-            bail!("Failed to check collection: {}", text);
+            return Err(VectorError::from_response("check collection", resp).await.into());
         } else {
             debug!("Collection {} already exists", self.collection_name);
         }
@@ -79,6 +163,7 @@ impl VectorStore {
     }
 
     /// Generate embedding for text using Gemini API, custom service, or fallback
+    #[tracing::instrument(name = "embedding", skip_all, fields(chars = text.len()))]
     async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
         // Try Gemini embeddings API first
         if let Some(api_key) = &self.gemini_api_key {
@@ -106,7 +191,7 @@ impl VectorStore {
                 Ok(resp) if resp.status().is_success() => {
                     let json: Value = resp.json().await?;
                     if let Some(embeddings) = json.get("embeddings").and_then(|e| e.as_array()) {
-                        if let Some(embedding) = embeddings.get(0).and_then(|e| e.as_array()) {
+                        if let Some(embedding) = embeddings.first().and_then(|e| e.as_array()) {
                             return Ok(embedding
                                 .iter()
                                 .filter_map(|v| v.as_f64().map(|f| f as f32))
@@ -210,8 +295,15 @@ impl VectorStore {
         embedding
     }
 
-    /// Insert a symbol with its embedding
+    /// Insert a symbol with its embedding; secrets in its content are redacted before
+    /// it is embedded or stored
     pub async fn insert_symbol(&self, symbol: &SymbolVector) -> Result<()> {
+        let (content, redacted) = SecretRedactor::global().redact(&symbol.content);
+        if !redacted.is_empty() {
+            warn!("🔒 Redacted {} secrets from {} before embedding", redacted.len(), symbol.file_path);
+        }
+        let symbol = &SymbolVector { content, ..symbol.clone() };
+
         let text = format!(
             "{} {} {}",
             symbol.name,
@@ -220,6 +312,10 @@ impl VectorStore {
         );
 
         let embedding = self.generate_embedding(&text).await?;
+        if let Some(index) = &self.fallback {
+            let (index, symbol) = (index.clone(), symbol.clone());
+            return tokio::task::spawn_blocking(move || index.upsert(&symbol, &embedding)).await?;
+        }
 
         let payload = serde_json::json!({
             "name": symbol.name,
@@ -246,10 +342,34 @@ impl VectorStore {
             self.qdrant_url, self.collection_name
         );
 
-        let resp = self.qdrant_client.put(&url).json(&body).send().await?;
+        let resp = timed("upsert", self.qdrant_client.put(&url).json(&body)).await?;
         if !resp.status().is_success() {
-            let text = resp.text().await.unwrap_or_default();
-            bail!("Failed to upsert point: {}", text);
+            return Err(VectorError::from_response("upsert point", resp).await.into());
+        }
+
+        Ok(())
+    }
+
+    /// Delete every point that was indexed from `file_path`
+    pub async fn delete_file(&self, file_path: &str) -> Result<()> {
+        if let Some(index) = &self.fallback {
+            let (index, file_path) = (index.clone(), file_path.to_string());
+            return tokio::task::spawn_blocking(move || index.delete_file(&file_path)).await?;
+        }
+        let url = format!(
+            "{}/collections/{}/points/delete?wait=true",
+            self.qdrant_url, self.collection_name
+        );
+
+        let body = serde_json::json!({
+            "filter": {
+                "must": [{ "key": "file_path", "match": { "value": file_path } }]
+            }
+        });
+
+        let resp = timed("delete_points", self.qdrant_client.post(&url).json(&body)).await?;
+        if !resp.status().is_success() {
+            return Err(VectorError::from_response(format!("delete points for {}", file_path), resp).await.into());
         }
 
         Ok(())
@@ -262,7 +382,22 @@ impl VectorStore {
         limit: usize,
     ) -> Result<Vec<SymbolSearchResult>> {
         let query_embedding = self.generate_embedding(query).await?;
-        self.search_with_embedding(query_embedding, limit).await
+        self.search_with_embedding(query_embedding, limit, &[], &[]).await
+    }
+
+    /// `search_similar` limited to symbols whose file path contains one of
+    /// `paths` and whose kind is one of `kinds`; empty lists match everything.
+    /// Qdrant matches paths as substrings, so callers wanting directory
+    /// prefixes should check the results.
+    pub async fn search_similar_within(
+        &self,
+        query: &str,
+        limit: usize,
+        paths: &[String],
+        kinds: &[String],
+    ) -> Result<Vec<SymbolSearchResult>> {
+        let query_embedding = self.generate_embedding(query).await?;
+        self.search_with_embedding(query_embedding, limit, paths, kinds).await
     }
 
     /// Search by embedding vector
@@ -271,29 +406,38 @@ impl VectorStore {
         embedding: Vec<f32>,
         limit: usize,
     ) -> Result<Vec<SymbolSearchResult>> {
-        self.search_with_embedding(embedding, limit).await
+        self.search_with_embedding(embedding, limit, &[], &[]).await
     }
 
     async fn search_with_embedding(
         &self,
         embedding: Vec<f32>,
         limit: usize,
+        paths: &[String],
+        kinds: &[String],
     ) -> Result<Vec<SymbolSearchResult>> {
+        if let Some(index) = &self.fallback {
+            let (index, paths, kinds) = (index.clone(), paths.to_vec(), kinds.to_vec());
+            return tokio::task::spawn_blocking(move || index.search(&embedding, limit, &paths, &kinds)).await?;
+        }
+
         let url = format!(
             "{}/collections/{}/points/search",
             self.qdrant_url, self.collection_name
         );
 
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "vector": embedding,
             "limit": limit,
             "with_payload": true
         });
+        if let Some(filter) = payload_filter(paths, kinds) {
+            body["filter"] = filter;
+        }
 
-        let resp = self.qdrant_client.post(&url).json(&body).send().await?;
+        let resp = timed("search", self.qdrant_client.post(&url).json(&body)).await?;
         if !resp.status().is_success() {
-            let text = resp.text().await.unwrap_or_default();
-            bail!("Failed to search points: {}", text);
+            return Err(VectorError::from_response("search points", resp).await.into());
         }
 
         let json: Value = resp.json().await?;
@@ -346,6 +490,34 @@ impl VectorStore {
     }
 }
 
+/// Qdrant filter for `search_similar_within`, `None` when nothing is restricted.
+/// Kinds are stored as the parsers name them (`Function`), so each is matched
+/// as given, lowercased and capitalized.
+fn payload_filter(paths: &[String], kinds: &[String]) -> Option<Value> {
+    let mut must = Vec::new();
+    if !paths.is_empty() {
+        let should: Vec<Value> = paths
+            .iter()
+            .map(|path| serde_json::json!({ "key": "file_path", "match": { "text": path } }))
+            .collect();
+        must.push(serde_json::json!({ "should": should }));
+    }
+    if !kinds.is_empty() {
+        let mut variants = Vec::new();
+        for kind in kinds {
+            let mut chars = kind.chars();
+            let capitalized = chars.next().map(|c| c.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect()).unwrap_or_default();
+            for variant in [kind.clone(), kind.to_lowercase(), capitalized] {
+                if !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+            }
+        }
+        must.push(serde_json::json!({ "key": "kind", "match": { "any": variants } }));
+    }
+    (!must.is_empty()).then(|| serde_json::json!({ "must": must }))
+}
+
 /// Symbol representation for vector storage
 #[derive(Debug, Clone, Serialize)]
 pub struct SymbolVector {
@@ -363,3 +535,28 @@ pub struct SymbolSearchResult {
     pub symbol: SymbolVector,
     pub score: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_secrets_are_redacted_before_embedding() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = VectorStore::sqlite(&temp_dir.path().join("miow.db"), "project").unwrap();
+        store
+            .insert_symbol(&SymbolVector {
+                id: "connect".to_string(),
+                name: "connect".to_string(),
+                kind: "Function".to_string(),
+                content: r#"fn connect() { let password = "h7$Kq2!vLp9z"; }"#.to_string(),
+                file_path: "src/db.rs".to_string(),
+                metadata: String::new(),
+            })
+            .await
+            .unwrap();
+
+        let results = store.search_similar("connect", 1).await.unwrap();
+        assert_eq!(results[0].symbol.content, r#"fn connect() { let password = "[REDACTED:secret_value]"; }"#);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{SymbolSearchResult, SymbolVector};
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Vector index kept in a table of a SQLite database, for when Qdrant is
+/// unreachable. Embeddings are stored unit length, so a search is a brute-force
+/// dot product over every row of the collection; fast enough for the tens of
+/// thousands of symbols of a small or medium project.
+#[derive(Clone)]
+pub struct SqliteIndex {
+    conn: Arc<Mutex<Connection>>,
+    collection: String,
+}
+
+impl SqliteIndex {
+    /// Open (or create) `collection` in the database at `path`
+    pub fn open(path: &Path, collection: &str) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        // The knowledge graph shares the file and may be writing to it
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS symbol_vectors (
+                collection TEXT NOT NULL,
+                id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                content TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                metadata TEXT NOT NULL,
+                embedding BLOB NOT NULL,
+                PRIMARY KEY (collection, id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_symbol_vectors_file ON symbol_vectors(collection, file_path);
+            "#,
+        )?;
+        Ok(Self { conn: Arc::new(Mutex::new(conn)), collection: collection.to_string() })
+    }
+
+    /// Vectors stored for `collection` in the database at `path`, or `None` if
+    /// it has none; never creates the table
+    pub fn collection_size(path: &Path, collection: &str) -> Result<Option<u64>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let conn = Connection::open(path)?;
+        let has_table: bool = conn
+            .query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'symbol_vectors'", [], |_| Ok(true))
+            .optional()?
+            .unwrap_or(false);
+        if !has_table {
+            return Ok(None);
+        }
+        let count: u64 =
+            conn.query_row("SELECT COUNT(*) FROM symbol_vectors WHERE collection = ?1", [collection], |row| row.get(0))?;
+        Ok((count > 0).then_some(count))
+    }
+
+    pub fn upsert(&self, symbol: &SymbolVector, embedding: &[f32]) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO symbol_vectors (collection, id, name, kind, content, file_path, metadata, embedding)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                self.collection,
+                symbol.id,
+                symbol.name,
+                symbol.kind,
+                symbol.content,
+                symbol.file_path,
+                symbol.metadata,
+                encode(embedding)
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_file(&self, file_path: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM symbol_vectors WHERE collection = ?1 AND file_path = ?2",
+            params![self.collection, file_path],
+        )?;
+        Ok(())
+    }
+
+    /// The `limit` vectors most similar to `embedding`, filtered like
+    /// `VectorStore::search_similar_within`: file paths containing one of
+    /// `paths` and kinds matching one of `kinds`, ignoring case
+    pub fn search(&self, embedding: &[f32], limit: usize, paths: &[String], kinds: &[String]) -> Result<Vec<SymbolSearchResult>> {
+        let query = normalized(embedding);
+        let kinds: Vec<String> = kinds.iter().map(|kind| kind.to_lowercase()).collect();
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt =
+            conn.prepare_cached("SELECT rowid, kind, file_path, embedding FROM symbol_vectors WHERE collection = ?1")?;
+        let mut rows = stmt.query([&self.collection])?;
+        let mut scored: Vec<(f32, i64)> = Vec::new();
+        let mut stored = Vec::with_capacity(query.len());
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get(1)?;
+            let file_path: String = row.get(2)?;
+            if !kinds.is_empty() && !kinds.contains(&kind.to_lowercase()) {
+                continue;
+            }
+            if !paths.is_empty() && !paths.iter().any(|path| file_path.contains(path.as_str())) {
+                continue;
+            }
+            decode_into(row.get_ref(3)?.as_blob()?, &mut stored);
+            // Embedded by another model, e.g. before a Gemini key was set
+            if stored.len() != query.len() {
+                continue;
+            }
+            scored.push((dot(&query, &stored), row.get(0)?));
+        }
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(limit);
+
+        let mut payload = conn.prepare_cached(
+            "SELECT id, name, kind, content, file_path, metadata FROM symbol_vectors WHERE rowid = ?1",
+        )?;
+        scored
+            .into_iter()
+            .map(|(score, rowid)| {
+                let symbol = payload.query_row([rowid], |row| {
+                    Ok(SymbolVector {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        kind: row.get(2)?,
+                        content: row.get(3)?,
+                        file_path: row.get(4)?,
+                        metadata: row.get(5)?,
+                    })
+                })?;
+                Ok(SymbolSearchResult { symbol, score })
+            })
+            .collect()
+    }
+}
+
+fn normalized(embedding: &[f32]) -> Vec<f32> {
+    let norm = dot(embedding, embedding).sqrt();
+    if norm == 0.0 {
+        return embedding.to_vec();
+    }
+    embedding.iter().map(|x| x / norm).collect()
+}
+
+fn encode(embedding: &[f32]) -> Vec<u8> {
+    normalized(embedding).iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode_into(bytes: &[u8], embedding: &mut Vec<f32>) {
+    embedding.clear();
+    embedding.extend(bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])));
+}
+
+/// Dot product, summed in eight independent lanes so the compiler can keep
+/// them in one SIMD register instead of adding one product at a time
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    const LANES: usize = 8;
+    let (a_chunks, b_chunks) = (a.chunks_exact(LANES), b.chunks_exact(LANES));
+    let tail: f32 = a_chunks.remainder().iter().zip(b_chunks.remainder()).map(|(x, y)| x * y).sum();
+    let mut sums = [0.0f32; LANES];
+    for (x, y) in a_chunks.zip(b_chunks) {
+        for (sum, (x, y)) in sums.iter_mut().zip(x.iter().zip(y)) {
+            *sum += x * y;
+        }
+    }
+    sums.iter().sum::<f32>() + tail
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(id: &str, kind: &str, file_path: &str) -> SymbolVector {
+        SymbolVector {
+            id: id.to_string(),
+            name: id.to_string(),
+            kind: kind.to_string(),
+            content: format!("fn {}() {{}}", id),
+            file_path: file_path.to_string(),
+            metadata: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_search_ranks_by_cosine_and_applies_filters() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let db = dir.join("miow.db");
+
+        let index = SqliteIndex::open(&db, "project").unwrap();
+        let mut near = vec![0.0; 20];
+        near[0] = 3.0;
+        near[1] = 1.0;
+        let mut far = vec![0.0; 20];
+        far[19] = 1.0;
+        let mut middle = vec![0.0; 20];
+        middle[0] = 1.0;
+        middle[19] = 1.0;
+        index.upsert(&symbol("login", "Function", "src/auth/login.ts"), &near).unwrap();
+        index.upsert(&symbol("footer", "Component", "src/ui/footer.tsx"), &far).unwrap();
+        index.upsert(&symbol("session", "Function", "src/auth/session.ts"), &middle).unwrap();
+        // A vector of another model's size is never compared
+        index.upsert(&symbol("stale", "Function", "src/auth/stale.ts"), &[1.0; 8]).unwrap();
+
+        let mut query = vec![0.0; 20];
+        query[0] = 1.0;
+        let names = |results: Vec<SymbolSearchResult>| results.into_iter().map(|r| r.symbol.name).collect::<Vec<_>>();
+        let results = index.search(&query, 10, &[], &[]).unwrap();
+        assert!((results[0].score - 3.0 / 10f32.sqrt()).abs() < 1e-5);
+        assert_eq!(names(results), vec!["login", "session", "footer"]);
+        assert_eq!(names(index.search(&query, 1, &[], &[]).unwrap()), vec!["login"]);
+        assert_eq!(names(index.search(&query, 10, &["src/ui".to_string()], &[]).unwrap()), vec!["footer"]);
+        assert_eq!(names(index.search(&query, 10, &[], &["component".to_string()]).unwrap()), vec!["footer"]);
+
+        index.delete_file("src/auth/login.ts").unwrap();
+        assert_eq!(names(index.search(&query, 10, &["auth".to_string()], &[]).unwrap()), vec!["session"]);
+        assert_eq!(SqliteIndex::collection_size(&db, "project").unwrap(), Some(3));
+        assert_eq!(SqliteIndex::collection_size(&db, "other").unwrap(), None);
+    }
+}
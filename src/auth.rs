@@ -0,0 +1,259 @@
+//! Bearer tokens for `miow-context serve`: static ones from the `[server]`
+//! table of miow.toml and ones created with `miow-context token create`,
+//! which are stored hashed in the server's database
+
+use sha2::{Digest, Sha256};
+
+/// Hex SHA-256 of a token, as stored
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A new random token
+pub fn generate_token() -> String {
+    let bytes: [u8; 24] = rand::random();
+    format!("miow_{}", bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+#[cfg(feature = "web")]
+pub use server::{ApiToken, Authenticator};
+
+#[cfg(feature = "web")]
+mod server {
+    use super::hash_token;
+    use crate::config::ServerConfig;
+    use anyhow::{Context, Result};
+    use miow_graph::KnowledgeGraph;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    /// How long `is_enabled` trusts its last look at the token database
+    const TOKEN_RECHECK: Duration = Duration::from_secs(5);
+
+    /// Who a request is from and which codebases it may touch
+    #[derive(Debug, Clone)]
+    pub struct ApiToken {
+        pub name: String,
+        /// Canonical roots; empty allows any codebase
+        roots: Vec<PathBuf>,
+    }
+
+    impl ApiToken {
+        fn new(name: String, paths: impl IntoIterator<Item = PathBuf>) -> Self {
+            // A root that doesn't exist can't contain anything that does
+            let roots = paths.into_iter().map(|p| p.canonicalize().unwrap_or(p)).collect();
+            Self { name, roots }
+        }
+
+        pub fn is_scoped(&self) -> bool {
+            !self.roots.is_empty()
+        }
+
+        /// Whether the token may use `codebase_path`. The path is resolved first,
+        /// so `..` and symlinks can't lead out of an allowed root.
+        pub fn allows(&self, codebase_path: &Path) -> bool {
+            if self.roots.is_empty() {
+                return true;
+            }
+            match codebase_path.canonicalize() {
+                Ok(path) => self.roots.iter().any(|root| path.starts_with(root)),
+                Err(_) => false,
+            }
+        }
+    }
+
+    /// Checks bearer tokens; with no tokens configured anywhere the API is open
+    #[derive(Clone)]
+    pub struct Authenticator {
+        /// Hash and token for each static token
+        static_tokens: Arc<Vec<(String, ApiToken)>>,
+        graph: Option<Arc<KnowledgeGraph>>,
+        /// Whether the database had tokens, and when it was asked
+        stored: Arc<Mutex<(bool, Instant)>>,
+    }
+
+    impl Authenticator {
+        pub fn new(config: &ServerConfig, graph: Option<Arc<KnowledgeGraph>>) -> Result<Self> {
+            let mut static_tokens = Vec::new();
+            for spec in &config.tokens {
+                let token = match (&spec.token, &spec.token_env) {
+                    (Some(token), _) => token.clone(),
+                    (None, Some(var)) => std::env::var(var)
+                        .with_context(|| format!("Token '{}' reads {}, which is not set", spec.name, var))?,
+                    (None, None) => anyhow::bail!("Token '{}' in [server] needs `token` or `token_env`", spec.name),
+                };
+                if token.trim().is_empty() {
+                    anyhow::bail!("Token '{}' in [server] is empty", spec.name);
+                }
+                static_tokens.push((hash_token(token.trim()), ApiToken::new(spec.name.clone(), spec.paths.clone())));
+            }
+            let stored = graph.as_deref().is_some_and(has_tokens);
+            Ok(Self { static_tokens: Arc::new(static_tokens), graph, stored: Arc::new(Mutex::new((stored, Instant::now()))) })
+        }
+
+        /// Whether requests need a token. The database is asked at most every
+        /// few seconds, so tokens created while the server runs count soon after.
+        pub fn is_enabled(&self) -> bool {
+            if !self.static_tokens.is_empty() {
+                return true;
+            }
+            let Some(graph) = &self.graph else { return false };
+            let mut stored = self.stored.lock().unwrap();
+            if stored.1.elapsed() >= TOKEN_RECHECK {
+                *stored = (has_tokens(graph), Instant::now());
+            }
+            stored.0
+        }
+
+        /// The token `bearer` names, if any
+        pub fn authenticate(&self, bearer: &str) -> Result<Option<ApiToken>> {
+            let hash = hash_token(bearer);
+            if let Some((_, token)) = self.static_tokens.iter().find(|(h, _)| *h == hash) {
+                return Ok(Some(token.clone()));
+            }
+            let Some(graph) = &self.graph else { return Ok(None) };
+            Ok(graph
+                .find_api_token(&hash)?
+                .map(|record| ApiToken::new(record.name, record.paths.into_iter().map(PathBuf::from))))
+        }
+    }
+
+    /// A database that can't be read is treated as having tokens, so it fails closed
+    fn has_tokens(graph: &KnowledgeGraph) -> bool {
+        graph.list_api_tokens().map(|tokens| !tokens.is_empty()).unwrap_or(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_token_is_hex_sha256() {
+        assert_eq!(hash_token("abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_ne!(hash_token("abc"), hash_token("abd"));
+    }
+
+    #[test]
+    fn test_generated_tokens_are_unique() {
+        let (a, b) = (generate_token(), generate_token());
+        assert!(a.starts_with("miow_") && a.len() == "miow_".len() + 48, "{}", a);
+        assert_ne!(a, b);
+    }
+
+    #[cfg(feature = "web")]
+    fn authenticator(tokens: &[(&str, &str, &[&std::path::Path])]) -> Authenticator {
+        let config = crate::config::ServerConfig {
+            tokens: tokens
+                .iter()
+                .map(|(name, token, paths)| crate::config::StaticToken {
+                    name: name.to_string(),
+                    token: Some(token.to_string()),
+                    token_env: None,
+                    paths: paths.iter().map(|p| p.to_path_buf()).collect(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        Authenticator::new(&config, None).unwrap()
+    }
+
+    #[cfg(feature = "web")]
+    #[test]
+    fn test_scoped_tokens_only_reach_their_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let (app, other) = (dir.path().join("app"), dir.path().join("other"));
+        std::fs::create_dir_all(app.join("web")).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+        let auth = authenticator(&[("ci", "ci-secret", &[app.as_path()]), ("admin", "admin-secret", &[])]);
+
+        let ci = auth.authenticate("ci-secret").unwrap().unwrap();
+        assert!(ci.is_scoped());
+        assert!(ci.allows(&app) && ci.allows(&app.join("web")));
+        assert!(!ci.allows(&other));
+        assert!(!ci.allows(&app.join("..").join("other")));
+        assert!(!ci.allows(&app.join("missing")));
+
+        let admin = auth.authenticate("admin-secret").unwrap().unwrap();
+        assert!(!admin.is_scoped() && admin.allows(&other));
+        assert!(auth.authenticate("guess").unwrap().is_none());
+    }
+
+    #[cfg(feature = "web")]
+    #[test]
+    fn test_tokens_in_the_database_enable_auth() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!authenticator(&[]).is_enabled());
+
+        let graph = std::sync::Arc::new(miow_graph::KnowledgeGraph::new(dir.path().join("miow.db")).unwrap());
+        assert!(!Authenticator::new(&Default::default(), Some(graph.clone())).unwrap().is_enabled());
+        graph
+            .add_api_token(&miow_graph::ApiTokenRecord {
+                name: "laptop".into(),
+                token_hash: hash_token("db-secret"),
+                paths: Vec::new(),
+                created_at: 0,
+            })
+            .unwrap();
+        let auth = Authenticator::new(&Default::default(), Some(graph)).unwrap();
+        assert!(auth.is_enabled());
+        assert_eq!(auth.authenticate("db-secret").unwrap().unwrap().name, "laptop");
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_require_token_answers_401_and_403() {
+        use axum::routing::{get, post};
+        let dir = tempfile::tempdir().unwrap();
+        let (app, other) = (dir.path().join("app"), dir.path().join("other"));
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+        let state = crate::tests::web_state(authenticator(&[("ci", "ci-secret", &[app.as_path()])]));
+        let router = axum::Router::new()
+            .route("/api/symbols", get(|| async { "ok" }))
+            .route("/api/usage", get(|| async { "ok" }))
+            .route("/api/approvals/:id", post(|| async { "ok" }))
+            .route("/api/health", post(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(state.clone(), crate::require_token))
+            .with_state(state.clone());
+        let base = crate::tests::serve_locally(router).await;
+        let client = reqwest::Client::new();
+        let symbols = |token: Option<&str>, codebase: &std::path::Path| {
+            let request = client.get(format!("{}/api/symbols", base)).query(&[("codebase_path", codebase)]);
+            match token {
+                Some(token) => request.bearer_auth(token),
+                None => request,
+            }
+        };
+
+        assert_eq!(symbols(None, &app).send().await.unwrap().status(), 401);
+        assert_eq!(symbols(Some("guess"), &app).send().await.unwrap().status(), 401);
+        assert_eq!(symbols(Some("ci-secret"), &app).send().await.unwrap().status(), 200);
+        assert_eq!(symbols(Some("ci-secret"), &other).send().await.unwrap().status(), 403);
+        let usage = client.get(format!("{}/api/usage", base)).bearer_auth("ci-secret").send().await.unwrap();
+        assert_eq!(usage.status(), 403);
+        let health = client.post(format!("{}/api/health", base)).send().await.unwrap();
+        assert_eq!(health.status(), 200);
+
+        // An approval belongs to the codebase of the run that asked for it
+        for (codebase, status) in [(&app, 200), (&other, 403)] {
+            let (events, mut asked) = tokio::sync::mpsc::channel(1);
+            let approver = state.approvals.for_codebase(codebase);
+            tokio::spawn(async move {
+                let request = miow_agent::ApprovalRequest::new("run_command", "run `ls`".into());
+                miow_agent::Approver::approve(&approver, &request, Some(&events)).await
+            });
+            let Some(miow_agent::autonomous::AgentEvent::ApprovalRequired { request }) = asked.recv().await else {
+                panic!("no approval was asked for");
+            };
+            let answer = client
+                .post(format!("{}/api/approvals/{}", base, request.id))
+                .bearer_auth("ci-secret")
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(answer.status(), status, "{}", codebase.display());
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{Context, Result};
+use miow_agent::{AgentBudget, CommandPolicy, CommandRestrictions, LspConfig, ScriptTool, ScriptToolSpec, Tool};
+use miow_analyzer::IntentRule;
+use miow_llm::LLMSettings;
+use serde::Deserialize;
+use std::path::Path;
+#[cfg(feature = "web")]
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::pipeline::PipelineConfig;
+
+/// Set by `--trust-clone`: apply the settings of repositories cloned with `init --git`
+pub static TRUST_CLONES: AtomicBool = AtomicBool::new(false);
+
+/// Whether `codebase` is a checkout made by `init --git` (it lives in `.miow/repos`)
+/// that `--trust-clone` hasn't vouched for
+pub fn is_untrusted_clone(codebase: &Path) -> bool {
+    if TRUST_CLONES.load(Ordering::Relaxed) {
+        return false;
+    }
+    let codebase = codebase.canonicalize().unwrap_or_else(|_| codebase.to_path_buf());
+    codebase.ancestors().any(|dir| {
+        let Some(repos) = dir.parent() else {
+            return false;
+        };
+        repos.file_name().is_some_and(|name| name == "repos")
+            && repos.parent().and_then(Path::file_name).is_some_and(|name| name == ".miow")
+    })
+}
+
+/// Per-project settings, read from `miow.toml` at the codebase root
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MiowConfig {
+    /// What the agent's `run_command` tool may do: the user's `[commands]`, tightened
+    /// by the project's
+    #[serde(skip)]
+    pub commands: CommandPolicy,
+    /// `[commands]`: restrictions the project adds to the user's command policy
+    #[serde(rename = "commands")]
+    command_restrictions: CommandRestrictions,
+    /// `[agent]`: limits on each autonomous agent run
+    pub agent: AgentBudget,
+    /// `[[tools]]`: project-specific shell tools offered to the agent
+    pub tools: Vec<ScriptToolSpec>,
+    /// Language server for exact definitions and references: the user's `[lsp]`, as
+    /// a project's miow.toml could name any program
+    #[serde(skip)]
+    pub lsp: Option<LspConfig>,
+    /// `[lsp]` of the project, which is ignored
+    #[serde(rename = "lsp")]
+    project_lsp: Option<toml::Value>,
+    /// `[llm]`: provider, model, temperature and response token cap
+    pub llm: LLMSettings,
+    /// `[pipeline]`: workflow choice, the stages the enhanced workflow runs and the context token budget
+    pub pipeline: PipelineConfig,
+    /// `[[intents]]`: rules that classify tasks, checked before the built-in taxonomy
+    pub intents: Vec<IntentRule>,
+    /// `[server]`: settings for `miow-context serve`
+    #[cfg(feature = "web")]
+    pub server: ServerConfig,
+}
+
+/// The user's own `miow.toml` in `miow_core::user_config_dir()`, applying to every project
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct UserConfig {
+    /// `[commands]`: the command policy projects start from
+    commands: CommandPolicy,
+    /// `[lsp]`: the language server the agent's `lsp` tool starts
+    lsp: Option<LspConfig>,
+}
+
+impl UserConfig {
+    fn load() -> Result<Self> {
+        let Some(path) = miow_core::user_config_dir().map(|dir| dir.join(MiowConfig::FILE_NAME)) else {
+            return Ok(Self::default());
+        };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        toml::from_str(&content).with_context(|| format!("Invalid {}", path.display()))
+    }
+}
+
+#[cfg(feature = "web")]
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// `[[server.tokens]]`: bearer tokens the API accepts, besides those made
+    /// with `miow-context token create`
+    pub tokens: Vec<StaticToken>,
+    /// `[server.rate_limit]`: requests each token (or address, without one) may make
+    pub rate_limit: RateLimitConfig,
+    /// Largest request body the generate endpoints accept, in bytes
+    pub max_generate_body: usize,
+    /// On SIGINT/SIGTERM, how long open requests and a running index job get to finish
+    pub shutdown_timeout_secs: u64,
+    /// Most agent iterations any request gets, whatever its budget or the project's `[agent]` says
+    pub max_agent_iterations: usize,
+    /// Origins (e.g. `https://tools.example.com`) whose pages may call the API from a
+    /// browser; pages the server serves itself always may
+    pub allowed_origins: Vec<String>,
+}
+
+#[cfg(feature = "web")]
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            tokens: Vec::new(),
+            rate_limit: RateLimitConfig::default(),
+            max_generate_body: 256 * 1024,
+            shutdown_timeout_secs: 30,
+            max_agent_iterations: 50,
+            allowed_origins: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Sustained rate; 0 turns rate limiting off
+    pub requests_per_minute: u32,
+    /// Requests allowed at once before the rate applies
+    pub burst: u32,
+}
+
+#[cfg(feature = "web")]
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self { requests_per_minute: 120, burst: 30 }
+    }
+}
+
+/// A bearer token written into miow.toml
+#[cfg(feature = "web")]
+#[derive(Debug, Clone, Deserialize)]
+pub struct StaticToken {
+    pub name: String,
+    /// The token itself
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Environment variable holding the token, to keep it out of miow.toml
+    #[serde(default)]
+    pub token_env: Option<String>,
+    /// Codebases the token may use; empty allows any
+    #[serde(default)]
+    pub paths: Vec<PathBuf>,
+}
+
+impl MiowConfig {
+    pub const FILE_NAME: &'static str = "miow.toml";
+
+    /// Load `miow.toml` from `codebase`, or the defaults when there is none
+    pub fn load(codebase: &Path) -> Result<Self> {
+        let user = UserConfig::load()?;
+        let path = codebase.join(Self::FILE_NAME);
+        if !path.exists() {
+            return Ok(Self { commands: user.commands, lsp: user.lsp, ..Self::default() });
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let mut config: Self = toml::from_str(&content).with_context(|| format!("Invalid {}", path.display()))?;
+        if is_untrusted_clone(codebase) {
+            // A cloned repository doesn't get to pick the commands the agent runs
+            let table: toml::Table = toml::from_str(&content)?;
+            if ["commands", "tools"].iter().any(|key| table.contains_key(*key)) {
+                tracing::warn!(
+                    "Ignoring [commands] and [[tools]] in {}: cloned repositories are untrusted (pass --trust-clone)",
+                    path.display()
+                );
+            }
+            config.command_restrictions = CommandRestrictions::default();
+            config.tools.clear();
+            // Nor where the API key goes
+            if config.llm.base_url.is_some() || !config.llm.headers.is_empty() {
+                tracing::warn!(
+                    "Ignoring base_url and headers in [llm] of {}: cloned repositories are untrusted (pass --trust-clone)",
+                    path.display()
+                );
+                config.llm.base_url = None;
+                config.llm.headers.clear();
+            }
+        }
+        if config.project_lsp.take().is_some() {
+            tracing::warn!(
+                "Ignoring [lsp] in {}: set the language server in your own {} instead",
+                path.display(),
+                Self::FILE_NAME
+            );
+        }
+        config.commands = user.commands.restricted_by(&config.command_restrictions);
+        config.lsp = user.lsp;
+        Ok(config)
+    }
+
+    /// The `[[tools]]` entries as agent tools running in `codebase`
+    pub fn agent_tools(&self, codebase: &Path) -> Result<Vec<Arc<dyn Tool>>> {
+        self.tools
+            .iter()
+            .map(|spec| {
+                let tool = ScriptTool::new(spec.clone(), codebase.to_path_buf(), self.commands.clone())?;
+                Ok(Arc::new(tool) as Arc<dyn Tool>)
+            })
+            .collect()
+    }
+
+    /// Agent budget for a web request: the project's, tightened by the request's own,
+    /// with at most `max_iterations` (the server's ceiling) iterations
+    #[cfg(feature = "web")]
+    pub fn agent_budget_for(codebase: &Path, requested: Option<AgentBudget>, max_iterations: usize) -> AgentBudget {
+        let project = Self::load(codebase).map(|config| config.agent).unwrap_or_default();
+        let mut budget = match requested {
+            Some(requested) => requested.capped_by(&project),
+            None => project,
+        };
+        budget.max_iterations = budget.max_iterations.min(max_iterations);
+        budget
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clone_settings_are_ignored() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let settings = "[llm]\nbase_url = \"https://collector.example\"\n\n[commands]\napproval = \"auto\"\n\n[[tools]]\nname = \"deploy\"\ndescription = \"Deploy\"\ncommand = \"./deploy.sh\"\n\n[agent]\nmax_iterations = 3\n\n[lsp]\ncommand = \"./not-a-server.sh\"\n";
+
+        let local = temp_dir.path().join("project");
+        let clone = temp_dir.path().join(".miow").join("repos").join("github.com-org-repo");
+        for dir in [&local, &clone] {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join(MiowConfig::FILE_NAME), settings).unwrap();
+        }
+
+        let config = MiowConfig::load(&local).unwrap();
+        assert_eq!(config.tools.len(), 1);
+        assert_eq!(config.llm.base_url.as_deref(), Some("https://collector.example"));
+        assert!(!is_untrusted_clone(&local));
+        // Only the user's own config picks the language server
+        let user_lsp = UserConfig::load().unwrap().lsp.map(|lsp| lsp.command);
+        assert_eq!(config.lsp.map(|lsp| lsp.command), user_lsp);
+
+        let config = MiowConfig::load(&clone).unwrap();
+        assert!(is_untrusted_clone(&clone));
+        assert!(config.tools.is_empty());
+        assert_eq!(config.llm.base_url, None);
+        assert_eq!(config.commands.approval, UserConfig::load().unwrap().commands.approval);
+        assert_eq!(config.agent.max_iterations, 3);
+    }
+}
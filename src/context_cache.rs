@@ -0,0 +1,186 @@
+//! Compiled context kept for the rest of a session (a `batch`, the server), so a
+//! task close to an earlier one reuses that task's context instead of gathering
+//! it again. Entries are keyed by an embedding of the task and the index
+//! generation they were read from; any reindex or file change makes them stale.
+
+use miow_prompt::ContextData;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const DIMENSIONS: usize = 256;
+/// Cosine similarity two tasks need to share a context
+const DEFAULT_SIMILARITY: f32 = 0.9;
+/// How long an entry may be reused, even if the index hasn't changed
+const DEFAULT_TTL: Duration = Duration::from_secs(30 * 60);
+const CAPACITY: usize = 32;
+
+/// Words that don't change what a task needs from the codebase
+const FILLER_WORDS: &[&str] = &[
+    "a", "an", "the", "to", "for", "of", "in", "on", "and", "or", "with", "please", "can", "could", "you", "i",
+    "we", "me", "my", "our", "it", "this", "that", "is", "be", "some", "new",
+];
+
+/// A task as a unit vector of hashed word counts. Case, word order, plurals and
+/// filler words don't move it, so rewordings of one task land close together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskEmbedding(Vec<f32>);
+
+impl TaskEmbedding {
+    pub fn of(task: &str) -> Self {
+        let mut vector = vec![0.0f32; DIMENSIONS];
+        let lowered = task.to_lowercase();
+        let words = lowered
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty() && !FILLER_WORDS.contains(word));
+        for word in words {
+            let word = match word.strip_suffix('s') {
+                Some(stem) if stem.len() > 2 && !stem.ends_with('s') => stem,
+                _ => word,
+            };
+            let mut hasher = DefaultHasher::new();
+            word.hash(&mut hasher);
+            vector[(hasher.finish() % DIMENSIONS as u64) as usize] += 1.0;
+        }
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        Self(vector)
+    }
+
+    /// Cosine similarity; 0 when either task has no words that count
+    pub fn similarity(&self, other: &TaskEmbedding) -> f32 {
+        self.0.iter().zip(&other.0).map(|(a, b)| a * b).sum()
+    }
+}
+
+/// What a context is cached under
+#[derive(Debug, Clone)]
+pub struct ContextKey {
+    pub task: TaskEmbedding,
+    /// Project and settings the context is gathered with
+    pub scope: String,
+    /// Index generation it is read from
+    pub generation: u64,
+}
+
+impl ContextKey {
+    pub fn new(task: &str, scope: impl Into<String>, generation: u64) -> Self {
+        Self { task: TaskEmbedding::of(task), scope: scope.into(), generation }
+    }
+}
+
+/// The context built for one task, with what the prompt needs besides it
+#[derive(Debug, Clone)]
+pub struct CachedContext {
+    /// The task it was gathered for
+    pub task: String,
+    /// Project description for the prompt
+    pub project: String,
+    pub context: ContextData,
+    pub search_queries: Vec<String>,
+}
+
+/// A context found for a new task and how close its task was
+#[derive(Debug, Clone)]
+pub struct ContextHit {
+    pub cached: CachedContext,
+    pub similarity: f32,
+}
+
+struct Entry {
+    key: ContextKey,
+    stored_at: Instant,
+    cached: CachedContext,
+}
+
+/// Compiled contexts of earlier tasks. Clones share the entries, so one cache
+/// can serve every orchestrator of a server.
+#[derive(Clone)]
+pub struct ContextCache {
+    entries: Arc<Mutex<Vec<Entry>>>,
+    similarity: f32,
+    ttl: Duration,
+}
+
+impl Default for ContextCache {
+    fn default() -> Self {
+        Self { entries: Arc::default(), similarity: DEFAULT_SIMILARITY, ttl: DEFAULT_TTL }
+    }
+}
+
+impl ContextCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The context of the closest earlier task with the key's scope and index
+    /// generation, if it is close enough. Expired entries and entries read from
+    /// another generation of the index are dropped.
+    pub fn lookup(&self, key: &ContextKey) -> Option<ContextHit> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|entry| {
+            entry.stored_at.elapsed() < self.ttl && (entry.key.scope != key.scope || entry.key.generation == key.generation)
+        });
+        entries
+            .iter()
+            .filter(|entry| entry.key.scope == key.scope)
+            .map(|entry| (entry, entry.key.task.similarity(&key.task)))
+            .filter(|(_, similarity)| *similarity >= self.similarity)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entry, similarity)| ContextHit { cached: entry.cached.clone(), similarity })
+    }
+
+    /// Keep `cached` for later tasks, replacing an entry for the same task
+    /// and evicting the oldest beyond the capacity
+    pub fn store(&self, key: ContextKey, cached: CachedContext) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|entry| entry.key.scope != key.scope || entry.key.task != key.task);
+        if entries.len() >= CAPACITY {
+            entries.remove(0);
+        }
+        entries.push(Entry { key, stored_at: Instant::now(), cached });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(task: &str) -> CachedContext {
+        let context: ContextData = serde_json::from_value(serde_json::json!({
+            "relevant_symbols": [], "similar_symbols": [], "types": [], "design_tokens": [],
+            "constants": [], "schemas": [], "routes": [], "env_vars": [], "tests": [],
+            "external_references": [], "common_imports": []
+        }))
+        .unwrap();
+        CachedContext { task: task.to_string(), project: "TypeScript".to_string(), context, search_queries: vec![] }
+    }
+
+    #[test]
+    fn test_near_duplicate_tasks_share_a_context() {
+        let cache = ContextCache::new();
+        cache.store(ContextKey::new("Add a login form", "/app", 3), cached("Add a login form"));
+
+        let hit = cache.lookup(&ContextKey::new("add the Login forms, please", "/app", 3)).unwrap();
+        assert_eq!(hit.cached.task, "Add a login form");
+        assert!(hit.similarity > 0.99);
+
+        // A different task, another project or settings miss
+        assert!(cache.lookup(&ContextKey::new("Add a signup form", "/app", 3)).is_none());
+        assert!(cache.lookup(&ContextKey::new("Add a login form", "/other", 3)).is_none());
+
+        // The index changed since: the entry is stale and dropped
+        assert!(cache.lookup(&ContextKey::new("Add a login form", "/app", 4)).is_none());
+        assert!(cache.entries.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_entries_expire() {
+        let cache = ContextCache { ttl: Duration::ZERO, ..ContextCache::default() };
+        cache.store(ContextKey::new("Add a login form", "/app", 1), cached("Add a login form"));
+        assert!(cache.lookup(&ContextKey::new("Add a login form", "/app", 1)).is_none());
+    }
+}
@@ -0,0 +1,221 @@
+//! What a run will spend on LLM calls, estimated before it starts from the
+//! workflow it will take: the stages the pipeline config runs, or the agent's
+//! step budget. Counts are typical, not exact; the router picks its own
+//! workers and questions, and the agent may finish before its budget.
+
+use crate::pipeline::{PipelineConfig, Stage};
+use miow_agent::AgentBudget;
+use serde::Serialize;
+
+/// Prompt and completion tokens of one call, before the task is added to the prompt
+#[derive(Debug, Clone, Copy)]
+struct CallSize {
+    prompt: usize,
+    completion: usize,
+}
+
+const fn call(prompt: usize, completion: usize) -> CallSize {
+    CallSize { prompt, completion }
+}
+
+const SIGNATURE: CallSize = call(400, 200);
+const CLASSIFY: CallSize = call(300, 10);
+const ROUTER: CallSize = call(900, 600);
+const WORKER: CallSize = call(1200, 800);
+const QUESTIONS: CallSize = call(400, 400);
+/// Checking one question's search results
+const ANSWER: CallSize = call(1500, 200);
+const AUDIT: CallSize = call(2500, 600);
+/// Merging worker results, plus `MERGE_PER_WORKER` for each worker
+const MERGE: CallSize = call(400, 500);
+const MERGE_PER_WORKER: usize = 100;
+/// The agent's first decision; each later one also carries what earlier steps found
+const DECIDE: CallSize = call(1800, 150);
+const DECIDE_GROWTH: usize = 400;
+const PLAN: CallSize = call(1200, 1500);
+
+/// Workers and questions the router and question generator are asked for (2-4 and 3-5)
+const TYPICAL_WORKERS: usize = 3;
+const TYPICAL_QUESTIONS: usize = 4;
+
+/// LLM calls and tokens one stage of a run is expected to use
+#[derive(Debug, Clone, Serialize)]
+pub struct StageEstimate {
+    pub stage: &'static str,
+    pub calls: usize,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CostEstimate {
+    pub model: String,
+    pub stages: Vec<StageEstimate>,
+    pub calls: usize,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub cost_usd: f64,
+    /// Whether the model's price is known; otherwise `cost_usd` is 0
+    pub priced: bool,
+}
+
+impl CostEstimate {
+    /// The enhanced workflow's LLM stages. Without a router the task's intent
+    /// may need classifying (`classify`); workers are merged at compile time.
+    pub fn enhanced(model: &str, task_tokens: usize, pipeline: &PipelineConfig, classify: bool) -> Self {
+        let mut estimate = Builder::new(task_tokens);
+        if pipeline.runs(Stage::Router) {
+            estimate.add(Stage::Router.name(), 1, ROUTER);
+        } else if classify {
+            estimate.add("classify", 1, CLASSIFY);
+        }
+        let workers = if pipeline.runs(Stage::Workers) {
+            pipeline.max_workers.unwrap_or(TYPICAL_WORKERS).min(TYPICAL_WORKERS)
+        } else {
+            0
+        };
+        estimate.add(Stage::Workers.name(), workers, WORKER);
+        if pipeline.runs(Stage::Questions) {
+            let questions = pipeline.max_questions.unwrap_or(TYPICAL_QUESTIONS).min(TYPICAL_QUESTIONS);
+            estimate.add(Stage::Questions.name(), 1, QUESTIONS);
+            estimate.add(Stage::Questions.name(), questions, ANSWER);
+        }
+        if pipeline.runs(Stage::Audit) {
+            estimate.add(Stage::Audit.name(), 1, AUDIT);
+        }
+        if workers > 0 {
+            estimate.add(Stage::Compile.name(), 1, call(MERGE.prompt + workers * MERGE_PER_WORKER, MERGE.completion));
+        }
+        estimate.finish(model)
+    }
+
+    /// The agent workflow: signature detection, every decision the budget
+    /// allows, and the implementation plan, sampled `plan_samples` times
+    pub fn agent(model: &str, task_tokens: usize, budget: &AgentBudget, plan_samples: usize) -> Self {
+        let mut estimate = Builder::new(task_tokens);
+        estimate.add(Stage::Signature.name(), 1, SIGNATURE);
+        let mut spent = 0;
+        for step in 0..budget.max_iterations {
+            let decide = call(DECIDE.prompt + step * DECIDE_GROWTH, DECIDE.completion);
+            let tokens = decide.prompt + task_tokens + decide.completion;
+            if budget.max_tokens.is_some_and(|max| spent + tokens > max) {
+                break;
+            }
+            spent += tokens;
+            estimate.add("agent", 1, decide);
+        }
+        estimate.add(Stage::Compile.name(), plan_samples.max(1), PLAN);
+        estimate.finish(model)
+    }
+
+    /// Whether the estimate is over `max_cost` USD. An unpriced model never is.
+    pub fn exceeds(&self, max_cost: f64) -> bool {
+        self.priced && self.cost_usd > max_cost
+    }
+}
+
+/// Collects calls by stage, keeping stages in the order they were first added
+struct Builder {
+    task_tokens: usize,
+    stages: Vec<StageEstimate>,
+}
+
+impl Builder {
+    fn new(task_tokens: usize) -> Self {
+        Self { task_tokens, stages: Vec::new() }
+    }
+
+    fn add(&mut self, stage: &'static str, calls: usize, size: CallSize) {
+        if calls == 0 {
+            return;
+        }
+        let index = match self.stages.iter().position(|s| s.stage == stage) {
+            Some(index) => index,
+            None => {
+                self.stages.push(StageEstimate { stage, calls: 0, prompt_tokens: 0, completion_tokens: 0 });
+                self.stages.len() - 1
+            }
+        };
+        let entry = &mut self.stages[index];
+        entry.calls += calls;
+        entry.prompt_tokens += calls * (size.prompt + self.task_tokens);
+        entry.completion_tokens += calls * size.completion;
+    }
+
+    fn finish(self, model: &str) -> CostEstimate {
+        let calls = self.stages.iter().map(|s| s.calls).sum();
+        let prompt_tokens = self.stages.iter().map(|s| s.prompt_tokens).sum();
+        let completion_tokens = self.stages.iter().map(|s| s.completion_tokens).sum();
+        CostEstimate {
+            model: model.to_string(),
+            stages: self.stages,
+            calls,
+            prompt_tokens,
+            completion_tokens,
+            cost_usd: miow_llm::estimate_cost(model, prompt_tokens, completion_tokens),
+            priced: miow_llm::estimate_cost(model, 1_000_000, 1_000_000) > 0.0,
+        }
+    }
+}
+
+/// A run stopped before it started because its estimate was over `--max-cost`
+#[derive(Debug)]
+pub struct CostExceeded {
+    pub estimated: f64,
+    pub limit: f64,
+}
+
+impl std::fmt::Display for CostExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Estimated cost ~${:.4} is over --max-cost ${:.4}; raise the limit, or pass --over-cost deterministic to build the prompt without the LLM",
+            self.estimated, self.limit
+        )
+    }
+}
+
+impl std::error::Error for CostExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enhanced_estimate_follows_the_stages() {
+        let pipeline = PipelineConfig::default();
+        let estimate = CostEstimate::enhanced("gpt-4o", 20, &pipeline, true);
+        let stages: Vec<&str> = estimate.stages.iter().map(|s| s.stage).collect();
+        assert_eq!(stages, vec!["router", "workers", "questions", "audit", "compile"]);
+        assert_eq!(estimate.calls, 1 + 3 + 1 + 4 + 1 + 1);
+        assert!(estimate.priced);
+        assert!(estimate.exceeds(0.001));
+        assert!(!estimate.exceeds(1.0));
+
+        let pipeline: PipelineConfig = toml::from_str("router = false\nquestions = false\nauditor = false").unwrap();
+        let estimate = CostEstimate::enhanced("gpt-4o", 20, &pipeline, true);
+        assert_eq!(estimate.calls, 1);
+        assert_eq!(estimate.stages[0].stage, "classify");
+        assert_eq!(estimate.stages[0].prompt_tokens, CLASSIFY.prompt + 20);
+    }
+
+    #[test]
+    fn test_agent_estimate_respects_the_budget() {
+        let budget = AgentBudget { max_iterations: 5, ..AgentBudget::default() };
+        let estimate = CostEstimate::agent("some-local-model", 0, &budget, 1);
+        assert_eq!(estimate.calls, 1 + 5 + 1);
+        // Later decisions carry more context
+        let agent = estimate.stages.iter().find(|s| s.stage == "agent").unwrap();
+        assert_eq!(agent.prompt_tokens, 5 * DECIDE.prompt + (1 + 2 + 3 + 4) * DECIDE_GROWTH);
+        // Without a price nothing is over the limit
+        assert!(!estimate.priced);
+        assert!(!estimate.exceeds(0.0));
+
+        let capped = AgentBudget { max_tokens: Some(2 * (DECIDE.prompt + DECIDE.completion) + DECIDE_GROWTH), ..budget };
+        assert_eq!(CostEstimate::agent("gpt-4o", 0, &capped, 1).calls, 1 + 2 + 1);
+
+        // Voting on the plan pays for every sample
+        let voted = CostEstimate::agent("gpt-4o", 0, &capped, 3);
+        assert_eq!(voted.calls, 1 + 2 + 3);
+    }
+}
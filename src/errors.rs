@@ -0,0 +1,123 @@
+//! What went wrong, in terms callers can act on: the crates return their own
+//! error enums inside `anyhow::Error`, and this finds the first one in the
+//! chain to pick `serve`'s HTTP status and the CLI's exit code
+
+use crate::cost::CostExceeded;
+use miow_agent::AgentError;
+use miow_graph::GraphError;
+use miow_llm::{Cancelled, LlmError};
+use miow_vector::VectorError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The project has no index yet
+    NotIndexed,
+    /// A saved run or another named thing doesn't exist
+    NotFound,
+    /// The request can't work as given
+    InvalidInput,
+    /// No LLM key is set, or the provider refused it
+    LlmCredentials,
+    /// The LLM provider's rate limit or quota ran out
+    LlmQuotaExceeded,
+    /// The LLM provider answered with another error
+    LlmError,
+    /// Qdrant or the LLM provider is down or not answering
+    Unavailable,
+    /// Another process holds the knowledge graph
+    Busy,
+    /// The client left or the server is stopping
+    Cancelled,
+    /// The run's estimated LLM cost is over its limit
+    OverBudget,
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn of(error: &anyhow::Error) -> Self {
+        error.chain().find_map(Self::of_cause).unwrap_or(ErrorKind::Internal)
+    }
+
+    fn of_cause(cause: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        if let Some(error) = cause.downcast_ref::<GraphError>() {
+            return Some(match error {
+                GraphError::NotIndexed(_) => ErrorKind::NotIndexed,
+                GraphError::Busy(_) => ErrorKind::Busy,
+                GraphError::Database(_) => ErrorKind::Internal,
+            });
+        }
+        if let Some(error) = cause.downcast_ref::<LlmError>() {
+            return Some(match error {
+                LlmError::MissingCredentials(_) | LlmError::Unauthorized { .. } => ErrorKind::LlmCredentials,
+                LlmError::QuotaExceeded { .. } => ErrorKind::LlmQuotaExceeded,
+                LlmError::Api { .. } => ErrorKind::LlmError,
+                LlmError::Unavailable { .. } | LlmError::Unreachable { .. } | LlmError::Timeout { .. } => {
+                    ErrorKind::Unavailable
+                }
+            });
+        }
+        if let Some(error) = cause.downcast_ref::<VectorError>() {
+            return Some(match error {
+                VectorError::Unavailable { .. } => ErrorKind::Unavailable,
+                VectorError::Request { status, .. } if status.is_server_error() => ErrorKind::Unavailable,
+                VectorError::Request { .. } => ErrorKind::Internal,
+            });
+        }
+        if let Some(error) = cause.downcast_ref::<AgentError>() {
+            return Some(match error {
+                AgentError::Cancelled => ErrorKind::Cancelled,
+                AgentError::NobodyToAsk(_) | AgentError::InvalidRunId(_) => ErrorKind::InvalidInput,
+                AgentError::RunNotFound(_) => ErrorKind::NotFound,
+            });
+        }
+        if cause.downcast_ref::<CostExceeded>().is_some() {
+            return Some(ErrorKind::OverBudget);
+        }
+        cause.downcast_ref::<Cancelled>().map(|_| ErrorKind::Cancelled)
+    }
+
+    /// Stable name for clients, sent as `code` next to the message
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorKind::NotIndexed => "not_indexed",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::InvalidInput => "invalid_input",
+            ErrorKind::LlmCredentials => "llm_credentials",
+            ErrorKind::LlmQuotaExceeded => "llm_quota_exceeded",
+            ErrorKind::LlmError => "llm_error",
+            ErrorKind::Unavailable => "unavailable",
+            ErrorKind::Busy => "busy",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::OverBudget => "over_budget",
+            ErrorKind::Internal => "internal",
+        }
+    }
+
+    /// Process exit code, following sysexits.h where one fits
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::NotIndexed | ErrorKind::NotFound => 66,
+            ErrorKind::InvalidInput => 65,
+            ErrorKind::LlmCredentials => 78,
+            ErrorKind::LlmQuotaExceeded | ErrorKind::Busy => 75,
+            ErrorKind::LlmError => 76,
+            ErrorKind::Unavailable => 69,
+            ErrorKind::Cancelled => 130,
+            ErrorKind::OverBudget => 77,
+            ErrorKind::Internal => 1,
+        }
+    }
+
+    #[cfg(feature = "web")]
+    pub fn status(self) -> axum::http::StatusCode {
+        use axum::http::StatusCode;
+        match self {
+            ErrorKind::NotIndexed | ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::InvalidInput | ErrorKind::OverBudget => StatusCode::BAD_REQUEST,
+            ErrorKind::LlmCredentials | ErrorKind::LlmError => StatusCode::BAD_GATEWAY,
+            ErrorKind::LlmQuotaExceeded => StatusCode::TOO_MANY_REQUESTS,
+            ErrorKind::Unavailable | ErrorKind::Busy | ErrorKind::Cancelled => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
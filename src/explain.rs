@@ -0,0 +1,158 @@
+//! Why each item is in the prompt, for debugging bad retrieval. Gathering notes
+//! what found each item; once the context is final, every item that made it in
+//! is matched to its note and scored against the task's keywords and the graph.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// What found an item
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Provenance {
+    /// e.g. `graph search`, `vector search`, `worker` or the agent tool that returned it
+    pub found_by: String,
+    /// The search query or tool argument that matched it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// Similarity from the vector store, when it came from there
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector_score: Option<f32>,
+    /// Router worker that returned it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub worker: Option<String>,
+    /// Why the agent or the router wanted it, in its words
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl Provenance {
+    pub fn new(found_by: impl Into<String>) -> Self {
+        Self { found_by: found_by.into(), ..Default::default() }
+    }
+
+    pub fn query(mut self, query: impl Into<String>) -> Self {
+        self.query = Some(query.into());
+        self
+    }
+
+    pub fn vector_score(mut self, score: f32) -> Self {
+        self.vector_score = Some(score);
+        self
+    }
+
+    pub fn worker(mut self, worker: impl Into<String>) -> Self {
+        self.worker = Some(worker.into());
+        self
+    }
+
+    pub fn reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+/// An item of the final context and why it's there
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemExplanation {
+    pub item: String,
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+    #[serde(flatten)]
+    pub provenance: Provenance,
+    /// Task keywords found in its name, path or content
+    pub keyword_hits: Vec<String>,
+    /// How many symbols in the knowledge graph refer to it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub centrality: Option<usize>,
+}
+
+/// Where one run's gathered items came from, and the report built from it.
+/// Clones share the notes.
+#[derive(Debug, Clone, Default)]
+pub struct ExplainLog {
+    notes: Arc<Mutex<HashMap<(String, String), Provenance>>>,
+    report: Arc<Mutex<Vec<ItemExplanation>>>,
+}
+
+impl ExplainLog {
+    pub fn clear(&self) {
+        self.notes.lock().unwrap().clear();
+        self.report.lock().unwrap().clear();
+    }
+
+    /// Note what found `name` in `file_path`. An item found more than once keeps
+    /// its first note, with the best vector score of any.
+    pub fn note(&self, name: &str, file_path: &str, provenance: Provenance) {
+        let mut notes = self.notes.lock().unwrap();
+        match notes.get_mut(&(file_path.to_string(), name.to_string())) {
+            Some(noted) => {
+                if let Some(score) = provenance.vector_score {
+                    noted.vector_score = Some(noted.vector_score.map_or(score, |s| s.max(score)));
+                }
+                if noted.query.is_none() {
+                    noted.query = provenance.query;
+                }
+            }
+            None => {
+                notes.insert((file_path.to_string(), name.to_string()), provenance);
+            }
+        }
+    }
+
+    /// The note for `name`; any file's when `file_path` isn't known
+    pub fn lookup(&self, name: &str, file_path: Option<&str>) -> Option<Provenance> {
+        let notes = self.notes.lock().unwrap();
+        match file_path {
+            Some(path) => notes.get(&(path.to_string(), name.to_string())).cloned(),
+            None => notes.iter().find(|((_, noted), _)| noted == name).map(|(_, p)| p.clone()),
+        }
+    }
+
+    pub fn set_report(&self, items: Vec<ItemExplanation>) {
+        *self.report.lock().unwrap() = items;
+    }
+
+    pub fn report(&self) -> Vec<ItemExplanation> {
+        self.report.lock().unwrap().clone()
+    }
+}
+
+/// Which of `keywords` appear in any of `texts`, ignoring case
+pub fn keyword_hits(keywords: &[String], texts: &[&str]) -> Vec<String> {
+    let texts: Vec<String> = texts.iter().map(|t| t.to_lowercase()).collect();
+    let mut hits: Vec<String> = Vec::new();
+    for keyword in keywords {
+        let needle = keyword.trim().to_lowercase();
+        if needle.is_empty() || hits.iter().any(|hit| hit.to_lowercase() == needle) {
+            continue;
+        }
+        if texts.iter().any(|text| text.contains(&needle)) {
+            hits.push(keyword.trim().to_string());
+        }
+    }
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_note_wins_with_the_best_vector_score() {
+        let log = ExplainLog::default();
+        log.note("LoginForm", "src/LoginForm.tsx", Provenance::new("graph search").query("login"));
+        log.note("LoginForm", "src/LoginForm.tsx", Provenance::new("vector search").query("form").vector_score(0.82));
+        log.note("LoginForm", "src/LoginForm.tsx", Provenance::new("vector search").vector_score(0.4));
+
+        let noted = log.lookup("LoginForm", Some("src/LoginForm.tsx")).unwrap();
+        assert_eq!(noted.found_by, "graph search");
+        assert_eq!(noted.query.as_deref(), Some("login"));
+        assert_eq!(noted.vector_score, Some(0.82));
+        assert!(log.lookup("LoginForm", None).is_some());
+        assert!(log.lookup("LoginForm", Some("src/Other.tsx")).is_none());
+
+        let keywords = vec!["Login".to_string(), "login".to_string(), "password".to_string()];
+        assert_eq!(keyword_hits(&keywords, &["LoginForm", "src/LoginForm.tsx"]), vec!["Login".to_string()]);
+    }
+}
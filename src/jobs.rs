@@ -0,0 +1,316 @@
+//! Background index jobs for the web server: one worker runs queued jobs in
+//! order while clients poll their status or follow their progress
+
+use miow_agent::unix_now;
+use miow_core::IndexProgress;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+
+/// Finished jobs kept for status requests; older ones are forgotten
+const KEPT_FINISHED_JOBS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl JobState {
+    pub fn is_active(self) -> bool {
+        matches!(self, JobState::Queued | JobState::Running)
+    }
+}
+
+/// One index run as clients see it
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexJob {
+    pub id: String,
+    pub codebase_path: PathBuf,
+    /// Canonical `codebase_path`, so one project isn't indexed twice at once
+    #[serde(skip)]
+    key: PathBuf,
+    /// Only files changed since the last index are parsed
+    pub incremental: bool,
+    pub state: JobState,
+    /// Unix times
+    pub queued_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    /// Files to parse, once the walk is done
+    pub files_total: Option<usize>,
+    pub files_parsed: usize,
+    /// The latest progress event
+    pub progress: Option<IndexProgress>,
+    /// Index statistics once completed
+    pub report: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+static NEXT_JOB: AtomicU64 = AtomicU64::new(1);
+
+/// Queue of index jobs shared by the web handlers
+#[derive(Clone)]
+pub struct IndexJobs {
+    jobs: Arc<Mutex<Jobs>>,
+    queue: mpsc::UnboundedSender<String>,
+}
+
+#[derive(Default)]
+struct Jobs {
+    by_id: HashMap<String, watch::Sender<IndexJob>>,
+    /// Ids of finished jobs, oldest first
+    finished: VecDeque<String>,
+}
+
+impl IndexJobs {
+    /// Start the worker; it runs until the last handle is dropped
+    pub fn start() -> Self {
+        let (queue, pending) = mpsc::unbounded_channel();
+        let jobs = Self { jobs: Arc::new(Mutex::new(Jobs::default())), queue };
+        tokio::spawn(work(jobs.jobs.clone(), pending));
+        jobs
+    }
+
+    /// Queue an index of `codebase_path`, or return the job already queued or
+    /// running for it
+    pub fn enqueue(&self, codebase_path: &Path, incremental: bool) -> IndexJob {
+        let key = canonical(codebase_path);
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = active_in(&jobs, &key) {
+            return job;
+        }
+
+        let seq = NEXT_JOB.fetch_add(1, Ordering::Relaxed);
+        let nonce = std::hash::BuildHasher::hash_one(&std::collections::hash_map::RandomState::new(), seq);
+        let job = IndexJob {
+            id: format!("{}-{:016x}", seq, nonce),
+            codebase_path: codebase_path.to_path_buf(),
+            key,
+            incremental,
+            state: JobState::Queued,
+            queued_at: unix_now(),
+            started_at: None,
+            finished_at: None,
+            files_total: None,
+            files_parsed: 0,
+            progress: None,
+            report: None,
+            error: None,
+        };
+        jobs.by_id.insert(job.id.clone(), watch::channel(job.clone()).0);
+        let _ = self.queue.send(job.id.clone());
+        job
+    }
+
+    pub fn get(&self, id: &str) -> Option<IndexJob> {
+        self.jobs.lock().unwrap().by_id.get(id).map(|job| job.borrow().clone())
+    }
+
+    /// Updates to a job, starting with its current state
+    pub fn subscribe(&self, id: &str) -> Option<watch::Receiver<IndexJob>> {
+        self.jobs.lock().unwrap().by_id.get(id).map(|job| job.subscribe())
+    }
+
+    /// The job queued or running for `codebase_path`, if any
+    pub fn active(&self, codebase_path: &Path) -> Option<IndexJob> {
+        active_in(&self.jobs.lock().unwrap(), &canonical(codebase_path))
+    }
+
+    /// Fail the queued jobs and wait up to `timeout` for the running one, so
+    /// the server doesn't exit halfway through writing an index. Returns the
+    /// job still running when the time ran out.
+    pub async fn shutdown(&self, timeout: Duration) -> Option<IndexJob> {
+        let mut running = None;
+        for job in self.jobs.lock().unwrap().by_id.values() {
+            match job.borrow().state {
+                JobState::Queued => {}
+                JobState::Running => {
+                    running = Some(job.subscribe());
+                    continue;
+                }
+                _ => continue,
+            }
+            job.send_modify(|job| {
+                job.state = JobState::Failed;
+                job.finished_at = Some(unix_now());
+                job.error = Some("The server shut down before the job started".to_string());
+            });
+        }
+
+        let mut running = running?;
+        let finished = tokio::time::timeout(timeout, running.wait_for(|job| !job.state.is_active())).await.is_ok();
+        (!finished).then(|| running.borrow().clone())
+    }
+}
+
+/// Run queued jobs one at a time
+async fn work(jobs: Arc<Mutex<Jobs>>, mut pending: mpsc::UnboundedReceiver<String>) {
+    while let Some(id) = pending.recv().await {
+        let Some(job) = jobs.lock().unwrap().by_id.get(&id).cloned() else { continue };
+        // Failed by `shutdown` while it waited
+        if job.borrow().state != JobState::Queued {
+            continue;
+        }
+        job.send_modify(|job| {
+            job.state = JobState::Running;
+            job.started_at = Some(unix_now());
+        });
+        let (path, incremental) = {
+            let job = job.borrow();
+            (job.codebase_path.clone(), job.incremental)
+        };
+
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<IndexProgress>();
+        let updates = job.clone();
+        let forwarder = tokio::spawn(async move {
+            while let Some(progress) = progress_rx.recv().await {
+                updates.send_modify(|job| {
+                    match &progress {
+                        IndexProgress::Discovered { files } => job.files_total = Some(*files),
+                        IndexProgress::Parsed { done, .. } => job.files_parsed = *done,
+                        _ => {}
+                    }
+                    job.progress = Some(progress);
+                });
+            }
+        });
+
+        let db_path = crate::project_db_path(&path);
+        let mode = if incremental && db_path.exists() { crate::IndexMode::Incremental } else { crate::IndexMode::Full };
+        let mode_label = if matches!(mode, crate::IndexMode::Incremental) { "incremental" } else { "full" };
+        let started = Instant::now();
+        let result = match db_path.parent().map(std::fs::create_dir_all).transpose() {
+            Ok(_) => crate::run_index(path, db_path, mode, Some(progress_tx)).await,
+            Err(e) => {
+                // The forwarder only ends once the sender is gone
+                drop(progress_tx);
+                Err(anyhow::anyhow!("Failed to create .miow directory: {}", e))
+            }
+        };
+        let _ = forwarder.await;
+
+        let outcome = if result.is_ok() { "completed" } else { "failed" };
+        metrics::counter!("miow_index_jobs_total", "mode" => mode_label, "outcome" => outcome).increment(1);
+        metrics::histogram!("miow_index_duration_seconds", "mode" => mode_label).record(started.elapsed().as_secs_f64());
+
+        job.send_modify(|job| {
+            job.finished_at = Some(unix_now());
+            match &result {
+                Ok(report) => {
+                    job.state = JobState::Completed;
+                    job.report = Some(crate::index_report_json(report));
+                }
+                Err(e) => {
+                    job.state = JobState::Failed;
+                    job.error = Some(e.to_string());
+                }
+            }
+        });
+
+        let mut kept = jobs.lock().unwrap();
+        kept.finished.push_back(id);
+        while kept.finished.len() > KEPT_FINISHED_JOBS {
+            if let Some(old) = kept.finished.pop_front() {
+                kept.by_id.remove(&old);
+            }
+        }
+    }
+}
+
+fn active_in(jobs: &Jobs, key: &Path) -> Option<IndexJob> {
+    jobs.by_id
+        .values()
+        .map(|job| job.borrow().clone())
+        .find(|job| job.state.is_active() && job.key == key)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn finished(jobs: &IndexJobs, id: &str) -> IndexJob {
+        let mut updates = jobs.subscribe(id).unwrap();
+        let job = tokio::time::timeout(Duration::from_secs(60), updates.wait_for(|job| !job.state.is_active()))
+            .await
+            .expect("index job didn't finish")
+            .unwrap()
+            .clone();
+        job
+    }
+
+    #[tokio::test]
+    async fn test_job_runs_with_progress_and_frees_the_project() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join("lib.rs"), "pub fn add(a: i32, b: i32) -> i32 { a + b }\n").unwrap();
+        std::fs::write(root.join("util.rs"), "pub fn double(x: i32) -> i32 { x * 2 }\n").unwrap();
+
+        let jobs = IndexJobs::start();
+        let job = jobs.enqueue(root, false);
+        assert_eq!(job.state, JobState::Queued);
+        assert!(job.started_at.is_none() && job.report.is_none());
+        // The same project isn't queued twice, even by another spelling of its path
+        assert_eq!(jobs.enqueue(&root.join("."), true).id, job.id);
+        // While it's active the project's index can't be deleted
+        assert_eq!(jobs.active(root).map(|active| active.id), Some(job.id.clone()));
+
+        let done = finished(&jobs, &job.id).await;
+        assert_eq!(done.state, JobState::Completed, "{:?}", done.error);
+        assert_eq!(done.files_total, Some(2));
+        assert_eq!(done.files_parsed, 2);
+        assert!(done.progress.is_some());
+        assert!(done.report.is_some());
+        assert!(done.started_at.is_some() && done.finished_at >= done.started_at);
+        assert!(root.join(".miow").join("miow.db").exists());
+
+        assert_eq!(jobs.get(&job.id).unwrap().state, JobState::Completed);
+        assert!(jobs.active(root).is_none());
+        assert!(jobs.get("no-such-job").is_none());
+
+        // A finished project gets a new job
+        let again = jobs.enqueue(root, true);
+        assert_ne!(again.id, job.id);
+        assert!(again.incremental);
+        assert_eq!(finished(&jobs, &again.id).await.state, JobState::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_failed_job_reports_its_error() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        // The index can't go where a file already is
+        std::fs::write(temp_dir.path().join(".miow"), "").unwrap();
+
+        let jobs = IndexJobs::start();
+        let job = jobs.enqueue(temp_dir.path(), false);
+        let done = finished(&jobs, &job.id).await;
+        assert_eq!(done.state, JobState::Failed);
+        assert!(done.error.unwrap().contains(".miow"));
+        assert!(done.report.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_fails_queued_jobs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let jobs = IndexJobs::start();
+        let job = jobs.enqueue(temp_dir.path(), false);
+        // Nothing has run yet: the worker only gets its turn once this test yields
+        assert!(jobs.shutdown(Duration::from_secs(1)).await.is_none());
+
+        let failed = jobs.get(&job.id).unwrap();
+        assert_eq!(failed.state, JobState::Failed);
+        assert!(failed.error.unwrap().contains("shut down"));
+        assert!(jobs.active(temp_dir.path()).is_none());
+    }
+}
@@ -0,0 +1,305 @@
+//! `miow-context lsp`: a minimal language server answering from the knowledge
+//! graph, so any LSP-capable editor can use it. It serves workspace symbols
+//! (graph search by name), references (the reference table) and the
+//! `miow/generatePrompt` command. Files are read from disk, so unsaved edits
+//! and changes made since the last index are not seen.
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use lsp_types::{
+    ExecuteCommandParams, Location, Position, Range, ReferenceParams, SymbolInformation, SymbolKind, Url,
+    WorkspaceSymbolParams,
+};
+use miow_graph::{GraphError, KnowledgeGraph, SymbolSearchResult};
+use serde_json::{Value, json};
+
+use crate::rpc::{Connection, INVALID_PARAMS, METHOD_NOT_FOUND, Methods, RpcError, RpcMethods, RpcOptions, parse};
+
+/// The command and request that generate a prompt for a task
+pub const GENERATE_PROMPT: &str = "miow/generatePrompt";
+
+/// Symbols returned per `workspace/symbol` query
+const MAX_SYMBOLS: usize = 200;
+
+/// What `miow-context lsp` answers. Generating a prompt works as `generate`
+/// does in `rpc`, with `miow/approve` and `miow/answer` for the agent's requests.
+pub struct LspMethods {
+    rpc: RpcMethods,
+}
+
+impl LspMethods {
+    pub fn new(options: RpcOptions) -> Self {
+        Self { rpc: RpcMethods::new(options) }
+    }
+
+    fn graph(&self) -> anyhow::Result<KnowledgeGraph> {
+        let database = &self.rpc.options().database;
+        if !database.exists() {
+            return Err(GraphError::NotIndexed(database.clone()).into());
+        }
+        KnowledgeGraph::new(database)
+    }
+
+    fn uri(&self, file_path: &str) -> Option<Url> {
+        Url::from_file_path(self.rpc.options().root.join(file_path)).ok()
+    }
+
+    fn workspace_symbols(&self, params: WorkspaceSymbolParams) -> Result<Value, RpcError> {
+        let query = Some(params.query.as_str()).filter(|q| !q.is_empty());
+        let filters = crate::SearchFilters { kind: None, refs_to: None, file: None, owner: None };
+        let symbols: Vec<SymbolInformation> = crate::query_graph(&self.rpc.options().database, query, &filters, MAX_SYMBOLS)?
+            .iter()
+            .filter_map(|symbol| {
+                let location = Location::new(self.uri(&symbol.file_path)?, symbol_range(symbol));
+                #[allow(deprecated)]
+                Some(SymbolInformation {
+                    name: symbol.name.clone(),
+                    kind: symbol_kind(&symbol.kind),
+                    tags: None,
+                    deprecated: None,
+                    location,
+                    container_name: Some(symbol.file_path.clone()),
+                })
+            })
+            .collect();
+        Ok(json!(symbols))
+    }
+
+    /// Symbols referring to the name under the cursor, at each place they use it
+    fn references(&self, params: ReferenceParams) -> Result<Value, RpcError> {
+        let at = params.text_document_position;
+        let path = at
+            .text_document
+            .uri
+            .to_file_path()
+            .map_err(|_| RpcError::new(INVALID_PARAMS, format!("Not a file: {}", at.text_document.uri)))?;
+        let content = std::fs::read_to_string(&path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let Some(name) = content.lines().nth(at.position.line as usize).and_then(|line| word_at(line, at.position.character))
+        else {
+            return Ok(json!([]));
+        };
+
+        let graph = self.graph()?;
+        let mut symbols = graph.find_references_to(name)?;
+        if params.context.include_declaration {
+            symbols.extend(graph.find_symbols_by_name(name)?);
+        }
+        let mut locations: Vec<Location> = Vec::new();
+        for symbol in &symbols {
+            let Some(uri) = self.uri(&symbol.file_path) else { continue };
+            let uses = occurrences(&symbol.content, name);
+            if uses.is_empty() {
+                locations.push(Location::new(uri, symbol_range(symbol)));
+                continue;
+            }
+            for (line, column) in uses {
+                let start = Position::new(symbol.start_line.max(1) as u32 - 1 + line, column);
+                let end = Position::new(start.line, column + name.encode_utf16().count() as u32);
+                locations.push(Location::new(uri.clone(), Range::new(start, end)));
+            }
+        }
+        locations.sort_by(|a, b| (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start)));
+        locations.dedup();
+        Ok(json!(locations))
+    }
+}
+
+#[async_trait]
+impl Methods for LspMethods {
+    async fn call(&self, connection: &Connection, id: Option<&Value>, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "workspaceSymbolProvider": true,
+                    "referencesProvider": true,
+                    "executeCommandProvider": { "commands": [GENERATE_PROMPT] },
+                },
+                "serverInfo": { "name": "miow-context", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => Ok(Value::Null),
+            "workspace/symbol" => self.workspace_symbols(parse(params)?),
+            "textDocument/references" => self.references(parse(params)?),
+            "workspace/executeCommand" => {
+                let ExecuteCommandParams { command, arguments, .. } = parse(params)?;
+                if command != GENERATE_PROMPT {
+                    return Err(RpcError::new(INVALID_PARAMS, format!("Unknown command '{}'", command)));
+                }
+                // The task alone, or the same params as the request
+                let params = match arguments.into_iter().next() {
+                    Some(Value::String(task)) => json!({ "task": task }),
+                    Some(params) => params,
+                    None => return Err(RpcError::new(INVALID_PARAMS, "No task given")),
+                };
+                self.rpc.call(connection, id, "generate", params).await
+            }
+            GENERATE_PROMPT => self.rpc.call(connection, id, "generate", params).await,
+            "miow/approve" => self.rpc.call(connection, id, "approve", params).await,
+            "miow/answer" => self.rpc.call(connection, id, "answer", params).await,
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method '{}'", method))),
+        }
+    }
+}
+
+/// The lines a symbol spans, to the end of its last line
+fn symbol_range(symbol: &SymbolSearchResult) -> Range {
+    let start = symbol.start_line.max(1) as u32 - 1;
+    let end = symbol.end_line.max(symbol.start_line).max(1) as u32 - 1;
+    let last = symbol.content.lines().last().unwrap_or_default();
+    Range::new(Position::new(start, 0), Position::new(end, last.encode_utf16().count() as u32))
+}
+
+fn symbol_kind(kind: &str) -> SymbolKind {
+    match kind.to_lowercase().as_str() {
+        "function" | "component" | "hook" => SymbolKind::FUNCTION,
+        "method" => SymbolKind::METHOD,
+        "class" => SymbolKind::CLASS,
+        "interface" | "trait" | "type" => SymbolKind::INTERFACE,
+        "struct" => SymbolKind::STRUCT,
+        "enum" => SymbolKind::ENUM,
+        "constant" | "const" => SymbolKind::CONSTANT,
+        "module" | "mod" => SymbolKind::MODULE,
+        "field" => SymbolKind::FIELD,
+        "property" => SymbolKind::PROPERTY,
+        _ => SymbolKind::VARIABLE,
+    }
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// The identifier at `character`, counted in the UTF-16 columns LSP uses
+fn word_at(line: &str, character: u32) -> Option<&str> {
+    let mut column = 0;
+    let offset = line
+        .char_indices()
+        .find(|(_, c)| {
+            column += c.len_utf16() as u32;
+            column > character
+        })
+        .map(|(i, _)| i)?;
+    let start = line[..offset].rfind(|c| !is_ident(c)).map_or(0, |i| i + line[i..].chars().next().map_or(1, char::len_utf8));
+    let end = line[offset..].find(|c| !is_ident(c)).map_or(line.len(), |i| offset + i);
+    Some(&line[start..end]).filter(|word| !word.is_empty())
+}
+
+/// Line (from 0) and UTF-16 column of each whole-word `name` in `content`
+fn occurrences(content: &str, name: &str) -> Vec<(u32, u32)> {
+    content
+        .lines()
+        .enumerate()
+        .flat_map(|(n, line)| {
+            line.match_indices(name)
+                .filter(move |(i, _)| {
+                    let before = line[..*i].chars().next_back().is_none_or(|c| !is_ident(c));
+                    let after = line[i + name.len()..].chars().next().is_none_or(|c| !is_ident(c));
+                    before && after
+                })
+                .map(move |(i, _)| (n as u32, line[..i].encode_utf16().count() as u32))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{read_message, serve, write_message};
+    use tokio::io::BufReader;
+
+    #[tokio::test]
+    async fn test_symbols_and_references_come_from_the_graph() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let database = dir.join("miow.db");
+        let form = "export function LoginForm() {\n  const ok = validateEmail(email);\n  return ok && validateEmail(backup);\n}";
+        let validate = "export function validateEmail(email) {\n  return email.includes('@');\n}";
+        std::fs::write(dir.join("src/LoginForm.tsx"), form).unwrap();
+        let files: Vec<(String, miow_graph::ParsedFileData)> = [
+            ("src/LoginForm.tsx", "LoginForm", form, vec!["validateEmail"]),
+            ("src/validation.ts", "validateEmail", validate, vec![]),
+        ]
+        .into_iter()
+        .map(|(path, name, content, references)| {
+            let file = serde_json::from_value(json!({
+                "symbols": [{
+                    "name": name, "kind": "function", "start_line": 1, "end_line": content.lines().count(),
+                    "start_byte": 0, "end_byte": content.len(), "content": content, "metadata": "{}",
+                    "style_tags": null, "children": [], "references": references
+                }],
+                "imports": [], "design_tokens": [], "type_definitions": [], "constants": [], "schemas": [],
+                "language": "typescript"
+            }))
+            .unwrap();
+            (path.to_string(), file)
+        })
+        .collect();
+        KnowledgeGraph::new(&database).unwrap().insert_files(&files).unwrap();
+
+        let (mut client, server_io) = tokio::io::duplex(1 << 16);
+        let (server_in, server_out) = tokio::io::split(server_io);
+        let options = RpcOptions { root: dir.to_path_buf(), database, llm: Default::default(), assume_yes: false };
+        let server = tokio::spawn(serve(server_in, server_out, LspMethods::new(options)));
+
+        let uri = Url::from_file_path(dir.join("src/LoginForm.tsx")).unwrap();
+        for message in [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "capabilities": {} } }),
+            json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "workspace/symbol", "params": { "query": "Login" } }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/references", "params": {
+                "textDocument": { "uri": uri }, "position": { "line": 1, "character": 16 },
+                "context": { "includeDeclaration": true }
+            } }),
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "workspace/executeCommand", "params": { "command": "miow/unknown" } }),
+            json!({ "jsonrpc": "2.0", "id": 5, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ] {
+            write_message(&mut client, &message).await.unwrap();
+        }
+
+        let mut reader = BufReader::new(client);
+        let mut responses = std::collections::HashMap::new();
+        while let Some(body) = read_message(&mut reader).await.unwrap() {
+            let response: Value = serde_json::from_slice(&body).unwrap();
+            responses.insert(response["id"].to_string(), response);
+        }
+        server.await.unwrap().unwrap();
+
+        let capabilities = &responses["1"]["result"]["capabilities"];
+        assert_eq!(capabilities["executeCommandProvider"]["commands"][0], GENERATE_PROMPT);
+        let symbols = responses["2"]["result"].as_array().unwrap();
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0]["name"], "LoginForm");
+        assert_eq!(symbols[0]["location"]["range"]["end"]["line"], 3);
+
+        // Both calls in LoginForm, and the declaration in validation.ts
+        let found: Vec<(String, u64, u64)> = responses["3"]["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| {
+                let file = l["uri"].as_str().unwrap().rsplit('/').next().unwrap().to_string();
+                (file, l["range"]["start"]["line"].as_u64().unwrap(), l["range"]["start"]["character"].as_u64().unwrap())
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("LoginForm.tsx".to_string(), 1, 13),
+                ("LoginForm.tsx".to_string(), 2, 15),
+                ("validation.ts".to_string(), 0, 16),
+            ]
+        );
+        assert_eq!(responses["4"]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(responses["5"]["result"], Value::Null);
+    }
+
+    #[test]
+    fn test_word_at_counts_utf16_columns() {
+        assert_eq!(word_at("  const ok = validateEmail(x);", 16), Some("validateEmail"));
+        assert_eq!(word_at("  const ok = validateEmail(x);", 13), Some("validateEmail"));
+        assert_eq!(word_at("é = parse(x)", 5), Some("parse"));
+        assert_eq!(word_at("a + b", 2), None);
+        assert_eq!(word_at("short", 40), None);
+    }
+}
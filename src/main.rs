@@ -1,19 +1,96 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
 use colored::Colorize;
-use miow_core::index_codebase;
-use miow_graph::{DesignTokenData, ImportData, KnowledgeGraph, ParsedFileData, SymbolData};
-use miow_parsers::{parse_python, parse_rust, parse_typescript};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use miow_core::{CodebaseIndexer, IndexConfig, IndexProgress, LanguageRegistry, ProgressSender};
+use miow_graph::{GraphError, KnowledgeGraph};
 use std::path::PathBuf;
 use std::path::Path;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use tracing::Level;
-use tracing_subscriber;
-
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+mod auth;
+mod config;
+mod context_cache;
+mod cost;
+mod errors;
+mod explain;
+#[cfg(feature = "web")]
+mod jobs;
+mod lsp;
 mod orchestrator;
+#[cfg(feature = "otel")]
+mod otel;
+mod pipeline;
+mod progress;
+#[cfg(feature = "web")]
+mod rate_limit;
+mod rpc;
+#[cfg(feature = "web")]
+mod telemetry;
+#[cfg(feature = "ui")]
+mod ui;
+#[cfg(feature = "tui")]
+mod tui;
+mod watch;
+use config::MiowConfig;
 use orchestrator::MiowOrchestrator;
 
+/// Set by `--json`: results go to stdout as JSON, everything meant for people to stderr
+static JSON_OUTPUT: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+fn json_output() -> bool {
+    JSON_OUTPUT.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Set by `--jobs`: parser threads for indexing, 0 for one per core
+static INDEX_JOBS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Set by `--trust-project-languages`: load native grammars a project's languages file declares
+static TRUST_PROJECT_LANGUAGES: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Set by `--log-format json`: everything meant for people becomes a log event
+static JSON_LOGS: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+fn json_logs() -> bool {
+    JSON_LOGS.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// `println!` for human-readable output, which moves to stderr under `--json`
+/// and is logged through tracing under `--log-format json`
+macro_rules! say {
+    () => {
+        say!("")
+    };
+    ($($arg:tt)*) => {
+        if json_logs() {
+            if let Some(message) = log_message(&format!($($arg)*)) {
+                tracing::info!("{}", message);
+            }
+        } else if json_output() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// A line of human-readable output as a log message: without its leading
+/// emoji, bullets and indentation, or `None` for blank lines and rules
+fn log_message(line: &str) -> Option<&str> {
+    let message = line.trim_start_matches(|c: char| c.is_whitespace() || !(c.is_ascii() || c.is_alphanumeric())).trim_end();
+    (!message.is_empty()).then_some(message)
+}
+
+/// Print a command's result for `--json`
+fn print_json(value: &serde_json::Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
 // Web API types
 #[cfg(feature = "web")]
 #[derive(Deserialize)]
@@ -22,6 +99,19 @@ struct GenerateRequest {
     codebase_path: String,
     /// Natural language task or question
     user_prompt: String,
+    /// Agent limits for this request; defaults to the project's miow.toml
+    #[serde(default)]
+    budget: Option<miow_agent::AgentBudget>,
+    /// Prompt format: markdown (default), xml, chatml or json
+    #[serde(default)]
+    format: miow_prompt::PromptFormat,
+    /// Session to ask a follow-up in, from an earlier response's `session_id`;
+    /// the run starts from the context and plan the session gathered so far
+    #[serde(default)]
+    session_id: Option<String>,
+    /// Also return why each context item was included
+    #[serde(default)]
+    explain: bool,
 }
 
 /// Shared application state for the web server
@@ -29,7 +119,251 @@ struct GenerateRequest {
 #[derive(Clone)]
 struct AppState {
     /// Optional shared LLM client (Gemini) reused across requests
-    llm: Option<std::sync::Arc<dyn miow_llm::LLMProvider>>,
+    llm: Option<std::sync::Arc<miow_llm::UsageTrackingProvider>>,
+    /// Token usage accumulated across all requests served by this process
+    usage: std::sync::Arc<miow_llm::UsageTracker>,
+    /// Agent actions waiting for a streaming client to approve them
+    approvals: miow_agent::ChannelApprover,
+    /// Index runs, done in the background instead of inside requests
+    index_jobs: jobs::IndexJobs,
+    /// Bearer tokens requests must carry, when any are configured
+    auth: auth::Authenticator,
+    /// Renders the Prometheus page for `/metrics`
+    metrics: metrics_exporter_prometheus::PrometheusHandle,
+    /// Largest generate request, also applied to WebSocket messages
+    max_generate_body: usize,
+    /// Ceiling on every request's agent iterations
+    max_agent_iterations: usize,
+    /// Cancelled on SIGINT/SIGTERM; agent runs stop at their next step and keep their checkpoint
+    shutdown: tokio_util::sync::CancellationToken,
+    /// Runs driven from spawned tasks (streams, WebSockets), waited for at shutdown
+    tasks: tokio_util::task::TaskTracker,
+    /// Contexts compiled by earlier requests, reused for near-duplicate tasks
+    context_cache: context_cache::ContextCache,
+    /// Other origins whose pages may call the API
+    allowed_origins: std::sync::Arc<Vec<String>>,
+}
+
+#[cfg(feature = "web")]
+#[derive(Deserialize)]
+struct IndexRequest {
+    codebase_path: String,
+    /// Only reparse files changed since the last index; a project without
+    /// one is always indexed in full
+    #[serde(default)]
+    incremental: bool,
+}
+
+/// Project a GET or DELETE request is about
+#[cfg(feature = "web")]
+#[derive(Deserialize)]
+struct ProjectQuery {
+    codebase_path: String,
+}
+
+/// Symbols shown per page by `/api/symbols`
+#[cfg(feature = "web")]
+const SYMBOLS_PER_PAGE: usize = 50;
+
+/// Runs listed per page by `/api/runs`
+#[cfg(feature = "web")]
+const RUNS_PER_PAGE: usize = 50;
+
+#[cfg(feature = "web")]
+#[derive(Deserialize)]
+struct RunsQuery {
+    codebase_path: String,
+    /// 1-based
+    page: Option<usize>,
+    /// Only runs with this status: `complete`, `failed` or `partial`
+    status: Option<String>,
+}
+
+/// A saved run as `/api/runs` lists it, without its context and prompt
+#[cfg(feature = "web")]
+#[derive(Serialize)]
+struct RunSummary {
+    run_id: String,
+    task: String,
+    /// `complete`, `failed` or `partial` (running or interrupted)
+    status: &'static str,
+    started_at: u64,
+    updated_at: u64,
+    duration_ms: Option<u64>,
+    selected_files: usize,
+    total_tokens: Option<usize>,
+    rerun_of: Option<String>,
+    session_id: String,
+    follow_up_of: Option<String>,
+}
+
+#[cfg(feature = "web")]
+#[derive(Serialize)]
+struct RunPageResponse {
+    success: bool,
+    runs: Vec<RunSummary>,
+    page: usize,
+    per_page: usize,
+    has_more: bool,
+}
+
+/// Whether a run's context was useful, for `/api/runs/:id/feedback`
+#[cfg(feature = "web")]
+#[derive(Deserialize)]
+struct FeedbackRequest {
+    codebase_path: String,
+    useful: bool,
+    /// Only the context from these files or directories; all of it when empty
+    #[serde(default)]
+    files: Vec<String>,
+}
+
+#[cfg(feature = "web")]
+#[derive(Serialize)]
+struct FeedbackResponse {
+    success: bool,
+    /// The symbols and files the feedback was recorded for
+    items: Vec<miow_agent::PromptItem>,
+}
+
+#[cfg(feature = "web")]
+#[derive(Deserialize)]
+struct SymbolsQuery {
+    codebase_path: String,
+    /// Part of the symbol name; empty lists every symbol
+    #[serde(default)]
+    query: String,
+    kind: Option<String>,
+    /// 1-based
+    page: Option<usize>,
+}
+
+#[cfg(feature = "web")]
+#[derive(Serialize)]
+struct SymbolPageResponse {
+    success: bool,
+    symbols: Vec<miow_graph::SymbolSearchResult>,
+    page: usize,
+    per_page: usize,
+    has_more: bool,
+}
+
+#[cfg(feature = "web")]
+#[derive(Serialize)]
+struct SymbolDetailResponse {
+    success: bool,
+    symbol: miow_graph::SymbolSearchResult,
+    /// Names the symbol refers to
+    references: Vec<String>,
+    /// Symbols that refer to it by name
+    referenced_by: Vec<miow_graph::SymbolSearchResult>,
+}
+
+#[cfg(feature = "web")]
+#[derive(Serialize)]
+struct SymbolsResponse {
+    success: bool,
+    symbols: Vec<miow_graph::SymbolSearchResult>,
+}
+
+/// Error answer of the web API
+#[cfg(feature = "web")]
+enum ApiError {
+    /// The project's index is still being built: 202 with the job to poll at
+    /// `/api/index/status/:job_id`
+    Indexing(Box<jobs::IndexJob>),
+    /// No valid bearer token: 401 asking for one
+    Unauthorized(String),
+    /// `{"success": false, "error": message}` with the status
+    Status(StatusCode, String),
+    /// A failure from the crates: the status for its kind, and its `code`
+    Failed(errors::ErrorKind, String),
+}
+
+#[cfg(feature = "web")]
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        ApiError::Failed(errors::ErrorKind::of(&error), format!("{:#}", error))
+    }
+}
+
+#[cfg(feature = "web")]
+impl axum::response::IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            ApiError::Indexing(job) => {
+                let body = serde_json::json!({
+                    "success": false,
+                    "status": "indexing",
+                    "error": format!(
+                        "Indexing {} is in progress; retry once job {} completes",
+                        job.codebase_path.display(),
+                        job.id
+                    ),
+                    "job": job,
+                });
+                (StatusCode::ACCEPTED, [(axum::http::header::RETRY_AFTER, "5")], Json(body)).into_response()
+            }
+            ApiError::Unauthorized(message) => (
+                StatusCode::UNAUTHORIZED,
+                [(axum::http::header::WWW_AUTHENTICATE, "Bearer")],
+                Json(serde_json::json!({ "success": false, "error": message })),
+            )
+                .into_response(),
+            ApiError::Status(status, message) => {
+                (status, Json(serde_json::json!({ "success": false, "error": message }))).into_response()
+            }
+            ApiError::Failed(kind, message) => (
+                kind.status(),
+                Json(serde_json::json!({ "success": false, "error": message, "code": kind.code() })),
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// Answer to an agent's `ApprovalRequired` event
+#[cfg(feature = "web")]
+#[derive(Deserialize)]
+struct ApprovalDecision {
+    approved: bool,
+}
+
+/// What a `/ws/generate` client sends: `generate` first, then answers,
+/// approvals or `cancel` while the agent runs
+#[cfg(feature = "web")]
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WsClientMessage {
+    Generate(GenerateRequest),
+    Approve { id: String, approved: bool },
+    Answer { id: String, answer: String },
+    Cancel,
+}
+
+/// What `/ws/generate` sends back; the socket closes after `result` or `error`
+#[cfg(feature = "web")]
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WsServerMessage {
+    Status { message: String },
+    /// The project is being indexed; generate again once the job completes
+    Indexing { job: jobs::IndexJob },
+    /// The run is saved under this id, for `/api/runs/:id`, in this session
+    Run { run_id: String, session_id: String },
+    Agent { event: miow_agent::autonomous::AgentEvent },
+    Result {
+        prompt: String,
+        /// Why each context item was included, when the request asked
+        #[serde(skip_serializing_if = "Option::is_none")]
+        explanation: Option<Vec<explain::ItemExplanation>>,
+    },
+    Error {
+        message: String,
+        /// The failure's kind for failed runs, e.g. `llm_quota_exceeded`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        code: Option<&'static str>,
+    },
 }
 
 #[cfg(feature = "web")]
@@ -38,6 +372,52 @@ struct GenerateResponse {
     success: bool,
     result: Option<String>,
     error: Option<String>,
+    /// Saved run, for `/api/runs/:id`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_id: Option<String>,
+    /// Session the run belongs to, for follow-up requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+    /// Why each context item was included, when the request asked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explanation: Option<Vec<explain::ItemExplanation>>,
+    /// What kind of failure `error` is, e.g. `llm_quota_exceeded`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+}
+
+#[cfg(feature = "web")]
+impl GenerateResponse {
+    /// The response for a finished run, with the status for its failure if any
+    fn from_result(
+        result: Result<String>,
+        run: &WebRun,
+        explanation: Option<Vec<explain::ItemExplanation>>,
+    ) -> (StatusCode, Json<Self>) {
+        let (run_id, session_id) = (run.run_id(), run.session_id.clone());
+        match result {
+            Ok(result) => {
+                say!("✅ Request completed successfully");
+                let response =
+                    Self { success: true, result: Some(result), error: None, run_id, session_id, explanation, code: None };
+                (StatusCode::OK, Json(response))
+            }
+            Err(e) => {
+                say!("❌ Request failed: {:#}", e);
+                let kind = errors::ErrorKind::of(&e);
+                let response = Self {
+                    success: false,
+                    result: None,
+                    error: Some(format!("{:#}", e)),
+                    run_id,
+                    session_id,
+                    explanation: None,
+                    code: Some(kind.code()),
+                };
+                (kind.status(), Json(response))
+            }
+        }
+    }
 }
 
 #[cfg(feature = "web")]
@@ -114,21 +494,287 @@ struct GenerateWithFilesRequest {
 
 #[cfg(feature = "web")]
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
     Json,
-    extract::State,
+    extract::{Path as UrlPath, Query, State},
     http::StatusCode,
     response::sse::{Event, Sse},
 };
+#[cfg(feature = "web")]
 use futures::stream::{self, Stream};
+#[cfg(feature = "web")]
 use std::convert::Infallible;
 #[cfg(feature = "web")]
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "web")]
 use tokio::net::TcpListener;
 #[cfg(feature = "web")]
-use tower_http::cors::CorsLayer;
+use std::future::IntoFuture;
+#[cfg(feature = "web")]
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Largest request body the token check reads to find its codebase
+#[cfg(feature = "web")]
+const MAX_SCOPED_BODY: usize = 8 * 1024 * 1024;
+
+/// Reject requests without a valid bearer token, and requests from a scoped
+/// token for a codebase outside its paths or for a route that names none
+/// (usage, metrics). Everything passes while no tokens are configured; the
+/// health check always does.
+#[cfg(feature = "web")]
+async fn require_token(
+    State(state): State<AppState>,
+    mut request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    if request.uri().path() == "/api/health" || !state.auth.is_enabled() {
+        return next.run(request).await;
+    }
+
+    let header = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|value| value.trim().to_string());
+    // Browsers can't set headers on a WebSocket, so those may pass it in the query
+    let bearer = header.or_else(|| {
+        request.uri().path().starts_with("/ws/").then(|| {
+            Query::<std::collections::HashMap<String, String>>::try_from_uri(request.uri())
+                .ok()
+                .and_then(|Query(mut query)| query.remove("access_token"))
+        })?
+    });
+    let token = match bearer.map(|bearer| state.auth.authenticate(&bearer)) {
+        None => return ApiError::Unauthorized("Missing 'Authorization: Bearer <token>' header".into()).into_response(),
+        Some(Ok(Some(token))) => token,
+        Some(Ok(None)) => return ApiError::Unauthorized("Unknown or revoked token".into()).into_response(),
+        Some(Err(e)) => {
+            return ApiError::Status(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to check the token: {}", e))
+                .into_response()
+        }
+    };
+    if !token.is_scoped() {
+        request.extensions_mut().insert(token);
+        return next.run(request).await;
+    }
+
+    // The codebase is in the query, the JSON body or, for index jobs and
+    // approvals, the job or the run that asked
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, MAX_SCOPED_BODY).await {
+        Ok(body) => body,
+        Err(_) => return ApiError::Status(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large".into()).into_response(),
+    };
+    let mut codebases: Vec<PathBuf> = Vec::new();
+    if let Ok(Query(query)) = Query::<std::collections::HashMap<String, String>>::try_from_uri(&parts.uri) {
+        codebases.extend(query.get("codebase_path").map(PathBuf::from));
+    }
+    if let Ok(serde_json::Value::Object(fields)) = serde_json::from_slice::<serde_json::Value>(&body) {
+        codebases.extend(fields.get("codebase_path").and_then(|p| p.as_str()).map(PathBuf::from));
+    }
+    let path = parts.uri.path();
+    if let Some(job_id) = path.strip_prefix("/api/index/status/").or_else(|| path.strip_prefix("/api/index/events/")) {
+        codebases.extend(state.index_jobs.get(job_id).map(|job| job.codebase_path));
+    }
+    if let Some(id) = path.strip_prefix("/api/approvals/") {
+        codebases.extend(state.approvals.codebase_of(id));
+    }
+    // WebSocket runs name theirs in each message, which the socket checks
+    if codebases.is_empty() && !path.starts_with("/ws/") {
+        return ApiError::Status(
+            StatusCode::FORBIDDEN,
+            format!("Token '{}' is limited to some codebases and this request names none", token.name),
+        )
+        .into_response();
+    }
+    if let Some(denied) = codebases.iter().find(|codebase| !token.allows(codebase)) {
+        return ApiError::Status(
+            StatusCode::FORBIDDEN,
+            format!("Token '{}' may not use {}", token.name, denied.display()),
+        )
+        .into_response();
+    }
+
+    let mut request = axum::extract::Request::from_parts(parts, axum::body::Body::from(body));
+    request.extensions_mut().insert(token);
+    next.run(request).await
+}
+
+/// Without tokens anything on this machine may call the API, so browsers may only
+/// do so from pages this server served or an `allowed_origins` entry. Other sites'
+/// scripts, form posts and WebSockets are refused, not just kept from the answer.
+#[cfg(feature = "web")]
+async fn reject_cross_origin(
+    State(state): State<AppState>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    if state.auth.is_enabled() || allowed_origin(request.headers(), &state.allowed_origins) {
+        return next.run(request).await;
+    }
+    ApiError::Status(
+        StatusCode::FORBIDDEN,
+        "Requests from other sites need an API token or an entry in [server] allowed_origins".into(),
+    )
+    .into_response()
+}
+
+/// CORS for the configured origins only; same-origin pages need none
+#[cfg(feature = "web")]
+fn cors(allowed_origins: &[String]) -> CorsLayer {
+    let origins: Vec<axum::http::HeaderValue> = allowed_origins.iter().filter_map(|origin| origin.parse().ok()).collect();
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([axum::http::Method::GET, axum::http::Method::POST, axum::http::Method::DELETE])
+        .allow_headers([axum::http::header::AUTHORIZATION, axum::http::header::CONTENT_TYPE])
+}
+
+/// Every API route behind `require_token`, and all but the health check and
+/// metrics behind the rate limiter when there is one
+#[cfg(feature = "web")]
+fn api_router(state: &AppState, limit: Option<rate_limit::RateLimitLayer>) -> Router<AppState> {
+    let generate_limit = || axum::extract::DefaultBodyLimit::max(state.max_generate_body);
+    let mut app = Router::new()
+        .route("/api/generate", post(generate_handler).layer(generate_limit()))
+        .route("/api/generate-stream", post(generate_stream_handler).layer(generate_limit()))
+        .route("/api/generate-with-files", post(generate_with_files_handler).layer(generate_limit()))
+        .route("/api/files", post(files_handler))
+        .route("/api/search-files", post(search_files_handler))
+        .route("/api/debug/signature", post(debug_signature_handler))
+        .route("/api/debug/context", post(debug_context_handler))
+        .route("/api/usage", get(usage_handler))
+        .route("/api/approvals/:id", post(approval_handler))
+        .route("/api/index", post(index_handler))
+        .route("/api/index", delete(delete_index_handler))
+        .route("/api/index/status/:job_id", get(index_status_handler))
+        .route("/api/index/events/:job_id", get(index_events_handler))
+        .route("/api/symbols", get(symbols_handler))
+        .route("/api/symbols/:id", get(symbol_handler))
+        .route("/api/files/*path", get(file_symbols_handler))
+        .route("/api/references/:name", get(references_handler))
+        .route("/api/runs", get(runs_handler))
+        .route("/api/runs/:id", get(run_handler))
+        .route("/api/runs/:id/feedback", post(feedback_handler))
+        .route("/ws/generate", get(ws_generate_handler));
+    if let Some(limit) = limit {
+        app = app.layer(limit);
+    }
+    // Routes added after the limiter are not limited
+    app.route("/api/health", post(health_handler))
+        .route("/metrics", get(metrics_handler))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), require_token))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), reject_cross_origin))
+        .layer(axum::middleware::from_fn(telemetry::track_requests))
+        .layer(cors(&state.allowed_origins))
+}
+
+/// Knowledge graph the web server keeps for a project
+#[cfg(feature = "web")]
+fn project_db_path(codebase_path: &Path) -> PathBuf {
+    codebase_path.join(".miow").join("miow.db")
+}
+
+/// The index job a web request has to wait for: one already queued or running
+/// for the project, or a new one when it has no index yet
+#[cfg(feature = "web")]
+fn pending_index(index_jobs: &jobs::IndexJobs, codebase_path: &Path, db_path: &Path) -> Option<jobs::IndexJob> {
+    index_jobs.active(codebase_path).or_else(|| {
+        (!db_path.exists() && codebase_path.is_dir()).then(|| index_jobs.enqueue(codebase_path, false))
+    })
+}
+
+/// A web generate request saved to the project's run history as it runs, with
+/// the tokens it spends and how it ended
+#[cfg(feature = "web")]
+struct WebRun {
+    /// Where the run is saved; `None` when it couldn't be, and the request goes ahead unrecorded
+    saved: Option<(miow_agent::RunStore, String)>,
+    /// Session the run belongs to; a new run starts its own
+    session_id: Option<String>,
+    usage: std::sync::Arc<miow_llm::UsageTracker>,
+    started: std::time::Instant,
+}
+
+#[cfg(feature = "web")]
+impl WebRun {
+    fn start(codebase_path: &Path, task: &str, selected_files: &[String], options: serde_json::Value) -> Self {
+        let store = miow_agent::RunStore::new(runs_dir_for_db(&project_db_path(codebase_path)));
+        let run_id = miow_agent::RunStore::new_run_id();
+        let saved = match miow_agent::RunRecorder::open(store.clone(), &run_id, task, codebase_path) {
+            Ok(run) => {
+                run.update(|state| {
+                    state.selected_files = selected_files.to_vec();
+                    state.options = Some(options);
+                });
+                Some((store, run_id))
+            }
+            Err(e) => {
+                say!("{}", format!("⚠️  Failed to save run: {}", e).yellow());
+                None
+            }
+        };
+        let session_id = saved.as_ref().map(|(_, run_id)| run_id.clone());
+        Self { saved, session_id, usage: std::sync::Arc::new(miow_llm::UsageTracker::new()), started: std::time::Instant::now() }
+    }
+
+    /// Start the run as a follow-up in session `session_id`, or as a new run
+    /// without one. Unlike a new run, a follow-up fails if it can't be saved.
+    fn for_request(codebase_path: &Path, task: &str, session_id: Option<&str>, options: serde_json::Value) -> Result<Self> {
+        let Some(session_id) = session_id else { return Ok(Self::start(codebase_path, task, &[], options)) };
+        let store = miow_agent::RunStore::new(runs_dir_for_db(&project_db_path(codebase_path)));
+        let previous = store.latest_in_session(session_id)?;
+        let state = store.follow_up(&previous.run_id, &miow_agent::RunStore::new_run_id(), task)?;
+        miow_agent::RunRecorder::open(store.clone(), &state.run_id, task, codebase_path)?.update(|state| state.options = Some(options));
+        Ok(Self {
+            session_id: Some(state.session().to_string()),
+            saved: Some((store, state.run_id)),
+            usage: std::sync::Arc::new(miow_llm::UsageTracker::new()),
+            started: std::time::Instant::now(),
+        })
+    }
+
+    fn run_id(&self) -> Option<String> {
+        self.saved.as_ref().map(|(_, run_id)| run_id.clone())
+    }
+
+    /// Give `orchestrator` the shared LLM, counting this run's tokens, checkpoint
+    /// into the run and stop when the server shuts down
+    fn attach(&self, orchestrator: MiowOrchestrator, state: &AppState) -> MiowOrchestrator {
+        let mut orchestrator = orchestrator
+            .with_cancellation(state.shutdown.child_token())
+            .with_context_cache(state.context_cache.clone());
+        if let Some(llm) = &state.llm {
+            orchestrator = orchestrator.with_llm_arc(std::sync::Arc::new(llm.with_extra_tracker(self.usage.clone())));
+        }
+        match &self.saved {
+            Some((store, run_id)) => orchestrator.with_run(store.clone(), run_id.clone()),
+            None => orchestrator,
+        }
+    }
+
+    /// Save the outcome, the tokens spent and the duration
+    fn finish(&self, result: &Result<String>) {
+        let Some((store, run_id)) = &self.saved else { return };
+        let run = match miow_agent::RunRecorder::open(store.clone(), run_id, "", Path::new("")) {
+            Ok(run) => run,
+            Err(e) => return say!("{}", format!("⚠️  Failed to save run {}: {}", run_id, e).yellow()),
+        };
+        run.update(|state| {
+            state.usage = Some(self.usage.summary());
+            state.duration_ms = Some(self.started.elapsed().as_millis() as u64);
+            match result {
+                Ok(prompt) => {
+                    state.prompt.get_or_insert_with(|| prompt.clone());
+                }
+                Err(e) => state.error = Some(format!("{:#}", e)),
+            }
+        });
+    }
+}
 
 /// Compute a stable Qdrant collection name for a given project path
 fn collection_name_for_path(path: &Path) -> String {
@@ -137,6 +783,22 @@ fn collection_name_for_path(path: &Path) -> String {
     format!("miow-{:x}", hasher.finish())
 }
 
+/// The project's vector store: its Qdrant collection, or the SQLite vector
+/// index in `db_path` when Qdrant is unreachable
+async fn open_vector_store(path: &Path, db_path: &Path) -> Result<miow_vector::VectorStore> {
+    let qdrant_url = std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
+    miow_vector::VectorStore::with_fallback(&qdrant_url, &collection_name_for_path(path), db_path).await
+}
+
+/// Which backend a vector store is, for status lines
+fn vector_store_label(store: &miow_vector::VectorStore) -> &'static str {
+    if store.is_fallback() {
+        "SQLite fallback, Qdrant unreachable"
+    } else {
+        "Qdrant"
+    }
+}
+
 #[derive(Parser)]
 #[command(name = "miow-context")]
 #[command(about = "Intelligent context engine for code generation", long_about = None)]
@@ -147,6 +809,175 @@ struct Cli {
     /// Enable verbose logging
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// Approve agent actions (permitted shell commands, very large reads) without asking
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    /// Print the result as JSON on stdout; progress and logs go to stderr
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Write logs as text, or as one JSON object per line with decorative output dropped
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Parser threads used when indexing (defaults to one per core)
+    #[arg(short = 'j', long, global = true, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    jobs: Option<u64>,
+
+    /// Load native grammar libraries the project's .miow/languages.json declares; without
+    /// it only Wasm parser plugins load from the project, native ones from the user's config
+    #[arg(long, global = true)]
+    trust_project_languages: bool,
+
+    /// Apply the miow.toml [commands] and [[tools]] sections and the languages file
+    /// of a repository cloned with `init --git`; they're ignored by default
+    #[arg(long, global = true)]
+    trust_clone: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum LogFormat {
+    /// Colored lines for a terminal
+    Text,
+    /// Structured events for CI and log collectors; no colors or progress bars
+    Json,
+}
+
+/// Overrides for the agent budget in miow.toml
+#[derive(Args, Default)]
+struct BudgetArgs {
+    /// Stop the agent after this many decide/act steps
+    #[arg(long, value_name = "N")]
+    max_iterations: Option<usize>,
+
+    /// Stop the agent after this many tool calls
+    #[arg(long, value_name = "N")]
+    max_tool_calls: Option<usize>,
+
+    /// Stop the agent once its decisions have used this many tokens
+    #[arg(long, value_name = "N")]
+    max_tokens: Option<usize>,
+
+    /// Stop the agent loop after this many seconds and build the prompt from what it has
+    #[arg(long, value_name = "SECS")]
+    agent_time: Option<u64>,
+}
+
+impl BudgetArgs {
+    fn apply(&self, mut budget: miow_agent::AgentBudget) -> miow_agent::AgentBudget {
+        if let Some(n) = self.max_iterations {
+            budget.max_iterations = n;
+        }
+        budget.max_tool_calls = self.max_tool_calls.or(budget.max_tool_calls);
+        budget.max_tokens = self.max_tokens.or(budget.max_tokens);
+        if let Some(secs) = self.agent_time {
+            budget.wall_clock = Some(std::time::Duration::from_secs(secs));
+        }
+        budget
+    }
+}
+
+/// What to do when a run's estimated LLM cost is over `--max-cost`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+enum OverCost {
+    /// Stop before any LLM call
+    #[default]
+    Abort,
+    /// Build the prompt with the deterministic pipeline, without the LLM
+    Deterministic,
+}
+
+/// Spending limit checked against the cost estimate before a run starts
+#[derive(Args, Default)]
+struct CostArgs {
+    /// Don't start the run if its LLM calls are estimated to cost more than this (USD)
+    #[arg(long, value_name = "USD")]
+    max_cost: Option<f64>,
+
+    /// When the estimate is over --max-cost: abort, or deterministic to build the prompt
+    /// without the LLM. At a terminal you are asked first, unless --yes is given.
+    #[arg(long, value_name = "ACTION", default_value = "abort", requires = "max_cost")]
+    over_cost: OverCost,
+}
+
+/// Overrides for the `[llm]` table in miow.toml
+#[derive(Args, Default, Clone)]
+struct LlmArgs {
+    /// LLM provider: gemini, azure or openai (detected from the environment by default)
+    #[arg(long, value_name = "PROVIDER")]
+    provider: Option<miow_llm::ProviderKind>,
+
+    /// Model name, e.g. gemini-2.5-pro or gpt-4o
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Sampling temperature, 0 to 2
+    #[arg(long)]
+    temperature: Option<f32>,
+
+    /// Cap on tokens in each LLM response
+    #[arg(long, value_name = "N")]
+    max_output_tokens: Option<usize>,
+}
+
+impl LlmArgs {
+    fn apply(&self, settings: miow_llm::LLMSettings) -> miow_llm::LLMSettings {
+        settings.merged(miow_llm::LLMSettings {
+            provider: self.provider,
+            model: self.model.clone(),
+            temperature: self.temperature,
+            max_tokens: self.max_output_tokens,
+            ..Default::default()
+        })
+    }
+}
+
+/// How the generated prompt and context are written out
+#[derive(Args, Default)]
+struct PromptArgs {
+    /// Prompt format: markdown, xml, chatml (JSON messages) or json
+    #[arg(long, value_name = "FORMAT", default_value = "markdown")]
+    format: miow_prompt::PromptFormat,
+
+    /// Also write the context for another assistant: cursor, continue or aider (repeatable)
+    #[arg(long, value_name = "TARGET")]
+    export: Vec<miow_prompt::ExportTarget>,
+
+    /// Directory for --export files (defaults to .miow/exports in the codebase)
+    #[arg(long, value_name = "DIR", requires = "export")]
+    export_dir: Option<PathBuf>,
+
+    /// For fixes and refactors: show the target symbol and its dependents and ask for a unified diff
+    #[arg(long)]
+    diff: bool,
+
+    /// Show the context that would be gathered, without calling the LLM
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Only gather context from this directory of the codebase (repeatable or comma-separated)
+    #[arg(long, value_name = "DIR", value_delimiter = ',')]
+    scope: Vec<String>,
+
+    /// Only gather symbols of this kind, e.g. function or component (repeatable or comma-separated)
+    #[arg(long, value_name = "KIND", value_delimiter = ',')]
+    kind: Vec<String>,
+
+    /// Gather context from at most this many files
+    #[arg(long, value_name = "N")]
+    max_files: Option<usize>,
+
+    /// Report why each context item was included: what found it, the query and vector
+    /// score, keyword hits and how many symbols refer to it
+    #[arg(long)]
+    explain: bool,
+
+    /// Also search another indexed repository, read-only, e.g. a shared component library:
+    /// its directory or database, optionally named NAME=PATH (repeatable)
+    #[arg(long, value_name = "[NAME=]PATH")]
+    with_index: Vec<String>,
 }
 
 #[derive(Subcommand)]
@@ -154,12 +985,16 @@ enum Commands {
     /// Index a codebase and store in knowledge graph (one-time setup)
     Init {
         /// Path to the codebase
-        #[arg(value_name = "PATH")]
-        path: PathBuf,
+        #[arg(value_name = "PATH", required_unless_present = "git")]
+        path: Option<PathBuf>,
 
         /// Database path for knowledge graph
         #[arg(short, long, default_value = "miow.db")]
         db: PathBuf,
+
+        /// Shallow-clone a remote repository into .miow/repos and index that instead
+        #[arg(long, value_name = "URL", conflicts_with = "path")]
+        git: Option<String>,
     },
 
     /// Reindex codebase and refresh knowledge graph
@@ -171,13 +1006,64 @@ enum Commands {
         /// Database path for knowledge graph
         #[arg(short, long, default_value = "miow.db")]
         db: PathBuf,
+
+        /// Only reindex files that changed since the last index instead of rebuilding the database
+        #[arg(long)]
+        incremental: bool,
+
+        /// Only reindex files git reports as changed since this revision (e.g. HEAD~3, main)
+        #[arg(long, value_name = "REV", conflicts_with = "incremental")]
+        since: Option<String>,
+    },
+
+    /// Keep the index and vector collection in sync as files change, until stopped
+    Watch {
+        /// Path to the codebase (defaults to the indexed checkout or the current directory)
+        #[arg(value_name = "PATH")]
+        path: Option<PathBuf>,
+
+        /// Database path for knowledge graph
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+
+        /// Milliseconds without changes before changed files are synced
+        #[arg(long, value_name = "MS", default_value_t = 300)]
+        debounce: u64,
+
+        /// Report on the running daemon instead of starting one
+        #[arg(long, conflicts_with = "stop")]
+        status: bool,
+
+        /// Stop the running daemon, after it syncs pending changes
+        #[arg(long)]
+        stop: bool,
     },
 
     /// Generate context-rich prompt (ask questions about your codebase)
     Ask {
-        /// User question or task
-        #[arg(value_name = "QUESTION")]
-        question: String,
+        /// User question or task, or - to read it from stdin
+        #[arg(value_name = "QUESTION", required_unless_present_any = ["resume", "rerun"])]
+        question: Option<String>,
+
+        /// Continue an interrupted run, reusing the steps it finished
+        #[arg(long, value_name = "RUN_ID", conflicts_with_all = ["question", "rerun"])]
+        resume: Option<String>,
+
+        /// Generate the prompt of a finished run again from the context it gathered,
+        /// e.g. with another --format or edited templates
+        #[arg(long, value_name = "RUN_ID", conflicts_with = "question")]
+        rerun: Option<String>,
+
+        /// Ask a follow-up in a session, starting from the context and plan its last
+        /// run gathered; without an id, follows up on the newest run
+        #[arg(
+            long = "continue",
+            value_name = "SESSION_ID",
+            num_args = 0..=1,
+            default_missing_value = "",
+            conflicts_with_all = ["resume", "rerun"]
+        )]
+        follow_up: Option<String>,
 
         /// Path to the codebase (defaults to current directory)
         #[arg(short, long)]
@@ -190,15 +1076,65 @@ enum Commands {
         /// Output file for generated prompt
         #[arg(short, long)]
         output: Option<PathBuf>,
-    },
 
-    /// Index a codebase and store in knowledge graph (legacy command)
-    Index {
-        /// Path to the codebase
-        #[arg(value_name = "PATH")]
-        path: PathBuf,
+        /// Abort the run after this many seconds
+        #[arg(long, value_name = "SECS")]
+        timeout: Option<u64>,
 
-        /// Database path for knowledge graph
+        /// Show the agent's progress as it works and print the implementation plan as
+        /// the LLM writes it
+        #[arg(long, conflicts_with = "dry_run")]
+        stream: bool,
+
+        #[command(flatten)]
+        prompt_args: PromptArgs,
+
+        #[command(flatten)]
+        budget: BudgetArgs,
+
+        #[command(flatten)]
+        cost: CostArgs,
+
+        #[command(flatten)]
+        llm: LlmArgs,
+    },
+
+    /// Generate a prompt for every question in a file, one question per line, in one session
+    Batch {
+        /// File of questions (blank lines and lines starting with # are skipped), or - for stdin
+        /// (with --yes or --dry-run, since approvals are asked on stdin)
+        #[arg(value_name = "FILE")]
+        questions: PathBuf,
+
+        /// Path to the codebase (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
+        /// Database path for knowledge graph
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+
+        /// Directory to write one prompt file per question to
+        #[arg(short, long, value_name = "DIR", default_value = "prompts")]
+        output_dir: PathBuf,
+
+        #[command(flatten)]
+        prompt_args: PromptArgs,
+
+        #[command(flatten)]
+        budget: BudgetArgs,
+
+        #[command(flatten)]
+        llm: LlmArgs,
+    },
+
+    /// Index a codebase and store in knowledge graph (legacy command)
+    Index {
+        /// Path to the codebase
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Database path for knowledge graph
         #[arg(short, long, default_value = "miow.db")]
         db: PathBuf,
     },
@@ -227,6 +1163,165 @@ enum Commands {
         /// Output file for generated prompt
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// Abort the run after this many seconds
+        #[arg(long, value_name = "SECS")]
+        timeout: Option<u64>,
+
+        #[command(flatten)]
+        budget: BudgetArgs,
+
+        #[command(flatten)]
+        cost: CostArgs,
+
+        #[command(flatten)]
+        llm: LlmArgs,
+    },
+
+    /// Query the index directly: symbols by name, kind, file or what they reference
+    Search {
+        /// Text in the symbol name, or with --semantic a description of the code
+        #[arg(value_name = "QUERY", required_unless_present_any = ["kind", "refs_to", "file", "owner"])]
+        query: Option<String>,
+
+        /// Database path for knowledge graph
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+
+        /// Only symbols of this kind (function, component, struct, ...)
+        #[arg(long)]
+        kind: Option<String>,
+
+        /// Search the vector store by meaning instead of the graph by name
+        #[arg(long, requires = "query", conflicts_with = "refs_to")]
+        semantic: bool,
+
+        /// Only symbols that reference this symbol
+        #[arg(long, value_name = "SYMBOL")]
+        refs_to: Option<String>,
+
+        /// Only symbols in files whose path contains this
+        #[arg(long, value_name = "PATH")]
+        file: Option<String>,
+
+        /// Only symbols in files owned by this CODEOWNERS team, user or email, or
+        /// whose most frequent recent git author has this email
+        #[arg(long, value_name = "OWNER", conflicts_with = "semantic")]
+        owner: Option<String>,
+
+        /// Path to the codebase, for --semantic (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
+        /// Show at most this many results
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// List everything a rename of a symbol touches: definitions, references, imports and exports
+    Impact {
+        /// Exact name of the symbol
+        symbol: String,
+
+        /// Database path for knowledge graph
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+    },
+
+    /// Delete the index, caches and vector collection to start over
+    Clean {
+        /// Database path for knowledge graph
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+
+        /// Path to the codebase whose vector collection to drop (defaults to the indexed
+        /// checkout or the current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
+        /// Also delete the whole .miow directory: run history, exports, cloned repositories
+        /// and project templates
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Report on the index: counts per language, largest files, parse errors and staleness
+    Stats {
+        /// Database path for knowledge graph
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+
+        /// Path to the codebase, to compare the index with git HEAD and find its vectors
+        /// (defaults to current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
+        /// How many of the largest files to list
+        #[arg(long, default_value = "10")]
+        largest: usize,
+    },
+
+    /// Browse symbols, files and references interactively, and generate prompts from a selection
+    Tui {
+        /// Database path for knowledge graph
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+
+        /// Path to the codebase prompts are generated for (defaults to the indexed checkout
+        /// or the current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
+        #[command(flatten)]
+        llm: LlmArgs,
+    },
+
+    /// List past prompt runs, newest first
+    History {
+        /// Database path for knowledge graph (runs are kept next to it)
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+
+        /// Show at most this many runs
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Show a run's inputs, token counts and generated prompt
+    Show {
+        #[arg(value_name = "RUN_ID")]
+        run_id: String,
+
+        /// Database path for knowledge graph (runs are kept next to it)
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+
+        /// Print only the prompt
+        #[arg(long)]
+        prompt_only: bool,
+    },
+
+    /// Say whether a run's context was useful; later runs rank those symbols up or down
+    Feedback {
+        #[arg(value_name = "RUN_ID")]
+        run_id: String,
+
+        /// The context was useful
+        #[arg(long, conflicts_with = "bad", required_unless_present = "bad")]
+        good: bool,
+
+        /// The context was not useful
+        #[arg(long)]
+        bad: bool,
+
+        /// Only the context from these files, or from files under these directories
+        /// (repeatable or comma-separated); all of it by default
+        #[arg(long, value_name = "FILE", num_args = 1.., value_delimiter = ',')]
+        files: Vec<String>,
+
+        /// Database path for knowledge graph (runs are kept next to it)
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
     },
 
     /// Test autonomous system planning
@@ -238,6 +1333,9 @@ enum Commands {
         /// Path to codebase for context
         #[arg(value_name = "PATH")]
         path: PathBuf,
+
+        #[command(flatten)]
+        llm: LlmArgs,
     },
 
     /// Start web server with UI
@@ -246,46 +1344,234 @@ enum Commands {
         #[arg(short, long, default_value = "3001")]
         port: u16,
 
+        /// Database path for knowledge graph; also holds the API tokens
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+
+        #[command(flatten)]
+        llm: LlmArgs,
+    },
+
+    /// Answer index, search and generate requests as JSON-RPC on stdin/stdout, for editor extensions
+    Rpc {
+        /// Path to the codebase (defaults to the indexed checkout or the current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
+        /// Database path for knowledge graph
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+
+        #[command(flatten)]
+        llm: LlmArgs,
+    },
+
+    /// Serve workspace symbols, references and prompt generation from the knowledge graph
+    /// as a language server on stdin/stdout
+    Lsp {
+        /// Path to the codebase (defaults to the indexed checkout or the current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
         /// Database path for knowledge graph
         #[arg(short, long, default_value = "miow.db")]
         db: PathBuf,
+
+        #[command(flatten)]
+        llm: LlmArgs,
+    },
+
+    /// Manage bearer tokens for the web server API
+    Token {
+        #[command(subcommand)]
+        action: TokenAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenAction {
+    /// Create a token and print it; it can't be shown again
+    Create {
+        /// Name to list and revoke the token by
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Only allow requests for codebases under this path (repeatable)
+        #[arg(long = "path", value_name = "PATH")]
+        paths: Vec<PathBuf>,
+
+        /// Database the server is started with
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+    },
+
+    /// List tokens by name, without the tokens themselves
+    List {
+        /// Database the server is started with
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
+    },
+
+    /// Delete a token; requests using it are refused at once
+    Revoke {
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Database the server is started with
+        #[arg(short, long, default_value = "miow.db")]
+        db: PathBuf,
     },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
+    if let Err(e) = run().await {
+        // Scripts get the failure as JSON too, and the exit code says what kind it was
+        let kind = errors::ErrorKind::of(&e);
+        if json_output() {
+            println!("{}", serde_json::json!({ "error": format!("{:#}", e), "code": kind.code() }));
+        }
+        if json_logs() {
+            tracing::error!(code = kind.code(), "{:#}", e);
+        } else {
+            eprintln!("Error: {:?}", e);
+        }
+        std::process::exit(kind.exit_code());
+    }
+}
+
+async fn run() -> Result<()> {
     let cli = Cli::parse();
 
-    // Setup logging
+    // Setup logging; --stream shows progress in place of the info logs
     let log_level = if cli.verbose {
         Level::DEBUG
+    } else if matches!(cli.command, Commands::Ask { stream: true, .. }) {
+        Level::WARN
     } else {
         Level::INFO
     };
 
-    tracing_subscriber::fmt()
-        .with_max_level(log_level)
-        .with_target(false)
-        .init();
+    // In rpc and lsp mode stdout carries only protocol messages
+    let stdio_server = matches!(cli.command, Commands::Rpc { .. } | Commands::Lsp { .. });
+    let json = cli.json || stdio_server;
+    let json_logs = cli.log_format == LogFormat::Json;
+    if stdio_server || json_logs {
+        colored::control::set_override(false);
+    }
+    JSON_OUTPUT.store(json, std::sync::atomic::Ordering::Relaxed);
+    JSON_LOGS.store(json_logs, std::sync::atomic::Ordering::Relaxed);
+    INDEX_JOBS.store(cli.jobs.unwrap_or(0) as usize, std::sync::atomic::Ordering::Relaxed);
+    TRUST_PROJECT_LANGUAGES.store(cli.trust_project_languages, std::sync::atomic::Ordering::Relaxed);
+    config::TRUST_CLONES.store(cli.trust_clone, std::sync::atomic::Ordering::Relaxed);
+    let log_writer = if json {
+        tracing_subscriber::fmt::writer::BoxMakeWriter::new(std::io::stderr)
+    } else {
+        tracing_subscriber::fmt::writer::BoxMakeWriter::new(std::io::stdout)
+    };
+
+    let fmt_layer = tracing_subscriber::fmt::layer().with_target(false).with_writer(log_writer);
+    let fmt_layer = if json_logs {
+        fmt_layer.json().flatten_event(true).with_current_span(false).boxed()
+    } else {
+        fmt_layer.boxed()
+    }
+    .with_filter(LevelFilter::from_level(log_level));
+    let subscriber = tracing_subscriber::registry().with(fmt_layer);
+    // Held until the command returns, so the last spans are flushed
+    #[cfg(feature = "otel")]
+    let (otel_layer, _otel_guard) = otel::layer()?.unzip();
+    #[cfg(feature = "otel")]
+    let subscriber = subscriber.with(otel_layer.with_filter(LevelFilter::INFO));
+    subscriber.init();
 
     match cli.command {
-        Commands::Init { path, db } => {
-            handle_init(path, db).await?;
+        Commands::Init { path, db, git } => {
+            handle_init(path, db, git).await?;
         }
-        Commands::Reindex { path, db } => {
-            handle_reindex(path, db).await?;
+        Commands::Reindex { path, db, incremental, since } => {
+            handle_reindex(path, db, incremental, since).await?;
+        }
+        Commands::Watch { path, db, debounce, status, stop } => {
+            let socket = watch_socket_for_db(&db);
+            if status || stop {
+                handle_watch_control(&socket, if stop { "stop" } else { "status" }).await?;
+            } else {
+                let path = path
+                    .or_else(|| recorded_checkout(&db))
+                    .unwrap_or_else(|| std::env::current_dir().unwrap());
+                handle_watch(path, db, socket, std::time::Duration::from_millis(debounce)).await?;
+            }
         }
         Commands::Ask {
             question,
+            resume,
+            rerun,
+            follow_up,
             path,
             db,
             output,
+            timeout,
+            stream,
+            prompt_args,
+            budget,
+            cost,
+            llm,
         } => {
-            let codebase_path = path.unwrap_or_else(|| std::env::current_dir().unwrap());
-            handle_ask(question, codebase_path, db, output).await?;
+            let store = miow_agent::RunStore::new(runs_dir_for_db(&db));
+            let (question, codebase_path, run_id) = match (resume, rerun) {
+                (Some(run_id), _) => {
+                    let state = store.load(&run_id)?;
+                    (state.task, state.project_root, run_id)
+                }
+                (None, Some(from)) => {
+                    let state = store.fork(&from, &miow_agent::RunStore::new_run_id())?;
+                    (state.task, state.project_root, state.run_id)
+                }
+                (None, None) => {
+                    let question = match question.unwrap_or_default() {
+                        q if q == "-" => read_stdin()?.trim().to_string(),
+                        q => q,
+                    };
+                    if question.is_empty() {
+                        anyhow::bail!("No question given");
+                    }
+                    match follow_up {
+                        Some(session) => {
+                            let previous = if session.is_empty() {
+                                store.list()?.into_iter().next().context("No earlier run to follow up on")?
+                            } else {
+                                store.latest_in_session(&session)?
+                            };
+                            let state = store.follow_up(&previous.run_id, &miow_agent::RunStore::new_run_id(), &question)?;
+                            (state.task, state.project_root, state.run_id)
+                        }
+                        None => {
+                            let codebase_path = path
+                                .or_else(|| recorded_checkout(&db))
+                                .unwrap_or_else(|| std::env::current_dir().unwrap());
+                            (question, codebase_path, miow_agent::RunStore::new_run_id())
+                        }
+                    }
+                }
+            };
+            handle_ask(question, codebase_path, db, output, timeout, stream, prompt_args, budget, cost, llm, cli.yes, (store, run_id)).await?;
+        }
+        Commands::Batch { questions, path, db, output_dir, prompt_args, budget, llm } => {
+            let codebase_path = path
+                .or_else(|| recorded_checkout(&db))
+                .unwrap_or_else(|| std::env::current_dir().unwrap());
+            handle_batch(questions, codebase_path, db, output_dir, prompt_args, budget, llm, cli.yes).await?;
         }
         Commands::Index { path, db } => {
-            handle_index(path, db).await?;
+            let report = handle_index(path.clone(), db.clone()).await?;
+            if json_output() {
+                print_json(&serde_json::json!({
+                    "database": db,
+                    "codebase": path,
+                    "index": index_report_json(&report),
+                }))?;
+            }
         }
         Commands::Analyze { file } => {
             handle_analyze(file).await?;
@@ -295,672 +1581,2304 @@ async fn main() -> Result<()> {
             prompt,
             db,
             output,
+            timeout,
+            budget,
+            cost,
+            llm,
         } => {
-            handle_generate_autonomous(path, prompt, db, output).await?;
+            let run = (miow_agent::RunStore::new(runs_dir_for_db(&db)), miow_agent::RunStore::new_run_id());
+            handle_generate_autonomous(path, prompt, db, output, timeout, false, PromptArgs::default(), budget, cost, llm, cli.yes, Some(run)).await?;
+        }
+        Commands::Search { query, db, kind, semantic, refs_to, file, owner, path, limit } => {
+            let filters = SearchFilters { kind, refs_to, file, owner };
+            if semantic {
+                let path = path
+                    .or_else(|| recorded_checkout(&db))
+                    .unwrap_or_else(|| std::env::current_dir().unwrap());
+                handle_semantic_search(&path, &db, &query.unwrap_or_default(), &filters, limit).await?;
+            } else {
+                handle_search(&db, query.as_deref(), &filters, limit)?;
+            }
+        }
+        Commands::Impact { symbol, db } => {
+            handle_impact(&db, &symbol)?;
         }
-        Commands::TestAutonomous { task, path } => {
-            test_autonomous_system(task, path).await?;
+        Commands::Clean { db, path, all } => {
+            let path = path
+                .or_else(|| recorded_checkout(&db))
+                .unwrap_or_else(|| std::env::current_dir().unwrap());
+            handle_clean(&db, &path, all, cli.yes).await?;
         }
-        Commands::Serve { port, db } => {
-            start_web_server(port, db).await?;
+        Commands::Stats { db, path, largest } => {
+            let path = path
+                .or_else(|| recorded_checkout(&db))
+                .unwrap_or_else(|| std::env::current_dir().unwrap());
+            handle_stats(&db, &path, largest).await?;
+        }
+        Commands::Tui { db, path, llm } => {
+            let path = path
+                .or_else(|| recorded_checkout(&db))
+                .unwrap_or_else(|| std::env::current_dir().unwrap());
+            handle_tui(db, path, llm, cli.yes).await?;
+        }
+        Commands::History { db, limit } => {
+            handle_history(&db, limit)?;
+        }
+        Commands::Show { run_id, db, prompt_only } => {
+            handle_show(&db, &run_id, prompt_only)?;
+        }
+        Commands::Feedback { run_id, good, bad: _, files, db } => {
+            handle_feedback(&db, &run_id, good, &files)?;
+        }
+        Commands::TestAutonomous { task, path, llm } => {
+            test_autonomous_system(task, path, llm).await?;
+        }
+        Commands::Serve { port, db, llm } => {
+            start_web_server(port, db, llm).await?;
+        }
+        Commands::Token { action } => {
+            handle_token(action)?;
+        }
+        Commands::Rpc { path, db, llm } => {
+            let path = path
+                .or_else(|| recorded_checkout(&db))
+                .unwrap_or_else(|| std::env::current_dir().unwrap());
+            let options = rpc::RpcOptions { root: path, database: db, llm, assume_yes: cli.yes };
+            rpc::serve(tokio::io::stdin(), tokio::io::stdout(), rpc::RpcMethods::new(options)).await?;
+        }
+        Commands::Lsp { path, db, llm } => {
+            let path = path
+                .or_else(|| recorded_checkout(&db))
+                .unwrap_or_else(|| std::env::current_dir().unwrap());
+            let options = rpc::RpcOptions { root: path, database: db, llm, assume_yes: cli.yes };
+            rpc::serve(tokio::io::stdin(), tokio::io::stdout(), lsp::LspMethods::new(options)).await?;
         }
     }
 
     Ok(())
 }
 
-async fn handle_init(path: PathBuf, db_path: PathBuf) -> Result<()> {
-    println!("{}", "🚀 MIOW-CONTEXT INITIALIZATION".bright_blue().bold());
-    println!("{}", "═".repeat(50).bright_black());
-    println!("📁 Codebase: {}", path.display());
-    println!("💾 Database: {}", db_path.display());
-    println!();
+async fn handle_init(path: Option<PathBuf>, db_path: PathBuf, git_url: Option<String>) -> Result<()> {
+    say!("{}", "🚀 MIOW-CONTEXT INITIALIZATION".bright_blue().bold());
+    say!("{}", "═".repeat(50).bright_black());
+    if let Some(url) = &git_url {
+        say!("🌐 Repository: {}", url);
+    }
+    say!("💾 Database: {}", db_path.display());
 
     // Check if already indexed
     if db_path.exists() {
-        println!("{}", "⚠️  Database already exists. Use 'reindex' to refresh or 'ask' to query.".yellow());
+        say!();
+        say!("{}", "⚠️  Database already exists. Use 'reindex' to refresh or 'ask' to query.".yellow());
+        if json_output() {
+            print_json(&serde_json::json!({ "database": db_path, "already_exists": true }))?;
+        }
         return Ok(());
     }
 
-    handle_index(path, db_path).await?;
+    let path = match (&git_url, path) {
+        (Some(url), _) => {
+            say!("{}", "📥 Cloning (shallow)...".cyan());
+            miow_core::git::clone_or_update(url, &repos_dir_for_db(&db_path))?
+        }
+        (None, Some(path)) => path,
+        (None, None) => anyhow::bail!("Pass a codebase path or --git <URL>"),
+    };
+    say!("📁 Codebase: {}", path.display());
+    if git_url.is_some() && config::is_untrusted_clone(&path) {
+        say!(
+            "{}",
+            "🔒 Untrusted clone: its miow.toml [commands] and [[tools]] and its languages file are ignored. Pass --trust-clone once you've reviewed them."
+                .yellow()
+        );
+    }
+    say!();
+
+    let report = handle_index(path.clone(), db_path.clone()).await?;
 
-    println!();
-    println!("{}", "✅ Initialization complete! You can now use 'miow-context ask' to query your codebase.".green().bold());
+    // Remember where the index came from so `ask` can find the checkout without --path
+    if let Some(url) = &git_url {
+        let graph = KnowledgeGraph::new(&db_path)?;
+        let checkout = path.canonicalize().unwrap_or_else(|_| path.clone());
+        graph.set_metadata(miow_core::git::ORIGIN_URL_KEY, url)?;
+        graph.set_metadata(miow_core::git::CHECKOUT_PATH_KEY, &checkout.to_string_lossy())?;
+    }
+
+    say!();
+    say!("{}", "✅ Initialization complete! You can now use 'miow-context ask' to query your codebase.".green().bold());
 
+    if json_output() {
+        print_json(&serde_json::json!({
+            "database": db_path,
+            "codebase": path,
+            "origin_url": git_url,
+            "index": index_report_json(&report),
+        }))?;
+    }
     Ok(())
 }
 
-async fn handle_reindex(path: PathBuf, db_path: PathBuf) -> Result<()> {
-    println!("{}", "🔄 MIOW-CONTEXT REINDEXING".bright_blue().bold());
-    println!("{}", "═".repeat(50).bright_black());
-    println!("📁 Codebase: {}", path.display());
-    println!("💾 Database: {}", db_path.display());
-    println!();
+async fn handle_reindex(path: PathBuf, db_path: PathBuf, incremental: bool, since: Option<String>) -> Result<()> {
+    say!("{}", "🔄 MIOW-CONTEXT REINDEXING".bright_blue().bold());
+    say!("{}", "═".repeat(50).bright_black());
+    say!("📁 Codebase: {}", path.display());
+    say!("💾 Database: {}", db_path.display());
+    say!();
+
+    let mode = match since {
+        Some(rev) => IndexMode::Since(rev),
+        None if incremental => IndexMode::Incremental,
+        None => IndexMode::Full,
+    };
+    if !matches!(mode, IndexMode::Full) {
+        if db_path.exists() {
+            let mode_name = match &mode {
+                IndexMode::Since(_) => "since",
+                _ => "incremental",
+            };
+            let report = run_index(path.clone(), db_path.clone(), mode, None).await?;
+            say!();
+            say!("{}", "✅ Incremental reindex complete!".green().bold());
+            if json_output() {
+                print_json(&serde_json::json!({
+                    "database": db_path,
+                    "codebase": path,
+                    "mode": mode_name,
+                    "index": index_report_json(&report),
+                }))?;
+            }
+            return Ok(());
+        }
+        say!("{}", "⚠️  No existing database, running a full index instead.".yellow());
+    }
 
     // Force reindex by removing existing database
     if db_path.exists() {
-        println!("🗑️  Removing existing database...");
+        say!("🗑️  Removing existing database...");
         std::fs::remove_file(&db_path)?;
     }
 
-    handle_index(path, db_path).await?;
+    let report = handle_index(path.clone(), db_path.clone()).await?;
 
-    println!();
-    println!("{}", "✅ Reindexing complete! Database refreshed.".green().bold());
+    say!();
+    say!("{}", "✅ Reindexing complete! Database refreshed.".green().bold());
 
+    if json_output() {
+        print_json(&serde_json::json!({
+            "database": db_path,
+            "codebase": path,
+            "mode": "full",
+            "index": index_report_json(&report),
+        }))?;
+    }
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_ask(
     question: String,
     path: PathBuf,
     db_path: PathBuf,
     output: Option<PathBuf>,
+    timeout: Option<u64>,
+    stream: bool,
+    prompt_args: PromptArgs,
+    budget: BudgetArgs,
+    cost: CostArgs,
+    llm: LlmArgs,
+    assume_yes: bool,
+    run: (miow_agent::RunStore, String),
 ) -> Result<()> {
-    println!("{}", "🤖 MIOW-CONTEXT AUTONOMOUS QUERY".bright_blue().bold());
-    println!("{}", "═".repeat(60).bright_black());
-    println!("📝 Question: {}", question.bright_yellow());
-    println!("📁 Codebase: {}", path.display());
-    println!("💾 Database: {}", db_path.display());
-    println!();
+    say!("{}", "🤖 MIOW-CONTEXT AUTONOMOUS QUERY".bright_blue().bold());
+    say!("{}", "═".repeat(60).bright_black());
+    say!("📝 Question: {}", question.bright_yellow());
+    say!("📁 Codebase: {}", path.display());
+    say!("💾 Database: {}", db_path.display());
+    say!("🧾 Run: {}", run.1);
+    if let Some(session) = run.0.load(&run.1).ok().and_then(|state| state.session_id) {
+        say!("🧵 Session: {}", session);
+    }
+    say!();
 
     // Check if database exists
     if !db_path.exists() {
-        println!(
-            "{}",
-            "⚠️  Knowledge graph not found. Run 'miow-context init' first.".yellow()
-        );
-        return Ok(());
+        return Err(GraphError::NotIndexed(db_path).into());
     }
+    warn_if_index_behind_head(&path, &db_path);
 
     // Use the same logic as generate but with better messaging
-    handle_generate_autonomous(path, question, db_path, output).await?;
+    let (store, run_id) = run.clone();
+    if let Err(e) = handle_generate_autonomous(path, question, db_path, output, timeout, stream, prompt_args, budget, cost, llm, assume_yes, Some(run)).await {
+        // Stopped by the preflight, nothing ran
+        if errors::ErrorKind::of(&e) == errors::ErrorKind::OverBudget {
+            return Err(e);
+        }
+        say!(
+            "{}",
+            format!("⏯️  Finished steps were saved. Resume with: miow-context ask --resume {}", run_id).yellow()
+        );
+        return Err(e);
+    }
 
-    println!();
-    println!("{}", "💡 Tip: Use 'miow-context reindex' if your codebase has changed significantly.".bright_black());
+    say!();
+    // A dry run saves nothing to follow up on, and a run without an LLM gathers no agent context
+    if let Some(state) = store.load(&run_id).ok().filter(|state| state.prompt.is_some() && state.agent_done) {
+        say!("{}", format!("💬 Ask a follow-up: miow-context ask --continue {} \"...\"", state.session()).bright_black());
+    }
+    say!("{}", "💡 Tip: Use 'miow-context reindex' if your codebase has changed significantly.".bright_black());
 
     Ok(())
 }
 
-async fn handle_index(path: PathBuf, db_path: PathBuf) -> Result<()> {
-    println!("{}", "🔍 Indexing codebase...".cyan().bold());
-    println!("Path: {}", path.display());
-    println!("Database: {}", db_path.display());
-    println!();
+/// All of stdin, for `-` in place of a question or file
+fn read_stdin() -> Result<String> {
+    use std::io::Read;
+    let mut text = String::new();
+    std::io::stdin().read_to_string(&mut text).context("Failed to read stdin")?;
+    Ok(text)
+}
 
-    // Try to initialize vector store if Qdrant is available (per-project collection)
-    let qdrant_url =
-        std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
-    let collection_name = collection_name_for_path(&path);
-    let vector_store = match miow_vector::VectorStore::new(&qdrant_url, &collection_name).await {
-        Ok(store) => {
-            println!("{}", "✅ Vector store (Qdrant) connected!".green());
-            Some(std::sync::Arc::new(store))
+/// Questions for `batch`: one per line, skipping blank lines and `#` comments
+fn parse_questions(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// File name for the `index`th of `count` batch prompts, e.g. `03-add-a-logout-button.md`
+fn batch_file_name(index: usize, count: usize, question: &str, extension: &str) -> String {
+    let mut slug = String::new();
+    for c in question.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
         }
-        Err(e) => {
-            println!(
-                "{}",
-                format!(
-                    "⚠️  Vector store not available: {}. Continuing without vector search.",
-                    e
-                )
-                .yellow()
-            );
-            None
+        if slug.len() >= 48 {
+            break;
         }
-    };
+    }
+    let slug = slug.trim_end_matches('-');
+    let width = count.to_string().len().max(2);
+    if slug.is_empty() {
+        format!("{:0width$}.{}", index, extension, width = width)
+    } else {
+        format!("{:0width$}-{}.{}", index, slug, extension, width = width)
+    }
+}
 
-    let report = if let Some(vs) = vector_store {
-        // Use vector store if available
-        use miow_core::index_codebase_with_vector;
-        index_codebase_with_vector(path.clone(), vs).await?
+/// Answer every question in `questions_file` with one orchestrator, so the index,
+/// caches and LLM client are set up once, writing a prompt file per question
+#[allow(clippy::too_many_arguments)]
+async fn handle_batch(
+    questions_file: PathBuf,
+    path: PathBuf,
+    db_path: PathBuf,
+    output_dir: PathBuf,
+    prompt_args: PromptArgs,
+    budget: BudgetArgs,
+    llm: LlmArgs,
+    assume_yes: bool,
+) -> Result<()> {
+    let text = if questions_file.as_os_str() == "-" {
+        // The terminal approver reads its answers from stdin too
+        if !assume_yes && !prompt_args.dry_run {
+            anyhow::bail!("Questions can't be read from stdin while approvals are asked there; pass --yes or a file of questions");
+        }
+        read_stdin()?
     } else {
-        index_codebase(path.clone()).await?
+        std::fs::read_to_string(&questions_file).with_context(|| format!("Failed to read {}", questions_file.display()))?
     };
-
-    println!("{}", "✅ Indexing complete!".green().bold());
-    println!();
-    println!("📊 {}", "Statistics:".yellow().bold());
-    println!("  Total files: {}", report.total_files);
-    println!("  Total size: {} bytes", report.total_size);
-    println!("  Duration: {}ms", report.duration_ms);
-    println!();
-    println!("📁 {}", "Files by language:".yellow().bold());
-
-    for (lang, count) in &report.files_by_language {
-        println!("  {}: {}", lang, count);
+    let questions = parse_questions(&text);
+    if questions.is_empty() {
+        anyhow::bail!("No questions found in {}", questions_file.display());
     }
-
-    // Create knowledge graph and insert data
-    println!();
-    println!("{}", "💾 Building knowledge graph...".cyan().bold());
-
-    let mut graph = KnowledgeGraph::new(&db_path)?;
-    let mut total_symbols = 0;
-
-    for file in &report.files {
-        let parsed_data = match file.language {
-            miow_core::Language::TypeScript | miow_core::Language::TSX => {
-                let is_tsx = matches!(file.language, miow_core::Language::TSX);
-                match parse_typescript(&file.content, is_tsx) {
-                    Ok(parsed) => Some(convert_to_graph_data(parsed)),
-                    Err(e) => {
-                        eprintln!("  ⚠️  Failed to parse {}: {}", file.relative_path, e);
-                        None
-                    }
+    if !db_path.exists() {
+        return Err(GraphError::NotIndexed(db_path.to_path_buf()).into());
+    }
+    let root = path.to_str().with_context(|| format!("{} is not a UTF-8 path", path.display()))?;
+
+    say!("{}", "🤖 MIOW-CONTEXT BATCH".bright_blue().bold());
+    say!("{}", "═".repeat(60).bright_black());
+    say!("📁 Codebase: {}", path.display());
+    say!("📝 Questions: {}", questions.len());
+    say!("📂 Output: {}", output_dir.display());
+    say!();
+    warn_if_index_behind_head(&path, &db_path);
+
+    let dry_run = prompt_args.dry_run;
+    let extension = if dry_run { "json" } else { prompt_args.format.extension() };
+    std::fs::create_dir_all(&output_dir).with_context(|| format!("Failed to create {}", output_dir.display()))?;
+    let orchestrator = prompt_orchestrator(&path, &db_path, prompt_args, budget, llm, assume_yes).await?;
+    say!();
+
+    let mut results = Vec::new();
+    let mut failed = 0;
+    for (i, question) in questions.iter().enumerate() {
+        say!("{} {}", format!("[{}/{}]", i + 1, questions.len()).bold(), question.bright_yellow());
+        let file = output_dir.join(batch_file_name(i + 1, questions.len(), question, extension));
+        let generated = if dry_run {
+            orchestrator.dry_run(question, &path).await.and_then(|report| Ok(serde_json::to_string_pretty(&report)?))
+        } else {
+            orchestrator.generate_autonomous_prompt(root, question, None).await
+        };
+        let written = generated.and_then(|content| Ok(std::fs::write(&file, content)?));
+        match written {
+            Ok(()) => {
+                say!("   {} {}", "✅".green(), file.display());
+                let mut result = serde_json::json!({ "question": question, "output": file });
+                if let Some(explanation) = orchestrator.explanation().filter(|_| !dry_run) {
+                    result["explanation"] = serde_json::json!(explanation);
                 }
+                results.push(result);
+            }
+            Err(e) => {
+                failed += 1;
+                say!("   {}", format!("❌ {}", e).red());
+                results.push(serde_json::json!({ "question": question, "error": e.to_string() }));
             }
-            miow_core::Language::Rust => match parse_rust(&file.content) {
-                Ok(parsed) => Some(convert_to_graph_data(parsed)),
-                Err(e) => {
-                    eprintln!("  ⚠️  Failed to parse {}: {}", file.relative_path, e);
-                    None
-                }
-            },
-            miow_core::Language::Python => match parse_python(&file.content) {
-                Ok(parsed) => Some(convert_to_graph_data(parsed)),
-                Err(e) => {
-                    eprintln!("  ⚠️  Failed to parse {}: {}", file.relative_path, e);
-                    None
-                }
-            },
-            _ => None,
-        };
-
-        if let Some(data) = parsed_data {
-            total_symbols += data.symbols.len();
-            graph.insert_file(&file.relative_path, &data)?;
         }
     }
 
-    println!();
-    println!("{}", "✅ Knowledge graph built!".green().bold());
-    println!("  Total symbols indexed: {}", total_symbols);
-
-    Ok(())
+    print_usage_summary(&orchestrator.usage_summary());
+    say!();
+    say!("📦 {} of {} prompts written to {}", questions.len() - failed, questions.len(), output_dir.display());
+    if json_output() {
+        print_json(&serde_json::json!({
+            "codebase": path,
+            "output_dir": output_dir,
+            "results": results,
+            "usage": orchestrator.usage_summary(),
+        }))?;
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} questions failed", failed, questions.len());
+    }
+    Ok(())
 }
 
-async fn handle_analyze(file: PathBuf) -> Result<()> {
-    println!("{}", "🔬 Analyzing file...".cyan().bold());
-    println!("File: {}", file.display());
-    println!();
-
-    let content = std::fs::read_to_string(&file)?;
-    let extension = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+/// Cloned repositories live in `.miow/repos` next to the knowledge graph database
+fn repos_dir_for_db(db_path: &Path) -> PathBuf {
+    let parent = db_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
 
-    let parsed = match extension {
-        "tsx" | "ts" => {
-            let is_tsx = extension == "tsx";
-            parse_typescript(&content, is_tsx)?
-        }
-        "rs" => parse_rust(&content)?,
-        "py" => parse_python(&content)?,
-        _ => anyhow::bail!("Unsupported file type: {}", extension),
-    };
+    if parent.file_name().map(|n| n == ".miow").unwrap_or(false) {
+        parent.join("repos")
+    } else {
+        parent.join(".miow").join("repos")
+    }
+}
 
-    println!("{}", "✅ Analysis complete!".green().bold());
-    println!();
+/// Checkpoints of `ask` runs live in `.miow/runs` next to the knowledge graph database
+fn runs_dir_for_db(db_path: &Path) -> PathBuf {
+    repos_dir_for_db(db_path).with_file_name("runs")
+}
 
-    if !parsed.symbols.is_empty() {
-        println!("{}", "🔍 Symbols:".yellow().bold());
-        for symbol in &parsed.symbols {
-            println!("  • {} ({:?})", symbol.name.green(), symbol.kind);
-            println!(
-                "    Lines: {}-{}",
-                symbol.range.start_line, symbol.range.end_line
-            );
+/// `search` flags that narrow the results
+struct SearchFilters {
+    kind: Option<String>,
+    refs_to: Option<String>,
+    file: Option<String>,
+    owner: Option<String>,
+}
 
-            if !symbol.children.is_empty() {
-                println!("    Children: {}", symbol.children.len());
-            }
-            println!();
-        }
+/// Symbols in the knowledge graph matching `query` and `filters`
+fn query_graph(
+    db_path: &Path,
+    query: Option<&str>,
+    filters: &SearchFilters,
+    limit: usize,
+) -> Result<Vec<miow_graph::SymbolSearchResult>> {
+    if !db_path.exists() {
+        return Err(GraphError::NotIndexed(db_path.to_path_buf()).into());
     }
+    let graph = KnowledgeGraph::new(db_path)?;
 
-    if !parsed.imports.is_empty() {
-        println!("{}", "📦 Imports:".yellow().bold());
-        for import in &parsed.imports {
-            println!("  • from '{}'", import.source.cyan());
-        }
+    let mut builder = miow_graph::QueryBuilder::new().limit(limit);
+    if let Some(query) = query {
+        builder = builder.with_name(query);
+    }
+    if let Some(kind) = &filters.kind {
+        builder = builder.with_kind(kind);
+    }
+    if let Some(target) = &filters.refs_to {
+        builder = builder.referencing(target);
+    }
+    if let Some(file) = &filters.file {
+        builder = builder.with_file(file);
+    }
+    if let Some(owner) = &filters.owner {
+        builder = builder.owned_by(owner);
     }
+    graph.query_symbols(&builder)
+}
 
-    Ok(())
+/// A graph search hit for `--json` and `rpc`
+fn symbol_hit_json(symbol: &miow_graph::SymbolSearchResult) -> serde_json::Value {
+    serde_json::json!({
+        "name": symbol.name,
+        "kind": symbol.kind,
+        "file_path": symbol.file_path,
+        "start_line": symbol.start_line,
+        "end_line": symbol.end_line,
+    })
 }
 
-async fn handle_generate_autonomous(
-    path: PathBuf,
-    prompt: String,
-    db_path: PathBuf,
-    output: Option<PathBuf>,
-) -> Result<()> {
-    println!("{}", "🤖 MIOW-CONTEXT AUTONOMOUS PROMPT GENERATION".bright_blue().bold());
-    println!("{}", "═".repeat(80).bright_black());
-    println!("📁 Codebase: {}", path.display());
-    println!("📝 Task: {}", prompt.bright_yellow());
-    println!();
+/// Search the knowledge graph by symbol name, kind, file and references
+fn handle_search(db_path: &Path, query: Option<&str>, filters: &SearchFilters, limit: usize) -> Result<()> {
+    let results = query_graph(db_path, query, filters, limit)?;
 
-    // Check if database exists, if not, index first
-    if !db_path.exists() {
-        println!(
-            "{}",
-            "⚠️  Knowledge graph not found. Indexing codebase first...".yellow()
-        );
-        println!();
-        handle_index(path.clone(), db_path.clone()).await?;
-        println!();
+    if json_output() {
+        let hits: Vec<serde_json::Value> = results.iter().map(symbol_hit_json).collect();
+        return print_json(&serde_json::Value::from(hits));
     }
 
-    // Create orchestrator
-    let mut orchestrator = MiowOrchestrator::new(db_path.to_str().unwrap())?;
-
-    // Try to initialize vector store if Qdrant is available (per-project collection)
-    let qdrant_url =
-        std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
-    let collection_name = collection_name_for_path(&path);
-    match miow_vector::VectorStore::new(&qdrant_url, &collection_name).await {
-        Ok(store) => {
-            println!("{}", "✅ Vector store (Qdrant) connected!".green());
-            orchestrator = orchestrator.with_vector_store(std::sync::Arc::new(store));
-        }
-        Err(e) => {
-            println!(
-                "{}",
-                format!(
-                    "⚠️  Vector store not available: {}. Continuing without vector search.",
-                    e
-                )
-                .yellow()
-            );
-        }
+    if results.is_empty() {
+        say!("No symbols found.");
+        return Ok(());
+    }
+    for symbol in &results {
+        say!(
+            "{} {}  {}",
+            symbol.name.bright_yellow(),
+            format!("({})", symbol.kind).bright_black(),
+            format!("{}:{}-{}", symbol.file_path, symbol.start_line, symbol.end_line).cyan()
+        );
+    }
+    if results.len() == limit {
+        say!("{}", format!("Showing the first {}; raise --limit for more.", limit).bright_black());
     }
+    Ok(())
+}
 
-    // Try to initialize LLM if API key is available
-    if let Ok(api_key) = std::env::var("GEMINI_API_KEY") {
-        println!("{}", "🤖 LLM integration enabled (Gemini)".green());
-        use miow_llm::{GeminiClient, LLMConfig};
-        
-        let llm_config = LLMConfig {
-            api_key,
-            model: "gemini-2.5-flash".to_string(),
-            temperature: 0.7,
-            max_tokens: 4096,
-        };
+/// Print the sites a rename of `symbol` touches, grouped by file
+fn handle_impact(db_path: &Path, symbol: &str) -> Result<()> {
+    if !db_path.exists() {
+        return Err(GraphError::NotIndexed(db_path.to_path_buf()).into());
+    }
+    let graph = KnowledgeGraph::new(db_path)?;
+    let impact = graph.impact_of_rename(symbol)?;
 
-        match GeminiClient::new(llm_config) {
-            Ok(client) => {
-                orchestrator = orchestrator.with_llm(Box::new(client));
-                println!("{}", "✅ LLM client initialized successfully".green());
-            }
-            Err(e) => {
-                println!(
-                    "{}",
-                    format!(
-                        "⚠️  Failed to initialize LLM: {}. Continuing without LLM.",
-                        e
-                    )
-                    .yellow()
-                );
-            }
-        }
-        println!();
-    } else {
-        println!(
-            "{}",
-            "ℹ️  GEMINI_API_KEY not set. Using basic context analysis (no LLM).".yellow()
-        );
-        println!(
-            "{}",
-            "   Set GEMINI_API_KEY environment variable for advanced LLM-powered analysis."
-                .bright_black()
-        );
-        println!();
+    if json_output() {
+        return print_json(&serde_json::to_value(&impact)?);
     }
 
-    // Try to initialize vector store for semantic recall (re-use same per-project collection)
-    let qdrant_url =
-        std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
-    let collection_name = collection_name_for_path(&path);
-    match miow_vector::VectorStore::new(&qdrant_url, &collection_name).await {
-        Ok(store) => {
-            orchestrator = orchestrator.with_vector_store(std::sync::Arc::new(store));
-            println!(
-                "{}",
-                "✅ Vector store (Qdrant) ready for generation!".green()
-            );
-        }
-        Err(e) => {
-            println!(
-                "{}",
-                format!(
-                    "⚠️  Vector store not available: {}. Continuing with graph-only search.",
-                    e
-                )
-                .yellow()
+    if impact.locations.is_empty() {
+        say!("Nothing named '{}' is indexed.", symbol);
+        return Ok(());
+    }
+    let files = impact.files();
+    say!(
+        "{} {} site(s) in {} file(s)",
+        format!("Renaming {}:", symbol).bold(),
+        impact.locations.len(),
+        files.len()
+    );
+    for file in files {
+        say!();
+        say!("{}", file.cyan());
+        for location in impact.locations.iter().filter(|l| l.file_path == file) {
+            let detail = match (&location.symbol, &location.source) {
+                (Some(name), _) => format!("{} ", name.bright_yellow()),
+                (None, Some(source)) => format!("from {} ", source),
+                (None, None) => String::new(),
+            };
+            let lines: Vec<String> = location.lines.iter().map(|l| l.to_string()).collect();
+            let lines = if lines.is_empty() {
+                format!("{}-{}", location.start_line, location.end_line)
+            } else {
+                lines.join(", ")
+            };
+            say!(
+                "  {:<10} {}{}",
+                location.kind.as_str(),
+                detail,
+                format!("line(s) {}", lines).bright_black()
             );
         }
     }
+    Ok(())
+}
 
-    println!("{}", "🔍 Analyzing prompt...".cyan());
-    println!("User prompt: \"{}\"", prompt.bright_blue());
-    println!();
+/// Code in the project's vector store similar to `query`, narrowed by `filters`
+async fn query_similar(
+    path: &Path,
+    db_path: &Path,
+    query: &str,
+    filters: &SearchFilters,
+    limit: usize,
+) -> Result<Vec<miow_vector::SymbolSearchResult>> {
+    let store = open_vector_store(path, db_path).await.context("Vector store not available")?;
+
+    // Filters apply after the search, so ask for more than will be shown
+    let fetch = if filters.kind.is_some() || filters.file.is_some() { limit * 5 } else { limit };
+    let mut results = store.search_similar(query, fetch).await?;
+    results.retain(|r| {
+        filters.kind.as_ref().is_none_or(|k| r.symbol.kind.eq_ignore_ascii_case(k))
+            && filters.file.as_ref().is_none_or(|f| r.symbol.file_path.contains(f.as_str()))
+    });
+    results.truncate(limit);
+    Ok(results)
+}
 
-    // Generate context-aware prompt using Universal Knowledge Graph workflow
-    let generated_prompt = orchestrator.generate_autonomous_prompt(
-        path.to_str().unwrap(),
-        &prompt,
-        None // No event streaming for CLI
-    ).await?;
+/// A vector search hit for `--json` and `rpc`
+fn similar_hit_json(result: &miow_vector::SymbolSearchResult) -> serde_json::Value {
+    serde_json::json!({
+        "name": result.symbol.name,
+        "kind": result.symbol.kind,
+        "file_path": result.symbol.file_path,
+        "score": result.score,
+    })
+}
 
-    println!("{}", "✅ Context-aware prompt generated!".green().bold());
-    println!();
-    println!("{}", "═".repeat(80).bright_black());
-    println!();
-    println!("{}", generated_prompt);
-    println!();
-    println!("{}", "═".repeat(80).bright_black());
+/// Search the project's vector store for code similar to `query`
+async fn handle_semantic_search(path: &Path, db_path: &Path, query: &str, filters: &SearchFilters, limit: usize) -> Result<()> {
+    let results = query_similar(path, db_path, query, filters, limit).await?;
 
-    // Save to file if requested
-    if let Some(output_path) = output {
-        std::fs::write(&output_path, &generated_prompt)?;
-        println!();
-        println!("💾 Prompt saved to: {}", output_path.display());
+    if json_output() {
+        let hits: Vec<serde_json::Value> = results.iter().map(similar_hit_json).collect();
+        return print_json(&serde_json::Value::from(hits));
     }
 
+    if results.is_empty() {
+        say!("No similar code found.");
+        return Ok(());
+    }
+    for result in &results {
+        say!(
+            "{:.3} {} {}  {}",
+            result.score,
+            result.symbol.name.bright_yellow(),
+            format!("({})", result.symbol.kind).bright_black(),
+            result.symbol.file_path.cyan()
+        );
+    }
     Ok(())
 }
 
-// Helper function to convert parser output to graph data
-fn convert_to_graph_data(parsed: miow_parsers::ParsedFile) -> ParsedFileData {
-    ParsedFileData {
-        symbols: parsed.symbols.into_iter().map(convert_symbol).collect(),
-        imports: parsed
-            .imports
-            .into_iter()
-            .map(|imp| ImportData {
-                source: imp.source,
-                names: imp.names.into_iter().map(|n| n.name).collect(),
-                start_line: imp.range.start_line,
-                end_line: imp.range.end_line,
-            })
-            .collect(),
-        design_tokens: parsed
-            .design_tokens
-            .into_iter()
-            .map(|token| DesignTokenData {
-                token_type: format!("{:?}", token.token_type),
-                name: token.name,
-                value: token.value,
-                context: token.context,
-                start_line: token.range.start_line,
-                end_line: token.range.end_line,
-            })
-            .collect(),
-        type_definitions: parsed
-            .type_definitions
-            .into_iter()
-            .map(|td| miow_graph::TypeDefinitionData {
-                name: td.name,
-                kind: format!("{:?}", td.kind),
-                definition: td.definition,
-                start_line: td.range.start_line,
-                end_line: td.range.end_line,
-            })
-            .collect(),
-        constants: parsed
-            .constants
-            .into_iter()
-            .map(|c| miow_graph::ConstantData {
-                name: c.name,
-                value: c.value,
-                category: format!("{:?}", c.category),
-                start_line: c.range.start_line,
-                end_line: c.range.end_line,
-            })
-            .collect(),
-        schemas: parsed
-            .schemas
-            .into_iter()
-            .map(|s| miow_graph::SchemaData {
-                name: s.name,
-                schema_type: format!("{:?}", s.schema_type),
-                definition: s.definition,
-                start_line: s.range.start_line,
-                end_line: s.range.end_line,
-            })
-            .collect(),
-        language: parsed.language,
-    }
+/// The watch daemon's control socket lives in `.miow` next to the knowledge graph database
+fn watch_socket_for_db(db_path: &Path) -> PathBuf {
+    repos_dir_for_db(db_path).with_file_name("watch.sock")
 }
 
-async fn test_autonomous_system(task: String, path: PathBuf) -> Result<()> {
-    println!("{}", "🧠 AUTONOMOUS SYSTEM TEST".bright_blue().bold());
-    println!("{}", "═══════════════════════════════════════════════════════════════".bright_black());
+/// Bring the index up to date, then sync every change under `path` until the
+/// daemon is stopped through its socket or with Ctrl-C
+async fn handle_watch(path: PathBuf, db_path: PathBuf, socket: PathBuf, debounce: std::time::Duration) -> Result<()> {
+    say!("{}", "👀 MIOW-CONTEXT WATCH".bright_blue().bold());
+    say!("{}", "═".repeat(50).bright_black());
 
-    println!("📋 Task: {}", task.bright_yellow());
-    println!("📁 Codebase: {}", path.display().to_string().bright_cyan());
+    // Catch up with whatever changed while nothing was watching
+    if db_path.exists() {
+        run_index(path.clone(), db_path.clone(), IndexMode::Incremental, None).await?;
+    } else {
+        handle_index(path.clone(), db_path.clone()).await?;
+    }
+    say!();
+
+    let vector_store = open_vector_store(&path, &db_path).await.ok();
+    let graph = std::sync::Arc::new(KnowledgeGraph::new(&db_path)?);
+    // Change events carry absolute paths, which must resolve against the root
+    let root = path.canonicalize().unwrap_or_else(|_| path.clone());
+    let mut indexer = project_indexer(root.clone())?.with_graph(graph.clone());
+    let has_vectors = vector_store.is_some();
+    if let Some(store) = vector_store {
+        indexer = indexer.with_vector_store(std::sync::Arc::new(store));
+    }
 
-    // Initialize LLM client
-    let api_key = std::env::var("GEMINI_API_KEY")
-        .map_err(|_| anyhow::anyhow!("GEMINI_API_KEY environment variable not set"))?;
-
-    use miow_llm::{GeminiClient, LLMConfig, LLMProvider};
-    let llm_config = LLMConfig {
-        api_key,
-        model: "gemini-2.5-flash".to_string(),
-        temperature: 0.7,
-        max_tokens: 4096,
+    say!("📁 Watching: {}", path.display());
+    say!("💾 Database: {}", db_path.display());
+    say!("🧭 Vector store: {}", if has_vectors { "syncing" } else { "not available, graph only" });
+    say!("🔌 Control: {} (miow-context watch --status | --stop)", socket.display());
+    say!();
+
+    let options = watch::WatchOptions {
+        root,
+        database: db_path.clone(),
+        debounce,
+        socket,
+        vector_store: has_vectors,
     };
-    let llm: Box<dyn LLMProvider> = Box::new(GeminiClient::new(llm_config)?);
+    let status = watch::run(indexer, graph, options, |file, result| match result {
+        Ok(miow_core::FileSync::Indexed { symbols }) => say!("🔄 {} ({} symbols)", file, symbols),
+        Ok(miow_core::FileSync::Removed) => say!("🗑️  {}", file),
+        Ok(miow_core::FileSync::Skipped) => {}
+        Err(e) => say!("{}", format!("❌ {}: {}", file, e).red()),
+    })
+    .await?;
 
-    println!("\n🤖 LLM Autonomous Planning Analysis:");
-    println!("{}", "─".repeat(50).bright_black());
+    say!();
+    say!(
+        "{}",
+        format!("✅ Watch stopped: {} files synced, {} removed, {} errors", status.indexed, status.removed, status.errors).green()
+    );
+    if json_output() {
+        print_json(&serde_json::to_value(&status)?)?;
+    }
+    Ok(())
+}
 
-    // Autonomous task planning prompt
-    let plan_prompt = format!(
-        "Autonomously analyze task '{}' in the codebase at '{}'.\n
-        Task Analysis Protocol:
-        1. DETECT REQUIREMENTS: What does this task fundamentally need?
-        2. SEARCH CODEBASE: What existing services/utilities/patterns can I find?
-        3. MAKE DECISIONS: Should I reuse existing code or plan new implementations?
-        4. NO BIASES: Don't assume specific technologies - discover from code patterns
-        5. BE SPECIFIC: Reference actual patterns found in the codebase
+/// Ask the running watch daemon for its status, or to stop
+async fn handle_watch_control(socket: &Path, command: &str) -> Result<()> {
+    let response = watch::request(socket, command).await?;
+    if let Some(error) = response.get("error").and_then(|e| e.as_str()) {
+        anyhow::bail!("{}", error);
+    }
+    if json_output() {
+        return print_json(&response);
+    }
 
-        Output Format:
-        {{
-            \"requirements\": [\"list of what task needs\"],
-            \"existing_services\": [\"services found in codebase\"],
-            \"decisions\": [\"specific decisions made\"],
-            \"plan\": \"detailed implementation plan\",
-            \"confidence\": \"high/medium/low\"
-        }}",
-        task, path.display()
-    );
+    let field = |name: &str| response.get(name).cloned().unwrap_or_default();
+    if command == "stop" {
+        say!("{}", "🛑 Watch daemon stopping after it syncs pending changes".green());
+    }
+    say!("📁 Watching: {}", field("root").as_str().unwrap_or_default());
+    say!("💾 Database: {}", field("database").as_str().unwrap_or_default());
+    say!("🔢 PID: {}, up {}s", field("pid"), field("uptime_secs"));
+    say!("🔄 Synced: {} files, {} removed, {} pending", field("indexed"), field("removed"), field("pending"));
+    if let Some(last_sync) = field("last_sync").as_u64() {
+        let ago = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs().saturating_sub(last_sync))
+            .unwrap_or_default();
+        say!("🕒 Last sync: {}s ago", ago);
+    }
+    if field("errors").as_u64().unwrap_or_default() > 0 {
+        say!("{}", format!("⚠️  {} errors, last: {}", field("errors"), field("last_error").as_str().unwrap_or_default()).yellow());
+    }
+    Ok(())
+}
 
-    match llm.generate(&plan_prompt).await {
-        Ok(response) => {
-            println!("{}", response.content.bright_green());
+/// Remove index artifacts after confirmation: the database, caches and the
+/// Qdrant collection, and with `all` every `.miow` directory of the project
+async fn handle_clean(db_path: &Path, path: &Path, all: bool, assume_yes: bool) -> Result<()> {
+    let repos_dir = repos_dir_for_db(db_path);
+    let miow_dir = repos_dir.parent().unwrap_or(Path::new("."));
+    let mut candidates = vec![
+        db_path.to_path_buf(),
+        PathBuf::from(format!("{}-wal", db_path.display())),
+        PathBuf::from(format!("{}-shm", db_path.display())),
+        path.join(".miow_cache.json"),
+        miow_dir.join("cache"),
+    ];
+    if all {
+        candidates.push(miow_dir.to_path_buf());
+        candidates.push(path.join(".miow"));
+    }
+    // Skip anything inside a directory that is removed anyway
+    let existing: Vec<PathBuf> = candidates
+        .into_iter()
+        .filter(|p| p.exists())
+        .filter_map(|p| p.canonicalize().ok())
+        .collect();
+    let mut targets: Vec<PathBuf> = Vec::new();
+    for target in &existing {
+        let covered = existing.iter().any(|other| other != target && target.starts_with(other));
+        if !covered && !targets.contains(target) {
+            targets.push(target.clone());
+        }
+    }
+    // Never take the user's own settings with it, e.g. `$MIOW_CONFIG_DIR` pointed at a project's `.miow`
+    if let Some(user_dir) = miow_core::user_config_dir().and_then(|dir| dir.canonicalize().ok()) {
+        targets.retain(|target| {
+            let keep = !user_dir.starts_with(target);
+            if !keep {
+                say!("{}", format!("⚠️  Keeping {}: it holds your user settings", target.display()).yellow());
+            }
+            keep
+        });
+    }
+
+    let qdrant_url =
+        std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
+    let collection = collection_name_for_path(path);
+    let vectors = miow_vector::VectorStore::collection_size(&qdrant_url, &collection).await;
 
-            println!("\n✅ Autonomous Analysis Complete!");
-            println!("{}", "─".repeat(50).bright_black());
-            println!("🎯 Key Achievements:");
-            println!("  • LLM analyzed task without hardcoded biases");
-            println!("  • Discovered services autonomously from codebase");
-            println!("  • Made independent decisions about reuse vs. implementation");
-            println!("  • Adapted to detected patterns (no assumptions)");
+    if targets.is_empty() && !matches!(vectors, Ok(Some(_))) {
+        say!("{}", "✨ Nothing to clean.".green());
+        if json_output() {
+            print_json(&serde_json::json!({ "removed": [], "collection": null }))?;
         }
-        Err(e) => {
-            println!("❌ LLM Error: {}", e.to_string().bright_red());
+        return Ok(());
+    }
+
+    say!("{}", "🧹 This will delete:".yellow().bold());
+    for target in &targets {
+        say!("  {}", target.display());
+    }
+    match &vectors {
+        Ok(Some(count)) => say!("  Qdrant collection {} ({} vectors)", collection, count),
+        Ok(None) => {}
+        Err(e) => say!("{}", format!("⚠️  Vector store not available, keeping its collection: {}", e).yellow()),
+    }
+
+    if !assume_yes && !confirm("Delete these? [y/N] ")? {
+        say!("Aborted.");
+        return Ok(());
+    }
+
+    for target in &targets {
+        if target.is_dir() {
+            std::fs::remove_dir_all(target)?;
+        } else {
+            std::fs::remove_file(target)?;
         }
     }
+    let dropped = match vectors {
+        Ok(Some(_)) => miow_vector::VectorStore::delete_collection(&qdrant_url, &collection).await?,
+        _ => false,
+    };
 
+    say!("{}", "✅ Clean complete. Run 'miow-context init' to index again.".green().bold());
+    if json_output() {
+        print_json(&serde_json::json!({
+            "removed": targets,
+            "collection": dropped.then_some(collection),
+        }))?;
+    }
     Ok(())
 }
 
-fn convert_symbol(symbol: miow_parsers::Symbol) -> SymbolData {
-    SymbolData {
-        name: symbol.name,
-        kind: format!("{:?}", symbol.kind),
-        start_line: symbol.range.start_line,
-        end_line: symbol.range.end_line,
-        start_byte: symbol.range.start_byte,
-        end_byte: symbol.range.end_byte,
-        content: symbol.content,
-        metadata: serde_json::to_string(&symbol.metadata).unwrap_or_default(),
-        style_tags: None, // Will be populated during style analysis
-        children: symbol.children.into_iter().map(convert_symbol).collect(),
-        references: symbol.references,
+/// Ask a yes/no question on the terminal; fails when there is none
+fn confirm(question: &str) -> Result<bool> {
+    use std::io::{IsTerminal, Write};
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("Confirmation needed but there is no terminal; rerun with --yes");
     }
+    eprint!("{}", question);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
 }
 
-#[cfg(feature = "web")]
-async fn start_web_server(port: u16, _db_path: PathBuf) -> Result<()> {
-    println!("{}", "🌐 Starting MIOW-CONTEXT Web Server".bright_blue().bold());
-    println!("{}", "═".repeat(50).bright_black());
-    println!("📍 Port: {}", port);
-    println!("🌍 Web UI: http://localhost:{}", port);
-    println!("🚀 API: http://localhost:{}/api", port);
-    println!();
+/// Print the index health report
+async fn handle_stats(db_path: &Path, path: &Path, largest: usize) -> Result<()> {
+    if !db_path.exists() {
+        return Err(GraphError::NotIndexed(db_path.to_path_buf()).into());
+    }
+    let graph = KnowledgeGraph::new(db_path)?;
+    let stats = graph.index_stats(largest)?;
+
+    let indexed_commit = graph.get_metadata(miow_core::git::INDEXED_COMMIT_KEY)?;
+    let head = miow_core::git::head_commit(path);
+    let commits_behind = match (&indexed_commit, &head) {
+        (Some(indexed), Some(head)) if indexed == head => Some(0),
+        (Some(indexed), Some(_)) => miow_core::git::commits_since(path, indexed),
+        _ => None,
+    };
 
-    let mut llm: Option<std::sync::Arc<dyn miow_llm::LLMProvider>> = None;
+    let qdrant_url =
+        std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
+    let collection = collection_name_for_path(path);
+    let vectors = miow_vector::VectorStore::collection_size(&qdrant_url, &collection).await;
+    // Vectors indexed while Qdrant was down live in the database
+    let fallback_vectors = match &vectors {
+        Err(_) => miow_vector::SqliteIndex::collection_size(db_path, &collection)?,
+        Ok(_) => None,
+    };
 
-    // Try to initialize LLM if API key is available
-    if let Ok(api_key) = std::env::var("GEMINI_API_KEY") {
-        println!("{}", "🤖 LLM integration enabled (Gemini)".green());
-        use miow_llm::{GeminiClient, LLMConfig};
-        
-        let llm_config = LLMConfig {
-            api_key,
-            model: "gemini-2.5-flash".to_string(),
-            temperature: 0.7,
-            max_tokens: 4096,
-        };
-        match GeminiClient::new(llm_config) {
-            Ok(client) => {
-                llm = Some(std::sync::Arc::new(client));
-                println!("{}", "✅ LLM client initialized successfully".green());
-            }
-            Err(e) => {
-                println!("{}", format!("⚠️  Failed to initialize LLM: {}", e).yellow());
-            }
-        }
+    if json_output() {
+        let report = serde_json::json!({
+            "database": db_path,
+            "files": stats.languages.iter().map(|l| l.files).sum::<usize>(),
+            "symbols": stats.languages.iter().map(|l| l.symbols).sum::<usize>(),
+            "references": stats.languages.iter().map(|l| l.references).sum::<usize>(),
+            "languages": stats.languages,
+            "largest_files": stats.largest_files,
+            "index_errors": stats.index_errors,
+            "files_with_errors": stats.files_with_errors,
+            "last_indexed_at": stats.last_indexed_at,
+            "indexed_commit": indexed_commit,
+            "head_commit": head,
+            "commits_behind": commits_behind,
+            "vector_collection": collection,
+            "vectors": vectors.as_ref().ok().copied().flatten().or(fallback_vectors),
+            "vector_backend": if fallback_vectors.is_some() { "sqlite" } else { "qdrant" },
+        });
+        return print_json(&report);
     }
 
-    let state = AppState { llm };
+    say!("{}", "📊 INDEX STATS".bright_blue().bold());
+    say!("{}", "═".repeat(60).bright_black());
+    say!("💾 Database: {}", db_path.display());
+    say!("🕒 Last indexed: {}", stats.last_indexed_at.as_deref().unwrap_or("never"));
+    say!();
 
-    // Create router
-    let app = Router::new()
-        .route("/api/generate", post(generate_handler))
-        .route("/api/generate-stream", post(generate_stream_handler))
-        .route("/api/generate-with-files", post(generate_with_files_handler))
-        .route("/api/files", post(files_handler))
-        .route("/api/debug/signature", post(debug_signature_handler))
-        .route("/api/debug/context", post(debug_context_handler))
-        .route("/api/health", post(health_handler))
-        .layer(CorsLayer::permissive())
-        .with_state(state);
+    say!("{:<14} {:>8} {:>10} {:>12}", "LANGUAGE", "FILES", "SYMBOLS", "REFERENCES");
+    for language in &stats.languages {
+        say!("{:<14} {:>8} {:>10} {:>12}", language.language, language.files, language.symbols, language.references);
+    }
+    say!(
+        "{:<14} {:>8} {:>10} {:>12}",
+        "total".bold(),
+        stats.languages.iter().map(|l| l.files).sum::<usize>(),
+        stats.languages.iter().map(|l| l.symbols).sum::<usize>(),
+        stats.languages.iter().map(|l| l.references).sum::<usize>()
+    );
+    say!();
 
-    // Start server
-    let addr = format!("0.0.0.0:{}", port);
-    let listener = TcpListener::bind(&addr).await?;
-    println!("✅ Server running at {}", addr.bright_green());
+    if !stats.largest_files.is_empty() {
+        say!("📏 Largest files:");
+        for file in &stats.largest_files {
+            say!("   {:>6} lines {:>5} symbols  {}", file.lines, file.symbols, file.path.cyan());
+        }
+        say!();
+    }
+
+    if stats.index_errors == 0 {
+        say!("{}", "✅ No parse errors".green());
+    } else {
+        say!(
+            "{}",
+            format!("⚠️  {} parse errors in {} files", stats.index_errors, stats.files_with_errors).yellow()
+        );
+    }
 
-    axum::serve(listener, app).await?;
+    match (&indexed_commit, commits_behind) {
+        (Some(_), _) if head.is_none() => say!("{}", "ℹ️  The codebase isn't a git repository; staleness unknown".bright_black()),
+        (Some(_), Some(0)) => say!("{}", "✅ Index is up to date with HEAD".green()),
+        (Some(indexed), Some(behind)) => {
+            let short: String = indexed.chars().take(12).collect();
+            say!(
+                "{}",
+                format!("⚠️  Index is {} commits behind HEAD. Run 'miow-context reindex --since {}'", behind, short).yellow()
+            );
+        }
+        (Some(_), None) => say!("{}", "⚠️  Index was built from a commit HEAD doesn't descend from".yellow()),
+        (None, _) => say!("{}", "ℹ️  No commit recorded; the codebase isn't a git repository".bright_black()),
+    }
 
+    match (vectors, fallback_vectors) {
+        (Ok(Some(count)), _) => say!("🧮 Vectors: {} in {}", count, collection),
+        (Ok(None), _) => say!("{}", format!("ℹ️  No vector collection {}", collection).bright_black()),
+        (Err(_), Some(count)) => say!("🧮 Vectors: {} in the SQLite fallback index (Qdrant unreachable)", count),
+        (Err(e), None) => say!("{}", format!("⚠️  Vector store not available: {}", e).yellow()),
+    }
     Ok(())
 }
 
-#[cfg(feature = "web")]
-async fn generate_handler(
-    State(state): State<AppState>,
-    Json(request): Json<GenerateRequest>,
-) -> Result<Json<GenerateResponse>, StatusCode> {
-    println!("🤖 Processing request: {}", request.user_prompt.bright_yellow());
-
-    let codebase_path = PathBuf::from(&request.codebase_path);
-    
-    // Determine project-specific DB path
-    let db_path = codebase_path.join(".miow").join("miow.db");
-    let db_dir = db_path.parent().unwrap();
-    
-    // Create .miow directory if it doesn't exist
-    if !db_dir.exists() {
-        if let Err(e) = std::fs::create_dir_all(db_dir) {
-             return Ok(Json(GenerateResponse {
-                success: false,
-                result: None,
-                error: Some(format!("Failed to create .miow directory: {}", e)),
-            }));
+/// Run the TUI; each prompt it asks for is generated on the normal screen, then browsing resumes
+#[cfg(feature = "tui")]
+async fn handle_tui(db_path: PathBuf, path: PathBuf, llm: LlmArgs, assume_yes: bool) -> Result<()> {
+    if !db_path.exists() {
+        return Err(GraphError::NotIndexed(db_path.to_path_buf()).into());
+    }
+    let mut app = tui::App::new(KnowledgeGraph::new(&db_path)?)?;
+    while let tui::TuiExit::Generate(task) = tui::run(&mut app)? {
+        let run = (miow_agent::RunStore::new(runs_dir_for_db(&db_path)), miow_agent::RunStore::new_run_id());
+        let generated = handle_generate_autonomous(
+            path.clone(),
+            task,
+            db_path.clone(),
+            None,
+            None,
+            false,
+            PromptArgs::default(),
+            BudgetArgs::default(),
+            CostArgs::default(),
+            llm.clone(),
+            assume_yes,
+            Some(run),
+        )
+        .await;
+        if let Err(e) = generated {
+            say!("{}", format!("❌ {}", e).red());
         }
+        say!();
+        say!("{}", "Press Enter to return to the browser...".bright_black());
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
     }
+    Ok(())
+}
 
-    // Auto-index if DB doesn't exist
-    if !db_path.exists() {
-        println!("{}", format!("⚠️  No index found for {}. Indexing now...", codebase_path.display()).yellow());
-        println!("{}", "⏳ This may take a few minutes depending on codebase size...".bright_black());
-        
-        match handle_index(codebase_path.clone(), db_path.clone()).await {
-            Ok(_) => {
-                println!("✅ Indexing completed successfully");
+#[cfg(not(feature = "tui"))]
+async fn handle_tui(_db_path: PathBuf, _path: PathBuf, _llm: LlmArgs, _assume_yes: bool) -> Result<()> {
+    anyhow::bail!("TUI not enabled. Compile with --features tui")
+}
+
+/// Print saved runs, newest first
+fn handle_token(action: TokenAction) -> Result<()> {
+    match action {
+        TokenAction::Create { name, paths, db } => {
+            let mut roots = Vec::new();
+            for path in &paths {
+                let root = path
+                    .canonicalize()
+                    .with_context(|| format!("Allowed path {} does not exist", path.display()))?;
+                roots.push(root.to_string_lossy().to_string());
             }
-            Err(e) => {
-                let error_msg = format!(
-                    "Indexing failed: {}. This might be due to:\n- Insufficient permissions\n- Corrupted files\n- Network issues (if using remote paths)\n\nPlease check the path and try again.",
-                    e
+            let token = auth::generate_token();
+            let created_at = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or_default();
+            KnowledgeGraph::new(&db)?.add_api_token(&miow_graph::ApiTokenRecord {
+                name: name.clone(),
+                token_hash: auth::hash_token(&token),
+                paths: roots.clone(),
+                created_at,
+            })?;
+
+            say!("{}", format!("🔑 Created token '{}'", name).green().bold());
+            if roots.is_empty() {
+                say!("  Allowed codebases: any");
+            } else {
+                say!("  Allowed codebases: {}", roots.join(", "));
+            }
+            say!("  Send it as 'Authorization: Bearer <token>'. It is not stored and can't be shown again:");
+            if json_output() {
+                print_json(&serde_json::json!({ "name": name, "token": token, "paths": roots }))?;
+            } else {
+                println!("{}", token);
+            }
+        }
+        TokenAction::List { db } => {
+            let tokens = KnowledgeGraph::new(&db)?.list_api_tokens()?;
+            if json_output() {
+                let tokens: Vec<_> = tokens
+                    .iter()
+                    .map(|t| serde_json::json!({ "name": t.name, "paths": t.paths, "created_at": t.created_at }))
+                    .collect();
+                return print_json(&serde_json::Value::Array(tokens));
+            }
+            if tokens.is_empty() {
+                println!("No tokens yet. Create one with 'miow-context token create <name>'.");
+                return Ok(());
+            }
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            println!("{:<20} {:>9}  CODEBASES", "NAME", "CREATED");
+            for token in &tokens {
+                let paths = if token.paths.is_empty() { "any".to_string() } else { token.paths.join(", ") };
+                println!(
+                    "{:<20} {:>9}  {}",
+                    token.name,
+                    format_age(now.saturating_sub(token.created_at.max(0) as u64)),
+                    paths
                 );
-                return Ok(Json(GenerateResponse {
-                    success: false,
-                    result: None,
-                    error: Some(error_msg),
-                }));
+            }
+        }
+        TokenAction::Revoke { name, db } => {
+            let revoked = KnowledgeGraph::new(&db)?.revoke_api_token(&name)?;
+            if !revoked {
+                anyhow::bail!("No token named '{}'", name);
+            }
+            say!("{}", format!("🗑️  Revoked token '{}'", name).green());
+            if json_output() {
+                print_json(&serde_json::json!({ "revoked": name }))?;
             }
         }
     }
+    Ok(())
+}
+
+fn handle_history(db_path: &Path, limit: usize) -> Result<()> {
+    let runs = miow_agent::RunStore::new(runs_dir_for_db(db_path)).list()?;
+    if runs.is_empty() {
+        println!("No runs saved yet. Runs of 'miow-context ask' are kept in {}", runs_dir_for_db(db_path).display());
+        return Ok(());
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    println!("{:<22} {:>9} {:<10} {:>8}  TASK", "RUN", "STARTED", "STATUS", "TOKENS");
+    for run in runs.iter().take(limit) {
+        let status = if run.prompt.is_some() {
+            "complete".green()
+        } else if run.error.is_some() {
+            "failed".red()
+        } else {
+            "partial".yellow()
+        };
+        let tokens = run.record.as_ref().map(|r| r.prompt_tokens.to_string()).unwrap_or_else(|| "-".to_string());
+        let task: String = run.task.chars().take(60).collect();
+        println!(
+            "{:<22} {:>9} {:<10} {:>8}  {}",
+            run.run_id,
+            format_age(now.saturating_sub(run.started_at)),
+            status,
+            tokens,
+            task
+        );
+    }
+    if runs.len() > limit {
+        println!("{}", format!("... {} older runs (use -n to show more)", runs.len() - limit).bright_black());
+    }
+    Ok(())
+}
+
+/// Print a saved run: its inputs, what the prompt was built from and the prompt
+fn handle_show(db_path: &Path, run_id: &str, prompt_only: bool) -> Result<()> {
+    let run = miow_agent::RunStore::new(runs_dir_for_db(db_path)).load(run_id)?;
+    if prompt_only {
+        match &run.prompt {
+            Some(prompt) => println!("{}", prompt),
+            None => anyhow::bail!("Run '{}' has no prompt yet; resume it with: miow-context ask --resume {}", run_id, run_id),
+        }
+        return Ok(());
+    }
+
+    println!("{}", format!("🧾 Run {}", run.run_id).bright_blue().bold());
+    println!("📝 Task: {}", run.task.bright_yellow());
+    println!("📁 Codebase: {}", run.project_root.display());
+    if let Some(from) = &run.rerun_of {
+        println!("🔁 Rerun of: {}", from);
+    }
+    if let Some(from) = &run.follow_up_of {
+        println!("🧵 Session {}, following up on run {}", run.session(), from);
+    }
+    if !run.selected_files.is_empty() {
+        println!("📋 Selected files: {}", run.selected_files.join(", "));
+    }
+    if let Some(usage) = &run.usage {
+        println!("💰 LLM usage: {} calls, {} tokens (~${:.4})", usage.calls, usage.total_tokens, usage.estimated_cost_usd);
+    }
+    if let Some(error) = &run.error {
+        println!("{}", format!("❌ Failed: {}", error).red());
+    }
+    if let Some(record) = &run.record {
+        if let Some(model) = &record.model {
+            println!("🤖 Model: {}", model);
+        }
+        println!("🔢 Tokens: {} in the prompt, {} of context", record.prompt_tokens, record.context_tokens);
+        println!("⚙️  Config: {}", record.config);
+        println!("📚 Context ({} items):", record.context_ids.len());
+        for id in &record.context_ids {
+            println!("   - {}", id);
+        }
+    }
+    println!();
+    match &run.prompt {
+        Some(prompt) => {
+            println!("{}", "═".repeat(80).bright_black());
+            println!("{}", prompt);
+        }
+        None => println!("{}", format!("⏯️  Not finished. Resume with: miow-context ask --resume {}", run.run_id).yellow()),
+    }
+    println!();
+    println!("{}", format!("💡 Regenerate with other settings: miow-context ask --rerun {} --format xml", run.run_id).bright_black());
+    if run.record.is_some() {
+        println!("{}", format!("👍 Rate its context: miow-context feedback {} --good (or --bad, optionally --files ...)", run.run_id).bright_black());
+    }
+    Ok(())
+}
+
+/// The items of a run's prompt that feedback on `files` is about, or why there are none
+fn feedback_items(run: &miow_agent::RunState, files: &[String]) -> std::result::Result<Vec<miow_agent::PromptItem>, String> {
+    let Some(record) = &run.record else {
+        return Err(format!("Run '{}' has no prompt to give feedback on", run.run_id));
+    };
+    let items = record.items_in(files);
+    match (items.is_empty(), files.is_empty()) {
+        (false, _) => Ok(items),
+        (true, true) => Err(format!("Run '{}' has no symbols or files in its context", run.run_id)),
+        (true, false) => Err(format!("None of {} is in the context of run '{}'", files.join(", "), run.run_id)),
+    }
+}
+
+/// Record whether a run's context (or the part from `files`) was useful
+fn handle_feedback(db_path: &Path, run_id: &str, useful: bool, files: &[String]) -> Result<()> {
+    let run = miow_agent::RunStore::new(runs_dir_for_db(db_path)).load(run_id)?;
+    let items = feedback_items(&run, files).map_err(|message| anyhow::anyhow!(message))?;
+    let graph = KnowledgeGraph::new(db_path)?;
+    for item in &items {
+        graph.record_feedback(&item.file_path, item.name.as_deref().unwrap_or(""), useful)?;
+    }
+
+    if json_output() {
+        return print_json(&serde_json::json!({ "run_id": run_id, "useful": useful, "items": items }));
+    }
+    let verdict = if useful { "👍 Useful" } else { "👎 Not useful" };
+    println!("{}", format!("{}: {} items of run {}", verdict, items.len(), run_id).green().bold());
+    for item in &items {
+        match &item.name {
+            Some(name) => println!("   - {}  {}", name, item.file_path.bright_black()),
+            None => println!("   - {}", item.file_path),
+        }
+    }
+    println!("{}", "Later runs on this index rank these up or down accordingly.".bright_black());
+    Ok(())
+}
+
+/// How long ago `secs` seconds is, e.g. "42s ago" or "3h ago"
+fn format_age(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s ago", s),
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s if s < 86_400 => format!("{}h ago", s / 3600),
+        s => format!("{}d ago", s / 86_400),
+    }
+}
+
+/// Checkout recorded by `init --git`, if the database was built from a remote repository
+fn recorded_checkout(db_path: &Path) -> Option<PathBuf> {
+    if !db_path.exists() {
+        return None;
+    }
+    let graph = KnowledgeGraph::new(db_path).ok()?;
+    let checkout = graph.get_metadata(miow_core::git::CHECKOUT_PATH_KEY).ok().flatten()?;
+    Some(PathBuf::from(checkout))
+}
+
+/// Print a warning when HEAD has moved past the commit the index was built from
+fn warn_if_index_behind_head(path: &Path, db_path: &Path) {
+    let Ok(graph) = KnowledgeGraph::new(db_path) else {
+        return;
+    };
+    let indexed = graph.get_metadata(miow_core::git::INDEXED_COMMIT_KEY).ok().flatten();
+    let (Some(indexed), Some(head)) = (indexed, miow_core::git::head_commit(path)) else {
+        return;
+    };
+    if indexed == head {
+        return;
+    }
+
+    let behind = match miow_core::git::commits_since(path, &indexed) {
+        Some(count) => format!("{} commits", count),
+        None => "an unknown number of commits".to_string(),
+    };
+    let short: String = indexed.chars().take(12).collect();
+    say!(
+        "{}",
+        format!(
+            "⚠️  The index is {} behind HEAD (indexed at {}). Run 'miow-context reindex --since {}' to catch up.",
+            behind, short, short
+        )
+        .yellow()
+    );
+    say!();
+}
+
+async fn handle_index(path: PathBuf, db_path: PathBuf) -> Result<miow_core::IndexReport> {
+    run_index(path, db_path, IndexMode::Full, None).await
+}
+
+/// Which files `run_index` parses
+enum IndexMode {
+    Full,
+    /// Files whose content changed since the last index
+    Incremental,
+    /// Files git reports as changed since a revision
+    Since(String),
+}
+
+/// Index into the knowledge graph at `db_path`. Progress goes to `progress`
+/// when given, otherwise it is drawn as progress bars.
+async fn run_index(
+    path: PathBuf,
+    db_path: PathBuf,
+    mode: IndexMode,
+    progress: Option<ProgressSender>,
+) -> Result<miow_core::IndexReport> {
+    say!("{}", "🔍 Indexing codebase...".cyan().bold());
+    say!("Path: {}", path.display());
+    say!("Database: {}", db_path.display());
+    say!();
+
+    // Qdrant if it is available (per-project collection), otherwise the SQLite index
+    let vector_store = match open_vector_store(&path, &db_path).await {
+        Ok(store) => {
+            say!("{}", format!("✅ Vector store ({}) connected!", vector_store_label(&store)).green());
+            Some(std::sync::Arc::new(store))
+        }
+        Err(e) => {
+            say!(
+                "{}",
+                format!(
+                    "⚠️  Vector store not available: {}. Continuing without vector search.",
+                    e
+                )
+                .yellow()
+            );
+            None
+        }
+    };
+
+    // Parsed files stream straight into the knowledge graph while indexing
+    let graph = std::sync::Arc::new(KnowledgeGraph::new(&db_path)?);
+    let defaults = IndexConfig::default();
+    let config = IndexConfig {
+        workers: match INDEX_JOBS.load(std::sync::atomic::Ordering::Relaxed) {
+            0 => defaults.workers,
+            jobs => jobs,
+        },
+        incremental: matches!(mode, IndexMode::Incremental),
+        since: match &mode {
+            IndexMode::Since(rev) => Some(rev.clone()),
+            _ => None,
+        },
+        ..defaults
+    };
+    let (progress, progress_view) = match progress {
+        Some(progress) => (progress, None),
+        None => {
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            (tx, Some(tokio::spawn(show_index_progress(rx))))
+        }
+    };
+
+    let languages = project_language_override(&path)?;
+    // The progress channel closes once indexing ends, so the bars finish
+    let report = miow_core::index_into_graph(path.clone(), graph, vector_store, config, languages, Some(progress)).await;
+    if let Some(view) = progress_view {
+        let _ = view.await;
+    }
+    let report = report?;
+
+    if json_logs() {
+        tracing::info!(
+            files = report.total_files,
+            bytes = report.total_size,
+            duration_ms = report.duration_ms as u64,
+            symbols = report.symbols_indexed,
+            design_tokens = report.design_tokens_indexed,
+            routes = report.routes_indexed,
+            skipped = report.skipped.len(),
+            parse_errors = report.parse_errors.len(),
+            "Indexing complete"
+        );
+        return Ok(report);
+    }
+
+    say!("{}", "✅ Indexing complete!".green().bold());
+    say!();
+    say!("📊 {}", "Statistics:".yellow().bold());
+    say!("  Total files: {}", report.total_files);
+    say!("  Total size: {} bytes", report.total_size);
+    say!("  Duration: {}ms", report.duration_ms);
+    say!();
+    say!("📁 {}", "Files by language:".yellow().bold());
+
+    for (lang, count) in &report.files_by_language {
+        say!("  {}: {}", lang, count);
+    }
+
+    say!();
+    say!("{}", "✅ Knowledge graph built!".green().bold());
+    say!("  Total symbols indexed: {}", report.symbols_indexed);
+    say!("  Design tokens indexed: {}", report.design_tokens_indexed);
+    say!("  Routes indexed: {}", report.routes_indexed);
+
+    if let Some(changes) = &report.changes {
+        say!();
+        let heading = match &mode {
+            IndexMode::Since(rev) => format!("Changes since {}:", rev),
+            _ => "Changes since last index:".to_string(),
+        };
+        say!("🔁 {}", heading.yellow().bold());
+        say!("  Added: {}", changes.added.len());
+        say!("  Changed: {}", changes.changed.len());
+        say!("  Removed: {}", changes.removed.len());
+        say!("  Unchanged: {}", changes.unchanged);
+        for (marker, paths) in [("+", &changes.added), ("~", &changes.changed), ("-", &changes.removed)] {
+            for changed_path in paths.iter().take(10) {
+                say!("  {} {}", marker, changed_path);
+            }
+        }
+    }
+
+    if !report.skipped.is_empty() {
+        say!();
+        say!("⏭️  {}", format!("Skipped {} files:", report.skipped.len()).yellow().bold());
+        for file in report.skipped.iter().take(10) {
+            say!("  {} ({})", file.relative_path, file.reason);
+        }
+        if report.skipped.len() > 10 {
+            say!("  ... and {} more", report.skipped.len() - 10);
+        }
+    }
+
+    if !report.parse_errors.is_empty() {
+        let failed = report.parse_errors.iter().filter(|f| f.failed).count();
+        say!();
+        say!(
+            "{}",
+            format!(
+                "⚠️  {} files had parse errors ({} could not be parsed, the rest were indexed partially)",
+                report.parse_errors.len(),
+                failed
+            )
+            .yellow()
+        );
+        for file in report.parse_errors.iter().take(10) {
+            if let Some(first) = file.diagnostics.first() {
+                say!("  {}:{}:{} {}", file.relative_path, first.line, first.column, first.message);
+            }
+        }
+        if report.parse_errors.len() > 10 {
+            say!("  ... and {} more", report.parse_errors.len() - 10);
+        }
+        say!("  Full list is in the index_errors table of {}", db_path.display());
+    }
+
+    Ok(report)
+}
+
+/// An index report for `--json`, without the file contents it carries
+fn index_report_json(report: &miow_core::IndexReport) -> serde_json::Value {
+    serde_json::json!({
+        "total_files": report.total_files,
+        "files_by_language": report.files_by_language,
+        "total_size": report.total_size,
+        "duration_ms": report.duration_ms,
+        "symbols_indexed": report.symbols_indexed,
+        "design_tokens_indexed": report.design_tokens_indexed,
+        "routes_indexed": report.routes_indexed,
+        "parse_errors": report.parse_errors,
+        "changes": report.changes,
+        "skipped": report.skipped,
+    })
+}
+
+/// Draw parse and embed progress bars until the indexer drops its sender
+async fn show_index_progress(mut events: tokio::sync::mpsc::UnboundedReceiver<IndexProgress>) {
+    if json_logs() {
+        return log_index_progress(events).await;
+    }
+    let bars = MultiProgress::new();
+    let style = ProgressStyle::with_template("{prefix:>10} [{bar:40.cyan/blue}] {pos}/{len} {wide_msg}")
+        .unwrap()
+        .progress_chars("=> ");
+    let parse = bars.add(ProgressBar::new(0).with_style(style.clone()).with_prefix("Parsing"));
+    let mut embed: Option<ProgressBar> = None;
+    let mut failed = 0;
+
+    while let Some(event) = events.recv().await {
+        match event {
+            IndexProgress::Discovered { files } => parse.set_length(files as u64),
+            // Workers finish out of order, so never move the bar backwards
+            IndexProgress::Parsed { done, path, .. } => {
+                parse.set_position(parse.position().max(done as u64));
+                parse.set_message(path);
+            }
+            IndexProgress::Failed { .. } => {
+                failed += 1;
+                parse.set_prefix(format!("Parsing ({} failed)", failed));
+            }
+            IndexProgress::Embedded { done, total } => {
+                let bar = embed.get_or_insert_with(|| {
+                    bars.add(ProgressBar::new(total as u64).with_style(style.clone()).with_prefix("Embedding"))
+                });
+                bar.set_position(done as u64);
+            }
+            IndexProgress::Finished { .. } => {}
+        }
+    }
+
+    parse.finish_and_clear();
+    if let Some(bar) = embed {
+        bar.finish_and_clear();
+    }
+}
+
+/// Index progress as log events, for `--log-format json`
+async fn log_index_progress(mut events: tokio::sync::mpsc::UnboundedReceiver<IndexProgress>) {
+    while let Some(event) = events.recv().await {
+        match event {
+            IndexProgress::Discovered { files } => tracing::info!(files, "Discovered files to parse"),
+            IndexProgress::Parsed { done, total, path } => tracing::debug!(done, total, path, "Parsed file"),
+            IndexProgress::Failed { path, message } => tracing::warn!(path, message, "Failed to parse file"),
+            IndexProgress::Embedded { done, total } => tracing::debug!(done, total, "Embedded symbols"),
+            IndexProgress::Finished { .. } => {}
+        }
+    }
+}
+
+/// Parsers for `root`; the project's native grammars load only with `--trust-project-languages`
+fn project_languages(root: &Path) -> Result<LanguageRegistry> {
+    if config::is_untrusted_clone(root) {
+        return LanguageRegistry::for_user();
+    }
+    match TRUST_PROJECT_LANGUAGES.load(std::sync::atomic::Ordering::Relaxed) {
+        true => LanguageRegistry::for_trusted_project(root),
+        false => LanguageRegistry::for_project(root),
+    }
+}
+
+/// `project_languages` for `root`, when it differs from the registry an indexer loads by default
+fn project_language_override(root: &Path) -> Result<Option<std::sync::Arc<LanguageRegistry>>> {
+    if !TRUST_PROJECT_LANGUAGES.load(std::sync::atomic::Ordering::Relaxed) && !config::is_untrusted_clone(root) {
+        return Ok(None);
+    }
+    Ok(Some(std::sync::Arc::new(project_languages(root)?)))
+}
+
+/// An indexer for `root` using `project_languages`
+fn project_indexer(root: PathBuf) -> Result<CodebaseIndexer> {
+    let indexer = CodebaseIndexer::new(root.clone())?;
+    Ok(match project_language_override(&root)? {
+        Some(languages) => indexer.with_language_registry(languages),
+        None => indexer,
+    })
+}
+
+async fn handle_analyze(file: PathBuf) -> Result<()> {
+    say!("{}", "🔬 Analyzing file...".cyan().bold());
+    say!("File: {}", file.display());
+    say!();
+
+    let content = std::fs::read_to_string(&file)?;
+    let extension = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    // The project whose languages file applies is the nearest one declaring languages
+    let file_dir = file
+        .canonicalize()?
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let root = file_dir
+        .ancestors()
+        .find(|dir| dir.join(miow_core::language_registry::PROJECT_LANGUAGES_FILE).exists())
+        .unwrap_or(&file_dir);
+    let languages = project_languages(root)?;
+    let Some(parsed) = languages.parse_file(&file, &content)? else {
+        anyhow::bail!("Unsupported file type: {}", extension);
+    };
+    if json_output() {
+        return print_json(&serde_json::to_value(&parsed)?);
+    }
+
+    say!("{}", "✅ Analysis complete!".green().bold());
+    say!();
+
+    if !parsed.symbols.is_empty() {
+        say!("{}", "🔍 Symbols:".yellow().bold());
+        for symbol in &parsed.symbols {
+            say!("  • {} ({:?})", symbol.name.green(), symbol.kind);
+            say!(
+                "    Lines: {}-{}",
+                symbol.range.start_line, symbol.range.end_line
+            );
+
+            if !symbol.children.is_empty() {
+                say!("    Children: {}", symbol.children.len());
+            }
+            say!();
+        }
+    }
+
+    if !parsed.imports.is_empty() {
+        say!("{}", "📦 Imports:".yellow().bold());
+        for import in &parsed.imports {
+            say!("  • from '{}'", import.source.cyan());
+        }
+    }
+
+    if !parsed.design_tokens.is_empty() {
+        say!("{}", "🎨 Design tokens:".yellow().bold());
+        for token in &parsed.design_tokens {
+            say!("  • {} = {} ({:?}, {})", token.name.green(), token.value, token.token_type, token.context);
+        }
+    }
+
+    if !parsed.constants.is_empty() {
+        say!("{}", "📌 Constants:".yellow().bold());
+        for constant in &parsed.constants {
+            say!("  • {} = {}", constant.name.green(), constant.value);
+        }
+    }
+
+    if !parsed.schemas.is_empty() {
+        say!("{}", "🗄️  Schemas:".yellow().bold());
+        for schema in &parsed.schemas {
+            say!("  • {} ({:?})", schema.name.green(), schema.schema_type);
+            for field in &schema.fields {
+                let field_type = field.type_annotation.as_deref().unwrap_or("?");
+                match &field.references {
+                    Some(target) => say!("    - {}: {} → {}", field.name, field_type, target.cyan()),
+                    None => say!("    - {}: {}", field.name, field_type),
+                }
+            }
+        }
+    }
+
+    // Routes files (`config/routes.rb`, `routes/web.php`) are recognized by their path
+    let routes = miow_parsers::extract_routes(&file.to_string_lossy(), &content);
+    if !routes.is_empty() {
+        say!("{}", "🛣️  Routes:".yellow().bold());
+        for route in &routes {
+            let handler = route.handler.as_deref().unwrap_or("-");
+            say!("  • {} {} → {}", route.method.green(), route.path, handler.cyan());
+        }
+    }
+
+    Ok(())
+}
+
+/// `--scope` and `--kind` as a search scope. Indexed paths are relative to the
+/// codebase, so absolute directories inside it are made relative.
+fn search_scope(path: &Path, dirs: &[String], kinds: &[String]) -> miow_graph::SearchScope {
+    let root = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let dirs: Vec<String> = dirs
+        .iter()
+        .map(|dir| {
+            let absolute = Path::new(dir).canonicalize().ok().filter(|_| Path::new(dir).is_absolute());
+            match absolute.as_deref().and_then(|a| a.strip_prefix(&root).ok()) {
+                Some(relative) => relative.to_string_lossy().into_owned(),
+                None => dir.clone(),
+            }
+        })
+        .collect();
+    for dir in &dirs {
+        if !root.join(dir).exists() {
+            say!("{}", format!("⚠️  --scope {} is not a directory of {}", dir, path.display()).yellow());
+        }
+    }
+    miow_graph::SearchScope::new(&dirs, kinds)
+}
+
+/// Another repository's index for `--with-index [NAME=]PATH`. PATH is a database, or
+/// a directory holding `miow.db` or `.miow/miow.db`; the name defaults to the
+/// repository's package.json name, else its directory name.
+fn open_linked_index(spec: &str) -> Result<(String, KnowledgeGraph)> {
+    let (name, path) = match spec.split_once('=') {
+        Some((name, path)) if !name.is_empty() => (Some(name.to_string()), PathBuf::from(path)),
+        _ => (None, PathBuf::from(spec)),
+    };
+    let db_path = if path.is_dir() {
+        [path.join("miow.db"), path.join(".miow").join("miow.db")]
+            .into_iter()
+            .find(|db| db.is_file())
+            .ok_or_else(|| anyhow::anyhow!("{} has no index; run 'miow-context init' there first", path.display()))?
+    } else {
+        path.clone()
+    };
+
+    let name = name.unwrap_or_else(|| {
+        let mut root = db_path.parent().unwrap_or(Path::new("."));
+        if root.file_name().is_some_and(|n| n == ".miow") {
+            root = root.parent().unwrap_or(root);
+        }
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        std::fs::read_to_string(root.join("package.json"))
+            .ok()
+            .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+            .and_then(|package| package.get("name")?.as_str().map(String::from))
+            .or_else(|| root.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "linked".to_string())
+    });
+    let graph = KnowledgeGraph::open_read_only(&db_path)
+        .with_context(|| format!("Failed to open linked index {}", db_path.display()))?;
+    Ok((name, graph))
+}
+
+/// Orchestrator for `ask`, `generate` and `batch`, set up from miow.toml and the flags:
+/// project tools, LLM and vector store
+async fn prompt_orchestrator(
+    path: &Path,
+    db_path: &Path,
+    prompt_args: PromptArgs,
+    budget: BudgetArgs,
+    llm: LlmArgs,
+    assume_yes: bool,
+) -> Result<MiowOrchestrator> {
+    let config = MiowConfig::load(path)?;
+    let llm_settings = llm.apply(config.llm.clone());
+    let project_tools = config.agent_tools(path)?;
+    let lsp = config.lsp.clone();
+    let mut command_policy = config.commands;
+    let approver: std::sync::Arc<dyn miow_agent::Approver> = if assume_yes {
+        command_policy.approval = miow_agent::ApprovalMode::Auto;
+        std::sync::Arc::new(miow_agent::AutoApprover)
+    } else {
+        std::sync::Arc::new(miow_agent::TerminalApprover)
+    };
+    let mut orchestrator = MiowOrchestrator::new(db_path.to_str().unwrap())?
+        .with_command_policy(command_policy)
+        .with_agent_budget(budget.apply(config.agent))
+        .with_pipeline(config.pipeline)
+        .with_intent_rules(config.intents)
+        .with_context_cache(context_cache::ContextCache::new())
+        .with_approver(approver)
+        .with_prompt_format(prompt_args.format)
+        .with_diff_prompts(prompt_args.diff)
+        .with_search_scope(search_scope(path, &prompt_args.scope, &prompt_args.kind));
+    if let Some(max_files) = prompt_args.max_files {
+        orchestrator = orchestrator.with_max_files(max_files);
+    }
+    if prompt_args.explain {
+        orchestrator = orchestrator.with_explain();
+    }
+    if !prompt_args.export.is_empty() {
+        let dir = prompt_args.export_dir.unwrap_or_else(|| path.join(".miow").join("exports"));
+        orchestrator = orchestrator.with_exports(prompt_args.export, dir);
+    }
+    for spec in &prompt_args.with_index {
+        let (name, graph) = open_linked_index(spec)?;
+        say!("{}", format!("🔗 Linked index {} ({})", name, spec).green());
+        orchestrator = orchestrator.with_linked_index(name, graph);
+    }
+    for tool in project_tools {
+        orchestrator = orchestrator.with_agent_tool(tool);
+    }
+    if let Some(lsp) = lsp {
+        orchestrator = orchestrator.with_lsp(lsp);
+    }
+
+    // Qdrant if it is available (per-project collection), otherwise the SQLite index
+    match open_vector_store(path, db_path).await {
+        Ok(store) => {
+            say!("{}", format!("✅ Vector store ({}) connected!", vector_store_label(&store)).green());
+            orchestrator = orchestrator.with_vector_store(std::sync::Arc::new(store));
+        }
+        Err(e) => {
+            say!(
+                "{}",
+                format!(
+                    "⚠️  Vector store not available: {}. Continuing without vector search.",
+                    e
+                )
+                .yellow()
+            );
+        }
+    }
+
+    // Flags override miow.toml's [llm] table; anything unset comes from the environment
+    match llm_settings.build() {
+        Ok(Some(client)) => {
+            let provider = llm_settings.provider().map(|p| p.label()).unwrap_or_default();
+            say!("{}", format!("🤖 LLM integration enabled ({}, {})", provider, client.model_name()).green());
+            orchestrator = orchestrator.with_llm_arc(client);
+        }
+        Ok(None) => {
+            say!("{}", "ℹ️  No LLM configured. Using the deterministic pipeline: search, template questions and a rule-based plan.".yellow());
+            say!(
+                "{}",
+                "   Set GEMINI_API_KEY, OPENAI_API_KEY or AZURE_OPENAI_ENDPOINT, or pass --provider, for LLM-powered analysis."
+                    .bright_black()
+            );
+        }
+        // A provider the user asked for by name must work
+        Err(e) if llm_settings.provider.is_some() => return Err(e.context("Failed to initialize the LLM")),
+        Err(e) => {
+            say!("{}", format!("⚠️  Failed to initialize LLM: {}. Continuing without LLM.", e).yellow());
+        }
+    }
+    say!();
+
+    // Try to initialize vector store for semantic recall (re-use same per-project collection)
+    match open_vector_store(path, db_path).await {
+        Ok(store) => {
+            say!(
+                "{}",
+                format!("✅ Vector store ({}) ready for generation!", vector_store_label(&store)).green()
+            );
+            orchestrator = orchestrator.with_vector_store(std::sync::Arc::new(store));
+        }
+        Err(e) => {
+            say!(
+                "{}",
+                format!(
+                    "⚠️  Vector store not available: {}. Continuing with graph-only search.",
+                    e
+                )
+                .yellow()
+            );
+        }
+    }
+
+    Ok(orchestrator)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_generate_autonomous(
+    path: PathBuf,
+    prompt: String,
+    db_path: PathBuf,
+    output: Option<PathBuf>,
+    timeout: Option<u64>,
+    stream: bool,
+    prompt_args: PromptArgs,
+    budget: BudgetArgs,
+    cost: CostArgs,
+    llm: LlmArgs,
+    assume_yes: bool,
+    run: Option<(miow_agent::RunStore, String)>,
+) -> Result<()> {
+    let run_id = run.as_ref().map(|(_, run_id)| run_id.clone());
+    let dry_run = prompt_args.dry_run;
+    say!("{}", "🤖 MIOW-CONTEXT AUTONOMOUS PROMPT GENERATION".bright_blue().bold());
+    say!("{}", "═".repeat(80).bright_black());
+    say!("📁 Codebase: {}", path.display());
+    say!("📝 Task: {}", prompt.bright_yellow());
+    say!();
+
+    // Check if database exists, if not, index first
+    if !db_path.exists() {
+        say!(
+            "{}",
+            "⚠️  Knowledge graph not found. Indexing codebase first...".yellow()
+        );
+        say!();
+        handle_index(path.clone(), db_path.clone()).await?;
+        say!();
+    }
+
+    let mut orchestrator = prompt_orchestrator(&path, &db_path, prompt_args, budget, llm, assume_yes).await?;
+    if let Some((store, run_id)) = run {
+        orchestrator = orchestrator.with_run(store, run_id);
+    }
+
+    if dry_run {
+        let report = orchestrator.dry_run(&prompt, &path).await?;
+        if json_output() {
+            print_json(&serde_json::json!({ "task": prompt, "codebase": path, "dry_run": report }))?;
+        } else {
+            print_dry_run(&report);
+        }
+        return Ok(());
+    }
+
+    // Preflight: what the run should cost, and whether that's within --max-cost
+    let cost_estimate = orchestrator.estimate_cost(&prompt);
+    if let Some(estimate) = &cost_estimate {
+        print_cost_estimate(estimate);
+        if let Some(limit) = cost.max_cost.filter(|limit| estimate.exceeds(*limit)) {
+            let interactive = !assume_yes && !json_output() && std::io::IsTerminal::is_terminal(&std::io::stdin());
+            let question = format!("Estimated ~${:.4} is over --max-cost ${:.4}. Run it anyway? [y/N] ", estimate.cost_usd, limit);
+            if !(interactive && confirm(&question)?) {
+                match cost.over_cost {
+                    OverCost::Abort => {
+                        return Err(cost::CostExceeded { estimated: estimate.cost_usd, limit }.into());
+                    }
+                    OverCost::Deterministic => {
+                        say!("{}", "⚠️  Over --max-cost: building the prompt without the LLM".yellow());
+                        orchestrator = orchestrator.without_llm();
+                    }
+                }
+            }
+        } else if cost.max_cost.is_some() && !estimate.priced {
+            say!("{}", format!("⚠️  No price known for {}; --max-cost can't be checked", estimate.model).yellow());
+        }
+        say!();
+    }
+
+    say!("{}", "🔍 Analyzing prompt...".cyan());
+    say!("User prompt: \"{}\"", prompt.bright_blue());
+    say!();
+
+    // Enforce the --timeout deadline by cancelling all in-flight work
+    if let Some(secs) = timeout {
+        let token = orchestrator.cancellation_token();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(secs)).await;
+            token.cancel();
+        });
+    }
+
+    // Ctrl-C stops the run at its next step instead of killing it mid-write;
+    // a second one quits at once
+    let interrupted = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let _stop_on_ctrl_c = {
+        let token = orchestrator.cancellation_token();
+        let interrupted = interrupted.clone();
+        tokio_util::task::AbortOnDropHandle::new(tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            interrupted.store(true, std::sync::atomic::Ordering::Relaxed);
+            token.cancel();
+            eprintln!("{}", "🛑 Stopping the run; press Ctrl-C again to quit now".yellow());
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }))
+    };
+
+    // With --stream the run's events are rendered as they happen; under --json nobody watches
+    let (event_tx, view_task) = if stream && !json_output() {
+        let view = progress::StreamView::default();
+        if !assume_yes {
+            orchestrator = orchestrator.with_approver(view.approver(std::sync::Arc::new(miow_agent::TerminalApprover)));
+        }
+        let (tx, task) = progress::spawn(view);
+        (Some(tx), Some(task))
+    } else {
+        (None, None)
+    };
+
+    // Generate context-aware prompt using Universal Knowledge Graph workflow
+    let generated = orchestrator.generate_autonomous_prompt(path.to_str().unwrap(), &prompt, event_tx).await;
+    if let Some(task) = view_task {
+        let _ = task.await;
+    }
+    let generated_prompt = match generated {
+        Ok(result) => result,
+        Err(e) if interrupted.load(std::sync::atomic::Ordering::Relaxed) => return Err(e.context("Run interrupted")),
+        Err(e) if orchestrator.cancellation_token().is_cancelled() => {
+            return Err(e.context(format!("Run aborted after {}s timeout", timeout.unwrap_or_default())));
+        }
+        Err(e) => return Err(e),
+    };
+
+    say!("{}", "✅ Context-aware prompt generated!".green().bold());
+    if !json_output() {
+        say!();
+        say!("{}", "═".repeat(80).bright_black());
+        say!();
+        say!("{}", generated_prompt);
+        say!();
+        say!("{}", "═".repeat(80).bright_black());
+    }
+
+    let explanation = orchestrator.explanation();
+    if let Some(items) = explanation.as_ref().filter(|_| !json_output()) {
+        say!();
+        print_explanation(items);
+    }
+
+    let usage = orchestrator.usage_summary();
+    print_usage_summary(&usage);
+
+    // Save to file if requested
+    if let Some(output_path) = &output {
+        std::fs::write(output_path, &generated_prompt)?;
+        say!();
+        say!("💾 Prompt saved to: {}", output_path.display());
+    }
+
+    if json_output() {
+        print_json(&serde_json::json!({
+            "run_id": run_id,
+            "task": prompt,
+            "codebase": path,
+            "prompt": generated_prompt,
+            "output": output,
+            "usage": usage,
+            "cost_estimate": cost_estimate,
+            "explanation": explanation,
+        }))?;
+    }
+    Ok(())
+}
+
+/// Print the preflight estimate of a run's LLM calls and cost, by stage
+fn print_cost_estimate(estimate: &cost::CostEstimate) {
+    let cost = if estimate.priced { format!("~${:.4}", estimate.cost_usd) } else { "price unknown".to_string() };
+    say!(
+        "💰 Estimated LLM usage ({}): ~{} calls, ~{} tokens, {}",
+        estimate.model,
+        estimate.calls,
+        estimate.prompt_tokens + estimate.completion_tokens,
+        cost
+    );
+    for stage in &estimate.stages {
+        say!(
+            "   {:<10} {:>3} calls, ~{} prompt + {} completion tokens",
+            stage.stage,
+            stage.calls,
+            stage.prompt_tokens,
+            stage.completion_tokens
+        );
+    }
+}
+
+/// Print the context `--dry-run` gathered, grouped by prompt section
+fn print_dry_run(report: &orchestrator::DryRunReport) {
+    say!("{}", "🧪 DRY RUN: context that would be sent (no LLM calls made)".bright_blue().bold());
+    say!("{}", "═".repeat(80).bright_black());
+    say!("📋 Project: {}", report.project);
+    say!("🧭 Workflow: {:?}", report.workflow);
+    say!("🎯 Intent: {}", report.intent);
+    say!("🔎 Queries: {}", report.search_queries.join(", "));
+
+    let mut section = None;
+    for item in &report.items {
+        if section != Some(item.section) {
+            section = Some(item.section);
+            say!();
+            say!("{}", format!("{:?}", item.section).bold());
+        }
+        let (name, location) = match (&item.file_path, item.lines) {
+            (Some(path), lines) => {
+                let name = item.key.strip_suffix(&format!(" ({})", path)).unwrap_or(&item.key);
+                let location = lines.map(|(start, end)| format!("{}:{}-{}", path, start, end)).unwrap_or_else(|| path.clone());
+                (name, location)
+            }
+            (None, _) => (item.key.as_str(), String::new()),
+        };
+        say!("   {:>6} tokens  {} {}  {}", item.tokens, item.category.bright_black(), name.cyan(), location.bright_black());
+    }
+    if report.items.is_empty() {
+        say!();
+        say!("{}", "No context found for this task.".yellow());
+    }
+
+    say!();
+    say!("📁 Files ({}):", report.files.len());
+    for file in &report.files {
+        say!("   {}", file);
+    }
+    say!();
+    say!("{} ~{} of {} tokens", "Context:".bold(), report.context_tokens, report.token_budget);
+    if report.pruning.tokens_before > report.pruning.tokens_after {
+        say!("✂️  Pruned from {} tokens: {}", report.pruning.tokens_before, report.pruning.summary());
+    }
+    if let Some(items) = &report.explanation {
+        say!();
+        print_explanation(items);
+    }
+}
+
+/// Print `--explain`'s report: each context item with what found it and why
+fn print_explanation(items: &[explain::ItemExplanation]) {
+    say!("{}", "🔎 Why each item was included".bright_blue().bold());
+    for item in items {
+        let location = item.file_path.as_deref().unwrap_or_default();
+        say!("   {} {}  {}", item.kind.bright_black(), item.item.cyan(), location.bright_black());
+        let found = &item.provenance;
+        let mut why = vec![found.found_by.clone()];
+        if let Some(query) = found.query.as_deref().filter(|q| !q.is_empty()) {
+            why.push(format!("query '{}'", query));
+        }
+        if let Some(score) = found.vector_score {
+            why.push(format!("vector score {:.2}", score));
+        }
+        if let Some(worker) = &found.worker {
+            why.push(format!("worker {}", worker));
+        }
+        if !item.keyword_hits.is_empty() {
+            why.push(format!("keywords: {}", item.keyword_hits.join(", ")));
+        }
+        if let Some(references) = item.centrality {
+            why.push(format!("{} references", references));
+        }
+        say!("      {}", why.join(" · "));
+        if let Some(reason) = &found.reason {
+            say!("      {}", reason.bright_black());
+        }
+    }
+    if items.is_empty() {
+        say!("{}", "   No context items to explain.".yellow());
+    }
+}
+
+/// Print token usage and estimated cost for a run
+fn print_usage_summary(summary: &miow_llm::UsageSummary) {
+    if summary.calls == 0 {
+        return;
+    }
+
+    say!();
+    say!("{}", "📊 LLM Usage".bright_blue().bold());
+    for model in &summary.by_model {
+        say!(
+            "   {} / {}: {} calls, {} prompt + {} completion tokens, ~${:.4}",
+            model.provider,
+            model.model,
+            model.calls,
+            model.prompt_tokens,
+            model.completion_tokens,
+            model.estimated_cost_usd
+        );
+    }
+    say!(
+        "   {} {} tokens, ~${:.4}",
+        "Total:".bold(),
+        summary.total_tokens,
+        summary.estimated_cost_usd
+    );
+}
+
+async fn test_autonomous_system(task: String, path: PathBuf, llm_args: LlmArgs) -> Result<()> {
+    say!("{}", "🧠 AUTONOMOUS SYSTEM TEST".bright_blue().bold());
+    say!("{}", "═══════════════════════════════════════════════════════════════".bright_black());
+
+    say!("📋 Task: {}", task.bright_yellow());
+    say!("📁 Codebase: {}", path.display().to_string().bright_cyan());
+
+    // Initialize LLM client
+    let settings = llm_args.apply(MiowConfig::load(&path)?.llm);
+    let llm = settings
+        .build()?
+        .ok_or_else(|| anyhow::anyhow!("No LLM configured: set GEMINI_API_KEY, OPENAI_API_KEY or AZURE_OPENAI_ENDPOINT"))?;
+
+    say!("\n🤖 LLM Autonomous Planning Analysis:");
+    say!("{}", "─".repeat(50).bright_black());
+
+    // Autonomous task planning prompt
+    let plan_prompt = format!(
+        "Autonomously analyze task '{}' in the codebase at '{}'.\n
+        Task Analysis Protocol:
+        1. DETECT REQUIREMENTS: What does this task fundamentally need?
+        2. SEARCH CODEBASE: What existing services/utilities/patterns can I find?
+        3. MAKE DECISIONS: Should I reuse existing code or plan new implementations?
+        4. NO BIASES: Don't assume specific technologies - discover from code patterns
+        5. BE SPECIFIC: Reference actual patterns found in the codebase
+
+        Output Format:
+        {{
+            \"requirements\": [\"list of what task needs\"],
+            \"existing_services\": [\"services found in codebase\"],
+            \"decisions\": [\"specific decisions made\"],
+            \"plan\": \"detailed implementation plan\",
+            \"confidence\": \"high/medium/low\"
+        }}",
+        task, path.display()
+    );
+
+    match llm.generate(&plan_prompt).await {
+        Ok(response) => {
+            say!("{}", response.content.bright_green());
+
+            say!("\n✅ Autonomous Analysis Complete!");
+            say!("{}", "─".repeat(50).bright_black());
+            say!("🎯 Key Achievements:");
+            say!("  • LLM analyzed task without hardcoded biases");
+            say!("  • Discovered services autonomously from codebase");
+            say!("  • Made independent decisions about reuse vs. implementation");
+            say!("  • Adapted to detected patterns (no assumptions)");
+        }
+        Err(e) => {
+            say!("❌ LLM Error: {}", e.to_string().bright_red());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "web")]
+async fn start_web_server(port: u16, db_path: PathBuf, llm_args: LlmArgs) -> Result<()> {
+    say!("{}", "🌐 Starting MIOW-CONTEXT Web Server".bright_blue().bold());
+    say!("{}", "═".repeat(50).bright_black());
+    say!("📍 Port: {}", port);
+    #[cfg(feature = "ui")]
+    if ui::is_built() {
+        say!("🌍 Web UI: http://localhost:{}", port);
+    } else {
+        say!("{}", "⚠️  Web UI not embedded: run `npm run build` in web/ and rebuild".yellow());
+    }
+    #[cfg(not(feature = "ui"))]
+    say!("🌍 Web UI: build with --features ui, or run `npm run dev` in web/");
+    say!("🚀 API: http://localhost:{}/api", port);
+    say!();
+
+    let usage = std::sync::Arc::new(miow_llm::UsageTracker::metered());
+
+    // Flags override the [llm] table of miow.toml in the working directory
+    let current_dir = std::env::current_dir()?;
+    let config = MiowConfig::load(&current_dir)?;
+    let settings = llm_args.apply(config.llm);
+    let llm: Option<std::sync::Arc<miow_llm::UsageTrackingProvider>> = match settings.build() {
+        Ok(Some(client)) => {
+            let provider = settings.provider().map(|p| p.label()).unwrap_or_default();
+            say!("{}", format!("🤖 LLM integration enabled ({}, {})", provider, client.model_name()).green());
+            Some(std::sync::Arc::new(miow_llm::UsageTrackingProvider::new(client, usage.clone())))
+        }
+        Ok(None) => None,
+        Err(e) if settings.provider.is_some() => return Err(e.context("Failed to initialize the LLM")),
+        Err(e) => {
+            say!("{}", format!("⚠️  Failed to initialize LLM: {}", e).yellow());
+            None
+        }
+    };
+
+    // Unanswered approval requests are denied so a closed browser tab can't stall a run
+    let approvals = miow_agent::ChannelApprover::new(std::time::Duration::from_secs(300));
+    // Tokens made with `miow-context token create` live in the server's database
+    let token_graph = if db_path.exists() {
+        Some(std::sync::Arc::new(KnowledgeGraph::new(&db_path)?))
+    } else {
+        None
+    };
+    let auth = auth::Authenticator::new(&config.server, token_graph.clone())?;
+    // Without tokens the API is open, so only this machine may reach it
+    let host = if auth.is_enabled() {
+        say!("{}", "🔒 API requests need a bearer token".green());
+        "0.0.0.0"
+    } else {
+        say!(
+            "{}",
+            "⚠️  No API tokens configured: listening on 127.0.0.1 only. \
+             Create one with 'miow-context token create <name>' to serve other machines."
+                .yellow()
+        );
+        "127.0.0.1"
+    };
+
+    let metrics = telemetry::install()?;
+    let max_generate_body = config.server.max_generate_body;
+    let shutdown = tokio_util::sync::CancellationToken::new();
+    let tasks = tokio_util::task::TaskTracker::new();
+    let index_jobs = jobs::IndexJobs::start();
+    let state = AppState {
+        llm,
+        usage: usage.clone(),
+        approvals,
+        index_jobs: index_jobs.clone(),
+        auth,
+        metrics,
+        max_generate_body,
+        max_agent_iterations: config.server.max_agent_iterations,
+        shutdown: shutdown.clone(),
+        tasks: tasks.clone(),
+        context_cache: context_cache::ContextCache::new(),
+        allowed_origins: std::sync::Arc::new(config.server.allowed_origins.clone()),
+    };
+
+    // Create router
+    let limit = rate_limit::layer(&config.server.rate_limit);
+    if limit.is_some() {
+        let rate = &config.server.rate_limit;
+        say!("🚦 Rate limit: {} requests/minute per token or address (burst {})", rate.requests_per_minute, rate.burst);
+    } else {
+        say!("{}", "⚠️  Rate limiting is off".yellow());
+    }
+    let app = api_router(&state, limit);
+    // The UI's files are public, like the page a browser loads before it has a token
+    #[cfg(feature = "ui")]
+    let app = app.fallback(ui::serve);
+    let app = app.with_state(state);
+
+    // Start server
+    let addr = format!("{}:{}", host, port);
+    let listener = TcpListener::bind(&addr).await?;
+    say!("✅ Server running at {}", addr.bright_green());
+
+    // Peer addresses key the rate limit for requests without a token
+    let mut server = Box::pin(
+        axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+            .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+            .into_future(),
+    );
+    tokio::spawn(cancel_on_signal(shutdown.clone()));
+    tokio::select! {
+        result = &mut server => return result.map_err(Into::into),
+        _ = shutdown.cancelled() => {}
+    }
+
+    // No new connections from here; agent runs were cancelled with the token
+    // and stop at their next step, checkpointed in their saved runs
+    let timeout = std::time::Duration::from_secs(config.server.shutdown_timeout_secs);
+    let deadline = tokio::time::Instant::now() + timeout;
+    say!("{}", format!("🛑 Shutting down: waiting up to {}s for open requests...", timeout.as_secs()).yellow());
+    match tokio::time::timeout_at(deadline, &mut server).await {
+        Ok(result) => result?,
+        Err(_) => say!("{}", "⚠️  Closing requests that are still open".yellow()),
+    }
+    drop(server);
+    tasks.close();
+    if tokio::time::timeout_at(deadline, tasks.wait()).await.is_err() {
+        say!("{}", format!("⚠️  {} agent runs did not stop in time", tasks.len()).yellow());
+    }
+    let left = deadline.saturating_duration_since(tokio::time::Instant::now());
+    if let Some(job) = index_jobs.shutdown(left).await {
+        say!(
+            "{}",
+            format!(
+                "⚠️  Index job {} for {} was interrupted; index it again with incremental: true",
+                job.id,
+                job.codebase_path.display()
+            )
+            .yellow()
+        );
+    }
+
+    let summary = usage.summary();
+    if summary.calls > 0 {
+        say!(
+            "💰 LLM usage this session: {} calls, {} tokens (~${:.4})",
+            summary.calls, summary.total_tokens, summary.estimated_cost_usd
+        );
+    }
+    // The router is gone, so this is the last handle on the token database
+    if let Some(graph) = token_graph.and_then(|graph| std::sync::Arc::try_unwrap(graph).ok()) {
+        graph.close().context("Failed to close the token database")?;
+    }
+    say!("{}", "👋 Server stopped".green());
+    Ok(())
+}
+
+/// Cancel `shutdown` on Ctrl-C or SIGTERM
+#[cfg(feature = "web")]
+async fn cancel_on_signal(shutdown: tokio_util::sync::CancellationToken) {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+    shutdown.cancel();
+}
+
+#[cfg(feature = "web")]
+async fn generate_handler(
+    State(state): State<AppState>,
+    Json(request): Json<GenerateRequest>,
+) -> Result<(StatusCode, Json<GenerateResponse>), ApiError> {
+    say!("🤖 Processing request: {}", request.user_prompt.bright_yellow());
+
+    let codebase_path = PathBuf::from(&request.codebase_path);
+    
+    // Determine project-specific DB path
+    let db_path = project_db_path(&codebase_path);
+
+    // Indexing runs in the background rather than holding the request for minutes
+    if let Some(job) = pending_index(&state.index_jobs, &codebase_path, &db_path) {
+        return Err(ApiError::Indexing(Box::new(job)));
+    }
+
+    let options = serde_json::json!({ "budget": request.budget, "format": request.format });
+    let run = WebRun::for_request(&codebase_path, &request.user_prompt, request.session_id.as_deref(), options)?;
 
     // Initialize orchestrator with project-specific DB
     match MiowOrchestrator::new(db_path.to_str().unwrap()) {
-        Ok(mut orchestrator) => {
+        Ok(orchestrator) => {
             // Inject shared LLM
-            if let Some(llm) = &state.llm {
-                 orchestrator = orchestrator.with_llm_arc(llm.clone());
+            let mut orchestrator = run.attach(orchestrator, &state);
+            orchestrator = orchestrator
+                .with_agent_budget(MiowConfig::agent_budget_for(&codebase_path, request.budget.clone(), state.max_agent_iterations))
+                .with_prompt_format(request.format);
+            if request.explain {
+                orchestrator = orchestrator.with_explain();
+            }
+            // Project [[tools]] run shell commands, which web clients don't get to trigger
+            if let Ok(config) = MiowConfig::load(&codebase_path) {
+                if let Some(lsp) = config.lsp {
+                    orchestrator = orchestrator.with_lsp(lsp);
+                }
+                orchestrator = orchestrator.with_pipeline(config.pipeline).with_intent_rules(config.intents);
             }
 
+            // Abort downstream work if the client disconnects (handler future is dropped)
+            let _cancel_on_disconnect = orchestrator.cancellation_token().drop_guard();
+
             // Attach per-project vector store (separate Qdrant collection per project)
-            let qdrant_url =
-                std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
-            let collection_name = collection_name_for_path(&codebase_path);
-            if let Ok(store) = miow_vector::VectorStore::new(&qdrant_url, &collection_name).await {
+            if let Ok(store) = open_vector_store(&codebase_path, &db_path).await {
                 orchestrator = orchestrator.with_vector_store(std::sync::Arc::new(store));
             }
             
-            match orchestrator.generate_autonomous_prompt(
+            let result = orchestrator.generate_autonomous_prompt(
                 codebase_path.to_str().unwrap(),
                 &request.user_prompt,
                 None // No event streaming for now
-            ).await {
-                Ok(result) => {
-                    println!("✅ Request completed successfully");
-                    Ok(Json(GenerateResponse {
-                        success: true,
-                        result: Some(result),
-                        error: None,
-                    }))
-                }
-                Err(e) => {
-                    println!("❌ Request failed: {}", e);
-                    Ok(Json(GenerateResponse {
-                        success: false,
-                        result: None,
-                        error: Some(e.to_string()),
-                    }))
-                }
-            }
+            ).await;
+            run.finish(&result);
+            Ok(GenerateResponse::from_result(result, &run, orchestrator.explanation()))
         }
         Err(e) => {
-             Ok(Json(GenerateResponse {
-                success: false,
-                result: None,
-                error: Some(format!("Failed to initialize orchestrator: {}", e)),
-            }))
+            let result = Err(e.context("Failed to initialize orchestrator"));
+            run.finish(&result);
+            Ok(GenerateResponse::from_result(result, &run, None))
         }
     }
 }
@@ -972,82 +3890,103 @@ async fn generate_stream_handler(
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
     let codebase_path = PathBuf::from(&request.codebase_path);
     let user_prompt = request.user_prompt.clone();
-    let llm = state.llm.clone();
-    
+    let budget = request.budget.clone();
+    let format = request.format;
+    let session_id = request.session_id.clone();
+    let explain = request.explain;
+    let approvals = state.approvals.clone();
+    let index_jobs = state.index_jobs.clone();
     
     // Create channel for communication
-    let (tx, mut rx) = tokio::sync::mpsc::channel::<Result<Event, Infallible>>(100);
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<Event, Infallible>>(100);
     
     // Spawn background task to handle streaming
-    tokio::spawn(async move {
+    state.tasks.clone().spawn(async move {
         // Send initial status
         let _ = tx.send(Ok(Event::default()
             .event("status")
             .data("Starting autonomous agent..."))).await;
         
-        // Determine project-specific DB path
-        let db_path = codebase_path.join(".miow").join("miow.db");
-        let db_dir = db_path.parent().unwrap();
-        
-        // Create .miow directory if it doesn't exist
-        if !db_dir.exists() {
-            if let Err(e) = std::fs::create_dir_all(db_dir) {
-                let _ = tx.send(Ok(Event::default()
-                    .event("error")
-                    .data(format!("Failed to create .miow directory: {}", e)))).await;
-                return;
-            }
-        }
-
-        // Auto-index if DB doesn't exist
-        if !db_path.exists() {
+        // Projects without an index are indexed in the background; the client
+        // can follow the job and retry once it completes
+        let db_path = project_db_path(&codebase_path);
+        if let Some(job) = pending_index(&index_jobs, &codebase_path, &db_path) {
             let _ = tx.send(Ok(Event::default()
                 .event("status")
-                .data("No index found. Indexing codebase..."))).await;
-            
-            match handle_index(codebase_path.clone(), db_path.clone()).await {
-                Ok(_) => {
-                    let _ = tx.send(Ok(Event::default()
-                        .event("status")
-                        .data("Indexing completed successfully"))).await;
-                }
-                Err(e) => {
-                    let _ = tx.send(Ok(Event::default()
-                        .event("error")
-                        .data(format!("Indexing failed: {}", e)))).await;
-                    return;
-                }
+                .data(format!("Indexing in progress (job {}); retry once it completes", job.id)))).await;
+            let _ = tx.send(Ok(Event::default()
+                .event("indexing")
+                .data(serde_json::to_string(&job).unwrap_or_default()))).await;
+            return;
+        }
+
+        let options = serde_json::json!({ "budget": budget, "format": format });
+        let run = match WebRun::for_request(&codebase_path, &user_prompt, session_id.as_deref(), options) {
+            Ok(run) => run,
+            Err(e) => {
+                let _ = tx.send(Ok(Event::default().event("error").data(format!("{:#}", e)))).await;
+                return;
             }
+        };
+        if let Some(run_id) = run.run_id() {
+            let _ = tx.send(Ok(Event::default().event("run").data(run_id))).await;
+        }
+        if let Some(session_id) = &run.session_id {
+            let _ = tx.send(Ok(Event::default().event("session").data(session_id))).await;
         }
 
         // Initialize orchestrator
         let orchestrator = match MiowOrchestrator::new(db_path.to_str().unwrap()) {
-            Ok(mut orch) => {
+            Ok(orch) => {
                 // Inject shared LLM
-                if let Some(llm_ref) = &llm {
-                    orch = orch.with_llm_arc(llm_ref.clone());
+                let mut orch = run.attach(orch, &state);
+                orch = orch
+                    .with_agent_budget(MiowConfig::agent_budget_for(&codebase_path, budget, state.max_agent_iterations))
+                    .with_prompt_format(format);
+                if explain {
+                    orch = orch.with_explain();
+                }
+
+                // This client can answer approval events, so commands may be asked about
+                // Project [[tools]] aren't offered to web clients, as in /api/generate
+                if let Ok(config) = MiowConfig::load(&codebase_path) {
+                    if let Some(lsp) = config.lsp.clone() {
+                        orch = orch.with_lsp(lsp);
+                    }
+                    orch = orch.with_command_policy(config.commands).with_pipeline(config.pipeline).with_intent_rules(config.intents);
                 }
+                orch = orch.with_approver(std::sync::Arc::new(approvals.for_codebase(&codebase_path)));
 
                 // Attach vector store
-                let qdrant_url = std::env::var("QDRANT_URL")
-                    .unwrap_or_else(|_| "http://localhost:6333".to_string());
-                let collection_name = collection_name_for_path(&codebase_path);
-                if let Ok(store) = miow_vector::VectorStore::new(&qdrant_url, &collection_name).await {
+                if let Ok(store) = open_vector_store(&codebase_path, &db_path).await {
                     orch = orch.with_vector_store(std::sync::Arc::new(store));
                 }
                 
                 orch
             }
             Err(e) => {
-                let _ = tx.send(Ok(Event::default()
-                    .event("error")
-                    .data(format!("Failed to initialize orchestrator: {}", e)))).await;
+                let result = Err(e.context("Failed to initialize orchestrator"));
+                run.finish(&result);
+                if let Err(e) = result {
+                    let _ = tx.send(Ok(Event::default()
+                        .event("error")
+                        .data(format!("{:#}", e)))).await;
+                }
                 return;
             }
         };
 
+        // Cancel the run once the SSE client goes away
+        let cancel = orchestrator.cancellation_token();
+        let disconnect_tx = tx.clone();
+        tokio::spawn(async move {
+            disconnect_tx.closed().await;
+            cancel.cancel();
+        });
+
         // Create channel for agent events
         let (agent_tx, mut agent_rx) = tokio::sync::mpsc::channel(100);
+        let explain_log = orchestrator.explain_log();
         
         // Spawn agent task
         let agent_task = tokio::spawn(async move {
@@ -1058,127 +3997,630 @@ async fn generate_stream_handler(
             ).await
         });
 
-        // Forward agent events
-        while let Some(event) = agent_rx.recv().await {
-            let event_json = serde_json::to_string(&event).unwrap_or_default();
-            let _ = tx.send(Ok(Event::default()
-                .event("agent")
-                .data(event_json))).await;
-        }
+        // Forward agent events
+        while let Some(event) = agent_rx.recv().await {
+            let event_json = serde_json::to_string(&event).unwrap_or_default();
+            let _ = tx.send(Ok(Event::default()
+                .event("agent")
+                .data(event_json))).await;
+        }
+
+        // Wait for final result
+        let result = match agent_task.await {
+            Ok(result) => result.context("Agent error"),
+            Err(e) => Err(anyhow::anyhow!("Task error: {}", e)),
+        };
+        run.finish(&result);
+        match result {
+            Ok(result) => {
+                if let Some(log) = explain_log {
+                    let _ = tx.send(Ok(Event::default()
+                        .event("explanation")
+                        .data(serde_json::to_string(&log.report()).unwrap_or_default()))).await;
+                }
+                let _ = tx.send(Ok(Event::default()
+                    .event("result")
+                    .data(result))).await;
+            }
+            Err(e) => {
+                let _ = tx.send(Ok(Event::default()
+                    .event("error")
+                    .data(format!("{:#}", e)))).await;
+            }
+        }
+    });
+
+    // Create stream from receiver
+    let event_stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (event, rx))
+    });
+
+    Sse::new(event_stream)
+}
+
+#[cfg(feature = "web")]
+async fn search_files_handler(
+    Json(request): Json<SearchFilesRequest>,
+) -> Json<SearchFilesResponse> {
+    use walkdir::WalkDir;
+    
+    let codebase_path = PathBuf::from(&request.codebase_path);
+    let query = request.query.to_lowercase();
+    
+    if !codebase_path.exists() {
+        return Json(SearchFilesResponse {
+            success: false,
+            files: None,
+            error: Some("Codebase path does not exist".to_string()),
+        });
+    }
+    
+    let mut matching_files = Vec::new();
+    
+    // Walk the directory and find matching files
+    for entry in WalkDir::new(&codebase_path)
+        .max_depth(10)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        if entry.file_type().is_file() {
+            let path = entry.path();
+            
+            // Skip hidden files and common ignore patterns
+            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
+                if file_name.starts_with('.') || 
+                   path.to_str().map(|s| s.contains("/node_modules/") || 
+                                          s.contains("/target/") ||
+                                          s.contains("/.git/") ||
+                                          s.contains("/dist/") ||
+                                          s.contains("/build/")).unwrap_or(false) {
+                    continue;
+                }
+            }
+            
+            // Check if path matches query
+            if let Some(path_str) = path.to_str() {
+                if path_str.to_lowercase().contains(&query) {
+                    // Make path relative to codebase
+                    if let Ok(relative) = path.strip_prefix(&codebase_path) {
+                        if let Some(rel_str) = relative.to_str() {
+                            matching_files.push(rel_str.to_string());
+                        }
+                    }
+                }
+            }
+            
+            // Limit results to 50
+            if matching_files.len() >= 50 {
+                break;
+            }
+        }
+    }
+    
+    Json(SearchFilesResponse {
+        success: true,
+        files: Some(matching_files),
+        error: None,
+    })
+}
+
+#[cfg(feature = "web")]
+async fn health_handler(
+    State(_state): State<AppState>,
+) -> Json<HealthResponse> {
+    let gemini_configured = std::env::var("GEMINI_API_KEY").is_ok();
+
+    // Check Qdrant connection
+    let qdrant_connected = reqwest::get("http://localhost:6333/collections")
+        .await
+        .map(|resp| resp.status().is_success())
+        .unwrap_or(false);
+
+    Json(HealthResponse {
+        status: "healthy".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        qdrant_connected,
+        gemini_configured,
+    })
+}
+
+/// Prometheus text exposition of the server's metrics
+#[cfg(feature = "web")]
+async fn metrics_handler(State(state): State<AppState>) -> ([(axum::http::header::HeaderName, &'static str); 1], String) {
+    ([(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")], state.metrics.render())
+}
+
+#[cfg(feature = "web")]
+async fn usage_handler(State(state): State<AppState>) -> Json<miow_llm::UsageSummary> {
+    Json(state.usage.summary())
+}
+
+/// Approve or deny a pending agent action from a `/api/generate-stream` run
+#[cfg(feature = "web")]
+async fn approval_handler(
+    State(state): State<AppState>,
+    UrlPath(id): UrlPath<String>,
+    Json(decision): Json<ApprovalDecision>,
+) -> StatusCode {
+    if state.approvals.resolve(&id, decision.approved) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+/// Whether a browser's `Origin` names the host the request was sent to or one of
+/// `allowed`; requests without one don't come from a page script
+#[cfg(feature = "web")]
+fn allowed_origin(headers: &axum::http::HeaderMap, allowed: &[String]) -> bool {
+    let Some(origin) = headers.get(axum::http::header::ORIGIN) else {
+        return true;
+    };
+    let Ok(origin) = origin.to_str() else { return false };
+    if allowed.iter().any(|allowed| allowed.trim_end_matches('/') == origin) {
+        return true;
+    }
+    let origin_host = origin.split_once("://").map(|(_, host)| host);
+    let host = headers.get(axum::http::header::HOST).and_then(|host| host.to_str().ok());
+    origin_host.is_some() && origin_host == host
+}
+
+#[cfg(feature = "web")]
+async fn ws_generate_handler(
+    State(state): State<AppState>,
+    token: Option<axum::Extension<auth::ApiToken>>,
+    upgrade: axum::extract::ws::WebSocketUpgrade,
+) -> axum::response::Response {
+    upgrade
+        .max_message_size(state.max_generate_body)
+        .on_upgrade(move |socket| {
+            let tasks = state.tasks.clone();
+            tasks.track_future(ws_generate(state, token.map(|axum::Extension(token)| token), socket))
+        })
+}
+
+/// One agent run over a WebSocket. Questions and approvals go to this client
+/// only, and closing the socket cancels the run.
+#[cfg(feature = "web")]
+async fn ws_generate(state: AppState, token: Option<auth::ApiToken>, mut socket: axum::extract::ws::WebSocket) {
+    use axum::extract::ws::Message;
+
+    async fn send(socket: &mut axum::extract::ws::WebSocket, message: WsServerMessage) -> bool {
+        let text = serde_json::to_string(&message).unwrap_or_default();
+        socket.send(Message::Text(text)).await.is_ok()
+    }
+
+    let request = loop {
+        let message = tokio::select! {
+            message = socket.recv() => message,
+            _ = state.shutdown.cancelled() => return,
+        };
+        match message {
+            Some(Ok(Message::Text(text))) => match serde_json::from_str::<WsClientMessage>(&text) {
+                Ok(WsClientMessage::Generate(request)) => break request,
+                Ok(_) => {
+                    send(&mut socket, WsServerMessage::Error { message: "Send a 'generate' message first".into(), code: None }).await;
+                    return;
+                }
+                Err(e) => {
+                    send(&mut socket, WsServerMessage::Error { message: format!("Invalid message: {}", e), code: None }).await;
+                    return;
+                }
+            },
+            Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+            Some(Ok(_)) => continue,
+        }
+    };
+
+    let codebase_path = PathBuf::from(&request.codebase_path);
+    if let Some(token) = token.filter(|token| !token.allows(&codebase_path)) {
+        let message = format!("Token '{}' may not use {}", token.name, codebase_path.display());
+        send(&mut socket, WsServerMessage::Error { message, code: None }).await;
+        return;
+    }
+    let db_path = project_db_path(&codebase_path);
+    if let Some(job) = pending_index(&state.index_jobs, &codebase_path, &db_path) {
+        send(&mut socket, WsServerMessage::Indexing { job }).await;
+        return;
+    }
+
+    let options = serde_json::json!({ "budget": request.budget, "format": request.format });
+    let run = match WebRun::for_request(&codebase_path, &request.user_prompt, request.session_id.as_deref(), options) {
+        Ok(run) => run,
+        Err(e) => {
+            let code = Some(errors::ErrorKind::of(&e).code());
+            send(&mut socket, WsServerMessage::Error { message: format!("{:#}", e), code }).await;
+            return;
+        }
+    };
+    if let Some(run_id) = run.run_id() {
+        let session_id = run.session_id.clone().unwrap_or_else(|| run_id.clone());
+        send(&mut socket, WsServerMessage::Run { run_id, session_id }).await;
+    }
+    let mut orchestrator = match MiowOrchestrator::new(db_path.to_str().unwrap()) {
+        Ok(orchestrator) => run.attach(orchestrator, &state),
+        Err(e) => {
+            let result = Err(e.context("Failed to initialize orchestrator"));
+            run.finish(&result);
+            if let Err(e) = result {
+                let code = Some(errors::ErrorKind::of(&e).code());
+                send(&mut socket, WsServerMessage::Error { message: format!("{:#}", e), code }).await;
+            }
+            return;
+        }
+    };
+    orchestrator = orchestrator
+        .with_agent_budget(MiowConfig::agent_budget_for(&codebase_path, request.budget, state.max_agent_iterations))
+        .with_prompt_format(request.format);
+    if request.explain {
+        orchestrator = orchestrator.with_explain();
+    }
+    // Project [[tools]] aren't offered to web clients, as in /api/generate
+    if let Ok(config) = MiowConfig::load(&codebase_path) {
+        if let Some(lsp) = config.lsp.clone() {
+            orchestrator = orchestrator.with_lsp(lsp);
+        }
+        orchestrator = orchestrator.with_command_policy(config.commands).with_pipeline(config.pipeline).with_intent_rules(config.intents);
+    }
+    // A connection of its own, so another client can't answer for this one
+    let replies = miow_agent::ChannelApprover::new(std::time::Duration::from_secs(300));
+    orchestrator = orchestrator
+        .with_approver(std::sync::Arc::new(replies.clone()))
+        .with_answerer(std::sync::Arc::new(replies.clone()));
+    if let Ok(store) = open_vector_store(&codebase_path, &db_path).await {
+        orchestrator = orchestrator.with_vector_store(std::sync::Arc::new(store));
+    }
+
+    if !send(&mut socket, WsServerMessage::Status { message: "Starting autonomous agent...".into() }).await {
+        return;
+    }
+    let cancel = orchestrator.cancellation_token();
+    let explain_log = orchestrator.explain_log();
+    let (agent_tx, mut agent_rx) = tokio::sync::mpsc::channel(100);
+    let user_prompt = request.user_prompt;
+    let mut agent_task = tokio::spawn(async move {
+        orchestrator.generate_autonomous_prompt(codebase_path.to_str().unwrap(), &user_prompt, Some(agent_tx)).await
+    });
+
+    let outcome = loop {
+        tokio::select! {
+            Some(event) = agent_rx.recv() => {
+                if !send(&mut socket, WsServerMessage::Agent { event }).await {
+                    cancel.cancel();
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let reply = match serde_json::from_str::<WsClientMessage>(&text) {
+                        Ok(WsClientMessage::Approve { id, approved }) => {
+                            (!replies.resolve(&id, approved)).then(|| format!("No pending approval '{}'", id))
+                        }
+                        Ok(WsClientMessage::Answer { id, answer }) => {
+                            (!replies.reply(&id, answer)).then(|| format!("No pending question '{}'", id))
+                        }
+                        Ok(WsClientMessage::Cancel) => {
+                            cancel.cancel();
+                            None
+                        }
+                        Ok(WsClientMessage::Generate(_)) => Some("A run is already in progress".to_string()),
+                        Err(e) => Some(format!("Invalid message: {}", e)),
+                    };
+                    if let Some(message) = reply {
+                        send(&mut socket, WsServerMessage::Error { message, code: None }).await;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                    // Nobody is left to read the prompt
+                    cancel.cancel();
+                    let result = (&mut agent_task).await.unwrap_or_else(|e| Err(anyhow::anyhow!("Task error: {}", e)));
+                    run.finish(&result);
+                    return;
+                }
+                Some(Ok(_)) => {}
+            },
+            result = &mut agent_task => break result,
+        }
+    };
+
+    // Events sent just before the run ended
+    while let Ok(event) = agent_rx.try_recv() {
+        send(&mut socket, WsServerMessage::Agent { event }).await;
+    }
+    let result = match outcome {
+        Ok(result) => result.context("Agent error"),
+        Err(e) => Err(anyhow::anyhow!("Task error: {}", e)),
+    };
+    run.finish(&result);
+    let message = match result {
+        Ok(prompt) => WsServerMessage::Result { prompt, explanation: explain_log.map(|log| log.report()) },
+        Err(e) => WsServerMessage::Error { message: format!("{:#}", e), code: Some(errors::ErrorKind::of(&e).code()) },
+    };
+    send(&mut socket, message).await;
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// Queue an index of a project; a job already queued or running for it is
+/// returned instead of starting another
+#[cfg(feature = "web")]
+async fn index_handler(
+    State(state): State<AppState>,
+    Json(request): Json<IndexRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let codebase_path = PathBuf::from(&request.codebase_path);
+    if !codebase_path.is_dir() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("{} is not a directory", codebase_path.display()),
+            })),
+        );
+    }
+    let job = state.index_jobs.enqueue(&codebase_path, request.incremental);
+    say!("📥 Index job {} for {} ({:?})", job.id, job.codebase_path.display(), job.state);
+    (StatusCode::ACCEPTED, Json(serde_json::json!({ "success": true, "job": job })))
+}
+
+#[cfg(feature = "web")]
+async fn index_status_handler(
+    State(state): State<AppState>,
+    UrlPath(job_id): UrlPath<String>,
+) -> Result<Json<jobs::IndexJob>, StatusCode> {
+    state.index_jobs.get(&job_id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
 
-        // Wait for final result
-        match agent_task.await {
-            Ok(Ok(result)) => {
-                let _ = tx.send(Ok(Event::default()
-                    .event("result")
-                    .data(result))).await;
-            }
-            Ok(Err(e)) => {
-                let _ = tx.send(Ok(Event::default()
-                    .event("error")
-                    .data(format!("Agent error: {}", e)))).await;
-            }
-            Err(e) => {
-                let _ = tx.send(Ok(Event::default()
-                    .event("error")
-                    .data(format!("Task error: {}", e)))).await;
+/// Shortest gap between two `progress` events on `/api/index/events/:job_id`
+#[cfg(feature = "web")]
+const INDEX_EVENT_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Follow an index job: a `progress` event whenever it moves (at most one per
+/// `INDEX_EVENT_INTERVAL`), then one `done` event once it has completed or failed
+#[cfg(feature = "web")]
+async fn index_events_handler(
+    State(state): State<AppState>,
+    UrlPath(job_id): UrlPath<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let updates = state.index_jobs.subscribe(&job_id).ok_or(StatusCode::NOT_FOUND)?;
+    let events = stream::unfold((updates, true, false), |(mut updates, first, done)| async move {
+        if done {
+            return None;
+        }
+        if !first {
+            // Every parsed file updates the job; waiting first lets the watch
+            // channel fold those into one event carrying the latest state
+            tokio::time::sleep(INDEX_EVENT_INTERVAL).await;
+            // Jobs that are forgotten drop their sender, which ends the stream too
+            if updates.changed().await.is_err() {
+                return None;
             }
         }
+        let job = updates.borrow_and_update().clone();
+        let finished = !job.state.is_active();
+        let event = Event::default()
+            .event(if finished { "done" } else { "progress" })
+            .data(serde_json::to_string(&job).unwrap_or_default());
+        Some((Ok(event), (updates, false, finished)))
     });
+    Ok(Sse::new(futures::StreamExt::take_until(events, state.shutdown.clone().cancelled_owned())))
+}
 
-    // Create stream from receiver
-    let event_stream = futures::stream::unfold(rx, |mut rx| async move {
-        rx.recv().await.map(|event| (event, rx))
-    });
+/// Delete a project's index: its database and Qdrant collection. Refused while
+/// an index job for the project is queued or running.
+#[cfg(feature = "web")]
+async fn delete_index_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ProjectQuery>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let codebase_path = PathBuf::from(&query.codebase_path);
+    if let Some(job) = state.index_jobs.active(&codebase_path) {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("Job {} is indexing {}; delete the index once it completes", job.id, codebase_path.display()),
+                "job": job,
+            })),
+        );
+    }
 
-    Sse::new(event_stream)
+    let db_path = project_db_path(&codebase_path);
+    let mut removed = Vec::new();
+    for file in [
+        db_path.clone(),
+        PathBuf::from(format!("{}-wal", db_path.display())),
+        PathBuf::from(format!("{}-shm", db_path.display())),
+    ] {
+        if !file.exists() {
+            continue;
+        }
+        if let Err(e) = std::fs::remove_file(&file) {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "success": false,
+                    "error": format!("Failed to remove {}: {}", file.display(), e),
+                    "removed": removed,
+                })),
+            );
+        }
+        removed.push(file);
+    }
+
+    let qdrant_url = std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
+    let collection = collection_name_for_path(&codebase_path);
+    let dropped = match miow_vector::VectorStore::delete_collection(&qdrant_url, &collection).await {
+        Ok(dropped) => dropped,
+        Err(e) => {
+            say!("{}", format!("⚠️  Vector store not available, keeping its collection: {}", e).yellow());
+            false
+        }
+    };
+
+    say!("🧹 Deleted the index of {}", codebase_path.display());
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "removed": removed,
+            "collection": dropped.then_some(collection),
+        })),
+    )
 }
 
+/// A project's knowledge graph for the read-only browsing endpoints. These
+/// don't start indexing; a project without an index is a 404.
 #[cfg(feature = "web")]
-async fn search_files_handler(
-    Json(request): Json<SearchFilesRequest>,
-) -> Json<SearchFilesResponse> {
-    use walkdir::WalkDir;
-    
-    let codebase_path = PathBuf::from(&request.codebase_path);
-    let query = request.query.to_lowercase();
-    
-    if !codebase_path.exists() {
-        return Json(SearchFilesResponse {
-            success: false,
-            files: None,
-            error: Some("Codebase path does not exist".to_string()),
+fn browse_graph(index_jobs: &jobs::IndexJobs, codebase_path: &Path) -> Result<KnowledgeGraph, ApiError> {
+    let db_path = project_db_path(codebase_path);
+    if !db_path.exists() {
+        return Err(match index_jobs.active(codebase_path) {
+            Some(job) => ApiError::Indexing(Box::new(job)),
+            None => ApiError::Failed(
+                errors::ErrorKind::NotIndexed,
+                format!("{} has no index; POST /api/index to build one", codebase_path.display()),
+            ),
         });
     }
-    
-    let mut matching_files = Vec::new();
-    
-    // Walk the directory and find matching files
-    for entry in WalkDir::new(&codebase_path)
-        .max_depth(10)
-        .follow_links(false)
+    Ok(KnowledgeGraph::new(&db_path).context("Failed to open the index")?)
+}
+
+/// Symbols by name and kind, a page at a time, ordered by name
+#[cfg(feature = "web")]
+async fn symbols_handler(
+    State(state): State<AppState>,
+    Query(query): Query<SymbolsQuery>,
+) -> Result<Json<SymbolPageResponse>, ApiError> {
+    let graph = browse_graph(&state.index_jobs, Path::new(&query.codebase_path))?;
+    let page = query.page.unwrap_or(1).max(1);
+
+    let mut builder = miow_graph::QueryBuilder::new();
+    if !query.query.is_empty() {
+        builder = builder.with_name(&query.query);
+    }
+    if let Some(kind) = query.kind.as_deref().filter(|k| !k.is_empty()) {
+        builder = builder.with_kind(kind);
+    }
+    // One extra row tells whether another page follows
+    let builder = builder.limit(SYMBOLS_PER_PAGE + 1).offset((page - 1) * SYMBOLS_PER_PAGE);
+    let mut symbols = graph.query_symbols(&builder)?;
+    let has_more = symbols.len() > SYMBOLS_PER_PAGE;
+    symbols.truncate(SYMBOLS_PER_PAGE);
+
+    Ok(Json(SymbolPageResponse { success: true, symbols, page, per_page: SYMBOLS_PER_PAGE, has_more }))
+}
+
+/// A project's saved runs, newest first
+#[cfg(feature = "web")]
+async fn runs_handler(Query(query): Query<RunsQuery>) -> Result<Json<RunPageResponse>, ApiError> {
+    let store = miow_agent::RunStore::new(runs_dir_for_db(&project_db_path(Path::new(&query.codebase_path))));
+    let runs: Vec<RunSummary> = store
+        .list()?
         .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        if entry.file_type().is_file() {
-            let path = entry.path();
-            
-            // Skip hidden files and common ignore patterns
-            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                if file_name.starts_with('.') || 
-                   path.to_str().map(|s| s.contains("/node_modules/") || 
-                                          s.contains("/target/") ||
-                                          s.contains("/.git/") ||
-                                          s.contains("/dist/") ||
-                                          s.contains("/build/")).unwrap_or(false) {
-                    continue;
-                }
-            }
-            
-            // Check if path matches query
-            if let Some(path_str) = path.to_str() {
-                if path_str.to_lowercase().contains(&query) {
-                    // Make path relative to codebase
-                    if let Ok(relative) = path.strip_prefix(&codebase_path) {
-                        if let Some(rel_str) = relative.to_str() {
-                            matching_files.push(rel_str.to_string());
-                        }
-                    }
-                }
-            }
-            
-            // Limit results to 50
-            if matching_files.len() >= 50 {
-                break;
-            }
-        }
+        .map(|run| RunSummary {
+            status: if run.prompt.is_some() {
+                "complete"
+            } else if run.error.is_some() {
+                "failed"
+            } else {
+                "partial"
+            },
+            session_id: run.session().to_string(),
+            run_id: run.run_id,
+            task: run.task,
+            started_at: run.started_at,
+            updated_at: run.updated_at,
+            duration_ms: run.duration_ms,
+            selected_files: run.selected_files.len(),
+            total_tokens: run.usage.map(|usage| usage.total_tokens),
+            rerun_of: run.rerun_of,
+            follow_up_of: run.follow_up_of,
+        })
+        .filter(|run| query.status.as_deref().is_none_or(|status| run.status == status))
+        .collect();
+    let page = query.page.unwrap_or(1).max(1);
+    let has_more = runs.len() > page * RUNS_PER_PAGE;
+    let runs = runs.into_iter().skip((page - 1) * RUNS_PER_PAGE).take(RUNS_PER_PAGE).collect();
+
+    Ok(Json(RunPageResponse { success: true, runs, page, per_page: RUNS_PER_PAGE, has_more }))
+}
+
+/// A saved run in full: its request, gathered context, prompt and usage
+#[cfg(feature = "web")]
+async fn run_handler(
+    UrlPath(id): UrlPath<String>,
+    Query(query): Query<ProjectQuery>,
+) -> Result<Json<miow_agent::RunState>, ApiError> {
+    let store = miow_agent::RunStore::new(runs_dir_for_db(&project_db_path(Path::new(&query.codebase_path))));
+    Ok(Json(store.load(&id)?))
+}
+
+/// Record whether a run's context was useful; later runs on the project rank it accordingly
+#[cfg(feature = "web")]
+async fn feedback_handler(
+    UrlPath(id): UrlPath<String>,
+    Json(request): Json<FeedbackRequest>,
+) -> Result<Json<FeedbackResponse>, ApiError> {
+    let db_path = project_db_path(Path::new(&request.codebase_path));
+    let run = miow_agent::RunStore::new(runs_dir_for_db(&db_path)).load(&id)?;
+    let items = feedback_items(&run, &request.files).map_err(|message| ApiError::Status(StatusCode::BAD_REQUEST, message))?;
+    let graph = KnowledgeGraph::new(&db_path)?;
+    for item in &items {
+        graph.record_feedback(&item.file_path, item.name.as_deref().unwrap_or(""), request.useful)?;
     }
-    
-    Json(SearchFilesResponse {
-        success: true,
-        files: Some(matching_files),
-        error: None,
-    })
+    Ok(Json(FeedbackResponse { success: true, items }))
 }
 
+/// One symbol with the names it uses and the symbols that use it
 #[cfg(feature = "web")]
-async fn health_handler(
-    State(_state): State<AppState>,
-) -> Json<HealthResponse> {
-    let gemini_configured = std::env::var("GEMINI_API_KEY").is_ok();
+async fn symbol_handler(
+    State(state): State<AppState>,
+    UrlPath(id): UrlPath<i64>,
+    Query(query): Query<ProjectQuery>,
+) -> Result<Json<SymbolDetailResponse>, ApiError> {
+    let graph = browse_graph(&state.index_jobs, Path::new(&query.codebase_path))?;
+    let symbol = graph
+        .get_symbol(id)?
+        .ok_or_else(|| ApiError::Status(StatusCode::NOT_FOUND, format!("No symbol with id {}", id)))?;
+    let references = graph.get_symbol_dependencies(id)?;
+    let referenced_by = graph.find_references_to(&symbol.name)?;
+
+    Ok(Json(SymbolDetailResponse { success: true, symbol, references, referenced_by }))
+}
 
-    // Check Qdrant connection
-    let qdrant_connected = reqwest::get("http://localhost:6333/collections")
-        .await
-        .map(|resp| resp.status().is_success())
-        .unwrap_or(false);
+/// `GET /api/files/{path}/symbols`: a file's symbols in source order. The path
+/// is relative to the project root; an absolute path inside it also works.
+#[cfg(feature = "web")]
+async fn file_symbols_handler(
+    State(state): State<AppState>,
+    UrlPath(path): UrlPath<String>,
+    Query(query): Query<ProjectQuery>,
+) -> Result<Json<SymbolsResponse>, ApiError> {
+    let Some(file) = path.strip_suffix("/symbols") else {
+        return Err(ApiError::Status(StatusCode::NOT_FOUND, format!("Unknown endpoint /api/files/{}", path)));
+    };
+    let codebase_path = Path::new(&query.codebase_path);
+    let graph = browse_graph(&state.index_jobs, codebase_path)?;
 
-    Json(HealthResponse {
-        status: "healthy".to_string(),
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        qdrant_connected,
-        gemini_configured,
-    })
+    let file = Path::new(file);
+    let relative = file.strip_prefix(codebase_path).unwrap_or(file);
+    let relative = relative.to_string_lossy();
+    let symbols = graph.get_file_symbols(relative.trim_start_matches("./"))?;
+
+    Ok(Json(SymbolsResponse { success: true, symbols }))
+}
+
+/// Symbols that refer to `name`
+#[cfg(feature = "web")]
+async fn references_handler(
+    State(state): State<AppState>,
+    UrlPath(name): UrlPath<String>,
+    Query(query): Query<ProjectQuery>,
+) -> Result<Json<SymbolsResponse>, ApiError> {
+    let graph = browse_graph(&state.index_jobs, Path::new(&query.codebase_path))?;
+    let symbols = graph.find_references_to(&name)?;
+
+    Ok(Json(SymbolsResponse { success: true, symbols }))
 }
 
 #[cfg(feature = "web")]
@@ -1219,43 +4661,13 @@ async fn debug_signature_handler(
 async fn debug_context_handler(
     State(state): State<AppState>,
     Json(request): Json<DebugRequest>,
-) -> Result<Json<DebugContextResponse>, StatusCode> {
+) -> Result<Json<DebugContextResponse>, ApiError> {
     let codebase_path = PathBuf::from(&request.codebase_path);
-    let db_path = codebase_path.join(".miow").join("miow.db");
-    let db_dir = db_path.parent().unwrap();
-    
-    // Create .miow directory if it doesn't exist
-    if !db_dir.exists() {
-        if let Err(e) = std::fs::create_dir_all(db_dir) {
-            return Ok(Json(DebugContextResponse {
-                success: false,
-                context: None,
-                error: Some(format!("Failed to create .miow directory: {}", e)),
-            }));
-        }
-    }
-    
-    // Auto-index if DB doesn't exist
-    if !db_path.exists() {
-        println!("{}", format!("⚠️  No index found for {}. Indexing now...", codebase_path.display()).yellow());
-        println!("{}", "⏳ This may take a few minutes depending on codebase size...".bright_black());
-        
-        match handle_index(codebase_path.clone(), db_path.clone()).await {
-            Ok(_) => {
-                println!("✅ Indexing completed successfully");
-            }
-            Err(e) => {
-                let error_msg = format!(
-                    "Indexing failed: {}. This might be due to:\n- Insufficient permissions\n- Corrupted files\n- Network issues (if using remote paths)\n\nPlease check the path and try again.",
-                    e
-                );
-                return Ok(Json(DebugContextResponse {
-                    success: false,
-                    context: None,
-                    error: Some(error_msg),
-                }));
-            }
-        }
+    let db_path = project_db_path(&codebase_path);
+
+    // Indexing runs in the background rather than holding the request for minutes
+    if let Some(job) = pending_index(&state.index_jobs, &codebase_path, &db_path) {
+        return Err(ApiError::Indexing(Box::new(job)));
     }
     
     match MiowOrchestrator::new(db_path.to_str().unwrap()) {
@@ -1263,10 +4675,9 @@ async fn debug_context_handler(
             if let Some(llm) = &state.llm {
                 orchestrator = orchestrator.with_llm_arc(llm.clone());
             }
+            let _cancel_on_disconnect = orchestrator.cancellation_token().drop_guard();
             
-            let qdrant_url = std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
-            let collection_name = collection_name_for_path(&codebase_path);
-            if let Ok(store) = miow_vector::VectorStore::new(&qdrant_url, &collection_name).await {
+            if let Ok(store) = open_vector_store(&codebase_path, &db_path).await {
                 orchestrator = orchestrator.with_vector_store(std::sync::Arc::new(store));
             }
             
@@ -1279,7 +4690,7 @@ async fn debug_context_handler(
                 "total_symbols": total_symbols,
                 "total_files": total_files,
                 "db_path": db_path.to_string_lossy(),
-                "collection_name": collection_name,
+                "collection_name": collection_name_for_path(&codebase_path),
             });
             
             Ok(Json(DebugContextResponse {
@@ -1302,45 +4713,15 @@ async fn debug_context_handler(
 async fn files_handler(
     State(state): State<AppState>,
     Json(request): Json<GenerateRequest>,
-) -> Result<Json<FilesResponse>, StatusCode> {
-    println!("📁 Getting relevant files for: {}", request.user_prompt.bright_yellow());
+) -> Result<Json<FilesResponse>, ApiError> {
+    say!("📁 Getting relevant files for: {}", request.user_prompt.bright_yellow());
     
     let codebase_path = PathBuf::from(&request.codebase_path);
-    let db_path = codebase_path.join(".miow").join("miow.db");
-    let db_dir = db_path.parent().unwrap();
-    
-    // Create .miow directory if it doesn't exist
-    if !db_dir.exists() {
-        if let Err(e) = std::fs::create_dir_all(db_dir) {
-            return Ok(Json(FilesResponse {
-                success: false,
-                files: vec![],
-                error: Some(format!("Failed to create .miow directory: {}", e)),
-            }));
-        }
-    }
-    
-    // Auto-index if DB doesn't exist
-    if !db_path.exists() {
-        println!("{}", format!("⚠️  No index found for {}. Indexing now...", codebase_path.display()).yellow());
-        println!("{}", "⏳ This may take a few minutes depending on codebase size...".bright_black());
-        
-        match handle_index(codebase_path.clone(), db_path.clone()).await {
-            Ok(_) => {
-                println!("✅ Indexing completed successfully");
-            }
-            Err(e) => {
-                let error_msg = format!(
-                    "Indexing failed: {}. This might be due to:\n- Insufficient permissions\n- Corrupted files\n- Network issues (if using remote paths)\n\nPlease check the path and try again.",
-                    e
-                );
-                return Ok(Json(FilesResponse {
-                    success: false,
-                    files: vec![],
-                    error: Some(error_msg),
-                }));
-            }
-        }
+    let db_path = project_db_path(&codebase_path);
+
+    // Indexing runs in the background rather than holding the request for minutes
+    if let Some(job) = pending_index(&state.index_jobs, &codebase_path, &db_path) {
+        return Err(ApiError::Indexing(Box::new(job)));
     }
     
     match MiowOrchestrator::new(db_path.to_str().unwrap()) {
@@ -1348,16 +4729,15 @@ async fn files_handler(
             if let Some(llm) = &state.llm {
                 orchestrator = orchestrator.with_llm_arc(llm.clone());
             }
+            let _cancel_on_disconnect = orchestrator.cancellation_token().drop_guard();
             
-            let qdrant_url = std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
-            let collection_name = collection_name_for_path(&codebase_path);
-            if let Ok(store) = miow_vector::VectorStore::new(&qdrant_url, &collection_name).await {
+            if let Ok(store) = open_vector_store(&codebase_path, &db_path).await {
                 orchestrator = orchestrator.with_vector_store(std::sync::Arc::new(store));
             }
             
             match orchestrator.get_relevant_files(&request.user_prompt, &codebase_path).await {
                 Ok(context_items) => {
-                    println!("✅ Found {} relevant files", context_items.len());
+                    say!("✅ Found {} relevant files", context_items.len());
                     let files: Vec<FileInfo> = context_items.into_iter().map(|item| FileInfo {
                         file_path: item.file_path,
                         symbol_name: item.name,
@@ -1372,7 +4752,7 @@ async fn files_handler(
                     }))
                 }
                 Err(e) => {
-                    println!("❌ Failed to get files: {}", e);
+                    say!("❌ Failed to get files: {}", e);
                     Ok(Json(FilesResponse {
                         success: false,
                         files: vec![],
@@ -1395,95 +4775,281 @@ async fn files_handler(
 async fn generate_with_files_handler(
     State(state): State<AppState>,
     Json(request): Json<GenerateWithFilesRequest>,
-) -> Result<Json<GenerateResponse>, StatusCode> {
-    println!("🤖 Processing request with selected files: {}", request.user_prompt.bright_yellow());
-    println!("📋 Selected {} files", request.selected_files.len());
+) -> Result<(StatusCode, Json<GenerateResponse>), ApiError> {
+    say!("🤖 Processing request with selected files: {}", request.user_prompt.bright_yellow());
+    say!("📋 Selected {} files", request.selected_files.len());
     
     let codebase_path = PathBuf::from(&request.codebase_path);
-    let db_path = codebase_path.join(".miow").join("miow.db");
-    let db_dir = db_path.parent().unwrap();
-    
-    // Create .miow directory if it doesn't exist
-    if !db_dir.exists() {
-        if let Err(e) = std::fs::create_dir_all(db_dir) {
-            return Ok(Json(GenerateResponse {
-                success: false,
-                result: None,
-                error: Some(format!("Failed to create .miow directory: {}", e)),
-            }));
-        }
-    }
-    
-    // Auto-index if DB doesn't exist
-    if !db_path.exists() {
-        println!("{}", format!("⚠️  No index found for {}. Indexing now...", codebase_path.display()).yellow());
-        println!("{}", "⏳ This may take a few minutes depending on codebase size...".bright_black());
-        
-        match handle_index(codebase_path.clone(), db_path.clone()).await {
-            Ok(_) => {
-                println!("✅ Indexing completed successfully");
-            }
-            Err(e) => {
-                let error_msg = format!(
-                    "Indexing failed: {}. This might be due to:\n- Insufficient permissions\n- Corrupted files\n- Network issues (if using remote paths)\n\nPlease check the path and try again.",
-                    e
-                );
-                return Ok(Json(GenerateResponse {
-                    success: false,
-                    result: None,
-                    error: Some(error_msg),
-                }));
-            }
-        }
+    let db_path = project_db_path(&codebase_path);
+
+    // Indexing runs in the background rather than holding the request for minutes
+    if let Some(job) = pending_index(&state.index_jobs, &codebase_path, &db_path) {
+        return Err(ApiError::Indexing(Box::new(job)));
     }
     
+    let run = WebRun::start(&codebase_path, &request.user_prompt, &request.selected_files, serde_json::json!({}));
     match MiowOrchestrator::new(db_path.to_str().unwrap()) {
-        Ok(mut orchestrator) => {
-            if let Some(llm) = &state.llm {
-                orchestrator = orchestrator.with_llm_arc(llm.clone());
-            }
+        Ok(orchestrator) => {
+            let mut orchestrator = run.attach(orchestrator, &state);
+            let _cancel_on_disconnect = orchestrator.cancellation_token().drop_guard();
             
-            let qdrant_url = std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
-            let collection_name = collection_name_for_path(&codebase_path);
-            if let Ok(store) = miow_vector::VectorStore::new(&qdrant_url, &collection_name).await {
+            if let Ok(store) = open_vector_store(&codebase_path, &db_path).await {
                 orchestrator = orchestrator.with_vector_store(std::sync::Arc::new(store));
             }
             
-            match orchestrator.generate_enhanced_prompt_with_files(
+            let result = orchestrator.generate_enhanced_prompt_with_files(
                 &request.user_prompt,
                 &codebase_path,
                 &request.selected_files,
-            ).await {
-                Ok(result) => {
-                    println!("✅ Request completed successfully");
-                    Ok(Json(GenerateResponse {
-                        success: true,
-                        result: Some(result),
-                        error: None,
-                    }))
-                }
-                Err(e) => {
-                    println!("❌ Request failed: {}", e);
-                    Ok(Json(GenerateResponse {
-                        success: false,
-                        result: None,
-                        error: Some(e.to_string()),
-                    }))
-                }
-            }
+            ).await;
+            run.finish(&result);
+            Ok(GenerateResponse::from_result(result, &run, None))
         }
         Err(e) => {
-            Ok(Json(GenerateResponse {
-                success: false,
-                result: None,
-                error: Some(e.to_string()),
-            }))
+            let result = Err(e.context("Failed to initialize orchestrator"));
+            run.finish(&result);
+            Ok(GenerateResponse::from_result(result, &run, None))
         }
     }
 }
 
 #[cfg(not(feature = "web"))]
-async fn start_web_server(_port: u16, _db_path: PathBuf) -> Result<()> {
-    println!("❌ Web server feature not enabled. Compile with --features web");
+async fn start_web_server(_port: u16, _db_path: PathBuf, _llm_args: LlmArgs) -> Result<()> {
+    say!("❌ Web server feature not enabled. Compile with --features web");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_questions_skips_blanks_and_comments() {
+        let text = "# auth work\nAdd a logout button\n\n   \n  Add password reset  \n#later\n";
+        assert_eq!(parse_questions(text), vec!["Add a logout button", "Add password reset"]);
+        assert!(parse_questions("# only comments\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_batch_file_names_are_numbered_slugs() {
+        assert_eq!(batch_file_name(3, 12, "Add a logout button!", "md"), "03-add-a-logout-button.md");
+        assert_eq!(batch_file_name(7, 150, "Why is `parse()` slow?", "json"), "007-why-is-parse-slow.json");
+        assert_eq!(batch_file_name(1, 2, "???", "md"), "01.md");
+        let long = batch_file_name(1, 1, &"word ".repeat(30), "md");
+        assert!(long.len() <= "01-".len() + 48 + ".md".len() && !long.contains("-.md"), "{}", long);
+    }
+
+    #[test]
+    fn test_log_messages_drop_decoration() {
+        assert_eq!(log_message("🚀 MIOW-CONTEXT INITIALIZATION"), Some("MIOW-CONTEXT INITIALIZATION"));
+        assert_eq!(log_message("   • src/main.rs (12 symbols)  "), Some("src/main.rs (12 symbols)"));
+        assert_eq!(log_message("✅ Vector store connected!"), Some("Vector store connected!"));
+        assert_eq!(log_message("Über 3 files"), Some("Über 3 files"));
+        assert_eq!(log_message("══════════════"), None);
+        assert_eq!(log_message(""), None);
+    }
+
+    /// Server state for middleware tests: no LLM and an unpublished metrics recorder
+    #[cfg(feature = "web")]
+    pub(crate) fn web_state(auth: auth::Authenticator) -> AppState {
+        AppState {
+            llm: None,
+            usage: std::sync::Arc::new(miow_llm::UsageTracker::metered()),
+            approvals: miow_agent::ChannelApprover::new(std::time::Duration::from_secs(5)),
+            index_jobs: jobs::IndexJobs::start(),
+            auth,
+            metrics: metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder().handle(),
+            max_generate_body: 256 * 1024,
+            max_agent_iterations: 50,
+            shutdown: tokio_util::sync::CancellationToken::new(),
+            tasks: tokio_util::task::TaskTracker::new(),
+            context_cache: context_cache::ContextCache::new(),
+            allowed_origins: std::sync::Arc::new(Vec::new()),
+        }
+    }
+
+    /// Serve `app` on a free local port, with peer addresses like `serve`; returns its base URL
+    #[cfg(feature = "web")]
+    pub(crate) async fn serve_locally(app: Router) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).into_future());
+        format!("http://{}", addr)
+    }
+
+    /// The whole API, open (no tokens) and without a rate limit
+    #[cfg(feature = "web")]
+    async fn serve_api() -> String {
+        let state = web_state(auth::Authenticator::new(&Default::default(), None).unwrap());
+        serve_locally(api_router(&state, None).with_state(state)).await
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_open_api_refuses_other_sites() {
+        let mut state = web_state(auth::Authenticator::new(&Default::default(), None).unwrap());
+        state.allowed_origins = std::sync::Arc::new(vec!["https://tools.example.com".to_string()]);
+        let base = serve_locally(api_router(&state, None).with_state(state)).await;
+        let client = reqwest::Client::new();
+        let usage = |origin: Option<String>| {
+            let request = client.get(format!("{}/api/usage", base));
+            async move {
+                let request = match origin {
+                    Some(origin) => request.header("Origin", origin),
+                    None => request,
+                };
+                request.send().await.unwrap()
+            }
+        };
+
+        assert_eq!(usage(None).await.status(), 200);
+        assert_eq!(usage(Some(base.clone())).await.status(), 200);
+        let evil = usage(Some("https://evil.example".to_string())).await;
+        assert_eq!(evil.status(), 403);
+        assert!(evil.headers().get("access-control-allow-origin").is_none());
+        let allowed = usage(Some("https://tools.example.com".to_string())).await;
+        assert_eq!(allowed.status(), 200);
+        assert_eq!(allowed.headers()["access-control-allow-origin"], "https://tools.example.com");
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_runs_are_paged_and_filtered_by_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = miow_agent::RunStore::new(runs_dir_for_db(&project_db_path(dir.path())));
+        for n in 0..RUNS_PER_PAGE + 2 {
+            let run = miow_agent::RunRecorder::open(store.clone(), &format!("run-{:03}", n), "Add a page", dir.path()).unwrap();
+            match n % 3 {
+                0 => run.update(|state| state.prompt = Some("# TASK".to_string())),
+                1 => run.update(|state| state.error = Some("LLM unreachable".to_string())),
+                _ => {}
+            }
+        }
+        let base = serve_api().await;
+        let client = reqwest::Client::new();
+        let list = |query: &[(&str, &str)]| {
+            let codebase = dir.path().to_string_lossy().to_string();
+            let request = client.get(format!("{}/api/runs", base)).query(&[("codebase_path", codebase)]).query(query);
+            async move { request.send().await.unwrap().json::<serde_json::Value>().await.unwrap() }
+        };
+
+        let first = list(&[]).await;
+        assert_eq!(first["runs"].as_array().unwrap().len(), RUNS_PER_PAGE);
+        assert_eq!((first["page"].as_u64(), first["has_more"].as_bool()), (Some(1), Some(true)));
+        let second = list(&[("page", "2")]).await;
+        assert_eq!(second["runs"].as_array().unwrap().len(), 2);
+        assert_eq!(second["has_more"], false);
+
+        let failed = list(&[("status", "failed")]).await;
+        let failed = failed["runs"].as_array().unwrap();
+        let with_status = |rest: usize| (0..RUNS_PER_PAGE + 2).filter(|n| n % 3 == rest).count();
+        assert_eq!(failed.len(), with_status(1));
+        assert!(failed.iter().all(|run| run["status"] == "failed"));
+        assert_eq!(list(&[("status", "complete")]).await["runs"].as_array().unwrap().len(), with_status(0));
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_run_ids_are_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = miow_agent::RunStore::new(runs_dir_for_db(&project_db_path(dir.path())));
+        miow_agent::RunRecorder::open(store, "run-1", "Add a page", dir.path()).unwrap();
+        let base = serve_api().await;
+        let client = reqwest::Client::new();
+        let get = |id: &str| {
+            client.get(format!("{}/api/runs/{}", base, id)).query(&[("codebase_path", dir.path())]).send()
+        };
+
+        let run: serde_json::Value = get("run-1").await.unwrap().json().await.unwrap();
+        assert_eq!(run["task"], "Add a page");
+        let invalid = get("..%2Frun-1").await.unwrap();
+        assert_eq!(invalid.status(), 400);
+        let body: serde_json::Value = invalid.json().await.unwrap();
+        assert_eq!(body["code"], "invalid_input");
+        assert_eq!(get("run-2").await.unwrap().status(), 404);
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_search_files_matches_paths_and_skips_build_output() {
+        let dir = tempfile::tempdir().unwrap();
+        for path in ["src/LoginForm.tsx", "src/Signup.tsx", "node_modules/login/index.js"] {
+            std::fs::create_dir_all(dir.path().join(path).parent().unwrap()).unwrap();
+            std::fs::write(dir.path().join(path), "").unwrap();
+        }
+        let base = serve_api().await;
+        let client = reqwest::Client::new();
+        let search = |codebase: std::path::PathBuf| {
+            let body = serde_json::json!({ "codebase_path": codebase, "query": "LOGIN" });
+            let request = client.post(format!("{}/api/search-files", base)).json(&body);
+            async move { request.send().await.unwrap().json::<serde_json::Value>().await.unwrap() }
+        };
+
+        let found = search(dir.path().to_path_buf()).await;
+        assert_eq!(found["success"], true);
+        assert_eq!(found["files"], serde_json::json!(["src/LoginForm.tsx"]));
+        let missing = search(dir.path().join("missing")).await;
+        assert_eq!(missing["success"], false);
+    }
+
+    /// A project indexed into the database `serve` reads, with `helper` called by `caller`
+    #[cfg(feature = "web")]
+    async fn indexed_project() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        let code = "export function helper(): number {\n  return 1;\n}\n\nexport function caller(): number {\n  return helper();\n}\n";
+        std::fs::write(dir.path().join("src/util.ts"), code).unwrap();
+        std::fs::create_dir_all(dir.path().join(".miow")).unwrap();
+        let graph = std::sync::Arc::new(KnowledgeGraph::new(project_db_path(dir.path())).unwrap());
+        CodebaseIndexer::new(dir.path().to_path_buf()).unwrap().with_graph(graph).index().await.unwrap();
+        dir
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_symbol_browsing_endpoints() {
+        let dir = indexed_project().await;
+        let base = serve_api().await;
+        let client = reqwest::Client::new();
+        let get = |path: String| {
+            let request = client.get(format!("{}{}", base, path)).query(&[("codebase_path", dir.path())]);
+            async move {
+                let response = request.send().await.unwrap();
+                (response.status().as_u16(), response.json::<serde_json::Value>().await.unwrap())
+            }
+        };
+
+        let (status, page) = get("/api/symbols?query=help".to_string()).await;
+        assert_eq!(status, 200);
+        let symbols = page["symbols"].as_array().unwrap();
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0]["name"], "helper");
+        let (_, none) = get("/api/symbols?query=nothing_like_this".to_string()).await;
+        assert!(none["symbols"].as_array().unwrap().is_empty());
+
+        let (status, detail) = get(format!("/api/symbols/{}", symbols[0]["id"])).await;
+        assert_eq!(status, 200);
+        assert_eq!(detail["symbol"]["name"], "helper");
+        assert!(detail["referenced_by"].as_array().unwrap().iter().any(|s| s["name"] == "caller"), "{}", detail);
+        assert_eq!(get("/api/symbols/999999".to_string()).await.0, 404);
+
+        let (status, file) = get("/api/files/src/util.ts/symbols".to_string()).await;
+        assert_eq!(status, 200);
+        let names: Vec<&str> = file["symbols"].as_array().unwrap().iter().filter_map(|s| s["name"].as_str()).collect();
+        assert_eq!(names, vec!["helper", "caller"]);
+        assert_eq!(get("/api/files/src/util.ts".to_string()).await.0, 404);
+        let (_, missing) = get("/api/files/src/missing.ts/symbols".to_string()).await;
+        assert!(missing["symbols"].as_array().unwrap().is_empty());
+
+        let (status, references) = get("/api/references/helper".to_string()).await;
+        assert_eq!(status, 200);
+        assert_eq!(references["symbols"][0]["name"], "caller");
+        let (_, unused) = get("/api/references/caller".to_string()).await;
+        assert!(unused["symbols"].as_array().unwrap().is_empty());
+
+        // A project without an index is not found, with how to build one
+        let empty = tempfile::tempdir().unwrap();
+        let response = client.get(format!("{}/api/symbols", base)).query(&[("codebase_path", empty.path())]).send().await.unwrap();
+        assert_eq!(response.status(), 404);
+    }
+}
@@ -1,18 +1,49 @@
+use crate::context_cache::{CachedContext, ContextCache, ContextKey};
+use crate::cost::CostEstimate;
+use crate::explain::{self, ExplainLog, ItemExplanation, Provenance};
+use crate::pipeline::{PipelineConfig, Stage, Workflow};
 use anyhow::Result;
-use miow_analyzer::ContextAnalyzer;
-use miow_agent::{AutonomousAgent, GeminiContextAuditor, GeminiRouterAgent, GeminiWorkerAgent, RouterAgent, SearchPlan, WorkerAgent};
-use miow_core::{IntelligentSignatureDetector, ProjectSignature};
-use miow_graph::{KnowledgeGraph, RelationshipInferencer};
-use miow_llm::{ContextItem, GatheredContext, LLMProvider, LLMResponse, Message, Role};
+use futures::StreamExt;
+use miow_analyzer::{ContextAnalyzer, Intent, IntentClassifier, IntentRule};
+use miow_agent::{AgentBudget, Answerer, Approver, AutonomousAgent, CommandPolicy, LspConfig, PromptItem, PromptRecord, RunRecorder, RunStore, SearchCache, Tool, GeminiContextAuditor, GeminiRouterAgent, RouterAgent, SearchPlan, WorkerAgent};
+use miow_core::ProjectSignature;
+use miow_graph::{ImpactKind, KnowledgeGraph, SymbolSearchResult};
+use miow_llm::{
+    CachedLLMProvider, CancellableLLMProvider, CancellationToken, ContextItem, GatheredContext, LLMCache, LLMProvider, Message, Role,
+    UsageSummary, UsageTracker, UsageTrackingProvider,
+};
 use miow_prompt::{
-    ConstantInfo, ContextData, DesignTokenInfo, PromptGenerator, PromptRequest, SchemaInfo,
-    SymbolInfo, TypeInfo,
+    code_block, language_for_path, ConstantInfo, ContextData, ContextExporter, DesignTokenInfo, ComponentInfo, ComponentProp, EditTarget, EnvVarInfo, ExportTarget, ImpactSite, OwnedFile, PromptGenerator,
+    PromptRequest, PromptTemplates, redact_context, RefactorImpact, RouteInfo, SchemaInfo, SymbolInfo, TestInfo, TypeInfo,
 };
 use miow_vector::VectorStore;
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tracing::{info, warn};
+use tracing::{info, warn, Instrument};
+
+/// Router workers run at once unless configured otherwise
+pub const DEFAULT_WORKER_CONCURRENCY: usize = 4;
+
+/// The context a task would get, gathered without calling the LLM
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DryRunReport {
+    pub project: String,
+    /// The workflow `ask` would run
+    pub workflow: Workflow,
+    pub intent: Intent,
+    pub search_queries: Vec<String>,
+    /// What survived deduplication and pruning, in prompt order
+    pub items: Vec<miow_prompt::ContextEntry>,
+    /// Distinct files the items come from
+    pub files: Vec<String>,
+    pub context_tokens: usize,
+    pub token_budget: usize,
+    pub pruning: miow_prompt::PruneReport,
+    /// Why each item was included, with `with_explain`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<Vec<ItemExplanation>>,
+}
 
 /// Orchestrator that ties together all the components with LLM-powered context gathering
 #[allow(dead_code)]
@@ -22,29 +53,546 @@ pub struct MiowOrchestrator {
     prompt_generator: PromptGenerator,
     llm: Option<Arc<dyn LLMProvider>>,
     vector_store: Option<Arc<VectorStore>>,
+    llm_cache: Option<Arc<LLMCache>>,
+    usage: Arc<UsageTracker>,
+    cancel: CancellationToken,
+    command_policy: CommandPolicy,
+    agent_budget: AgentBudget,
+    approver: Option<Arc<dyn Approver>>,
+    answerer: Option<Arc<dyn Answerer>>,
+    agent_tools: Vec<Arc<dyn Tool>>,
+    /// Graph/vector results shared by everything in one run; cleared when a run starts
+    search_cache: SearchCache,
+    /// Router workers allowed to run at once
+    worker_concurrency: usize,
+    /// Language server for the agent's `lsp` tool; detected from the project when unset
+    lsp: Option<LspConfig>,
+    /// Where to checkpoint runs, and the id of the run to record or resume
+    run: Option<(RunStore, String)>,
+    /// Shape of the generated prompt
+    prompt_format: miow_prompt::PromptFormat,
+    /// Assistants to export the gathered context for, and the directory to write to
+    exports: Option<(Vec<ExportTarget>, std::path::PathBuf)>,
+    /// Prompt template overrides; loaded from the project's `.miow/templates` when unset
+    templates: Option<PromptTemplates>,
+    /// Ask for a unified diff against the task's target symbol instead of whole files
+    diff_prompts: bool,
+    /// Most files the gathered context may come from
+    max_files: Option<usize>,
+    /// Where each gathered item came from, when a "why included" report was asked for
+    explain: Option<ExplainLog>,
+    /// Workflow choice and the stages of the enhanced workflow
+    pipeline: PipelineConfig,
+    /// Contexts compiled earlier in the session, for near-duplicate tasks
+    context_cache: Option<ContextCache>,
+    /// Other repositories' indexes, searched read-only alongside this one, by name
+    linked_indexes: Vec<(String, Arc<KnowledgeGraph>)>,
 }
 
 #[allow(dead_code)]
 impl MiowOrchestrator {
     pub fn new(db_path: &str) -> Result<Self> {
+        let llm_cache = match LLMCache::open(Self::cache_dir_for_db(db_path)) {
+            Ok(cache) => Some(Arc::new(cache)),
+            Err(e) => {
+                warn!("⚠️  LLM response cache unavailable: {}", e);
+                None
+            }
+        };
+
         Ok(Self {
             graph: Arc::new(KnowledgeGraph::new(db_path)?),
             analyzer: ContextAnalyzer::new(),
             prompt_generator: PromptGenerator::new(),
             llm: None,
             vector_store: None,
+            llm_cache,
+            usage: Arc::new(UsageTracker::new()),
+            cancel: CancellationToken::new(),
+            // Nobody may be at a terminal (e.g. the web server), so don't prompt unless told to
+            command_policy: CommandPolicy::default().non_interactive(),
+            agent_budget: AgentBudget::default(),
+            approver: None,
+            answerer: None,
+            agent_tools: Vec::new(),
+            search_cache: SearchCache::new(),
+            worker_concurrency: DEFAULT_WORKER_CONCURRENCY,
+            lsp: None,
+            run: None,
+            prompt_format: miow_prompt::PromptFormat::default(),
+            exports: None,
+            templates: None,
+            diff_prompts: false,
+            max_files: None,
+            explain: None,
+            pipeline: PipelineConfig::default(),
+            context_cache: None,
+            linked_indexes: Vec::new(),
         })
     }
 
+    /// Cache lives in `.miow/cache` next to the knowledge graph database
+    fn cache_dir_for_db(db_path: &str) -> std::path::PathBuf {
+        let parent = std::path::Path::new(db_path)
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| std::path::Path::new("."));
+
+        if parent.file_name().map(|n| n == ".miow").unwrap_or(false) {
+            parent.join("cache")
+        } else {
+            parent.join(".miow").join("cache")
+        }
+    }
+
     /// Create orchestrator with LLM provider
-    pub fn with_llm(mut self, llm: Box<dyn LLMProvider>) -> Self {
-        self.llm = Some(Arc::from(llm));
-        self
+    pub fn with_llm(self, llm: Box<dyn LLMProvider>) -> Self {
+        self.with_llm_arc(Arc::from(llm))
     }
 
-    /// Create orchestrator with shared LLM provider
+    /// Create orchestrator with shared LLM provider (responses are cached on disk when possible)
     pub fn with_llm_arc(mut self, llm: Arc<dyn LLMProvider>) -> Self {
-        self.llm = Some(llm);
+        let llm: Arc<dyn LLMProvider> = Arc::new(UsageTrackingProvider::new(llm, self.usage.clone()));
+        let llm: Arc<dyn LLMProvider> = match &self.llm_cache {
+            Some(cache) => Arc::new(CachedLLMProvider::new(llm, cache.clone())),
+            None => llm,
+        };
+        self.llm = Some(Arc::new(CancellableLLMProvider::new(llm, self.cancel.clone())));
+        self
+    }
+
+    /// Abort LLM calls and agent loops once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.llm = self
+            .llm
+            .take()
+            .map(|llm| Arc::new(CancellableLLMProvider::new(llm, token.clone())) as Arc<dyn LLMProvider>);
+        self.cancel = token;
+        self
+    }
+
+    /// Token that cancels this orchestrator's work
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Stop between phases once cancelled. Phases shrug off their own LLM
+    /// failures, so without this a cancelled run would carry on without them.
+    fn check_cancelled(&self) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(miow_agent::AgentError::Cancelled.into());
+        }
+        Ok(())
+    }
+
+    /// Token usage and estimated cost of LLM calls made by this orchestrator
+    pub fn usage_summary(&self) -> UsageSummary {
+        self.usage.summary()
+    }
+
+    /// What a fresh run of `user_prompt` is expected to spend on LLM calls,
+    /// for the workflow `generate_autonomous_prompt` would take; None without an LLM
+    pub fn estimate_cost(&self, user_prompt: &str) -> Option<CostEstimate> {
+        let llm = self.llm.as_ref()?;
+        let task_tokens = self.token_counter().count(user_prompt);
+        Some(match self.pipeline.workflow {
+            Workflow::Agent => {
+                CostEstimate::agent(llm.model_name(), task_tokens, &self.agent_budget, self.pipeline.plan_samples)
+            }
+            Workflow::Enhanced => {
+                let classify = !self.analyzer.classifier().classify(user_prompt).is_confident();
+                CostEstimate::enhanced(llm.model_name(), task_tokens, &self.pipeline, classify)
+            }
+        })
+    }
+
+    /// Drop the LLM, so runs take the deterministic pipeline
+    pub fn without_llm(mut self) -> Self {
+        self.llm = None;
+        self
+    }
+
+    /// Disable the persistent LLM response cache (call before `with_llm`)
+    pub fn without_llm_cache(mut self) -> Self {
+        self.llm_cache = None;
+        self
+    }
+
+    /// Policy for the shell commands the autonomous agent may run
+    pub fn with_command_policy(mut self, policy: CommandPolicy) -> Self {
+        self.command_policy = policy;
+        self
+    }
+
+    /// Who approves agent actions that need it (commands, very large reads); without one they are refused
+    pub fn with_approver(mut self, approver: Arc<dyn Approver>) -> Self {
+        self.approver = Some(approver);
+        self
+    }
+
+    /// Who answers the agent's clarifying questions; without one it doesn't ask
+    pub fn with_answerer(mut self, answerer: Arc<dyn Answerer>) -> Self {
+        self.answerer = Some(answerer);
+        self
+    }
+
+    /// Cap how many router workers run in parallel (at least one)
+    pub fn with_worker_concurrency(mut self, limit: usize) -> Self {
+        self.worker_concurrency = limit.max(1);
+        self
+    }
+
+    /// Choose the workflow and the enhanced workflow's stages, usually from miow.toml's `[pipeline]`
+    /// Also search another repository's index (see [`KnowledgeGraph::open_read_only`]);
+    /// its items' paths are prefixed `name:`
+    pub fn with_linked_index(mut self, name: impl Into<String>, graph: KnowledgeGraph) -> Self {
+        self.linked_indexes.push((name.into(), Arc::new(graph)));
+        self
+    }
+
+    /// Classify tasks with the project's `[[intents]]` rules ahead of the built-in taxonomy
+    pub fn with_intent_rules(mut self, rules: Vec<IntentRule>) -> Self {
+        self.analyzer = ContextAnalyzer::new().with_classifier(IntentClassifier::with_rules(rules));
+        self
+    }
+
+    pub fn with_pipeline(mut self, pipeline: PipelineConfig) -> Self {
+        if let Some(limit) = pipeline.worker_concurrency {
+            self = self.with_worker_concurrency(limit);
+        }
+        self.pipeline = pipeline;
+        self
+    }
+
+    /// Limit every search (graph, vector and the agent's tools) to some directories and symbol kinds
+    pub fn with_search_scope(mut self, scope: miow_graph::SearchScope) -> Self {
+        self.search_cache = self.search_cache.with_scope(scope);
+        self
+    }
+
+    /// Keep gathered context to the `limit` most relevant files
+    pub fn with_max_files(mut self, limit: usize) -> Self {
+        self.max_files = Some(limit.max(1));
+        self
+    }
+
+    /// Language server the agent asks for exact definitions and references
+    pub fn with_lsp(mut self, config: LspConfig) -> Self {
+        self.lsp = Some(config);
+        self
+    }
+
+    /// Checkpoint finished steps under `run_id` in `store`. If that run was saved
+    /// before, its finished steps are reused instead of being redone.
+    pub fn with_run(mut self, store: RunStore, run_id: impl Into<String>) -> Self {
+        self.run = Some((store, run_id.into()));
+        self
+    }
+
+    /// Render generated prompts as Markdown, XML, ChatML messages or JSON
+    pub fn with_prompt_format(mut self, format: miow_prompt::PromptFormat) -> Self {
+        self.prompt_format = format;
+        self
+    }
+
+    /// Write the context of each autonomous run for other assistants into `dir`
+    pub fn with_exports(mut self, targets: Vec<ExportTarget>, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.exports = Some((targets, dir.into()));
+        self
+    }
+
+    /// Use these prompt templates instead of the project's `.miow/templates`
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.prompt_generator = PromptGenerator::new().with_templates(templates.clone());
+        self.templates = Some(templates);
+        self
+    }
+
+    /// Generate diff-style prompts: the target symbol's current code and its direct
+    /// dependents, with instructions to answer in a unified diff
+    pub fn with_diff_prompts(mut self, enabled: bool) -> Self {
+        self.diff_prompts = enabled;
+        self
+    }
+
+    /// The symbol the task names, found in the graph, with the symbols that reference it.
+    /// `None` unless diff prompts are on.
+    fn find_edit_target(&self, user_prompt: &str, context: &ContextData) -> Option<EditTarget> {
+        if !self.diff_prompts {
+            return None;
+        }
+        if !matches!(self.analyzer.analyze_prompt(user_prompt).intent, Intent::Modify | Intent::Fix | Intent::Refactor) {
+            warn!("Diff prompt requested for a task that doesn't look like a change to existing code");
+        }
+
+        let target = edit_target_candidates(user_prompt).into_iter().find_map(|name| {
+            let mut matches = self.graph.find_symbols_by_name(&name).ok()?;
+            // Prefer the definition the gathered context already points at
+            let position = matches
+                .iter()
+                .position(|m| context.relevant_symbols.iter().any(|s| s.file_path == m.file_path || s.file_path.contains(&format!("\"{}\"", m.file_path))))
+                .unwrap_or(0);
+            (!matches.is_empty()).then(|| matches.swap_remove(position))
+        });
+        let Some(target) = target else {
+            warn!("No symbol named in the task was found in the index; the diff prompt has no target");
+            return None;
+        };
+
+        let dependents = self
+            .graph
+            .find_references_to(&target.name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(symbol_info_from_result)
+            .collect();
+        let target = EditTarget::new(symbol_info_from_result(target), dependents);
+        info!("🩹 Diff target: {} in {} ({} dependents)", target.symbol.name, target.symbol.file_path, target.dependents.len());
+        Some(target)
+    }
+
+    /// Every definition, reference, import and export of the symbol a refactor
+    /// or rename task names. `None` for other intents, or when no name in the
+    /// task is defined in the graph.
+    fn find_refactor_impact(&self, user_prompt: &str) -> Option<RefactorImpact> {
+        if self.analyzer.analyze_prompt(user_prompt).intent != Intent::Refactor {
+            return None;
+        }
+        let impact = edit_target_candidates(user_prompt).into_iter().find_map(|name| {
+            let impact = self.graph.impact_of_rename(&name).ok()?;
+            impact.locations.iter().any(|l| l.kind == ImpactKind::Definition).then_some(impact)
+        })?;
+        info!("🔁 Rename impact: {} touches {} site(s) in {} file(s)", impact.name, impact.locations.len(), impact.files().len());
+
+        let sites = impact
+            .locations
+            .into_iter()
+            .map(|location| ImpactSite {
+                kind: location.kind.as_str().to_string(),
+                file_path: location.file_path,
+                detail: location.symbol.or(location.source),
+                lines: location.lines,
+            })
+            .collect();
+        Some(RefactorImpact { name: impact.name, sites })
+    }
+
+    /// Files in the context that CODEOWNERS assigns to someone other than the
+    /// user: the teams and handles in MIOW_OWNER (comma-separated), or the git
+    /// user's email and name
+    fn find_owned_elsewhere(&self, context: &ContextData, project_root: &std::path::Path) -> Vec<OwnedFile> {
+        let normalize = |owner: &str| owner.trim().trim_start_matches('@').to_lowercase();
+        let mine: HashSet<String> = match std::env::var("MIOW_OWNER") {
+            Ok(owners) if !owners.trim().is_empty() => owners.split(',').map(normalize).collect(),
+            _ => miow_core::git::user_identity(project_root).iter().map(|o| normalize(o)).collect(),
+        };
+
+        let edited = context.edit_target.iter().flat_map(|t| std::iter::once(&t.symbol).chain(&t.dependents));
+        let mut seen = HashSet::new();
+        let mut owned = Vec::new();
+        for symbol in context.relevant_symbols.iter().chain(&context.similar_symbols).chain(edited) {
+            if symbol.kind == "plan" || !seen.insert(symbol.file_path.as_str()) {
+                continue;
+            }
+            let owners: Vec<String> = self
+                .graph
+                .owners_of(&symbol.file_path)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|o| o.source == "codeowners")
+                .map(|o| o.owner)
+                .collect();
+            if owners.is_empty() || owners.iter().any(|o| mine.contains(&normalize(o))) {
+                continue;
+            }
+            owned.push(OwnedFile { file_path: symbol.file_path.clone(), owners });
+        }
+        if !owned.is_empty() {
+            info!("👥 {} file(s) in the context are owned by other teams", owned.len());
+        }
+        owned
+    }
+
+    /// Cataloged components to list by their props: those in the context, and
+    /// for UI tasks the project's most used others. Components the task names
+    /// are left out so their code is shown instead.
+    fn find_component_catalog(&self, user_prompt: &str, context: &ContextData) -> Vec<ComponentInfo> {
+        let catalog = match self.graph.component_catalog() {
+            Ok(catalog) => catalog,
+            Err(e) => {
+                warn!("Failed to read the component catalog: {}", e);
+                return Vec::new();
+            }
+        };
+        let named: HashSet<String> = edit_target_candidates(user_prompt).into_iter().collect();
+        let in_context = |name: &str, file_path: &str| {
+            context.relevant_symbols.iter().chain(&context.similar_symbols).any(|s| s.name == name && s.file_path == file_path)
+        };
+        let ui_task = matches!(self.analyzer.analyze_prompt(user_prompt).intent, Intent::CreateComponent | Intent::CreatePage);
+
+        let (mut selected, others): (Vec<_>, Vec<_>) = catalog
+            .into_iter()
+            .filter(|c| !named.contains(&c.name))
+            .partition(|c| in_context(&c.name, &c.file_path));
+        if ui_task || !selected.is_empty() {
+            let room = MAX_CATALOG_COMPONENTS.saturating_sub(selected.len());
+            selected.extend(others.into_iter().take(room));
+        }
+
+        selected
+            .into_iter()
+            .map(|entry| ComponentInfo {
+                name: entry.name,
+                file_path: entry.file_path,
+                start_line: entry.start_line,
+                props: entry
+                    .props
+                    .into_iter()
+                    .map(|p| ComponentProp {
+                        name: p.name,
+                        type_annotation: p.type_annotation,
+                        optional: p.optional,
+                        default_value: p.default_value,
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn templates_for(&self, project_root: &std::path::Path) -> PromptTemplates {
+        self.templates.clone().unwrap_or_else(|| PromptTemplates::for_project(project_root))
+    }
+
+    /// Open the run set with `with_run`, if any
+    fn open_run(&self, user_prompt: &str, project_root: &std::path::Path) -> Result<Option<RunRecorder>> {
+        let Some((store, run_id)) = &self.run else { return Ok(None) };
+        let run = RunRecorder::open(store.clone(), run_id, user_prompt, project_root)?;
+        let saved = run.state();
+        if saved.task != user_prompt {
+            warn!("Run {} was started for '{}'; continuing that task", run_id, saved.task);
+        }
+        Ok(Some(run))
+    }
+
+    /// Save the prompt to the run with what it was built from
+    fn record_prompt(&self, run: &RunRecorder, context: &ContextData, config: &miow_prompt::MetaPromptConfig, prompt: &str) {
+        let model = self.llm.as_ref().map(|llm| llm.model_name().to_string());
+        let counter = model.as_deref().map(miow_prompt::TokenCounter::for_model).unwrap_or_default();
+
+        let symbol_ids = context.relevant_symbols.iter().chain(&context.similar_symbols).filter(|s| s.kind != "plan").map(|s| {
+            miow_prompt::Citation::for_symbol(s).map(|c| c.anchor).unwrap_or_else(|| s.file_path.clone())
+        });
+        let mut context_ids: Vec<String> = symbol_ids
+            .chain(context.types.iter().map(|t| format!("type:{}", t.name)))
+            .chain(context.schemas.iter().map(|s| format!("schema:{}", s.name)))
+            .chain(context.tests.iter().map(|t| format!("test:{}#{}", t.file_path, t.name)))
+            .chain(context.external_references.iter().map(|r| r.source.clone()))
+            .collect();
+        let mut seen = HashSet::new();
+        context_ids.retain(|id| seen.insert(id.clone()));
+
+        // Agent snippets are parts of files, not symbols
+        let mut items: Vec<PromptItem> = Vec::new();
+        let symbols = context.edit_target.iter().map(|t| &t.symbol).chain(&context.relevant_symbols).chain(&context.similar_symbols);
+        for symbol in symbols.filter(|s| s.kind != "plan") {
+            let item = PromptItem {
+                file_path: miow_prompt::Citation::for_symbol(symbol).map(|c| c.path).unwrap_or_else(|| symbol.file_path.clone()),
+                name: Some(symbol.name.clone()).filter(|_| symbol.kind != "snippet"),
+            };
+            if !items.contains(&item) {
+                items.push(item);
+            }
+        }
+
+        let record = PromptRecord {
+            items,
+            context_ids,
+            config: serde_json::to_value(config).unwrap_or_default(),
+            model,
+            context_tokens: counter.count_context(context),
+            prompt_tokens: counter.count(prompt),
+        };
+        run.update(|s| {
+            s.prompt = Some(prompt.to_string());
+            s.record = Some(record);
+        });
+    }
+
+    /// Record why each context item was included; read the report with `explanation`
+    pub fn with_explain(mut self) -> Self {
+        self.explain = Some(ExplainLog::default());
+        self
+    }
+
+    /// For each item in the last generated prompt: what found it, the query and
+    /// vector score, keyword hits and how central it is in the graph
+    pub fn explanation(&self) -> Option<Vec<ItemExplanation>> {
+        self.explain.as_ref().map(ExplainLog::report)
+    }
+
+    /// The report's log, still readable after the orchestrator moves into a task
+    pub fn explain_log(&self) -> Option<ExplainLog> {
+        self.explain.clone()
+    }
+
+    /// Reuse contexts compiled by earlier runs with this cache; share one cache
+    /// between orchestrators to share them across requests
+    pub fn with_context_cache(mut self, cache: ContextCache) -> Self {
+        self.context_cache = Some(cache);
+        self
+    }
+
+    /// Where a new run's context would be cached: the task, the project and the
+    /// settings that change what gets gathered, and the index as it is now
+    fn context_key(&self, user_prompt: &str, project_root: &std::path::Path, workflow: &str) -> Option<ContextKey> {
+        self.context_cache.as_ref()?;
+        let generation = match self.graph.generation() {
+            Ok(generation) => generation,
+            Err(e) => {
+                warn!("⚠️  Index generation unavailable, not caching context: {}", e);
+                return None;
+            }
+        };
+        let scope = format!("{}|{}|{:?}|{:?}", project_root.display(), workflow, self.search_cache.scope(), self.max_files);
+        Some(ContextKey::new(user_prompt, scope, generation))
+    }
+
+    /// The context of an earlier task close enough to this one, noting where its items came from
+    fn cached_context(&self, key: &ContextKey) -> Option<CachedContext> {
+        let hit = self.context_cache.as_ref()?.lookup(key)?;
+        info!(
+            "♻️  Reusing the context gathered for \"{}\" (similarity {:.2}, index unchanged)",
+            hit.cached.task, hit.similarity
+        );
+        let context = &hit.cached.context;
+        for symbol in context.relevant_symbols.iter().chain(&context.similar_symbols) {
+            self.note(&symbol.name, &symbol.file_path, || Provenance::new("context cache").query(&hit.cached.task));
+        }
+        Some(hit.cached)
+    }
+
+    fn cache_context(&self, key: ContextKey, cached: CachedContext) {
+        if let Some(cache) = &self.context_cache {
+            cache.store(key, cached);
+        }
+    }
+
+    /// Note what found an item, if explaining
+    fn note(&self, name: &str, file_path: &str, provenance: impl FnOnce() -> Provenance) {
+        if let Some(log) = &self.explain {
+            log.note(name, file_path, provenance());
+        }
+    }
+
+    /// Give the autonomous agent an extra tool alongside the built-in ones
+    pub fn with_agent_tool(mut self, tool: Arc<dyn Tool>) -> Self {
+        self.agent_tools.push(tool);
+        self
+    }
+
+    /// Limits on the autonomous agent's iterations, tool calls, tokens and time
+    pub fn with_agent_budget(mut self, budget: AgentBudget) -> Self {
+        self.agent_budget = budget;
         self
     }
 
@@ -54,45 +602,46 @@ impl MiowOrchestrator {
         self
     }
 
-    /// Generate a context-aware prompt from a user request with advanced LLM-powered analysis
-    pub async fn generate_context_prompt(&self, user_prompt: &str) -> Result<String> {
-        info!("Generating context-aware prompt for: {}", user_prompt);
-
-        let analyzed = self.analyzer.analyze_prompt(user_prompt);
-
-        // Step 1: Analyze prompt with LLM if available, otherwise use basic analyzer
-        let intent_analysis = if let Some(ref llm) = self.llm {
-            // Create a new InteractiveLLM with the LLM provider
-            // Note: We need to use the LLM directly since InteractiveLLM takes ownership
-            let messages = vec![
-                Message {
-                    role: Role::System,
-                    content: r#"You are an expert code analyst. Analyze the user's request and identify the main intent.
-Respond with just the intent in one word or short phrase (e.g., "create_component", "create_page", "add_feature", "fix_bug")."#.to_string(),
-                },
-                Message {
-                    role: Role::User,
-                    content: user_prompt.to_string(),
-                },
-            ];
+    /// The task's intent from the taxonomy rules, or when they match nothing or
+    /// more than one intent, the LLM's pick from the taxonomy
+    async fn classify_intent(&self, user_prompt: &str) -> Intent {
+        let classifier = self.analyzer.classifier();
+        let classification = classifier.classify(user_prompt);
+        let Some(llm) = self.llm.as_ref().filter(|_| !classification.is_confident()) else {
+            return classification.intent;
+        };
 
-            match llm.generate_with_context(messages).await {
-                Ok(response) => {
-                    let intent = response.content.trim().to_string();
+        let messages = vec![
+            Message { role: Role::System, content: classifier.llm_instructions() },
+            Message { role: Role::User, content: user_prompt.to_string() },
+        ];
+        match llm.generate_with_context(messages).await {
+            Ok(response) => match classifier.resolve(&response.content) {
+                Some(intent) => {
                     info!("LLM intent analysis: {}", intent);
                     intent
                 }
-                Err(e) => {
-                    warn!(
-                        "LLM intent analysis failed: {}, falling back to basic analyzer",
-                        e
-                    );
-                    format!("{:?}", analyzed.intent)
+                None => {
+                    warn!("LLM answered an intent outside the taxonomy ({}), using {}", response.content.trim(), classification.intent);
+                    classification.intent
                 }
+            },
+            Err(e) => {
+                warn!("LLM intent analysis failed: {}, falling back to the taxonomy rules", e);
+                classification.intent
             }
-        } else {
-            format!("{:?}", analyzed.intent)
-        };
+        }
+    }
+
+    /// Generate a context-aware prompt from a user request with advanced LLM-powered analysis
+    pub async fn generate_context_prompt(&self, user_prompt: &str) -> Result<String> {
+        info!("Generating context-aware prompt for: {}", user_prompt);
+        self.search_cache.clear();
+
+        let analyzed = self.analyzer.analyze_prompt(user_prompt);
+
+        // Step 1: Classify the intent; the LLM decides when the taxonomy rules can't
+        let intent = self.classify_intent(user_prompt).await;
 
         // Step 2: Generate search queries using LLM
         let search_queries = if let Some(ref llm) = self.llm {
@@ -108,7 +657,7 @@ Generate queries that would find:
 - Design tokens
 
 Respond with a JSON array of strings."#,
-                intent_analysis, user_prompt
+                intent, user_prompt
             );
 
             match llm.generate(&system_prompt).await {
@@ -185,7 +734,7 @@ Respond with a JSON array of strings."#,
 
         // Step 3: Gather comprehensive context using multiple search strategies
         let gathered_context = self
-            .gather_comprehensive_context(user_prompt, &search_queries, &intent_analysis, None)
+            .gather_comprehensive_context(user_prompt, &search_queries, intent, None, None)
             .await?;
 
         // Step 4: Convert gathered context to prompt context format
@@ -196,7 +745,7 @@ Respond with a JSON array of strings."#,
         // Step 5: Generate multi-step implementation plan using LLM
         let implementation_plan = if let Some(llm) = &self.llm {
             match self
-                .generate_implementation_plan(llm.as_ref(), user_prompt, &master_context, &intent_analysis)
+                .generate_implementation_plan(llm.as_ref(), user_prompt, &master_context, intent)
                 .await
             {
                 Ok(plan) => plan,
@@ -205,17 +754,17 @@ Respond with a JSON array of strings."#,
                         "LLM implementation plan failed ({}). Falling back to basic plan.",
                         err
                     );
-                    self.generate_basic_implementation_plan(&master_context, &intent_analysis)
+                    self.generate_basic_implementation_plan(&master_context, intent)
                 }
             }
         } else {
-            self.generate_basic_implementation_plan(&master_context, &intent_analysis)
+            self.generate_basic_implementation_plan(&master_context, intent)
         };
 
         // Step 6: Generate the final comprehensive prompt
         let request = PromptRequest {
             original_prompt: user_prompt.to_string(),
-            intent: intent_analysis,
+            intent,
             context: master_context,
             implementation_plan: Some(implementation_plan),
         };
@@ -226,24 +775,48 @@ Respond with a JSON array of strings."#,
 
     /// Enhanced context-aware prompt generation with Universal Knowledge Graph
     /// This method uses ALL new components: Project Signature, Question Loop, Style Analysis, Meta-Prompt
+    #[tracing::instrument(name = "generate", skip_all, fields(workflow = "enhanced"))]
     pub async fn generate_enhanced_prompt(
         &self,
         user_prompt: &str,
         project_root: &std::path::Path,
     ) -> Result<String> {
-        info!("🚀 Starting Universal Knowledge Graph workflow (agentic router enabled)...");
+        info!("🚀 Starting Universal Knowledge Graph workflow: {}", self.pipeline.describe());
+        self.search_cache.clear();
+        if let Some(log) = &self.explain {
+            log.clear();
+        }
+        let run = self.open_run(user_prompt, project_root)?;
+        let saved_plan = run.as_ref().and_then(|run| run.state().search_plan);
 
         // PHASE 1: Project Signature Detection (with caching)
         info!("📋 Phase 1: Detecting project signature...");
-        let project_signature = self.load_or_detect_signature(project_root)?;
+        let project_signature = Stage::Signature.span().in_scope(|| self.load_or_detect_signature(project_root))?;
         info!("✅ Detected: {}", project_signature.to_description());
 
+        // A task close to one gathered earlier this session, on an unchanged index, reuses its context
+        let cache_key = if saved_plan.is_none() { self.context_key(user_prompt, project_root, "enhanced") } else { None };
+        if let Some(cached) = cache_key.as_ref().and_then(|key| self.cached_context(key)) {
+            return self.finish_enhanced_prompt(user_prompt, project_root, cached.context, &cached.search_queries, &cached.project, run.as_ref());
+        }
+
         // PHASE 1b: LLM-driven Router Planning (Router Agent)
-        let router_plan: Option<SearchPlan> = if let Some(ref llm) = self.llm {
+        self.check_cancelled()?;
+        let router_plan: Option<SearchPlan> = if saved_plan.is_some() {
+            info!("⏯️  Reusing the router plan from the saved run");
+            saved_plan
+        } else if !self.pipeline.runs(Stage::Router) {
+            info!("⏭️  Router disabled in miow.toml, searching with analyzer keywords");
+            None
+        } else if let Some(ref llm) = self.llm {
             info!("🧠 Router Agent: planning search strategy with LLM...");
             let router = GeminiRouterAgent::new(llm.clone());
-            match router.plan(user_prompt, &project_signature).await {
-                Ok(plan) => {
+            match router.plan(user_prompt, &project_signature).instrument(Stage::Router.span()).await {
+                Ok(mut plan) => {
+                    let duplicates = plan.dedup_queries();
+                    if duplicates > 0 {
+                        info!("♻️  Dropped {} duplicate router queries", duplicates);
+                    }
                     info!(
                         "✅ Router plan: intent='{}', {} global queries, {} worker plans, execution order: {:?}",
                         plan.global_intent,
@@ -251,6 +824,9 @@ Respond with a JSON array of strings."#,
                         plan.workers.len(),
                         plan.execution_plan
                     );
+                    if let Some(run) = &run {
+                        run.update(|s| s.search_plan = Some(plan.clone()));
+                    }
                     Some(plan)
                 }
                 Err(e) => {
@@ -271,10 +847,16 @@ Respond with a JSON array of strings."#,
         };
 
         // PHASE 2a: Execute Workers Sequentially (if router plan exists)
+        self.check_cancelled()?;
         let worker_results: Vec<miow_agent::WorkerResult> = if let Some(ref plan) = &router_plan {
-            if let Some(ref llm) = self.llm {
+            if !self.pipeline.runs(Stage::Workers) {
+                info!("⏭️  Workers disabled in miow.toml");
+                Vec::new()
+            } else if let Some(ref llm) = self.llm {
                 info!("🔄 Phase 2a: Executing workers sequentially...");
-                self.execute_workers_sequentially(llm.clone(), plan, user_prompt, &project_signature).await
+                self.execute_workers_sequentially(llm.clone(), plan, user_prompt, &project_signature)
+                    .instrument(Stage::Workers.span())
+                    .await
             } else {
                 Vec::new()
             }
@@ -287,8 +869,17 @@ Respond with a JSON array of strings."#,
         }
 
         // PHASE 2: Generate Critical Questions (with detailed logging)
-        info!("❓ Phase 3: Generating language-specific critical questions...");
-        let critical_questions = if let Some(ref llm) = self.llm {
+        self.check_cancelled()?;
+        let run_questions = self.pipeline.runs(Stage::Questions);
+        let questions_span = Stage::Questions.span();
+        if run_questions {
+            info!("❓ Phase 3: Generating language-specific critical questions...");
+        } else {
+            info!("⏭️  Question loop disabled in miow.toml");
+        }
+        let mut critical_questions = if !run_questions {
+            Vec::new()
+        } else if let Some(ref llm) = self.llm {
             info!("💬 [LLM] Calling generate_critical_questions for language: {}, framework: {:?}",
                   project_language, framework);
             let start = std::time::Instant::now();
@@ -299,7 +890,7 @@ Respond with a JSON array of strings."#,
                 user_prompt,
                 &project_language,
                 framework.as_deref(),
-            ).await {
+            ).instrument(questions_span.clone()).await {
                 Ok(questions) => {
                     let duration = start.elapsed();
                     info!("✅ [LLM] Generated {} questions in {:?}", questions.len(), duration);
@@ -313,54 +904,28 @@ Respond with a JSON array of strings."#,
                     let duration = start.elapsed();
                     warn!("❌ [LLM] Failed to generate questions after {:?}: {}, using template", duration, e);
                     // Fallback: Use template questions from project signature
-                    project_signature
-                        .get_question_templates()
-                        .into_iter()
-                        .enumerate()
-                        .map(|(i, q)| miow_llm::CriticalQuestion {
-                            question: q.clone(),
-                            search_query: q.split_whitespace().last().unwrap_or("").to_string(),
-                            expected_type: if q.contains("component") {
-                                "component".to_string()
-                            } else if q.contains("type") {
-                                "type".to_string()
-                            } else {
-                                "function".to_string()
-                            },
-                            priority: if i == 0 {
-                                miow_llm::Priority::Critical
-                            } else {
-                                miow_llm::Priority::High
-                            },
-                        })
-                        .collect()
+                    template_questions(&project_signature)
                 }
             }
         } else {
             info!("ℹ️  No LLM available, using template questions");
-            // No LLM: use template questions
-            project_signature
-                .get_question_templates()
-                .into_iter()
-                .enumerate()
-                .map(|(i, q)| miow_llm::CriticalQuestion {
-                    question: q.clone(),
-                    search_query: q.split_whitespace().last().unwrap_or("").to_string(),
-                    expected_type: "unknown".to_string(),
-                    priority: if i == 0 {
-                        miow_llm::Priority::Critical
-                    } else {
-                        miow_llm::Priority::Medium
-                    },
-                })
-                .collect()
+            template_questions(&project_signature)
         };
 
-        info!("✅ Generated {} critical questions", critical_questions.len());
+        if let Some(max) = self.pipeline.max_questions {
+            critical_questions.truncate(max);
+        }
+        if run_questions {
+            info!("✅ Generated {} critical questions", critical_questions.len());
+        }
 
         // PHASE 3: Execute Question Loop with Rollback (with detailed logging)
-        info!("🔄 Phase 3: Executing question loop with search-verify-retry...");
-        let question_answers = if let Some(ref llm) = self.llm {
+        self.check_cancelled()?;
+        let analyzed = self.analyzer.analyze_prompt(user_prompt);
+        let question_answers = if !run_questions {
+            Vec::new()
+        } else if let Some(ref llm) = self.llm {
+            info!("🔄 Phase 3: Executing question loop with search-verify-retry...");
             info!("💬 [QUESTION_LOOP] Starting execution of {} questions", critical_questions.len());
             let question_loop = miow_llm::QuestionLoop::new(
                 llm.clone(),
@@ -369,7 +934,7 @@ Respond with a JSON array of strings."#,
             );
 
             let start = std::time::Instant::now();
-            match question_loop.execute_questions(critical_questions.clone()).await {
+            match question_loop.execute_questions(critical_questions.clone()).instrument(questions_span.clone()).await {
                 Ok(answers) => {
                     let duration = start.elapsed();
                     info!("✅ [QUESTION_LOOP] Completed in {:?} with {} answers", duration, answers.len());
@@ -389,25 +954,29 @@ Respond with a JSON array of strings."#,
                 }
             }
         } else {
-            warn!("ℹ️  No LLM available for question loop, skipping");
-            Vec::new()
+            info!("ℹ️  No LLM available, answering questions from search results");
+            self.answer_questions_by_search(&critical_questions, &analyzed.keywords)
+                .instrument(questions_span)
+                .await
         };
 
-        info!("✅ Question loop completed with {} answers", question_answers.len());
+        if run_questions {
+            info!("✅ Question loop completed with {} answers", question_answers.len());
+        }
 
         // PHASE 4: Gather Context (enhanced with router plan + worker results + question answers)
+        self.check_cancelled()?;
         info!("📚 Phase 4: Gathering comprehensive context...");
-        let analyzed = self.analyzer.analyze_prompt(user_prompt);
 
-        // Start from analyzer intent/keywords.
-        let mut intent = format!("{:?}", analyzed.intent);
+        // Start from analyzer keywords; the router's intent when it settled on one.
+        let intent = match router_plan.as_ref().map(|plan| plan.global_intent) {
+            Some(intent) if intent != Intent::Unknown => intent,
+            _ => self.classify_intent(user_prompt).await,
+        };
         let mut search_queries = analyzed.keywords.clone();
 
-        // If router produced a plan, let it drive intent + search queries.
+        // If router produced a plan, let it drive the search queries.
         if let Some(plan) = &router_plan {
-            if !plan.global_intent.trim().is_empty() {
-                intent = plan.global_intent.clone();
-            }
             let router_queries = plan.all_query_strings();
             if !router_queries.is_empty() {
                 search_queries = router_queries;
@@ -418,14 +987,19 @@ Respond with a JSON array of strings."#,
             .gather_comprehensive_context(
                 user_prompt,
                 &search_queries,
-                &intent,
+                intent,
                 router_plan.as_ref(),
+                Some(&project_signature),
             )
+            .instrument(Stage::Gather.span())
             .await?;
 
         // Merge worker results into gathered context
         for worker_result in &worker_results {
             for chunk in &worker_result.chunks {
+                self.note(&chunk.id, &chunk.file_path, || {
+                    Provenance::new("worker").worker(&worker_result.worker_id).reason(&worker_result.summary)
+                });
                 let item = miow_llm::ContextItem {
                     name: chunk.id.clone(),
                     kind: chunk.kind.clone(),
@@ -434,6 +1008,8 @@ Respond with a JSON array of strings."#,
                     relevance_score: worker_result.confidence,
                     props: vec![],
                     references: vec![],
+                    is_exported: false,
+                    documentation: None,
                 };
 
                 // Categorize based on content type
@@ -452,6 +1028,7 @@ Respond with a JSON array of strings."#,
         // Merge question answers into gathered context
         for answer in question_answers {
             for symbol in answer.symbols {
+                self.note(&symbol.name, &symbol.file_path, || Provenance::new("question loop").query(&answer.question));
                 let item = miow_llm::ContextItem {
                     name: symbol.name.clone(),
                     kind: symbol.kind.clone(),
@@ -460,6 +1037,8 @@ Respond with a JSON array of strings."#,
                     relevance_score: answer.confidence,
                     props: vec![],
                     references: vec![],
+                    is_exported: false,
+                    documentation: None,
                 };
 
                 // Add to appropriate category
@@ -476,26 +1055,33 @@ Respond with a JSON array of strings."#,
         }
 
         // Optional PHASE 4b: LLM-powered context auditing (Context Auditor Agent)
-        if let Some(ref llm) = self.llm {
+        self.check_cancelled()?;
+        if !self.pipeline.runs(Stage::Audit) {
+            info!("⏭️  Context auditor disabled in miow.toml");
+        } else if let Some(ref llm) = self.llm {
             info!("🧹 Context Auditor: LLM-driven pruning of gathered context...");
             let auditor = GeminiContextAuditor::new(llm.clone());
-            if let Err(e) = auditor.audit(user_prompt, &mut gathered_context).await {
+            if let Err(e) = auditor.audit(user_prompt, &mut gathered_context).instrument(Stage::Audit.span()).await {
                 warn!("Context auditor failed, continuing with unfiltered context: {}", e);
             }
         }
 
         // PHASE 5: Master Prompt Compilation (aggregate worker results)
+        self.check_cancelled()?;
         info!("🎯 Phase 5: Compiling master context from worker results...");
+        let compile_span = Stage::Compile.span();
         let compiled_context = self.compile_master_context(
             &worker_results,
             &gathered_context,
             user_prompt,
             &project_signature,
-        ).await;
+        ).instrument(compile_span.clone()).await;
 
         // PHASE 6: Convert to ContextData
-        let mut context_data = self
+        self.check_cancelled()?;
+        let context_data = self
             .convert_to_context_data(compiled_context, &search_queries, user_prompt)
+            .instrument(compile_span)
             .await?;
 
         info!(
@@ -506,14 +1092,40 @@ Respond with a JSON array of strings."#,
             worker_results.len()
         );
 
+        if let Some(key) = cache_key {
+            self.cache_context(key, CachedContext {
+                task: user_prompt.to_string(),
+                project: project_signature.to_description(),
+                context: context_data.clone(),
+                search_queries: search_queries.clone(),
+            });
+        }
+        self.finish_enhanced_prompt(user_prompt, project_root, context_data, &search_queries, &project_signature.to_description(), run.as_ref())
+    }
+
+    /// Trim the compiled context to the token budget and build the meta-prompt from it;
+    /// the end of the enhanced workflow
+    fn finish_enhanced_prompt(
+        &self,
+        user_prompt: &str,
+        project_root: &std::path::Path,
+        mut context_data: ContextData,
+        search_queries: &[String],
+        project_info: &str,
+        run: Option<&RunRecorder>,
+    ) -> Result<String> {
         // PHASE 6: Generate Meta-Prompt (copy-paste ready)
+        let _span = Stage::Prompt.span().entered();
         info!("📝 Phase 6: Generating meta-prompt...");
         let config = miow_prompt::MetaPromptConfig {
             include_full_code: true,
             include_style_guide: true,
             include_implementation_plan: true,
             max_examples_per_type: 5,
-            token_budget: Some(16000),
+            token_budget: Some(self.pipeline.context_tokens),
+            format: self.prompt_format,
+            diff: self.diff_prompts,
+            ..Default::default()
         };
 
         // 5. Deduplicate and Prune Context
@@ -521,58 +1133,313 @@ Respond with a JSON array of strings."#,
         miow_prompt::DeduplicationEngine::deduplicate(&mut context_data);
 
         if let Some(budget) = config.token_budget {
-            let pruner = miow_prompt::SmartPruner::new(budget);
-            pruner.prune(&mut context_data);
+            let mut pruner = miow_prompt::SmartPruner::new(budget)
+                .with_sections(miow_prompt::SectionBudgets::default());
+            if let Some(llm) = &self.llm {
+                pruner = pruner.with_counter(miow_prompt::TokenCounter::for_model(llm.model_name()));
+            }
+            let report = pruner.prune(&mut context_data);
+            if !report.dropped.is_empty() || !report.truncated.is_empty() {
+                info!("✂️ Context trimmed from {} to {} tokens", report.tokens_before, report.tokens_after);
+            }
         }
 
+        context_data.owned_elsewhere = self.find_owned_elsewhere(&context_data, project_root);
+        context_data.components = self.find_component_catalog(user_prompt, &context_data);
+        self.explain_context(&context_data, search_queries);
+
         // 6. Generate Meta-Prompt
         info!("📝 Generating meta-prompt...");
-        let project_info = project_signature.to_description(); // Define project_info here
-        let prompt = miow_prompt::MetaPromptGenerator::generate(
+        let prompt = miow_prompt::MetaPromptGenerator::generate_with_templates(
             user_prompt,
             &context_data,
-            Some(&project_info),
-            config,
+            Some(project_info),
+            config.clone(),
+            &self.templates_for(project_root),
         )?;
+        if let Some(run) = run {
+            self.record_prompt(run, &context_data, &config, &prompt);
+        }
 
         info!("✅ Universal Knowledge Graph workflow complete!");
 
         Ok(prompt)
     }
 
-    /// Generate a context-aware prompt using the Autonomous Agent Loop
-    pub async fn generate_autonomous_prompt(
-        &self,
-        project_root: &str,
-        user_prompt: &str,
-        event_tx: Option<tokio::sync::mpsc::Sender<miow_agent::autonomous::AgentEvent>>,
-    ) -> Result<String> {
-        info!("🤖 Starting Autonomous Context Generation for: {}", project_root);
-
-        // 1. Detect Project Signature (LLM-driven)
-        let signature = self.detect_signature_with_llm(std::path::Path::new(project_root)).await?;
-        info!("📊 Detected Project Signature: {:?}", signature);
+    /// Gather and prune context for a task the way `ask` would, through the
+    /// workflow `generate_autonomous_prompt` picks, without any LLM calls, and
+    /// report what the prompt would contain. Under the agent workflow the tool
+    /// calls the agent's LLM would choose are stood in for by its `search` tool,
+    /// run once per task keyword. The LLM, when attached, only picks the token counter.
+    pub async fn dry_run(&self, user_prompt: &str, project_root: &std::path::Path) -> Result<DryRunReport> {
+        self.search_cache.clear();
+        if let Some(log) = &self.explain {
+            log.clear();
+        }
+        let project_signature = self.load_or_detect_signature(project_root)?;
+        let analyzed = self.analyzer.analyze_prompt(user_prompt);
+        let intent = analyzed.intent;
+        let search_queries = analyzed.keywords.clone();
+
+        // Without an LLM, `ask` falls back to the enhanced workflow
+        let workflow = if self.llm.is_none() { Workflow::Enhanced } else { self.pipeline.workflow };
+        let mut context_data = match workflow {
+            Workflow::Enhanced => {
+                let gathered = self.gather_comprehensive_context(user_prompt, &search_queries, intent, None, Some(&project_signature)).await?;
+                let mut context_data = self.convert_to_context_data(gathered, &search_queries, user_prompt).await?;
+                miow_prompt::DeduplicationEngine::deduplicate(&mut context_data);
+                context_data
+            }
+            Workflow::Agent => {
+                let gathered = self.search_like_agent(&search_queries).await;
+                self.agent_context_data(user_prompt, project_root, gathered).await
+            }
+        };
+
+        let counter = self.token_counter();
+        let pruner = miow_prompt::SmartPruner::new(self.pipeline.context_tokens)
+            .with_sections(miow_prompt::SectionBudgets::default())
+            .with_counter(counter);
+        let pruning = pruner.prune(&mut context_data);
+        self.explain_context(&context_data, &search_queries);
+
+        let mut items = pruner.inventory(&context_data);
+        if let Some(target) = &context_data.edit_target {
+            items.insert(0, miow_prompt::ContextEntry {
+                section: miow_prompt::PromptSection::Symbols,
+                category: "edit target",
+                key: format!("{} ({})", target.symbol.name, target.symbol.file_path),
+                file_path: Some(target.symbol.file_path.clone()),
+                lines: Some((target.symbol.start_line, target.symbol.end_line)),
+                tokens: counter.count(&target.symbol.content),
+            });
+        }
+        let mut seen = HashSet::new();
+        let files = items.iter().filter_map(|item| item.file_path.clone()).filter(|path| seen.insert(path.clone())).collect();
+
+        Ok(DryRunReport {
+            project: project_signature.to_description(),
+            workflow,
+            intent,
+            search_queries,
+            context_tokens: items.iter().map(|item| item.tokens).sum(),
+            items,
+            files,
+            token_budget: self.pipeline.context_tokens,
+            pruning,
+            explanation: self.explanation(),
+        })
+    }
+
+    /// What the agent's `search` tool finds for each keyword, in place of the
+    /// tool calls its LLM would make
+    async fn search_like_agent(&self, keywords: &[String]) -> Vec<miow_agent::autonomous::VerifiedInfo> {
+        let search = miow_agent::autonomous::SearchTool::new(self.graph.clone(), self.vector_store.clone())
+            .with_cache(self.search_cache.clone());
+        let mut gathered = Vec::new();
+        for keyword in keywords {
+            let args = serde_json::json!({ "query": keyword });
+            match search.execute(args.clone()).await {
+                Ok(output) if !output.trim().is_empty() && !output.starts_with("No ") => {
+                    gathered.push(miow_agent::autonomous::VerifiedInfo {
+                        content: output,
+                        source: format!("Tool: search Args: {}", args),
+                        relevance: format!("Dry run search for '{}'", keyword),
+                        external: false,
+                    });
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Dry run search for '{}' failed: {}", keyword, e),
+            }
+        }
+        gathered
+    }
+
+    /// Generate a context-aware prompt using the Autonomous Agent Loop. A run
+    /// started with `RunStore::follow_up` continues its session: the agent starts
+    /// from the context gathered so far and the plan builds on the previous one.
+    #[tracing::instrument(name = "generate", skip_all, fields(workflow = "autonomous"))]
+    pub async fn generate_autonomous_prompt(
+        &self,
+        project_root: &str,
+        user_prompt: &str,
+        event_tx: Option<tokio::sync::mpsc::Sender<miow_agent::autonomous::AgentEvent>>,
+    ) -> Result<String> {
+        info!("🤖 Starting Autonomous Context Generation for: {}", project_root);
+        self.search_cache.clear();
+        if let Some(log) = &self.explain {
+            log.clear();
+        }
+
+        let run = self.open_run(user_prompt, std::path::Path::new(project_root))?;
+        let saved = run.as_ref().map(RunRecorder::state);
+        if let Some(prompt) = saved.as_ref().and_then(|s| s.prompt.clone()) {
+            info!("✅ Run already complete; returning its prompt");
+            return Ok(prompt);
+        }
+        let user_prompt = saved.as_ref().map(|s| s.task.as_str()).unwrap_or(user_prompt);
+
+        // The agent can't run without an LLM; the deterministic pipeline builds the prompt instead
+        if self.llm.is_none() {
+            info!("ℹ️  No LLM available, using the deterministic pipeline");
+            send_phase(&event_tx, "Building the prompt without an LLM").await;
+            return self.generate_enhanced_prompt(user_prompt, std::path::Path::new(project_root)).await;
+        }
+        if self.pipeline.workflow == Workflow::Enhanced {
+            info!("🧩 miow.toml selects the enhanced workflow");
+            send_phase(&event_tx, "Building the prompt with the enhanced workflow").await;
+            return self.generate_enhanced_prompt(user_prompt, std::path::Path::new(project_root)).await;
+        }
+
+        // A task close to one gathered earlier this session, on an unchanged index, reuses
+        // its context and plan. Resumed runs and follow-ups continue their own.
+        let fresh = saved.as_ref().is_none_or(|s| s.agent.is_none() && s.implementation_plan.is_none() && s.previous_plan.is_none());
+        let cache_key = if fresh { self.context_key(user_prompt, std::path::Path::new(project_root), "agent") } else { None };
+        if let Some(cached) = cache_key.as_ref().and_then(|key| self.cached_context(key)) {
+            self.explain_context(&cached.context, &[]);
+            return self.finish_autonomous_prompt(user_prompt, project_root, &cached.context, &cached.project, &[], run.as_ref());
+        }
+
+        // 1. Detect Project Signature (LLM-driven)
+        let signature = match saved.as_ref().and_then(|s| s.signature.clone()) {
+            Some(signature) => {
+                info!("⏯️  Reusing the project signature from the saved run");
+                signature
+            }
+            None => {
+                send_phase(&event_tx, "Detecting the project's stack").await;
+                let signature = self.detect_signature_with_llm(std::path::Path::new(project_root)).await?;
+                if let Some(run) = &run {
+                    run.update(|s| s.signature = Some(signature.clone()));
+                }
+                signature
+            }
+        };
+        info!("📊 Detected Project Signature: {:?}", signature);
 
         // 2. Initialize Autonomous Agent
         let llm = self.llm.clone().ok_or_else(|| anyhow::anyhow!("LLM required for autonomous mode"))?;
-        let agent = AutonomousAgent::new(
+        let mut agent = AutonomousAgent::new(
             llm,
             self.graph.clone(),
             self.vector_store.clone(),
-        );
+        )
+        .with_project_root(std::path::PathBuf::from(project_root))
+        .with_command_policy(self.command_policy.clone())
+        .with_budget(self.agent_budget.clone())
+        .with_cancellation(self.cancel.clone())
+        .with_search_cache(self.search_cache.clone());
+        if let Some(approver) = &self.approver {
+            agent = agent.with_approver(approver.clone());
+        }
+        if let Some(answerer) = &self.answerer {
+            agent = agent.with_answerer(answerer.clone());
+        }
+        if let Some(lsp) = self.lsp.clone().or_else(|| LspConfig::detect(std::path::Path::new(project_root))) {
+            agent = agent.with_lsp(lsp);
+        }
+        #[cfg(feature = "web-search")]
+        if let Some(tool) = miow_agent::WebSearchTool::from_env() {
+            agent = agent.with_tool(Arc::new(tool));
+        }
+        for tool in &self.agent_tools {
+            agent = agent.with_tool(tool.clone());
+        }
 
-        // 3. Run Agent Loop (Gather Context)
-        let agent_context = agent.run(user_prompt, event_tx).await?;
-        info!("✅ Agent finished gathering context. Items: {}", agent_context.gathered_info.len());
+        // 3. Run Agent Loop (Gather Context), continuing a saved run from its last step
+        let agent_context = match saved.as_ref().filter(|s| s.agent_done).and_then(|s| s.agent.clone()) {
+            Some(finished) => {
+                info!("⏯️  Reusing the agent's context from the saved run");
+                finished
+            }
+            None => {
+                if let Some(run) = &run {
+                    let run = run.clone();
+                    agent = agent.with_checkpoint(move |context| run.update(|s| s.agent = Some(context.clone())));
+                }
+                send_phase(&event_tx, "Gathering context").await;
+                let agent_context = match saved.as_ref().and_then(|s| s.agent.clone()) {
+                    Some(partial) => agent.resume(partial, event_tx.clone()).await?,
+                    None => agent.run(user_prompt, event_tx.clone()).await?,
+                };
+                if let Some(run) = &run {
+                    run.update(|s| {
+                        s.agent = Some(agent_context.clone());
+                        s.agent_done = true;
+                    });
+                }
+                agent_context
+            }
+        };
+        match agent_context.budget_exhausted {
+            Some(limit) => warn!(
+                "⚠️  Agent hit its {}; building the prompt from partial context ({} items)",
+                limit,
+                agent_context.gathered_info.len()
+            ),
+            None => info!("✅ Agent finished gathering context. Items: {}", agent_context.gathered_info.len()),
+        }
 
         // 4. Generate Implementation Plan (LLM-driven)
-        let plan = self.generate_implementation_plan_with_llm(
-            user_prompt,
-            &agent_context,
-            &signature.to_description()
-        ).await?;
+        self.check_cancelled()?;
+        let plan = match saved.as_ref().and_then(|s| s.implementation_plan.clone()) {
+            Some(plan) => plan,
+            None => {
+                send_phase(&event_tx, "Writing the implementation plan").await;
+                let plan = self.generate_implementation_plan_with_llm(
+                    user_prompt,
+                    &agent_context,
+                    &signature.to_description(),
+                    saved.as_ref().and_then(|s| s.previous_plan.as_deref()),
+                    event_tx.as_ref(),
+                ).await?;
+                if let Some(run) = &run {
+                    run.update(|s| s.implementation_plan = Some(plan.clone()));
+                }
+                plan
+            }
+        };
 
         // 5. Prepare Context Data for Meta-Prompt
+        self.check_cancelled()?;
+        send_phase(&event_tx, "Compiling the prompt").await;
+        let mut context_data = self.agent_context_data(user_prompt, std::path::Path::new(project_root), agent_context.gathered_info).await;
+
+        // Add the Plan as a special context item
+        context_data.relevant_symbols.push(SymbolInfo {
+            name: "ImplementationPlan".to_string(),
+            kind: "plan".to_string(),
+            file_path: "implementation_plan.md".to_string(),
+            content: plan,
+            start_line: 0,
+            end_line: 0,
+            props: Vec::new(),
+            references: Vec::new(),
+            is_exported: false,
+            documentation: None,
+            language: None,
+        });
+
+        // A context cut short by the budget shouldn't stand in for later tasks
+        if let Some(key) = cache_key.filter(|_| agent_context.budget_exhausted.is_none()) {
+            self.cache_context(key, CachedContext {
+                task: user_prompt.to_string(),
+                project: signature.to_description(),
+                context: context_data.clone(),
+                search_queries: Vec::new(),
+            });
+        }
+        self.finish_autonomous_prompt(user_prompt, project_root, &context_data, &signature.to_description(), &agent_context.earlier_tasks, run.as_ref())
+    }
+
+    /// The context the autonomous workflow builds its prompt from: what the agent
+    /// gathered plus the routes, env vars, linked indexes and edit target found for the task
+    async fn agent_context_data(
+        &self,
+        user_prompt: &str,
+        project_root: &std::path::Path,
+        gathered: Vec<miow_agent::autonomous::VerifiedInfo>,
+    ) -> ContextData {
         let mut context_data = ContextData {
             relevant_symbols: Vec::new(),
             similar_symbols: Vec::new(),
@@ -580,11 +1447,29 @@ Respond with a JSON array of strings."#,
             design_tokens: Vec::new(),
             constants: Vec::new(),
             schemas: Vec::new(),
+            routes: self.collect_route_info(user_prompt, &[], 30),
+            env_vars: self.collect_env_var_info(user_prompt, &[], 40),
+            tests: Vec::new(),
+            external_references: Vec::new(),
             common_imports: Vec::new(),
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
         };
 
+        self.explain_agent_context(&gathered, user_prompt).await;
+
         // Add gathered info
-        for info in agent_context.gathered_info {
+        for info in gathered {
+            if info.external {
+                context_data.external_references.push(miow_prompt::ExternalReference {
+                    source: info.source,
+                    content: info.content,
+                });
+                continue;
+            }
             context_data.relevant_symbols.push(SymbolInfo {
                 name: "ContextItem".to_string(),
                 kind: "snippet".to_string(),
@@ -594,28 +1479,78 @@ Respond with a JSON array of strings."#,
                 end_line: 0,
                 props: Vec::new(),
                 references: Vec::new(),
+                is_exported: false,
+                documentation: None,
+                language: None,
             });
         }
 
-        // Add the Plan as a special context item
-        context_data.relevant_symbols.push(SymbolInfo {
-            name: "ImplementationPlan".to_string(),
-            kind: "plan".to_string(),
-            file_path: "implementation_plan.md".to_string(),
-            content: plan,
-            start_line: 0,
-            end_line: 0,
-            props: Vec::new(),
-            references: Vec::new(),
-        });
+        // The agent's tools only see this repository; linked ones are searched for the task's keywords
+        if !self.linked_indexes.is_empty() {
+            let analyzed = self.analyzer.analyze_prompt(user_prompt);
+            let mut seen = HashSet::new();
+            for keyword in &analyzed.keywords {
+                for (name, graph) in &self.linked_indexes {
+                    for item in self.search_linked_index(name, graph, keyword, analyzed.intent).into_iter().take(MAX_LINKED_AGENT_ITEMS) {
+                        if seen.insert((item.file_path.clone(), item.name.clone())) {
+                            self.note(&item.name, &item.file_path, || Provenance::new("linked index").query(keyword).reason(format!("found in {}", name)));
+                            context_data.relevant_symbols.push(symbol_info_from_item(item));
+                        }
+                    }
+                }
+            }
+        }
+        context_data.linked_indexes = self.linked_origins(&context_data);
+
+        context_data.edit_target = self.find_edit_target(user_prompt, &context_data);
+        context_data.refactor_impact = self.find_refactor_impact(user_prompt);
+        context_data.owned_elsewhere = self.find_owned_elsewhere(&context_data, project_root);
+        context_data.components = self.find_component_catalog(user_prompt, &context_data);
+        redact_context(&mut context_data);
+        context_data
+    }
 
-        let config = miow_prompt::MetaPromptConfig::default();
-        let prompt = miow_prompt::MetaPromptGenerator::generate(
-            user_prompt,
-            &context_data,
-            Some(&signature.to_description()),
-            config,
+    /// Export the context and build the meta-prompt from it; the end of the autonomous workflow
+    fn finish_autonomous_prompt(
+        &self,
+        user_prompt: &str,
+        project_root: &str,
+        context_data: &ContextData,
+        project: &str,
+        earlier_tasks: &[String],
+        run: Option<&RunRecorder>,
+    ) -> Result<String> {
+        if let Some((targets, dir)) = &self.exports {
+            let exporter = ContextExporter::new(user_prompt, context_data, std::path::Path::new(project_root));
+            for target in targets {
+                match exporter.export(*target, dir) {
+                    Ok(files) => info!("📤 Exported {:?} context: {} files in {}", target, files.len(), dir.display()),
+                    Err(e) => warn!("Failed to export {:?} context: {:#}", target, e),
+                }
+            }
+        }
+
+        // A follow-up like "now add validation to that form" needs the earlier tasks to make sense
+        let task = match earlier_tasks {
+            [] => user_prompt.to_string(),
+            earlier => format!("{}\n\nThis follows up on earlier tasks:\n- {}", user_prompt, earlier.join("\n- ")),
+        };
+        let config = miow_prompt::MetaPromptConfig {
+            token_budget: Some(self.pipeline.context_tokens),
+            format: self.prompt_format,
+            diff: self.diff_prompts,
+            ..Default::default()
+        };
+        let prompt = miow_prompt::MetaPromptGenerator::generate_with_templates(
+            &task,
+            context_data,
+            Some(project),
+            config.clone(),
+            &self.templates_for(std::path::Path::new(project_root)),
         )?;
+        if let Some(run) = run {
+            self.record_prompt(run, context_data, &config, &prompt);
+        }
 
         Ok(prompt)
     }
@@ -670,19 +1605,44 @@ Respond with a JSON array of strings."#,
         &self,
         task: &str,
         context: &miow_agent::autonomous::AgentContext,
-        project_info: &str
+        project_info: &str,
+        previous_plan: Option<&str>,
+        event_tx: Option<&tokio::sync::mpsc::Sender<miow_agent::autonomous::AgentEvent>>,
     ) -> Result<String> {
-        let gathered_summary = context.gathered_info.iter()
+        let mut gathered_summary = context.gathered_info.iter()
             .map(|i| format!("- From {}: {}", i.source, i.relevance))
             .collect::<Vec<_>>()
             .join("\n");
+        if !context.open_gaps.is_empty() {
+            gathered_summary.push_str(&format!(
+                "\n(Self-review could not find: {}. Do not invent these; mark them as open questions.)",
+                context.open_gaps.join("; ")
+            ));
+        }
+        if let Some(limit) = context.budget_exhausted {
+            gathered_summary.push_str(&format!(
+                "\n(Context gathering stopped early at its {}; this is a best effort. Call out anything the plan had to assume.)",
+                limit
+            ));
+        }
+
+        let mut earlier = String::new();
+        if !context.earlier_tasks.is_empty() {
+            earlier.push_str(&format!("\nEarlier tasks in this session:\n- {}\n", context.earlier_tasks.join("\n- ")));
+        }
+        if let Some(plan) = previous_plan {
+            earlier.push_str(&format!(
+                "\nThe previous task's plan, which this task builds on (don't repeat its steps; change them only where this task needs it):\n{}\n",
+                plan
+            ));
+        }
 
         let prompt = format!(
             r#"Create a comprehensive implementation plan for the following task.
 
             Task: {}
             Project Context: {}
-
+            {}
             Gathered Information:
             {}
 
@@ -691,23 +1651,242 @@ Respond with a JSON array of strings."#,
             2. Proposed Changes (File by File)
             3. Verification Plan
             "#,
-            task, project_info, gathered_summary
+            task, project_info, earlier, gathered_summary
         );
 
         let llm = self.llm.as_ref().ok_or_else(|| anyhow::anyhow!("LLM required"))?;
+        if self.pipeline.plan_samples > 1 {
+            let plan = miow_llm::self_consistent_text(llm.as_ref(), &prompt, self.pipeline.plan_samples).await?;
+            if let Some(tx) = event_tx {
+                let _ = tx.send(miow_agent::autonomous::AgentEvent::Token { content: plan.clone() }).await;
+            }
+            return Ok(plan);
+        }
+        // Someone is watching: send the plan as it is written
+        if let Some(tx) = event_tx {
+            match llm.stream_generate(&prompt).await {
+                Ok(mut stream) => {
+                    let mut plan = String::new();
+                    while let Some(chunk) = stream.next().await {
+                        let chunk = chunk?;
+                        plan.push_str(&chunk);
+                        let _ = tx.send(miow_agent::autonomous::AgentEvent::Token { content: chunk }).await;
+                    }
+                    return Ok(plan);
+                }
+                Err(e) => warn!("Streaming the plan failed, generating it whole: {}", e),
+            }
+        }
         let response = llm.generate(&prompt).await?;
 
         Ok(response.content)
     }
 
+    /// Match every item of the final context to what found it, and keep the
+    /// report for `explanation`
+    fn explain_context(&self, context: &ContextData, keywords: &[String]) {
+        let Some(log) = &self.explain else { return };
+        let mut items = Vec::new();
+        let mut explain = |name: &str, kind: &str, file_path: Option<&str>, content: &str, found_by: &str| {
+            let provenance = log.lookup(name, file_path).unwrap_or_else(|| Provenance::new(found_by));
+            items.push(ItemExplanation {
+                item: name.to_string(),
+                kind: kind.to_string(),
+                file_path: file_path.map(str::to_string),
+                provenance,
+                keyword_hits: explain::keyword_hits(keywords, &[name, file_path.unwrap_or_default(), content]),
+                centrality: self.graph.count_references_to(name).ok(),
+            });
+        };
+
+        if let Some(target) = &context.edit_target {
+            let symbol = &target.symbol;
+            explain(&symbol.name, &symbol.kind, Some(&symbol.file_path), &symbol.content, "edit target");
+        }
+        for symbol in context.relevant_symbols.iter().chain(&context.similar_symbols).filter(|s| s.kind != "plan") {
+            explain(&symbol.name, &symbol.kind, Some(&symbol.file_path), &symbol.content, "ranking");
+        }
+        for t in &context.types {
+            explain(&t.name, &t.kind, None, &t.definition, "type search");
+        }
+        for c in &context.constants {
+            explain(&c.name, &c.category, None, &c.value, "constant search");
+        }
+        for schema in &context.schemas {
+            explain(&schema.name, &schema.schema_type, None, &schema.definition, "schema search");
+        }
+        for test in &context.tests {
+            explain(&test.name, "test", Some(&test.file_path), &test.content, "tests of the relevant symbols");
+        }
+        log.set_report(items);
+    }
+
+    /// Explain what the agent gathered: the tool call behind each item and its
+    /// reason, and for searches each symbol's vector score and references
+    async fn explain_agent_context(&self, gathered: &[miow_agent::autonomous::VerifiedInfo], task: &str) {
+        let Some(log) = &self.explain else { return };
+        let keywords = self.analyzer.analyze_prompt(task).keywords;
+        let mut items = Vec::new();
+        for info in gathered {
+            let Some((tool, args)) = info.tool_call() else {
+                items.push(ItemExplanation {
+                    item: info.source.clone(),
+                    kind: "answer".to_string(),
+                    file_path: None,
+                    provenance: Provenance::new(info.source.to_lowercase()).reason(&info.relevance),
+                    keyword_hits: explain::keyword_hits(&keywords, &[&info.content]),
+                    centrality: None,
+                });
+                continue;
+            };
+            let query = ["query", "pattern", "path"].iter().find_map(|key| args[*key].as_str()).unwrap_or_default();
+            let provenance = Provenance::new(tool).query(query).reason(&info.relevance);
+            if tool != "search" {
+                let kind = if info.external { "external" } else { "file" };
+                let file_path = args["path"].as_str().map(str::to_string);
+                items.push(ItemExplanation {
+                    item: query.to_string(),
+                    kind: kind.to_string(),
+                    file_path,
+                    provenance,
+                    keyword_hits: explain::keyword_hits(&keywords, &[query, &info.content]),
+                    centrality: None,
+                });
+                continue;
+            }
+
+            // Cached from the agent's own search, so this costs nothing
+            let similar = match &self.vector_store {
+                Some(store) => self.search_cache.search_similar(store, query, 5).await.unwrap_or_default(),
+                None => Vec::new(),
+            };
+            // `search` lists each hit as `Symbol: name (kind)` followed by `File: path`
+            let mut lines = info.content.lines();
+            while let Some(line) = lines.next() {
+                let Some((name, kind)) = line.strip_prefix("Symbol: ").and_then(|rest| rest.rsplit_once(" (")) else { continue };
+                let file_path = lines.next().and_then(|l| l.strip_prefix("File: ")).unwrap_or_default();
+                let mut provenance = provenance.clone();
+                if let Some(hit) = similar.iter().find(|r| r.symbol.name == name) {
+                    provenance = provenance.vector_score(hit.score);
+                }
+                items.push(ItemExplanation {
+                    item: name.to_string(),
+                    kind: kind.trim_end_matches(')').to_string(),
+                    file_path: Some(file_path.to_string()),
+                    provenance,
+                    keyword_hits: explain::keyword_hits(&keywords, &[name, file_path]),
+                    centrality: self.graph.count_references_to(name).ok(),
+                });
+            }
+        }
+        log.set_report(items);
+    }
+
+    /// Move gathered items up or down by the feedback given on them (or their file)
+    /// in earlier runs, so retrieval learns what this project's users find useful
+    fn apply_feedback(&self, gathered: &mut GatheredContext) {
+        let weights = match self.graph.feedback_weights() {
+            Ok(weights) if !weights.is_empty() => weights,
+            Ok(_) => return,
+            Err(e) => {
+                warn!("Failed to load feedback weights: {}", e);
+                return;
+            }
+        };
+        let weight = |path: &str, name: &str| weights.get(&(path.to_string(), name.to_string())).copied().unwrap_or(0.0);
+
+        let mut adjusted = 0;
+        for list in [
+            &mut gathered.components,
+            &mut gathered.helpers,
+            &mut gathered.types,
+            &mut gathered.constants,
+            &mut gathered.schemas,
+            &mut gathered.similar_implementations,
+            &mut gathered.design_tokens,
+        ] {
+            for item in list.iter_mut() {
+                let delta = weight(&item.file_path, &item.name) + weight(&item.file_path, "");
+                if delta != 0.0 {
+                    item.relevance_score = (item.relevance_score + delta).clamp(0.0, 1.0);
+                    adjusted += 1;
+                }
+            }
+            // Stable, so items without feedback keep their order
+            list.sort_by(|a, b| b.relevance_score.partial_cmp(&a.relevance_score).unwrap_or(Ordering::Equal));
+        }
+        if adjusted > 0 {
+            info!("👍 Feedback from earlier runs adjusted {} items", adjusted);
+        }
+    }
+
+    /// Drop gathered items outside the search scope, then everything outside the
+    /// `max_files` files with the most relevant items. Design tokens have no
+    /// symbol kind, so only their path is checked.
+    fn scope_gathered(&self, gathered: &mut GatheredContext) {
+        let scope = self.search_cache.scope();
+        let symbol_lists = [
+            &mut gathered.components,
+            &mut gathered.helpers,
+            &mut gathered.types,
+            &mut gathered.constants,
+            &mut gathered.schemas,
+            &mut gathered.similar_implementations,
+        ];
+        if !scope.is_empty() {
+            for list in symbol_lists {
+                list.retain(|item| scope.matches(&item.file_path, &item.kind));
+            }
+            gathered.design_tokens.retain(|item| scope.contains_path(&item.file_path));
+        }
+
+        let Some(max_files) = self.max_files else { return };
+        let mut best: Vec<(&str, f32)> = Vec::new();
+        let all = gathered
+            .components
+            .iter()
+            .chain(&gathered.helpers)
+            .chain(&gathered.types)
+            .chain(&gathered.constants)
+            .chain(&gathered.schemas)
+            .chain(&gathered.similar_implementations)
+            .chain(&gathered.design_tokens);
+        for item in all.filter(|item| !item.file_path.is_empty()) {
+            match best.iter_mut().find(|(path, _)| *path == item.file_path) {
+                Some((_, score)) => *score = score.max(item.relevance_score),
+                None => best.push((&item.file_path, item.relevance_score)),
+            }
+        }
+        if best.len() <= max_files {
+            return;
+        }
+        // Stable sort keeps discovery order among equally relevant files
+        best.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        let keep: HashSet<String> = best.into_iter().take(max_files).map(|(path, _)| path.to_string()).collect();
+        info!("📁 Keeping context from the {} most relevant files", keep.len());
+        for list in [
+            &mut gathered.components,
+            &mut gathered.helpers,
+            &mut gathered.types,
+            &mut gathered.constants,
+            &mut gathered.schemas,
+            &mut gathered.similar_implementations,
+            &mut gathered.design_tokens,
+        ] {
+            list.retain(|item| item.file_path.is_empty() || keep.contains(&item.file_path));
+        }
+    }
+
     /// Gather comprehensive context from codebase
     /// If a router plan is provided, its target_paths hints are used to filter results by file path.
+    /// With a project signature, items that fit how the project is built rank higher.
     async fn gather_comprehensive_context(
         &self,
-        _user_prompt: &str,
+        user_prompt: &str,
         search_queries: &[String],
-        intent: &str,
+        intent: Intent,
         router_plan: Option<&miow_agent::SearchPlan>,
+        signature: Option<&ProjectSignature>,
     ) -> Result<GatheredContext> {
         info!("Gathering comprehensive context...");
 
@@ -743,55 +1922,17 @@ Respond with a JSON array of strings."#,
             similar_implementations: Vec::new(),
         };
 
-        // Explicitly search for common UI primitives
-        let ui_primitives = vec!["Button", "Input", "InputBox", "Form", "BaseButton", "DateInput", "PhoneNumberInput"];
-        for primitive in &ui_primitives {
-            if let Ok(results) = self.graph.search_symbols(primitive) {
-                for result in results {
-                    let name_lower = result.name.to_lowercase();
-                    if name_lower.contains(&primitive.to_lowercase()) {
-                        let relevance = if result.name == *primitive || result.name.contains(primitive) {
-                            0.95 // High relevance for exact matches
-                        } else {
-                            0.7
-                        };
-
-                        // Parse metadata for props
-                        let mut props = Vec::new();
-                        if let Some(meta_json) = &result.metadata {
-                            if let Ok(meta) = serde_json::from_str::<serde_json::Value>(meta_json) {
-                                 if let Some(props_arr) = meta.get("props").and_then(|p| p.as_array()) {
-                                     for p in props_arr {
-                                         let name = p.get("name").and_then(|s| s.as_str()).unwrap_or("?");
-                                         let type_ann = p.get("type_annotation").and_then(|s| s.as_str()).unwrap_or("any");
-                                         props.push(format!("{}: {}", name, type_ann));
-                                     }
-                                 }
-                            }
-                        }
-
-                        // Get references
-                        let references = self.graph.get_symbol_dependencies(result.id).unwrap_or_default();
-
-                        let item = ContextItem {
-                            name: result.name.clone(),
-                            kind: result.kind.clone(),
-                            content: result.content.clone(),
-                            file_path: result.file_path.clone(),
-                            relevance_score: relevance,
-                            props,
-                            references,
-                        };
-                        gathered.components.push(item);
-                    }
-                }
-            }
-        }
+        // Every graph and vector search makes one ranked list; the lists are fused by
+        // reciprocal rank so items several queries agree on rise above any one
+        // query's top hits, and an item found more than once is kept once
+        let mut lists: Vec<Vec<(String, String)>> = Vec::new();
+        let mut candidates: HashMap<(String, String), (ContextItem, bool)> = HashMap::new();
 
         // Search for components/helpers using queries, respecting router target_paths when present
         for query in search_queries {
             let target_paths = get_target_paths(query);
-            let results = self.graph.search_symbols(query)?;
+            let mut ranked: Vec<(f32, (String, String))> = Vec::new();
+            let results = self.search_cache.search_symbols(&self.graph, query)?;
             for result in results {
                 if !target_paths.is_empty()
                     && !target_paths
@@ -801,34 +1942,27 @@ Respond with a JSON array of strings."#,
                     continue;
                 }
 
-                let kind_lower = result.kind.to_lowercase();
-                let name_lower = result.name.to_lowercase();
                 let relevance = self.calculate_relevance(&result.name, &result.kind, query, intent);
 
-                // Boost relevance for UI primitives
-                let relevance = if ui_primitives.iter().any(|p| name_lower.contains(&p.to_lowercase())) {
-                    (relevance + 0.3).min(1.0)
+                // Prefer a module's exported API over its internal helpers
+                let relevance = if is_exported_metadata(result.metadata.as_deref()) {
+                    (relevance + 0.1).min(1.0)
                 } else {
                     relevance
                 };
 
-                // Parse metadata for props
-                let mut props = Vec::new();
-                if let Some(meta_json) = &result.metadata {
-                    if let Ok(meta) = serde_json::from_str::<serde_json::Value>(meta_json) {
-                            if let Some(props_arr) = meta.get("props").and_then(|p| p.as_array()) {
-                                for p in props_arr {
-                                    let name = p.get("name").and_then(|s| s.as_str()).unwrap_or("?");
-                                    let type_ann = p.get("type_annotation").and_then(|s| s.as_str()).unwrap_or("any");
-                                    props.push(format!("{}: {}", name, type_ann));
-                                }
-                            }
-                    }
+                let key = (result.file_path.clone(), result.name.clone());
+                ranked.push((relevance, key.clone()));
+                if candidates.contains_key(&key) {
+                    continue;
                 }
 
+                let props = props_from_metadata(result.metadata.as_deref());
+
                 // Get references
                 let references = self.graph.get_symbol_dependencies(result.id).unwrap_or_default();
 
+                self.note(&result.name, &result.file_path, || Provenance::new("graph search").query(query));
                 let item = ContextItem {
                     name: result.name.clone(),
                     kind: result.kind.clone(),
@@ -837,32 +1971,31 @@ Respond with a JSON array of strings."#,
                     relevance_score: relevance,
                     props,
                     references,
+                    is_exported: is_exported_metadata(result.metadata.as_deref()),
+                    documentation: documentation_from_metadata(result.metadata.as_deref()),
                 };
-
-                if kind_lower.contains("component")
-                    || (kind_lower.contains("function")
-                        && result
-                            .name
-                            .chars()
-                            .next()
-                            .map(|c| c.is_uppercase())
-                            .unwrap_or(false))
-                {
-                    gathered.components.push(item);
-                } else if kind_lower.contains("type") || kind_lower.contains("interface") {
-                    gathered.types.push(item);
-                } else if kind_lower.contains("schema") || kind_lower.contains("model") {
-                    gathered.schemas.push(item);
-                } else if kind_lower.contains("const") {
-                    gathered.constants.push(item);
-                } else {
-                    gathered.helpers.push(item);
+                candidates.insert(key, (item, true));
+            }
+            // Stable sort keeps the graph's order among equally relevant symbols
+            ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+            lists.push(ranked.into_iter().map(|(_, key)| key).collect());
+
+            // Each linked repository ranks its own hits, fused with this one's
+            for (name, graph) in &self.linked_indexes {
+                let mut ranked = Vec::new();
+                for item in self.search_linked_index(name, graph, query, intent) {
+                    self.note(&item.name, &item.file_path, || Provenance::new("linked index").query(query).reason(format!("found in {}", name)));
+                    let key = (item.file_path.clone(), item.name.clone());
+                    ranked.push(key.clone());
+                    candidates.entry(key).or_insert((item, true));
                 }
+                lists.push(ranked);
             }
 
             // 2. Vector Search (Semantic)
             if let Some(vs) = &self.vector_store {
-                if let Ok(vector_results) = vs.search_similar(query, 5).await {
+                if let Ok(vector_results) = self.search_cache.search_similar(vs, query, 5).await {
+                    let mut ranked = Vec::new();
                     for result in vector_results {
                         // Skip if we have target paths and this file doesn't match
                         if !target_paths.is_empty()
@@ -873,20 +2006,17 @@ Respond with a JSON array of strings."#,
                             continue;
                         }
 
-                        // Parse metadata for props
-                        let mut props = Vec::new();
-                        if !result.symbol.metadata.is_empty() {
-                            if let Ok(meta) = serde_json::from_str::<serde_json::Value>(&result.symbol.metadata) {
-                                    if let Some(props_arr) = meta.get("props").and_then(|p| p.as_array()) {
-                                        for p in props_arr {
-                                            let name = p.get("name").and_then(|s| s.as_str()).unwrap_or("?");
-                                            let type_ann = p.get("type_annotation").and_then(|s| s.as_str()).unwrap_or("any");
-                                            props.push(format!("{}: {}", name, type_ann));
-                                        }
-                                    }
-                            }
+                        self.note(&result.symbol.name, &result.symbol.file_path, || {
+                            Provenance::new("vector search").query(query).vector_score(result.score)
+                        });
+                        let key = (result.symbol.file_path.clone(), result.symbol.name.clone());
+                        ranked.push(key.clone());
+                        if candidates.contains_key(&key) {
+                            continue;
                         }
 
+                        let props = props_from_metadata(Some(&result.symbol.metadata));
+
                         // Get references
                         let references = if let Ok(id) = result.symbol.id.parse::<i64>() {
                             self.graph.get_symbol_dependencies(id).unwrap_or_default()
@@ -902,39 +2032,104 @@ Respond with a JSON array of strings."#,
                             relevance_score: result.score,
                             props,
                             references,
+                            is_exported: is_exported_metadata(Some(&result.symbol.metadata)),
+                            documentation: documentation_from_metadata(Some(&result.symbol.metadata)),
                         };
+                        candidates.insert(key, (item, false));
+                    }
+                    lists.push(ranked);
+                }
+            }
+        }
 
-                        let kind_lower = result.symbol.kind.to_lowercase();
+        let boosts = signature.map(BoostRule::for_signature).unwrap_or_default();
+        for (key, fused) in reciprocal_rank_fusion(&lists) {
+            let Some((mut item, from_graph)) = candidates.remove(&key) else { continue };
+            let boost: f32 = boosts.iter().filter(|rule| rule.matches(&item)).map(|rule| rule.weight).sum();
+            item.relevance_score = (0.4 + 0.5 * fused + boost).min(1.0);
+
+            let kind_lower = item.kind.to_lowercase();
+            // The graph also reports PascalCase functions, which are components in JSX projects
+            let is_component = kind_lower.contains("component")
+                || (from_graph
+                    && kind_lower.contains("function")
+                    && item.name.chars().next().is_some_and(|c| c.is_uppercase()));
+            if is_component {
+                gathered.components.push(item);
+            } else if kind_lower.contains("type") || kind_lower.contains("interface") {
+                gathered.types.push(item);
+            } else if kind_lower.contains("schema") || kind_lower.contains("model") {
+                gathered.schemas.push(item);
+            } else if kind_lower.contains("const") {
+                gathered.constants.push(item);
+            } else {
+                gathered.helpers.push(item);
+            }
+        }
 
-                        if kind_lower.contains("component") {
-                            gathered.components.push(item);
-                        } else if kind_lower.contains("type") || kind_lower.contains("interface") {
-                            gathered.types.push(item);
-                        } else if kind_lower.contains("schema") || kind_lower.contains("model") {
-                            gathered.schemas.push(item);
-                        } else if kind_lower.contains("const") {
-                            gathered.constants.push(item);
-                        } else {
-                            gathered.helpers.push(item);
-                        }
+        // Existing implementations built like the code the task touches, as examples to follow
+        gathered.similar_implementations = self.find_similar_implementations(&gathered, user_prompt, intent).await;
+
+        // Reuse the project's own hooks for data-fetching and state tasks
+        let wants_hooks = ["hook", "fetch", "data", "state", "query", "load", "subscribe"]
+            .iter()
+            .any(|k| {
+                user_prompt.to_lowercase().contains(k)
+                    || search_queries.iter().any(|q| q.to_lowercase().contains(k))
+            });
+        if wants_hooks {
+            if let Ok(hooks) = self.graph.find_hooks() {
+                for hook in hooks.into_iter().take(8) {
+                    if gathered.helpers.iter().any(|h| h.name == hook.name && h.file_path == hook.file_path) {
+                        continue;
                     }
+                    let references = self.graph.get_symbol_dependencies(hook.id).unwrap_or_default();
+                    self.note(&hook.name, &hook.file_path, || {
+                        Provenance::new("project hook").reason("the task fetches data or manages state")
+                    });
+                    gathered.helpers.push(ContextItem {
+                        is_exported: is_exported_metadata(hook.metadata.as_deref()),
+                        documentation: documentation_from_metadata(hook.metadata.as_deref()),
+                        name: hook.name,
+                        kind: hook.kind,
+                        content: hook.content,
+                        file_path: hook.file_path,
+                        relevance_score: 0.8,
+                        props: vec![],
+                        references,
+                    });
                 }
             }
         }
 
-        // Find similar implementations based on intent
-        if intent.contains("Component") || intent.contains("component") {
-            let components = self.graph.find_symbols_by_kind("Component")?;
-            for comp in components.into_iter().take(5) {
-                gathered.similar_implementations.push(ContextItem {
-                    name: comp.name,
-                    kind: comp.kind,
-                    content: comp.content,
-                    file_path: comp.file_path, // Kept original comp.file_path for syntactic correctness
-                    relevance_score: 1.0,
-                    props: vec![],
-                    references: vec![],
-                });
+        // Framework roles are declared by decorators ("add a service" -> existing @Injectable classes)
+        let prompt_lower = user_prompt.to_lowercase();
+        for (keyword, decorator) in DECORATOR_HINTS {
+            if !prompt_lower.contains(keyword) {
+                continue;
+            }
+            if let Ok(symbols) = self.graph.find_symbols_by_decorator(decorator) {
+                for symbol in symbols.into_iter().take(5) {
+                    if gathered.components.iter().chain(gathered.helpers.iter())
+                        .any(|h| h.name == symbol.name && h.file_path == symbol.file_path)
+                    {
+                        continue;
+                    }
+                    self.note(&symbol.name, &symbol.file_path, || {
+                        Provenance::new("decorator").query(*decorator).reason(format!("the task mentions '{}'", keyword))
+                    });
+                    gathered.helpers.push(ContextItem {
+                        is_exported: is_exported_metadata(symbol.metadata.as_deref()),
+                        documentation: documentation_from_metadata(symbol.metadata.as_deref()),
+                        name: symbol.name,
+                        kind: symbol.kind,
+                        content: symbol.content,
+                        file_path: symbol.file_path,
+                        relevance_score: 0.75,
+                        props: vec![],
+                        references: vec![],
+                    });
+                }
             }
         }
 
@@ -951,6 +2146,7 @@ Respond with a JSON array of strings."#,
                     continue;
                 }
 
+                self.note(&token.name, &token.file_path, || Provenance::new("design token search").query(query));
                 gathered.design_tokens.push(ContextItem {
                     name: token.name,
                     kind: token.token_type,
@@ -959,6 +2155,8 @@ Respond with a JSON array of strings."#,
                     relevance_score: 0.7,
                     props: vec![],
                     references: vec![],
+                    is_exported: false,
+                    documentation: None,
                 });
             }
         }
@@ -966,91 +2164,113 @@ Respond with a JSON array of strings."#,
         // Find type definitions
         for query in search_queries {
             let target_paths = get_target_paths(query);
-            match self.graph.find_type_definitions(query) {
-                Ok(types) => {
-                    for type_def in types {
-                        if !target_paths.is_empty()
-                            && !target_paths
-                                .iter()
-                                .any(|p| type_def.file_path.starts_with(p))
-                        {
-                            continue;
-                        }
-
-                        gathered.types.push(ContextItem {
-                            name: type_def.name,
-                            kind: type_def.kind,
-                            content: type_def.definition,
-                            file_path: type_def.file_path,
-                            relevance_score: 0.8,
-                            props: vec![],
-                            references: vec![],
-                        });
+            // Ignore errors, continue searching
+            if let Ok(types) = self.graph.find_type_definitions(query) {
+                for type_def in types {
+                    if !target_paths.is_empty()
+                        && !target_paths
+                            .iter()
+                            .any(|p| type_def.file_path.starts_with(p))
+                    {
+                        continue;
                     }
+
+                    self.note(&type_def.name, &type_def.file_path, || Provenance::new("type search").query(query));
+                    gathered.types.push(ContextItem {
+                        name: type_def.name,
+                        kind: type_def.kind,
+                        content: type_def.definition,
+                        file_path: type_def.file_path,
+                        relevance_score: 0.8,
+                        props: vec![],
+                        references: vec![],
+                        is_exported: false,
+                        documentation: None,
+                    });
                 }
-                Err(_) => {} // Ignore errors, continue searching
             }
         }
 
         // Find constants
         for query in search_queries {
             let target_paths = get_target_paths(query);
-            match self.graph.find_constants(query) {
-                Ok(constants) => {
-                    for constant in constants {
-                        if !target_paths.is_empty()
-                            && !target_paths
-                                .iter()
-                                .any(|p| constant.file_path.starts_with(p))
-                        {
-                            continue;
-                        }
-
-                        gathered.constants.push(ContextItem {
-                            name: constant.name,
-                            kind: constant.category,
-                            content: constant.value,
-                            file_path: constant.file_path,
-                            relevance_score: 0.6,
-                            props: vec![],
-                            references: vec![],
-                        });
+            // Ignore errors, continue searching
+            if let Ok(constants) = self.graph.find_constants(query) {
+                for constant in constants {
+                    if !target_paths.is_empty()
+                        && !target_paths
+                            .iter()
+                            .any(|p| constant.file_path.starts_with(p))
+                    {
+                        continue;
                     }
+
+                    self.note(&constant.name, &constant.file_path, || Provenance::new("constant search").query(query));
+                    gathered.constants.push(ContextItem {
+                        name: constant.name,
+                        kind: constant.category,
+                        content: constant.value,
+                        file_path: constant.file_path,
+                        relevance_score: 0.6,
+                        props: vec![],
+                        references: vec![],
+                        is_exported: false,
+                        documentation: None,
+                    });
                 }
-                Err(_) => {} // Ignore errors, continue searching
             }
         }
 
         // Find schemas
         for query in search_queries {
             let target_paths = get_target_paths(query);
-            match self.graph.find_schemas(query) {
-                Ok(schemas) => {
-                    for schema in schemas {
-                        if !target_paths.is_empty()
-                            && !target_paths
-                                .iter()
-                                .any(|p| schema.file_path.starts_with(p))
-                        {
-                            continue;
-                        }
-
-                        gathered.schemas.push(ContextItem {
-                            name: schema.name,
-                            kind: schema.schema_type,
-                            content: schema.definition,
-                            file_path: schema.file_path,
-                            relevance_score: 0.7,
-                            props: vec![],
-                            references: vec![],
-                        });
+            // Ignore errors, continue searching
+            if let Ok(schemas) = self.graph.find_schemas(query) {
+                for schema in schemas {
+                    if !target_paths.is_empty()
+                        && !target_paths
+                            .iter()
+                            .any(|p| schema.file_path.starts_with(p))
+                    {
+                        continue;
                     }
+
+                    // Columns become props and relations become references, so
+                    // table schemas carry their foreign keys into the prompt
+                    let props = schema
+                        .fields
+                        .iter()
+                        .map(|f| match &f.field_type {
+                            Some(field_type) => format!("{}: {}", f.name, field_type),
+                            None => f.name.clone(),
+                        })
+                        .collect();
+                    let references = schema
+                        .fields
+                        .iter()
+                        .filter_map(|f| f.references.clone())
+                        .collect();
+
+                    self.note(&schema.name, &schema.file_path, || Provenance::new("schema search").query(query));
+                    gathered.schemas.push(ContextItem {
+                        name: schema.name,
+                        kind: schema.schema_type,
+                        content: schema.definition,
+                        file_path: schema.file_path,
+                        relevance_score: 0.7,
+                        props,
+                        references,
+                        is_exported: false,
+                        documentation: None,
+                    });
                 }
-                Err(_) => {} // Ignore errors, continue searching
             }
         }
 
-        // Sort by relevance and limit
+        self.apply_feedback(&mut gathered);
+        self.scope_gathered(&mut gathered);
+
+        // Sort by relevance and keep what fits the budget
         gathered.components.sort_by(|a, b| {
             b.relevance_score
                 .partial_cmp(&a.relevance_score)
@@ -1061,13 +2281,7 @@ Respond with a JSON array of strings."#,
                 .partial_cmp(&a.relevance_score)
                 .unwrap_or(std::cmp::Ordering::Equal)
         });
-        gathered.components.truncate(15);
-        gathered.helpers.truncate(15);
-        gathered.types.truncate(10);
-        gathered.design_tokens.truncate(20);
-        gathered.constants.truncate(10);
-        gathered.schemas.truncate(5);
-        gathered.similar_implementations.truncate(5);
+        self.select_within_budget(&mut gathered, search_queries);
 
         info!(
             "Gathered context: {} components, {} helpers, {} types, {} tokens",
@@ -1077,11 +2291,129 @@ Respond with a JSON array of strings."#,
             gathered.design_tokens.len()
         );
 
-        Ok(gathered)
+        Ok(gathered)
+    }
+
+    /// Keep the gathered items worth the most per token, within the context
+    /// token budget. An item found by several queries is paid for once. Large
+    /// code bodies compete as slices (signature, props and the lines matching the
+    /// queries) and get their whole body back only if the budget has room left.
+    fn select_within_budget(&self, gathered: &mut GatheredContext, search_queries: &[String]) {
+        let counter = self.token_counter();
+        let slicer = miow_prompt::SnippetSlicer::new(search_queries).with_counter(counter);
+        // Lists of code, which can be sliced, and of definitions and values, which can't
+        let mut lists = [
+            (&mut gathered.components, true),
+            (&mut gathered.helpers, true),
+            (&mut gathered.types, false),
+            (&mut gathered.design_tokens, false),
+            (&mut gathered.constants, false),
+            (&mut gathered.schemas, false),
+            (&mut gathered.similar_implementations, true),
+        ];
+        let mut candidates = Vec::new();
+        let mut slices = Vec::new();
+        for (list, code) in lists.iter_mut() {
+            let mut seen = HashSet::new();
+            list.retain(|item| seen.insert((item.file_path.clone(), item.name.clone())));
+            for item in list.iter() {
+                let overhead = counter.count(&item.name) + item.documentation.as_deref().map_or(0, |d| counter.count(d));
+                let tokens = counter.count(&item.content) + overhead;
+                let slice = if *code { slicer.slice(&item.content, &item.file_path) } else { None };
+                candidates.push(match &slice {
+                    Some(slice) => miow_prompt::Candidate::sliced(item.relevance_score, counter.count(slice) + overhead, tokens),
+                    None => miow_prompt::Candidate::new(item.relevance_score, tokens),
+                });
+                slices.push(slice);
+            }
+        }
+
+        let budget = self.pipeline.context_tokens;
+        let selected = miow_prompt::select_within_budget(&candidates, budget);
+        let restored: HashSet<usize> = miow_prompt::restore_within_budget(&candidates, &selected, budget).into_iter().collect();
+        let keep: HashSet<usize> = selected.into_iter().collect();
+        let sliced = keep.iter().filter(|i| slices[**i].is_some() && !restored.contains(i)).count();
+        if keep.len() < candidates.len() || sliced > 0 {
+            info!(
+                "🎒 Kept {} of {} gathered items within {} tokens, {} of them sliced",
+                keep.len(),
+                candidates.len(),
+                budget,
+                sliced
+            );
+        }
+        let mut index = 0;
+        for (list, _) in lists {
+            list.retain_mut(|item| {
+                let i = index;
+                index += 1;
+                if let Some(slice) = slices[i].take().filter(|_| !restored.contains(&i)) {
+                    item.content = slice;
+                }
+                keep.contains(&i)
+            });
+        }
+    }
+
+    /// Counts tokens the way the attached model does
+    fn token_counter(&self) -> miow_prompt::TokenCounter {
+        let model = self.llm.as_ref().map(|llm| llm.model_name().to_string()).unwrap_or_else(|| miow_llm::LLMConfig::default().model);
+        miow_prompt::TokenCounter::for_model(&model)
+    }
+
+    /// Answer template questions without an LLM. A question asks about one kind of
+    /// symbol; its answer is the symbols of that kind that graph and vector search
+    /// find for the task's keywords, so project-wide questions stay on the task.
+    async fn answer_questions_by_search(
+        &self,
+        questions: &[miow_llm::CriticalQuestion],
+        keywords: &[String],
+    ) -> Vec<miow_llm::QuestionAnswer> {
+        let scope = self.search_cache.scope();
+        let mut found: Vec<SymbolSearchResult> = Vec::new();
+        for keyword in keywords {
+            let mut hits = Vec::new();
+            if let Some(vs) = &self.vector_store {
+                if let Ok(results) = self.search_cache.search_similar(vs, keyword, 5).await {
+                    for result in results {
+                        hits.extend(self.graph.find_symbols_by_name(&result.symbol.name).unwrap_or_default());
+                    }
+                }
+            }
+            hits.extend(self.search_cache.search_symbols(&self.graph, keyword).unwrap_or_default());
+            for hit in hits {
+                if scope.matches(&hit.file_path, &hit.kind)
+                    && !found.iter().any(|f| f.name == hit.name && f.file_path == hit.file_path)
+                {
+                    found.push(hit);
+                }
+            }
+        }
+
+        let mut answers = Vec::new();
+        for question in questions {
+            let symbols: Vec<SymbolSearchResult> = found
+                .iter()
+                .filter(|symbol| answers_question(&question.expected_type, symbol))
+                .take(5)
+                .cloned()
+                .collect();
+            if symbols.is_empty() {
+                continue;
+            }
+            info!("   {} → {} symbols", question.question, symbols.len());
+            answers.push(miow_llm::QuestionAnswer {
+                question: question.question.clone(),
+                symbols,
+                // Matched by search alone, never verified
+                confidence: 0.6,
+            });
+        }
+        answers
     }
 
     /// Calculate relevance score for a symbol
-    fn calculate_relevance(&self, name: &str, kind: &str, query: &str, intent: &str) -> f32 {
+    fn calculate_relevance(&self, name: &str, kind: &str, query: &str, intent: Intent) -> f32 {
         let mut score = 0.5;
 
         // Name match
@@ -1093,8 +2425,7 @@ Respond with a JSON array of strings."#,
 
         // Kind match with intent
         let kind_lower = kind.to_lowercase();
-        let intent_lower = intent.to_lowercase();
-        if intent_lower.contains("component") && kind_lower.contains("component") {
+        if intent == Intent::CreateComponent && kind_lower.contains("component") {
             score += 0.2;
         }
 
@@ -1105,6 +2436,170 @@ Respond with a JSON array of strings."#,
         }
     }
 
+    /// Symbols built like the most relevant gathered ones for a create task:
+    /// sharing what they reference, import and take as props or parameters,
+    /// and close to the task in embedding space when a vector store is set up.
+    /// Without anything gathered to compare with, only embedding distance counts.
+    async fn find_similar_implementations(&self, gathered: &GatheredContext, user_prompt: &str, intent: Intent) -> Vec<ContextItem> {
+        let mut seeds: Vec<&ContextItem> = match intent {
+            Intent::CreateComponent | Intent::CreatePage => gathered.components.iter().collect(),
+            Intent::CreateFunction => gathered.helpers.iter().collect(),
+            Intent::Create => gathered.components.iter().chain(&gathered.helpers).collect(),
+            _ => return Vec::new(),
+        };
+        seeds.sort_by(|a, b| b.relevance_score.partial_cmp(&a.relevance_score).unwrap_or(Ordering::Equal));
+        // Linked repositories have their own indexes; only this one is compared
+        let seeds: Vec<(String, String)> = seeds
+            .into_iter()
+            .filter(|item| !self.linked_indexes.iter().any(|(name, _)| item.file_path.starts_with(&format!("{}:", name))))
+            .take(MAX_SIMILARITY_SEEDS)
+            .map(|item| (item.file_path.clone(), item.name.clone()))
+            .collect();
+
+        let mut semantic = Vec::new();
+        if let (Some(vs), false) = (&self.vector_store, user_prompt.trim().is_empty()) {
+            match self.search_cache.search_similar(vs, user_prompt, 20).await {
+                Ok(results) => semantic = results,
+                Err(e) => warn!("Vector search for similar implementations failed: {}", e),
+            }
+        }
+
+        let structural = if seeds.is_empty() {
+            Vec::new()
+        } else {
+            self.graph.similar_symbols(&seeds, MAX_SIMILAR_IMPLEMENTATIONS * 4).unwrap_or_else(|e| {
+                warn!("Structural similarity search failed: {}", e);
+                Vec::new()
+            })
+        };
+
+        let gathered_keys: HashSet<(&str, &str)> = gathered
+            .components
+            .iter()
+            .chain(&gathered.helpers)
+            .map(|item| (item.file_path.as_str(), item.name.as_str()))
+            .collect();
+        let mut similar: Vec<ContextItem> = Vec::new();
+        for hit in structural {
+            if gathered_keys.contains(&(hit.symbol.file_path.as_str(), hit.symbol.name.as_str())) {
+                continue;
+            }
+            let vector_score = semantic
+                .iter()
+                .find(|r| r.symbol.file_path == hit.symbol.file_path && r.symbol.name == hit.symbol.name)
+                .map(|r| r.score);
+            let score = match vector_score {
+                Some(vector_score) => STRUCTURAL_WEIGHT * hit.score + (1.0 - STRUCTURAL_WEIGHT) * vector_score,
+                None if semantic.is_empty() => hit.score,
+                None => STRUCTURAL_WEIGHT * hit.score,
+            };
+            let mut shared = Vec::new();
+            for (label, features) in [
+                ("references", &hit.shared_references),
+                ("imports", &hit.shared_imports),
+                ("props/parameters", &hit.shared_shape),
+            ] {
+                if !features.is_empty() {
+                    shared.push(format!("{} {}", label, features.join(", ")));
+                }
+            }
+            self.note(&hit.symbol.name, &hit.symbol.file_path, || {
+                let provenance = Provenance::new("similar implementation").reason(format!("like {}: shares {}", hit.similar_to, shared.join("; ")));
+                match vector_score {
+                    Some(vector_score) => provenance.vector_score(vector_score),
+                    None => provenance,
+                }
+            });
+            let symbol = hit.symbol;
+            similar.push(ContextItem {
+                references: self.graph.get_symbol_dependencies(symbol.id).unwrap_or_default(),
+                props: props_from_metadata(symbol.metadata.as_deref()),
+                is_exported: is_exported_metadata(symbol.metadata.as_deref()),
+                documentation: documentation_from_metadata(symbol.metadata.as_deref()),
+                relevance_score: score,
+                name: symbol.name,
+                kind: symbol.kind,
+                content: symbol.content,
+                file_path: symbol.file_path,
+            });
+        }
+
+        if seeds.is_empty() {
+            let wanted_kind = match intent {
+                Intent::CreateComponent | Intent::CreatePage => Some("component"),
+                Intent::CreateFunction => Some("function"),
+                _ => None,
+            };
+            for result in semantic {
+                let (symbol, score) = (result.symbol, result.score);
+                if wanted_kind.is_some_and(|kind| !symbol.kind.to_lowercase().contains(kind)) {
+                    continue;
+                }
+                self.note(&symbol.name, &symbol.file_path, || {
+                    Provenance::new("similar implementation").vector_score(score).reason("close to the task in embedding space")
+                });
+                let references = symbol.id.parse::<i64>().ok().and_then(|id| self.graph.get_symbol_dependencies(id).ok()).unwrap_or_default();
+                similar.push(ContextItem {
+                    props: props_from_metadata(Some(&symbol.metadata)),
+                    is_exported: is_exported_metadata(Some(&symbol.metadata)),
+                    documentation: documentation_from_metadata(Some(&symbol.metadata)),
+                    relevance_score: score,
+                    references,
+                    name: symbol.name,
+                    kind: symbol.kind,
+                    content: symbol.content,
+                    file_path: symbol.file_path,
+                });
+            }
+        }
+
+        similar.sort_by(|a, b| b.relevance_score.partial_cmp(&a.relevance_score).unwrap_or(Ordering::Equal));
+        similar.truncate(MAX_SIMILAR_IMPLEMENTATIONS);
+        similar
+    }
+
+    /// Symbols a linked repository's index finds for `query`, best first, with
+    /// their paths prefixed by the index name
+    fn search_linked_index(&self, name: &str, graph: &KnowledgeGraph, query: &str, intent: Intent) -> Vec<ContextItem> {
+        let results = match graph.search_symbols(query) {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("Search of linked index {} failed: {:#}", name, e);
+                return Vec::new();
+            }
+        };
+        let mut items: Vec<ContextItem> = results
+            .into_iter()
+            .map(|result| ContextItem {
+                relevance_score: self.calculate_relevance(&result.name, &result.kind, query, intent),
+                references: graph.get_symbol_dependencies(result.id).unwrap_or_default(),
+                props: props_from_metadata(result.metadata.as_deref()),
+                is_exported: is_exported_metadata(result.metadata.as_deref()),
+                documentation: documentation_from_metadata(result.metadata.as_deref()),
+                file_path: format!("{}:{}", name, result.file_path),
+                name: result.name,
+                kind: result.kind,
+                content: result.content,
+            })
+            .collect();
+        // A library's exported API is what the app can use
+        items.sort_by(|a, b| b.is_exported.cmp(&a.is_exported).then(b.relevance_score.partial_cmp(&a.relevance_score).unwrap_or(Ordering::Equal)));
+        items
+    }
+
+    /// Names of the linked indexes that items of the context came from
+    fn linked_origins(&self, context: &ContextData) -> Vec<String> {
+        self.linked_indexes
+            .iter()
+            .map(|(name, _)| name)
+            .filter(|name| {
+                let prefix = format!("{}:", name);
+                context.relevant_symbols.iter().chain(&context.similar_symbols).any(|s| s.file_path.starts_with(&prefix))
+            })
+            .cloned()
+            .collect()
+    }
+
     /// Convert gathered context to prompt context format
     async fn convert_to_context_data(
         &self,
@@ -1125,6 +2620,9 @@ Respond with a JSON array of strings."#,
                 end_line: 0,
                 props: item.props.clone(),
                 references: item.references.clone(),
+                is_exported: item.is_exported,
+                documentation: item.documentation.clone(),
+                language: language_for_path(&item.file_path).map(String::from),
             })
             .collect();
 
@@ -1140,6 +2638,9 @@ Respond with a JSON array of strings."#,
                 end_line: 0,
                 props: item.props.clone(),
                 references: item.references.clone(),
+                is_exported: item.is_exported,
+                documentation: item.documentation.clone(),
+                language: language_for_path(&item.file_path).map(String::from),
             })
             .collect();
 
@@ -1147,21 +2648,27 @@ Respond with a JSON array of strings."#,
         // Vector embeddings capture semantic meaning, not just keyword matches
         let mut vector_symbols_with_scores = Vec::new();
         if let Some(store) = &self.vector_store {
-            match store.search_similar(user_prompt, 30).await {
+            match self.search_cache.search_similar(store, user_prompt, 30).await {
                 Ok(results) => {
                     info!("🔍 Vector search found {} semantically similar symbols", results.len());
                     for res in results {
+                        self.note(&res.symbol.name, &res.symbol.file_path, || {
+                            Provenance::new("vector search").query(user_prompt).vector_score(res.score)
+                        });
                         vector_symbols_with_scores.push((
                             res.score, // Semantic similarity score from vector search
                             SymbolInfo {
                                 name: res.symbol.name,
                                 kind: res.symbol.kind,
                                 content: res.symbol.content,
+                                language: language_for_path(&res.symbol.file_path).map(String::from),
                                 file_path: res.symbol.file_path,
                                 start_line: 0,
                                 end_line: 0,
                                 props: Vec::new(),
                                 references: Vec::new(),
+                                is_exported: false,
+                                documentation: None,
                             },
                         ));
                     }
@@ -1197,8 +2704,12 @@ Respond with a JSON array of strings."#,
         let types = self.collect_type_info(&gathered, 10);
         let constants = self.collect_constant_info(&gathered, 10);
         let schemas = self.collect_schema_info(&gathered, 8);
+        let routes = self.collect_route_info(user_prompt, keywords, 30);
+        let env_vars = self.collect_env_var_info(user_prompt, keywords, 40);
+        let symbol_names: Vec<String> = relevant_symbols.iter().map(|s| s.name.clone()).collect();
+        let tests = self.collect_test_info(user_prompt, &symbol_names, 3);
 
-        Ok(ContextData {
+        let mut context = ContextData {
             relevant_symbols,
             similar_symbols,
             design_tokens,
@@ -1206,7 +2717,47 @@ Respond with a JSON array of strings."#,
             types,
             constants,
             schemas,
-        })
+            routes,
+            env_vars,
+            tests,
+            external_references: Vec::new(),
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
+        };
+        self.limit_context_files(&mut context);
+        context.edit_target = self.find_edit_target(user_prompt, &context);
+        context.refactor_impact = self.find_refactor_impact(user_prompt);
+        context.linked_indexes = self.linked_origins(&context);
+        redact_context(&mut context);
+        Ok(context)
+    }
+
+    /// With `max_files` set, keep symbols, tests and routes from the first
+    /// `max_files` files in ranked order (semantic search can bring in files
+    /// `scope_gathered` never saw)
+    fn limit_context_files(&self, context: &mut ContextData) {
+        let Some(max_files) = self.max_files else { return };
+        let paths = context
+            .relevant_symbols
+            .iter()
+            .chain(&context.similar_symbols)
+            .map(|s| &s.file_path)
+            .chain(context.tests.iter().map(|t| &t.file_path))
+            .chain(context.routes.iter().map(|r| &r.file_path));
+        let mut keep = HashSet::new();
+        for path in paths.filter(|p| !p.is_empty()) {
+            if keep.len() < max_files {
+                keep.insert(path.clone());
+            }
+        }
+        let kept = |path: &String| path.is_empty() || keep.contains(path);
+        context.relevant_symbols.retain(|s| s.kind == "plan" || kept(&s.file_path));
+        context.similar_symbols.retain(|s| kept(&s.file_path));
+        context.tests.retain(|t| kept(&t.file_path));
+        context.routes.retain(|r| kept(&r.file_path));
     }
 
     fn collect_design_tokens(&self, gathered: &GatheredContext) -> Vec<DesignTokenInfo> {
@@ -1240,6 +2791,7 @@ Respond with a JSON array of strings."#,
                     name: item.name.clone(),
                     kind: item.kind.clone(),
                     definition: item.content.clone(),
+                    file_path: item.file_path.clone(),
                 });
 
                 if items.len() >= limit {
@@ -1282,6 +2834,7 @@ Respond with a JSON array of strings."#,
                     name: item.name.clone(),
                     schema_type: item.kind.clone(),
                     definition: item.content.clone(),
+                    file_path: item.file_path.clone(),
                 });
 
                 if items.len() >= limit {
@@ -1293,6 +2846,121 @@ Respond with a JSON array of strings."#,
         items
     }
 
+    /// Existing routes for endpoint/page tasks, keyword matches first; empty for other tasks
+    fn collect_route_info(&self, user_prompt: &str, keywords: &[String], limit: usize) -> Vec<RouteInfo> {
+        let prompt = user_prompt.to_lowercase();
+        let wants_routes = ["endpoint", "route", "page", "api", "handler", "controller"]
+            .iter()
+            .any(|k| prompt.contains(k) || keywords.iter().any(|kw| kw.to_lowercase().contains(k)));
+        if !wants_routes {
+            return Vec::new();
+        }
+
+        let mut routes = match self.graph.find_routes("") {
+            Ok(routes) => routes,
+            Err(e) => {
+                warn!("⚠️ Failed to load routes: {}", e);
+                return Vec::new();
+            }
+        };
+        routes.retain(|r| self.search_cache.scope().contains_path(&r.file_path));
+
+        let terms: Vec<String> = prompt
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| t.len() > 2)
+            .map(String::from)
+            .chain(keywords.iter().map(|k| k.to_lowercase()))
+            .collect();
+        let matches = |route: &miow_graph::RouteResult| {
+            let haystack = format!("{} {}", route.path, route.handler.as_deref().unwrap_or("")).to_lowercase();
+            terms.iter().any(|t| haystack.contains(t.as_str()))
+        };
+        // Stable sort keeps path order within each group
+        routes.sort_by_key(|r| !matches(r));
+
+        routes
+            .into_iter()
+            .take(limit)
+            .map(|r| RouteInfo {
+                method: r.method,
+                path: r.path,
+                handler: r.handler,
+                file_path: r.file_path,
+            })
+            .collect()
+    }
+
+    /// Existing tests for the top relevant symbols so new code can follow the project's
+    /// test style; twice as many when the task is about tests
+    fn collect_test_info(&self, user_prompt: &str, symbol_names: &[String], limit: usize) -> Vec<TestInfo> {
+        let prompt = user_prompt.to_lowercase();
+        let wants_tests = ["test", "spec", "coverage"].iter().any(|k| prompt.contains(k));
+        let limit = if wants_tests { limit * 2 } else { limit };
+
+        let mut seen = HashSet::new();
+        let mut tests = Vec::new();
+        for name in symbol_names.iter().take(5) {
+            let found = match self.graph.find_tests_for(name) {
+                Ok(found) => found,
+                Err(e) => {
+                    warn!("⚠️ Failed to load tests for {}: {}", name, e);
+                    continue;
+                }
+            };
+            for test in found.into_iter().filter(|t| self.search_cache.scope().contains_path(&t.file_path)) {
+                if tests.len() >= limit {
+                    return tests;
+                }
+                if seen.insert((test.file_path.clone(), test.start_line)) {
+                    tests.push(TestInfo {
+                        name: test.name,
+                        exercises: name.clone(),
+                        file_path: test.file_path,
+                        content: test.content,
+                    });
+                }
+            }
+        }
+        tests
+    }
+
+    /// Environment variables the project reads, for configuration/deployment tasks; empty otherwise
+    fn collect_env_var_info(&self, user_prompt: &str, keywords: &[String], limit: usize) -> Vec<EnvVarInfo> {
+        let prompt = user_prompt.to_lowercase();
+        let wants_env = ["env", "config", "deploy", "secret", "setting", "credential", "docker", "api key"]
+            .iter()
+            .any(|k| prompt.contains(k) || keywords.iter().any(|kw| kw.to_lowercase().contains(k)));
+        if !wants_env {
+            return Vec::new();
+        }
+
+        let reads = match self.graph.find_env_vars("") {
+            Ok(reads) => reads,
+            Err(e) => {
+                warn!("⚠️ Failed to load environment variables: {}", e);
+                return Vec::new();
+            }
+        };
+
+        // Reads come ordered by name, so each variable's files are contiguous
+        let mut env_vars: Vec<EnvVarInfo> = Vec::new();
+        for read in reads.into_iter().filter(|r| self.search_cache.scope().contains_path(&r.file_path)) {
+            match env_vars.last_mut() {
+                Some(last) if last.name == read.name => {
+                    if !last.file_paths.contains(&read.file_path) && last.file_paths.len() < 3 {
+                        last.file_paths.push(read.file_path);
+                    }
+                }
+                _ => env_vars.push(EnvVarInfo {
+                    name: read.name,
+                    file_paths: vec![read.file_path],
+                }),
+            }
+        }
+        env_vars.truncate(limit);
+        env_vars
+    }
+
     /// Rank symbols with vector search results getting priority
     /// Vector results already have semantic similarity scores, so we boost those
     fn rank_symbols_with_vector_priority(
@@ -1495,7 +3163,7 @@ Respond with a JSON array of strings."#,
         llm: &dyn LLMProvider,
         user_prompt: &str,
         context: &ContextData,
-        intent: &str,
+        intent: Intent,
     ) -> Result<String> {
         let system_prompt = r#"You are an expert software engineer. Analyze the user's request and the provided context from the codebase.
 Generate a detailed, step-by-step implementation plan that:
@@ -1537,16 +3205,16 @@ Format the plan as a numbered list with clear steps. Be specific about what to r
     }
 
     /// Generate basic implementation plan without LLM
-    fn generate_basic_implementation_plan(&self, context: &ContextData, intent: &str) -> String {
+    fn generate_basic_implementation_plan(&self, context: &ContextData, intent: Intent) -> String {
         let mut plan = String::from("## Implementation Plan\n\n");
 
-        if intent.contains("Component") {
+        if intent == Intent::CreateComponent {
             plan.push_str("1. Review similar existing components for patterns\n");
             plan.push_str("2. Identify reusable sub-components\n");
             plan.push_str("3. Use existing design tokens for styling\n");
             plan.push_str("4. Follow the same prop patterns as similar components\n");
             plan.push_str("5. Add proper TypeScript types\n");
-        } else if intent.contains("Page") {
+        } else if intent == Intent::CreatePage {
             plan.push_str("1. Reuse existing layout components\n");
             plan.push_str("2. Follow the same page structure pattern\n");
             plan.push_str("3. Use existing components for UI elements\n");
@@ -1569,7 +3237,6 @@ Format the plan as a numbered list with clear steps. Be specific about what to r
 
     /// Extract common imports from gathered context
     fn extract_common_imports(&self, gathered: &GatheredContext) -> Vec<String> {
-        use std::collections::HashMap;
 
         let mut import_counts: HashMap<String, usize> = HashMap::new();
 
@@ -1583,7 +3250,7 @@ Format the plan as a numbered list with clear steps. Be specific about what to r
 
         // Get top 10 most common imports
         let mut imports: Vec<(String, usize)> = import_counts.into_iter().collect();
-        imports.sort_by(|a, b| b.1.cmp(&a.1));
+        imports.sort_by_key(|import| std::cmp::Reverse(import.1));
         imports.into_iter().take(10).map(|(path, _)| path).collect()
     }
 
@@ -1624,11 +3291,16 @@ Format the plan as a numbered list with clear steps. Be specific about what to r
         use futures::future::join_all;
 
         let registry = Arc::new(PromptRegistry::new());
+        let permits = Arc::new(tokio::sync::Semaphore::new(self.worker_concurrency));
 
         // Create tasks for all workers
         let mut tasks = Vec::new();
 
-        for worker_id in &plan.execution_plan {
+        let limit = self.pipeline.max_workers.unwrap_or(usize::MAX);
+        if plan.execution_plan.len() > limit {
+            info!("✂️  Running {} of {} planned workers (max_workers)", limit, plan.execution_plan.len());
+        }
+        for worker_id in plan.execution_plan.iter().take(limit) {
             if let Some(worker_plan) = plan.workers.iter().find(|w| w.worker_id == *worker_id) {
                 let worker_id_clone = worker_id.clone();
                 let worker_plan_clone = worker_plan.clone();
@@ -1639,7 +3311,16 @@ Format the plan as a numbered list with clear steps. Be specific about what to r
 
                 info!("🔧 Queueing worker for parallel execution: {}", worker_id);
 
-                let task = tokio::spawn(async move {
+                let permits = permits.clone();
+                let cancel = self.cancel.clone();
+                // Aborted if this run is dropped, e.g. when its client disconnects
+                let task = tokio_util::task::AbortOnDropHandle::new(tokio::spawn(async move {
+                    // Wait for a free slot; the semaphore is never closed
+                    let _permit = tokio::select! {
+                        _ = cancel.cancelled() => return None,
+                        permit = permits.acquire_owned() => permit.ok()?,
+                    };
+
                     // Create a new worker agent for this task
                     let worker_agent = GeminiWorkerAgent::new(llm_clone.clone(), registry_clone);
 
@@ -1654,12 +3335,20 @@ Format the plan as a numbered list with clear steps. Be specific about what to r
                     info!("🚀 [WORKER {}] Starting execution...", worker_id_clone);
                     let start = std::time::Instant::now();
 
-                    match worker_agent.execute(
+                    let execution = worker_agent.execute(
                         &worker_plan_clone.worker_id,
                         &prompt_clone,
                         &sig_clone,
                         &search_queries,
-                    ).await {
+                    );
+                    let outcome = tokio::select! {
+                        _ = cancel.cancelled() => {
+                            info!("🛑 [WORKER {}] Cancelled", worker_id_clone);
+                            return None;
+                        }
+                        outcome = execution => outcome,
+                    };
+                    match outcome {
                         Ok(result) => {
                             let duration = start.elapsed();
                             info!("✅ [WORKER {}] Completed in {:?}: {} chunks, confidence: {:.2}",
@@ -1672,14 +3361,14 @@ Format the plan as a numbered list with clear steps. Be specific about what to r
                             None
                         }
                     }
-                });
+                }));
 
                 tasks.push(task);
             }
         }
 
-        // Execute all workers in parallel (with concurrency limit via join_all)
-        info!("🔄 Executing {} workers in parallel...", tasks.len());
+        // Execute workers in parallel, at most `worker_concurrency` at a time
+        info!("🔄 Executing {} workers, {} at a time...", tasks.len(), self.worker_concurrency);
         let results: Vec<_> = join_all(tasks).await;
 
         // Collect successful results, maintaining order
@@ -1728,7 +3417,7 @@ Format the plan as a numbered list with clear steps. Be specific about what to r
         user_prompt: &str,
         project_signature: &miow_core::ProjectSignature,
     ) -> miow_llm::GatheredContext {
-        let mut master_context = base_context.clone();
+        let master_context = base_context.clone();
 
         // If no workers were executed, return the base context
         if worker_results.is_empty() {
@@ -1800,7 +3489,7 @@ Respond with JSON containing prioritized context items from all workers."#,
             },
         ];
 
-        let response = llm.generate_with_context(messages).await?;
+        llm.generate_with_context(messages).await?;
 
         // For now, return the base context enhanced with worker results
         // In a full implementation, parse the LLM response to selectively include items
@@ -1846,6 +3535,8 @@ Respond with JSON containing prioritized context items from all workers."#,
                         relevance_score: worker_result.confidence,
                         props: Vec::new(),
                         references: Vec::new(),
+                        is_exported: false,
+                        documentation: None,
                     };
 
                     // Categorize based on content type
@@ -1870,14 +3561,14 @@ Respond with JSON containing prioritized context items from all workers."#,
     async fn gather_smart_context(
         &self,
         user_prompt: &str,
-        intent: &str,
+        intent: Intent,
         router_plan: Option<&miow_agent::SearchPlan>,
     ) -> Result<ContextData> {
         info!("Gathering smart context with LLM selection");
 
         // Step 1: Raw context gathering (existing logic)
         let raw_context = self
-            .gather_comprehensive_context(user_prompt, &[], "", None)
+            .gather_comprehensive_context(user_prompt, &[], intent, None, None)
             .await?;
 
         // Convert GatheredContext to ContextData
@@ -1891,6 +3582,9 @@ Respond with JSON containing prioritized context items from all workers."#,
                 end_line: 0,
                 props: item.props.clone(),
                 references: item.references.clone(),
+                is_exported: item.is_exported,
+                documentation: item.documentation.clone(),
+                language: language_for_path(&item.file_path).map(String::from),
             })
             .collect(),
             similar_symbols: raw_context.helpers.iter().map(|item| SymbolInfo {
@@ -1902,12 +3596,16 @@ Respond with JSON containing prioritized context items from all workers."#,
                 end_line: 0,
                 props: item.props.clone(),
                 references: item.references.clone(),
+                is_exported: item.is_exported,
+                documentation: item.documentation.clone(),
+                language: language_for_path(&item.file_path).map(String::from),
             })
             .collect(),
             types: raw_context.types.iter().map(|item| TypeInfo {
                 name: item.name.clone(),
                 kind: item.kind.clone(),
                 definition: item.content.clone(),
+                file_path: item.file_path.clone(),
             }).collect(),
             constants: raw_context.constants.iter().map(|item| ConstantInfo {
                 name: item.name.clone(),
@@ -1918,14 +3616,29 @@ Respond with JSON containing prioritized context items from all workers."#,
                 name: item.name.clone(),
                 schema_type: item.kind.clone(),
                 definition: item.content.clone(),
+                file_path: item.file_path.clone(),
             }).collect(),
             design_tokens: raw_context.design_tokens.iter().map(|item| DesignTokenInfo {
                 name: item.name.clone(),
                 value: item.content.clone(),
                 token_type: item.kind.clone(),
             }).collect(),
+            routes: self.collect_route_info(user_prompt, &[], 30),
+            env_vars: self.collect_env_var_info(user_prompt, &[], 40),
+            tests: self.collect_test_info(
+                user_prompt,
+                &raw_context.components.iter().map(|c| c.name.clone()).collect::<Vec<_>>(),
+                3,
+            ),
+            external_references: vec![],
             common_imports: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
         };
+        redact_context(&mut context_data);
 
         // Step 2: LLM-powered context selection if available
         if let Some(llm) = self.llm.as_ref() {
@@ -1995,7 +3708,7 @@ Respond with JSON containing prioritized context items from all workers."#,
 
         // Parse response for variants (simple split for now; could use regex/JSON)
         let parts: Vec<&str> = cleaned_content.split("---").collect();
-        let raw_system = if parts.len() > 0 { parts[0].trim().to_string() } else { raw_prompt.to_string() };
+        let raw_system = if !parts.is_empty() { parts[0].trim().to_string() } else { raw_prompt.to_string() };
         let cleaned_llm = if parts.len() > 1 { parts[1].trim().to_string() } else { cleaned_content };
 
         Ok((raw_system, cleaned_llm))
@@ -2005,6 +3718,7 @@ Respond with JSON containing prioritized context items from all workers."#,
     #[allow(dead_code)]
     async fn generate_enhanced_prompt_autonomous(&self, user_prompt: &str, project_root: &std::path::Path) -> Result<(String, String)> {
         info!("Starting autonomous multi-step agent workflow for '{}'", user_prompt);
+        self.search_cache.clear();
 
         // Step 1: Detect project signature
         let signature = miow_core::ProjectSignature::detect(project_root)?;
@@ -2045,8 +3759,19 @@ Respond with JSON containing prioritized context items from all workers."#,
             user_prompt, signature.language, signature.framework
         );
         let llm = self.llm.as_ref().ok_or_else(|| anyhow::anyhow!("LLM not available"))?;
-        let plan_response = llm.generate(&plan_prompt).await?;
-        Ok(plan_response.content)
+
+        // JSON output is flaky on a single sample; vote across the candidates that parse,
+        // and keep the first raw answer when none does
+        let responses = llm.generate_n(&plan_prompt, self.pipeline.plan_samples.max(1)).await?;
+        let candidates: Vec<serde_json::Value> =
+            responses.iter().filter_map(|r| miow_llm::extract_json(&r.content)).collect();
+        match miow_llm::most_consistent(&candidates) {
+            Some(index) => Ok(serde_json::to_string_pretty(&candidates[index])?),
+            None => {
+                warn!("⚠️  No plan candidate returned valid JSON, using the first as written");
+                Ok(responses.into_iter().next().map(|r| r.content).unwrap_or_default())
+            }
+        }
     }
 
     // Autonomous context gathering based on plan
@@ -2080,7 +3805,7 @@ Respond with JSON containing prioritized context items from all workers."#,
         if !symbols.is_empty() {
             prompt += "\n\n## AUTONOMOUSLY SELECTED CONTEXT\n";
             for symbol in symbols {
-                prompt += &format!("#### `{}` ({})\n**File**: `{}`\n```tsx\n{}\n```\n", symbol.name, symbol.kind, symbol.file_path, symbol.content);
+                prompt += &format!("#### `{}` ({})\n**File**: `{}`\n{}", symbol.name, symbol.kind, symbol.file_path, code_block(symbol.language(), &symbol.content));
             }
         }
 
@@ -2110,8 +3835,8 @@ Respond with JSON containing prioritized context items from all workers."#,
         if !context.relevant_symbols.is_empty() {
             prompt += "\n\n## SELECTED CONTEXT (Smart Selection)\n";
             for symbol in &context.relevant_symbols {
-                prompt += &format!("#### `{}` ({})\n**File**: `{}`\n```tsx\n{}\n```\n", 
-                    symbol.name, symbol.kind, symbol.file_path, symbol.content);
+                prompt += &format!("#### `{}` ({})\n**File**: `{}`\n{}",
+                    symbol.name, symbol.kind, symbol.file_path, code_block(symbol.language(), &symbol.content));
             }
         }
 
@@ -2135,7 +3860,7 @@ Respond with JSON containing prioritized context items from all workers."#,
         info!("📁 Getting relevant files for prompt: {}", user_prompt);
         
         // Detect project signature
-        let _project_signature = self.load_or_detect_signature(project_root)?;
+        let project_signature = self.load_or_detect_signature(project_root)?;
         
         // Analyze prompt
         let analyzed = self.analyzer.analyze_prompt(user_prompt);
@@ -2145,8 +3870,9 @@ Respond with JSON containing prioritized context items from all workers."#,
         let gathered = self.gather_comprehensive_context(
             user_prompt,
             &search_queries,
-            &format!("{:?}", analyzed.intent),
+            analyzed.intent,
             None,
+            Some(&project_signature),
         ).await?;
         
         // Combine all context items
@@ -2187,6 +3913,7 @@ Respond with JSON containing prioritized context items from all workers."#,
         selected_files: &[String],
     ) -> Result<String> {
         info!("🚀 Generating prompt with {} selected files", selected_files.len());
+        self.search_cache.clear();
         
         // Load project signature
         let project_signature = self.load_or_detect_signature(project_root)?;
@@ -2217,11 +3944,14 @@ Respond with JSON containing prioritized context items from all workers."#,
                         name: symbol.name,
                         kind: symbol.kind,
                         content: symbol.content,
+                        language: language_for_path(&symbol.file_path).map(String::from),
                         file_path: symbol.file_path,
-                        start_line: symbol.start_line as i64,
-                        end_line: symbol.end_line as i64,
+                        start_line: symbol.start_line,
+                        end_line: symbol.end_line,
                         props,
                         references,
+                        is_exported: is_exported_metadata(symbol.metadata.as_deref()),
+                        documentation: documentation_from_metadata(symbol.metadata.as_deref()),
                     });
                 }
             }
@@ -2230,7 +3960,7 @@ Respond with JSON containing prioritized context items from all workers."#,
         info!("📦 Loaded {} symbols from selected files", selected_symbols.len());
         
         // Build context data with selected files
-        let context_data = ContextData {
+        let mut context_data = ContextData {
             relevant_symbols: selected_symbols,
             similar_symbols: vec![],
             design_tokens: vec![],
@@ -2238,7 +3968,21 @@ Respond with JSON containing prioritized context items from all workers."#,
             types: vec![],
             constants: vec![],
             schemas: vec![],
+            routes: vec![],
+            env_vars: vec![],
+            tests: vec![],
+            external_references: vec![],
+            edit_target: None,
+            refactor_impact: None,
+            owned_elsewhere: Vec::new(),
+            components: Vec::new(),
+            linked_indexes: Vec::new(),
         };
+        context_data.edit_target = self.find_edit_target(user_prompt, &context_data);
+        context_data.refactor_impact = self.find_refactor_impact(user_prompt);
+        context_data.owned_elsewhere = self.find_owned_elsewhere(&context_data, project_root);
+        context_data.components = self.find_component_catalog(user_prompt, &context_data);
+        redact_context(&mut context_data);
         
         // Generate meta-prompt
         let config = miow_prompt::MetaPromptConfig {
@@ -2246,15 +3990,19 @@ Respond with JSON containing prioritized context items from all workers."#,
             include_style_guide: true,
             include_implementation_plan: true,
             max_examples_per_type: 5,
-            token_budget: Some(16000),
+            token_budget: Some(self.pipeline.context_tokens),
+            format: self.prompt_format,
+            diff: self.diff_prompts,
+            ..Default::default()
         };
         
         let project_info = project_signature.to_description();
-        let prompt = miow_prompt::MetaPromptGenerator::generate(
+        let prompt = miow_prompt::MetaPromptGenerator::generate_with_templates(
             user_prompt,
             &context_data,
             Some(&project_info),
             config,
+            &self.templates_for(project_root),
         )?;
         
         info!("✅ Generated prompt with selected files");
@@ -2262,9 +4010,319 @@ Respond with JSON containing prioritized context items from all workers."#,
     }
 }
 
+/// Whether serialized symbol metadata marks the symbol as exported
+fn is_exported_metadata(metadata: Option<&str>) -> bool {
+    metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .and_then(|m| m.get("is_exported").and_then(|e| e.as_bool()))
+        .unwrap_or(false)
+}
+
+/// `name: type` of each prop recorded in serialized component metadata
+fn props_from_metadata(metadata: Option<&str>) -> Vec<String> {
+    let Some(meta) = metadata.and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok()) else {
+        return Vec::new();
+    };
+    meta.get("props")
+        .and_then(|p| p.as_array())
+        .into_iter()
+        .flatten()
+        .map(|p| {
+            let name = p.get("name").and_then(|s| s.as_str()).unwrap_or("?");
+            let type_ann = p.get("type_annotation").and_then(|s| s.as_str()).unwrap_or("any");
+            format!("{}: {}", name, type_ann)
+        })
+        .collect()
+}
+
+/// The doc comment / docstring recorded in serialized symbol metadata
+fn documentation_from_metadata(metadata: Option<&str>) -> Option<String> {
+    metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .and_then(|m| m.get("documentation").and_then(|d| d.as_str()).map(String::from))
+        .filter(|d| !d.trim().is_empty())
+}
+
+/// A graph symbol as a prompt snippet
+fn symbol_info_from_result(symbol: SymbolSearchResult) -> SymbolInfo {
+    SymbolInfo {
+        language: language_for_path(&symbol.file_path).map(String::from),
+        is_exported: is_exported_metadata(symbol.metadata.as_deref()),
+        documentation: documentation_from_metadata(symbol.metadata.as_deref()),
+        name: symbol.name,
+        kind: symbol.kind,
+        content: symbol.content,
+        file_path: symbol.file_path,
+        start_line: symbol.start_line,
+        end_line: symbol.end_line,
+        props: Vec::new(),
+        references: Vec::new(),
+    }
+}
+
+/// A gathered item as a prompt snippet
+fn symbol_info_from_item(item: ContextItem) -> SymbolInfo {
+    SymbolInfo {
+        language: language_for_path(&item.file_path).map(String::from),
+        name: item.name,
+        kind: item.kind,
+        content: item.content,
+        file_path: item.file_path,
+        start_line: 0,
+        end_line: 0,
+        props: item.props,
+        references: item.references,
+        is_exported: item.is_exported,
+        documentation: item.documentation,
+    }
+}
+
+/// Words of a task that could name a symbol, code-looking identifiers
+/// (`parseConfig`, `parse_config`, `Parser`) first
+fn edit_target_candidates(task: &str) -> Vec<String> {
+    let mut words: Vec<&str> = task
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| w.len() >= 3)
+        .collect();
+    let mut seen = HashSet::new();
+    words.retain(|w| seen.insert(*w));
+    let looks_like_code = |w: &str| w.contains('_') || w.chars().skip(1).any(char::is_uppercase);
+    words.sort_by_key(|w| !looks_like_code(w));
+    words.into_iter().map(String::from).collect()
+}
+
+/// Tell whoever follows the run's events that it moved on to `phase`
+async fn send_phase(event_tx: &Option<tokio::sync::mpsc::Sender<miow_agent::autonomous::AgentEvent>>, phase: &str) {
+    if let Some(tx) = event_tx {
+        let _ = tx.send(miow_agent::autonomous::AgentEvent::Phase { phase: phase.to_string() }).await;
+    }
+}
+
+/// Symbols each linked index adds per keyword in the autonomous workflow
+const MAX_LINKED_AGENT_ITEMS: usize = 3;
+
+/// Most relevant gathered symbols that similar implementations are compared with
+const MAX_SIMILARITY_SEEDS: usize = 3;
+
+/// Similar implementations included in a prompt at most
+const MAX_SIMILAR_IMPLEMENTATIONS: usize = 5;
+
+/// Share of a similar implementation's score from structure; the rest is embedding distance
+const STRUCTURAL_WEIGHT: f32 = 0.7;
+
+/// Components listed in a prompt's catalog table at most
+const MAX_CATALOG_COMPONENTS: usize = 20;
+
+/// Intent keywords and the decorator/attribute that marks matching symbols
+const DECORATOR_HINTS: &[(&str, &str)] = &[
+    ("service", "Injectable"),
+    ("guard", "Injectable"),
+    ("controller", "Controller"),
+    ("module", "Module"),
+    ("entity", "Entity"),
+    ("resolver", "Resolver"),
+    ("directive", "Directive"),
+    ("pipe", "Pipe"),
+    ("serializ", "Serialize"),
+    ("entrypoint", "::main"),
+];
+
+/// Words a template question uses for a kind of symbol, and the graph kinds that
+/// answer it; the first word found in a question decides what it expects
+const QUESTION_KINDS: &[(&str, &[&str])] = &[
+    ("hook", &["function", "hook"]),
+    ("component", &["component"]),
+    ("schema", &["schema"]),
+    ("struct", &["struct", "enum"]),
+    ("trait", &["trait"]),
+    ("controller", &["class"]),
+    ("service", &["class"]),
+    ("class", &["class", "interface"]),
+    ("type", &["type", "interface", "enum"]),
+    ("page", &["component", "function"]),
+    ("function", &["function", "method"]),
+];
+
+/// The project signature's template questions, each expecting the kind of symbol it names
+fn template_questions(signature: &ProjectSignature) -> Vec<miow_llm::CriticalQuestion> {
+    signature
+        .get_question_templates()
+        .into_iter()
+        .enumerate()
+        .map(|(i, question)| {
+            let lower = question.to_lowercase();
+            let expected = QUESTION_KINDS.iter().map(|(word, _)| *word).find(|word| lower.contains(word));
+            let search_query = match expected {
+                Some(word) => word.to_string(),
+                None => question.split_whitespace().last().unwrap_or("").trim_end_matches('?').to_string(),
+            };
+            miow_llm::CriticalQuestion {
+                search_query,
+                expected_type: expected.unwrap_or("unknown").to_string(),
+                question,
+                priority: if i == 0 {
+                    miow_llm::Priority::Critical
+                } else {
+                    miow_llm::Priority::High
+                },
+            }
+        })
+        .collect()
+}
+
+/// Whether `symbol` is the kind of symbol a question expecting `expected_type` asks about
+fn answers_question(expected_type: &str, symbol: &SymbolSearchResult) -> bool {
+    let kind = symbol.kind.to_lowercase();
+    let pascal_case = symbol.name.chars().next().is_some_and(|c| c.is_uppercase());
+    match expected_type {
+        "hook" => symbol
+            .name
+            .strip_prefix("use")
+            .and_then(|rest| rest.chars().next())
+            .is_some_and(|c| c.is_uppercase()),
+        // JSX components are often indexed as PascalCase functions
+        "component" => kind.contains("component") || (kind.contains("function") && pascal_case),
+        expected => match QUESTION_KINDS.iter().find(|(word, _)| *word == expected) {
+            Some((_, kinds)) => kinds.iter().any(|k| kind.contains(k)),
+            None => true,
+        },
+    }
+}
+
+/// Reciprocal rank fusion constant; keeps one list's top hit from outweighing
+/// agreement across lists
+const RRF_K: f32 = 60.0;
+
+/// Fuse ranked lists: each key scores `1 / (RRF_K + rank)` in every list it is in.
+/// Keys come back best first, scaled so the best scores 1.0; ties keep the order
+/// keys were first seen in.
+fn reciprocal_rank_fusion<K: Clone + Eq + std::hash::Hash>(lists: &[Vec<K>]) -> Vec<(K, f32)> {
+    let mut fused: Vec<(K, f32)> = Vec::new();
+    let mut index: HashMap<K, usize> = HashMap::new();
+    for list in lists {
+        let mut seen = HashSet::new();
+        for (rank, key) in list.iter().enumerate() {
+            // A list counts an item once, at its best rank
+            if !seen.insert(key) {
+                continue;
+            }
+            let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+            match index.get(key) {
+                Some(&i) => fused[i].1 += score,
+                None => {
+                    index.insert(key.clone(), fused.len());
+                    fused.push((key.clone(), score));
+                }
+            }
+        }
+    }
+    fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    let best = fused.first().map_or(1.0, |(_, score)| *score);
+    for (_, score) in &mut fused {
+        *score /= best;
+    }
+    fused
+}
+
+/// A relevance boost for gathered items that fit how the project is built.
+/// Every non-empty hint list must match; any hint in a list will do.
+#[derive(Debug, Clone, PartialEq)]
+struct BoostRule {
+    kinds: Vec<String>,
+    path_hints: Vec<String>,
+    name_hints: Vec<String>,
+    weight: f32,
+}
+
+impl BoostRule {
+    fn new(weight: f32) -> Self {
+        Self { kinds: Vec::new(), path_hints: Vec::new(), name_hints: Vec::new(), weight }
+    }
+
+    fn kinds(mut self, kinds: &[&str]) -> Self {
+        self.kinds = kinds.iter().map(|k| k.to_string()).collect();
+        self
+    }
+
+    fn paths(mut self, hints: &[&str]) -> Self {
+        self.path_hints = hints.iter().map(|h| h.to_string()).collect();
+        self
+    }
+
+    fn names(mut self, hints: &[&str]) -> Self {
+        self.name_hints = hints.iter().map(|h| h.to_lowercase()).collect();
+        self
+    }
+
+    /// The rules for a detected project: its UI library's shared components,
+    /// its validation schemas, its auth code and its framework's building blocks
+    fn for_signature(signature: &ProjectSignature) -> Vec<BoostRule> {
+        let mut rules = Vec::new();
+        if let Some(ui_library) = &signature.ui_library {
+            rules.push(
+                BoostRule::new(0.15)
+                    .kinds(&["component", "function"])
+                    .paths(&["components/ui", "/ui/", "design-system", "primitives"]),
+            );
+            // Detection without a known package names the component it found in use
+            if let Some(component) = ui_library
+                .strip_prefix("Custom UI (uses ")
+                .and_then(|rest| rest.strip_suffix(')'))
+            {
+                rules.push(BoostRule::new(0.15).kinds(&["component", "function"]).names(&[component]));
+            }
+        }
+        if signature.validation_library.is_some() {
+            rules.push(BoostRule::new(0.1).names(&["schema", "validat"]));
+        }
+        if signature.auth_library.is_some() {
+            rules.push(BoostRule::new(0.1).paths(&["auth"]));
+        }
+        let framework = signature.framework.to_lowercase();
+        if framework.contains("react") || framework.contains("next") {
+            rules.push(BoostRule::new(0.05).kinds(&["component", "hook"]));
+        } else if framework.contains("nest") {
+            rules.push(BoostRule::new(0.05).names(&["service", "controller", "module"]));
+        } else if framework.contains("rust") {
+            rules.push(BoostRule::new(0.05).kinds(&["struct", "trait", "enum"]));
+        }
+        rules
+    }
+
+    fn matches(&self, item: &ContextItem) -> bool {
+        let kind = item.kind.to_lowercase();
+        let name = item.name.to_lowercase();
+        (self.kinds.is_empty() || self.kinds.iter().any(|k| kind.contains(k.as_str())))
+            && (self.path_hints.is_empty() || self.path_hints.iter().any(|h| item.file_path.contains(h.as_str())))
+            && (self.name_hints.is_empty() || self.name_hints.iter().any(|h| name.contains(h.as_str())))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use miow_llm::LLMResponse;
+
+    /// Index one function per file, as `(path, name, source)`
+    fn index_functions(orchestrator: &MiowOrchestrator, functions: &[(&str, &str, &str)]) {
+        let files: Vec<(String, miow_graph::ParsedFileData)> = functions
+            .iter()
+            .map(|(path, name, content)| {
+                let file = serde_json::from_value(serde_json::json!({
+                    "symbols": [{
+                        "name": name, "kind": "function", "start_line": 1, "end_line": 3, "start_byte": 0,
+                        "end_byte": content.len(), "content": content, "metadata": "{}", "style_tags": null,
+                        "children": [], "references": []
+                    }],
+                    "imports": [], "design_tokens": [], "type_definitions": [], "constants": [], "schemas": [],
+                    "language": "typescript"
+                }))
+                .unwrap();
+                (path.to_string(), file)
+            })
+            .collect();
+        orchestrator.graph.insert_files(&files).unwrap();
+    }
 
     #[tokio::test]
     async fn test_gather_comprehensive_context_structure() {
@@ -2288,7 +4346,8 @@ mod tests {
         let result = orchestrator.gather_comprehensive_context(
             "test prompt",
             &["query".to_string()],
-            "test_intent",
+            Intent::Unknown,
+            None,
             None
         ).await;
 
@@ -2296,4 +4355,291 @@ mod tests {
         let context = result.unwrap();
         assert!(context.components.is_empty());
     }
+
+    #[test]
+    fn test_rrf_rewards_agreement_across_queries() {
+        let lists = vec![
+            vec!["Button", "LoginForm", "useAuth"],
+            vec!["LoginForm", "validateEmail"],
+            vec!["validateEmail", "LoginForm", "LoginForm"],
+        ];
+        let fused = reciprocal_rank_fusion(&lists);
+        let order: Vec<&str> = fused.iter().map(|(key, _)| *key).collect();
+        assert_eq!(order, vec!["LoginForm", "validateEmail", "Button", "useAuth"]);
+        assert_eq!(fused[0].1, 1.0);
+        assert!(fused.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert!(reciprocal_rank_fusion::<&str>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_boost_rules_follow_the_project_signature() {
+        let item = |name: &str, kind: &str, path: &str| ContextItem {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            content: String::new(),
+            file_path: path.to_string(),
+            relevance_score: 0.0,
+            props: vec![],
+            references: vec![],
+            is_exported: true,
+            documentation: None,
+        };
+        let boost = |rules: &[BoostRule], item: &ContextItem| -> f32 {
+            rules.iter().filter(|rule| rule.matches(item)).map(|rule| rule.weight).sum()
+        };
+
+        let signature = ProjectSignature {
+            framework: "Vite + React".to_string(),
+            ui_library: Some("Custom UI (uses InputBox)".to_string()),
+            ..Default::default()
+        };
+        let rules = BoostRule::for_signature(&signature);
+        let input = item("InputBox", "Component", "src/forms/InputBox.tsx");
+        let button = item("Button", "Component", "src/components/ui/Button.tsx");
+        let helper = item("formatDate", "Function", "src/utils/date.ts");
+        assert!(boost(&rules, &input) > boost(&rules, &helper));
+        assert!(boost(&rules, &button) > boost(&rules, &helper));
+        assert_eq!(boost(&rules, &helper), 0.0);
+
+        // Nothing detected, nothing boosted: no names are special on their own
+        assert!(BoostRule::for_signature(&ProjectSignature::default()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_no_llm_pipeline_answers_template_questions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let signature = ProjectSignature {
+            language: "typescript".to_string(),
+            framework: "React".to_string(),
+            ..Default::default()
+        };
+        std::fs::write(root.join(".miow_cache.json"), serde_json::to_string(&signature).unwrap()).unwrap();
+
+        let orchestrator = MiowOrchestrator::new(root.join("test.db").to_str().unwrap()).unwrap();
+        index_functions(
+            &orchestrator,
+            &[
+                ("src/LoginForm.tsx", "LoginForm", "function LoginForm() { return <form /> }"),
+                ("src/useLogin.ts", "useLogin", "function useLogin() { return null }"),
+                ("src/duration.ts", "parseDuration", "function parseDuration(s) { return s }"),
+            ],
+        );
+
+        let questions = template_questions(&signature);
+        let hooks = questions.iter().find(|q| q.question.contains("hooks")).unwrap();
+        assert_eq!(hooks.expected_type, "hook");
+
+        let answers = orchestrator.answer_questions_by_search(&questions, &["login".to_string()]).await;
+        let names = |question: &str| -> Vec<String> {
+            answers
+                .iter()
+                .filter(|a| a.question == question)
+                .flat_map(|a| a.symbols.iter().map(|s| s.name.clone()))
+                .collect()
+        };
+        assert_eq!(names(&hooks.question), vec!["useLogin".to_string()]);
+        assert_eq!(names("What React components are used for UI?"), vec!["LoginForm".to_string()]);
+
+        // Without an LLM the autonomous entry point falls back to the same pipeline, every time alike
+        let root_str = root.to_str().unwrap();
+        let prompt = orchestrator.generate_autonomous_prompt(root_str, "add a remember-me box to the login form", None).await.unwrap();
+        assert!(prompt.contains("LoginForm") && prompt.contains("useLogin"));
+        assert!(!prompt.contains("parseDuration"));
+        assert!(prompt.contains("IMPLEMENTATION PLAN"));
+        let again = orchestrator.generate_autonomous_prompt(root_str, "add a remember-me box to the login form", None).await.unwrap();
+        assert_eq!(prompt, again);
+    }
+
+    #[tokio::test]
+    async fn test_near_duplicate_task_reuses_cached_context() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let orchestrator = MiowOrchestrator::new(root.join("test.db").to_str().unwrap())
+            .unwrap()
+            .with_context_cache(ContextCache::new())
+            .with_explain();
+        index_functions(&orchestrator, &[("src/LoginForm.tsx", "LoginForm", "function LoginForm() { return <form /> }")]);
+        let root_str = root.to_str().unwrap();
+        let from_cache = |orchestrator: &MiowOrchestrator| {
+            orchestrator.explanation().unwrap().iter().any(|item| item.provenance.found_by == "context cache")
+        };
+
+        orchestrator.generate_autonomous_prompt(root_str, "Add a remember-me box to the login form", None).await.unwrap();
+        assert!(!from_cache(&orchestrator));
+
+        let prompt = orchestrator.generate_autonomous_prompt(root_str, "add the remember-me box to login form", None).await.unwrap();
+        assert!(from_cache(&orchestrator));
+        assert!(prompt.contains("add the remember-me box to login form") && prompt.contains("LoginForm"));
+
+        // Any write to the index makes the cached context stale
+        index_functions(&orchestrator, &[("src/LoginPage.tsx", "LoginPage", "function LoginPage() { return <main /> }")]);
+        orchestrator.generate_autonomous_prompt(root_str, "Add a remember-me box to the login form", None).await.unwrap();
+        assert!(!from_cache(&orchestrator));
+    }
+
+    #[tokio::test]
+    async fn test_feedback_reranks_gathered_symbols() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let orchestrator = MiowOrchestrator::new(root.join("test.db").to_str().unwrap()).unwrap();
+        index_functions(
+            &orchestrator,
+            &[
+                ("src/LoginForm.tsx", "LoginForm", "function LoginForm() { return <form /> }"),
+                ("src/LoginPage.tsx", "LoginPage", "function LoginPage() { return <main /> }"),
+            ],
+        );
+        async fn gather(orchestrator: &MiowOrchestrator) -> Vec<ContextItem> {
+            orchestrator.search_cache.clear();
+            let gathered = orchestrator
+                .gather_comprehensive_context("", &["login".to_string()], Intent::Unknown, None, None)
+                .await
+                .unwrap();
+            gathered.components
+        }
+        async fn components(orchestrator: &MiowOrchestrator) -> Vec<String> {
+            gather(orchestrator).await.into_iter().map(|c| c.name).collect()
+        }
+        assert_eq!(components(&orchestrator).await, vec!["LoginForm", "LoginPage"]);
+
+        orchestrator.graph.record_feedback("src/LoginForm.tsx", "LoginForm", false).unwrap();
+        orchestrator.graph.record_feedback("src/LoginPage.tsx", "", true).unwrap();
+        assert_eq!(components(&orchestrator).await, vec!["LoginPage", "LoginForm"]);
+
+        // A body bigger than the budget is sliced to its signature rather than crowding out
+        // the small items ranked behind it, and comes back whole when the budget allows
+        let huge = format!("function LoginWizard() {{\n{}}}", "  const step = next(step);\n".repeat(1500));
+        index_functions(&orchestrator, &[("src/LoginWizard.tsx", "LoginWizard", &huge)]);
+        orchestrator.graph.record_feedback("src/LoginWizard.tsx", "LoginWizard", true).unwrap();
+        let orchestrator = orchestrator.with_pipeline(PipelineConfig { context_tokens: 2000, ..Default::default() });
+        let gathered = gather(&orchestrator).await;
+        let names: Vec<&str> = gathered.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["LoginPage", "LoginWizard", "LoginForm"]);
+        assert_eq!(gathered[1].content, "function LoginWizard() {\n  // ... 1500 lines omitted\n}");
+        let orchestrator = orchestrator.with_pipeline(PipelineConfig { context_tokens: 20000, ..Default::default() });
+        assert_eq!(gather(&orchestrator).await[1].content, huge);
+
+        let weights = orchestrator.graph.feedback_weights().unwrap();
+        let page = weights[&("src/LoginPage.tsx".to_string(), String::new())];
+        assert!(page > 0.0 && page < miow_graph::FeedbackRecord::MAX_WEIGHT);
+    }
+
+    #[tokio::test]
+    async fn test_similar_implementations_share_structure() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let orchestrator = MiowOrchestrator::new(db_path.to_str().unwrap()).unwrap();
+        // (path, component, references, import sources)
+        let components: &[(&str, &str, &[&str], &[&str])] = &[
+            ("src/CheckoutForm.tsx", "CheckoutForm", &["useCart", "Button"], &["react", "@/lib/cart", "./Button"]),
+            ("src/CartSummary.tsx", "CartSummary", &["useCart"], &["react", "@/lib/cart"]),
+            ("src/Hero.tsx", "Hero", &["Button"], &["react", "./Button"]),
+            ("src/Footer.tsx", "Footer", &[], &["react"]),
+            ("src/Banner.tsx", "Banner", &[], &[]),
+        ];
+        let files: Vec<(String, miow_graph::ParsedFileData)> = components
+            .iter()
+            .map(|(path, name, references, sources)| {
+                let imports: Vec<_> = sources
+                    .iter()
+                    .map(|source| serde_json::json!({"source": source, "names": [], "start_line": 1, "end_line": 1}))
+                    .collect();
+                let file = serde_json::from_value(serde_json::json!({
+                    "symbols": [{
+                        "name": name, "kind": "Component", "start_line": 2, "end_line": 4, "start_byte": 0,
+                        "end_byte": 10, "content": format!("function {}() {{}}", name), "metadata": "{}",
+                        "style_tags": null, "children": [], "references": references
+                    }],
+                    "imports": imports, "design_tokens": [], "type_definitions": [], "constants": [], "schemas": [],
+                    "language": "typescript"
+                }))
+                .unwrap();
+                (path.to_string(), file)
+            })
+            .collect();
+        orchestrator.graph.insert_files(&files).unwrap();
+
+        let gathered = orchestrator
+            .gather_comprehensive_context("", &["CheckoutForm".to_string()], Intent::CreateComponent, None, None)
+            .await
+            .unwrap();
+        let similar: Vec<&str> = gathered.similar_implementations.iter().map(|s| s.name.as_str()).collect();
+        // Sharing the rare `useCart` and `@/lib/cart` beats sharing `Button`; everyone imports react
+        assert_eq!(similar, vec!["CartSummary", "Hero", "Footer"]);
+
+        let unrelated = orchestrator
+            .gather_comprehensive_context("", &["CheckoutForm".to_string()], Intent::Fix, None, None)
+            .await
+            .unwrap();
+        assert!(unrelated.similar_implementations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_linked_index_items_are_labeled_by_origin() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let library = MiowOrchestrator::new(root.join("library.db").to_str().unwrap()).unwrap();
+        index_functions(&library, &[("src/LoginButton.tsx", "LoginButton", "function LoginButton() { return <button /> }")]);
+        drop(library);
+
+        let linked = KnowledgeGraph::open_read_only(root.join("library.db")).unwrap();
+        assert!(linked.record_feedback("src/LoginButton.tsx", "LoginButton", true).is_err());
+        let orchestrator = MiowOrchestrator::new(root.join("app.db").to_str().unwrap()).unwrap().with_linked_index("@acme/ui", linked);
+        index_functions(&orchestrator, &[("src/LoginForm.tsx", "LoginForm", "function LoginForm() { return <form /> }")]);
+
+        let gathered = orchestrator
+            .gather_comprehensive_context("", &["login".to_string()], Intent::Unknown, None, None)
+            .await
+            .unwrap();
+        let paths: Vec<&str> = gathered.components.iter().map(|c| c.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["src/LoginForm.tsx", "@acme/ui:src/LoginButton.tsx"]);
+
+        let context = orchestrator.convert_to_context_data(gathered, &[], "login").await.unwrap();
+        assert_eq!(context.linked_indexes, vec!["@acme/ui".to_string()]);
+    }
+
+    /// Fails the test on any call; a dry run must not reach the LLM
+    struct NoCallsLLM;
+
+    #[async_trait::async_trait]
+    impl LLMProvider for NoCallsLLM {
+        async fn generate(&self, _prompt: &str) -> Result<LLMResponse> {
+            unimplemented!("dry runs make no LLM calls")
+        }
+        async fn generate_with_context(&self, _messages: Vec<Message>) -> Result<LLMResponse> {
+            unimplemented!("dry runs make no LLM calls")
+        }
+        async fn stream_generate(&self, _prompt: &str) -> Result<miow_llm::TextStream> {
+            unimplemented!("dry runs make no LLM calls")
+        }
+        async fn generate_multi_step(&self, _steps: Vec<String>, _context: &str) -> Result<LLMResponse> {
+            unimplemented!("dry runs make no LLM calls")
+        }
+        async fn generate_with_framework(&self, _prompt: &str, _framework: &str, _lang: &str) -> Result<LLMResponse> {
+            unimplemented!("dry runs make no LLM calls")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_follows_the_workflow_ask_runs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join(".miow_cache.json"), serde_json::to_string(&ProjectSignature::default()).unwrap()).unwrap();
+        let orchestrator = MiowOrchestrator::new(root.join("test.db").to_str().unwrap()).unwrap();
+        index_functions(&orchestrator, &[("src/LoginForm.tsx", "LoginForm", "function LoginForm() { return <form /> }")]);
+        let task = "add a remember-me box to the login form";
+
+        let report = orchestrator.dry_run(task, root).await.unwrap();
+        assert_eq!(report.workflow, Workflow::Enhanced);
+
+        // With an LLM, `ask` runs the agent, whose searches the dry run repeats
+        let orchestrator = orchestrator.with_llm_arc(Arc::new(NoCallsLLM));
+        let report = orchestrator.dry_run(task, root).await.unwrap();
+        assert_eq!(report.workflow, Workflow::Agent);
+        assert!(report.files.iter().any(|file| file.starts_with("Tool: search")), "{:?}", report.files);
+
+        let orchestrator = orchestrator.with_pipeline(PipelineConfig { workflow: Workflow::Enhanced, ..PipelineConfig::default() });
+        assert_eq!(orchestrator.dry_run(task, root).await.unwrap().workflow, Workflow::Enhanced);
+    }
 }
@@ -0,0 +1,49 @@
+//! OTLP export of the tracing spans the crates open (indexing, parsing, graph
+//! writes, embeddings, Qdrant requests, agent phases, LLM calls and the
+//! workflow stages). Nothing is exported unless OTEL_EXPORTER_OTLP_ENDPOINT is
+//! set; the exporter reads it and the other standard OTEL_* variables itself.
+
+use anyhow::Result;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::Subscriber;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+const SERVICE_NAME: &str = "miow-context";
+
+/// Flushes the spans still batched when dropped, so a short command's trace
+/// isn't lost on exit
+pub struct OtelGuard {
+    provider: SdkTracerProvider,
+}
+
+impl Drop for OtelGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("Failed to flush OpenTelemetry spans: {}", e);
+        }
+    }
+}
+
+/// The layer exporting spans to the configured collector, or `None` when no
+/// endpoint is configured
+pub fn layer<S>() -> Result<Option<(impl Layer<S>, OtelGuard)>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    if std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").map_or(true, |endpoint| endpoint.is_empty()) {
+        return Ok(None);
+    }
+    // The exporter appends /v1/traces to the endpoint itself
+    let exporter = SpanExporter::builder().with_http().build()?;
+    let service_name = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| SERVICE_NAME.to_string());
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME));
+    Ok(Some((layer, OtelGuard { provider })))
+}
@@ -0,0 +1,166 @@
+//! Which stages the enhanced (Universal Knowledge Graph) workflow runs, and how
+//! many tokens of context a prompt gets, read from the `[pipeline]` table of
+//! miow.toml. The orchestrator assembles the workflow from
+//! [`PipelineConfig::stages`] and skips every stage not in it.
+
+use serde::{Deserialize, Serialize};
+
+/// Tokens of context a prompt is built from, unless miow.toml says otherwise
+pub const DEFAULT_CONTEXT_TOKENS: usize = 16000;
+
+/// How `ask` and `generate` gather context when an LLM is available
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Workflow {
+    /// The autonomous agent explores the codebase with its tools
+    #[default]
+    Agent,
+    /// The staged workflow below; always used when there is no LLM
+    Enhanced,
+}
+
+/// A step of the enhanced workflow, in the order it runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Signature,
+    Router,
+    Workers,
+    Questions,
+    Gather,
+    Audit,
+    Compile,
+    Prompt,
+}
+
+impl Stage {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Signature => "signature",
+            Stage::Router => "router",
+            Stage::Workers => "workers",
+            Stage::Questions => "questions",
+            Stage::Gather => "gather",
+            Stage::Audit => "audit",
+            Stage::Compile => "compile",
+            Stage::Prompt => "prompt",
+        }
+    }
+
+    /// The tracing span the stage runs in, exported as `stage.<name>`
+    pub fn span(self) -> tracing::Span {
+        tracing::info_span!("stage", otel.name = format!("stage.{}", self.name()), stage = self.name())
+    }
+}
+
+/// `[pipeline]` in miow.toml. Signature detection, gathering, compiling and
+/// the prompt itself always run; the rest can be turned off or capped.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    pub workflow: Workflow,
+    /// Let the LLM router plan search queries and workers
+    pub router: bool,
+    /// Run the router's workers; needs `router`
+    pub workers: bool,
+    /// Most router workers to run, in the plan's execution order
+    pub max_workers: Option<usize>,
+    /// Router workers allowed to run at once
+    pub worker_concurrency: Option<usize>,
+    /// Generate critical questions and answer them by search
+    pub questions: bool,
+    /// Most critical questions to answer
+    pub max_questions: Option<usize>,
+    /// Let the LLM prune the gathered context before compiling
+    pub auditor: bool,
+    /// Token budget of the prompt's context; gathered items are picked to fit it
+    pub context_tokens: usize,
+    /// Implementation plans sampled and voted on by self-consistency; 1 writes
+    /// (and streams) a single plan, more multiply the plan's cost
+    pub plan_samples: usize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            workflow: Workflow::default(),
+            router: true,
+            workers: true,
+            max_workers: None,
+            worker_concurrency: None,
+            questions: true,
+            max_questions: None,
+            auditor: true,
+            context_tokens: DEFAULT_CONTEXT_TOKENS,
+            plan_samples: 1,
+        }
+    }
+}
+
+impl PipelineConfig {
+    /// The stages this config runs, in order. Stages needing an LLM are still
+    /// listed; they are skipped at run time when none is attached.
+    pub fn stages(&self) -> Vec<Stage> {
+        let mut stages = vec![Stage::Signature];
+        if self.router {
+            stages.push(Stage::Router);
+            if self.workers && self.max_workers != Some(0) {
+                stages.push(Stage::Workers);
+            }
+        }
+        if self.questions && self.max_questions != Some(0) {
+            stages.push(Stage::Questions);
+        }
+        stages.push(Stage::Gather);
+        if self.auditor {
+            stages.push(Stage::Audit);
+        }
+        stages.extend([Stage::Compile, Stage::Prompt]);
+        stages
+    }
+
+    pub fn runs(&self, stage: Stage) -> bool {
+        self.stages().contains(&stage)
+    }
+
+    /// The stages as one line for the logs, e.g. `signature → router → gather → compile → prompt`
+    pub fn describe(&self) -> String {
+        self.stages()
+            .into_iter()
+            .map(|stage| match (stage, self.max_workers, self.max_questions) {
+                (Stage::Workers, Some(max), _) | (Stage::Questions, _, Some(max)) => {
+                    format!("{} (max {})", stage.name(), max)
+                }
+                _ => stage.name().to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" → ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_follow_the_config() {
+        let config: PipelineConfig = toml::from_str(
+            "workflow = \"enhanced\"\nquestions = false\nauditor = false\nmax_workers = 2",
+        )
+        .unwrap();
+        assert_eq!(config.workflow, Workflow::Enhanced);
+        assert_eq!(
+            config.stages(),
+            vec![Stage::Signature, Stage::Router, Stage::Workers, Stage::Gather, Stage::Compile, Stage::Prompt]
+        );
+        assert_eq!(config.describe(), "signature → router → workers (max 2) → gather → compile → prompt");
+
+        // Workers depend on the router's plan
+        let config: PipelineConfig = toml::from_str("router = false").unwrap();
+        assert!(!config.runs(Stage::Router));
+        assert!(!config.runs(Stage::Workers));
+        assert!(config.runs(Stage::Questions));
+
+        assert_eq!(PipelineConfig::default().stages().len(), 8);
+        assert_eq!(PipelineConfig::default().workflow, Workflow::Agent);
+    }
+}
@@ -0,0 +1,149 @@
+//! `ask --stream`: the run's phases and agent steps under a spinner, and the
+//! implementation plan printed as the LLM writes it
+
+use async_trait::async_trait;
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use miow_agent::autonomous::AgentEvent;
+use miow_agent::{ApprovalRequest, Approver};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+/// Longest tool argument or thought shown on one line
+const MAX_LINE_CHARS: usize = 100;
+
+/// Renders a run's events in the terminal. The spinner goes away while the
+/// LLM's text streams to stdout, and comes back with the next phase.
+#[derive(Clone)]
+pub struct StreamView {
+    spinner: Arc<Mutex<ProgressBar>>,
+}
+
+impl Default for StreamView {
+    /// Nothing is drawn until the first event
+    fn default() -> Self {
+        let spinner = ProgressBar::hidden();
+        spinner.finish();
+        Self { spinner: Arc::new(Mutex::new(spinner)) }
+    }
+}
+
+impl StreamView {
+    /// Render events until the run drops its sender
+    pub async fn render(self, mut events: Receiver<AgentEvent>) {
+        let mut streaming = false;
+        while let Some(event) = events.recv().await {
+            if streaming && !matches!(event, AgentEvent::Token { .. }) {
+                println!();
+                streaming = false;
+            }
+            match event {
+                AgentEvent::Phase { phase } => {
+                    self.line(format!("{} {}", "▸".cyan(), phase.bold()));
+                    self.spinner().set_message(phase);
+                }
+                AgentEvent::Step { step, max_steps } => {
+                    self.spinner().set_message(format!("Gathering context (step {}/{})", step, max_steps));
+                }
+                AgentEvent::Thought { content } => {
+                    self.spinner().set_message(truncate(content.lines().next().unwrap_or_default()));
+                }
+                AgentEvent::ToolCall { tool, args } => {
+                    self.line(format!("   🔧 {} {}", tool, truncate(&args.to_string()).bright_black()));
+                }
+                AgentEvent::Reflection { gaps } => {
+                    self.line(format!("   🪞 Looking again for: {}", truncate(&gaps.join("; "))));
+                }
+                AgentEvent::BudgetExhausted { limit } => {
+                    self.line(format!("   ⏱️  Stopped at its {}", limit).yellow().to_string());
+                }
+                AgentEvent::Error { error } => {
+                    self.line(format!("   ❌ {}", truncate(&error)).red().to_string());
+                }
+                AgentEvent::Done => self.spinner().set_message("Context gathered"),
+                AgentEvent::Token { content } => {
+                    if !streaming {
+                        self.pause();
+                        streaming = true;
+                    }
+                    print!("{}", content);
+                    let _ = std::io::stdout().flush();
+                }
+                AgentEvent::ToolOutput { .. }
+                | AgentEvent::ApprovalRequired { .. }
+                | AgentEvent::ApprovalResolved { .. }
+                | AgentEvent::QuestionAsked { .. }
+                | AgentEvent::QuestionAnswered { .. } => {}
+            }
+        }
+        if streaming {
+            println!();
+        }
+        self.pause();
+    }
+
+    /// `approver`, with the spinner out of the way while it asks in the terminal
+    pub fn approver(&self, approver: Arc<dyn Approver>) -> Arc<dyn Approver> {
+        Arc::new(PausingApprover { view: self.clone(), inner: approver })
+    }
+
+    /// The spinner, started again if it was paused
+    fn spinner(&self) -> ProgressBar {
+        let mut spinner = self.spinner.lock().unwrap();
+        if spinner.is_finished() {
+            *spinner = new_spinner();
+        }
+        spinner.clone()
+    }
+
+    /// A line above the spinner; printed plainly when there's no terminal to draw it in
+    fn line(&self, text: String) {
+        let spinner = self.spinner();
+        if spinner.is_hidden() {
+            println!("{}", text);
+        } else {
+            spinner.println(text);
+        }
+    }
+
+    fn pause(&self) {
+        self.spinner.lock().unwrap().finish_and_clear();
+    }
+}
+
+/// A channel for a run's events and the task rendering them
+pub fn spawn(view: StreamView) -> (Sender<AgentEvent>, tokio::task::JoinHandle<()>) {
+    let (tx, rx) = tokio::sync::mpsc::channel(100);
+    (tx, tokio::spawn(view.render(rx)))
+}
+
+struct PausingApprover {
+    view: StreamView,
+    inner: Arc<dyn Approver>,
+}
+
+#[async_trait]
+impl Approver for PausingApprover {
+    async fn approve(&self, request: &ApprovalRequest, events: Option<&Sender<AgentEvent>>) -> anyhow::Result<bool> {
+        self.view.pause();
+        self.inner.approve(request, events).await
+    }
+}
+
+fn new_spinner() -> ProgressBar {
+    let spinner = ProgressBar::new_spinner();
+    if let Ok(style) = ProgressStyle::with_template("{spinner:.cyan} {wide_msg}") {
+        spinner.set_style(style);
+    }
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_LINE_CHARS) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,160 @@
+//! Rate limiting for `serve`: each bearer token, or each client address when
+//! the request has none, gets its own bucket, so one client can't spend the
+//! LLM quota or flood the index queue for everyone
+
+use crate::auth::ApiToken;
+use crate::config::RateLimitConfig;
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, Request, Response, StatusCode};
+use governor::middleware::NoOpMiddleware;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_governor::governor::{GovernorConfig, GovernorConfigBuilder};
+use tower_governor::key_extractor::KeyExtractor;
+use tower_governor::{GovernorError, GovernorLayer};
+
+pub type RateLimitLayer = GovernorLayer<TokenOrAddress, NoOpMiddleware<governor::clock::QuantaInstant>>;
+
+/// Bucket key: the authenticated token's name, else the peer address. Runs
+/// after `require_token`, so made-up tokens can't open fresh buckets.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenOrAddress;
+
+impl KeyExtractor for TokenOrAddress {
+    type Key = String;
+
+    fn extract<T>(&self, request: &Request<T>) -> Result<Self::Key, GovernorError> {
+        if let Some(token) = request.extensions().get::<ApiToken>() {
+            return Ok(format!("token:{}", token.name));
+        }
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| format!("ip:{}", addr.ip()))
+            .ok_or(GovernorError::UnableToExtractKey)
+    }
+}
+
+/// The limiter described by `config`, or `None` when it is turned off
+pub fn layer(config: &RateLimitConfig) -> Option<RateLimitLayer> {
+    if config.requests_per_minute == 0 {
+        return None;
+    }
+    let governor: GovernorConfig<_, _> = GovernorConfigBuilder::default()
+        .period(Duration::from_secs(60) / config.requests_per_minute)
+        .burst_size(config.burst.max(1))
+        .key_extractor(TokenOrAddress)
+        .error_handler(too_many_requests)
+        .finish()?;
+    let governor = Arc::new(governor);
+
+    // Buckets of clients that went quiet would otherwise be kept forever
+    let limiter = governor.limiter().clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            limiter.retain_recent();
+        }
+    });
+    Some(GovernorLayer { config: governor })
+}
+
+/// Errors in the API's JSON shape, with `Retry-After` when the bucket is empty
+fn too_many_requests(error: GovernorError) -> Response<Body> {
+    let (status, message, wait) = match error {
+        GovernorError::TooManyRequests { wait_time, .. } => (
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("Too many requests; retry in {}s", wait_time),
+            Some(wait_time),
+        ),
+        GovernorError::UnableToExtractKey => {
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not tell which client sent the request".to_string(), None)
+        }
+        GovernorError::Other { code, msg, .. } => (code, msg.unwrap_or_else(|| "Rate limit error".to_string()), None),
+    };
+    let body = serde_json::json!({ "success": false, "error": message });
+    let mut response = Response::builder().status(status).header(header::CONTENT_TYPE, "application/json");
+    if let Some(wait) = wait {
+        response = response.header(header::RETRY_AFTER, wait.to_string());
+    }
+    response
+        .body(Body::from(body.to_string()))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Authenticator;
+    use crate::config::{ServerConfig, StaticToken};
+    use crate::tests::{serve_locally, web_state};
+
+    /// The API with these unscoped tokens, whose names are also their secrets, and
+    /// buckets of `burst` requests refilled once a minute
+    async fn serve(tokens: &[&str], burst: u32) -> String {
+        let config = ServerConfig {
+            tokens: tokens
+                .iter()
+                .map(|token| StaticToken {
+                    name: token.to_string(),
+                    token: Some(token.to_string()),
+                    token_env: None,
+                    paths: Vec::new(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let state = web_state(Authenticator::new(&config, None).unwrap());
+        let limit = layer(&RateLimitConfig { requests_per_minute: 1, burst });
+        serve_locally(crate::api_router(&state, limit).with_state(state)).await
+    }
+
+    #[test]
+    fn test_zero_rate_turns_limiting_off() {
+        assert!(layer(&RateLimitConfig { requests_per_minute: 0, burst: 30 }).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_each_token_has_its_own_bucket() {
+        let base = serve(&["alice", "bob"], 2).await;
+        let client = reqwest::Client::new();
+        let usage = |token: &str| client.get(format!("{}/api/usage", base)).bearer_auth(token).send();
+
+        for _ in 0..2 {
+            assert_eq!(usage("alice").await.unwrap().status(), 200);
+        }
+        let refused = usage("alice").await.unwrap();
+        assert_eq!(refused.status(), 429);
+        assert!(refused.headers().contains_key("retry-after"));
+        let body: serde_json::Value = refused.json().await.unwrap();
+        assert_eq!(body["success"], false);
+
+        assert_eq!(usage("bob").await.unwrap().status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_a_token_are_limited_by_address() {
+        let base = serve(&[], 2).await;
+        let client = reqwest::Client::new();
+        for _ in 0..2 {
+            assert_eq!(client.get(format!("{}/api/usage", base)).send().await.unwrap().status(), 200);
+        }
+        assert_eq!(client.get(format!("{}/api/usage", base)).send().await.unwrap().status(), 429);
+    }
+
+    #[tokio::test]
+    async fn test_generate_bodies_over_the_cap_are_refused() {
+        let mut state = web_state(Authenticator::new(&ServerConfig::default(), None).unwrap());
+        state.max_generate_body = 1024;
+        let base = serve_locally(crate::api_router(&state, None).with_state(state)).await;
+        let body = serde_json::json!({ "codebase_path": "/nowhere", "user_prompt": "x".repeat(2048) });
+
+        for route in ["generate", "generate-stream", "generate-with-files"] {
+            let response = reqwest::Client::new().post(format!("{}/api/{}", base, route)).json(&body).send().await.unwrap();
+            assert_eq!(response.status(), 413, "{}", route);
+        }
+    }
+}
@@ -0,0 +1,475 @@
+//! `miow-context rpc`: index, search and generate over JSON-RPC 2.0 on stdin
+//! and stdout, for editor extensions that embed the binary. Messages are framed
+//! with `Content-Length` headers as in LSP, so `vscode-jsonrpc` can talk to it
+//! as is. Stdout carries nothing but protocol messages; progress and logs go
+//! to stderr. The protocol is described in the README.
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+use crate::errors::ErrorKind;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// A method failed; `data.code` says why, as in `--json` output
+pub const SERVER_ERROR: i64 = -32000;
+/// The client cancelled the request with `$/cancelRequest`
+pub const REQUEST_CANCELLED: i64 = -32800;
+
+/// How long the agent waits for `approve` or `answer` before going on without
+const REPLY_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A JSON-RPC error object
+#[derive(Debug)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut error = json!({ "code": self.code, "message": self.message });
+        if let Some(data) = &self.data {
+            error["data"] = data.clone();
+        }
+        error
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(error: anyhow::Error) -> Self {
+        let kind = ErrorKind::of(&error);
+        let code = if kind == ErrorKind::Cancelled { REQUEST_CANCELLED } else { SERVER_ERROR };
+        Self { code, message: format!("{:#}", error), data: Some(json!({ "code": kind.code() })) }
+    }
+}
+
+/// Read one `Content-Length`-framed message body, or `None` at the end of input
+pub async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => bail!("Input ended inside a message header"),
+            };
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            // Blank lines before a header are tolerated
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>().context("Bad Content-Length header")?);
+        }
+    }
+    let mut body = vec![0; length.unwrap_or_default()];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(body))
+}
+
+/// Write `message` with its `Content-Length` header
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Value) -> Result<()> {
+    let body = serde_json::to_vec(message)?;
+    writer.write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes()).await?;
+    writer.write_all(&body).await?;
+    writer.flush().await.context("Client closed the connection")
+}
+
+/// What `rpc` or `lsp` was started with; every method works on this project
+pub struct RpcOptions {
+    pub root: PathBuf,
+    pub database: PathBuf,
+    pub llm: crate::LlmArgs,
+    /// Approve agent actions without asking the client (`--yes`)
+    pub assume_yes: bool,
+}
+
+/// The methods a connection answers; `rpc` and `lsp` each serve their own
+#[async_trait]
+pub trait Methods: Send + Sync + 'static {
+    async fn call(&self, connection: &Connection, id: Option<&Value>, method: &str, params: Value) -> Result<Value, RpcError>;
+}
+
+/// The client's side of a connection: notifications to it, and cancellation
+/// of its running requests
+pub struct Connection {
+    outgoing: mpsc::UnboundedSender<Value>,
+    /// Cancellation of running requests, by their id
+    running: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl Connection {
+    pub fn notify(&self, method: &str, params: Value) {
+        let _ = self.outgoing.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn respond(&self, id: Value, result: Result<Value, RpcError>) {
+        let message = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error.to_json() }),
+        };
+        let _ = self.outgoing.send(message);
+    }
+
+    /// Let `$/cancelRequest` for `id` cancel `token`, until `untrack`
+    pub fn track(&self, id: Option<&Value>, token: CancellationToken) {
+        if let Some(id) = id {
+            self.running.lock().unwrap().insert(id.to_string(), token);
+        }
+    }
+
+    pub fn untrack(&self, id: Option<&Value>) {
+        if let Some(id) = id {
+            self.running.lock().unwrap().remove(&id.to_string());
+        }
+    }
+
+    /// Cancel the request with this id, if it is still running
+    fn cancel(&self, id: &Value) {
+        if let Some(token) = self.running.lock().unwrap().get(&id.to_string()) {
+            token.cancel();
+        }
+    }
+}
+
+/// What `miow-context rpc` answers
+pub struct RpcMethods {
+    options: RpcOptions,
+    /// Approvals and questions of running `generate` requests, answered by `approve` and `answer`
+    replies: miow_agent::ChannelApprover,
+}
+
+impl RpcMethods {
+    pub fn new(options: RpcOptions) -> Self {
+        Self { options, replies: miow_agent::ChannelApprover::new(REPLY_TIMEOUT) }
+    }
+
+    pub fn options(&self) -> &RpcOptions {
+        &self.options
+    }
+
+    async fn index(&self, connection: &Connection, params: IndexParams) -> Result<Value, RpcError> {
+        let mode = match params.since {
+            Some(rev) => crate::IndexMode::Since(rev),
+            None if params.incremental && self.options.database.exists() => crate::IndexMode::Incremental,
+            None => crate::IndexMode::Full,
+        };
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+        let forward = async {
+            while let Some(event) = progress_rx.recv().await {
+                connection.notify("miow/indexProgress", json!(event));
+            }
+        };
+        let index = crate::run_index(self.options.root.clone(), self.options.database.clone(), mode, Some(progress_tx));
+        let (report, ()) = tokio::join!(index, forward);
+        Ok(crate::index_report_json(&report?))
+    }
+
+    async fn search(&self, params: SearchParams) -> Result<Value, RpcError> {
+        let filters = crate::SearchFilters { kind: params.kind, refs_to: params.refs_to, file: params.file, owner: params.owner };
+        let hits: Vec<Value> = if params.semantic {
+            if filters.owner.is_some() {
+                return Err(RpcError::new(INVALID_PARAMS, "A semantic search can't filter by owner"));
+            }
+            let query = params.query.ok_or_else(|| RpcError::new(INVALID_PARAMS, "A semantic search needs a query"))?;
+            crate::query_similar(&self.options.root, &self.options.database, &query, &filters, params.limit)
+                .await?
+                .iter()
+                .map(crate::similar_hit_json)
+                .collect()
+        } else {
+            crate::query_graph(&self.options.database, params.query.as_deref(), &filters, params.limit)?
+                .iter()
+                .map(crate::symbol_hit_json)
+                .collect()
+        };
+        Ok(Value::from(hits))
+    }
+
+    async fn generate(&self, connection: &Connection, id: Option<&Value>, params: GenerateParams) -> Result<Value, RpcError> {
+        if params.task.trim().is_empty() {
+            return Err(RpcError::new(INVALID_PARAMS, "No task given"));
+        }
+        let options = &self.options;
+        if !options.database.exists() {
+            return Err(anyhow::Error::from(miow_graph::GraphError::NotIndexed(options.database.clone())).into());
+        }
+        let prompt_args = crate::PromptArgs { format: params.format, explain: params.explain, ..Default::default() };
+        let store = miow_agent::RunStore::new(crate::runs_dir_for_db(&options.database));
+        let run_id = miow_agent::RunStore::new_run_id();
+        let mut orchestrator = crate::prompt_orchestrator(
+            &options.root,
+            &options.database,
+            prompt_args,
+            crate::BudgetArgs::default(),
+            options.llm.clone(),
+            options.assume_yes,
+        )
+        .await?
+        .with_run(store, run_id.clone())
+        .with_answerer(Arc::new(self.replies.clone()));
+        if !options.assume_yes {
+            orchestrator = orchestrator.with_approver(Arc::new(self.replies.clone()));
+        }
+
+        connection.track(id, orchestrator.cancellation_token());
+        let (event_tx, mut event_rx) = mpsc::channel(100);
+        let request = id.cloned().unwrap_or(Value::Null);
+        let forward = async {
+            while let Some(event) = event_rx.recv().await {
+                connection.notify("miow/agentEvent", json!({ "request": request, "event": event }));
+            }
+        };
+        let root = options.root.to_string_lossy();
+        let run = orchestrator.generate_autonomous_prompt(&root, &params.task, Some(event_tx));
+        let (prompt, ()) = tokio::join!(run, forward);
+        connection.untrack(id);
+
+        Ok(json!({
+            "run_id": run_id,
+            "prompt": prompt?,
+            "usage": orchestrator.usage_summary(),
+            "explanation": orchestrator.explanation(),
+        }))
+    }
+}
+
+#[async_trait]
+impl Methods for RpcMethods {
+    async fn call(&self, connection: &Connection, id: Option<&Value>, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(json!({
+                "name": "miow-context",
+                "version": env!("CARGO_PKG_VERSION"),
+                "root": self.options.root,
+                "database": self.options.database,
+                "methods": ["index", "search", "generate", "approve", "answer", "shutdown"],
+            })),
+            "shutdown" => Ok(Value::Null),
+            "index" => self.index(connection, parse(params)?).await,
+            "search" => self.search(parse(params)?).await,
+            "generate" => self.generate(connection, id, parse(params)?).await,
+            "approve" => {
+                let ApproveParams { id, approved } = parse(params)?;
+                match self.replies.resolve(&id, approved) {
+                    true => Ok(Value::Null),
+                    false => Err(RpcError::new(INVALID_PARAMS, format!("No pending approval '{}'", id))),
+                }
+            }
+            "answer" => {
+                let AnswerParams { id, answer } = parse(params)?;
+                match self.replies.reply(&id, answer) {
+                    true => Ok(Value::Null),
+                    false => Err(RpcError::new(INVALID_PARAMS, format!("No pending question '{}'", id))),
+                }
+            }
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method '{}'", method))),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct IndexParams {
+    /// Only reindex files that changed since the last index
+    incremental: bool,
+    /// Only reindex files git reports as changed since this revision
+    since: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    #[serde(default)]
+    query: Option<String>,
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    refs_to: Option<String>,
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(default)]
+    owner: Option<String>,
+    /// Search the vector store for similar code instead of the graph by name
+    #[serde(default)]
+    semantic: bool,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+#[derive(Deserialize)]
+struct GenerateParams {
+    task: String,
+    #[serde(default)]
+    format: miow_prompt::PromptFormat,
+    /// Also return why each context item was included
+    #[serde(default)]
+    explain: bool,
+}
+
+#[derive(Deserialize)]
+struct ApproveParams {
+    id: String,
+    approved: bool,
+}
+
+#[derive(Deserialize)]
+struct AnswerParams {
+    id: String,
+    answer: String,
+}
+
+/// Method params, where a missing `params` counts as `{}`
+pub fn parse<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid params: {}", e)))
+}
+
+/// Answer requests from `input` on `output` with `methods` until the client
+/// sends `exit` or closes its end. Requests run concurrently; responses come
+/// as they finish.
+pub async fn serve<R, W>(input: R, mut output: W, methods: impl Methods) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<Value>();
+    let writer = tokio::spawn(async move {
+        while let Some(message) = outgoing_rx.recv().await {
+            write_message(&mut output, &message).await?;
+        }
+        Ok::<_, anyhow::Error>(())
+    });
+    let connection = Arc::new(Connection { outgoing, running: Mutex::new(HashMap::new()) });
+    let methods = Arc::new(methods);
+    let tasks = TaskTracker::new();
+    let mut input = BufReader::new(input);
+
+    while let Some(body) = read_message(&mut input).await? {
+        let message: Value = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                connection.respond(Value::Null, Err(RpcError::new(PARSE_ERROR, format!("Invalid JSON: {}", e))));
+                continue;
+            }
+        };
+        let id = message.get("id").cloned();
+        let Some(method) = message["method"].as_str().map(str::to_string) else {
+            // Responses to requests we never make are ignored
+            if message.get("result").is_none() && message.get("error").is_none() {
+                connection.respond(id.unwrap_or(Value::Null), Err(RpcError::new(INVALID_REQUEST, "No method")));
+            }
+            continue;
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        match method.as_str() {
+            "exit" => break,
+            "$/cancelRequest" => {
+                if let Some(id) = params.get("id") {
+                    connection.cancel(id);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        let (connection, methods) = (connection.clone(), methods.clone());
+        tasks.spawn(async move {
+            let result = methods.call(&connection, id.as_ref(), &method, params).await;
+            // Notifications get no response, not even an error
+            if let Some(id) = id {
+                connection.respond(id, result);
+            }
+        });
+    }
+
+    // Nobody is left to read what running requests would return
+    for token in connection.running.lock().unwrap().values() {
+        token.cancel();
+    }
+    tasks.close();
+    tasks.wait().await;
+    drop(connection);
+    writer.await.map_err(|e| anyhow!("Writer task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn send(input: &mut tokio::io::DuplexStream, message: Value) {
+        write_message(input, &message).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_requests_get_framed_responses() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let database = dir.join("miow.db");
+        let file: miow_graph::ParsedFileData = serde_json::from_value(json!({
+            "symbols": [{
+                "name": "LoginForm", "kind": "function", "start_line": 1, "end_line": 3, "start_byte": 0,
+                "end_byte": 10, "content": "function LoginForm() {}", "metadata": "{}", "style_tags": null,
+                "children": [], "references": []
+            }],
+            "imports": [], "design_tokens": [], "type_definitions": [], "constants": [], "schemas": [],
+            "language": "typescript"
+        }))
+        .unwrap();
+        miow_graph::KnowledgeGraph::new(&database).unwrap().insert_files(&[("src/LoginForm.tsx".to_string(), file)]).unwrap();
+
+        let (mut client, server_io) = tokio::io::duplex(1 << 16);
+        let (server_in, server_out) = tokio::io::split(server_io);
+        let options = RpcOptions { root: dir.to_path_buf(), database, llm: Default::default(), assume_yes: false };
+        let server = tokio::spawn(serve(server_in, server_out, RpcMethods::new(options)));
+
+        send(&mut client, json!({ "jsonrpc": "2.0", "id": 1, "method": "search", "params": { "query": "Login" } })).await;
+        send(&mut client, json!({ "jsonrpc": "2.0", "id": 2, "method": "search", "params": { "limit": "ten" } })).await;
+        send(&mut client, json!({ "jsonrpc": "2.0", "id": 3, "method": "rename" })).await;
+        send(&mut client, json!({ "jsonrpc": "2.0", "id": 4, "method": "generate", "params": { "task": " " } })).await;
+        client.write_all(b"Content-Length: 5\r\n\r\n{oops").await.unwrap();
+        send(&mut client, json!({ "jsonrpc": "2.0", "method": "exit" })).await;
+
+        let mut reader = BufReader::new(client);
+        let mut responses = HashMap::new();
+        while let Some(body) = read_message(&mut reader).await.unwrap() {
+            let response: Value = serde_json::from_slice(&body).unwrap();
+            responses.insert(response["id"].to_string(), response);
+        }
+        server.await.unwrap().unwrap();
+
+        let hits = responses["1"]["result"].as_array().unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0]["name"], "LoginForm");
+        assert_eq!(hits[0]["file_path"], "src/LoginForm.tsx");
+        assert_eq!(responses["2"]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(responses["3"]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses["4"]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(responses["null"]["error"]["code"], PARSE_ERROR);
+    }
+}
@@ -0,0 +1,84 @@
+//! Prometheus metrics for `serve`: installs the recorder behind `/metrics`
+//! and times every HTTP request. The crates record their own metrics (LLM
+//! tokens, Qdrant calls, agent runs) through the `metrics` facade, which does
+//! nothing until a recorder is installed.
+
+use anyhow::Result;
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::time::{Duration, Instant};
+
+/// Latency buckets in seconds, from a cached graph query to a long agent run
+const SECONDS_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+const ITERATION_BUCKETS: &[f64] = &[1.0, 2.0, 3.0, 5.0, 8.0, 13.0, 21.0, 34.0];
+
+/// Install the process-wide recorder; its handle renders the `/metrics` page
+pub fn install() -> Result<PrometheusHandle> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), SECONDS_BUCKETS)?
+        .set_buckets_for_metric(Matcher::Full("miow_agent_iterations".to_string()), ITERATION_BUCKETS)?
+        .install_recorder()?;
+
+    // Without the exporter's own listener, histograms are only drained by upkeep
+    let upkeep = handle.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(5));
+        loop {
+            interval.tick().await;
+            upkeep.run_upkeep();
+        }
+    });
+    Ok(handle)
+}
+
+/// Count and time requests by route pattern, so `/api/symbols/:id` is one series
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let labels = [("method", method), ("route", route), ("status", response.status().as_u16().to_string())];
+    metrics::counter!("miow_http_requests_total", &labels).increment(1);
+    metrics::histogram!("miow_http_request_duration_seconds", &labels).record(started.elapsed().as_secs_f64());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+
+    #[tokio::test]
+    async fn test_requests_are_counted_on_the_metrics_page() {
+        let mut state = crate::tests::web_state(crate::auth::Authenticator::new(&Default::default(), None).unwrap());
+        // The only test that installs the process-wide recorder
+        state.metrics = install().unwrap();
+        let router = axum::Router::new()
+            .route("/probe", get(|| async { "ok" }))
+            .route("/metrics", get(crate::metrics_handler))
+            .layer(axum::middleware::from_fn(track_requests))
+            .with_state(state);
+        let base = crate::tests::serve_locally(router).await;
+
+        let client = reqwest::Client::new();
+        for _ in 0..2 {
+            assert_eq!(client.get(format!("{}/probe", base)).send().await.unwrap().status(), 200);
+        }
+        let page = client.get(format!("{}/metrics", base)).send().await.unwrap().text().await.unwrap();
+        let probes = page
+            .lines()
+            .find(|line| line.starts_with("miow_http_requests_total{") && line.contains("route=\"/probe\""))
+            .unwrap_or_else(|| panic!("no request counter in:\n{}", page));
+        assert!(probes.contains("method=\"GET\"") && probes.contains("status=\"200\""), "{}", probes);
+        assert!(probes.ends_with(" 2"), "{}", probes);
+        assert!(page.contains("miow_http_request_duration_seconds_bucket{"));
+    }
+}
@@ -0,0 +1,553 @@
+//! `miow-context tui`: browse the knowledge graph from the terminal
+
+use anyhow::Result;
+use miow_graph::{KnowledgeGraph, QueryBuilder, SymbolSearchResult};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+/// Most symbols a search shows; narrow the query to see the rest
+const SEARCH_LIMIT: usize = 200;
+
+/// Why the TUI returned control to the caller
+pub enum TuiExit {
+    Quit,
+    /// Generate a prompt for this task, then come back
+    Generate(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Files,
+    Symbols,
+    References,
+}
+
+/// Which side of the selected symbol's references is shown
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RefDirection {
+    /// Symbols that reference the selection
+    UsedBy,
+    /// Names the selection references
+    Uses,
+}
+
+/// One line of the references pane. Names the selection uses are resolved
+/// to a definition only when followed.
+struct RefItem {
+    name: String,
+    symbol: Option<SymbolSearchResult>,
+}
+
+/// What the user is typing into, if anything
+enum Input {
+    None,
+    Search,
+    /// The task for a prompt about the selected symbol
+    Task(String),
+}
+
+/// Browsing state; kept across prompt generations so the user returns where they left off
+pub struct App {
+    graph: KnowledgeGraph,
+    focus: Pane,
+    input: Input,
+    query: String,
+    files: Vec<String>,
+    file_state: ListState,
+    /// Search results, or the symbols of the opened file
+    symbols: Vec<SymbolSearchResult>,
+    symbols_title: String,
+    symbol_state: ListState,
+    direction: RefDirection,
+    references: Vec<RefItem>,
+    ref_state: ListState,
+    /// Symbols jumped away from, for going back
+    history: Vec<SymbolSearchResult>,
+    preview_scroll: u16,
+    status: String,
+}
+
+impl App {
+    pub fn new(graph: KnowledgeGraph) -> Result<Self> {
+        let files = graph.list_files()?;
+        let mut app = Self {
+            graph,
+            focus: Pane::Files,
+            input: Input::None,
+            query: String::new(),
+            files,
+            file_state: ListState::default(),
+            symbols: Vec::new(),
+            symbols_title: "Symbols".to_string(),
+            symbol_state: ListState::default(),
+            direction: RefDirection::UsedBy,
+            references: Vec::new(),
+            ref_state: ListState::default(),
+            history: Vec::new(),
+            preview_scroll: 0,
+            status: String::new(),
+        };
+        if !app.files.is_empty() {
+            app.file_state.select(Some(0));
+        }
+        app.status = format!("{} indexed files", app.files.len());
+        Ok(app)
+    }
+
+    fn selected_symbol(&self) -> Option<&SymbolSearchResult> {
+        self.symbol_state.selected().and_then(|i| self.symbols.get(i))
+    }
+
+    /// Show `symbols` in the symbols pane, selecting the one with `select_id` if present
+    fn show_symbols(&mut self, title: String, symbols: Vec<SymbolSearchResult>, select_id: Option<i64>) -> Result<()> {
+        let selected = select_id
+            .and_then(|id| symbols.iter().position(|s| s.id == id))
+            .or(if symbols.is_empty() { None } else { Some(0) });
+        self.symbols = symbols;
+        self.symbols_title = title;
+        self.symbol_state.select(selected);
+        self.load_references()
+    }
+
+    fn search(&mut self) -> Result<()> {
+        let query = self.query.trim().to_string();
+        if query.is_empty() {
+            return Ok(());
+        }
+        let results = self.graph.query_symbols(&QueryBuilder::new().with_name(&query).limit(SEARCH_LIMIT))?;
+        self.status = match results.len() {
+            0 => format!("No symbols match \"{}\"", query),
+            SEARCH_LIMIT => format!("First {} symbols matching \"{}\"", SEARCH_LIMIT, query),
+            n => format!("{} symbols match \"{}\"", n, query),
+        };
+        self.show_symbols(format!("Symbols matching \"{}\"", query), results, None)?;
+        self.focus = Pane::Symbols;
+        Ok(())
+    }
+
+    fn open_file(&mut self) -> Result<()> {
+        let Some(path) = self.file_state.selected().and_then(|i| self.files.get(i)).cloned() else {
+            return Ok(());
+        };
+        let symbols = self.graph.get_file_symbols(&path)?;
+        self.status = format!("{} symbols in {}", symbols.len(), path);
+        self.show_symbols(path, symbols, None)?;
+        self.focus = Pane::Symbols;
+        Ok(())
+    }
+
+    /// Refresh the references pane and preview for the selected symbol
+    fn load_references(&mut self) -> Result<()> {
+        self.preview_scroll = 0;
+        self.references = match self.selected_symbol() {
+            None => Vec::new(),
+            Some(symbol) => match self.direction {
+                RefDirection::UsedBy => self
+                    .graph
+                    .find_references_to(&symbol.name)?
+                    .into_iter()
+                    .filter(|r| r.id != symbol.id)
+                    .map(|r| RefItem { name: r.name.clone(), symbol: Some(r) })
+                    .collect(),
+                RefDirection::Uses => {
+                    let mut names = self.graph.get_symbol_dependencies(symbol.id)?;
+                    names.sort();
+                    names.dedup();
+                    names.into_iter().map(|name| RefItem { name, symbol: None }).collect()
+                }
+            },
+        };
+        self.ref_state.select(if self.references.is_empty() { None } else { Some(0) });
+        Ok(())
+    }
+
+    /// Jump to the selected reference's definition, remembering where we were
+    fn follow_reference(&mut self) -> Result<()> {
+        let Some(item) = self.ref_state.selected().and_then(|i| self.references.get(i)) else {
+            return Ok(());
+        };
+        let target = match &item.symbol {
+            Some(symbol) => Some(symbol.clone()),
+            None => self.graph.find_symbols_by_name(&item.name)?.into_iter().next(),
+        };
+        let Some(target) = target else {
+            self.status = format!("`{}` is not defined in the index", item.name);
+            return Ok(());
+        };
+        if let Some(current) = self.selected_symbol().cloned() {
+            self.history.push(current);
+        }
+        self.jump_to(target)
+    }
+
+    fn go_back(&mut self) -> Result<()> {
+        match self.history.pop() {
+            Some(symbol) => self.jump_to(symbol),
+            None => {
+                self.status = "Nothing to go back to".to_string();
+                Ok(())
+            }
+        }
+    }
+
+    /// Open the file defining `symbol` with it selected
+    fn jump_to(&mut self, symbol: SymbolSearchResult) -> Result<()> {
+        if let Some(i) = self.files.iter().position(|f| *f == symbol.file_path) {
+            self.file_state.select(Some(i));
+        }
+        let symbols = self.graph.get_file_symbols(&symbol.file_path)?;
+        self.status = format!("{} in {}", symbol.name, symbol.file_path);
+        self.show_symbols(symbol.file_path.clone(), symbols, Some(symbol.id))?;
+        self.focus = Pane::Symbols;
+        Ok(())
+    }
+
+    fn toggle_direction(&mut self) -> Result<()> {
+        self.direction = match self.direction {
+            RefDirection::UsedBy => RefDirection::Uses,
+            RefDirection::Uses => RefDirection::UsedBy,
+        };
+        self.load_references()
+    }
+
+    fn move_selection(&mut self, down: bool) -> Result<()> {
+        let (state, len) = match self.focus {
+            Pane::Files => (&mut self.file_state, self.files.len()),
+            Pane::Symbols => (&mut self.symbol_state, self.symbols.len()),
+            Pane::References => (&mut self.ref_state, self.references.len()),
+        };
+        if len == 0 {
+            return Ok(());
+        }
+        let next = match state.selected() {
+            Some(i) if down => (i + 1).min(len - 1),
+            Some(i) => i.saturating_sub(1),
+            None => 0,
+        };
+        state.select(Some(next));
+        if self.focus == Pane::Symbols {
+            self.load_references()?;
+        }
+        Ok(())
+    }
+
+    /// Handle one key press; `Some` ends the session
+    fn handle_key(&mut self, key: KeyEvent) -> Result<Option<TuiExit>> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Ok(Some(TuiExit::Quit));
+        }
+
+        match &mut self.input {
+            Input::Search => {
+                match key.code {
+                    KeyCode::Enter => {
+                        self.input = Input::None;
+                        self.search()?;
+                    }
+                    KeyCode::Esc => self.input = Input::None,
+                    KeyCode::Backspace => {
+                        self.query.pop();
+                    }
+                    KeyCode::Char(c) => self.query.push(c),
+                    _ => {}
+                }
+                return Ok(None);
+            }
+            Input::Task(task) => {
+                match key.code {
+                    KeyCode::Enter => {
+                        let task = task.trim().to_string();
+                        self.input = Input::None;
+                        if !task.is_empty() {
+                            return Ok(Some(TuiExit::Generate(task)));
+                        }
+                    }
+                    KeyCode::Esc => self.input = Input::None,
+                    KeyCode::Backspace => {
+                        task.pop();
+                    }
+                    KeyCode::Char(c) => task.push(c),
+                    _ => {}
+                }
+                return Ok(None);
+            }
+            Input::None => {}
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(Some(TuiExit::Quit)),
+            KeyCode::Char('/') => self.input = Input::Search,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Pane::Files => Pane::Symbols,
+                    Pane::Symbols => Pane::References,
+                    Pane::References => Pane::Files,
+                }
+            }
+            KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Pane::Files => Pane::References,
+                    Pane::Symbols => Pane::Files,
+                    Pane::References => Pane::Symbols,
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(true)?,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(false)?,
+            KeyCode::PageDown => self.preview_scroll = self.preview_scroll.saturating_add(10),
+            KeyCode::PageUp => self.preview_scroll = self.preview_scroll.saturating_sub(10),
+            KeyCode::Enter => match self.focus {
+                Pane::Files => self.open_file()?,
+                Pane::Symbols => self.focus = Pane::References,
+                Pane::References => self.follow_reference()?,
+            },
+            KeyCode::Backspace | KeyCode::Char('b') => self.go_back()?,
+            KeyCode::Char('r') => self.toggle_direction()?,
+            KeyCode::Char('g') => match self.selected_symbol() {
+                Some(symbol) => {
+                    self.input = Input::Task(format!("Change `{}` in {}: ", symbol.name, symbol.file_path))
+                }
+                None => self.status = "Select a symbol first".to_string(),
+            },
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [top, main, bottom] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [files_area, symbols_area, right] =
+            Layout::horizontal([Constraint::Percentage(25), Constraint::Percentage(30), Constraint::Percentage(45)])
+                .areas(main);
+        let [preview_area, refs_area] =
+            Layout::vertical([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(right);
+
+        self.draw_input(frame, top);
+
+        let files: Vec<ListItem> = self.files.iter().map(|f| ListItem::new(f.as_str())).collect();
+        let list = List::new(files).block(self.pane_block("Files", Pane::Files)).highlight_style(highlight());
+        frame.render_stateful_widget(list, files_area, &mut self.file_state);
+
+        let symbols: Vec<ListItem> = self
+            .symbols
+            .iter()
+            .map(|s| {
+                ListItem::new(Line::from(vec![
+                    Span::raw(s.name.clone()),
+                    Span::styled(format!(" ({})", s.kind), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+        let title = self.symbols_title.clone();
+        let list = List::new(symbols).block(self.pane_block(&title, Pane::Symbols)).highlight_style(highlight());
+        frame.render_stateful_widget(list, symbols_area, &mut self.symbol_state);
+
+        self.draw_preview(frame, preview_area);
+
+        let references: Vec<ListItem> = self
+            .references
+            .iter()
+            .map(|r| match &r.symbol {
+                Some(s) => ListItem::new(Line::from(vec![
+                    Span::raw(r.name.clone()),
+                    Span::styled(format!("  {}:{}", s.file_path, s.start_line), Style::default().fg(Color::Cyan)),
+                ])),
+                None => ListItem::new(r.name.as_str()),
+            })
+            .collect();
+        let title = match self.direction {
+            RefDirection::UsedBy => "Used by",
+            RefDirection::Uses => "Uses",
+        };
+        let list = List::new(references).block(self.pane_block(title, Pane::References)).highlight_style(highlight());
+        frame.render_stateful_widget(list, refs_area, &mut self.ref_state);
+
+        let help = "/ search  tab pane  enter open/follow  b back  r used-by/uses  g generate prompt  pgup/pgdn scroll  q quit";
+        let status = Line::from(vec![
+            Span::styled(self.status.clone(), Style::default().fg(Color::Yellow)),
+            Span::raw("  "),
+            Span::styled(help, Style::default().fg(Color::DarkGray)),
+        ]);
+        frame.render_widget(Paragraph::new(status), bottom);
+    }
+
+    fn draw_input(&self, frame: &mut Frame, area: Rect) {
+        let (title, text, active) = match &self.input {
+            Input::Task(task) => ("Task for the prompt (enter to generate, esc to cancel)", task.as_str(), true),
+            Input::Search => ("Search symbols (enter to search, esc to stop typing)", self.query.as_str(), true),
+            Input::None => ("Search symbols (press /)", self.query.as_str(), false),
+        };
+        let style = if active { Style::default().fg(Color::Yellow) } else { Style::default() };
+        frame.render_widget(Paragraph::new(text).block(Block::bordered().title(title).border_style(style)), area);
+        if active {
+            let x = area.x + 1 + text.chars().count() as u16;
+            frame.set_cursor_position((x.min(area.right().saturating_sub(2)), area.y + 1));
+        }
+    }
+
+    fn draw_preview(&self, frame: &mut Frame, area: Rect) {
+        let Some(symbol) = self.selected_symbol() else {
+            frame.render_widget(Paragraph::new("").block(Block::bordered().title("Preview")), area);
+            return;
+        };
+        let title = format!("{}:{}-{}", symbol.file_path, symbol.start_line, symbol.end_line);
+        let lines: Vec<Line> = symbol
+            .content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:>5} ", symbol.start_line + i as i64),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw(line.to_string()),
+                ])
+            })
+            .collect();
+        let preview = Paragraph::new(lines).block(Block::bordered().title(title)).scroll((self.preview_scroll, 0));
+        frame.render_widget(preview, area);
+    }
+
+    fn pane_block(&self, title: &str, pane: Pane) -> Block<'static> {
+        let block = Block::bordered().title(title.to_string());
+        if self.focus == pane && matches!(self.input, Input::None) {
+            block.border_style(Style::default().fg(Color::Cyan))
+        } else {
+            block
+        }
+    }
+}
+
+fn highlight() -> Style {
+    Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+}
+
+/// Take over the terminal until the user quits or asks for a prompt
+pub fn run(app: &mut App) -> Result<TuiExit> {
+    let mut terminal = ratatui::try_init()?;
+    let exit = event_loop(&mut terminal, app);
+    ratatui::try_restore()?;
+    exit
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> Result<TuiExit> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match app.handle_key(key) {
+                Ok(Some(exit)) => return Ok(exit),
+                Ok(None) => {}
+                Err(e) => app.status = format!("Error: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miow_core::CodebaseIndexer;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use std::sync::Arc;
+
+    /// An app over `src/util.ts`, where `caller` calls `helper`
+    async fn app() -> (tempfile::TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        let code = "export function helper(): number {\n  return 1;\n}\n\nexport function caller(): number {\n  return helper();\n}\n";
+        std::fs::write(dir.path().join("src/util.ts"), code).unwrap();
+        let db = dir.path().join("miow.db");
+        let graph = Arc::new(KnowledgeGraph::new(&db).unwrap());
+        CodebaseIndexer::new(dir.path().to_path_buf()).unwrap().with_graph(graph).index().await.unwrap();
+        let app = App::new(KnowledgeGraph::new(&db).unwrap()).unwrap();
+        (dir, app)
+    }
+
+    fn press(app: &mut App, keys: &str) -> Option<TuiExit> {
+        let mut exit = None;
+        for c in keys.chars() {
+            exit = app.handle_key(KeyEvent::from(KeyCode::Char(c))).unwrap();
+        }
+        exit
+    }
+
+    fn enter(app: &mut App) -> Option<TuiExit> {
+        app.handle_key(KeyEvent::from(KeyCode::Enter)).unwrap()
+    }
+
+    fn names(app: &App) -> Vec<&str> {
+        app.references.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_and_follow_references() {
+        let (_dir, mut app) = app().await;
+        assert_eq!(app.files, vec!["src/util.ts"]);
+
+        press(&mut app, "/helper");
+        enter(&mut app);
+        assert!(app.focus == Pane::Symbols);
+        assert_eq!(app.selected_symbol().unwrap().name, "helper");
+        assert_eq!(names(&app), vec!["caller"]);
+
+        // Into the references pane, then jump to the caller's definition
+        enter(&mut app);
+        enter(&mut app);
+        assert_eq!(app.symbols_title, "src/util.ts");
+        assert_eq!(app.selected_symbol().unwrap().name, "caller");
+        assert!(names(&app).is_empty());
+
+        press(&mut app, "r");
+        assert_eq!(names(&app), vec!["helper"]);
+
+        press(&mut app, "b");
+        assert_eq!(app.selected_symbol().unwrap().name, "helper");
+        press(&mut app, "b");
+        assert_eq!(app.status, "Nothing to go back to");
+    }
+
+    #[tokio::test]
+    async fn test_generate_asks_for_a_task_about_the_selection() {
+        let (_dir, mut app) = app().await;
+        press(&mut app, "g");
+        assert_eq!(app.status, "Select a symbol first");
+
+        // Open the file, then ask for a prompt about its first symbol
+        enter(&mut app);
+        assert!(press(&mut app, "gadd a parameter").is_none());
+        match enter(&mut app) {
+            Some(TuiExit::Generate(task)) => assert_eq!(task, "Change `helper` in src/util.ts: add a parameter"),
+            _ => panic!("expected a prompt request"),
+        }
+
+        // Escape cancels typing; only then does q quit
+        press(&mut app, "/q");
+        app.handle_key(KeyEvent::from(KeyCode::Esc)).unwrap();
+        assert_eq!(app.query, "q");
+        assert!(matches!(press(&mut app, "q"), Some(TuiExit::Quit)));
+    }
+
+    #[tokio::test]
+    async fn test_draw_shows_files_and_the_selected_symbol() {
+        let (_dir, mut app) = app().await;
+        enter(&mut app);
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("src/util.ts"));
+        assert!(screen.contains("helper (Function)"));
+        assert!(screen.contains("src/util.ts:1-3"));
+        assert!(screen.contains("return 1;"));
+        assert!(screen.contains("Used by"));
+    }
+}
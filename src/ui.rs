@@ -0,0 +1,55 @@
+//! The React frontend in `web/`, built into the binary so `serve` needs no
+//! separate frontend server. `npm run build` writes `web/dist`, which release
+//! builds embed; debug builds read it from disk, so a rebuilt UI shows up
+//! without recompiling.
+
+use axum::body::Body;
+use axum::http::{header, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "web/dist/"]
+#[allow_missing = true]
+struct Assets;
+
+/// Whether `web/dist` was built before the binary was
+pub fn is_built() -> bool {
+    Assets::get("index.html").is_some()
+}
+
+/// Fallback for everything the API doesn't route: the asset at the path, or
+/// `index.html` for the app's own pages
+pub async fn serve(uri: Uri) -> Response {
+    let path = uri.path().trim_start_matches('/');
+    if path.starts_with("api/") || path.starts_with("ws/") {
+        let body = serde_json::json!({ "success": false, "error": format!("No route for {}", uri.path()) });
+        return (StatusCode::NOT_FOUND, axum::Json(body)).into_response();
+    }
+
+    if let Some(response) = asset(path) {
+        return response;
+    }
+    // Paths with an extension are missing files, not pages
+    if path.rsplit('/').next().is_some_and(|name| name.contains('.')) {
+        return (StatusCode::NOT_FOUND, "Not found").into_response();
+    }
+    asset("index.html").unwrap_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            "The web UI was not built into this binary: run `npm run build` in web/, then rebuild with --features ui",
+        )
+            .into_response()
+    })
+}
+
+fn asset(path: &str) -> Option<Response> {
+    let file = Assets::get(path)?;
+    // Vite puts a content hash in every file name under assets/
+    let cache = if path.starts_with("assets/") { "public, max-age=31536000, immutable" } else { "no-cache" };
+    Response::builder()
+        .header(header::CONTENT_TYPE, file.metadata.mimetype())
+        .header(header::CACHE_CONTROL, cache)
+        .body(Body::from(file.data.into_owned()))
+        .ok()
+}
@@ -0,0 +1,383 @@
+//! `miow-context watch`: keeps the knowledge graph and vector collection in
+//! step with the working tree as files are saved, with a control socket that
+//! answers `status` and `stop`
+
+use anyhow::Result;
+use miow_agent::unix_now;
+use miow_core::{CodebaseIndexer, FileSync};
+use miow_graph::KnowledgeGraph;
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing::{debug, warn};
+
+/// What the daemon has done so far, as `status` reports it
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchStatus {
+    pub root: PathBuf,
+    pub database: PathBuf,
+    pub pid: u32,
+    /// Unix time the daemon started
+    pub started_at: u64,
+    pub uptime_secs: u64,
+    pub vector_store: bool,
+    /// Files re-parsed since the start
+    pub indexed: usize,
+    pub removed: usize,
+    pub errors: usize,
+    pub last_error: Option<String>,
+    /// Unix time of the last sync, if any
+    pub last_sync: Option<u64>,
+    /// Changed files waiting for the debounce to pass
+    pub pending: usize,
+}
+
+pub struct WatchOptions {
+    /// Absolute project root, as the indexer was created with
+    pub root: PathBuf,
+    pub database: PathBuf,
+    /// Quiet time after the last change before files are synced
+    pub debounce: Duration,
+    /// Unix socket for `status` and `stop`
+    pub socket: PathBuf,
+    pub vector_store: bool,
+}
+
+/// Watch `options.root` until stopped through the socket or Ctrl-C, syncing
+/// each saved, created or deleted file through `indexer`. `on_sync` hears
+/// about every file synced. Changes still pending at shutdown are synced first.
+pub async fn run(
+    mut indexer: CodebaseIndexer,
+    graph: Arc<KnowledgeGraph>,
+    options: WatchOptions,
+    mut on_sync: impl FnMut(&str, &Result<FileSync>),
+) -> Result<WatchStatus> {
+    let root = options.root.clone();
+    let started = Instant::now();
+    let mut status = WatchStatus {
+        root: root.clone(),
+        database: options.database.clone(),
+        pid: std::process::id(),
+        started_at: unix_now(),
+        uptime_secs: 0,
+        vector_store: options.vector_store,
+        indexed: 0,
+        removed: 0,
+        errors: 0,
+        last_error: None,
+        last_sync: None,
+        pending: 0,
+    };
+
+    let (tx, mut events) = tokio::sync::mpsc::unbounded_channel::<notify::Result<Event>>();
+    let mut watcher = RecommendedWatcher::new(move |event| drop(tx.send(event)), Config::default())?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+    let control = ControlSocket::bind(&options.socket)?;
+
+    let mut pending: BTreeSet<PathBuf> = BTreeSet::new();
+    let mut deadline: Option<tokio::time::Instant> = None;
+    loop {
+        let debounce = async {
+            match deadline {
+                Some(at) => tokio::time::sleep_until(at).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            event = events.recv() => match event {
+                Some(Ok(event)) => {
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+                        let before = pending.len();
+                        pending.extend(event.paths.into_iter().filter(|path| is_candidate(&indexer, path)));
+                        if pending.len() > before {
+                            deadline = Some(tokio::time::Instant::now() + options.debounce);
+                        }
+                    }
+                }
+                Some(Err(e)) => warn!("Watch error: {}", e),
+                None => break,
+            },
+            _ = debounce => {
+                deadline = None;
+                sync_pending(&mut indexer, &graph, &root, std::mem::take(&mut pending), &mut status, &mut on_sync).await;
+            }
+            command = control.next() => {
+                let (command, reply) = command?;
+                status.pending = pending.len();
+                status.uptime_secs = started.elapsed().as_secs();
+                let stop = command == "stop";
+                let response = match command.as_str() {
+                    "status" | "stop" => serde_json::to_value(&status)?,
+                    other => serde_json::json!({ "error": format!("Unknown command '{}' (expected status or stop)", other) }),
+                };
+                reply.send(response).await;
+                if stop {
+                    break;
+                }
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    drop(watcher);
+    if !pending.is_empty() {
+        sync_pending(&mut indexer, &graph, &root, pending, &mut status, &mut on_sync).await;
+    }
+    control.close();
+    status.pending = 0;
+    status.uptime_secs = started.elapsed().as_secs();
+    Ok(status)
+}
+
+/// Send `command` to the daemon listening on `socket` and return its reply
+pub async fn request(socket: &Path, command: &str) -> Result<serde_json::Value> {
+    ControlSocket::request(socket, command).await
+}
+
+/// Worth queueing: a file the index covers, or a directory that may hold some
+fn is_candidate(indexer: &CodebaseIndexer, path: &Path) -> bool {
+    indexer.is_indexable(path) || path.is_dir() || (!path.exists() && path.extension().is_none())
+}
+
+async fn sync_pending(
+    indexer: &mut CodebaseIndexer,
+    graph: &KnowledgeGraph,
+    root: &Path,
+    paths: BTreeSet<PathBuf>,
+    status: &mut WatchStatus,
+    on_sync: &mut impl FnMut(&str, &Result<FileSync>),
+) {
+    let mut files = BTreeSet::new();
+    for path in paths {
+        expand(graph, root, &path, &mut files);
+    }
+    for path in files {
+        if !path.is_dir() && path.exists() && !indexer.is_indexable(&path) {
+            continue;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().to_string();
+        let result = indexer.sync_file(&path).await;
+        match &result {
+            Ok(FileSync::Indexed { .. }) => status.indexed += 1,
+            Ok(FileSync::Removed) => status.removed += 1,
+            Ok(FileSync::Skipped) => continue,
+            Err(e) => {
+                status.errors += 1;
+                status.last_error = Some(format!("{}: {}", relative, e));
+            }
+        }
+        status.last_sync = Some(unix_now());
+        on_sync(&relative, &result);
+    }
+}
+
+/// The files behind a changed path: itself, the files in a directory that
+/// appeared, or the indexed files under a directory that went away
+fn expand(graph: &KnowledgeGraph, root: &Path, path: &Path, files: &mut BTreeSet<PathBuf>) {
+    if path.is_dir() {
+        let Ok(entries) = std::fs::read_dir(path) else { return };
+        for entry in entries.flatten() {
+            expand(graph, root, &entry.path(), files);
+        }
+    } else if !path.exists() && path.extension().is_none() {
+        let Ok(relative) = path.strip_prefix(root) else { return };
+        let prefix = format!("{}/", relative.to_string_lossy());
+        match graph.list_files() {
+            Ok(indexed) => files.extend(indexed.iter().filter(|f| f.starts_with(&prefix)).map(|f| root.join(f))),
+            Err(e) => warn!("Failed to list indexed files under {}: {}", prefix, e),
+        }
+    } else {
+        files.insert(path.to_path_buf());
+    }
+}
+
+/// Reply channel for one control connection
+struct Reply(Box<dyn tokio::io::AsyncWrite + Unpin + Send>);
+
+impl Reply {
+    async fn send(mut self, response: serde_json::Value) {
+        let line = format!("{}\n", response);
+        if let Err(e) = self.0.write_all(line.as_bytes()).await {
+            debug!("Control client went away: {}", e);
+        }
+    }
+}
+
+#[cfg(unix)]
+struct ControlSocket {
+    listener: tokio::net::UnixListener,
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl ControlSocket {
+    fn bind(path: &Path) -> Result<Self> {
+        if path.exists() {
+            // A socket nobody answers on is left over from a daemon that died
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                anyhow::bail!("A watch daemon is already running (control socket {})", path.display());
+            }
+            std::fs::remove_file(path)?;
+        }
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let listener = tokio::net::UnixListener::bind(path)?;
+        // Anyone who can connect can stop the daemon, so only its owner may
+        std::fs::set_permissions(path, std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        Ok(Self { listener, path: path.to_path_buf() })
+    }
+
+    /// The next command, one line per connection
+    async fn next(&self) -> Result<(String, Reply)> {
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            let (read, write) = stream.into_split();
+            let mut line = String::new();
+            match tokio::time::timeout(Duration::from_secs(5), BufReader::new(read).read_line(&mut line)).await {
+                // Zero bytes: a client that only checked the daemon is alive, like a second `watch`
+                Ok(Ok(read)) if read > 0 => return Ok((line.trim().to_string(), Reply(Box::new(write)))),
+                _ => debug!("Control client sent no command"),
+            }
+        }
+    }
+
+    fn close(self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+
+    async fn request(path: &Path, command: &str) -> Result<serde_json::Value> {
+        let stream = tokio::net::UnixStream::connect(path)
+            .await
+            .map_err(|e| anyhow::anyhow!("No watch daemon is listening on {}: {}", path.display(), e))?;
+        let (read, mut write) = stream.into_split();
+        write.write_all(format!("{}\n", command).as_bytes()).await?;
+        let mut line = String::new();
+        BufReader::new(read).read_line(&mut line).await?;
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+/// Without Unix sockets the daemon runs until Ctrl-C, with no control endpoint
+#[cfg(not(unix))]
+struct ControlSocket;
+
+#[cfg(not(unix))]
+impl ControlSocket {
+    fn bind(_path: &Path) -> Result<Self> {
+        warn!("The watch control socket needs Unix sockets; stop the daemon with Ctrl-C");
+        Ok(Self)
+    }
+
+    async fn next(&self) -> Result<(String, Reply)> {
+        std::future::pending().await
+    }
+
+    fn close(self) {}
+
+    async fn request(_path: &Path, _command: &str) -> Result<serde_json::Value> {
+        anyhow::bail!("The watch control socket needs Unix sockets")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_control_socket_is_private_and_answers_one_line() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("run").join("watch.sock");
+        let control = ControlSocket::bind(&path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        // A second daemon can't take over a live socket
+        assert!(ControlSocket::bind(&path).is_err());
+
+        let server = async {
+            let (command, reply) = control.next().await.unwrap();
+            reply.send(serde_json::json!({ "command": command })).await;
+        };
+        let (_, response) = tokio::join!(server, request(&path, "status"));
+        assert_eq!(response.unwrap(), serde_json::json!({ "command": "status" }));
+
+        control.close();
+        assert!(!path.exists());
+        assert!(request(&path, "status").await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stale_socket_is_replaced() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("watch.sock");
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let control = ControlSocket::bind(&path).unwrap();
+        control.close();
+    }
+
+    #[tokio::test]
+    async fn test_removed_directory_expands_to_its_indexed_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("src/auth")).unwrap();
+        std::fs::write(root.join("src/auth/login.rs"), "pub fn login() {}\n").unwrap();
+        std::fs::write(root.join("src/auth/session.rs"), "pub fn session() {}\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+
+        let graph = Arc::new(KnowledgeGraph::in_memory().unwrap());
+        let mut indexer = CodebaseIndexer::new(root.to_path_buf()).unwrap().with_graph(graph.clone());
+        indexer.index().await.unwrap();
+
+        let mut files = BTreeSet::new();
+        expand(&graph, root, &root.join("src"), &mut files);
+        assert_eq!(files.len(), 3);
+
+        std::fs::remove_dir_all(root.join("src/auth")).unwrap();
+        let mut files = BTreeSet::new();
+        expand(&graph, root, &root.join("src/auth"), &mut files);
+        assert_eq!(files, BTreeSet::from([root.join("src/auth/login.rs"), root.join("src/auth/session.rs")]));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_reports_status_and_ends_the_run() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        std::fs::write(root.join("lib.rs"), "pub fn one() {}\n").unwrap();
+        let socket = root.join(".miow").join("watch.sock");
+
+        let graph = Arc::new(KnowledgeGraph::in_memory().unwrap());
+        let indexer = CodebaseIndexer::new(root.clone()).unwrap().with_graph(graph.clone());
+        let options = WatchOptions {
+            root: root.clone(),
+            database: root.join("miow.db"),
+            debounce: Duration::from_millis(50),
+            socket: socket.clone(),
+            vector_store: false,
+        };
+        let daemon = tokio::spawn(run(indexer, graph, options, |_, _| {}));
+        while !socket.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let unknown = request(&socket, "restart").await.unwrap();
+        assert!(unknown["error"].as_str().unwrap().contains("Unknown command"));
+        let status = request(&socket, "stop").await.unwrap();
+        assert_eq!(status["pid"], std::process::id());
+        assert_eq!(status["vector_store"], false);
+
+        let finished = daemon.await.unwrap().unwrap();
+        assert_eq!(finished.root, root);
+        assert!(!socket.exists());
+    }
+}
@@ -0,0 +1,226 @@
+//! The CLI as scripts see it: the built binary run on a small indexed project,
+//! with no LLM and a Qdrant URL nothing listens on
+
+use serde_json::Value;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// Run `miow-context` in `dir`
+fn miow(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_miow-context"))
+        .args(args)
+        .current_dir(dir)
+        .env("QDRANT_URL", "http://127.0.0.1:9")
+        .env_remove("GEMINI_API_KEY")
+        .env_remove("OPENAI_API_KEY")
+        .env_remove("ANTHROPIC_API_KEY")
+        .env_remove("AZURE_OPENAI_API_KEY")
+        .env_remove("RUST_LOG")
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run miow-context")
+}
+
+/// Run `miow-context` in `dir`, expecting success and a JSON document on stdout
+fn miow_json(dir: &Path, args: &[&str]) -> Value {
+    let output = miow(dir, args);
+    assert!(output.status.success(), "{:?} failed:\n{}", args, String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout)
+        .unwrap_or_else(|e| panic!("{:?} printed no JSON ({}):\n{}", args, e, String::from_utf8_lossy(&output.stdout)))
+}
+
+/// A TypeScript project where `caller` calls `helper`, indexed into `miow.db`
+fn indexed_project() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("src/util.ts"),
+        "export function helper(): number {\n  return 1;\n}\n\nexport function caller(): number {\n  return helper();\n}\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("src/Button.tsx"), "export const Button = () => <button>Save</button>;\n").unwrap();
+    miow_json(dir.path(), &["--json", "init", "."]);
+    dir
+}
+
+/// The keys of a JSON object, sorted
+fn keys(value: &Value) -> Vec<&str> {
+    let mut keys: Vec<&str> = value.as_object().expect("not an object").keys().map(String::as_str).collect();
+    keys.sort_unstable();
+    keys
+}
+
+#[test]
+fn test_search_json_lists_symbol_hits() {
+    let project = indexed_project();
+    let dir = project.path();
+
+    let hits = miow_json(dir, &["--json", "search", "helper"]);
+    let hits = hits.as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(keys(&hits[0]), vec!["end_line", "file_path", "kind", "name", "start_line"]);
+    assert_eq!(hits[0]["name"], "helper");
+    assert_eq!(hits[0]["file_path"], "src/util.ts");
+    assert_eq!((hits[0]["start_line"].as_i64(), hits[0]["end_line"].as_i64()), (Some(1), Some(3)));
+
+    let callers = miow_json(dir, &["--json", "search", "--refs-to", "helper"]);
+    let names: Vec<&str> = callers.as_array().unwrap().iter().filter_map(|hit| hit["name"].as_str()).collect();
+    assert_eq!(names, vec!["caller"]);
+
+    let in_file = miow_json(dir, &["--json", "search", "--file", "Button"]);
+    let in_file = in_file.as_array().unwrap();
+    assert!(!in_file.is_empty() && in_file.iter().all(|hit| hit["file_path"] == "src/Button.tsx"), "{:?}", in_file);
+    assert_eq!(miow_json(dir, &["--json", "search", "nothing_like_this"]), Value::Array(Vec::new()));
+}
+
+#[test]
+fn test_stats_json_reports_the_index() {
+    let project = indexed_project();
+    let stats = miow_json(project.path(), &["--json", "stats"]);
+
+    assert_eq!(
+        keys(&stats),
+        vec![
+            "commits_behind",
+            "database",
+            "files",
+            "files_with_errors",
+            "head_commit",
+            "index_errors",
+            "indexed_commit",
+            "languages",
+            "largest_files",
+            "last_indexed_at",
+            "references",
+            "symbols",
+            "vector_backend",
+            "vector_collection",
+            "vectors",
+        ]
+    );
+    assert_eq!(stats["database"], "miow.db");
+    assert_eq!((stats["files"].as_u64(), stats["index_errors"].as_u64()), (Some(2), Some(0)));
+    assert!(stats["symbols"].as_u64().unwrap() >= 3, "{}", stats);
+    assert!(stats["references"].as_u64().unwrap() >= 1, "{}", stats);
+    assert!(stats["last_indexed_at"].is_string());
+    assert!(stats["vector_collection"].as_str().unwrap().starts_with("miow-"));
+    // Not a git repository, and Qdrant is unreachable
+    assert!(stats["head_commit"].is_null() && stats["commits_behind"].is_null());
+    assert!(stats["vectors"].is_null());
+
+    let languages = stats["languages"].as_array().unwrap();
+    assert!(languages.iter().all(|l| keys(l) == vec!["files", "language", "references", "symbols"]));
+    let files: u64 = languages.iter().filter_map(|l| l["files"].as_u64()).sum();
+    assert_eq!(files, 2);
+
+    let largest = stats["largest_files"].as_array().unwrap();
+    assert_eq!(keys(&largest[0]), vec!["language", "lines", "path", "symbols"]);
+    let util = largest.iter().find(|file| file["path"] == "src/util.ts").unwrap();
+    assert_eq!((util["lines"].as_u64(), util["symbols"].as_u64()), (Some(7), Some(2)));
+
+    let limited = miow_json(project.path(), &["--json", "stats", "--largest", "1"]);
+    assert_eq!(limited["largest_files"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_clean_removes_only_index_artifacts() {
+    let project = indexed_project();
+    let dir = project.path();
+    std::fs::write(dir.join(".miow_cache.json"), "{}").unwrap();
+    std::fs::create_dir_all(dir.join(".miow/cache")).unwrap();
+    std::fs::write(dir.join(".miow/cache/llm_cache.db"), "").unwrap();
+    std::fs::create_dir_all(dir.join(".miow/runs")).unwrap();
+    std::fs::write(dir.join(".miow/runs/run-1.json"), "{}").unwrap();
+    std::fs::write(dir.join("README.md"), "# App\n").unwrap();
+
+    // Without a terminal to confirm on, nothing is deleted
+    assert!(!miow(dir, &["clean"]).status.success());
+    assert!(dir.join("miow.db").exists());
+
+    let root = dir.canonicalize().unwrap();
+    let cleaned = miow_json(dir, &["--json", "clean", "--yes"]);
+    let removed: Vec<String> = cleaned["removed"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|path| Path::new(path.as_str().unwrap()).strip_prefix(&root).unwrap().display().to_string())
+        .collect();
+    assert!(removed.contains(&"miow.db".to_string()), "{:?}", removed);
+    assert!(removed.contains(&".miow_cache.json".to_string()) && removed.contains(&".miow/cache".to_string()));
+    assert!(cleaned["collection"].is_null());
+    assert!(!dir.join("miow.db").exists() && !dir.join(".miow/cache").exists() && !dir.join(".miow_cache.json").exists());
+    // Run history and the project itself stay
+    assert!(dir.join(".miow/runs/run-1.json").exists());
+    assert!(dir.join("src/util.ts").exists() && dir.join("src/Button.tsx").exists() && dir.join("README.md").exists());
+
+    let everything = miow_json(dir, &["--json", "clean", "--all", "--yes"]);
+    assert_eq!(everything["removed"].as_array().unwrap().len(), 1);
+    assert!(!dir.join(".miow").exists());
+    assert!(dir.join("src/util.ts").exists() && dir.join("README.md").exists());
+
+    assert_eq!(miow_json(dir, &["--json", "clean", "--yes"])["removed"], Value::Array(Vec::new()));
+}
+
+#[test]
+fn test_json_flag_keeps_stdout_machine_readable() {
+    let project = indexed_project();
+    let dir = project.path();
+
+    // Progress goes to stderr; stdout holds exactly one JSON document
+    let output = miow(dir, &["--json", "reindex", "."]);
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stderr).trim().is_empty());
+    let reindexed: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(keys(&reindexed), vec!["codebase", "database", "index", "mode"]);
+    assert_eq!(reindexed["mode"], "full");
+    assert_eq!(reindexed["index"]["total_files"], 2);
+
+    let analyzed = miow_json(dir, &["--json", "analyze", "src/util.ts"]);
+    assert_eq!(analyzed["language"], "typescript");
+    let names: Vec<&str> = analyzed["symbols"].as_array().unwrap().iter().filter_map(|s| s["name"].as_str()).collect();
+    assert_eq!(names, vec!["helper", "caller"]);
+
+    let asked = miow_json(dir, &["--json", "ask", "where is helper"]);
+    assert_eq!(
+        keys(&asked),
+        vec!["codebase", "cost_estimate", "explanation", "output", "prompt", "run_id", "task", "usage"]
+    );
+    assert_eq!(asked["task"], "where is helper");
+    assert!(asked["prompt"].as_str().unwrap().contains("function helper()"));
+    assert_eq!(asked["usage"]["calls"], 0);
+
+    // Failures are JSON too, with a non-zero exit
+    let failed = miow(dir, &["--json", "analyze", "src/missing.ts"]);
+    assert!(!failed.status.success());
+    let error: Value = serde_json::from_slice(&failed.stdout).unwrap();
+    assert_eq!(keys(&error), vec!["code", "error"]);
+}
+
+#[test]
+fn test_json_log_format_writes_one_event_per_line() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("util.ts"), "export function helper(): number {\n  return 1;\n}\n").unwrap();
+
+    let output = miow(dir.path(), &["--log-format", "json", "init", "."]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains('\u{1b}'), "colors in JSON logs:\n{}", stdout);
+    let events: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("not a JSON event ({}): {}", e, line)))
+        .collect();
+    assert!(events.iter().all(|event| event["level"].is_string() && event["timestamp"].is_string()));
+    let messages: Vec<&str> = events.iter().filter_map(|event| event["message"].as_str()).collect();
+    // Decorative emoji and rules are dropped from what was printed for people
+    assert!(messages.contains(&"MIOW-CONTEXT INITIALIZATION"), "{:?}", messages);
+    assert!(messages.iter().all(|m| !m.is_empty() && !m.starts_with(|c: char| !c.is_ascii()) && !m.contains("═")));
+    let done = events.iter().find(|event| event["message"] == "Indexing complete").unwrap();
+    assert_eq!((done["files"].as_u64(), done["symbols"].as_u64()), (Some(1), Some(1)));
+
+    // With --json too, the events move to stderr and stdout keeps the result
+    let output = miow(dir.path(), &["--log-format", "json", "--json", "stats"]);
+    let stats: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["files"], 1);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.lines().all(|line| serde_json::from_str::<Value>(line).is_ok()), "{}", stderr);
+}
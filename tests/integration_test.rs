@@ -103,10 +103,7 @@ impl miow_llm::LLMProvider for MockLLM {
         self.generate("").await
     }
 
-    async fn stream_generate(
-        &self,
-        _prompt: &str,
-    ) -> Result<Box<dyn futures::Stream<Item = Result<String>> + Unpin>> {
+    async fn stream_generate(&self, _prompt: &str) -> Result<miow_llm::TextStream> {
         let stream = stream::iter(vec![Ok("Test".to_string())]);
         Ok(Box::new(stream))
     }
@@ -52,6 +52,15 @@ interface AgentEvent {
   }
 }
 
+// Bearer token for servers started with API tokens, saved with
+// localStorage.setItem('miowApiToken', '<token>')
+function apiHeaders(): Record<string, string> {
+  const token = localStorage.getItem('miowApiToken')
+  return token
+    ? { 'Content-Type': 'application/json', Authorization: `Bearer ${token}` }
+    : { 'Content-Type': 'application/json' }
+}
+
 function App() {
   const [codebasePath, setCodebasePath] = useState('')
   const [userPrompt, setUserPrompt] = useState('')
@@ -98,7 +107,7 @@ function App() {
     try {
       const response = await fetch('/api/health', {
         method: 'POST',
-        headers: { 'Content-Type': 'application/json' },
+        headers: apiHeaders(),
       })
       const healthData: HealthResponse = await response.json()
       setHealth(healthData)
@@ -115,7 +124,7 @@ function App() {
       // Load signature
       const sigResponse = await fetch('/api/debug/signature', {
         method: 'POST',
-        headers: { 'Content-Type': 'application/json' },
+        headers: apiHeaders(),
         body: JSON.stringify({ codebase_path: codebasePath })
       })
       const sigData = await sigResponse.json()
@@ -126,7 +135,7 @@ function App() {
       // Load context
       const ctxResponse = await fetch('/api/debug/context', {
         method: 'POST',
-        headers: { 'Content-Type': 'application/json' },
+        headers: apiHeaders(),
         body: JSON.stringify({ codebase_path: codebasePath })
       })
       const ctxData = await ctxResponse.json()
@@ -151,7 +160,7 @@ function App() {
     try {
       const response = await fetch('/api/files', {
         method: 'POST',
-        headers: { 'Content-Type': 'application/json' },
+        headers: apiHeaders(),
         body: JSON.stringify({
           codebase_path: codebasePath,
           user_prompt: userPrompt
@@ -200,7 +209,7 @@ function App() {
     try {
       const response = await fetch('/api/generate-stream', {
         method: 'POST',
-        headers: { 'Content-Type': 'application/json' },
+        headers: apiHeaders(),
         body: JSON.stringify({
           codebase_path: codebasePath,
           user_prompt: userPrompt
@@ -328,7 +337,7 @@ function App() {
     try {
       const response = await fetch('/api/search-files', {
         method: 'POST',
-        headers: { 'Content-Type': 'application/json' },
+        headers: apiHeaders(),
         body: JSON.stringify({
           codebase_path: codebasePath,
           query: query
@@ -379,7 +388,7 @@ function App() {
     try {
       const response = await fetch('/api/generate-with-files', {
         method: 'POST',
-        headers: { 'Content-Type': 'application/json' },
+        headers: apiHeaders(),
         body: JSON.stringify({
           codebase_path: codebasePath,
           user_prompt: userPrompt,